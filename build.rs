@@ -0,0 +1,15 @@
+fn main() {
+    #[cfg(feature = "grpc")]
+    {
+        let protos = [
+            "proto/ratewatch.proto",
+            "proto/envoy/service/ratelimit/v3/rls.proto",
+        ];
+        for proto in protos {
+            println!("cargo:rerun-if-changed={proto}");
+        }
+        tonic_build::configure()
+            .compile(&protos, &["proto"])
+            .expect("failed to compile gRPC protos");
+    }
+}
@@ -0,0 +1,115 @@
+#!/usr/bin/env python3
+"""Write the tiny GeoIP2-Country style database used by the GeoIP tests.
+
+MaxMind's real databases can't be redistributed in the repo, so this builds a
+minimal MMDB (format v2, IPv4 tree, 24-bit records) with a few documentation
+and test networks. Regenerate with:
+
+    python3 scripts/generate_geoip_test_db.py tests/data/GeoIP2-Country-Test.mmdb
+"""
+
+import ipaddress
+import struct
+import sys
+
+NETWORKS = [
+    ("81.2.69.0/24", "GB", "United Kingdom"),
+    ("175.16.199.0/24", "CN", "China"),
+    ("89.160.20.0/24", "SE", "Sweden"),
+    ("216.160.83.0/24", "US", "United States"),
+]
+
+METADATA_MARKER = b"\xab\xcd\xefMaxMind.com"
+
+
+def control(type_id, size):
+    if size < 29:
+        size_bits, extra = size, b""
+    elif size < 285:
+        size_bits, extra = 29, bytes([size - 29])
+    elif size < 65821:
+        size_bits, extra = 30, struct.pack(">H", size - 285)
+    else:
+        size_bits, extra = 31, struct.pack(">I", size - 65821)[1:]
+    if type_id <= 7:
+        return bytes([(type_id << 5) | size_bits]) + extra
+    return bytes([size_bits, type_id - 7]) + extra
+
+
+def encode(value):
+    if isinstance(value, str):
+        data = value.encode()
+        return control(2, len(data)) + data
+    if isinstance(value, dict):
+        out = control(7, len(value))
+        for key, val in value.items():
+            out += encode(key) + encode(val)
+        return out
+    if isinstance(value, list):
+        out = control(11, len(value))
+        for item in value:
+            out += encode(item)
+        return out
+    if isinstance(value, tuple):
+        type_id, number = value  # explicit unsigned type for metadata fields
+        length = {5: 2, 6: 4, 9: 8}[type_id]
+        data = number.to_bytes(length, "big").lstrip(b"\0")
+        return control(type_id, len(data)) + data
+    raise TypeError(value)
+
+
+def build():
+    data_section = b""
+    leaves = []
+    for cidr, iso_code, name in NETWORKS:
+        offset = len(data_section)
+        data_section += encode({"country": {"iso_code": iso_code, "names": {"en": name}}})
+        network = ipaddress.ip_network(cidr)
+        bits = format(int(network.network_address), "032b")[: network.prefixlen]
+        leaves.append((bits, offset))
+
+    # Binary trie over the prefixes; node 0 is the root
+    nodes = [[None, None]]
+    for bits, offset in leaves:
+        node = 0
+        for i, bit in enumerate(bits):
+            side = int(bit)
+            if i == len(bits) - 1:
+                nodes[node][side] = ("data", offset)
+            else:
+                if nodes[node][side] is None:
+                    nodes.append([None, None])
+                    nodes[node][side] = ("node", len(nodes) - 1)
+                node = nodes[node][side][1]
+
+    node_count = len(nodes)
+
+    def record(entry):
+        if entry is None:
+            return node_count
+        kind, value = entry
+        return value if kind == "node" else node_count + 16 + value
+
+    tree = b"".join(
+        record(left).to_bytes(3, "big") + record(right).to_bytes(3, "big") for left, right in nodes
+    )
+
+    metadata = encode(
+        {
+            "binary_format_major_version": (5, 2),
+            "binary_format_minor_version": (5, 0),
+            "build_epoch": (9, 1700000000),  # fixed so regenerating is reproducible
+            "database_type": "GeoIP2-Country",
+            "description": {"en": "RateWatch GeoIP test database"},
+            "ip_version": (5, 4),
+            "languages": ["en"],
+            "node_count": (6, node_count),
+            "record_size": (5, 24),
+        }
+    )
+    return tree + b"\0" * 16 + data_section + METADATA_MARKER + metadata
+
+
+if __name__ == "__main__":
+    with open(sys.argv[1], "wb") as f:
+        f.write(build())
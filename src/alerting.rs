@@ -0,0 +1,816 @@
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+use tokio::{sync::watch, task::JoinHandle};
+
+use crate::config::{AlertChannel, AlertThresholds, AlertingConfig};
+use crate::metrics;
+
+/// Metrics evaluated against `AlertThresholds`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AlertMetric {
+    ErrorRate,
+    ResponseTimeP99,
+    CpuUsage,
+    MemoryUsage,
+}
+
+impl AlertMetric {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AlertMetric::ErrorRate => "error_rate",
+            AlertMetric::ResponseTimeP99 => "response_time_p99",
+            AlertMetric::CpuUsage => "cpu_usage",
+            AlertMetric::MemoryUsage => "memory_usage",
+        }
+    }
+
+    fn threshold(&self, thresholds: &AlertThresholds) -> f64 {
+        match self {
+            AlertMetric::ErrorRate => thresholds.error_rate,
+            AlertMetric::ResponseTimeP99 => thresholds.response_time_p99,
+            AlertMetric::CpuUsage => thresholds.cpu_usage,
+            AlertMetric::MemoryUsage => thresholds.memory_usage,
+        }
+    }
+}
+
+/// Point-in-time values; `None` means the metric could not be measured
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MetricsSnapshot {
+    pub error_rate: Option<f64>,
+    pub response_time_p99: Option<f64>,
+    pub cpu_usage: Option<f64>,
+    pub memory_usage: Option<f64>,
+}
+
+impl MetricsSnapshot {
+    fn get(&self, metric: AlertMetric) -> Option<f64> {
+        match metric {
+            AlertMetric::ErrorRate => self.error_rate,
+            AlertMetric::ResponseTimeP99 => self.response_time_p99,
+            AlertMetric::CpuUsage => self.cpu_usage,
+            AlertMetric::MemoryUsage => self.memory_usage,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AlertStatus {
+    Triggered,
+    Resolved,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AlertNotification {
+    pub metric: AlertMetric,
+    pub status: AlertStatus,
+    pub value: f64,
+    pub threshold: f64,
+    pub timestamp: chrono::DateTime<chrono::Utc>,
+}
+
+impl AlertNotification {
+    pub fn summary(&self) -> String {
+        match self.status {
+            AlertStatus::Triggered => format!(
+                "{} is {:.3}, above threshold {:.3}",
+                self.metric.as_str(),
+                self.value,
+                self.threshold
+            ),
+            AlertStatus::Resolved => format!(
+                "{} recovered to {:.3} (threshold {:.3})",
+                self.metric.as_str(),
+                self.value,
+                self.threshold
+            ),
+        }
+    }
+}
+
+/// Source of current metric values
+#[async_trait]
+pub trait MetricsSource: Send + Sync {
+    async fn snapshot(&mut self) -> Result<MetricsSnapshot>;
+}
+
+/// Destination for alert notifications
+#[async_trait]
+pub trait AlertNotifier: Send + Sync {
+    async fn notify(&self, notification: &AlertNotification) -> Result<()>;
+    fn name(&self) -> &str;
+}
+
+/// Build a notifier for a configured channel
+pub fn create_notifier(channel: &AlertChannel) -> Result<Box<dyn AlertNotifier>> {
+    match channel.channel_type.as_str() {
+        "webhook" => Ok(Box::new(WebhookAlertChannel::from_config(channel)?)),
+        "pagerduty" => Ok(Box::new(PagerDutyAlertChannel::from_config(channel)?)),
+        other => Err(anyhow!("Unsupported alert channel type: {}", other)),
+    }
+}
+
+/// Generic webhook channel posting a JSON payload
+pub struct WebhookAlertChannel {
+    name: String,
+    url: String,
+    headers: HashMap<String, String>,
+    client: reqwest::Client,
+}
+
+impl WebhookAlertChannel {
+    pub fn from_config(channel: &AlertChannel) -> Result<Self> {
+        let url = channel
+            .config
+            .get("url")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| anyhow!("Webhook channel '{}' requires a url", channel.name))?
+            .to_string();
+
+        let headers = channel
+            .config
+            .get("headers")
+            .and_then(|v| v.as_object())
+            .map(|map| {
+                map.iter()
+                    .filter_map(|(k, v)| v.as_str().map(|v| (k.clone(), v.to_string())))
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Ok(Self {
+            name: channel.name.clone(),
+            url,
+            headers,
+            client: reqwest::Client::new(),
+        })
+    }
+
+    fn payload(notification: &AlertNotification) -> Value {
+        json!({
+            "source": "ratewatch",
+            "metric": notification.metric,
+            "status": notification.status,
+            "value": notification.value,
+            "threshold": notification.threshold,
+            "summary": notification.summary(),
+            "timestamp": notification.timestamp.to_rfc3339(),
+        })
+    }
+}
+
+#[async_trait]
+impl AlertNotifier for WebhookAlertChannel {
+    async fn notify(&self, notification: &AlertNotification) -> Result<()> {
+        let mut request = self
+            .client
+            .post(&self.url)
+            .json(&Self::payload(notification))
+            .timeout(Duration::from_secs(10));
+
+        for (key, value) in &self.headers {
+            request = request.header(key, value);
+        }
+
+        let response = request.send().await?;
+        if !response.status().is_success() {
+            return Err(anyhow!("Webhook returned status {}", response.status()));
+        }
+
+        Ok(())
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+/// PagerDuty Events API v2 channel.
+///
+/// Breaches send `trigger` events and recoveries send `resolve` events with the
+/// same `dedup_key`, so a metric maps to a single incident that auto-resolves.
+pub struct PagerDutyAlertChannel {
+    name: String,
+    routing_key: String,
+    events_url: String,
+    source: String,
+    client: reqwest::Client,
+}
+
+impl PagerDutyAlertChannel {
+    pub const DEFAULT_EVENTS_URL: &'static str = "https://events.pagerduty.com/v2/enqueue";
+
+    pub fn from_config(channel: &AlertChannel) -> Result<Self> {
+        let get = |key: &str| channel.config.get(key).and_then(|v| v.as_str());
+
+        let routing_key = get("routing_key")
+            .filter(|key| !key.is_empty())
+            .ok_or_else(|| anyhow!("PagerDuty channel '{}' requires a routing_key", channel.name))?
+            .to_string();
+
+        Ok(Self {
+            name: channel.name.clone(),
+            routing_key,
+            events_url: get("url").unwrap_or(Self::DEFAULT_EVENTS_URL).to_string(),
+            source: get("source").unwrap_or("ratewatch").to_string(),
+            client: reqwest::Client::new(),
+        })
+    }
+
+    /// One incident per metric, regardless of how often it breaches
+    fn dedup_key(metric: AlertMetric) -> String {
+        format!("ratewatch-{}", metric.as_str())
+    }
+
+    /// Map breach magnitude (value relative to threshold) to a PagerDuty severity
+    fn severity(notification: &AlertNotification) -> &'static str {
+        let ratio = if notification.threshold > 0.0 {
+            notification.value / notification.threshold
+        } else {
+            f64::INFINITY
+        };
+
+        if ratio >= 2.0 {
+            "critical"
+        } else if ratio >= 1.5 {
+            "error"
+        } else {
+            "warning"
+        }
+    }
+
+    fn event(&self, notification: &AlertNotification) -> Value {
+        let dedup_key = Self::dedup_key(notification.metric);
+
+        match notification.status {
+            AlertStatus::Triggered => json!({
+                "routing_key": self.routing_key,
+                "event_action": "trigger",
+                "dedup_key": dedup_key,
+                "payload": {
+                    "summary": notification.summary(),
+                    "source": self.source,
+                    "severity": Self::severity(notification),
+                    "component": "ratewatch",
+                    "class": notification.metric.as_str(),
+                    "timestamp": notification.timestamp.to_rfc3339(),
+                    "custom_details": {
+                        "value": notification.value,
+                        "threshold": notification.threshold,
+                    },
+                },
+            }),
+            AlertStatus::Resolved => json!({
+                "routing_key": self.routing_key,
+                "event_action": "resolve",
+                "dedup_key": dedup_key,
+            }),
+        }
+    }
+}
+
+#[async_trait]
+impl AlertNotifier for PagerDutyAlertChannel {
+    async fn notify(&self, notification: &AlertNotification) -> Result<()> {
+        let response = self
+            .client
+            .post(&self.events_url)
+            .json(&self.event(notification))
+            .timeout(Duration::from_secs(10))
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(anyhow!("PagerDuty returned status {}", response.status()));
+        }
+
+        Ok(())
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+#[derive(Debug, Default)]
+struct AlertState {
+    firing: bool,
+    last_triggered: Option<Instant>,
+}
+
+/// Compares metrics against thresholds and dispatches triggered/resolved notifications.
+///
+/// A metric triggers once when it rises above its threshold and resolves once when
+/// it falls below `threshold * resolve_ratio`. A new trigger for the same metric is
+/// suppressed until `cooldown` has elapsed, so a flapping metric cannot spam channels.
+pub struct AlertEvaluator {
+    thresholds: AlertThresholds,
+    notifiers: Vec<Box<dyn AlertNotifier>>,
+    source: Box<dyn MetricsSource>,
+    states: HashMap<AlertMetric, AlertState>,
+    cooldown: Duration,
+    resolve_ratio: f64,
+}
+
+impl AlertEvaluator {
+    pub fn new(
+        thresholds: AlertThresholds,
+        notifiers: Vec<Box<dyn AlertNotifier>>,
+        source: Box<dyn MetricsSource>,
+    ) -> Self {
+        Self {
+            thresholds,
+            notifiers,
+            source,
+            states: HashMap::new(),
+            cooldown: Duration::from_secs(300),
+            resolve_ratio: 0.9,
+        }
+    }
+
+    pub fn with_cooldown(mut self, cooldown: Duration) -> Self {
+        self.cooldown = cooldown;
+        self
+    }
+
+    pub fn with_resolve_ratio(mut self, ratio: f64) -> Self {
+        self.resolve_ratio = ratio.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Run one evaluation pass, returning the notifications that were dispatched
+    pub async fn evaluate(&mut self) -> Result<Vec<AlertNotification>> {
+        let snapshot = self.source.snapshot().await?;
+        let now = Instant::now();
+        let mut notifications = Vec::new();
+
+        for metric in [
+            AlertMetric::ErrorRate,
+            AlertMetric::ResponseTimeP99,
+            AlertMetric::CpuUsage,
+            AlertMetric::MemoryUsage,
+        ] {
+            let value = match snapshot.get(metric) {
+                Some(value) => value,
+                None => continue,
+            };
+            let threshold = metric.threshold(&self.thresholds);
+            let state = self.states.entry(metric).or_default();
+
+            let status = if !state.firing && value > threshold {
+                let cooled_down = state
+                    .last_triggered
+                    .map_or(true, |at| now.duration_since(at) >= self.cooldown);
+                if !cooled_down {
+                    continue;
+                }
+                state.firing = true;
+                state.last_triggered = Some(now);
+                AlertStatus::Triggered
+            } else if state.firing && value < threshold * self.resolve_ratio {
+                state.firing = false;
+                AlertStatus::Resolved
+            } else {
+                continue;
+            };
+
+            notifications.push(AlertNotification {
+                metric,
+                status,
+                value,
+                threshold,
+                timestamp: chrono::Utc::now(),
+            });
+        }
+
+        for notification in &notifications {
+            for notifier in &self.notifiers {
+                if let Err(e) = notifier.notify(notification).await {
+                    tracing::warn!(
+                        channel = notifier.name(),
+                        metric = notification.metric.as_str(),
+                        "Failed to dispatch alert: {}",
+                        e
+                    );
+                }
+            }
+        }
+
+        Ok(notifications)
+    }
+
+    /// Evaluate periodically until stopped
+    pub fn spawn(mut self, interval: Duration) -> AlertEvaluatorHandle {
+        let (shutdown, mut shutdown_rx) = watch::channel(false);
+
+        let task = tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                tokio::select! {
+                    _ = ticker.tick() => {
+                        if let Err(e) = self.evaluate().await {
+                            tracing::warn!("Alert evaluation failed: {}", e);
+                        }
+                    }
+                    _ = shutdown_rx.changed() => break,
+                }
+            }
+        });
+
+        AlertEvaluatorHandle { shutdown, task }
+    }
+}
+
+pub struct AlertEvaluatorHandle {
+    shutdown: watch::Sender<bool>,
+    task: JoinHandle<()>,
+}
+
+impl AlertEvaluatorHandle {
+    pub async fn stop(self) {
+        let _ = self.shutdown.send(true);
+        let _ = self.task.await;
+    }
+}
+
+/// Start alert evaluation from configuration, if alerting is enabled and has channels
+pub fn start_alerting(config: &AlertingConfig) -> Result<Option<AlertEvaluatorHandle>> {
+    if !config.enabled || config.channels.is_empty() {
+        return Ok(None);
+    }
+
+    let notifiers = config
+        .channels
+        .iter()
+        .map(create_notifier)
+        .collect::<Result<Vec<_>>>()?;
+
+    let evaluator = AlertEvaluator::new(
+        config.thresholds.clone(),
+        notifiers,
+        Box::new(ProcessMetricsSource::new()),
+    )
+    .with_cooldown(Duration::from_secs(config.cooldown_seconds));
+
+    Ok(Some(evaluator.spawn(Duration::from_secs(
+        config.evaluation_interval_seconds.max(1),
+    ))))
+}
+
+/// Reads request metrics from the Prometheus registry and resource usage from `/proc`.
+///
+/// Error rate and p99 are computed over the interval since the previous snapshot.
+pub struct ProcessMetricsSource {
+    previous_requests: Option<(f64, f64)>,
+    previous_buckets: Option<Vec<(f64, u64)>>,
+    previous_cpu: Option<(f64, Instant)>,
+}
+
+impl ProcessMetricsSource {
+    pub fn new() -> Self {
+        Self {
+            previous_requests: None,
+            previous_buckets: None,
+            previous_cpu: None,
+        }
+    }
+
+    fn request_counts() -> (f64, f64) {
+        let mut total = 0.0;
+        let mut errors = 0.0;
+
+        for family in metrics::REGISTRY.gather() {
+            if family.name() != "ratewatch_requests_total" {
+                continue;
+            }
+            for metric in family.get_metric() {
+                let value = metric.get_counter().value();
+                total += value;
+                if metric
+                    .get_label()
+                    .iter()
+                    .any(|l| l.name() == "outcome" && l.value() == "error")
+                {
+                    errors += value;
+                }
+            }
+        }
+
+        (total, errors)
+    }
+
+    fn duration_buckets() -> Vec<(f64, u64)> {
+        metrics::REGISTRY
+            .gather()
+            .iter()
+            .filter(|family| family.name() == "ratewatch_request_duration_seconds")
+            .flat_map(|family| family.get_metric().iter())
+            .flat_map(|metric| {
+                metric
+                    .get_histogram()
+                    .get_bucket()
+                    .iter()
+                    .map(|b| (b.upper_bound(), b.cumulative_count()))
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+
+    /// p99 in milliseconds from the bucket counts observed since the last snapshot
+    fn p99_ms(previous: &[(f64, u64)], current: &[(f64, u64)]) -> Option<f64> {
+        let deltas: Vec<(f64, u64)> = current
+            .iter()
+            .enumerate()
+            .map(|(i, (bound, count))| {
+                let before = previous.get(i).map(|(_, c)| *c).unwrap_or(0);
+                (*bound, count.saturating_sub(before))
+            })
+            .collect();
+
+        let total = deltas.last().map(|(_, c)| *c)?;
+        if total == 0 {
+            return None;
+        }
+
+        let target = (total as f64 * 0.99).ceil() as u64;
+        deltas
+            .iter()
+            .find(|(_, count)| *count >= target)
+            .or(deltas.last())
+            .map(|(bound, _)| bound * 1000.0)
+    }
+
+    fn memory_usage_percent() -> Option<f64> {
+        let read_kb = |path: &str, field: &str| -> Option<f64> {
+            std::fs::read_to_string(path)
+                .ok()?
+                .lines()
+                .find(|line| line.starts_with(field))?
+                .split_whitespace()
+                .nth(1)?
+                .parse()
+                .ok()
+        };
+
+        let rss = read_kb("/proc/self/status", "VmRSS:")?;
+        let total = read_kb("/proc/meminfo", "MemTotal:")?;
+        (total > 0.0).then(|| rss / total * 100.0)
+    }
+
+    fn cpu_seconds() -> Option<f64> {
+        let stat = std::fs::read_to_string("/proc/self/stat").ok()?;
+        // Fields after the parenthesised command name; utime and stime are fields 14 and 15
+        let fields: Vec<&str> = stat.rsplit_once(')')?.1.split_whitespace().collect();
+        let utime: f64 = fields.get(11)?.parse().ok()?;
+        let stime: f64 = fields.get(12)?.parse().ok()?;
+        // Linux reports in clock ticks, which are 100 Hz on all mainstream kernels
+        Some((utime + stime) / 100.0)
+    }
+}
+
+impl Default for ProcessMetricsSource {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl MetricsSource for ProcessMetricsSource {
+    async fn snapshot(&mut self) -> Result<MetricsSnapshot> {
+        let (total, errors) = Self::request_counts();
+        let error_rate = self.previous_requests.and_then(|(prev_total, prev_errors)| {
+            let requests = total - prev_total;
+            (requests > 0.0).then(|| (errors - prev_errors) / requests)
+        });
+        self.previous_requests = Some((total, errors));
+
+        let buckets = Self::duration_buckets();
+        let response_time_p99 = self
+            .previous_buckets
+            .as_ref()
+            .and_then(|previous| Self::p99_ms(previous, &buckets));
+        self.previous_buckets = Some(buckets);
+
+        let now = Instant::now();
+        let cpu_usage = Self::cpu_seconds().and_then(|cpu| {
+            let usage = self.previous_cpu.and_then(|(prev_cpu, at)| {
+                let elapsed = now.duration_since(at).as_secs_f64();
+                let cores = std::thread::available_parallelism()
+                    .map(|n| n.get() as f64)
+                    .unwrap_or(1.0);
+                (elapsed > 0.0).then(|| (cpu - prev_cpu) / elapsed / cores * 100.0)
+            });
+            self.previous_cpu = Some((cpu, now));
+            usage
+        });
+
+        Ok(MetricsSnapshot {
+            error_rate,
+            response_time_p99,
+            cpu_usage,
+            memory_usage: Self::memory_usage_percent(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::VecDeque;
+    use std::sync::{Arc, Mutex};
+
+    struct ScriptedSource {
+        snapshots: VecDeque<MetricsSnapshot>,
+    }
+
+    #[async_trait]
+    impl MetricsSource for ScriptedSource {
+        async fn snapshot(&mut self) -> Result<MetricsSnapshot> {
+            self.snapshots
+                .pop_front()
+                .ok_or_else(|| anyhow!("no more snapshots"))
+        }
+    }
+
+    struct RecordingChannel {
+        received: Arc<Mutex<Vec<AlertNotification>>>,
+    }
+
+    #[async_trait]
+    impl AlertNotifier for RecordingChannel {
+        async fn notify(&self, notification: &AlertNotification) -> Result<()> {
+            self.received.lock().unwrap().push(notification.clone());
+            Ok(())
+        }
+
+        fn name(&self) -> &str {
+            "recording"
+        }
+    }
+
+    fn thresholds() -> AlertThresholds {
+        AlertThresholds {
+            error_rate: 0.05,
+            response_time_p99: 500.0,
+            cpu_usage: 80.0,
+            memory_usage: 85.0,
+        }
+    }
+
+    fn error_rate(value: f64) -> MetricsSnapshot {
+        MetricsSnapshot {
+            error_rate: Some(value),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_breach_triggers_once_and_recovery_resolves_once() {
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let source = ScriptedSource {
+            snapshots: vec![
+                error_rate(0.01),
+                error_rate(0.20),
+                error_rate(0.30),
+                error_rate(0.048), // below threshold but inside hysteresis band
+                error_rate(0.25),
+                error_rate(0.01),
+                error_rate(0.01),
+            ]
+            .into(),
+        };
+
+        let mut evaluator = AlertEvaluator::new(
+            thresholds(),
+            vec![Box::new(RecordingChannel {
+                received: received.clone(),
+            })],
+            Box::new(source),
+        );
+
+        for _ in 0..7 {
+            evaluator.evaluate().await.unwrap();
+        }
+
+        let received = received.lock().unwrap();
+        assert_eq!(received.len(), 2);
+        assert_eq!(received[0].status, AlertStatus::Triggered);
+        assert_eq!(received[0].metric, AlertMetric::ErrorRate);
+        assert_eq!(received[1].status, AlertStatus::Resolved);
+    }
+
+    #[tokio::test]
+    async fn test_cooldown_suppresses_flapping() {
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let source = ScriptedSource {
+            snapshots: vec![error_rate(0.2), error_rate(0.0), error_rate(0.2)].into(),
+        };
+
+        let mut evaluator = AlertEvaluator::new(
+            thresholds(),
+            vec![Box::new(RecordingChannel {
+                received: received.clone(),
+            })],
+            Box::new(source),
+        )
+        .with_cooldown(Duration::from_secs(3600));
+
+        for _ in 0..3 {
+            evaluator.evaluate().await.unwrap();
+        }
+
+        let statuses: Vec<AlertStatus> =
+            received.lock().unwrap().iter().map(|n| n.status).collect();
+        assert_eq!(statuses, vec![AlertStatus::Triggered, AlertStatus::Resolved]);
+    }
+
+    #[test]
+    fn test_p99_from_bucket_deltas() {
+        let previous = vec![(0.01, 10), (0.1, 10), (1.0, 10)];
+        let current = vec![(0.01, 60), (0.1, 109), (1.0, 110)];
+
+        assert_eq!(ProcessMetricsSource::p99_ms(&previous, &current), Some(100.0));
+        assert_eq!(ProcessMetricsSource::p99_ms(&current, &current), None);
+    }
+
+    #[test]
+    fn test_webhook_channel_requires_url() {
+        let channel = AlertChannel {
+            name: "ops".to_string(),
+            channel_type: "webhook".to_string(),
+            config: HashMap::new(),
+        };
+        assert!(create_notifier(&channel).is_err());
+
+        let mut config = HashMap::new();
+        config.insert("url".to_string(), json!("http://localhost:9999/hook"));
+        let channel = AlertChannel {
+            config,
+            ..channel
+        };
+        assert!(create_notifier(&channel).is_ok());
+    }
+
+    fn pagerduty_channel() -> PagerDutyAlertChannel {
+        let mut config = HashMap::new();
+        config.insert("routing_key".to_string(), json!("test-routing-key"));
+        PagerDutyAlertChannel::from_config(&AlertChannel {
+            name: "pager".to_string(),
+            channel_type: "pagerduty".to_string(),
+            config,
+        })
+        .unwrap()
+    }
+
+    fn notification(status: AlertStatus, value: f64) -> AlertNotification {
+        AlertNotification {
+            metric: AlertMetric::ErrorRate,
+            status,
+            value,
+            threshold: 0.05,
+            timestamp: chrono::Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_pagerduty_trigger_and_resolve_share_dedup_key() {
+        let channel = pagerduty_channel();
+
+        let trigger = channel.event(&notification(AlertStatus::Triggered, 0.06));
+        assert_eq!(trigger["event_action"], "trigger");
+        assert_eq!(trigger["routing_key"], "test-routing-key");
+        assert_eq!(trigger["payload"]["severity"], "warning");
+
+        let resolve = channel.event(&notification(AlertStatus::Resolved, 0.01));
+        assert_eq!(resolve["event_action"], "resolve");
+        assert_eq!(resolve["dedup_key"], trigger["dedup_key"]);
+        assert!(resolve.get("payload").is_none());
+    }
+
+    #[test]
+    fn test_pagerduty_severity_from_breach_magnitude() {
+        let severity = |value| PagerDutyAlertChannel::severity(&notification(AlertStatus::Triggered, value));
+
+        assert_eq!(severity(0.06), "warning");
+        assert_eq!(severity(0.08), "error");
+        assert_eq!(severity(0.5), "critical");
+    }
+
+    #[test]
+    fn test_pagerduty_requires_routing_key() {
+        let channel = AlertChannel {
+            name: "pager".to_string(),
+            channel_type: "pagerduty".to_string(),
+            config: HashMap::new(),
+        };
+        assert!(create_notifier(&channel).is_err());
+    }
+}
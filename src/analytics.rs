@@ -1,20 +1,26 @@
 use axum::{
     extract::{Query, State},
-    http::StatusCode,
     response::Json,
     routing::get,
     Router,
 };
-use redis::{AsyncCommands, Client};
+use redis::AsyncCommands;
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
 use std::{
     collections::HashMap,
     sync::Arc,
-    time::{SystemTime, UNIX_EPOCH},
+    time::{Duration, SystemTime, UNIX_EPOCH},
 };
 
+use crate::api_error::ApiError;
+use crate::rate_limiter::{DenyReason, RateLimitFailureMode, RateLimitResponse};
+use crate::redis_backend::{scan_keys, RedisConnector};
+use crate::security::middleware::AnalysisFailedOpen;
+
 #[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::IntoParams))]
+#[cfg_attr(feature = "openapi", into_params(parameter_in = Query))]
 pub struct AnalyticsQuery {
     pub key: Option<String>,
     pub window: Option<String>, // 1h, 6h, 24h, 7d
@@ -26,6 +32,8 @@ pub struct KeyMetric {
     pub key: String,
     pub count: u64,
     pub success_rate: f64,
+    /// Requests monitor mode allowed over the limit
+    pub would_deny_count: u64,
     pub last_seen: String,
 }
 
@@ -37,13 +45,42 @@ pub struct ActivityLog {
     pub key: Option<String>,
 }
 
+/// Per-minute `analytics:status:{status}:{minute}` counters summed by `get_stats`
+const STATUS_COUNTERS: [&str; 7] = [
+    "allowed",
+    "denied",
+    "would_deny",
+    "bypassed",
+    "allowlisted",
+    "bypassed_killswitch",
+    "unanalyzed",
+];
+
+/// How long per-minute counters are kept unless configured otherwise
+pub const DEFAULT_RETENTION: Duration = Duration::from_secs(86400);
+
 pub struct AnalyticsManager {
-    redis: Client,
+    redis: RedisConnector,
+    /// Expiry of the per-minute counters
+    retention: Duration,
 }
 
 impl AnalyticsManager {
-    pub fn new(redis: Client) -> Self {
-        Self { redis }
+    pub fn new(redis: RedisConnector) -> Self {
+        Self {
+            redis,
+            retention: DEFAULT_RETENTION,
+        }
+    }
+
+    /// Keep the per-minute counters, denials by reason among them, this long
+    pub fn with_retention(mut self, retention: Duration) -> Self {
+        self.retention = retention;
+        self
+    }
+
+    fn retention_seconds(&self) -> i64 {
+        self.retention.as_secs().max(1) as i64
     }
 
     /// Record a rate limit check for analytics
@@ -65,7 +102,7 @@ impl AnalyticsManager {
         let status = if allowed { "allowed" } else { "denied" };
         let status_key = format!("analytics:status:{}:{}", status, now / 60);
         let _: () = conn.incr(&status_key, 1).await?;
-        let _: () = conn.expire(&status_key, 86400).await?; // Keep for 24 hours
+        let _: () = conn.expire(&status_key, self.retention_seconds()).await?;
 
         // Update key statistics
         let key_stats = format!("analytics:key_stats:{key}");
@@ -84,6 +121,124 @@ impl AnalyticsManager {
         Ok(())
     }
 
+    /// Record a rate limit decision and log denials to the activity feed.
+    ///
+    /// Shared by the HTTP and gRPC front ends so both record checks identically.
+    pub async fn record_check(
+        &self,
+        key: &str,
+        response: &RateLimitResponse,
+        window: u64,
+    ) -> anyhow::Result<()> {
+        self.record_request(key, response.allowed, window).await?;
+        if let Some(reason) = response.deny_reason {
+            self.record_denial_reason(reason).await?;
+        }
+
+        if response.would_deny {
+            self.record_would_deny(key).await?;
+        } else if !response.allowed {
+            self.log_activity(
+                &format!("Rate limit exceeded for key: {key}"),
+                "warning",
+                Some(key),
+            )
+            .await?;
+        }
+
+        Ok(())
+    }
+
+    /// Count a request monitor mode let through over the limit.
+    ///
+    /// The request itself is recorded as allowed by `record_request`; this
+    /// tracks what enforce mode would have denied.
+    pub async fn record_would_deny(&self, key: &str) -> anyhow::Result<()> {
+        self.count_flagged("would_deny", key, "would_deny_requests").await?;
+        self.log_activity(
+            &format!("Rate limit would deny key (monitor mode): {key}"),
+            "info",
+            Some(key),
+        )
+        .await
+    }
+
+    /// Record a request from an allowlisted API key, which skipped the limiter
+    pub async fn record_bypass(&self, key: &str, window: u64) -> anyhow::Result<()> {
+        self.record_request(key, true, window).await?;
+        self.count_flagged("bypassed", key, "bypassed_requests").await
+    }
+
+    /// Record a request allowed because the enforcement kill-switch is off
+    pub async fn record_killswitch_bypass(&self, key: &str, window: u64) -> anyhow::Result<()> {
+        self.record_request(key, true, window).await?;
+        self.count_flagged("bypassed_killswitch", key, "bypassed_killswitch_requests").await
+    }
+
+    /// Record a request from an allowlisted client IP, which skipped the
+    /// limiter and threat detection
+    pub async fn record_allowlisted(&self, key: &str, window: u64) -> anyhow::Result<()> {
+        self.record_request(key, true, window).await?;
+        self.count_flagged("allowlisted", key, "allowlisted_requests").await
+    }
+
+    /// Count an allowed request that needs to be told apart from the rest,
+    /// per minute and on the key's stats
+    async fn count_flagged(&self, status: &str, key: &str, stats_field: &str) -> anyhow::Result<()> {
+        let mut conn = self.redis.get_async_connection().await?;
+        let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+
+        let status_key = format!("analytics:status:{}:{}", status, now / 60);
+        let _: () = conn.incr(&status_key, 1).await?;
+        let _: () = conn.expire(&status_key, self.retention_seconds()).await?;
+
+        let key_stats = format!("analytics:key_stats:{key}");
+        let _: () = conn.hincr(&key_stats, stats_field, 1).await?;
+        Ok(())
+    }
+
+    /// Record a decision made by the failure mode because Redis was unavailable.
+    ///
+    /// Best effort: this only lands if analytics can still reach Redis, e.g.
+    /// when the rate limit script failed but the server is up.
+    pub async fn record_backend_failure(
+        &self,
+        key: Option<&str>,
+        failure_mode: RateLimitFailureMode,
+    ) -> anyhow::Result<()> {
+        let (message, level) = match failure_mode {
+            RateLimitFailureMode::Allow => ("Rate limiter unavailable, failing open", "warning"),
+            RateLimitFailureMode::Deny => ("Rate limiter unavailable, failing closed", "error"),
+        };
+        if failure_mode == RateLimitFailureMode::Deny {
+            self.record_denial_reason(DenyReason::BackendUnavailable).await?;
+        }
+        self.log_activity(message, level, key).await
+    }
+
+    /// Count a request let through because its threat analysis failed and
+    /// threat detection fails open
+    pub async fn record_unanalyzed(&self) -> anyhow::Result<()> {
+        let mut conn = self.redis.get_async_connection().await?;
+        let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+
+        let status_key = format!("analytics:status:unanalyzed:{}", now / 60);
+        let _: () = conn.incr(&status_key, 1).await?;
+        let _: () = conn.expire(&status_key, self.retention_seconds()).await?;
+        Ok(())
+    }
+
+    /// Count a denial by its reason, per minute, for the `get_stats` breakdown
+    pub async fn record_denial_reason(&self, reason: DenyReason) -> anyhow::Result<()> {
+        let mut conn = self.redis.get_async_connection().await?;
+        let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+
+        let reason_key = format!("analytics:denied_reason:{}:{}", reason.as_str(), now / 60);
+        let _: () = conn.incr(&reason_key, 1).await?;
+        let _: () = conn.expire(&reason_key, self.retention_seconds()).await?;
+        Ok(())
+    }
+
     /// Log an activity event
     pub async fn log_activity(
         &self,
@@ -129,18 +284,44 @@ impl AnalyticsManager {
         let yesterday_key = format!("analytics:daily:{}", (now / 86400) - 1);
         let yesterday_requests: u64 = conn.get(&yesterday_key).await.unwrap_or(1);
 
-        // Calculate success rate for the last hour
+        // Calculate success rate for the last hour. Every per-minute counter is
+        // read in one MGET, which a cluster connection splits by slot
         let hour_start = now - 3600;
-        let mut total_allowed = 0u64;
-        let mut total_denied = 0u64;
-
-        for minute in (hour_start / 60)..=(now / 60) {
-            let allowed_key = format!("analytics:status:allowed:{minute}");
-            let denied_key = format!("analytics:status:denied:{minute}");
-
-            total_allowed += conn.get(&allowed_key).await.unwrap_or(0);
-            total_denied += conn.get(&denied_key).await.unwrap_or(0);
+        let keys: Vec<String> = ((hour_start / 60)..=(now / 60))
+            .flat_map(|minute| {
+                let statuses = STATUS_COUNTERS
+                    .iter()
+                    .map(move |status| format!("analytics:status:{status}:{minute}"));
+                let reasons = DenyReason::ALL
+                    .iter()
+                    .map(move |reason| format!("analytics:denied_reason:{}:{minute}", reason.as_str()));
+                statuses.chain(reasons)
+            })
+            .collect();
+        let counts: Vec<Option<u64>> = conn.mget(&keys).await.unwrap_or_default();
+
+        let mut totals = [0u64; STATUS_COUNTERS.len()];
+        let mut denied_by_reason: HashMap<&str, u64> =
+            DenyReason::ALL.iter().map(|reason| (reason.as_str(), 0)).collect();
+        for minute in counts.chunks(STATUS_COUNTERS.len() + DenyReason::ALL.len()) {
+            let (statuses, reasons) = minute.split_at(STATUS_COUNTERS.len());
+            for (total, count) in totals.iter_mut().zip(statuses) {
+                *total += count.unwrap_or(0);
+            }
+            for (reason, count) in DenyReason::ALL.iter().zip(reasons) {
+                *denied_by_reason.entry(reason.as_str()).or_default() += count.unwrap_or(0);
+            }
         }
+        // would_deny is counted within allowed too, since those requests were allowed
+        let [
+            total_allowed,
+            total_denied,
+            total_would_deny,
+            total_bypassed,
+            total_allowlisted,
+            total_bypassed_killswitch,
+            total_unanalyzed,
+        ] = totals;
 
         let total_requests = total_allowed + total_denied;
         let success_rate = if total_requests > 0 {
@@ -164,6 +345,12 @@ impl AnalyticsManager {
             "total_requests_hour": total_requests,
             "allowed_requests_hour": total_allowed,
             "denied_requests_hour": total_denied,
+            "would_deny_requests_hour": total_would_deny,
+            "bypassed_requests_hour": total_bypassed,
+            "allowlisted_requests_hour": total_allowlisted,
+            "bypassed_killswitch_requests_hour": total_bypassed_killswitch,
+            "unanalyzed_requests_hour": total_unanalyzed,
+            "denied_by_reason": denied_by_reason,
             "uptime": "99.9%"
         }))
     }
@@ -174,7 +361,7 @@ impl AnalyticsManager {
 
         // Get all key stats
         let pattern = "analytics:key_stats:*";
-        let keys: Vec<String> = conn.keys(pattern).await?;
+        let keys = scan_keys(&mut conn, pattern).await?;
 
         let mut key_metrics = Vec::new();
 
@@ -190,6 +377,10 @@ impl AnalyticsManager {
                     .get("allowed_requests")
                     .and_then(|s| s.parse().ok())
                     .unwrap_or(0);
+                let would_deny_requests: u64 = stats
+                    .get("would_deny_requests")
+                    .and_then(|s| s.parse().ok())
+                    .unwrap_or(0);
                 let last_seen: u64 = stats
                     .get("last_seen")
                     .and_then(|s| s.parse().ok())
@@ -205,6 +396,7 @@ impl AnalyticsManager {
                         key: clean_key.to_string(),
                         count: total_requests,
                         success_rate,
+                        would_deny_count: would_deny_requests,
                         last_seen: last_seen_str,
                     });
                 }
@@ -239,7 +431,8 @@ impl AnalyticsManager {
         }))
     }
 
-    /// Get request rate data for charts
+    /// Get request rate data for charts; `window` is one of
+    /// `REQUEST_RATE_WINDOWS`, anything else is treated as 1h
     pub async fn get_request_rate_data(&self, window: &str) -> anyhow::Result<Value> {
         let mut conn = self.redis.get_async_connection().await?;
         let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
@@ -288,53 +481,222 @@ impl AnalyticsManager {
     }
 }
 
+/// Count requests refused before reaching a handler, whose responses carry a
+/// `DenyReason` extension (see `DenyReason::response`), and requests let
+/// through after their threat analysis failed. Handlers record their own
+/// denials through `record_check`.
+pub async fn record_denials_middleware(
+    State(analytics): State<Arc<AnalyticsManager>>,
+    request: axum::extract::Request,
+    next: axum::middleware::Next,
+) -> axum::response::Response {
+    let response = next.run(request).await;
+    if let Some(reason) = response.extensions().get::<DenyReason>() {
+        if let Err(e) = analytics.record_denial_reason(*reason).await {
+            tracing::debug!("Failed to record denial reason: {}", e);
+        }
+    }
+    if response.extensions().get::<AnalysisFailedOpen>().is_some() {
+        if let Err(e) = analytics.record_unanalyzed().await {
+            tracing::debug!("Failed to record unanalyzed request: {}", e);
+        }
+    }
+    response
+}
+
+/// Windows `/v1/analytics/request-rate` can chart
+pub const REQUEST_RATE_WINDOWS: [&str; 4] = ["1h", "6h", "24h", "7d"];
+
 pub fn create_analytics_router(analytics: Arc<AnalyticsManager>) -> Router {
-    Router::new()
+    let router = Router::new()
         .route("/v1/analytics/stats", get(get_stats))
         .route("/v1/analytics/top-keys", get(get_top_keys))
         .route("/v1/analytics/recent-activity", get(get_recent_activity))
         .route("/v1/analytics/request-rate", get(get_request_rate))
-        .with_state(analytics)
+        .with_state(analytics.clone());
+
+    // Pushes the same data to WebSocket clients instead of having them poll
+    #[cfg(feature = "websocket")]
+    let router = router.merge(crate::analytics_stream::create_stream_router(analytics));
+
+    router
 }
 
+/// Analytics data lives in Redis, so a failure here is the server's
+fn analytics_error(e: anyhow::Error, message: &str) -> ApiError {
+    tracing::error!(error = %e, "{}", message);
+    ApiError::internal(message)
+}
+
+#[cfg_attr(
+    feature = "openapi",
+    utoipa::path(
+        get,
+        path = "/v1/analytics/stats",
+        tag = "analytics",
+        responses(
+            (status = 200, description = "Rate limiting statistics", body = Object),
+            (status = 500, description = "Internal error", body = crate::api_error::ProblemDetails),
+            (status = 401, description = "Missing or invalid API key"),
+        ),
+        security(("api_key" = [])),
+    )
+)]
 async fn get_stats(
     State(analytics): State<Arc<AnalyticsManager>>,
-) -> Result<Json<Value>, StatusCode> {
-    match analytics.get_stats().await {
-        Ok(stats) => Ok(Json(stats)),
-        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
-    }
+) -> Result<Json<Value>, ApiError> {
+    analytics.get_stats().await.map(Json).map_err(|e| analytics_error(e, "Failed to read statistics"))
 }
 
+#[cfg_attr(
+    feature = "openapi",
+    utoipa::path(
+        get,
+        path = "/v1/analytics/top-keys",
+        tag = "analytics",
+        params(AnalyticsQuery),
+        responses(
+            (status = 200, description = "Most requested keys", body = Object),
+            (status = 500, description = "Internal error", body = crate::api_error::ProblemDetails),
+            (status = 401, description = "Missing or invalid API key"),
+        ),
+        security(("api_key" = [])),
+    )
+)]
 async fn get_top_keys(
     State(analytics): State<Arc<AnalyticsManager>>,
     Query(params): Query<AnalyticsQuery>,
-) -> Result<Json<Value>, StatusCode> {
+) -> Result<Json<Value>, ApiError> {
     let limit = params.limit.unwrap_or(10);
-    match analytics.get_top_keys(limit).await {
-        Ok(keys) => Ok(Json(keys)),
-        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
-    }
+    analytics
+        .get_top_keys(limit)
+        .await
+        .map(Json)
+        .map_err(|e| analytics_error(e, "Failed to read top keys"))
 }
 
+#[cfg_attr(
+    feature = "openapi",
+    utoipa::path(
+        get,
+        path = "/v1/analytics/recent-activity",
+        tag = "analytics",
+        params(AnalyticsQuery),
+        responses(
+            (status = 200, description = "Recent activity log", body = Object),
+            (status = 500, description = "Internal error", body = crate::api_error::ProblemDetails),
+            (status = 401, description = "Missing or invalid API key"),
+        ),
+        security(("api_key" = [])),
+    )
+)]
 async fn get_recent_activity(
     State(analytics): State<Arc<AnalyticsManager>>,
     Query(params): Query<AnalyticsQuery>,
-) -> Result<Json<Value>, StatusCode> {
+) -> Result<Json<Value>, ApiError> {
     let limit = params.limit.unwrap_or(10);
-    match analytics.get_recent_activity(limit).await {
-        Ok(logs) => Ok(Json(logs)),
-        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
-    }
+    analytics
+        .get_recent_activity(limit)
+        .await
+        .map(Json)
+        .map_err(|e| analytics_error(e, "Failed to read recent activity"))
 }
 
+#[cfg_attr(
+    feature = "openapi",
+    utoipa::path(
+        get,
+        path = "/v1/analytics/request-rate",
+        tag = "analytics",
+        params(AnalyticsQuery),
+        responses(
+            (status = 200, description = "Allowed/denied chart data", body = Object),
+            (status = 400, description = "Unknown window", body = crate::api_error::ProblemDetails),
+            (status = 500, description = "Internal error", body = crate::api_error::ProblemDetails),
+            (status = 401, description = "Missing or invalid API key"),
+        ),
+        security(("api_key" = [])),
+    )
+)]
 async fn get_request_rate(
     State(analytics): State<Arc<AnalyticsManager>>,
     Query(params): Query<AnalyticsQuery>,
-) -> Result<Json<Value>, StatusCode> {
+) -> Result<Json<Value>, ApiError> {
     let window = params.window.as_deref().unwrap_or("1h");
-    match analytics.get_request_rate_data(window).await {
-        Ok(data) => Ok(Json(data)),
-        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+    if !REQUEST_RATE_WINDOWS.contains(&window) {
+        return Err(ApiError::bad_request(
+            "INVALID_WINDOW",
+            format!("window must be one of {}", REQUEST_RATE_WINDOWS.join(", ")),
+        ));
+    }
+    analytics
+        .get_request_rate_data(window)
+        .await
+        .map(Json)
+        .map_err(|e| analytics_error(e, "Failed to read request rate"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Body, http::{Request, StatusCode}};
+    use tower::ServiceExt;
+
+    #[tokio::test]
+    async fn test_unknown_window_is_a_client_error() {
+        let app = create_analytics_router(Arc::new(AnalyticsManager::new(
+            RedisConnector::open("redis://127.0.0.1:6379").unwrap(),
+        )));
+
+        let response = app
+            .oneshot(Request::builder().uri("/v1/analytics/request-rate?window=3w").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["code"], "INVALID_WINDOW");
+        assert_eq!(body["detail"], "window must be one of 1h, 6h, 24h, 7d");
+    }
+
+    #[tokio::test]
+    async fn test_denials_are_broken_down_by_reason() {
+        let redis = RedisConnector::open("redis://127.0.0.1:6379").unwrap();
+        let Ok(mut conn) = redis.get_async_connection().await else {
+            println!("Skipping denial breakdown test - Redis not available");
+            return;
+        };
+        let analytics = AnalyticsManager::new(redis).with_retention(Duration::from_secs(7200));
+        let by_reason = |stats: Value| -> HashMap<String, u64> {
+            serde_json::from_value(stats["denied_by_reason"].clone()).unwrap()
+        };
+        let before = by_reason(analytics.get_stats().await.unwrap());
+        // Every reason is reported, counted or not
+        assert_eq!(before.len(), DenyReason::ALL.len());
+
+        let denials = [
+            (DenyReason::Banned, 2),
+            (DenyReason::Challenged, 3),
+            (DenyReason::SecurityUnavailable, 1),
+        ];
+        for (reason, count) in denials {
+            for _ in 0..count {
+                analytics.record_denial_reason(reason).await.unwrap();
+            }
+        }
+
+        let after = by_reason(analytics.get_stats().await.unwrap());
+        for (reason, count) in denials {
+            assert_eq!(after[reason.as_str()] - before[reason.as_str()], count, "{}", reason.as_str());
+        }
+        let recorded: u64 = denials.iter().map(|(_, count)| count).sum();
+        assert!(after.values().sum::<u64>() - before.values().sum::<u64>() >= recorded);
+
+        let minute = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs() / 60;
+        let ttl: i64 = conn
+            .ttl(format!("analytics:denied_reason:{}:{minute}", DenyReason::Banned.as_str()))
+            .await
+            .unwrap();
+        assert!(ttl > 3600 && ttl <= 7200, "{}", ttl);
     }
 }
@@ -0,0 +1,217 @@
+//! Live analytics over WebSocket (enabled with the `websocket` feature).
+//!
+//! `GET /v1/analytics/stream` upgrades to a WebSocket that receives a JSON
+//! snapshot of the stats, the last hour's request rate and recent activity
+//! as soon as it connects, then again whenever a poll finds they changed.
+//! One poller per server reads `AnalyticsManager` every couple of seconds,
+//! and only while someone is connected, however many clients there are.
+//!
+//! Snapshots go through a `watch` channel, which holds only the latest one.
+//! A client that reads slower than snapshots are produced skips the ones it
+//! missed instead of building up a queue.
+
+use axum::{
+    extract::{
+        ws::{close_code, CloseFrame, Message, WebSocket, WebSocketUpgrade},
+        State,
+    },
+    response::Response,
+    routing::get,
+    Router,
+};
+use serde_json::{json, Value};
+use std::{sync::Arc, time::Duration};
+use tokio::sync::watch;
+
+use crate::analytics::AnalyticsManager;
+
+/// How often the poller reads analytics while clients are connected
+pub const STREAM_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Recent activity entries per snapshot
+const SNAPSHOT_ACTIVITY_LIMIT: u32 = 10;
+
+pub struct AnalyticsStream {
+    analytics: Arc<AnalyticsManager>,
+    snapshots: watch::Sender<Value>,
+}
+
+impl AnalyticsStream {
+    /// Start polling `analytics` every `interval`. The poller stops once the
+    /// returned stream is dropped.
+    pub fn spawn(analytics: Arc<AnalyticsManager>, interval: Duration) -> Arc<Self> {
+        let (snapshots, _) = watch::channel(Value::Null);
+        let stream = Arc::new(Self { analytics, snapshots });
+
+        let poller = Arc::downgrade(&stream);
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+            loop {
+                ticker.tick().await;
+                let Some(stream) = poller.upgrade() else {
+                    break;
+                };
+                // Nobody to tell, so leave Redis alone
+                if stream.snapshots.receiver_count() == 0 {
+                    continue;
+                }
+
+                match snapshot(&stream.analytics).await {
+                    Ok(latest) => {
+                        stream.snapshots.send_if_modified(|current| {
+                            if *current == latest {
+                                return false;
+                            }
+                            *current = latest;
+                            true
+                        });
+                    }
+                    Err(e) => tracing::debug!("Failed to poll analytics for stream: {:#}", e),
+                }
+            }
+        });
+
+        stream
+    }
+
+    async fn serve(self: Arc<Self>, mut socket: WebSocket) {
+        let mut snapshots = self.snapshots.subscribe();
+
+        // A fresh snapshot first, so clients don't wait for the next change
+        let initial = match snapshot(&self.analytics).await {
+            Ok(initial) => initial,
+            Err(e) => {
+                tracing::error!("Failed to read analytics for stream: {:#}", e);
+                let _ = socket
+                    .send(Message::Close(Some(CloseFrame {
+                        code: close_code::ERROR,
+                        reason: "analytics unavailable".into(),
+                    })))
+                    .await;
+                return;
+            }
+        };
+        if send(&mut socket, &initial).await.is_err() {
+            return;
+        }
+
+        loop {
+            tokio::select! {
+                changed = snapshots.changed() => {
+                    if changed.is_err() {
+                        break;
+                    }
+                    // Anything published while the last send was in flight is skipped
+                    let latest = snapshots.borrow_and_update().clone();
+                    if send(&mut socket, &latest).await.is_err() {
+                        break;
+                    }
+                }
+                message = socket.recv() => match message {
+                    Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                    // Pings are answered for us; anything else from the client is ignored
+                    Some(Ok(_)) => {}
+                },
+            }
+        }
+    }
+}
+
+/// What every stream message carries
+async fn snapshot(analytics: &AnalyticsManager) -> anyhow::Result<Value> {
+    Ok(json!({
+        "stats": analytics.get_stats().await?,
+        "request_rate": analytics.get_request_rate_data("1h").await?,
+        "recent_activity": analytics.get_recent_activity(SNAPSHOT_ACTIVITY_LIMIT).await?["logs"],
+    }))
+}
+
+async fn send(socket: &mut WebSocket, snapshot: &Value) -> Result<(), axum::Error> {
+    socket.send(Message::Text(snapshot.to_string())).await
+}
+
+/// The stream route; `create_analytics_router` mounts it next to the polling
+/// endpoints, behind the same authentication
+pub fn create_stream_router(analytics: Arc<AnalyticsManager>) -> Router {
+    router(AnalyticsStream::spawn(analytics, STREAM_INTERVAL))
+}
+
+fn router(stream: Arc<AnalyticsStream>) -> Router {
+    Router::new()
+        .route("/v1/analytics/stream", get(stream_analytics))
+        .with_state(stream)
+}
+
+async fn stream_analytics(State(stream): State<Arc<AnalyticsStream>>, upgrade: WebSocketUpgrade) -> Response {
+    upgrade.on_upgrade(move |socket| stream.serve(socket))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::{auth_middleware, ApiKeyValidator};
+    use crate::redis_backend::RedisConnector;
+    use futures_util::StreamExt;
+    use tokio_tungstenite::tungstenite::{self, client::IntoClientRequest};
+
+    const API_KEY: &str = "rw_1234567890abcdef1234567890abcdef";
+
+    type ClientSocket = tokio_tungstenite::WebSocketStream<tokio_tungstenite::MaybeTlsStream<tokio::net::TcpStream>>;
+
+    async fn next_snapshot(socket: &mut ClientSocket) -> Value {
+        loop {
+            if let tungstenite::Message::Text(text) = socket.next().await.unwrap().unwrap() {
+                return serde_json::from_str(&text).unwrap();
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_stream_sends_snapshot_then_updates() {
+        let analytics = Arc::new(AnalyticsManager::new(RedisConnector::open("redis://127.0.0.1:6379").unwrap()));
+        if analytics.get_stats().await.is_err() {
+            println!("Skipping analytics stream test - Redis not available");
+            return;
+        }
+
+        let validator = Arc::new(ApiKeyValidator::new("test_secret".to_string()));
+        let app = router(AnalyticsStream::spawn(analytics.clone(), Duration::from_millis(100)))
+            .layer(axum::middleware::from_fn_with_state(validator, auth_middleware));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}/v1/analytics/stream", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        // The upgrade goes through the same API key check as the polling endpoints
+        match tokio_tungstenite::connect_async(url.as_str()).await {
+            Err(tungstenite::Error::Http(response)) => assert_eq!(response.status(), 401),
+            other => panic!("expected 401, got {:?}", other.map(|(_, response)| response.status())),
+        }
+
+        let mut request = url.into_client_request().unwrap();
+        request
+            .headers_mut()
+            .insert("authorization", format!("Bearer {API_KEY}").parse().unwrap());
+        let (mut socket, _) = tokio_tungstenite::connect_async(request).await.unwrap();
+
+        let initial = next_snapshot(&mut socket).await;
+        let requests_today = initial["stats"]["total_requests_today"].as_u64().unwrap();
+        assert!(initial["recent_activity"].is_array());
+
+        let key = format!("stream-test-{}", uuid::Uuid::new_v4());
+        analytics.record_request(&key, true, 60).await.unwrap();
+
+        // Other tests record requests too, so wait for a snapshot that counts this one
+        let updated = tokio::time::timeout(Duration::from_secs(5), async {
+            loop {
+                let snapshot = next_snapshot(&mut socket).await;
+                if snapshot["stats"]["total_requests_today"].as_u64().unwrap() > requests_today {
+                    return snapshot;
+                }
+            }
+        })
+        .await
+        .expect("no updated snapshot within 5s");
+        assert!(updated["request_rate"]["allowed_data"].is_array());
+    }
+}
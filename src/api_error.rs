@@ -0,0 +1,309 @@
+//! Error responses as RFC 7807 problem documents.
+//!
+//! Handlers and middleware return `ApiError` rather than a bare `StatusCode`,
+//! so every failure has an `application/problem+json` body:
+//!
+//! ```json
+//! {
+//!   "type": "urn:ratewatch:problem:not-found",
+//!   "title": "Not found",
+//!   "status": 404,
+//!   "detail": "Tenant not found",
+//!   "code": "NOT_FOUND",
+//!   "correlation_id": "…"
+//! }
+//! ```
+//!
+//! `type` and `code` name the kind of problem and are stable for clients to
+//! match on; `title` goes with the type, `detail` describes this occurrence
+//! and is for people. A 4xx means the request was wrong and retrying it
+//! unchanged won't help, apart from a 429; a 5xx means the server couldn't
+//! complete it, and the cause is logged under the same correlation ID. The
+//! handler doesn't know the correlation ID, so `correlation_id_middleware`
+//! fills it in on the way out.
+
+use axum::{
+    body::Body,
+    http::{header, HeaderValue, StatusCode},
+    response::{IntoResponse, Json, Response},
+};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::rate_limiter::{DenyReason, DENY_REASON_HEADER};
+
+pub const PROBLEM_JSON: &str = "application/problem+json";
+
+/// Prefix of every problem `type`; the rest is the code in kebab case
+pub const PROBLEM_TYPE_PREFIX: &str = "urn:ratewatch:problem:";
+
+/// An RFC 7807 problem document, as sent to the client
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct ProblemDetails {
+    /// `urn:ratewatch:problem:` and the code in kebab case
+    #[serde(rename = "type")]
+    pub problem_type: String,
+    pub title: String,
+    pub status: u16,
+    pub detail: String,
+    /// Upper-case identifier of the problem, e.g. `NOT_FOUND`
+    pub code: String,
+    /// Why a request refused by the limiter or security layers was refused
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deny_reason: Option<DenyReason>,
+    /// The request's `x-correlation-id`, for finding it in the logs
+    pub correlation_id: Option<String>,
+}
+
+/// A failed API request: its status and the problem to send
+#[derive(Debug, Clone)]
+pub struct ApiError {
+    status: StatusCode,
+    problem: ProblemDetails,
+}
+
+/// `INVALID_WINDOW` as `invalid-window`
+fn problem_type(code: &str) -> String {
+    format!("{}{}", PROBLEM_TYPE_PREFIX, code.to_ascii_lowercase().replace('_', "-"))
+}
+
+/// `INVALID_WINDOW` as `Invalid window`
+fn title(code: &str) -> String {
+    let words = code.to_ascii_lowercase().replace('_', " ");
+    let mut chars = words.chars();
+    match chars.next() {
+        Some(first) => first.to_ascii_uppercase().to_string() + chars.as_str(),
+        None => String::new(),
+    }
+}
+
+impl ApiError {
+    pub fn new(status: StatusCode, code: &str, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            problem: ProblemDetails {
+                problem_type: problem_type(code),
+                title: title(code),
+                status: status.as_u16(),
+                detail: message.into(),
+                code: code.to_string(),
+                deny_reason: None,
+                correlation_id: None,
+            },
+        }
+    }
+
+    pub fn bad_request(code: &str, message: impl Into<String>) -> Self {
+        Self::new(StatusCode::BAD_REQUEST, code, message)
+    }
+
+    /// No credentials, or ones that aren't valid
+    pub fn unauthorized(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::UNAUTHORIZED, "UNAUTHORIZED", message)
+    }
+
+    pub fn forbidden(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::FORBIDDEN, "FORBIDDEN", message)
+    }
+
+    pub fn not_found(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::NOT_FOUND, "NOT_FOUND", message)
+    }
+
+    pub fn conflict(code: &str, message: impl Into<String>) -> Self {
+        Self::new(StatusCode::CONFLICT, code, message)
+    }
+
+    pub fn payload_too_large(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::PAYLOAD_TOO_LARGE, "PAYLOAD_TOO_LARGE", message)
+    }
+
+    pub fn unprocessable(code: &str, message: impl Into<String>) -> Self {
+        Self::new(StatusCode::UNPROCESSABLE_ENTITY, code, message)
+    }
+
+    /// A rate limit or quota was exceeded
+    pub fn too_many_requests(code: &str, message: impl Into<String>) -> Self {
+        Self::new(StatusCode::TOO_MANY_REQUESTS, code, message)
+    }
+
+    /// A feature the route needs is switched off
+    pub fn not_enabled(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::SERVICE_UNAVAILABLE, "NOT_ENABLED", message)
+    }
+
+    pub fn unavailable(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::SERVICE_UNAVAILABLE, "UNAVAILABLE", message)
+    }
+
+    /// The server failed; `message` says what it was doing, the cause goes
+    /// to the log rather than the client
+    pub fn internal(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::INTERNAL_SERVER_ERROR, "INTERNAL_ERROR", message)
+    }
+
+    /// A request refused for `reason`, with its message and upper-cased name
+    /// as the code
+    pub fn denied(reason: DenyReason, status: StatusCode) -> Self {
+        Self::new(status, &reason.as_str().to_ascii_uppercase(), reason.message()).with_deny_reason(reason)
+    }
+
+    /// Say why the request was refused, in the body and `DENY_REASON_HEADER`
+    pub fn with_deny_reason(mut self, reason: DenyReason) -> Self {
+        self.problem.deny_reason = Some(reason);
+        self
+    }
+
+    pub fn status(&self) -> StatusCode {
+        self.status
+    }
+
+    pub fn code(&self) -> &str {
+        &self.problem.code
+    }
+
+    pub fn with_correlation_id(mut self, correlation_id: Uuid) -> Self {
+        self.problem.correlation_id = Some(correlation_id.to_string());
+        self
+    }
+
+    fn body(&self) -> Vec<u8> {
+        serde_json::to_vec(&self.problem).unwrap_or_default()
+    }
+}
+
+/// An unexpected failure; the cause is logged and the client gets a 500
+impl From<anyhow::Error> for ApiError {
+    fn from(error: anyhow::Error) -> Self {
+        if let Some(errors) = error.downcast_ref::<validator::ValidationErrors>() {
+            return errors.clone().into();
+        }
+        tracing::error!("Request failed: {:#}", error);
+        Self::internal("The request could not be completed")
+    }
+}
+
+impl From<validator::ValidationErrors> for ApiError {
+    fn from(errors: validator::ValidationErrors) -> Self {
+        Self::bad_request("VALIDATION_FAILED", errors.to_string())
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let mut response = (self.status, Json(&self.problem)).into_response();
+        response
+            .headers_mut()
+            .insert(header::CONTENT_TYPE, HeaderValue::from_static(PROBLEM_JSON));
+        if let Some(reason) = self.problem.deny_reason {
+            response
+                .headers_mut()
+                .insert(DENY_REASON_HEADER, HeaderValue::from_static(reason.as_str()));
+        }
+        // Kept so `with_correlation_id` can complete the body later
+        response.extensions_mut().insert(self);
+        response
+    }
+}
+
+/// Put `correlation_id` into the body of an `ApiError` response that doesn't
+/// have one yet; other responses are left alone
+pub fn with_correlation_id(mut response: Response, correlation_id: Uuid) -> Response {
+    let Some(error) = response.extensions_mut().remove::<ApiError>() else {
+        return response;
+    };
+    if error.problem.correlation_id.is_some() {
+        return response;
+    }
+    let body = error.with_correlation_id(correlation_id).body();
+    if body.is_empty() {
+        tracing::debug!("Failed to add correlation ID to error body");
+        return response;
+    }
+    response.headers_mut().remove(header::CONTENT_LENGTH);
+    *response.body_mut() = Body::from(body);
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{http::Request, middleware, routing::get, Router};
+    use serde_json::{json, Value};
+    use tower::ServiceExt;
+
+    async fn body(response: Response) -> Value {
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        serde_json::from_slice(&bytes).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_problem_document() {
+        let response = ApiError::bad_request("INVALID_WINDOW", "window must be one of 1h, 6h, 24h, 7d").into_response();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(response.headers()[header::CONTENT_TYPE], PROBLEM_JSON);
+        assert_eq!(
+            body(response).await,
+            json!({
+                "type": "urn:ratewatch:problem:invalid-window",
+                "title": "Invalid window",
+                "status": 400,
+                "detail": "window must be one of 1h, 6h, 24h, 7d",
+                "code": "INVALID_WINDOW",
+                "correlation_id": null,
+            })
+        );
+
+        let response = DenyReason::TenantLimit.response(StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(response.headers()[DENY_REASON_HEADER], "tenant_limit");
+        assert_eq!(response.extensions().get::<DenyReason>(), Some(&DenyReason::TenantLimit));
+        let body = body(response).await;
+        assert_eq!(body["status"], 429);
+        assert_eq!(body["code"], "TENANT_LIMIT");
+        assert_eq!(body["deny_reason"], "tenant_limit");
+    }
+
+    #[test]
+    fn test_errors_are_mapped() {
+        let error = ApiError::from(anyhow::anyhow!("connection refused"));
+        assert_eq!(error.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        // The cause stays in the log
+        assert!(!error.problem.detail.contains("connection refused"));
+
+        let mut errors = validator::ValidationErrors::new();
+        errors.add("limit", validator::ValidationError::new("range"));
+        let error = ApiError::from(anyhow::Error::new(errors));
+        assert_eq!(error.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(error.code(), "VALIDATION_FAILED");
+        assert!(error.problem.detail.contains("limit"));
+    }
+
+    #[tokio::test]
+    async fn test_correlation_id_is_filled_in() {
+        let app = Router::new()
+            .route("/missing", get(|| async { ApiError::not_found("Tenant not found") }))
+            .route("/ok", get(|| async { "ok" }))
+            .layer(middleware::from_fn(crate::telemetry::correlation_id_middleware));
+        let correlation_id = Uuid::new_v4();
+        let request = |uri| {
+            Request::builder()
+                .uri(uri)
+                .header(crate::telemetry::CORRELATION_ID_HEADER, correlation_id.to_string())
+                .body(Body::empty())
+                .unwrap()
+        };
+
+        let response = app.clone().oneshot(request("/missing")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        assert_eq!(response.headers()[header::CONTENT_TYPE], PROBLEM_JSON);
+        let body = body(response).await;
+        assert_eq!(body["code"], "NOT_FOUND");
+        assert_eq!(body["detail"], "Tenant not found");
+        assert_eq!(body["correlation_id"], correlation_id.to_string());
+
+        let response = app.oneshot(request("/ok")).await.unwrap();
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(bytes, "ok");
+    }
+}
@@ -1,10 +1,16 @@
-use crate::audit::{AuditLogger, audit_event::ActorInfo};
+use crate::audit::{
+    audit_event::{ActorInfo, AuditEventType, AuditOutcome},
+    audit_logger::{AuditVerificationReport, SigningKeyRotation},
+    AuditCursor, AuditLogger, AuditQuery, DigitalSigner,
+};
+use crate::api_error::ApiError;
+use crate::auth::ApiKeyIdentity;
 use axum::{
     extract::{Query, State},
     http::StatusCode,
     response::Json,
-    routing::get,
-    Router,
+    routing::{get, post},
+    Extension, Router,
 };
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
@@ -12,22 +18,56 @@ use serde_json::{json, Value};
 use std::sync::Arc;
 
 #[derive(Debug, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::IntoParams))]
+#[cfg_attr(feature = "openapi", into_params(parameter_in = Query))]
 pub struct AuditQueryParams {
     pub start_time: Option<DateTime<Utc>>,
     pub end_time: Option<DateTime<Utc>>,
     pub actor_id: Option<String>,
     pub tenant_id: Option<String>,
+    /// Page size; defaults to 1000, at most 10000
     pub limit: Option<usize>,
+    /// `next_cursor` of the previous page
+    pub cursor: Option<String>,
+    /// Comma-separated event types, e.g. `Authentication,Authorization`
+    pub event_types: Option<String>,
+    /// Comma-separated outcomes, e.g. `Failure`
+    pub outcomes: Option<String>,
+    /// Case-insensitive substring of the action
+    pub action: Option<String>,
+}
+
+/// Audit events returned per page when the query gives no limit
+const DEFAULT_PAGE_SIZE: usize = 1000;
+/// Largest page a query may ask for
+const MAX_PAGE_SIZE: usize = 10_000;
+
+/// Parse a comma-separated list of enum variant names
+fn parse_list<T: serde::de::DeserializeOwned>(field: &str, list: Option<&str>) -> Result<Vec<T>, ApiError> {
+    list.unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(|name| {
+            serde_json::from_value(Value::String(name.to_string()))
+                .map_err(|_| ApiError::bad_request("INVALID_QUERY", format!("Unknown {} '{}'", field, name)))
+        })
+        .collect()
 }
 
 #[derive(Debug, Serialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 pub struct AuditQueryResponse {
     pub events: Vec<serde_json::Value>,
+    /// Events in this page
     pub total_count: usize,
+    /// Pass as `cursor` to fetch the next page; absent on the last one
+    pub next_cursor: Option<String>,
     pub query_info: AuditQueryInfo,
 }
 
 #[derive(Debug, Serialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 pub struct AuditQueryInfo {
     pub start_time: DateTime<Utc>,
     pub end_time: DateTime<Utc>,
@@ -36,6 +76,34 @@ pub struct AuditQueryInfo {
     pub query_timestamp: DateTime<Utc>,
 }
 
+#[derive(Debug, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::IntoParams))]
+#[cfg_attr(feature = "openapi", into_params(parameter_in = Query))]
+pub struct AuditVerifyParams {
+    /// Defaults to 24 hours before `end_time`
+    pub start_time: Option<DateTime<Utc>>,
+    /// Defaults to now
+    pub end_time: Option<DateTime<Utc>>,
+    pub tenant_id: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct SigningKeyRotationRequest {
+    /// The new key, at least 32 characters; one is generated if omitted
+    pub signing_key: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct SigningKeyRotationResponse {
+    #[serde(flatten)]
+    pub rotation: SigningKeyRotation,
+    /// The generated key, shown only in this response
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub signing_key: Option<String>,
+}
+
 pub fn create_audit_router(audit_logger: Arc<AuditLogger>) -> Router {
     Router::new()
         .route("/v1/audit/events", get(query_audit_events))
@@ -44,10 +112,34 @@ pub fn create_audit_router(audit_logger: Arc<AuditLogger>) -> Router {
         .with_state(audit_logger)
 }
 
+/// Operator routes for the audit log; mount behind `admin_auth_middleware`
+pub fn create_audit_admin_router(audit_logger: Arc<AuditLogger>) -> Router {
+    Router::new()
+        .route("/v1/admin/audit/verify", get(verify_audit_log))
+        .route("/v1/admin/audit/signing-key", post(rotate_signing_key))
+        .with_state(audit_logger)
+}
+
+#[cfg_attr(
+    feature = "openapi",
+    utoipa::path(
+        get,
+        path = "/v1/audit/events",
+        tag = "audit",
+        params(AuditQueryParams),
+        responses(
+            (status = 200, description = "Matching audit events", body = AuditQueryResponse),
+            (status = 400, description = "Unknown event type or outcome, or invalid cursor", body = crate::api_error::ProblemDetails),
+            (status = 500, description = "Internal error", body = crate::api_error::ProblemDetails),
+            (status = 401, description = "Missing or invalid API key"),
+        ),
+        security(("api_key" = [])),
+    )
+)]
 async fn query_audit_events(
     State(audit_logger): State<Arc<AuditLogger>>,
     Query(params): Query<AuditQueryParams>,
-) -> Result<Json<AuditQueryResponse>, StatusCode> {
+) -> Result<Json<AuditQueryResponse>, ApiError> {
     // Default time range: last 24 hours
     let end_time = params.end_time.unwrap_or_else(Utc::now);
     let start_time = params
@@ -57,37 +149,57 @@ async fn query_audit_events(
     // Create accessor info for audit-the-auditor
     let accessor = ActorInfo::new(); // Would be populated from request context
 
-    let events = if let Some(actor_id) = &params.actor_id {
-        // Query by actor
-        audit_logger
-            .get_events_by_actor(actor_id, params.tenant_id.as_deref(), accessor)
-            .await
-            .map_err(|e| {
-                tracing::error!("Failed to query audit events by actor: {}", e);
-                StatusCode::INTERNAL_SERVER_ERROR
-            })?
-    } else {
-        // Query by time range
-        audit_logger
-            .get_events_by_timerange(start_time, end_time, params.tenant_id.as_deref(), accessor)
-            .await
-            .map_err(|e| {
-                tracing::error!("Failed to query audit events by timerange: {}", e);
-                StatusCode::INTERNAL_SERVER_ERROR
-            })?
+    let event_types: Vec<AuditEventType> = parse_list("event type", params.event_types.as_deref())?;
+    let outcomes: Vec<AuditOutcome> = parse_list("outcome", params.outcomes.as_deref())?;
+    let narrowed = !event_types.is_empty() || !outcomes.is_empty() || params.action.is_some();
+    let cursor = params
+        .cursor
+        .as_deref()
+        .map(str::parse::<AuditCursor>)
+        .transpose()
+        .map_err(|e| ApiError::bad_request("INVALID_CURSOR", e.to_string()))?;
+    let page_size = params.limit.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE);
+
+    // An actor alone, with no start time, means their whole history
+    let query_start = match &params.actor_id {
+        Some(_) if !narrowed && params.start_time.is_none() => DateTime::<Utc>::UNIX_EPOCH,
+        _ => start_time,
     };
 
-    // Apply limit if specified
-    let limited_events = if let Some(limit) = params.limit {
-        events.into_iter().take(limit).collect()
+    // One event past the page tells whether there's another page
+    let mut query = AuditQuery::new(query_start, end_time)
+        .with_event_types(event_types)
+        .with_outcomes(outcomes)
+        .with_limit(page_size + 1);
+    if let Some(actor_id) = &params.actor_id {
+        query = query.with_actor_id(actor_id.clone());
+    }
+    if let Some(tenant_id) = &params.tenant_id {
+        query = query.with_tenant_id(tenant_id.clone());
+    }
+    if let Some(action) = &params.action {
+        query = query.with_action_contains(action.clone());
+    }
+    if let Some(cursor) = cursor {
+        query = query.with_after(cursor);
+    }
+
+    let mut events = audit_logger.query_events(&query, accessor).await.map_err(|e| {
+        tracing::error!("Failed to query audit events: {}", e);
+        ApiError::internal("Failed to query audit events")
+    })?;
+
+    let next_cursor = if events.len() > page_size {
+        events.truncate(page_size);
+        events.last().map(|event| AuditCursor::of(event).to_string())
     } else {
-        events
+        None
     };
 
-    let total_count = limited_events.len();
+    let total_count = events.len();
 
     // Convert events to JSON, redacting sensitive data
-    let event_json: Vec<Value> = limited_events
+    let event_json: Vec<Value> = events
         .into_iter()
         .map(|event| {
             let redacted_event = event.redacted();
@@ -98,8 +210,9 @@ async fn query_audit_events(
     let response = AuditQueryResponse {
         events: event_json,
         total_count,
+        next_cursor,
         query_info: AuditQueryInfo {
-            start_time,
+            start_time: query_start,
             end_time,
             tenant_id: params.tenant_id,
             actor_id: params.actor_id,
@@ -110,10 +223,25 @@ async fn query_audit_events(
     Ok(Json(response))
 }
 
+#[cfg_attr(
+    feature = "openapi",
+    utoipa::path(
+        get,
+        path = "/v1/audit/statistics",
+        tag = "audit",
+        params(AuditQueryParams),
+        responses(
+            (status = 200, description = "Audit statistics", body = Object),
+            (status = 500, description = "Internal error", body = crate::api_error::ProblemDetails),
+            (status = 401, description = "Missing or invalid API key"),
+        ),
+        security(("api_key" = [])),
+    )
+)]
 async fn get_audit_statistics(
     State(audit_logger): State<Arc<AuditLogger>>,
     Query(params): Query<AuditQueryParams>,
-) -> Result<Json<Value>, StatusCode> {
+) -> Result<Json<Value>, ApiError> {
     // Default time range: last 24 hours
     let end_time = params.end_time.unwrap_or_else(Utc::now);
     let start_time = params
@@ -125,7 +253,7 @@ async fn get_audit_statistics(
         .await
         .map_err(|e| {
             tracing::error!("Failed to get audit statistics: {}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
+            ApiError::internal("Failed to get audit statistics")
         })?;
 
     let response = json!({
@@ -141,15 +269,30 @@ async fn get_audit_statistics(
     Ok(Json(response))
 }
 
+#[cfg_attr(
+    feature = "openapi",
+    utoipa::path(
+        get,
+        path = "/v1/audit/health",
+        tag = "audit",
+        responses(
+            (status = 200, description = "Audit storage health and integrity", body = Object),
+            (status = 503, description = "Stored events failed the integrity check", body = Object),
+            (status = 500, description = "Internal error", body = crate::api_error::ProblemDetails),
+            (status = 401, description = "Missing or invalid API key"),
+        ),
+        security(("api_key" = [])),
+    )
+)]
 async fn audit_system_health(
     State(audit_logger): State<Arc<AuditLogger>>,
-) -> Result<Json<Value>, StatusCode> {
+) -> Result<(StatusCode, Json<Value>), ApiError> {
     let integrity_check = audit_logger
         .verify_storage_integrity()
         .await
         .map_err(|e| {
             tracing::error!("Audit system integrity check failed: {}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
+            ApiError::internal("Audit system integrity check failed")
         })?;
 
     let status = if integrity_check { "healthy" } else { "unhealthy" };
@@ -161,10 +304,117 @@ async fn audit_system_health(
         "version": env!("CARGO_PKG_VERSION")
     });
 
-    match integrity_check {
-        true => Ok(Json(response)),
-        false => Err(StatusCode::SERVICE_UNAVAILABLE),
+    let code = if integrity_check { StatusCode::OK } else { StatusCode::SERVICE_UNAVAILABLE };
+    Ok((code, Json(response)))
+}
+
+#[cfg_attr(
+    feature = "openapi",
+    utoipa::path(
+        get,
+        path = "/v1/admin/audit/verify",
+        tag = "admin",
+        params(AuditVerifyParams),
+        responses(
+            (status = 200, description = "Signature and completeness check of the events in the range", body = AuditVerificationReport),
+            (status = 400, description = "start_time is after end_time", body = crate::api_error::ProblemDetails),
+            (status = 500, description = "Internal error", body = crate::api_error::ProblemDetails),
+            (status = 401, description = "Missing or invalid API key"),
+            (status = 403, description = "API key is not an admin key"),
+        ),
+        security(("api_key" = [])),
+    )
+)]
+async fn verify_audit_log(
+    State(audit_logger): State<Arc<AuditLogger>>,
+    Extension(identity): Extension<ApiKeyIdentity>,
+    Query(params): Query<AuditVerifyParams>,
+) -> Result<Json<AuditVerificationReport>, ApiError> {
+    let end_time = params.end_time.unwrap_or_else(Utc::now);
+    let start_time = params
+        .start_time
+        .unwrap_or_else(|| end_time - chrono::Duration::hours(24));
+    if start_time > end_time {
+        return Err(ApiError::bad_request("INVALID_TIME_RANGE", "start_time is after end_time"));
+    }
+
+    let result = audit_logger
+        .verify_events_in_range(start_time, end_time, params.tenant_id.as_deref())
+        .await;
+
+    // The verification run is itself recorded, whatever it found
+    let (outcome, details) = match &result {
+        Ok(report) => (
+            AuditOutcome::Success,
+            serde_json::to_value(report).unwrap_or_else(|_| json!({})),
+        ),
+        Err(e) => (AuditOutcome::Failure, json!({ "error": format!("{e:#}") })),
+    };
+    let actor = ActorInfo::new().with_api_key(identity.key_id);
+    if let Err(e) = audit_logger
+        .log_admin_action(actor, "verify_integrity", "audit_log", None, outcome, params.tenant_id.clone(), Some(details))
+        .await
+    {
+        tracing::error!("Failed to audit integrity verification: {}", e);
+    }
+
+    let report = result.map_err(|e| {
+        tracing::error!("Audit log verification failed: {:#}", e);
+        ApiError::internal("Audit log verification failed")
+    })?;
+    if !report.is_valid() {
+        tracing::warn!(
+            invalid_signatures = report.invalid_signatures,
+            unreadable_entries = report.unreadable_entries,
+            first_invalid_event_id = ?report.first_invalid_event_id,
+            "Audit log verification found problems"
+        );
+    }
+
+    Ok(Json(report))
+}
+
+#[cfg_attr(
+    feature = "openapi",
+    utoipa::path(
+        post,
+        path = "/v1/admin/audit/signing-key",
+        tag = "admin",
+        request_body = SigningKeyRotationRequest,
+        responses(
+            (status = 200, description = "Events are now signed with the new key; the old one only verifies", body = SigningKeyRotationResponse),
+            (status = 400, description = "Signing key shorter than 32 characters", body = crate::api_error::ProblemDetails),
+            (status = 401, description = "Missing or invalid API key"),
+            (status = 403, description = "API key is not an admin key"),
+        ),
+        security(("api_key" = [])),
+    )
+)]
+async fn rotate_signing_key(
+    State(audit_logger): State<Arc<AuditLogger>>,
+    Extension(identity): Extension<ApiKeyIdentity>,
+    Json(request): Json<SigningKeyRotationRequest>,
+) -> Result<Json<SigningKeyRotationResponse>, ApiError> {
+    let generated = request.signing_key.is_none().then(DigitalSigner::generate_key);
+    let signing_key = request.signing_key.as_deref().or(generated.as_deref()).unwrap_or_default();
+    let rotation = audit_logger
+        .rotate_signing_key(signing_key)
+        .map_err(|e| ApiError::bad_request("INVALID_SIGNING_KEY", e.to_string()))?;
+
+    // Signed with the new key, so it also shows the new key works
+    let actor = ActorInfo::new().with_api_key(identity.key_id);
+    let changes = json!({ "key_id": rotation.key_id, "previous_key_id": rotation.previous_key_id });
+    if let Err(e) = audit_logger
+        .log_admin_action(actor, "rotate_signing_key", "audit_log", None, AuditOutcome::Success, None, Some(changes))
+        .await
+    {
+        tracing::error!("Failed to audit signing key rotation: {}", e);
     }
+
+    Ok(Json(SigningKeyRotationResponse {
+        rotation,
+        signing_key: generated,
+    }))
 }
 
 #[cfg(test)]
@@ -297,6 +547,110 @@ mod tests {
         assert_eq!(response.status(), StatusCode::OK);
     }
 
+    #[tokio::test]
+    async fn test_audit_events_filtered_by_type_and_outcome() {
+        let (audit_logger, test_storage) = create_test_audit_logger().await;
+
+        for (event_type, outcome, tenant) in [
+            (AuditEventType::Authentication, AuditOutcome::Failure, "tenant-x"),
+            (AuditEventType::Authentication, AuditOutcome::Success, "tenant-x"),
+            (AuditEventType::Authentication, AuditOutcome::Failure, "tenant-y"),
+            (AuditEventType::ApiRequest, AuditOutcome::Failure, "tenant-x"),
+        ] {
+            let event = AuditEvent::new(
+                event_type,
+                ActorInfo::new().with_api_key("test-key".to_string()),
+                ResourceInfo::new("authentication".to_string()),
+                "api_key_login".to_string(),
+                outcome,
+            )
+            .with_tenant_id(tenant.to_string());
+            test_storage.add_test_event(event).await;
+        }
+
+        let query = |uri: &str| {
+            let app = create_audit_router(audit_logger.clone());
+            let request = Request::builder().uri(uri.to_string()).body(Body::empty()).unwrap();
+            async move { app.oneshot(request).await.unwrap() }
+        };
+
+        let response = query("/v1/audit/events?event_types=Authentication&outcomes=Failure&tenant_id=tenant-x&action=login").await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["total_count"], 1);
+        assert_eq!(body["events"][0]["event_type"], "Authentication");
+        assert_eq!(body["events"][0]["outcome"], "Failure");
+        assert_eq!(body["events"][0]["tenant_id"], "tenant-x");
+
+        let response = query("/v1/audit/events?event_types=Authentication,ApiRequest&outcomes=Failure").await;
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(serde_json::from_slice::<Value>(&body).unwrap()["total_count"], 3);
+
+        let response = query("/v1/audit/events?event_types=Bogus").await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["code"], "INVALID_QUERY");
+        assert_eq!(body["detail"], "Unknown event type 'Bogus'");
+    }
+
+    #[tokio::test]
+    async fn test_audit_events_page_through_cursor() {
+        let (audit_logger, test_storage) = create_test_audit_logger().await;
+
+        // Pairs share a timestamp, so pages must split ties by ID
+        let base = Utc::now() - chrono::Duration::minutes(10);
+        let mut expected = Vec::new();
+        for i in 0..25 {
+            let mut event = AuditEvent::new(
+                AuditEventType::ApiRequest,
+                ActorInfo::new().with_api_key("test-key".to_string()),
+                ResourceInfo::new("rate_limiter".to_string()),
+                "check".to_string(),
+                AuditOutcome::Success,
+            );
+            event.timestamp = base + chrono::Duration::seconds(i / 2);
+            expected.push(event.id.to_string());
+            test_storage.add_test_event(event).await;
+        }
+
+        let mut seen = Vec::new();
+        let mut cursor: Option<String> = None;
+        let mut pages = 0;
+        loop {
+            let uri = match &cursor {
+                Some(cursor) => format!("/v1/audit/events?limit=10&cursor={}", cursor),
+                None => "/v1/audit/events?limit=10".to_string(),
+            };
+            let app = create_audit_router(audit_logger.clone());
+            let response = app.oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap()).await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+            let body: Value = serde_json::from_slice(&body).unwrap();
+
+            let events = body["events"].as_array().unwrap();
+            assert!(events.len() <= 10);
+            seen.extend(events.iter().map(|event| event["id"].as_str().unwrap().to_string()));
+            pages += 1;
+            match body["next_cursor"].as_str() {
+                Some(next) => cursor = Some(next.to_string()),
+                None => break,
+            }
+        }
+
+        assert_eq!(pages, 3);
+        let unique: std::collections::HashSet<_> = seen.iter().collect();
+        assert_eq!(unique.len(), seen.len(), "no event on two pages");
+        expected.sort();
+        seen.sort();
+        assert_eq!(seen, expected, "every event on some page");
+
+        let app = create_audit_router(audit_logger.clone());
+        let request = Request::builder().uri("/v1/audit/events?cursor=bogus").body(Body::empty()).unwrap();
+        assert_eq!(app.oneshot(request).await.unwrap().status(), StatusCode::BAD_REQUEST);
+    }
+
     #[tokio::test]
     async fn test_audit_statistics_endpoint() {
         let (audit_logger, _) = create_test_audit_logger().await;
@@ -315,6 +669,73 @@ mod tests {
         assert_eq!(response.status(), StatusCode::OK);
     }
 
+    #[tokio::test]
+    async fn test_verify_endpoint_reports_and_audits_itself() {
+        let (audit_logger, test_storage) = create_test_audit_logger().await;
+
+        let event = || {
+            AuditEvent::new(
+                AuditEventType::ApiRequest,
+                ActorInfo::new().with_api_key("test-key".to_string()),
+                ResourceInfo::new("rate_limiter".to_string()),
+                "check".to_string(),
+                AuditOutcome::Success,
+            )
+        };
+        audit_logger.log_event(event()).await.unwrap();
+        audit_logger.log_event(event()).await.unwrap();
+        // Written around the logger, so never signed
+        let unsigned = event();
+        test_storage.add_test_event(unsigned.clone()).await;
+
+        let app = create_audit_admin_router(audit_logger).layer(Extension(ApiKeyIdentity {
+            key_id: "admin-key".to_string(),
+            tenant_id: None,
+        }));
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/v1/admin/audit/verify")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let report: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(report["events_checked"], 3);
+        assert_eq!(report["valid_signatures"], 2);
+        assert_eq!(report["first_invalid_event_id"], unsigned.id.to_string());
+        assert_eq!(report["chain_status"], "intact");
+
+        let events = test_storage.events.read().await;
+        let audit_entry = events.last().unwrap();
+        assert_eq!(audit_entry.action, "verify_integrity");
+        assert_eq!(audit_entry.actor.api_key_id.as_deref(), Some("admin-key"));
+    }
+
+    #[tokio::test]
+    async fn test_verify_endpoint_rejects_inverted_range() {
+        let (audit_logger, _) = create_test_audit_logger().await;
+        let app = create_audit_admin_router(audit_logger).layer(Extension(ApiKeyIdentity {
+            key_id: "admin-key".to_string(),
+            tenant_id: None,
+        }));
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/v1/admin/audit/verify?start_time=2025-01-02T00:00:00Z&end_time=2025-01-01T00:00:00Z")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
     #[tokio::test]
     async fn test_audit_health_endpoint() {
         let (audit_logger, _) = create_test_audit_logger().await;
@@ -332,4 +753,72 @@ mod tests {
 
         assert_eq!(response.status(), StatusCode::OK);
     }
-}
\ No newline at end of file
+
+    #[tokio::test]
+    async fn test_rotating_signing_key_keeps_old_events_verifiable() {
+        let (audit_logger, test_storage) = create_test_audit_logger().await;
+        let event = || {
+            AuditEvent::new(
+                AuditEventType::ApiRequest,
+                ActorInfo::new().with_api_key("test-key".to_string()),
+                ResourceInfo::new("rate_limiter".to_string()),
+                "check".to_string(),
+                AuditOutcome::Success,
+            )
+        };
+        let key_a = audit_logger.signing_key_id();
+        audit_logger.log_event(event()).await.unwrap();
+
+        let rotate = |body: Value| {
+            let app = create_audit_admin_router(audit_logger.clone()).layer(Extension(ApiKeyIdentity {
+                key_id: "admin-key".to_string(),
+                tenant_id: None,
+            }));
+            let request = Request::builder()
+                .method("POST")
+                .uri("/v1/admin/audit/signing-key")
+                .header("content-type", "application/json")
+                .body(Body::from(body.to_string()))
+                .unwrap();
+            async move { app.oneshot(request).await.unwrap() }
+        };
+        assert_eq!(rotate(json!({ "signing_key": "short" })).await.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(audit_logger.signing_key_id(), key_a);
+
+        let response = rotate(json!({ "signing_key": "key-b-that-is-long-enough-for-audit-signing" })).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        let key_b = audit_logger.signing_key_id();
+        assert_ne!(key_b, key_a);
+        assert_eq!(body["key_id"], key_b);
+        assert_eq!(body["previous_key_id"], key_a);
+        assert_eq!(body["retired_key_ids"], json!([key_a]));
+        assert!(body.get("signing_key").is_none());
+        audit_logger.log_event(event()).await.unwrap();
+
+        // Events signed with key A and with key B both verify
+        let events = test_storage.events.read().await.clone();
+        let signed_with = |key_id: &str| events.iter().filter(|e| e.key_id.as_deref() == Some(key_id)).count();
+        assert_eq!(signed_with(&key_a), 1);
+        // The logged event and the rotation's own audit entry
+        assert_eq!(signed_with(&key_b), 2);
+        for event in &events {
+            assert!(audit_logger.verify_event_integrity(event).await.unwrap());
+        }
+        let report = audit_logger
+            .verify_events_in_range(Utc::now() - chrono::Duration::hours(1), Utc::now(), None)
+            .await
+            .unwrap();
+        assert_eq!(report.valid_signatures, 3);
+        assert!(report.is_valid());
+
+        // Without a key, one is generated and returned once
+        let response = rotate(json!({})).await;
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        let generated = body["signing_key"].as_str().unwrap();
+        assert_eq!(DigitalSigner::new(generated).unwrap().key_id(), audit_logger.signing_key_id());
+        assert_eq!(body["retired_key_ids"], json!([key_b, key_a]));
+    }
+}
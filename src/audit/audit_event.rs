@@ -14,6 +14,10 @@ pub struct AuditEvent {
     pub outcome: AuditOutcome,
     pub metadata: HashMap<String, serde_json::Value>,
     pub signature: Option<String>,
+    /// ID of the key `signature` was made with; absent on events signed
+    /// before keys could be rotated
+    #[serde(default)]
+    pub key_id: Option<String>,
     pub correlation_id: Option<Uuid>,
     pub tenant_id: Option<String>,
 }
@@ -76,6 +80,7 @@ impl AuditEvent {
             outcome,
             metadata: HashMap::new(),
             signature: None,
+            key_id: None,
             correlation_id: None,
             tenant_id: None,
         }
@@ -101,6 +106,11 @@ impl AuditEvent {
         self
     }
 
+    pub fn with_key_id(mut self, key_id: String) -> Self {
+        self.key_id = Some(key_id);
+        self
+    }
+
     /// Get the canonical string representation for signing
     pub fn canonical_string(&self) -> String {
         format!(
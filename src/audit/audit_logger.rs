@@ -1,21 +1,35 @@
 use crate::audit::{
     audit_event::{AuditEvent, AuditEventType, AuditOutcome, ActorInfo, ResourceInfo},
     audit_filter::{AuditFilter, AuditFilterSet},
-    audit_storage::AuditStorage,
+    audit_query::AuditQuery,
+    audit_storage::{AuditStorage, StoredEntry},
     digital_signer::DigitalSigner,
 };
+use crate::config::{AuditBackpressure, AuditBufferConfig};
 use anyhow::Result;
 use chrono::{DateTime, Utc};
 use std::sync::Arc;
-use tokio::sync::RwLock;
+use tokio::sync::{mpsc, Mutex, RwLock};
+use tokio::task::JoinHandle;
 use tracing::{error, info, warn};
 use uuid::Uuid;
 
 pub struct AuditLogger {
-    storage: Box<dyn AuditStorage>,
-    signer: DigitalSigner,
+    storage: Arc<dyn AuditStorage>,
+    /// Replaced whole when the signing key is rotated
+    signer: std::sync::RwLock<DigitalSigner>,
     filters: Arc<RwLock<AuditFilterSet>>,
     audit_access_logger: Option<Arc<AuditLogger>>, // For audit-the-auditor functionality
+    buffer: Option<AuditBuffer>,
+}
+
+/// Queue between `log_event` and the background task that stores events in
+/// batches. The sender is taken on shutdown, which lets the task drain the
+/// queue and exit.
+struct AuditBuffer {
+    sender: std::sync::Mutex<Option<mpsc::Sender<AuditEvent>>>,
+    backpressure: AuditBackpressure,
+    flusher: Mutex<Option<JoinHandle<()>>>,
 }
 
 impl AuditLogger {
@@ -27,10 +41,11 @@ impl AuditLogger {
         let filter_set = AuditFilterSet::with_filters(filters);
         
         Ok(Self {
-            storage,
-            signer,
+            storage: Arc::from(storage),
+            signer: std::sync::RwLock::new(signer),
             filters: Arc::new(RwLock::new(filter_set)),
             audit_access_logger: None,
+            buffer: None,
         })
     }
 
@@ -49,45 +64,122 @@ impl AuditLogger {
         let filter_set = AuditFilterSet::with_filters(filters);
         
         Ok(Self {
-            storage,
-            signer,
+            storage: Arc::from(storage),
+            signer: std::sync::RwLock::new(signer),
             filters: Arc::new(RwLock::new(filter_set)),
             audit_access_logger: Some(audit_access_logger),
+            buffer: None,
         })
     }
 
+    /// Store events from a background task instead of in `log_event`.
+    ///
+    /// Events are still filtered and signed when they're logged, so their
+    /// signatures don't depend on when they're written. `log_event` then only
+    /// waits for room in the queue (or, with `AuditBackpressure::Drop`, not at
+    /// all), which takes storage round-trips off the request path. The cost is
+    /// durability: events queued but not yet stored are lost if the process
+    /// dies, and storage errors are only logged and counted. Call `shutdown`
+    /// before exiting to store everything still queued.
+    pub fn with_buffer(mut self, config: &AuditBufferConfig) -> Self {
+        if !config.enabled {
+            return self;
+        }
+
+        let (sender, receiver) = mpsc::channel(config.capacity);
+        let flusher = tokio::spawn(flush_events(
+            self.storage.clone(),
+            receiver,
+            config.batch_size,
+            std::time::Duration::from_millis(config.flush_interval_ms),
+        ));
+        self.buffer = Some(AuditBuffer {
+            sender: std::sync::Mutex::new(Some(sender)),
+            backpressure: config.backpressure,
+            flusher: Mutex::new(Some(flusher)),
+        });
+        self
+    }
+
+    /// Store every buffered event and stop the background writer. Events
+    /// logged afterwards are stored directly. Does nothing without a buffer.
+    pub async fn shutdown(&self) {
+        let Some(buffer) = &self.buffer else {
+            return;
+        };
+
+        // Closing the queue lets the writer finish what's in it and exit
+        buffer.sender.lock().unwrap().take();
+        if let Some(flusher) = buffer.flusher.lock().await.take() {
+            if let Err(e) = flusher.await {
+                error!(error = %e, "Audit writer task failed during shutdown");
+            }
+        }
+    }
+
+    /// Sign events with `key` from now on. The current key is retired but
+    /// still verifies the events it signed.
+    pub fn rotate_signing_key(&self, key: &str) -> Result<SigningKeyRotation> {
+        let mut signer = self.signer.write().unwrap();
+        let rotated = signer.rotate(key)?;
+        let rotation = SigningKeyRotation {
+            previous_key_id: signer.key_id().to_string(),
+            key_id: rotated.key_id().to_string(),
+            retired_key_ids: rotated.retired_key_ids(),
+        };
+        *signer = rotated;
+        info!(key_id = %rotation.key_id, previous_key_id = %rotation.previous_key_id, "Audit signing key rotated");
+        Ok(rotation)
+    }
+
+    /// ID of the key events are currently signed with
+    pub fn signing_key_id(&self) -> String {
+        self.signer.read().unwrap().key_id().to_string()
+    }
+
     /// Log an audit event
     pub async fn log_event(&self, mut event: AuditEvent) -> Result<()> {
         // Check if the event should be filtered
         let filters = self.filters.read().await;
         if filters.should_filter(&event) {
+            crate::metrics::AUDIT_EVENTS_LOGGED.with_label_values(&["filtered"]).inc();
             return Ok(()); // Event filtered, don't log
         }
         drop(filters);
 
         // Sign the event
         let canonical_string = event.canonical_string();
-        let signature = self.signer.sign(&canonical_string)?;
-        event = event.with_signature(signature);
+        let (signature, key_id) = {
+            let signer = self.signer.read().unwrap();
+            (signer.sign(&canonical_string)?, signer.key_id().to_string())
+        };
+        event = event.with_signature(signature).with_key_id(key_id);
+
+        let sender = self
+            .buffer
+            .as_ref()
+            .and_then(|buffer| buffer.sender.lock().unwrap().clone().map(|sender| (sender, buffer.backpressure)));
+        let Some((sender, backpressure)) = sender else {
+            return store_events(self.storage.as_ref(), std::slice::from_ref(&event)).await;
+        };
 
-        // Store the event
-        match self.storage.store_event(&event).await {
-            Ok(_) => {
-                info!(
-                    event_id = %event.id,
-                    event_type = ?event.event_type,
-                    actor = ?event.actor.user_id.as_deref().unwrap_or("unknown"),
-                    "Audit event logged successfully"
-                );
-            }
-            Err(e) => {
-                error!(
-                    event_id = %event.id,
-                    error = %e,
-                    "Failed to store audit event"
-                );
-                return Err(e);
+        match backpressure {
+            AuditBackpressure::Block => {
+                if let Err(mpsc::error::SendError(event)) = sender.send(event).await {
+                    // Shut down since the sender was cloned
+                    return store_events(self.storage.as_ref(), std::slice::from_ref(&event)).await;
+                }
             }
+            AuditBackpressure::Drop => match sender.try_send(event) {
+                Ok(()) => {}
+                Err(mpsc::error::TrySendError::Full(event)) => {
+                    crate::metrics::AUDIT_EVENTS_LOGGED.with_label_values(&["dropped"]).inc();
+                    warn!(event_id = %event.id, "Audit buffer full, dropping event");
+                }
+                Err(mpsc::error::TrySendError::Closed(event)) => {
+                    return store_events(self.storage.as_ref(), std::slice::from_ref(&event)).await;
+                }
+            },
         }
 
         Ok(())
@@ -180,6 +272,7 @@ impl AuditLogger {
         tenant_id: Option<String>,
         threat_level: Option<&str>,
         details: Option<&str>,
+        correlation_id: Option<Uuid>,
     ) -> Result<()> {
         let resource = ResourceInfo::new(resource_type.to_string());
 
@@ -209,6 +302,10 @@ impl AuditLogger {
             );
         }
 
+        if let Some(cid) = correlation_id {
+            event = event.with_correlation_id(cid);
+        }
+
         self.log_event(event).await
     }
 
@@ -304,11 +401,33 @@ impl AuditLogger {
         self.storage.get_events_by_actor(actor_id, tenant_id).await
     }
 
+    /// Retrieve audit events matching a query (with audit-the-auditor logging)
+    pub async fn query_events(&self, query: &AuditQuery, accessor: ActorInfo) -> Result<Vec<AuditEvent>> {
+        // Log the audit access
+        if let Some(audit_access_logger) = &self.audit_access_logger {
+            let resource = ResourceInfo::new("audit_log".to_string());
+            let event = AuditEvent::new(
+                AuditEventType::DataAccess,
+                accessor.clone(),
+                resource,
+                "query".to_string(),
+                AuditOutcome::Success,
+            )
+            .with_metadata("query".to_string(), serde_json::to_value(query).unwrap_or_default());
+
+            if let Err(e) = audit_access_logger.log_event(event).await {
+                warn!("Failed to log audit access: {}", e);
+            }
+        }
+
+        self.storage.query(query).await
+    }
+
     /// Verify the integrity of an audit event
     pub async fn verify_event_integrity(&self, event: &AuditEvent) -> Result<bool> {
         if let Some(signature) = &event.signature {
             let canonical_string = event.canonical_string();
-            self.signer.verify(&canonical_string, signature)
+            self.signer.read().unwrap().verify_with_key(event.key_id.as_deref(), &canonical_string, signature)
         } else {
             Ok(false) // No signature means no integrity verification possible
         }
@@ -319,6 +438,49 @@ impl AuditLogger {
         self.storage.verify_integrity().await
     }
 
+    /// Check the signature of every event stored in the time range, streaming
+    /// them from storage so large logs aren't loaded at once
+    pub async fn verify_events_in_range(
+        &self,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+        tenant_id: Option<&str>,
+    ) -> Result<AuditVerificationReport> {
+        let mut report = AuditVerificationReport::new(start, end, tenant_id);
+        let signer = self.signer.read().unwrap().clone();
+
+        self.storage
+            .scan_events(start, end, tenant_id, &mut |entry| match entry {
+                StoredEntry::Event(event) => {
+                    report.events_checked += 1;
+                    let valid = event
+                        .signature
+                        .as_deref()
+                        .is_some_and(|signature| {
+                            signer
+                                .verify_with_key(event.key_id.as_deref(), &event.canonical_string(), signature)
+                                .unwrap_or(false)
+                        });
+                    if valid {
+                        report.valid_signatures += 1;
+                    } else {
+                        report.invalid_signatures += 1;
+                        report.first_invalid_event_id.get_or_insert(event.id);
+                    }
+                }
+                StoredEntry::Unreadable { reference } => {
+                    report.unreadable_entries += 1;
+                    report.first_unreadable_entry.get_or_insert(reference);
+                }
+            })
+            .await?;
+
+        if report.unreadable_entries > 0 {
+            report.chain_status = ChainStatus::Broken;
+        }
+        Ok(report)
+    }
+
     /// Add a new audit filter
     pub async fn add_filter(&self, filter: AuditFilter) {
         let mut filters = self.filters.write().await;
@@ -366,6 +528,128 @@ impl AuditLogger {
     }
 }
 
+/// Outcome of `AuditLogger::verify_events_in_range`
+/// Write signed events, recording the outcome in metrics
+async fn store_events(storage: &dyn AuditStorage, events: &[AuditEvent]) -> Result<()> {
+    let count = events.len() as u64;
+    match storage.store_events(events).await {
+        Ok(()) => {
+            crate::metrics::AUDIT_EVENTS_LOGGED.with_label_values(&["stored"]).inc_by(count);
+            for event in events {
+                info!(
+                    event_id = %event.id,
+                    event_type = ?event.event_type,
+                    actor = ?event.actor.user_id.as_deref().unwrap_or("unknown"),
+                    "Audit event logged successfully"
+                );
+            }
+            Ok(())
+        }
+        Err(e) => {
+            // Storages may have written part of a batch, but which part isn't known
+            crate::metrics::AUDIT_EVENTS_LOGGED.with_label_values(&["failed"]).inc_by(count);
+            for event in events {
+                error!(
+                    event_id = %event.id,
+                    error = %e,
+                    "Failed to store audit event"
+                );
+            }
+            Err(e)
+        }
+    }
+}
+
+/// Background writer for buffered logging. A batch is written once it holds
+/// `batch_size` events or its first event has waited `flush_interval`, and
+/// straight away once the queue is closed. Being the only writer, it stores
+/// events in the order they were logged. It exits once the queue is closed
+/// and empty.
+async fn flush_events(
+    storage: Arc<dyn AuditStorage>,
+    mut receiver: mpsc::Receiver<AuditEvent>,
+    batch_size: usize,
+    flush_interval: std::time::Duration,
+) {
+    let mut batch = Vec::with_capacity(batch_size);
+    while receiver.recv_many(&mut batch, batch_size).await > 0 {
+        let deadline = tokio::time::Instant::now() + flush_interval;
+        while batch.len() < batch_size {
+            let limit = batch_size - batch.len();
+            match tokio::time::timeout_at(deadline, receiver.recv_many(&mut batch, limit)).await {
+                Ok(0) | Err(_) => break,
+                Ok(_) => {}
+            }
+        }
+        // Already logged and counted as failed
+        let _ = store_events(storage.as_ref(), &batch).await;
+        batch.clear();
+    }
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct AuditVerificationReport {
+    pub start_time: DateTime<Utc>,
+    pub end_time: DateTime<Utc>,
+    pub tenant_id: Option<String>,
+    pub events_checked: u64,
+    pub valid_signatures: u64,
+    /// Events that are unsigned or whose signature doesn't match their contents
+    pub invalid_signatures: u64,
+    pub first_invalid_event_id: Option<Uuid>,
+    /// Entries storage holds but couldn't return as events
+    pub unreadable_entries: u64,
+    /// Event ID or file line of the first unreadable entry
+    pub first_unreadable_entry: Option<String>,
+    pub chain_status: ChainStatus,
+    pub verified_at: DateTime<Utc>,
+}
+
+/// Outcome of `AuditLogger::rotate_signing_key`
+#[derive(Debug, Clone, serde::Serialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct SigningKeyRotation {
+    /// ID of the key events are now signed with
+    pub key_id: String,
+    pub previous_key_id: String,
+    /// Keys kept to verify older events, most recently retired first
+    pub retired_key_ids: Vec<String>,
+}
+
+/// Whether every indexed or logged entry in the range could be read back
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum ChainStatus {
+    Intact,
+    /// Entries are missing or corrupted, so events may have been removed or altered
+    Broken,
+}
+
+impl AuditVerificationReport {
+    fn new(start: DateTime<Utc>, end: DateTime<Utc>, tenant_id: Option<&str>) -> Self {
+        Self {
+            start_time: start,
+            end_time: end,
+            tenant_id: tenant_id.map(str::to_string),
+            events_checked: 0,
+            valid_signatures: 0,
+            invalid_signatures: 0,
+            first_invalid_event_id: None,
+            unreadable_entries: 0,
+            first_unreadable_entry: None,
+            chain_status: ChainStatus::Intact,
+            verified_at: Utc::now(),
+        }
+    }
+
+    /// No tampered, unsigned or missing entries were found
+    pub fn is_valid(&self) -> bool {
+        self.invalid_signatures == 0 && self.chain_status == ChainStatus::Intact
+    }
+}
+
 #[derive(Debug, Clone, serde::Serialize)]
 pub struct AuditStatistics {
     pub total_events: u64,
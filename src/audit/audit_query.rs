@@ -0,0 +1,253 @@
+use crate::audit::audit_event::{AuditEvent, AuditEventType, AuditOutcome};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+use uuid::Uuid;
+
+/// Position in the audit log's order (timestamp, then ID), used to resume a
+/// query after the last event of the previous page. Rendered as
+/// `{seconds}.{nanoseconds}_{id}` for use in query strings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct AuditCursor {
+    pub timestamp: DateTime<Utc>,
+    pub id: Uuid,
+}
+
+impl AuditCursor {
+    pub fn of(event: &AuditEvent) -> Self {
+        Self {
+            timestamp: event.timestamp,
+            id: event.id,
+        }
+    }
+}
+
+impl fmt::Display for AuditCursor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}.{:09}_{}",
+            self.timestamp.timestamp(),
+            self.timestamp.timestamp_subsec_nanos(),
+            self.id.simple()
+        )
+    }
+}
+
+impl FromStr for AuditCursor {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let invalid = || anyhow::anyhow!("Invalid audit cursor: {}", value);
+        let (timestamp, id) = value.split_once('_').ok_or_else(invalid)?;
+        let (seconds, nanos) = timestamp.split_once('.').ok_or_else(invalid)?;
+        let timestamp = DateTime::from_timestamp(
+            seconds.parse().map_err(|_| invalid())?,
+            nanos.parse().map_err(|_| invalid())?,
+        )
+        .ok_or_else(invalid)?;
+        Ok(Self {
+            timestamp,
+            id: Uuid::parse_str(id).map_err(|_| invalid())?,
+        })
+    }
+}
+
+/// Criteria for `AuditStorage::query`. Empty lists and unset fields match
+/// everything; set criteria must all match.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditQuery {
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    /// Any of these event types
+    pub event_types: Vec<AuditEventType>,
+    /// Any of these outcomes
+    pub outcomes: Vec<AuditOutcome>,
+    /// Matches the actor's user ID or API key ID
+    pub actor_id: Option<String>,
+    pub tenant_id: Option<String>,
+    /// Case-insensitive substring of the event's action
+    pub action_contains: Option<String>,
+    /// Oldest matching events first, at most this many
+    pub limit: Option<usize>,
+    /// Only events after this position, to fetch the page following it
+    #[serde(default)]
+    pub after: Option<AuditCursor>,
+}
+
+impl AuditQuery {
+    pub fn new(start: DateTime<Utc>, end: DateTime<Utc>) -> Self {
+        Self {
+            start,
+            end,
+            event_types: Vec::new(),
+            outcomes: Vec::new(),
+            actor_id: None,
+            tenant_id: None,
+            action_contains: None,
+            limit: None,
+            after: None,
+        }
+    }
+
+    pub fn with_event_types(mut self, event_types: Vec<AuditEventType>) -> Self {
+        self.event_types = event_types;
+        self
+    }
+
+    pub fn with_outcomes(mut self, outcomes: Vec<AuditOutcome>) -> Self {
+        self.outcomes = outcomes;
+        self
+    }
+
+    pub fn with_actor_id(mut self, actor_id: String) -> Self {
+        self.actor_id = Some(actor_id);
+        self
+    }
+
+    pub fn with_tenant_id(mut self, tenant_id: String) -> Self {
+        self.tenant_id = Some(tenant_id);
+        self
+    }
+
+    pub fn with_action_contains(mut self, action: String) -> Self {
+        self.action_contains = Some(action);
+        self
+    }
+
+    pub fn with_limit(mut self, limit: usize) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    pub fn with_after(mut self, cursor: AuditCursor) -> Self {
+        self.after = Some(cursor);
+        self
+    }
+
+    /// Earliest timestamp a matching event can have
+    pub(crate) fn effective_start(&self) -> DateTime<Utc> {
+        match &self.after {
+            Some(cursor) => cursor.timestamp.max(self.start),
+            None => self.start,
+        }
+    }
+
+    /// Check if the event satisfies every criterion
+    pub fn matches(&self, event: &AuditEvent) -> bool {
+        if event.timestamp < self.start || event.timestamp > self.end {
+            return false;
+        }
+
+        if self.after.is_some_and(|cursor| AuditCursor::of(event) <= cursor) {
+            return false;
+        }
+
+        if !self.event_types.is_empty() && !self.event_types.contains(&event.event_type) {
+            return false;
+        }
+
+        if !self.outcomes.is_empty() && !self.outcomes.contains(&event.outcome) {
+            return false;
+        }
+
+        if let Some(actor_id) = &self.actor_id {
+            let matches_actor = event.actor.user_id.as_deref() == Some(actor_id.as_str())
+                || event.actor.api_key_id.as_deref() == Some(actor_id.as_str());
+            if !matches_actor {
+                return false;
+            }
+        }
+
+        if let Some(tenant_id) = &self.tenant_id {
+            if event.tenant_id.as_deref() != Some(tenant_id.as_str()) {
+                return false;
+            }
+        }
+
+        if let Some(action) = &self.action_contains {
+            if !event.action.to_lowercase().contains(&action.to_lowercase()) {
+                return false;
+            }
+        }
+
+        true
+    }
+
+    /// Whether `limit` matching events have been collected already
+    pub(crate) fn is_full(&self, collected: usize) -> bool {
+        self.limit.is_some_and(|limit| collected >= limit)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audit::audit_event::{ActorInfo, ResourceInfo};
+
+    fn event(event_type: AuditEventType, outcome: AuditOutcome, action: &str) -> AuditEvent {
+        AuditEvent::new(
+            event_type,
+            ActorInfo::new().with_api_key("key-1".to_string()),
+            ResourceInfo::new("authentication".to_string()),
+            action.to_string(),
+            outcome,
+        )
+        .with_tenant_id("tenant-x".to_string())
+    }
+
+    #[test]
+    fn test_all_criteria_must_match() {
+        let now = Utc::now();
+        let query = AuditQuery::new(now - chrono::Duration::days(7), now + chrono::Duration::minutes(1))
+            .with_event_types(vec![AuditEventType::Authentication])
+            .with_outcomes(vec![AuditOutcome::Failure])
+            .with_tenant_id("tenant-x".to_string())
+            .with_action_contains("LOGIN".to_string());
+
+        assert!(query.matches(&event(AuditEventType::Authentication, AuditOutcome::Failure, "api_key_login")));
+        assert!(!query.matches(&event(AuditEventType::Authentication, AuditOutcome::Success, "api_key_login")));
+        assert!(!query.matches(&event(AuditEventType::Authorization, AuditOutcome::Failure, "api_key_login")));
+        assert!(!query.matches(&event(AuditEventType::Authentication, AuditOutcome::Failure, "logout")));
+
+        let other_tenant = event(AuditEventType::Authentication, AuditOutcome::Failure, "login")
+            .with_tenant_id("tenant-y".to_string());
+        assert!(!query.matches(&other_tenant));
+
+        let mut last_month = event(AuditEventType::Authentication, AuditOutcome::Failure, "login");
+        last_month.timestamp = now - chrono::Duration::days(30);
+        assert!(!query.matches(&last_month));
+    }
+
+    #[test]
+    fn test_empty_query_matches_any_event_in_range() {
+        let now = Utc::now();
+        let query = AuditQuery::new(now - chrono::Duration::hours(1), now + chrono::Duration::minutes(1))
+            .with_actor_id("key-1".to_string());
+
+        assert!(query.matches(&event(AuditEventType::ApiRequest, AuditOutcome::Unknown, "check")));
+        assert!(!query
+            .clone()
+            .with_actor_id("key-2".to_string())
+            .matches(&event(AuditEventType::ApiRequest, AuditOutcome::Unknown, "check")));
+    }
+
+    #[test]
+    fn test_cursor_round_trips_and_excludes_earlier_events() {
+        let now = Utc::now();
+        let first = event(AuditEventType::ApiRequest, AuditOutcome::Success, "check");
+        let mut second = event(AuditEventType::ApiRequest, AuditOutcome::Success, "check");
+        second.timestamp = first.timestamp;
+
+        let cursor = AuditCursor::of(&first);
+        assert_eq!(cursor.to_string().parse::<AuditCursor>().unwrap(), cursor);
+        assert!("not-a-cursor".parse::<AuditCursor>().is_err());
+
+        let query = AuditQuery::new(now - chrono::Duration::hours(1), now + chrono::Duration::minutes(1))
+            .with_after(cursor);
+        assert!(!query.matches(&first));
+        // Events sharing the cursor's timestamp are ordered by ID
+        assert_eq!(query.matches(&second), second.id > first.id);
+    }
+}
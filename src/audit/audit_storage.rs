@@ -1,13 +1,20 @@
-use crate::audit::audit_event::AuditEvent;
+use crate::audit::audit_event::{AuditEvent, AuditEventType};
+use crate::audit::audit_query::{AuditCursor, AuditQuery};
+use crate::config::FileAuditConfig;
+use crate::redis_backend::{RedisConnection, RedisConnector};
 use anyhow::Result;
 use async_trait::async_trait;
 use chrono::{DateTime, Utc};
+use flate2::read::MultiGzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
 use redis::AsyncCommands;
 use serde_json;
-use std::fs::OpenOptions;
-use std::io::Write;
-use std::path::Path;
-use tokio::fs;
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufRead, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tokio::sync::mpsc;
 use uuid::Uuid;
 
 #[async_trait]
@@ -26,18 +33,71 @@ pub trait AuditStorage: Send + Sync {
         tenant_id: Option<&str>,
     ) -> Result<Vec<AuditEvent>>;
     async fn verify_integrity(&self) -> Result<bool>;
+
+    /// Store several events in order. Storages that can write them together
+    /// override this; an error may leave some of the events stored.
+    async fn store_events(&self, events: &[AuditEvent]) -> Result<()> {
+        for event in events {
+            self.store_event(event).await?;
+        }
+        Ok(())
+    }
+
+    /// Events matching every criterion in `query`, oldest first (ties broken
+    /// by ID, the order `AuditCursor` pages through)
+    async fn query(&self, query: &AuditQuery) -> Result<Vec<AuditEvent>> {
+        let mut events: Vec<AuditEvent> = self
+            .get_events_by_timerange(query.start, query.end, query.tenant_id.as_deref())
+            .await?
+            .into_iter()
+            .filter(|event| query.matches(event))
+            .collect();
+        events.sort_by_key(AuditCursor::of);
+        if let Some(limit) = query.limit {
+            events.truncate(limit);
+        }
+        Ok(events)
+    }
+
+    /// Visit every entry stored in the time range, in storage order, without
+    /// holding them all in memory. Storages that can read entries they can't
+    /// decode (or whose index points at a missing event) report them as
+    /// `StoredEntry::Unreadable` so integrity checks see the gap.
+    async fn scan_events(
+        &self,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+        tenant_id: Option<&str>,
+        visit: &mut (dyn FnMut(StoredEntry) + Send),
+    ) -> Result<()> {
+        for event in self.get_events_by_timerange(start, end, tenant_id).await? {
+            visit(StoredEntry::Event(event));
+        }
+        Ok(())
+    }
+}
+
+/// One entry seen by `AuditStorage::scan_events`
+#[derive(Debug)]
+pub enum StoredEntry {
+    Event(AuditEvent),
+    /// Present in storage but missing or undecodable; `reference` locates it
+    Unreadable { reference: String },
 }
 
+/// Events fetched per round trip when scanning Redis
+const SCAN_BATCH_SIZE: usize = 500;
+
 pub struct RedisAuditStorage {
-    client: redis::Client,
+    client: RedisConnector,
 }
 
 impl RedisAuditStorage {
-    pub fn new(client: redis::Client) -> Self {
+    pub fn new(client: RedisConnector) -> Self {
         Self { client }
     }
 
-    fn event_key(&self, event_id: &Uuid) -> String {
+    fn event_key(&self, event_id: &impl std::fmt::Display) -> String {
         format!("audit:event:{}", event_id)
     }
 
@@ -55,6 +115,103 @@ impl RedisAuditStorage {
     fn global_index_key(&self, date: &str) -> String {
         format!("audit:global:date:{}", date)
     }
+
+    fn type_index_key(&self, event_type: &AuditEventType, tenant_id: Option<&str>, date: &str) -> String {
+        match tenant_id {
+            Some(tid) => format!("audit:tenant:{}:type:{:?}:date:{}", tid, event_type, date),
+            None => format!("audit:type:{:?}:date:{}", event_type, date),
+        }
+    }
+
+    /// Indices whose union holds every event that can match `query` on `date`:
+    /// the per-type indices when types are given, else the tenant or global one
+    fn query_index_keys(&self, query: &AuditQuery, date: &str) -> Vec<String> {
+        let tenant_id = query.tenant_id.as_deref();
+        if query.event_types.is_empty() {
+            return vec![match tenant_id {
+                Some(tid) => self.tenant_index_key(tid, date),
+                None => self.global_index_key(date),
+            }];
+        }
+        query
+            .event_types
+            .iter()
+            .map(|event_type| self.type_index_key(event_type, tenant_id, date))
+            .collect()
+    }
+
+    /// Fetch events by ID in batches, keeping those that match `query`
+    async fn collect_matching(
+        &self,
+        conn: &mut RedisConnection,
+        event_ids: &[String],
+        query: &AuditQuery,
+        events: &mut Vec<AuditEvent>,
+    ) -> Result<()> {
+        for batch in event_ids.chunks(SCAN_BATCH_SIZE) {
+            let keys: Vec<String> = batch.iter().map(|id| self.event_key(id)).collect();
+            let values: Vec<Option<String>> = redis::cmd("MGET").arg(&keys).query_async(conn).await?;
+
+            events.extend(
+                values
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|json| serde_json::from_str::<AuditEvent>(&json).ok())
+                    .filter(|event| query.matches(event)),
+            );
+        }
+        Ok(())
+    }
+
+    /// Read the events in `index_key` within `query`'s range a batch at a
+    /// time, keeping those that match. With a limit, stops once the first
+    /// `limit` matches in log order are certain to have been read: the index
+    /// is ordered by second, so that's after the second holding the last of them.
+    async fn collect_index(
+        &self,
+        conn: &mut RedisConnection,
+        index_key: &str,
+        query: &AuditQuery,
+        events: &mut Vec<AuditEvent>,
+    ) -> Result<()> {
+        let (min, max) = (query.effective_start().timestamp(), query.end.timestamp());
+        let mut offset = 0;
+        let mut matched = 0;
+        let mut cutoff_second = None;
+
+        loop {
+            let batch: Vec<(String, f64)> = redis::cmd("ZRANGEBYSCORE")
+                .arg(index_key)
+                .arg(min)
+                .arg(max)
+                .arg("WITHSCORES")
+                .arg("LIMIT")
+                .arg(offset)
+                .arg(SCAN_BATCH_SIZE)
+                .query_async(conn)
+                .await?;
+            let Some(&(_, last_score)) = batch.last() else {
+                break;
+            };
+
+            let event_ids: Vec<String> = batch.iter().map(|(id, _)| id.clone()).collect();
+            let before = events.len();
+            self.collect_matching(conn, &event_ids, query, events).await?;
+            for event in &events[before..] {
+                matched += 1;
+                if cutoff_second.is_none() && query.is_full(matched) {
+                    cutoff_second = Some(event.timestamp.timestamp() as f64);
+                }
+            }
+
+            let past_cutoff = cutoff_second.is_some_and(|cutoff| last_score > cutoff);
+            if batch.len() < SCAN_BATCH_SIZE || past_cutoff {
+                break;
+            }
+            offset += batch.len();
+        }
+        Ok(())
+    }
 }
 
 #[async_trait]
@@ -72,6 +229,14 @@ impl AuditStorage for RedisAuditStorage {
         // Add to date-based indices
         let global_index = self.global_index_key(&date_str);
         conn.zadd::<_, _, _, ()>(&global_index, event.timestamp.timestamp(), &event.id.to_string()).await?;
+
+        // Per-type indices, so queries by event type don't scan every event of the day
+        let type_index = self.type_index_key(&event.event_type, None, &date_str);
+        conn.zadd::<_, _, _, ()>(&type_index, event.timestamp.timestamp(), &event.id.to_string()).await?;
+        if let Some(tenant_id) = &event.tenant_id {
+            let tenant_type_index = self.type_index_key(&event.event_type, Some(tenant_id), &date_str);
+            conn.zadd::<_, _, _, ()>(&tenant_type_index, event.timestamp.timestamp(), &event.id.to_string()).await?;
+        }
         
         // Add to tenant-specific index if tenant_id exists
         if let Some(tenant_id) = &event.tenant_id {
@@ -189,10 +354,145 @@ impl AuditStorage for RedisAuditStorage {
         let _: String = redis::cmd("PING").query_async(&mut conn).await?;
         Ok(true)
     }
+
+    async fn query(&self, query: &AuditQuery) -> Result<Vec<AuditEvent>> {
+        let mut conn = self.client.get_async_connection().await?;
+        let mut events = Vec::new();
+
+        // An actor's index spans all days, so it's the narrowest one when no type is given
+        if let (Some(actor_id), true) = (&query.actor_id, query.event_types.is_empty()) {
+            let actor_index = self.actor_index_key(actor_id, query.tenant_id.as_deref());
+            self.collect_index(&mut conn, &actor_index, query, &mut events).await?;
+        } else {
+            let mut current_date = query.effective_start().date_naive();
+            let end_date = query.end.date_naive();
+
+            while current_date <= end_date && !query.is_full(events.len()) {
+                let date_str = current_date.format("%Y-%m-%d").to_string();
+                for index_key in self.query_index_keys(query, &date_str) {
+                    self.collect_index(&mut conn, &index_key, query, &mut events).await?;
+                }
+
+                match current_date.succ_opt() {
+                    Some(next) => current_date = next,
+                    None => break,
+                }
+            }
+        }
+
+        events.sort_by_key(AuditCursor::of);
+        if let Some(limit) = query.limit {
+            events.truncate(limit);
+        }
+        Ok(events)
+    }
+
+    async fn scan_events(
+        &self,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+        tenant_id: Option<&str>,
+        visit: &mut (dyn FnMut(StoredEntry) + Send),
+    ) -> Result<()> {
+        let mut conn = self.client.get_async_connection().await?;
+        let mut current_date = start.date_naive();
+        let end_date = end.date_naive();
+
+        while current_date <= end_date {
+            let date_str = current_date.format("%Y-%m-%d").to_string();
+            let index_key = match tenant_id {
+                Some(tid) => self.tenant_index_key(tid, &date_str),
+                None => self.global_index_key(&date_str),
+            };
+
+            let event_ids: Vec<String> = conn
+                .zrangebyscore(&index_key, start.timestamp(), end.timestamp())
+                .await?;
+
+            for batch in event_ids.chunks(SCAN_BATCH_SIZE) {
+                let keys: Vec<String> = batch
+                    .iter()
+                    .map(|id| self.event_key(id))
+                    .collect();
+                let values: Vec<Option<String>> = redis::cmd("MGET")
+                    .arg(&keys)
+                    .query_async(&mut conn)
+                    .await?;
+
+                for (event_id, value) in batch.iter().zip(values) {
+                    match value.and_then(|json| serde_json::from_str::<AuditEvent>(&json).ok()) {
+                        Some(event) => visit(StoredEntry::Event(event)),
+                        None => visit(StoredEntry::Unreadable { reference: event_id.clone() }),
+                    }
+                }
+            }
+
+            match current_date.succ_opt() {
+                Some(next) => current_date = next,
+                None => break,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// When `FileAuditStorage` rolls the active file into a new segment
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileRotation {
+    /// Roll before an append would take the active file past this size
+    pub max_segment_bytes: u64,
+    /// Roll on the first append of a new UTC day
+    pub daily: bool,
+    /// Gzip rolled segments
+    pub compress: bool,
+}
+
+impl Default for FileRotation {
+    fn default() -> Self {
+        Self {
+            max_segment_bytes: 100 * 1024 * 1024,
+            daily: true,
+            compress: true,
+        }
+    }
+}
+
+impl FileRotation {
+    fn should_roll(&self, active: &std::fs::Metadata, incoming: usize) -> Result<bool> {
+        if active.len() == 0 {
+            return Ok(false);
+        }
+        if active.len() + incoming as u64 > self.max_segment_bytes {
+            return Ok(true);
+        }
+        if !self.daily {
+            return Ok(false);
+        }
+        let last_written: DateTime<Utc> = active.modified()?.into();
+        Ok(last_written.date_naive() != Utc::now().date_naive())
+    }
 }
 
+/// Timestamp in rolled segment names; fixed width, so names sort by age
+const SEGMENT_TIMESTAMP_FORMAT: &str = "%Y%m%dT%H%M%S%.6fZ";
+
+/// Lines buffered between the blocking file reader and the async caller
+const LINE_CHANNEL_CAPACITY: usize = 1024;
+
+/// Append-only JSON lines file, rolled into segments.
+///
+/// Events are appended to the active file at `file_path` and fsynced once
+/// per store, so a batch costs a single sync. When `FileRotation` says so, the active file is renamed to
+/// `<file_path>.<timestamp>` and, with `compress`, gzipped to
+/// `<file_path>.<timestamp>.gz`. Reads cover every segment, oldest first,
+/// then the active file.
 pub struct FileAuditStorage {
     file_path: String,
+    rotation: FileRotation,
+    /// Held while appending or rolling, and while readers open the active
+    /// file, so a roll can't happen between listing segments and opening it
+    write_lock: Arc<Mutex<()>>,
 }
 
 impl FileAuditStorage {
@@ -202,37 +502,287 @@ impl FileAuditStorage {
             std::fs::create_dir_all(parent)?;
         }
         
-        Ok(Self { file_path })
+        Ok(Self {
+            file_path,
+            rotation: FileRotation::default(),
+            write_lock: Arc::new(Mutex::new(())),
+        })
+    }
+
+    pub fn from_config(config: &FileAuditConfig) -> Result<Self> {
+        Ok(Self::new(config.path.clone())?.with_rotation(FileRotation {
+            max_segment_bytes: config.max_segment_bytes,
+            daily: config.rotate_daily,
+            compress: config.compress,
+        }))
+    }
+
+    pub fn with_rotation(mut self, rotation: FileRotation) -> Self {
+        self.rotation = rotation;
+        self
+    }
+
+    /// Every stored line, oldest segment first, read on a blocking thread.
+    ///
+    /// Dropping the receiver stops the reader.
+    fn lines(&self) -> mpsc::Receiver<Result<StoredLine>> {
+        let (tx, rx) = mpsc::channel(LINE_CHANNEL_CAPACITY);
+        let file_path = PathBuf::from(&self.file_path);
+        let write_lock = self.write_lock.clone();
+
+        tokio::task::spawn_blocking(move || {
+            if let Err(e) = read_lines(&file_path, &write_lock, &tx) {
+                let _ = tx.blocking_send(Err(e));
+            }
+        });
+        rx
+    }
+
+    /// Events for which `keep` is true, sorted by timestamp
+    async fn collect_events(&self, keep: impl Fn(&AuditEvent) -> bool) -> Result<Vec<AuditEvent>> {
+        let mut lines = self.lines();
+        let mut events = Vec::new();
+
+        while let Some(line) = lines.recv().await {
+            if let Ok(event) = serde_json::from_str::<AuditEvent>(&line?.content) {
+                if keep(&event) {
+                    events.push(event);
+                }
+            }
+        }
+
+        events.sort_by_key(AuditCursor::of);
+        Ok(events)
+    }
+}
+
+/// One line of the active file or a rolled segment
+struct StoredLine {
+    /// File name of the rolled segment, `None` for the active file
+    segment: Option<String>,
+    number: usize,
+    content: String,
+}
+
+impl StoredLine {
+    fn reference(&self) -> String {
+        match &self.segment {
+            Some(segment) => format!("{} line {}", segment, self.number),
+            None => format!("line {}", self.number),
+        }
+    }
+}
+
+fn read_lines(file_path: &Path, write_lock: &Mutex<()>, tx: &mpsc::Sender<Result<StoredLine>>) -> Result<()> {
+    let (segments, active) = {
+        let _guard = write_lock.lock().map_err(|_| anyhow::anyhow!("Audit file lock poisoned"))?;
+        let active = match File::open(file_path) {
+            Ok(file) => Some(file),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+            Err(e) => return Err(e.into()),
+        };
+        (list_segments(file_path)?, active)
+    };
+
+    let mut readers: Vec<(Option<String>, Box<dyn Read + Send>)> = Vec::new();
+    for segment in segments {
+        let (name, reader) = open_segment(&segment)?;
+        readers.push((Some(name), reader));
+    }
+    if let Some(active) = active {
+        readers.push((None, Box::new(active)));
     }
+
+    for (segment, reader) in readers {
+        for (index, content) in io::BufReader::new(reader).lines().enumerate() {
+            let line = StoredLine {
+                segment: segment.clone(),
+                number: index + 1,
+                content: content?,
+            };
+            if tx.blocking_send(Ok(line)).is_err() {
+                return Ok(());
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Open a rolled segment, following it to its `.gz` if it was compressed
+/// after being listed
+fn open_segment(segment: &Path) -> Result<(String, Box<dyn Read + Send>)> {
+    let (path, file) = match File::open(segment) {
+        Err(e) if e.kind() == io::ErrorKind::NotFound && !is_compressed(segment) => {
+            let compressed = compressed_path(segment);
+            let file = File::open(&compressed)?;
+            (compressed, file)
+        }
+        result => (segment.to_path_buf(), result?),
+    };
+
+    let name = path.file_name().unwrap_or_default().to_string_lossy().into_owned();
+    let reader: Box<dyn Read + Send> = if is_compressed(&path) {
+        Box::new(MultiGzDecoder::new(file))
+    } else {
+        Box::new(file)
+    };
+    Ok((name, reader))
+}
+
+/// Rolled segments of the active file at `file_path`, oldest first
+fn list_segments(file_path: &Path) -> Result<Vec<PathBuf>> {
+    let (Some(dir), Some(file_name)) = (segment_dir(file_path), file_path.file_name()) else {
+        return Ok(Vec::new());
+    };
+    let prefix = format!("{}.", file_name.to_string_lossy());
+
+    // A crash mid-compression can leave both forms; the .gz is complete once it exists
+    let mut segments: std::collections::BTreeMap<String, PathBuf> = Default::default();
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+        let Some(timestamp) = name.strip_prefix(&prefix) else {
+            continue;
+        };
+        let timestamp = timestamp.strip_suffix(".gz").unwrap_or(timestamp);
+        if chrono::NaiveDateTime::parse_from_str(timestamp, SEGMENT_TIMESTAMP_FORMAT).is_err() {
+            continue;
+        }
+
+        let path = entry.path();
+        let segment = segments.entry(timestamp.to_string()).or_insert_with(|| path.clone());
+        if is_compressed(&path) {
+            *segment = path;
+        }
+    }
+    Ok(segments.into_values().collect())
+}
+
+fn segment_dir(file_path: &Path) -> Option<&Path> {
+    match file_path.parent() {
+        Some(parent) if parent.as_os_str().is_empty() => Some(Path::new(".")),
+        parent => parent,
+    }
+}
+
+fn is_compressed(path: &Path) -> bool {
+    path.extension().is_some_and(|extension| extension == "gz")
+}
+
+fn compressed_path(segment: &Path) -> PathBuf {
+    let mut path = segment.as_os_str().to_owned();
+    path.push(".gz");
+    PathBuf::from(path)
+}
+
+/// Append lines to the active file, rolling it first whenever the next line
+/// needs it, and fsync once they're all written
+fn append_lines(file_path: &Path, rotation: FileRotation, lines: &[String]) -> Result<()> {
+    let open = || OpenOptions::new().create(true).read(true).append(true).open(file_path);
+
+    let mut file = open()?;
+    for (n, line) in lines.iter().enumerate() {
+        let metadata = file.metadata()?;
+        if rotation.should_roll(&metadata, line.len())? {
+            file.sync_all()?;
+            drop(file);
+            roll(file_path, rotation)?;
+            file = open()?;
+        } else if n == 0 && metadata.len() > 0 {
+            // A crash mid-append leaves a partial last line; end it so this
+            // record doesn't get glued onto it
+            let mut last = [0u8; 1];
+            file.seek(SeekFrom::End(-1))?;
+            file.read_exact(&mut last)?;
+            if last[0] != b'\n' {
+                file.write_all(b"\n")?;
+            }
+        }
+        file.write_all(line.as_bytes())?;
+    }
+
+    file.sync_all()?;
+    Ok(())
+}
+
+/// Move the active file to a new segment and compress any uncompressed segments
+fn roll(file_path: &Path, rotation: FileRotation) -> Result<()> {
+    let mut segment = file_path.as_os_str().to_owned();
+    segment.push(format!(".{}", Utc::now().format(SEGMENT_TIMESTAMP_FORMAT)));
+    let segment = PathBuf::from(segment);
+
+    std::fs::rename(file_path, &segment)?;
+    sync_dir(file_path)?;
+    tracing::info!(segment = %segment.display(), "Rolled audit log");
+
+    if rotation.compress {
+        // Includes segments left uncompressed by an earlier crash
+        for segment in list_segments(file_path)?.iter().filter(|segment| !is_compressed(segment)) {
+            compress_segment(segment)?;
+        }
+    }
+    Ok(())
+}
+
+/// Gzip a segment, replacing it only once the compressed copy is durable
+fn compress_segment(segment: &Path) -> Result<()> {
+    let compressed = compressed_path(segment);
+    if !compressed.exists() {
+        let mut partial = compressed.as_os_str().to_owned();
+        partial.push(".tmp");
+        let partial = PathBuf::from(partial);
+
+        let mut encoder = GzEncoder::new(File::create(&partial)?, Compression::default());
+        io::copy(&mut File::open(segment)?, &mut encoder)?;
+        encoder.finish()?.sync_all()?;
+        std::fs::rename(&partial, &compressed)?;
+        sync_dir(segment)?;
+    }
+    std::fs::remove_file(segment)?;
+    Ok(())
+}
+
+/// Make renames in the file's directory durable
+fn sync_dir(file_path: &Path) -> Result<()> {
+    // Directories can't be opened as files elsewhere
+    if cfg!(unix) {
+        if let Some(dir) = segment_dir(file_path) {
+            File::open(dir)?.sync_all()?;
+        }
+    }
+    Ok(())
 }
 
 #[async_trait]
 impl AuditStorage for FileAuditStorage {
     async fn store_event(&self, event: &AuditEvent) -> Result<()> {
-        let event_json = serde_json::to_string(event)?;
-        let log_line = format!("{}\n", event_json);
-        
+        self.store_events(std::slice::from_ref(event)).await
+    }
+
+    async fn store_events(&self, events: &[AuditEvent]) -> Result<()> {
+        let lines = events
+            .iter()
+            .map(|event| Ok(format!("{}\n", serde_json::to_string(event)?)))
+            .collect::<Result<Vec<String>>>()?;
+
         // Use blocking file operations in a spawn_blocking to avoid blocking the async runtime
-        let file_path = self.file_path.clone();
+        let file_path = PathBuf::from(&self.file_path);
+        let rotation = self.rotation;
+        let write_lock = self.write_lock.clone();
         tokio::task::spawn_blocking(move || {
-            let mut file = OpenOptions::new()
-                .create(true)
-                .append(true)
-                .open(&file_path)?;
-            file.write_all(log_line.as_bytes())?;
-            file.sync_all()?;
-            Ok::<(), anyhow::Error>(())
+            let _guard = write_lock.lock().map_err(|_| anyhow::anyhow!("Audit file lock poisoned"))?;
+            append_lines(&file_path, rotation, &lines)
         })
         .await??;
-        
+
         Ok(())
     }
 
     async fn get_event(&self, event_id: &Uuid) -> Result<Option<AuditEvent>> {
-        let content = fs::read_to_string(&self.file_path).await?;
-        
-        for line in content.lines() {
-            if let Ok(event) = serde_json::from_str::<AuditEvent>(line) {
+        let mut lines = self.lines();
+
+        while let Some(line) = lines.recv().await {
+            if let Ok(event) = serde_json::from_str::<AuditEvent>(&line?.content) {
                 if event.id == *event_id {
                     return Ok(Some(event));
                 }
@@ -248,25 +798,12 @@ impl AuditStorage for FileAuditStorage {
         end: DateTime<Utc>,
         tenant_id: Option<&str>,
     ) -> Result<Vec<AuditEvent>> {
-        let content = fs::read_to_string(&self.file_path).await?;
-        let mut events = Vec::new();
-        
-        for line in content.lines() {
-            if let Ok(event) = serde_json::from_str::<AuditEvent>(line) {
-                if event.timestamp >= start && event.timestamp <= end {
-                    if let Some(tid) = tenant_id {
-                        if event.tenant_id.as_deref() == Some(tid) {
-                            events.push(event);
-                        }
-                    } else {
-                        events.push(event);
-                    }
-                }
-            }
-        }
-        
-        events.sort_by(|a, b| a.timestamp.cmp(&b.timestamp));
-        Ok(events)
+        self.collect_events(|event| {
+            event.timestamp >= start
+                && event.timestamp <= end
+                && tenant_id.map_or(true, |tid| event.tenant_id.as_deref() == Some(tid))
+        })
+        .await
     }
 
     async fn get_events_by_actor(
@@ -274,44 +811,63 @@ impl AuditStorage for FileAuditStorage {
         actor_id: &str,
         tenant_id: Option<&str>,
     ) -> Result<Vec<AuditEvent>> {
-        let content = fs::read_to_string(&self.file_path).await?;
-        let mut events = Vec::new();
-        
-        for line in content.lines() {
-            if let Ok(event) = serde_json::from_str::<AuditEvent>(line) {
-                let matches_actor = event.actor.user_id.as_deref() == Some(actor_id)
-                    || event.actor.api_key_id.as_deref() == Some(actor_id);
-                
-                if matches_actor {
-                    if let Some(tid) = tenant_id {
-                        if event.tenant_id.as_deref() == Some(tid) {
-                            events.push(event);
-                        }
-                    } else {
-                        events.push(event);
-                    }
-                }
+        self.collect_events(|event| {
+            let matches_actor = event.actor.user_id.as_deref() == Some(actor_id)
+                || event.actor.api_key_id.as_deref() == Some(actor_id);
+            matches_actor && tenant_id.map_or(true, |tid| event.tenant_id.as_deref() == Some(tid))
+        })
+        .await
+    }
+
+    async fn verify_integrity(&self) -> Result<bool> {
+        // For file storage, verify that every segment is readable and contains valid JSON
+        let mut lines = self.lines();
+
+        while let Some(line) = lines.recv().await {
+            let line = line?;
+            if !line.content.trim().is_empty() {
+                serde_json::from_str::<AuditEvent>(&line.content)
+                    .map_err(|e| anyhow::anyhow!("Invalid audit entry at {}: {}", line.reference(), e))?;
             }
         }
         
-        events.sort_by(|a, b| a.timestamp.cmp(&b.timestamp));
-        Ok(events)
+        Ok(true)
     }
 
-    async fn verify_integrity(&self) -> Result<bool> {
-        // For file storage, verify by checking if the file is readable and contains valid JSON
-        if !Path::new(&self.file_path).exists() {
-            return Ok(true); // Empty file is valid
+    async fn query(&self, query: &AuditQuery) -> Result<Vec<AuditEvent>> {
+        let mut events = self.collect_events(|event| query.matches(event)).await?;
+        if let Some(limit) = query.limit {
+            events.truncate(limit);
         }
-        
-        let content = fs::read_to_string(&self.file_path).await?;
-        
-        for line in content.lines() {
-            if !line.trim().is_empty() {
-                serde_json::from_str::<AuditEvent>(line)?;
+        Ok(events)
+    }
+
+    async fn scan_events(
+        &self,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+        tenant_id: Option<&str>,
+        visit: &mut (dyn FnMut(StoredEntry) + Send),
+    ) -> Result<()> {
+        let mut lines = self.lines();
+
+        while let Some(line) = lines.recv().await {
+            let line = line?;
+            if line.content.trim().is_empty() {
+                continue;
+            }
+
+            // A line that doesn't parse can't be placed in the range, so it's always reported
+            let Ok(event) = serde_json::from_str::<AuditEvent>(&line.content) else {
+                visit(StoredEntry::Unreadable { reference: line.reference() });
+                continue;
+            };
+            let in_tenant = tenant_id.map_or(true, |tid| event.tenant_id.as_deref() == Some(tid));
+            if event.timestamp >= start && event.timestamp <= end && in_tenant {
+                visit(StoredEntry::Event(event));
             }
         }
-        
-        Ok(true)
+
+        Ok(())
     }
-}
\ No newline at end of file
+}
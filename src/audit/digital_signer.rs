@@ -1,48 +1,128 @@
 use anyhow::Result;
 use hmac::{Hmac, Mac};
-use sha2::Sha256;
+use sha2::{Digest, Sha256};
 use std::fmt;
 
 type HmacSha256 = Hmac<Sha256>;
 
+/// Signs with the current key and verifies with it or any retired one, so a
+/// key can be rotated without making the events it signed unverifiable.
+/// Keys are told apart by their key ID, a fingerprint of the key that's
+/// stamped on every event it signs.
 #[derive(Clone)]
 pub struct DigitalSigner {
+    current: SigningKey,
+    retired: Vec<SigningKey>,
+}
+
+#[derive(Clone)]
+struct SigningKey {
+    id: String,
     key: Vec<u8>,
 }
 
-impl DigitalSigner {
-    pub fn new(key: &str) -> Result<Self> {
+impl SigningKey {
+    fn new(key: &str) -> Result<Self> {
         if key.len() < 32 {
             return Err(anyhow::anyhow!(
                 "Signing key must be at least 32 characters long for security"
             ));
         }
-        
+
+        // The first 8 bytes of its SHA-256, enough to tell keys apart
+        let id = hex::encode(&Sha256::digest(key.as_bytes())[..8]);
         Ok(Self {
+            id,
             key: key.as_bytes().to_vec(),
         })
     }
 
-    /// Sign a message and return the signature as a hex string
-    pub fn sign(&self, message: &str) -> Result<String> {
+    fn sign(&self, message: &str) -> Result<String> {
         let mut mac = HmacSha256::new_from_slice(&self.key)
             .map_err(|e| anyhow::anyhow!("Failed to create HMAC: {}", e))?;
-        
+
         mac.update(message.as_bytes());
         let result = mac.finalize();
-        let signature = hex::encode(result.into_bytes());
-        
-        Ok(signature)
+        Ok(hex::encode(result.into_bytes()))
     }
 
-    /// Verify a signature against a message
-    pub fn verify(&self, message: &str, signature: &str) -> Result<bool> {
+    fn verify(&self, message: &str, signature: &str) -> Result<bool> {
         let expected_signature = self.sign(message)?;
         Ok(constant_time_eq::constant_time_eq(
             signature.as_bytes(),
             expected_signature.as_bytes(),
         ))
     }
+}
+
+impl DigitalSigner {
+    pub fn new(key: &str) -> Result<Self> {
+        Ok(Self {
+            current: SigningKey::new(key)?,
+            retired: Vec::new(),
+        })
+    }
+
+    /// Also verify events signed with these earlier keys
+    pub fn with_retired_keys<S: AsRef<str>>(mut self, keys: &[S]) -> Result<Self> {
+        for key in keys {
+            let key = SigningKey::new(key.as_ref())?;
+            if !self.knows(&key.id) {
+                self.retired.push(key);
+            }
+        }
+        Ok(self)
+    }
+
+    /// Sign with `key` from now on, keeping the current key for verification
+    pub fn rotate(&self, key: &str) -> Result<Self> {
+        let key = SigningKey::new(key)?;
+        let mut retired = vec![self.current.clone()];
+        retired.extend(self.retired.iter().cloned());
+        retired.retain(|retired| retired.id != key.id);
+        Ok(Self { current: key, retired })
+    }
+
+    /// ID of the key signatures are made with
+    pub fn key_id(&self) -> &str {
+        &self.current.id
+    }
+
+    /// IDs of the keys only kept for verification, most recently retired first
+    pub fn retired_key_ids(&self) -> Vec<String> {
+        self.retired.iter().map(|key| key.id.clone()).collect()
+    }
+
+    fn knows(&self, key_id: &str) -> bool {
+        self.current.id == key_id || self.retired.iter().any(|key| key.id == key_id)
+    }
+
+    /// Sign a message with the current key and return the signature as a hex string
+    pub fn sign(&self, message: &str) -> Result<String> {
+        self.current.sign(message)
+    }
+
+    /// Verify a signature against a message, made with any known key
+    pub fn verify(&self, message: &str, signature: &str) -> Result<bool> {
+        for key in std::iter::once(&self.current).chain(&self.retired) {
+            if key.verify(message, signature)? {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    /// Verify a signature made with the key `key_id`. Signatures from before
+    /// key IDs were recorded have none and are checked against every key.
+    pub fn verify_with_key(&self, key_id: Option<&str>, message: &str, signature: &str) -> Result<bool> {
+        let Some(key_id) = key_id else {
+            return self.verify(message, signature);
+        };
+        match std::iter::once(&self.current).chain(&self.retired).find(|key| key.id == key_id) {
+            Some(key) => key.verify(message, signature),
+            None => Ok(false),
+        }
+    }
 
     /// Generate a new random signing key (for key rotation)
     pub fn generate_key() -> String {
@@ -56,7 +136,8 @@ impl DigitalSigner {
 impl fmt::Debug for DigitalSigner {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("DigitalSigner")
-            .field("key", &"[REDACTED]")
+            .field("key_id", &self.current.id)
+            .field("retired_key_ids", &self.retired_key_ids())
             .finish()
     }
 }
@@ -92,4 +173,39 @@ mod tests {
         let signer = DigitalSigner::new(&key);
         assert!(signer.is_ok());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_rotated_keys_still_verify() {
+        let key_a = "key-a-that-is-long-enough-for-audit-signing";
+        let key_b = "key-b-that-is-long-enough-for-audit-signing";
+        let signer_a = DigitalSigner::new(key_a).unwrap();
+        let signed_by_a = signer_a.sign("event a").unwrap();
+
+        let signer_b = signer_a.rotate(key_b).unwrap();
+        let signed_by_b = signer_b.sign("event b").unwrap();
+        assert_ne!(signer_b.key_id(), signer_a.key_id());
+        assert_eq!(signer_b.retired_key_ids(), vec![signer_a.key_id().to_string()]);
+
+        let id_a = Some(signer_a.key_id());
+        let id_b = Some(signer_b.key_id());
+        assert!(signer_b.verify_with_key(id_a, "event a", &signed_by_a).unwrap());
+        assert!(signer_b.verify_with_key(id_b, "event b", &signed_by_b).unwrap());
+        // Only the named key counts
+        assert!(!signer_b.verify_with_key(id_b, "event a", &signed_by_a).unwrap());
+        assert!(!signer_b.verify_with_key(Some("unknown"), "event a", &signed_by_a).unwrap());
+        // Without a key ID any known key does
+        assert!(signer_b.verify_with_key(None, "event a", &signed_by_a).unwrap());
+        // The old signer never learns the new key
+        assert!(!signer_a.verify("event b", &signed_by_b).unwrap());
+
+        // Key IDs are stable, so a restarted instance recognises the same keys
+        let restarted = DigitalSigner::new(key_b).unwrap().with_retired_keys(&[key_a]).unwrap();
+        assert_eq!(restarted.key_id(), signer_b.key_id());
+        assert!(restarted.verify_with_key(id_a, "event a", &signed_by_a).unwrap());
+
+        // Rotating back to a retired key makes it current again
+        let signer_a_again = signer_b.rotate(key_a).unwrap();
+        assert_eq!(signer_a_again.key_id(), signer_a.key_id());
+        assert_eq!(signer_a_again.retired_key_ids(), vec![signer_b.key_id().to_string()]);
+    }
+}
@@ -3,6 +3,7 @@ pub mod audit_storage;
 pub mod digital_signer;
 pub mod audit_event;
 pub mod audit_filter;
+pub mod audit_query;
 pub mod middleware;
 pub mod api;
 
@@ -14,6 +15,7 @@ pub use audit_storage::{AuditStorage, RedisAuditStorage, FileAuditStorage};
 pub use digital_signer::DigitalSigner;
 pub use audit_event::{AuditEvent, AuditEventType, AuditOutcome, ActorInfo, ResourceInfo};
 pub use audit_filter::AuditFilter;
+pub use audit_query::{AuditCursor, AuditQuery};
 
 use anyhow::Result;
 use std::sync::Arc;
@@ -21,9 +23,11 @@ use std::sync::Arc;
 /// Initialize the audit system with the specified configuration
 pub async fn initialize_audit_system(
     storage_type: &str,
-    redis_client: Option<redis::Client>,
-    file_path: Option<String>,
+    redis_client: Option<crate::redis_backend::RedisConnector>,
+    file_config: Option<&crate::config::FileAuditConfig>,
+    buffer_config: Option<&crate::config::AuditBufferConfig>,
     signing_key: &str,
+    retired_signing_keys: &[String],
 ) -> Result<Arc<AuditLogger>> {
     let storage: Box<dyn AuditStorage> = match storage_type {
         "redis" => {
@@ -33,14 +37,17 @@ pub async fn initialize_audit_system(
             Box::new(RedisAuditStorage::new(client))
         }
         "file" => {
-            let path = file_path.unwrap_or_else(|| "/var/log/ratewatch/audit.log".to_string());
-            Box::new(FileAuditStorage::new(path)?)
+            let file_config = file_config.cloned().unwrap_or_default();
+            Box::new(FileAuditStorage::from_config(&file_config)?)
         }
         _ => return Err(anyhow::anyhow!("Unsupported audit storage type: {}", storage_type)),
     };
 
-    let signer = DigitalSigner::new(signing_key)?;
-    let audit_logger = AuditLogger::new(storage, signer, vec![]).await?;
-    
+    let signer = DigitalSigner::new(signing_key)?.with_retired_keys(retired_signing_keys)?;
+    let mut audit_logger = AuditLogger::new(storage, signer, vec![]).await?;
+    if let Some(buffer_config) = buffer_config {
+        audit_logger = audit_logger.with_buffer(buffer_config);
+    }
+
     Ok(Arc::new(audit_logger))
 }
\ No newline at end of file
@@ -3,7 +3,7 @@ use crate::audit::{
     audit_event::{ActorInfo, AuditEvent, AuditEventType, AuditOutcome, ResourceInfo},
     audit_filter::{AuditFilter, AuditFilterType},
     audit_logger::AuditLogger,
-    audit_storage::{AuditStorage, RedisAuditStorage},
+    audit_storage::{AuditStorage, FileAuditStorage, FileRotation, RedisAuditStorage, StoredEntry},
     digital_signer::DigitalSigner,
 };
 use chrono::{DateTime, Utc};
@@ -71,6 +71,53 @@ async fn test_audit_event_signing() {
     assert!(signer.verify(&canonical_string, &signed_event.signature.unwrap()).unwrap());
 }
 
+#[tokio::test]
+async fn test_file_log_verification_finds_tampering_and_corruption() {
+    let path = std::env::temp_dir().join(format!("ratewatch-audit-{}.log", Uuid::new_v4()));
+    let signer = DigitalSigner::new("test-key-for-audit-system-that-is-long-enough").unwrap();
+    let logger = AuditLogger::new(
+        Box::new(FileAuditStorage::new(path.to_string_lossy().into_owned()).unwrap()),
+        signer,
+        vec![],
+    )
+    .await
+    .unwrap();
+
+    let event = |action: &str| {
+        AuditEvent::new(
+            AuditEventType::AdminAction,
+            ActorInfo::new().with_api_key("admin".to_string()),
+            ResourceInfo::new("config".to_string()),
+            action.to_string(),
+            AuditOutcome::Success,
+        )
+    };
+    logger.log_event(event("reload_config")).await.unwrap();
+    logger.log_event(event("reload_config")).await.unwrap();
+
+    let (start, end) = (Utc::now() - chrono::Duration::hours(1), Utc::now() + chrono::Duration::hours(1));
+    let report = logger.verify_events_in_range(start, end, None).await.unwrap();
+    assert_eq!((report.events_checked, report.valid_signatures), (2, 2));
+    assert!(report.is_valid());
+
+    // An edited action keeps the old signature; a truncated line can't be read at all
+    let mut tampered = event("delete_user_data");
+    let other_signer = DigitalSigner::new("another-key-that-is-also-long-enough-to-use").unwrap();
+    tampered.signature = Some(other_signer.sign(&tampered.canonical_string()).unwrap());
+    let mut contents = std::fs::read_to_string(&path).unwrap();
+    contents.push_str(&format!("{}\n{{\"id\": \"trunc\n", serde_json::to_string(&tampered).unwrap()));
+    std::fs::write(&path, contents).unwrap();
+
+    let report = logger.verify_events_in_range(start, end, None).await.unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(report.events_checked, 3);
+    assert_eq!(report.invalid_signatures, 1);
+    assert_eq!(report.first_invalid_event_id, Some(tampered.id));
+    assert_eq!(report.first_unreadable_entry.as_deref(), Some("line 4"));
+    assert_eq!(report.chain_status, crate::audit::audit_logger::ChainStatus::Broken);
+}
+
 #[tokio::test]
 async fn test_audit_filters() {
     let health_filter = AuditFilter::health_check_filter();
@@ -196,6 +243,7 @@ mod integration_tests {
                 Some("tenant-123".to_string()),
                 Some("medium"),
                 Some("Rate limit exceeded for API key"),
+                None,
             )
             .await
             .unwrap();
@@ -275,4 +323,319 @@ mod integration_tests {
             Ok(true)
         }
     }
-}
\ No newline at end of file
+}
+#[tokio::test]
+async fn test_file_storage_reads_across_rolled_segments() {
+    let dir = std::env::temp_dir().join(format!("ratewatch-audit-rotation-{}", Uuid::new_v4()));
+    let path = dir.join("audit.log");
+    let storage = FileAuditStorage::new(path.to_string_lossy().into_owned())
+        .unwrap()
+        .with_rotation(FileRotation {
+            max_segment_bytes: 2048,
+            daily: true,
+            compress: true,
+        });
+
+    let mut ids = Vec::new();
+    for n in 0..20 {
+        let event = AuditEvent::new(
+            AuditEventType::ApiRequest,
+            ActorInfo::new().with_api_key("key-1".to_string()),
+            ResourceInfo::new("rate_limiter".to_string()),
+            format!("check_{n}"),
+            AuditOutcome::Success,
+        );
+        ids.push(event.id);
+        storage.store_event(&event).await.unwrap();
+    }
+
+    let files: Vec<String> = std::fs::read_dir(&dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+        .collect();
+    let compressed = files.iter().filter(|name| name.ends_with(".gz")).count();
+    assert!(compressed >= 2, "expected several rolled segments, found {files:?}");
+    // Only the active file and compressed segments are left
+    assert_eq!(files.len(), compressed + 1);
+    assert!(std::fs::metadata(&path).unwrap().len() <= 2048);
+
+    let (start, end) = (Utc::now() - chrono::Duration::hours(1), Utc::now() + chrono::Duration::hours(1));
+    let events = storage.get_events_by_timerange(start, end, None).await.unwrap();
+    assert_eq!(events.iter().map(|event| event.id).collect::<Vec<_>>(), ids);
+
+    // The first event is in the oldest compressed segment, the last in the active file
+    assert_eq!(storage.get_event(&ids[0]).await.unwrap().unwrap().action, "check_0");
+    assert_eq!(storage.get_event(&ids[19]).await.unwrap().unwrap().action, "check_19");
+    assert_eq!(storage.get_events_by_actor("key-1", None).await.unwrap().len(), 20);
+    assert!(storage.verify_integrity().await.unwrap());
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test]
+async fn test_file_storage_rolls_daily_and_isolates_torn_records() {
+    let dir = std::env::temp_dir().join(format!("ratewatch-audit-daily-{}", Uuid::new_v4()));
+    let path = dir.join("audit.log");
+    let storage = FileAuditStorage::new(path.to_string_lossy().into_owned()).unwrap();
+    let event = || {
+        AuditEvent::new(
+            AuditEventType::SystemEvent,
+            ActorInfo::new(),
+            ResourceInfo::new("config".to_string()),
+            "reload".to_string(),
+            AuditOutcome::Success,
+        )
+    };
+
+    storage.store_event(&event()).await.unwrap();
+    // A crash mid-append, then the file sits untouched until the next day
+    let mut file = std::fs::OpenOptions::new().append(true).open(&path).unwrap();
+    std::io::Write::write_all(&mut file, b"{\"id\": \"torn").unwrap();
+    file.set_modified(std::time::SystemTime::now() - std::time::Duration::from_secs(86400 + 60))
+        .unwrap();
+    drop(file);
+
+    storage.store_event(&event()).await.unwrap();
+    storage.store_event(&event()).await.unwrap();
+
+    let segments: Vec<String> = std::fs::read_dir(&dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+        .filter(|name| name.ends_with(".gz"))
+        .collect();
+    assert_eq!(segments.len(), 1);
+
+    let mut events = 0;
+    let mut unreadable = Vec::new();
+    let (start, end) = (Utc::now() - chrono::Duration::hours(1), Utc::now() + chrono::Duration::hours(1));
+    storage
+        .scan_events(start, end, None, &mut |entry| match entry {
+            StoredEntry::Event(_) => events += 1,
+            StoredEntry::Unreadable { reference } => unreadable.push(reference),
+        })
+        .await
+        .unwrap();
+    assert_eq!(events, 3);
+    assert_eq!(unreadable, vec![format!("{} line 2", segments[0])]);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+/// Takes a while per write and remembers how events were batched
+struct SlowAuditStorage {
+    batches: std::sync::Mutex<Vec<Vec<AuditEvent>>>,
+}
+
+impl SlowAuditStorage {
+    fn stored(&self) -> Vec<AuditEvent> {
+        self.batches.lock().unwrap().iter().flatten().cloned().collect()
+    }
+}
+
+#[async_trait::async_trait]
+impl AuditStorage for std::sync::Arc<SlowAuditStorage> {
+    async fn store_event(&self, event: &AuditEvent) -> anyhow::Result<()> {
+        self.store_events(std::slice::from_ref(event)).await
+    }
+
+    async fn store_events(&self, events: &[AuditEvent]) -> anyhow::Result<()> {
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        self.batches.lock().unwrap().push(events.to_vec());
+        Ok(())
+    }
+
+    async fn get_event(&self, _event_id: &Uuid) -> anyhow::Result<Option<AuditEvent>> {
+        Ok(None)
+    }
+
+    async fn get_events_by_timerange(
+        &self,
+        _start: DateTime<Utc>,
+        _end: DateTime<Utc>,
+        _tenant_id: Option<&str>,
+    ) -> anyhow::Result<Vec<AuditEvent>> {
+        Ok(self.stored())
+    }
+
+    async fn get_events_by_actor(&self, _actor_id: &str, _tenant_id: Option<&str>) -> anyhow::Result<Vec<AuditEvent>> {
+        Ok(self.stored())
+    }
+
+    async fn verify_integrity(&self) -> anyhow::Result<bool> {
+        Ok(true)
+    }
+}
+
+async fn buffered_logger(config: crate::config::AuditBufferConfig) -> (AuditLogger, std::sync::Arc<SlowAuditStorage>) {
+    let storage = std::sync::Arc::new(SlowAuditStorage {
+        batches: std::sync::Mutex::new(Vec::new()),
+    });
+    let signer = DigitalSigner::new("test-key-for-audit-system-that-is-long-enough").unwrap();
+    let logger = AuditLogger::new(Box::new(storage.clone()), signer, vec![])
+        .await
+        .unwrap()
+        .with_buffer(&config);
+    (logger, storage)
+}
+
+fn admin_event(n: usize) -> AuditEvent {
+    AuditEvent::new(
+        AuditEventType::AdminAction,
+        ActorInfo::new().with_api_key("admin".to_string()),
+        ResourceInfo::new("config".to_string()),
+        format!("reload_config_{n}"),
+        AuditOutcome::Success,
+    )
+}
+
+#[tokio::test]
+async fn test_buffered_logging_flushes_pending_events_on_shutdown() {
+    let (logger, storage) = buffered_logger(crate::config::AuditBufferConfig {
+        enabled: true,
+        capacity: 100,
+        batch_size: 10,
+        flush_interval_ms: 100,
+        backpressure: crate::config::AuditBackpressure::Block,
+    })
+    .await;
+
+    let events: Vec<AuditEvent> = (0..25).map(admin_event).collect();
+    let ids: Vec<Uuid> = events.iter().map(|event| event.id).collect();
+    for event in events {
+        logger.log_event(event).await.unwrap();
+    }
+    // Logging returned long before the slow storage could have written it all
+    assert!(storage.stored().len() < 25);
+
+    logger.shutdown().await;
+    let stored = storage.stored();
+    assert_eq!(stored.iter().map(|event| event.id).collect::<Vec<_>>(), ids);
+    assert!(storage.batches.lock().unwrap().iter().all(|batch| batch.len() <= 10));
+
+    // Signed when logged, not when written
+    let (start, end) = (Utc::now() - chrono::Duration::hours(1), Utc::now() + chrono::Duration::hours(1));
+    let report = logger.verify_events_in_range(start, end, None).await.unwrap();
+    assert_eq!(report.valid_signatures, 25);
+
+    // After shutdown events are stored directly
+    logger.log_event(admin_event(25)).await.unwrap();
+    assert_eq!(storage.stored().len(), 26);
+}
+
+#[tokio::test]
+async fn test_buffered_logging_writes_after_flush_interval() {
+    let (logger, storage) = buffered_logger(crate::config::AuditBufferConfig {
+        enabled: true,
+        capacity: 100,
+        batch_size: 100,
+        flush_interval_ms: 300,
+        backpressure: crate::config::AuditBackpressure::Block,
+    })
+    .await;
+
+    for n in 0..5 {
+        logger.log_event(admin_event(n)).await.unwrap();
+    }
+    // Short of a full batch, so they wait for the interval
+    tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+    assert!(storage.stored().is_empty());
+
+    tokio::time::sleep(std::time::Duration::from_millis(500)).await;
+    assert_eq!(storage.stored().len(), 5);
+    assert_eq!(storage.batches.lock().unwrap().len(), 1);
+    logger.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_buffered_logging_shutdown_writes_partial_batch() {
+    let (logger, storage) = buffered_logger(crate::config::AuditBufferConfig {
+        enabled: true,
+        capacity: 100,
+        batch_size: 100,
+        flush_interval_ms: 60_000,
+        backpressure: crate::config::AuditBackpressure::Block,
+    })
+    .await;
+
+    for n in 0..3 {
+        logger.log_event(admin_event(n)).await.unwrap();
+    }
+    assert!(storage.stored().is_empty());
+
+    // Shutdown doesn't sit out the interval
+    let started = std::time::Instant::now();
+    logger.shutdown().await;
+    assert!(started.elapsed() < std::time::Duration::from_secs(5));
+    assert_eq!(storage.stored().len(), 3);
+}
+
+#[tokio::test]
+async fn test_buffered_logging_drops_when_full() {
+    let (logger, storage) = buffered_logger(crate::config::AuditBufferConfig {
+        enabled: true,
+        capacity: 2,
+        batch_size: 2,
+        flush_interval_ms: 100,
+        backpressure: crate::config::AuditBackpressure::Drop,
+    })
+    .await;
+
+    let dropped = crate::metrics::AUDIT_EVENTS_LOGGED.with_label_values(&["dropped"]).get();
+    for n in 0..20 {
+        logger.log_event(admin_event(n)).await.unwrap();
+    }
+    logger.shutdown().await;
+
+    let stored = storage.stored().len() as u64;
+    assert!(stored > 0 && stored < 20, "stored {stored} of 20");
+    assert!(crate::metrics::AUDIT_EVENTS_LOGGED.with_label_values(&["dropped"]).get() - dropped >= 20 - stored);
+}
+
+#[tokio::test]
+async fn test_redis_query_pages_through_large_range_without_gaps() {
+    let client = crate::redis_backend::RedisConnector::open("redis://127.0.0.1:6379").unwrap();
+    let storage = RedisAuditStorage::new(client);
+    let tenant_id = format!("paging-{}", Uuid::new_v4());
+
+    // More events than one Redis batch, several to a second
+    let base = Utc::now() - chrono::Duration::minutes(30);
+    let events: Vec<AuditEvent> = (0..1200)
+        .map(|n| {
+            let mut event = AuditEvent::new(
+                AuditEventType::ApiRequest,
+                ActorInfo::new().with_api_key("key-1".to_string()),
+                ResourceInfo::new("rate_limiter".to_string()),
+                format!("check_{n}"),
+                AuditOutcome::Success,
+            )
+            .with_tenant_id(tenant_id.clone());
+            event.timestamp = base + chrono::Duration::milliseconds(n * 150);
+            event
+        })
+        .collect();
+    storage.store_events(&events).await.unwrap();
+
+    let (start, end) = (base - chrono::Duration::minutes(1), Utc::now());
+    let mut seen: Vec<Uuid> = Vec::new();
+    let mut cursor = None;
+    loop {
+        let mut query = AuditQuery::new(start, end).with_tenant_id(tenant_id.clone()).with_limit(250);
+        if let Some(cursor) = cursor {
+            query = query.with_after(cursor);
+        }
+        let page = storage.query(&query).await.unwrap();
+        seen.extend(page.iter().map(|event| event.id));
+        match page.last() {
+            Some(last) if page.len() == 250 => cursor = Some(AuditCursor::of(last)),
+            _ => break,
+        }
+    }
+
+    assert_eq!(seen.len(), 1200);
+    let unique: std::collections::HashSet<_> = seen.iter().collect();
+    assert_eq!(unique.len(), 1200, "no event on two pages");
+    // Pages come back in log order, so together they're the sorted events
+    let mut ordered = events;
+    ordered.sort_by_key(AuditCursor::of);
+    assert_eq!(seen, ordered.iter().map(|event| event.id).collect::<Vec<_>>());
+}
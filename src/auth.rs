@@ -1,19 +1,121 @@
 use axum::{
+    body::Body,
     extract::{Request, State},
     http::{HeaderMap, StatusCode},
     middleware::Next,
-    response::Response,
+    response::{IntoResponse, Response},
 };
 use blake3::Hasher;
 use std::sync::Arc;
 
+use crate::api_error::ApiError;
+use crate::key_access::{KeyAccess, KeyAccessList};
+use crate::rate_limiter::DenyReason;
+use crate::request_signing::RequestVerifier;
+use crate::tenant::api_keys::TenantKeyResolver;
+
 pub struct ApiKeyValidator {
     secret: String,
+    admin_key_hashes: Vec<String>,
+    key_access: Option<Arc<KeyAccessList>>,
+    request_verifier: Option<Arc<RequestVerifier>>,
+    tenant_keys: Option<Arc<TenantKeyResolver>>,
+}
+
+/// Identity of the caller, attached to requests that passed authentication
+#[derive(Debug, Clone)]
+pub struct ApiKeyIdentity {
+    /// Short prefix of the key hash, safe to log and audit
+    pub key_id: String,
+    /// Set when the key was issued to a tenant
+    pub tenant_id: Option<uuid::Uuid>,
+}
+
+impl ApiKeyIdentity {
+    /// What the caller's requests are rate limited and counted under: the
+    /// key they asked about, inside their tenant's namespace if they have one
+    pub fn scoped_key(&self, key: &str) -> String {
+        match self.tenant_id {
+            Some(tenant_id) => format!("tenant:{}:{}", tenant_id, key),
+            None => key.to_string(),
+        }
+    }
 }
 
 impl ApiKeyValidator {
     pub fn new(secret: String) -> Self {
-        Self { secret }
+        Self {
+            secret,
+            admin_key_hashes: Vec::new(),
+            key_access: None,
+            request_verifier: None,
+            tenant_keys: None,
+        }
+    }
+
+    /// Tag requests made with tenant-issued keys with their tenant, and
+    /// refuse revoked ones
+    pub fn with_tenant_keys(mut self, tenant_keys: Arc<TenantKeyResolver>) -> Self {
+        self.tenant_keys = Some(tenant_keys);
+        self
+    }
+
+    /// Require HMAC-signed requests on the authenticated HTTP routes
+    pub fn with_request_verifier(mut self, verifier: Arc<RequestVerifier>) -> Self {
+        self.request_verifier = Some(verifier);
+        self
+    }
+
+    /// Consult operator allow/deny lists after a key validates
+    pub fn with_key_access(mut self, key_access: Arc<KeyAccessList>) -> Self {
+        self.key_access = Some(key_access);
+        self
+    }
+
+    pub fn key_access_list(&self) -> Option<&Arc<KeyAccessList>> {
+        self.key_access.as_ref()
+    }
+
+    /// Where the key stands on the allow/deny lists, `Default` without them
+    pub async fn key_access(&self, identity: &ApiKeyIdentity) -> KeyAccess {
+        match &self.key_access {
+            Some(list) => list.access(&identity.key_id).await,
+            None => KeyAccess::Default,
+        }
+    }
+
+    /// Keys allowed to call admin-scoped routes. With none configured every
+    /// admin route is refused.
+    pub fn with_admin_keys<I, K>(mut self, keys: I) -> Self
+    where
+        I: IntoIterator<Item = K>,
+        K: AsRef<str>,
+    {
+        self.admin_key_hashes = keys
+            .into_iter()
+            .map(|key| key.as_ref().trim().to_string())
+            .filter(|key| !key.is_empty())
+            .map(|key| self.hash_api_key(&key))
+            .collect();
+        self
+    }
+
+    /// Valid key that is also configured as an admin key
+    pub fn is_admin_key(&self, api_key: &str) -> bool {
+        if !self.validate_key(api_key) {
+            return false;
+        }
+        let hash = self.hash_api_key(api_key);
+        self.admin_key_hashes
+            .iter()
+            .fold(false, |found, admin| found | constant_time_eq::constant_time_eq(admin.as_bytes(), hash.as_bytes()))
+    }
+
+    pub fn identity(&self, api_key: &str) -> ApiKeyIdentity {
+        ApiKeyIdentity {
+            key_id: self.hash_api_key(api_key)[..16].to_string(),
+            tenant_id: None,
+        }
     }
 
     /// Validate API key using secure Blake3 hashing with constant-time comparison
@@ -54,21 +156,83 @@ impl ApiKeyValidator {
         hex::encode(hasher.finalize().as_bytes())
     }
 
-    /// Generate a new API key (for admin use)
-    #[allow(dead_code)]
+    /// HMAC key the holder of `api_key` signs requests with. Derived with
+    /// its own context so it can't be computed from a key ID.
+    pub fn signing_secret(&self, api_key: &str) -> String {
+        let mut hasher = Hasher::new();
+        hasher.update(b"ratewatch request signing\0");
+        hasher.update(api_key.as_bytes());
+        hasher.update(self.secret.as_bytes());
+        hex::encode(hasher.finalize().as_bytes())
+    }
+
+    /// Generate a new API key
     pub fn generate_api_key() -> String {
-        use std::time::{SystemTime, UNIX_EPOCH};
+        format!("rw_{}", hex::encode(rand::random::<[u8; 24]>()))
+    }
+}
 
-        let timestamp = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_nanos();
+fn bearer_token(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get("authorization")
+        .and_then(|h| h.to_str().ok())
+        .and_then(|h| h.strip_prefix("Bearer "))
+}
 
-        let mut hasher = Hasher::new();
-        hasher.update(&timestamp.to_le_bytes());
-        hasher.update(b"ratewatch_api_key");
+/// Attach the caller's identity and list status, or refuse a denylisted key
+async fn admit(validator: &ApiKeyValidator, api_key: &str, request: &mut Request) -> Result<(), Response> {
+    let mut identity = validator.identity(api_key);
+    if let Some(tenant_keys) = &validator.tenant_keys {
+        match tenant_keys.binding(&identity.key_id).await {
+            Ok(Some(binding)) if binding.revoked_at.is_some() => {
+                tracing::warn!(key_id = %identity.key_id, tenant_id = %binding.tenant_id, "Revoked tenant key refused");
+                return Err(ApiError::unauthorized("API key has been revoked").into_response());
+            }
+            Ok(binding) => identity.tenant_id = binding.map(|binding| binding.tenant_id),
+            Err(e) => {
+                tracing::error!("Failed to look up tenant for key {}: {}", identity.key_id, e);
+                return Err(ApiError::unavailable("Tenant key lookup failed").into_response());
+            }
+        }
+    }
+    let access = validator.key_access(&identity).await;
 
-        format!("rw_{}", hex::encode(&hasher.finalize().as_bytes()[..24]))
+    if access == KeyAccess::Denied {
+        if let Some(list) = validator.key_access_list() {
+            list.record_denial(&identity, request.uri().path()).await;
+        }
+        return Err(DenyReason::KeyDenied.response(StatusCode::FORBIDDEN));
+    }
+
+    crate::request_context::record_identity(request, &identity);
+    request.extensions_mut().insert(identity);
+    request.extensions_mut().insert(access);
+    Ok(())
+}
+
+/// Check the request's signature when signing is required. The body is
+/// buffered to do so; the body limit layer in front has already capped it.
+async fn verify_signature(validator: &ApiKeyValidator, api_key: &str, request: Request) -> Result<Request, Response> {
+    let Some(verifier) = &validator.request_verifier else {
+        return Ok(request);
+    };
+
+    let (parts, body) = request.into_parts();
+    let body = axum::body::to_bytes(body, usize::MAX)
+        .await
+        .map_err(|_| ApiError::payload_too_large("Request body too large").into_response())?;
+    let path = parts.uri.path_and_query().map_or(parts.uri.path(), |path| path.as_str());
+    let key_id = validator.identity(api_key).key_id;
+
+    match verifier
+        .verify(&validator.signing_secret(api_key), &key_id, &parts.method, path, &parts.headers, &body)
+        .await
+    {
+        Ok(()) => Ok(Request::from_parts(parts, Body::from(body))),
+        Err(e) => {
+            tracing::warn!(key_id = %key_id, reason = e.code(), "Request signature refused");
+            Err(e.into_response())
+        }
     }
 }
 
@@ -78,24 +242,46 @@ pub async fn auth_middleware(
     headers: HeaderMap,
     request: Request,
     next: Next,
-) -> Result<Response, StatusCode> {
-    let auth_header = headers
-        .get("authorization")
-        .and_then(|h| h.to_str().ok())
-        .unwrap_or("");
-
-    let api_key = if let Some(key) = auth_header.strip_prefix("Bearer ") {
+) -> Result<Response, Response> {
+    let api_key = if let Some(key) = bearer_token(&headers) {
         key
     } else {
         tracing::warn!("Missing or invalid Authorization header format");
-        return Err(StatusCode::UNAUTHORIZED);
+        return Err(ApiError::unauthorized("Missing or malformed Authorization header").into_response());
     };
 
     if validator.validate_key(api_key) {
+        let mut request = verify_signature(&validator, api_key, request).await?;
+        admit(&validator, api_key, &mut request).await?;
         Ok(next.run(request).await)
     } else {
         tracing::warn!("API key validation failed");
-        Err(StatusCode::UNAUTHORIZED)
+        Err(ApiError::unauthorized("Invalid API key").into_response())
+    }
+}
+
+/// Like `auth_middleware`, but only admits configured admin keys (403 otherwise)
+pub async fn admin_auth_middleware(
+    State(validator): State<Arc<ApiKeyValidator>>,
+    headers: HeaderMap,
+    request: Request,
+    next: Next,
+) -> Result<Response, Response> {
+    let api_key = match bearer_token(&headers) {
+        Some(key) if validator.validate_key(key) => key,
+        _ => {
+            tracing::warn!("Admin request without a valid API key");
+            return Err(ApiError::unauthorized("Missing or invalid API key").into_response());
+        }
+    };
+
+    if validator.is_admin_key(api_key) {
+        let mut request = verify_signature(&validator, api_key, request).await?;
+        admit(&validator, api_key, &mut request).await?;
+        Ok(next.run(request).await)
+    } else {
+        tracing::warn!("Non-admin API key refused on admin route");
+        Err(ApiError::forbidden("Admin API key required").into_response())
     }
 }
 
@@ -139,6 +325,46 @@ mod tests {
         assert!(hash1.chars().all(|c| c.is_ascii_hexdigit()));
     }
 
+    #[test]
+    fn test_admin_keys() {
+        let admin_key = "rw_admin0000000000000000000000000000";
+        let validator = ApiKeyValidator::new("test_secret".to_string())
+            .with_admin_keys(format!("{admin_key}, ").split(','));
+
+        assert!(validator.is_admin_key(admin_key));
+        assert!(!validator.is_admin_key("rw_1234567890abcdef1234567890abcdef"));
+        assert!(!ApiKeyValidator::new("test_secret".to_string()).is_admin_key(admin_key));
+        assert_eq!(validator.identity(admin_key).key_id.len(), 16);
+    }
+
+    #[tokio::test]
+    async fn test_denylisted_key_is_refused() {
+        use axum::{body::Body, routing::get, Router};
+        use tower::ServiceExt;
+
+        let denied_key = "rw_denied000000000000000000000000000";
+        let validator = ApiKeyValidator::new("test_secret".to_string());
+        let denied_id = validator.identity(denied_key).key_id;
+        let validator = Arc::new(validator.with_key_access(Arc::new(KeyAccessList::with_lists(&[], &[&denied_id]))));
+
+        let app = Router::new()
+            .route("/", get(|| async { "ok" }))
+            .layer(axum::middleware::from_fn_with_state(validator, auth_middleware));
+        let request = |key: &str| {
+            axum::http::Request::builder()
+                .uri("/")
+                .header("authorization", format!("Bearer {key}"))
+                .body(Body::empty())
+                .unwrap()
+        };
+
+        let response = app.clone().oneshot(request(denied_key)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        assert_eq!(response.headers()[crate::rate_limiter::DENY_REASON_HEADER], "key_denied");
+        let response = app.oneshot(request("rw_1234567890abcdef1234567890abcdef")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
     #[test]
     fn test_api_key_generation() {
         let key1 = ApiKeyValidator::generate_api_key();
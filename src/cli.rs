@@ -0,0 +1,133 @@
+//! Subcommands that run instead of the server.
+
+use ratewatch::config::{ConfigCheckError, ConfigManager};
+
+const VALIDATE_CONFIG_USAGE: &str = "usage: ratewatch validate-config [--file <path>] [--skip-secrets]";
+
+/// `validate-config` exit codes, so CI can tell failures apart
+pub const EXIT_INVALID: i32 = 1;
+pub const EXIT_PARSE_ERROR: i32 = 2;
+pub const EXIT_NOT_FOUND: i32 = 3;
+pub const EXIT_SECRETS: i32 = 4;
+pub const EXIT_USAGE: i32 = 64;
+
+/// `ratewatch validate-config [--file config.toml] [--skip-secrets]`
+///
+/// Returns the exit code and the report to print.
+pub async fn validate_config(args: &[String]) -> (i32, String) {
+    let mut file = "config.toml".to_string();
+    let mut resolve_secrets = true;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--file" | "-f" => match args.next() {
+                Some(path) => file = path.clone(),
+                None => return (EXIT_USAGE, format!("--file needs a path\n{VALIDATE_CONFIG_USAGE}")),
+            },
+            "--skip-secrets" => resolve_secrets = false,
+            other => return (EXIT_USAGE, format!("unknown argument `{other}`\n{VALIDATE_CONFIG_USAGE}")),
+        }
+    }
+
+    match ConfigManager::check_file(&file, resolve_secrets).await {
+        Ok(_) => (0, format!("{file}: configuration is valid")),
+        Err(e) => {
+            let code = match e {
+                ConfigCheckError::NotFound(_) => EXIT_NOT_FOUND,
+                ConfigCheckError::Parse(_) => EXIT_PARSE_ERROR,
+                ConfigCheckError::Secrets(_) => EXIT_SECRETS,
+                ConfigCheckError::Invalid(_) => EXIT_INVALID,
+            };
+            (code, format!("{file}: {e}"))
+        }
+    }
+}
+
+/// `ratewatch signing-secret`: read an API key from stdin and print the
+/// secret its holder signs requests with. Uses this server's `API_KEY_SECRET`.
+pub fn print_signing_secret() -> anyhow::Result<()> {
+    let secret = std::env::var("API_KEY_SECRET").map_err(|_| anyhow::anyhow!("API_KEY_SECRET must be set"))?;
+    let mut api_key = String::new();
+    std::io::stdin().read_line(&mut api_key)?;
+
+    let validator = ratewatch::auth::ApiKeyValidator::new(secret);
+    let api_key = api_key.trim();
+    if !validator.validate_key(api_key) {
+        anyhow::bail!("not a valid API key");
+    }
+    println!("{}", validator.signing_secret(api_key));
+    Ok(())
+}
+
+/// `ratewatch --print-config-schema`: the JSON Schema for `config.toml`
+#[cfg(feature = "config-schema")]
+pub fn print_config_schema() -> anyhow::Result<()> {
+    println!("{}", serde_json::to_string_pretty(&ratewatch::config::EnterpriseConfig::json_schema())?);
+    Ok(())
+}
+
+#[cfg(not(feature = "config-schema"))]
+pub fn print_config_schema() -> anyhow::Result<()> {
+    anyhow::bail!("ratewatch was built without the `config-schema` feature")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SHIPPED_CONFIG: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/config.toml");
+
+    /// Write `contents` to a fresh temp `.toml` file and return its path
+    fn temp_config(contents: &str) -> String {
+        let path = std::env::temp_dir().join(format!("ratewatch-config-{}.toml", uuid::Uuid::new_v4()));
+        std::fs::write(&path, contents).unwrap();
+        path.to_string_lossy().to_string()
+    }
+
+    async fn run(args: &[&str]) -> (i32, String) {
+        let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+        validate_config(&args).await
+    }
+
+    #[tokio::test]
+    async fn test_shipped_config_is_valid() {
+        let (code, report) = run(&["--file", SHIPPED_CONFIG, "--skip-secrets"]).await;
+        assert_eq!(code, 0, "{report}");
+        assert!(report.ends_with("configuration is valid"));
+    }
+
+    #[tokio::test]
+    async fn test_out_of_range_value_is_a_validation_error() {
+        let shipped = std::fs::read_to_string(SHIPPED_CONFIG).unwrap();
+        let path = temp_config(&shipped.replacen("port = 8081", "port = 80", 1));
+
+        let (code, report) = run(&["--file", &path, "--skip-secrets"]).await;
+        let _ = std::fs::remove_file(&path);
+
+        assert_eq!(code, EXIT_INVALID, "{report}");
+        assert!(report.contains("validation error"));
+        assert!(report.contains("server.port: range"), "{report}");
+    }
+
+    #[tokio::test]
+    async fn test_malformed_toml_is_a_parse_error() {
+        let path = temp_config("[server\nport = ");
+
+        let (code, report) = run(&["--file", &path, "--skip-secrets"]).await;
+        let _ = std::fs::remove_file(&path);
+
+        assert_eq!(code, EXIT_PARSE_ERROR, "{report}");
+        assert!(report.contains("parse error"));
+    }
+
+    #[tokio::test]
+    async fn test_missing_file_and_bad_arguments() {
+        let (code, report) = run(&["--file", "/nonexistent/ratewatch.toml"]).await;
+        assert_eq!(code, EXIT_NOT_FOUND);
+        assert!(report.contains("file not found"));
+
+        let (code, _) = run(&["--bogus"]).await;
+        assert_eq!(code, EXIT_USAGE);
+    }
+}
@@ -0,0 +1,283 @@
+use axum::{
+    extract::State,
+    response::{IntoResponse, Json, Response},
+    routing::post,
+    Extension, Router,
+};
+use serde::Serialize;
+use serde_json::json;
+use std::sync::Arc;
+
+use super::ConfigManager;
+use crate::api_error::ApiError;
+use crate::audit::{audit_event::{ActorInfo, AuditOutcome}, AuditLogger};
+use crate::auth::ApiKeyIdentity;
+
+pub struct ConfigApiState {
+    pub config_manager: Arc<ConfigManager>,
+    pub audit: Arc<AuditLogger>,
+}
+
+#[derive(Debug, Serialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct ConfigReloadResponse {
+    pub applied: bool,
+    /// Dotted config keys whose values changed
+    pub changed_keys: Vec<String>,
+}
+
+/// Admin routes for runtime configuration; mount behind `admin_auth_middleware`
+pub fn create_config_router(config_manager: Arc<ConfigManager>, audit: Arc<AuditLogger>) -> Router {
+    let router = Router::new().route("/v1/config/reload", post(reload_config));
+    #[cfg(feature = "config-schema")]
+    let router = router.route("/v1/config/schema", axum::routing::get(get_config_schema));
+
+    router.with_state(Arc::new(ConfigApiState { config_manager, audit }))
+}
+
+#[cfg(feature = "config-schema")]
+#[cfg_attr(
+    feature = "openapi",
+    utoipa::path(
+        get,
+        path = "/v1/config/schema",
+        tag = "admin",
+        responses(
+            (status = 200, description = "JSON Schema for the configuration file", body = Object),
+            (status = 401, description = "Missing or invalid API key"),
+            (status = 403, description = "API key is not an admin key"),
+        ),
+        security(("api_key" = [])),
+    )
+)]
+pub(crate) async fn get_config_schema() -> Json<serde_json::Value> {
+    Json(super::EnterpriseConfig::json_schema())
+}
+
+#[cfg_attr(
+    feature = "openapi",
+    utoipa::path(
+        post,
+        path = "/v1/config/reload",
+        tag = "admin",
+        responses(
+            (status = 200, description = "New configuration applied", body = ConfigReloadResponse),
+            (status = 422, description = "New configuration rejected; running config unchanged", body = crate::api_error::ProblemDetails),
+            (status = 401, description = "Missing or invalid API key"),
+            (status = 403, description = "API key is not an admin key"),
+        ),
+        security(("api_key" = [])),
+    )
+)]
+async fn reload_config(
+    State(state): State<Arc<ConfigApiState>>,
+    Extension(identity): Extension<ApiKeyIdentity>,
+) -> Response {
+    let actor = ActorInfo::new().with_api_key(identity.key_id);
+    let result = state.config_manager.reload_config().await;
+
+    let (outcome, details) = match &result {
+        Ok(changed_keys) => (AuditOutcome::Success, json!({ "changed_keys": changed_keys })),
+        Err(e) => (AuditOutcome::Failure, json!({ "error": format!("{e:#}") })),
+    };
+    if let Err(e) = state
+        .audit
+        .log_admin_action(actor, "reload_config", "config", None, outcome, None, Some(details))
+        .await
+    {
+        tracing::error!("Failed to audit config reload: {}", e);
+    }
+
+    match result {
+        Ok(changed_keys) => Json(ConfigReloadResponse {
+            applied: true,
+            changed_keys,
+        })
+        .into_response(),
+        Err(e) => {
+            tracing::warn!("Config reload rejected: {:#}", e);
+            ApiError::unprocessable("INVALID_CONFIG", format!("{e:#}")).into_response()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::{admin_auth_middleware, ApiKeyValidator};
+    use crate::config::{ConfigChange, ConfigMap, ConfigSource, EnterpriseConfig};
+    use async_trait::async_trait;
+    use axum::{body::Body, http::{Request, StatusCode}, middleware};
+    use tokio::sync::{mpsc, Mutex};
+    use tower::ServiceExt;
+
+    const ADMIN_KEY: &str = "rw_admin0000000000000000000000000000";
+    const USER_KEY: &str = "rw_1234567890abcdef1234567890abcdef";
+
+    /// Source whose values the test edits between reloads
+    struct EditableSource(Arc<Mutex<ConfigMap>>);
+
+    #[async_trait]
+    impl ConfigSource for EditableSource {
+        async fn load_config(&self) -> anyhow::Result<ConfigMap> {
+            Ok(self.0.lock().await.clone())
+        }
+
+        async fn watch_changes(&self) -> anyhow::Result<mpsc::Receiver<ConfigChange>> {
+            Ok(mpsc::channel(1).1)
+        }
+
+        fn name(&self) -> &str {
+            "test"
+        }
+    }
+
+    async fn setup() -> (Router, Arc<ConfigManager>, Arc<Mutex<ConfigMap>>, String) {
+        let defaults = EnterpriseConfig::default();
+        let mut values = serde_json::to_value(&defaults).unwrap();
+        // Secrets serialize as `***`; put the real default back so the config loads
+        values["redis"]["url"] = defaults.redis.url.expose_secret().into();
+        let values = Arc::new(Mutex::new(
+            values
+                .as_object()
+                .unwrap()
+                .clone()
+                .into_iter()
+                .collect::<ConfigMap>(),
+        ));
+        let config_manager = Arc::new(
+            ConfigManager::with_sources(vec![Box::new(EditableSource(values.clone()))])
+                .await
+                .unwrap(),
+        );
+
+        let audit_path = std::env::temp_dir()
+            .join(format!("ratewatch-config-api-{}.log", uuid::Uuid::new_v4()))
+            .to_string_lossy()
+            .to_string();
+        let audit = crate::audit::initialize_audit_system(
+            "file",
+            None,
+            Some(&crate::config::FileAuditConfig {
+                path: audit_path.clone(),
+                ..Default::default()
+            }),
+            None,
+            "test-audit-signing-key-that-is-at-least-32-chars",
+            &[],
+        )
+        .await
+        .unwrap();
+
+        let validator = Arc::new(ApiKeyValidator::new("test_secret".to_string()).with_admin_keys([ADMIN_KEY]));
+        let router = create_config_router(config_manager.clone(), audit)
+            .layer(middleware::from_fn_with_state(validator, admin_auth_middleware));
+
+        (router, config_manager, values, audit_path)
+    }
+
+    #[cfg(feature = "config-schema")]
+    #[tokio::test]
+    async fn test_schema_endpoint() {
+        let (router, _, _, audit_path) = setup().await;
+
+        let response = router
+            .oneshot(
+                Request::builder()
+                    .uri("/v1/config/schema")
+                    .header("authorization", format!("Bearer {ADMIN_KEY}"))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(body_json(response).await["title"], "EnterpriseConfig");
+        let _ = std::fs::remove_file(audit_path);
+    }
+
+    fn reload(api_key: &str) -> Request<Body> {
+        Request::builder()
+            .method("POST")
+            .uri("/v1/config/reload")
+            .header("authorization", format!("Bearer {api_key}"))
+            .body(Body::empty())
+            .unwrap()
+    }
+
+    async fn body_json(response: Response) -> serde_json::Value {
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        serde_json::from_slice(&bytes).unwrap()
+    }
+
+    fn set_port(values: &mut ConfigMap, port: u16) {
+        values.get_mut("server").unwrap()["port"] = json!(port);
+    }
+
+    #[tokio::test]
+    async fn test_reload_applies_and_reports_changed_keys() {
+        let (router, config_manager, values, audit_path) = setup().await;
+        set_port(&mut *values.lock().await, 9090);
+
+        let response = router.oneshot(reload(ADMIN_KEY)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body = body_json(response).await;
+        assert_eq!(body["applied"], true);
+        assert_eq!(body["changed_keys"], json!(["server.port"]));
+        assert_eq!(config_manager.get_config().await.server.port, 9090);
+
+        let audit_log = std::fs::read_to_string(&audit_path).unwrap();
+        assert!(audit_log.contains("reload_config"));
+        let key_id = ApiKeyValidator::new("test_secret".to_string()).identity(ADMIN_KEY).key_id;
+        assert!(audit_log.contains(&key_id));
+        let _ = std::fs::remove_file(audit_path);
+    }
+
+    #[tokio::test]
+    async fn test_invalid_reload_returns_422_and_keeps_config() {
+        let (router, config_manager, values, audit_path) = setup().await;
+        // Below the validated 1024..=65535 range
+        set_port(&mut *values.lock().await, 80);
+
+        let response = router.oneshot(reload(ADMIN_KEY)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+        let body = body_json(response).await;
+        assert_eq!(body["code"], "INVALID_CONFIG");
+        assert!(body["detail"].as_str().unwrap().contains("validation"));
+        assert_eq!(config_manager.get_config().await.server.port, 8081);
+        let _ = std::fs::remove_file(audit_path);
+    }
+
+    #[tokio::test]
+    async fn test_reload_rejects_cross_field_violation() {
+        let (router, config_manager, values, audit_path) = setup().await;
+        // Each field is in range on its own, but min exceeds max
+        values.lock().await.get_mut("infrastructure").unwrap()["auto_scaling"]["min_instances"] = json!(50);
+
+        let response = router.oneshot(reload(ADMIN_KEY)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        assert!(body_json(response).await["detail"]
+            .as_str()
+            .unwrap()
+            .contains("infrastructure.auto_scaling.min_instances (50)"));
+        assert_eq!(config_manager.get_config().await.infrastructure.auto_scaling.min_instances, 1);
+        let _ = std::fs::remove_file(audit_path);
+    }
+
+    #[tokio::test]
+    async fn test_reload_requires_admin_key() {
+        let (router, _, _, audit_path) = setup().await;
+
+        let response = router.clone().oneshot(reload(USER_KEY)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+        let response = router
+            .oneshot(Request::builder().method("POST").uri("/v1/config/reload").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        let _ = std::fs::remove_file(audit_path);
+    }
+}
@@ -0,0 +1,186 @@
+//! ConfigMaps and Secrets read and watched through the Kubernetes API, for
+//! pods that don't mount them as volumes. `K8sConfigSource` talks to the
+//! cluster through `K8sObjectApi`; the `kubernetes` feature provides the
+//! `kube` implementation.
+
+use anyhow::Result;
+use async_trait::async_trait;
+use std::collections::BTreeMap;
+use std::fmt;
+use tokio::sync::mpsc;
+
+/// The kinds of object `K8sConfigSource` reads keys from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum K8sObjectKind {
+    ConfigMap,
+    Secret,
+}
+
+impl fmt::Display for K8sObjectKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            K8sObjectKind::ConfigMap => write!(f, "ConfigMap"),
+            K8sObjectKind::Secret => write!(f, "Secret"),
+        }
+    }
+}
+
+/// The service account isn't allowed `verb` on the object. RBAC problems
+/// are reported with this error so callers can tell them from outages.
+#[derive(Debug, Clone)]
+pub struct K8sForbidden {
+    pub kind: K8sObjectKind,
+    pub namespace: String,
+    pub name: String,
+    pub verb: &'static str,
+}
+
+impl fmt::Display for K8sForbidden {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "RBAC denies '{}' on {} {}/{}; grant the service account a Role allowing it",
+            self.verb, self.kind, self.namespace, self.name
+        )
+    }
+}
+
+impl std::error::Error for K8sForbidden {}
+
+/// Access to ConfigMaps and Secrets through the Kubernetes API
+#[async_trait]
+pub trait K8sObjectApi: Send + Sync {
+    /// The object's keys and values, Secret data decoded; `None` if it doesn't exist
+    async fn read(&self, kind: K8sObjectKind, namespace: &str, name: &str) -> Result<Option<BTreeMap<String, String>>>;
+
+    /// One message each time the object is modified, created or deleted,
+    /// until the receiver is dropped
+    async fn watch(&self, kind: K8sObjectKind, namespace: &str, name: &str) -> Result<mpsc::Receiver<()>>;
+}
+
+#[cfg(feature = "kubernetes")]
+pub use kube_api::KubeObjectApi;
+
+#[cfg(feature = "kubernetes")]
+mod kube_api {
+    use super::{K8sForbidden, K8sObjectApi, K8sObjectKind};
+    use anyhow::{Context, Result};
+    use async_trait::async_trait;
+    use futures_util::{StreamExt, TryStreamExt};
+    use k8s_openapi::api::core::v1::{ConfigMap, Secret};
+    use kube::runtime::{watcher, WatchStreamExt};
+    use kube::{Api, Client};
+    use std::collections::BTreeMap;
+    use tokio::sync::mpsc;
+
+    /// `K8sObjectApi` over the pod's in-cluster (or kubeconfig) client
+    pub struct KubeObjectApi {
+        client: Client,
+    }
+
+    impl KubeObjectApi {
+        pub async fn try_default() -> Result<Self> {
+            let client = Client::try_default()
+                .await
+                .context("Failed to create Kubernetes API client")?;
+            Ok(Self { client })
+        }
+
+        fn classify(error: kube::Error, kind: K8sObjectKind, namespace: &str, name: &str, verb: &'static str) -> anyhow::Error {
+            match &error {
+                kube::Error::Api(response) if response.code == 403 => K8sForbidden {
+                    kind,
+                    namespace: namespace.to_string(),
+                    name: name.to_string(),
+                    verb,
+                }
+                .into(),
+                _ => anyhow::Error::new(error).context(format!("Failed to {} {} {}/{}", verb, kind, namespace, name)),
+            }
+        }
+    }
+
+    fn is_forbidden(error: &watcher::Error) -> bool {
+        match error {
+            watcher::Error::InitialListFailed(kube::Error::Api(response))
+            | watcher::Error::WatchStartFailed(kube::Error::Api(response))
+            | watcher::Error::WatchFailed(kube::Error::Api(response))
+            | watcher::Error::WatchError(response) => response.code == 403,
+            _ => false,
+        }
+    }
+
+    #[async_trait]
+    impl K8sObjectApi for KubeObjectApi {
+        async fn read(&self, kind: K8sObjectKind, namespace: &str, name: &str) -> Result<Option<BTreeMap<String, String>>> {
+            match kind {
+                K8sObjectKind::ConfigMap => {
+                    let api: Api<ConfigMap> = Api::namespaced(self.client.clone(), namespace);
+                    let configmap = api
+                        .get_opt(name)
+                        .await
+                        .map_err(|e| Self::classify(e, kind, namespace, name, "get"))?;
+                    Ok(configmap.map(|configmap| configmap.data.unwrap_or_default()))
+                }
+                K8sObjectKind::Secret => {
+                    let api: Api<Secret> = Api::namespaced(self.client.clone(), namespace);
+                    let secret = api
+                        .get_opt(name)
+                        .await
+                        .map_err(|e| Self::classify(e, kind, namespace, name, "get"))?;
+                    Ok(secret.map(|secret| {
+                        secret
+                            .data
+                            .unwrap_or_default()
+                            .into_iter()
+                            .map(|(key, value)| (key, String::from_utf8_lossy(&value.0).into_owned()))
+                            .collect()
+                    }))
+                }
+            }
+        }
+
+        async fn watch(&self, kind: K8sObjectKind, namespace: &str, name: &str) -> Result<mpsc::Receiver<()>> {
+            let (tx, rx) = mpsc::channel(16);
+            let config = watcher::Config::default().fields(&format!("metadata.name={}", name));
+            let (namespace, name) = (namespace.to_string(), name.to_string());
+
+            // Events only say that the object changed; the data is re-read on reload
+            let mut events = match kind {
+                K8sObjectKind::ConfigMap => {
+                    let api: Api<ConfigMap> = Api::namespaced(self.client.clone(), &namespace);
+                    watcher(api, config).default_backoff().map_ok(|_| ()).boxed()
+                }
+                K8sObjectKind::Secret => {
+                    let api: Api<Secret> = Api::namespaced(self.client.clone(), &namespace);
+                    watcher(api, config).default_backoff().map_ok(|_| ()).boxed()
+                }
+            };
+
+            tokio::spawn(async move {
+                // The first event is the initial listing, not a change
+                let mut listed = false;
+                loop {
+                    match events.try_next().await {
+                        Ok(Some(())) if !listed => listed = true,
+                        Ok(Some(())) => {
+                            if tx.send(()).await.is_err() {
+                                return;
+                            }
+                        }
+                        Ok(None) => return,
+                        Err(e) if is_forbidden(&e) => {
+                            let forbidden = K8sForbidden { kind, namespace, name, verb: "watch" };
+                            tracing::error!("Stopped watching for config changes: {}", forbidden);
+                            return;
+                        }
+                        // The backoff retries; the stream carries on after it
+                        Err(e) => tracing::warn!("Kubernetes watch on {} {}/{} failed: {}", kind, namespace, name, e),
+                    }
+                }
+            });
+
+            Ok(rx)
+        }
+    }
+}
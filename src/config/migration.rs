@@ -0,0 +1,124 @@
+//! Upgrade older config layouts to the current `schema_version`.
+//!
+//! Files without a `schema_version` predate versioning and are treated as
+//! version 1. Each migration rewrites the nested document one version forward,
+//! so a file from any supported version is brought up to date before it is
+//! parsed. Versions newer than this build are rejected outright.
+
+use anyhow::{anyhow, Result};
+use serde_json::Value;
+
+/// Layout version this build reads and writes
+pub const CONFIG_SCHEMA_VERSION: u32 = 2;
+
+const SCHEMA_VERSION_KEY: &str = "schema_version";
+
+/// Files written before `schema_version` existed
+const UNVERSIONED: u32 = 1;
+
+struct Migration {
+    /// Upgrades `from` to `from + 1`
+    from: u32,
+    description: &'static str,
+    apply: fn(&mut Value),
+}
+
+const MIGRATIONS: &[Migration] = &[Migration {
+    from: 1,
+    description: "renamed observability.tracing.jaeger_endpoint to otlp_endpoint",
+    apply: rename_tracing_endpoint,
+}];
+
+pub(crate) fn current_schema_version() -> u32 {
+    CONFIG_SCHEMA_VERSION
+}
+
+/// Bring a nested config document up to `CONFIG_SCHEMA_VERSION` in place.
+///
+/// Returns the version the document started at.
+pub fn migrate(config: &mut Value) -> Result<u32> {
+    let version = match config.get(SCHEMA_VERSION_KEY) {
+        None | Some(Value::Null) => UNVERSIONED,
+        Some(value) => value
+            .as_u64()
+            .and_then(|v| u32::try_from(v).ok())
+            .filter(|v| *v >= 1)
+            .ok_or_else(|| anyhow!("schema_version must be a positive integer, got {}", value))?,
+    };
+
+    if version > CONFIG_SCHEMA_VERSION {
+        return Err(anyhow!(
+            "Config schema_version {} is newer than this build supports ({}); upgrade RateWatch or use an older config",
+            version,
+            CONFIG_SCHEMA_VERSION
+        ));
+    }
+
+    for migration in MIGRATIONS.iter().filter(|m| m.from >= version) {
+        (migration.apply)(config);
+        tracing::info!(
+            "Migrated config from schema_version {} to {}: {}",
+            migration.from,
+            migration.from + 1,
+            migration.description
+        );
+    }
+
+    if let Value::Object(map) = config {
+        map.insert(SCHEMA_VERSION_KEY.to_string(), CONFIG_SCHEMA_VERSION.into());
+    }
+    Ok(version)
+}
+
+fn rename_tracing_endpoint(config: &mut Value) {
+    rename(config, &["observability", "tracing"], "jaeger_endpoint", "otlp_endpoint");
+}
+
+/// Move `section.from` to `section.to`; a value already under the new name wins
+fn rename(config: &mut Value, section: &[&str], from: &str, to: &str) {
+    let Some(Value::Object(map)) = section.iter().try_fold(config, |value, key| value.get_mut(*key)) else {
+        return;
+    };
+    if let Some(value) = map.remove(from) {
+        map.entry(to.to_string()).or_insert(value);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_unversioned_document_is_migrated() {
+        let mut config = json!({
+            "observability": { "tracing": { "enabled": true, "jaeger_endpoint": "http://collector:4317" } }
+        });
+
+        assert_eq!(migrate(&mut config).unwrap(), 1);
+        assert_eq!(config["schema_version"], json!(CONFIG_SCHEMA_VERSION));
+        assert_eq!(config["observability"]["tracing"]["otlp_endpoint"], json!("http://collector:4317"));
+        assert!(config["observability"]["tracing"].get("jaeger_endpoint").is_none());
+    }
+
+    #[test]
+    fn test_current_version_is_untouched() {
+        let mut config = json!({
+            "schema_version": CONFIG_SCHEMA_VERSION,
+            "observability": { "tracing": { "otlp_endpoint": "http://collector:4317" } }
+        });
+        let before = config.clone();
+
+        assert_eq!(migrate(&mut config).unwrap(), CONFIG_SCHEMA_VERSION);
+        assert_eq!(config, before);
+    }
+
+    #[test]
+    fn test_newer_or_malformed_versions_are_rejected() {
+        let err = migrate(&mut json!({ "schema_version": CONFIG_SCHEMA_VERSION + 1 })).unwrap_err();
+        assert!(err.to_string().contains("newer than this build supports"), "{err}");
+
+        assert!(migrate(&mut json!({ "schema_version": 0 })).is_err());
+        assert!(migrate(&mut json!({ "schema_version": "2" })).is_err());
+    }
+}
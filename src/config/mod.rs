@@ -7,6 +7,9 @@ use tokio::sync::RwLock;
 use uuid::Uuid;
 use validator::Validate;
 
+pub mod api;
+pub mod k8s_api;
+pub mod migration;
 pub mod sources;
 pub mod secrets;
 pub mod validation;
@@ -17,7 +20,7 @@ use validation::ConfigValidator;
 
 /// Enterprise configuration manager with multiple sources and hot-reloading
 pub struct ConfigManager {
-    sources: Vec<Box<dyn ConfigSource>>,
+    sources: Arc<Vec<Box<dyn ConfigSource>>>,
     secret_manager: Arc<SecretManager>,
     validator: ConfigValidator,
     current_config: Arc<RwLock<EnterpriseConfig>>,
@@ -36,10 +39,19 @@ impl ConfigManager {
             sources.push(Box::new(VaultConfigSource::new().await?));
         }
 
+        if std::env::var("S3_CONFIG_BUCKET").is_ok() {
+            sources.push(Box::new(S3ConfigSource::new().await?));
+        }
+
         if std::env::var("KUBERNETES_SERVICE_HOST").is_ok() {
             sources.push(Box::new(K8sConfigSource::new().await?));
         }
 
+        Self::with_sources(sources).await
+    }
+
+    /// Manager over an explicit list of sources, merged in order
+    pub async fn with_sources(sources: Vec<Box<dyn ConfigSource>>) -> Result<Self> {
         let secret_manager = Arc::new(SecretManager::new().await?);
         let validator = ConfigValidator::new();
         
@@ -48,7 +60,7 @@ impl ConfigManager {
         validator.validate(&initial_config)?;
 
         Ok(Self {
-            sources,
+            sources: Arc::new(sources),
             secret_manager,
             validator,
             current_config: Arc::new(RwLock::new(initial_config)),
@@ -56,34 +68,112 @@ impl ConfigManager {
         })
     }
 
+    /// `[server]` as `config.toml` and the environment give it, for sizing
+    /// the async runtime before there is one. Remote sources and secrets
+    /// aren't consulted, and if loading fails the defaults are used; `new`
+    /// reports the problem once the runtime is up.
+    pub fn startup_server_config() -> ServerConfig {
+        tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .map_err(anyhow::Error::from)
+            .and_then(|runtime| runtime.block_on(Self::load_local_config()))
+            .map(|config| config.server)
+            .unwrap_or_else(|_| EnterpriseConfig::default().server)
+    }
+
+    async fn load_local_config() -> Result<EnterpriseConfig> {
+        let env = EnvConfigSource::new().load_config().await?;
+        let file = FileConfigSource::new("config.toml")?.load_config().await?;
+        // Same precedence as `new`: the file overrides the environment
+        let merged = merge_sources(vec![env, file]);
+        if merged.is_empty() {
+            return Ok(EnterpriseConfig::default());
+        }
+        merged.try_into()
+    }
+
+    /// Load `path` merged with the environment the way startup does, and
+    /// report every problem found.
+    ///
+    /// Used by `ratewatch validate-config`; stops before binding a port or
+    /// touching Redis. With `resolve_secrets` off, `secret://` references are
+    /// left as-is.
+    pub async fn check_file(
+        path: &str,
+        resolve_secrets: bool,
+    ) -> std::result::Result<EnterpriseConfig, ConfigCheckError> {
+        if !std::path::Path::new(path).is_file() {
+            return Err(ConfigCheckError::NotFound(path.to_string()));
+        }
+
+        let file = FileConfigSource::new(path)
+            .map_err(ConfigCheckError::Parse)?
+            .load_config()
+            .await
+            .map_err(ConfigCheckError::Parse)?;
+        let env = EnvConfigSource::new()
+            .load_config()
+            .await
+            .map_err(ConfigCheckError::Parse)?;
+
+        // Same precedence as `new`: the file overrides the environment
+        let mut merged = merge_sources(vec![env, file]);
+
+        if resolve_secrets {
+            let secret_manager = SecretManager::new().await.map_err(ConfigCheckError::Secrets)?;
+            Self::resolve_secrets(&mut merged, &secret_manager)
+                .await
+                .map_err(ConfigCheckError::Secrets)?;
+        }
+
+        // Well-formed but not shaped like the config, e.g. a missing section
+        let config: EnterpriseConfig = merged
+            .try_into()
+            .map_err(|e: anyhow::Error| ConfigCheckError::Invalid(vec![format!("{e:#}")]))?;
+
+        let problems = ConfigValidator::new().validate_all(&config);
+        if problems.is_empty() {
+            Ok(config)
+        } else {
+            Err(ConfigCheckError::Invalid(problems))
+        }
+    }
+
     pub async fn get_config(&self) -> EnterpriseConfig {
         self.current_config.read().await.clone()
     }
 
-    pub async fn reload_config(&self) -> Result<()> {
+    /// Reload and validate all sources, then swap the new config in.
+    ///
+    /// Returns the dotted keys whose values changed. On error the running
+    /// config is left untouched.
+    pub async fn reload_config(&self) -> Result<Vec<String>> {
         tracing::info!("Reloading configuration from all sources");
         
         let new_config = Self::load_merged_config(&self.sources, &self.secret_manager).await?;
         self.validator.validate(&new_config)?;
 
         let mut current = self.current_config.write().await;
+        let changed_keys = current.changed_keys(&new_config);
         *current = new_config;
 
-        tracing::info!("Configuration reloaded successfully");
-        Ok(())
+        tracing::info!("Configuration reloaded successfully ({} keys changed)", changed_keys.len());
+        warn_restart_required(&changed_keys);
+        Ok(changed_keys)
     }
 
     async fn load_merged_config(
         sources: &[Box<dyn ConfigSource>],
         secret_manager: &SecretManager,
     ) -> Result<EnterpriseConfig> {
-        let mut merged_config = ConfigMap::new();
+        let mut loaded = Vec::with_capacity(sources.len());
 
         // Load from all sources in order (later sources override earlier ones)
         for source in sources {
             match source.load_config().await {
                 Ok(config) => {
-                    merged_config.merge(config);
+                    loaded.push(config);
                     tracing::debug!("Loaded configuration from source: {}", source.name());
                 }
                 Err(e) => {
@@ -92,6 +182,8 @@ impl ConfigManager {
             }
         }
 
+        let mut merged_config = merge_sources(loaded);
+
         // Resolve secrets
         Self::resolve_secrets(&mut merged_config, secret_manager).await?;
 
@@ -106,38 +198,50 @@ impl ConfigManager {
         Ok(config)
     }
 
+    /// Replace every `secret://` string, however deeply nested, with its value.
+    ///
+    /// Fails on the first reference that can't be resolved, naming its config
+    /// path, e.g. `disaster_recovery.replication.replicas[0].endpoint`.
     async fn resolve_secrets(
         config: &mut ConfigMap,
         secret_manager: &SecretManager,
     ) -> Result<()> {
+        let mut refs = Vec::new();
         for (key, value) in config.iter_mut() {
-            if let Some(secret_ref) = value.as_str() {
-                if secret_ref.starts_with("secret://") {
-                    let secret_key = &secret_ref[9..]; // Remove "secret://" prefix
-                    match secret_manager.get_secret(secret_key).await {
-                        Ok(secret_value) => {
-                            *value = serde_json::Value::String(secret_value);
-                            tracing::debug!("Resolved secret for key: {}", key);
-                        }
-                        Err(e) => {
-                            tracing::error!("Failed to resolve secret {}: {}", secret_key, e);
-                            return Err(e);
-                        }
-                    }
+            collect_secret_refs(key.clone(), value, &mut refs);
+        }
+
+        for (path, value) in refs {
+            let secret_key = value.as_str().unwrap_or_default()[SECRET_REF_PREFIX.len()..].to_string();
+            match secret_manager.get_secret(&secret_key).await {
+                Ok(secret_value) => {
+                    // Wiped again once the map is converted; see `TryFrom<ConfigMap>`
+                    *value = serde_json::Value::String(secret_value.expose_secret().to_string());
+                    tracing::debug!("Resolved secret for key: {}", path);
+                }
+                Err(e) => {
+                    tracing::error!("Failed to resolve secret {} for key {}: {}", secret_key, path, e);
+                    return Err(e.context(format!("Failed to resolve secret for config key '{path}'")));
                 }
             }
         }
         Ok(())
     }
 
+    /// Watch every source and hot-reload when one reports a change.
+    ///
+    /// A change reloads and validates all sources, exactly like
+    /// `reload_config`; an invalid result leaves the running config in place.
+    /// The returned channel reports each outcome and must be drained.
     pub async fn watch_changes(&self) -> Result<tokio::sync::mpsc::Receiver<ConfigChangeEvent>> {
         let (tx, rx) = tokio::sync::mpsc::channel(100);
         
-        for source in &self.sources {
+        for source in self.sources.iter() {
             let mut change_stream = source.watch_changes().await?;
             let tx_clone = tx.clone();
             let config_manager = self.current_config.clone();
             let validator = ConfigValidator::new();
+            let sources = self.sources.clone();
             let secret_manager = self.secret_manager.clone();
             
             tokio::spawn(async move {
@@ -145,7 +249,7 @@ impl ConfigManager {
                     tracing::info!("Configuration change detected: {:?}", change);
                     
                     // Attempt to reload and validate
-                    match Self::load_merged_config(&[], &secret_manager).await {
+                    match Self::load_merged_config(&sources, &secret_manager).await {
                         Ok(new_config) => {
                             if let Err(e) = validator.validate(&new_config) {
                                 tracing::error!("Configuration validation failed: {}", e);
@@ -154,8 +258,16 @@ impl ConfigManager {
                             }
 
                             let mut current = config_manager.write().await;
+                            let changed_keys = current.changed_keys(&new_config);
                             *current = new_config;
-                            
+                            drop(current);
+
+                            tracing::info!(
+                                "Configuration hot-reloaded from {} ({} keys changed)",
+                                change.source,
+                                changed_keys.len()
+                            );
+                            warn_restart_required(&changed_keys);
                             let _ = tx_clone.send(ConfigChangeEvent::Updated).await;
                         }
                         Err(e) => {
@@ -171,6 +283,46 @@ impl ConfigManager {
     }
 }
 
+/// Settings the process reads once at startup. A reload still records a
+/// new value, but it only takes effect after a restart.
+const RESTART_REQUIRED_KEYS: &[&str] = &["server.worker_threads", "server.max_blocking_threads"];
+
+fn warn_restart_required(changed_keys: &[String]) {
+    for key in changed_keys.iter().filter(|key| RESTART_REQUIRED_KEYS.contains(&key.as_str())) {
+        tracing::warn!("{} changed, but only takes effect after a restart", key);
+    }
+}
+
+/// Why `ConfigManager::check_file` rejected a config file
+#[derive(Debug)]
+pub enum ConfigCheckError {
+    NotFound(String),
+    /// The file isn't valid TOML, YAML or JSON
+    Parse(anyhow::Error),
+    Secrets(anyhow::Error),
+    /// Parsed, but failed validation; one entry per problem
+    Invalid(Vec<String>),
+}
+
+impl std::fmt::Display for ConfigCheckError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConfigCheckError::NotFound(path) => write!(f, "file not found: {path}"),
+            ConfigCheckError::Parse(e) => write!(f, "parse error: {e:#}"),
+            ConfigCheckError::Secrets(e) => write!(f, "failed to resolve secrets: {e:#}"),
+            ConfigCheckError::Invalid(problems) => {
+                write!(f, "validation error:")?;
+                for problem in problems {
+                    write!(f, "\n  - {problem}")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for ConfigCheckError {}
+
 /// Configuration change events
 #[derive(Debug)]
 pub enum ConfigChangeEvent {
@@ -207,7 +359,11 @@ pub type ConfigMap = HashMap<String, serde_json::Value>;
 
 /// Enterprise configuration structure
 #[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+#[cfg_attr(feature = "config-schema", derive(schemars::JsonSchema))]
 pub struct EnterpriseConfig {
+    /// Layout version of the document; older layouts are migrated on load
+    #[serde(default = "migration::current_schema_version")]
+    pub schema_version: u32,
     #[validate(nested)]
     pub server: ServerConfig,
     #[validate(nested)]
@@ -220,20 +376,459 @@ pub struct EnterpriseConfig {
     pub disaster_recovery: DisasterRecoveryConfig,
     #[validate(nested)]
     pub infrastructure: InfrastructureConfig,
+    #[serde(default)]
+    #[validate(nested)]
+    pub rate_limiting: RateLimitingConfig,
+    #[serde(default)]
+    #[validate(nested)]
+    pub redis: RedisConfig,
+    #[serde(default)]
+    pub config_sources: ConfigSourcesConfig,
+}
+
+/// How config sources are combined
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "config-schema", derive(schemars::JsonSchema))]
+pub struct ConfigSourcesConfig {
+    #[serde(default)]
+    pub merge_strategy: MergeStrategy,
+}
+
+/// Merge behaviour when several sources set values under the same parent
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "config-schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum MergeStrategy {
+    /// A later source replaces a key wholesale, including object values
+    Override,
+    /// Object values are merged key by key; later sources win per leaf
+    #[default]
+    DeepMerge,
 }
 
+/// Redis deployment used by the limiter, analytics, audit and tenant storage
 #[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+#[cfg_attr(feature = "config-schema", derive(schemars::JsonSchema))]
+pub struct RedisConfig {
+    /// `redis://`, `redis+cluster://h1,h2` or `redis+sentinel://s1,s2/master`;
+    /// `REDIS_URL` overrides it. May carry a password, so it's kept as a secret
+    #[serde(default = "default_redis_url")]
+    pub url: SecretString,
+    /// Cluster seed nodes; takes precedence over `url`
+    #[serde(default)]
+    pub cluster_nodes: Vec<String>,
+    /// Sentinel-managed master; takes precedence over `url`
+    #[serde(default)]
+    #[validate(nested)]
+    pub sentinel: Option<RedisSentinelConfig>,
+    /// Multiplexed connections shared by all components (ignored for Cluster)
+    #[serde(default = "default_redis_pool_size")]
+    #[validate(range(min = 1, max = 64))]
+    pub pool_size: usize,
+    /// Give up on opening (or waiting for) a connection after this long
+    #[serde(default = "default_redis_connect_timeout_ms")]
+    #[validate(range(min = 1))]
+    pub connect_timeout_ms: u64,
+    /// Fail a command with no reply after this long; the connection is replaced
+    #[serde(default = "default_redis_response_timeout_ms")]
+    #[validate(range(min = 1))]
+    pub response_timeout_ms: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+#[cfg_attr(feature = "config-schema", derive(schemars::JsonSchema))]
+pub struct RedisSentinelConfig {
+    #[validate(length(min = 1))]
+    pub master_name: String,
+    /// Sentinel addresses, e.g. `redis://10.0.0.1:26379`
+    #[validate(length(min = 1))]
+    pub addresses: Vec<String>,
+}
+
+fn default_redis_url() -> SecretString {
+    SecretString::from("redis://127.0.0.1:6379")
+}
+
+fn default_redis_pool_size() -> usize {
+    crate::redis_backend::DEFAULT_POOL_SIZE
+}
+
+fn default_redis_connect_timeout_ms() -> u64 {
+    crate::redis_backend::DEFAULT_CONNECT_TIMEOUT.as_millis() as u64
+}
+
+fn default_redis_response_timeout_ms() -> u64 {
+    crate::redis_backend::DEFAULT_RESPONSE_TIMEOUT.as_millis() as u64
+}
+
+impl Default for RedisConfig {
+    fn default() -> Self {
+        Self {
+            url: default_redis_url(),
+            cluster_nodes: Vec::new(),
+            sentinel: None,
+            pool_size: default_redis_pool_size(),
+            connect_timeout_ms: default_redis_connect_timeout_ms(),
+            response_timeout_ms: default_redis_response_timeout_ms(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+#[cfg_attr(feature = "config-schema", derive(schemars::JsonSchema))]
+pub struct RateLimitingConfig {
+    /// `fixed_window` (default), `sliding_window` for globally consistent limits across
+    /// instances, or `leaky_bucket` to smooth requests to an even rate
+    #[serde(default)]
+    pub strategy: crate::rate_limiter::RateLimitStrategy,
+    /// Longest a leaky-bucket check may ask a caller to wait, in milliseconds;
+    /// checks that would wait longer are denied
+    #[serde(default = "default_max_wait_ms")]
+    #[validate(range(max = 60000))]
+    pub max_wait_ms: u64,
+    /// Maximum number of items accepted by `POST /v1/limit/batch`
+    #[serde(default = "default_max_batch_size")]
+    #[validate(range(min = 1, max = 10000))]
+    pub max_batch_size: usize,
+    /// `allow` (default) or `deny` checks while Redis is unreachable
+    #[serde(default)]
+    pub failure_mode: crate::rate_limiter::RateLimitFailureMode,
+    /// `enforce` (default), or `monitor` to allow everything and only count would-deny decisions
+    #[serde(default)]
+    pub mode: crate::rate_limiter::RateLimitMode,
+    /// How long `/v1/check` keeps a result for retries sent with the same
+    /// `Idempotency-Key`, in seconds
+    #[serde(default = "default_idempotency_ttl_seconds")]
+    #[validate(range(min = 1, max = 86400))]
+    pub idempotency_ttl_seconds: u64,
+    /// Limits and key extractor per route for `RateLimitLayer::for_route`
+    #[serde(default)]
+    #[validate(nested)]
+    pub routes: Vec<RouteLimitsConfig>,
+    /// Server-side limits applied to incoming requests by route pattern; the
+    /// most specific matching policy wins
+    #[serde(default)]
+    #[validate(nested)]
+    pub policies: Vec<RateLimitPolicyConfig>,
+    /// Applied to requests no policy matches; unmatched requests are unlimited without it
+    #[serde(default)]
+    #[validate(nested)]
+    pub default_policy: Option<PolicyLimitsConfig>,
+}
+
+impl RateLimitingConfig {
+    /// Limits declared for `path`, if any
+    pub fn route_limits(&self, path: &str) -> Option<&[crate::rate_limiter::RateLimitTier]> {
+        self.routes
+            .iter()
+            .find(|route| route.path == path)
+            .map(|route| route.limits.as_slice())
+    }
+}
+
+/// Limits applied together to one route, e.g. 10/s and 1000/h
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+#[cfg_attr(feature = "config-schema", derive(schemars::JsonSchema))]
+pub struct RouteLimitsConfig {
+    #[validate(length(min = 1))]
+    pub path: String,
+    #[validate(length(min = 1, max = 10))]
+    pub limits: Vec<crate::rate_limiter::RateLimitTier>,
+    /// What to limit by; defaults to the API key
+    #[serde(default)]
+    pub key: crate::key_extractor::KeyExtractorConfig,
+}
+
+/// A `[[rate_limiting.policies]]` entry
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+#[cfg_attr(feature = "config-schema", derive(schemars::JsonSchema))]
+pub struct RateLimitPolicyConfig {
+    /// Path pattern, e.g. `/api/users` or `/api/admin/*`. `*` matches one
+    /// segment, or any number of them when it's the last segment.
+    #[validate(length(min = 1))]
+    pub pattern: String,
+    /// HTTP methods the policy applies to; empty means all
+    #[serde(default)]
+    pub methods: Vec<String>,
+    #[serde(flatten)]
+    #[validate(nested)]
+    pub limits: PolicyLimitsConfig,
+}
+
+/// What a rate limit policy enforces
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+#[cfg_attr(feature = "config-schema", derive(schemars::JsonSchema))]
+pub struct PolicyLimitsConfig {
+    #[validate(range(min = 1))]
+    pub limit: u64,
+    /// Window in seconds
+    #[validate(range(min = 1))]
+    pub window: u64,
+    /// Defaults to `rate_limiting.strategy`
+    #[serde(default)]
+    pub algorithm: Option<crate::rate_limiter::RateLimitStrategy>,
+    /// Defaults to `rate_limiting.mode`
+    #[serde(default)]
+    pub mode: Option<crate::rate_limiter::RateLimitMode>,
+    /// Units each request consumes
+    #[serde(default = "default_policy_cost")]
+    #[validate(range(min = 1))]
+    pub cost: u64,
+    /// What to limit by; defaults to the API key
+    #[serde(default)]
+    pub key: crate::key_extractor::KeyExtractorConfig,
+    /// A limit per client IP on top of `key`'s, which also applies to
+    /// requests without a key, e.g. signups before any API key exists
+    #[serde(default)]
+    #[validate(nested)]
+    pub ip_limit: Option<IpLimitConfig>,
+}
+
+/// A policy's limit per client IP
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+#[cfg_attr(feature = "config-schema", derive(schemars::JsonSchema))]
+pub struct IpLimitConfig {
+    #[validate(range(min = 1))]
+    pub limit: u64,
+    /// Window in seconds
+    #[validate(range(min = 1))]
+    pub window: u64,
+}
+
+fn default_policy_cost() -> u64 {
+    1
+}
+
+fn default_max_batch_size() -> usize {
+    100
+}
+
+fn default_max_wait_ms() -> u64 {
+    crate::rate_limiter::DEFAULT_MAX_WAIT.as_millis() as u64
+}
+
+fn default_idempotency_ttl_seconds() -> u64 {
+    crate::rate_limiter::DEFAULT_IDEMPOTENCY_TTL.as_secs()
+}
+
+impl Default for RateLimitingConfig {
+    fn default() -> Self {
+        Self {
+            strategy: Default::default(),
+            max_wait_ms: default_max_wait_ms(),
+            max_batch_size: default_max_batch_size(),
+            failure_mode: Default::default(),
+            mode: Default::default(),
+            idempotency_ttl_seconds: default_idempotency_ttl_seconds(),
+            routes: Vec::new(),
+            policies: Vec::new(),
+            default_policy: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+#[cfg_attr(feature = "config-schema", derive(schemars::JsonSchema))]
 pub struct ServerConfig {
     #[validate(range(min = 1024, max = 65535))]
     pub port: u16,
     #[validate(length(min = 1))]
     pub host: String,
+    /// Tokio worker threads; one per CPU when unset. Fixed at startup, so a
+    /// change only takes effect after a restart.
+    #[serde(default)]
     #[validate(range(min = 1, max = 1000))]
-    pub worker_threads: usize,
+    pub worker_threads: Option<usize>,
+    /// Most threads Tokio runs blocking work on, such as file I/O; 512 when
+    /// unset. Also fixed at startup.
+    #[serde(default)]
+    #[validate(range(min = 1, max = 10000))]
+    pub max_blocking_threads: Option<usize>,
     pub tls: Option<TlsConfig>,
+    /// Load balancers and proxies, as IPs or CIDR ranges, whose
+    /// `X-Forwarded-For` and `Forwarded` headers are believed. Requests from
+    /// anywhere else are attributed to their peer address.
+    #[serde(default)]
+    #[validate(custom(function = "validate_ip_network_entries"))]
+    pub trusted_proxies: Vec<String>,
+    /// IPv6 clients are rate limited, profiled and banned by their network
+    /// of this length, e.g. 64; 128 treats each address as its own client
+    #[serde(default = "default_ipv6_prefix_length")]
+    #[validate(range(min = 1, max = 128))]
+    pub ipv6_prefix_length: u8,
+    /// Largest accepted request body; larger bodies get 413
+    #[serde(default = "default_max_body_bytes")]
+    #[validate(range(min = 1024))]
+    pub max_body_bytes: usize,
+    /// Per-request deadline; slower requests get 408
+    #[serde(default = "default_request_timeout_ms")]
+    #[validate(range(min = 1))]
+    pub request_timeout_ms: u64,
+    /// Different limits under a path prefix, e.g. for an upload endpoint;
+    /// the longest matching prefix wins
+    #[serde(default)]
+    #[validate(nested)]
+    pub route_request_limits: Vec<RouteRequestLimitsConfig>,
+    /// On SIGTERM or Ctrl-C, how long in-flight requests get to finish, and
+    /// queued SIEM events to be delivered, before the process exits anyway
+    #[serde(default = "default_shutdown_timeout_seconds")]
+    #[validate(range(min = 1, max = 3600))]
+    pub shutdown_timeout_seconds: u64,
+    #[serde(default)]
+    #[validate(nested)]
+    pub grpc: GrpcConfig,
+    #[serde(default)]
+    #[validate(nested)]
+    pub cors: CorsConfig,
+    #[serde(default)]
+    #[validate(nested)]
+    pub compression: CompressionConfig,
+}
+
+/// Body size and deadline for the routes under `path`. A limit left unset
+/// is the server-wide one.
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+#[cfg_attr(feature = "config-schema", derive(schemars::JsonSchema))]
+pub struct RouteRequestLimitsConfig {
+    /// Path prefix, matched by whole segments: `/v1/uploads` covers
+    /// `/v1/uploads/audit` but not `/v1/uploads-old`
+    #[validate(length(min = 1))]
+    pub path: String,
+    #[serde(default)]
+    #[validate(range(min = 1024))]
+    pub max_body_bytes: Option<usize>,
+    #[serde(default)]
+    #[validate(range(min = 1))]
+    pub request_timeout_ms: Option<u64>,
+}
+
+/// gzip/brotli response compression, negotiated via `Accept-Encoding`.
+///
+/// Disable when a proxy in front of RateWatch already compresses.
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+#[cfg_attr(feature = "config-schema", derive(schemars::JsonSchema))]
+pub struct CompressionConfig {
+    pub enabled: bool,
+    /// Responses smaller than this are sent uncompressed
+    #[serde(default = "default_compression_min_size")]
+    pub min_size_bytes: u16,
+}
+
+fn default_compression_min_size() -> u16 {
+    1024
+}
+
+impl Default for CompressionConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            min_size_bytes: default_compression_min_size(),
+        }
+    }
+}
+
+/// Cross-origin access for browser clients.
+///
+/// The default allows no origins, so browsers are limited to same-origin calls.
+/// `"*"` in `allowed_origins` opts in to any origin, but cannot be combined
+/// with `allow_credentials`.
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+#[cfg_attr(feature = "config-schema", derive(schemars::JsonSchema))]
+pub struct CorsConfig {
+    #[serde(default)]
+    pub allowed_origins: Vec<String>,
+    #[serde(default = "default_cors_methods")]
+    pub allowed_methods: Vec<String>,
+    #[serde(default = "default_cors_headers")]
+    pub allowed_headers: Vec<String>,
+    #[serde(default)]
+    pub allow_credentials: bool,
+    #[serde(default = "default_cors_max_age")]
+    pub max_age_seconds: u64,
+}
+
+fn default_cors_methods() -> Vec<String> {
+    vec!["GET".to_string(), "POST".to_string()]
+}
+
+fn default_cors_headers() -> Vec<String> {
+    vec![
+        "authorization".to_string(),
+        "content-type".to_string(),
+        "idempotency-key".to_string(),
+    ]
+}
+
+fn default_cors_max_age() -> u64 {
+    600
+}
+
+impl Default for CorsConfig {
+    fn default() -> Self {
+        Self {
+            allowed_origins: Vec::new(),
+            allowed_methods: default_cors_methods(),
+            allowed_headers: default_cors_headers(),
+            allow_credentials: false,
+            max_age_seconds: default_cors_max_age(),
+        }
+    }
+}
+
+fn default_ipv6_prefix_length() -> u8 {
+    128
 }
 
+fn default_max_body_bytes() -> usize {
+    1024 * 1024
+}
+
+fn default_request_timeout_ms() -> u64 {
+    30_000
+}
+
+fn default_shutdown_timeout_seconds() -> u64 {
+    30
+}
+
+/// gRPC listener; only served when built with the `grpc` feature
 #[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+#[cfg_attr(feature = "config-schema", derive(schemars::JsonSchema))]
+pub struct GrpcConfig {
+    pub enabled: bool,
+    #[validate(range(min = 1024, max = 65535))]
+    pub port: u16,
+    /// Limit applied to Envoy descriptors that carry no `limit` override
+    #[serde(default = "default_envoy_limit")]
+    #[validate(range(min = 1))]
+    pub envoy_default_limit: u64,
+    /// Window, in seconds, for Envoy descriptors without a `limit` override
+    #[serde(default = "default_envoy_window")]
+    #[validate(range(min = 1))]
+    pub envoy_default_window: u64,
+}
+
+fn default_envoy_limit() -> u64 {
+    100
+}
+
+fn default_envoy_window() -> u64 {
+    60
+}
+
+impl Default for GrpcConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            port: 50051,
+            envoy_default_limit: default_envoy_limit(),
+            envoy_default_window: default_envoy_window(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+#[cfg_attr(feature = "config-schema", derive(schemars::JsonSchema))]
 pub struct TlsConfig {
     #[validate(length(min = 1))]
     pub cert_path: String,
@@ -243,6 +838,7 @@ pub struct TlsConfig {
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+#[cfg_attr(feature = "config-schema", derive(schemars::JsonSchema))]
 pub struct SecurityConfig {
     #[validate(nested)]
     pub audit: AuditConfig,
@@ -252,9 +848,93 @@ pub struct SecurityConfig {
     pub secrets: SecretConfig,
     #[validate(nested)]
     pub compliance: ComplianceConfig,
+    #[serde(default)]
+    #[validate(nested)]
+    pub request_signing: RequestSigningConfig,
+    #[serde(default)]
+    #[validate(nested)]
+    pub ip_allowlist: IpAllowlistConfig,
+    #[serde(default)]
+    #[validate(nested)]
+    pub ip_denylist: IpDenylistConfig,
+    /// Where security events are forwarded; see `crate::security::siem_integration`
+    #[serde(default)]
+    pub siem: crate::security::siem_integration::SiemConfig,
+}
+
+/// Client IPs that skip threat detection and rate limiting; see `crate::ip_allowlist`
+#[derive(Debug, Clone, Default, Serialize, Deserialize, Validate)]
+#[cfg_attr(feature = "config-schema", derive(schemars::JsonSchema))]
+pub struct IpAllowlistConfig {
+    /// IP addresses and CIDR ranges, IPv4 or IPv6, e.g. `10.0.0.0/8`
+    #[serde(default)]
+    #[validate(custom(function = "validate_ip_network_entries"))]
+    pub entries: Vec<String>,
+}
+
+/// Client IPs refused with 403 before threat analysis; see
+/// `crate::security::ip_denylist`
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+#[cfg_attr(feature = "config-schema", derive(schemars::JsonSchema))]
+pub struct IpDenylistConfig {
+    /// Permanently banned IP addresses and CIDR ranges, IPv4 or IPv6
+    #[serde(default)]
+    #[validate(custom(function = "validate_ip_network_entries"))]
+    pub entries: Vec<String>,
+    /// How long a ban placed by threat detection's `BlockIp` action lasts
+    #[serde(default = "default_ip_ban_duration_seconds")]
+    #[validate(range(min = 1))]
+    pub ban_duration_seconds: u64,
+}
+
+fn default_ip_ban_duration_seconds() -> u64 {
+    3600
+}
+
+impl Default for IpDenylistConfig {
+    fn default() -> Self {
+        Self {
+            entries: Vec::new(),
+            ban_duration_seconds: default_ip_ban_duration_seconds(),
+        }
+    }
+}
+
+fn validate_ip_network_entries(entries: &[String]) -> Result<(), validator::ValidationError> {
+    entries
+        .iter()
+        .try_for_each(|entry| crate::ip_allowlist::parse_network(entry).map(|_| ()))
+        .map_err(|_| validator::ValidationError::new("invalid_ip_or_cidr"))
 }
 
+/// HMAC-signed requests with replay protection; see `crate::request_signing`
 #[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+#[cfg_attr(feature = "config-schema", derive(schemars::JsonSchema))]
+pub struct RequestSigningConfig {
+    /// Refuse authenticated HTTP requests that aren't signed
+    #[serde(default)]
+    pub enabled: bool,
+    /// How far `X-Timestamp` may be from the server's clock
+    #[serde(default = "default_max_clock_skew_seconds")]
+    #[validate(range(min = 1, max = 3600))]
+    pub max_clock_skew_seconds: u64,
+}
+
+fn default_max_clock_skew_seconds() -> u64 {
+    300
+}
+
+impl Default for RequestSigningConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_clock_skew_seconds: default_max_clock_skew_seconds(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+#[cfg_attr(feature = "config-schema", derive(schemars::JsonSchema))]
 pub struct AuditConfig {
     pub enabled: bool,
     #[validate(length(min = 1))]
@@ -262,19 +942,594 @@ pub struct AuditConfig {
     pub digital_signing: bool,
     #[validate(range(min = 1))]
     pub retention_days: u32,
+    /// Used when `storage_backend = "file"`
+    #[serde(default)]
+    #[validate(nested)]
+    pub file: FileAuditConfig,
+    /// Write events from a background task instead of the request path
+    #[serde(default)]
+    #[validate(nested)]
+    pub buffer: AuditBufferConfig,
+}
+
+/// Buffered audit writes: events are signed when logged and queued for a
+/// background task that stores them in batches. Requests no longer wait for
+/// storage, but events still queued when the process dies are lost.
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+#[cfg_attr(feature = "config-schema", derive(schemars::JsonSchema))]
+pub struct AuditBufferConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// Events queued before backpressure applies
+    #[serde(default = "default_audit_buffer_capacity")]
+    #[validate(range(min = 1))]
+    pub capacity: usize,
+    /// Most events stored per write
+    #[serde(default = "default_audit_buffer_batch_size")]
+    #[validate(range(min = 1))]
+    pub batch_size: usize,
+    /// Longest an event waits for its batch to fill before the batch is
+    /// written anyway
+    #[serde(default = "default_audit_buffer_flush_interval_ms")]
+    #[validate(range(min = 1, max = 60000))]
+    pub flush_interval_ms: u64,
+    #[serde(default)]
+    pub backpressure: AuditBackpressure,
+}
+
+/// What logging does when the audit buffer is full
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "config-schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum AuditBackpressure {
+    /// Wait for room, slowing requests down to the storage's pace
+    #[default]
+    Block,
+    /// Discard the event and count it in `ratewatch_audit_events_total{outcome="dropped"}`
+    Drop,
+}
+
+fn default_audit_buffer_capacity() -> usize {
+    10_000
+}
+
+fn default_audit_buffer_batch_size() -> usize {
+    100
+}
+
+fn default_audit_buffer_flush_interval_ms() -> u64 {
+    100
+}
+
+impl Default for AuditBufferConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            capacity: default_audit_buffer_capacity(),
+            batch_size: default_audit_buffer_batch_size(),
+            flush_interval_ms: default_audit_buffer_flush_interval_ms(),
+            backpressure: AuditBackpressure::default(),
+        }
+    }
+}
+
+/// Where file audit storage writes and when it rolls the active file into a
+/// segment. Rolled segments stay readable by audit queries.
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+#[cfg_attr(feature = "config-schema", derive(schemars::JsonSchema))]
+pub struct FileAuditConfig {
+    #[serde(default = "default_audit_file_path")]
+    #[validate(length(min = 1))]
+    pub path: String,
+    /// Roll before the active file would grow past this size
+    #[serde(default = "default_audit_max_segment_bytes")]
+    #[validate(range(min = 1))]
+    pub max_segment_bytes: u64,
+    /// Also roll on the first write of each UTC day
+    #[serde(default = "default_audit_rotate_daily")]
+    pub rotate_daily: bool,
+    /// Gzip rolled segments
+    #[serde(default = "default_audit_compress")]
+    pub compress: bool,
+}
+
+fn default_audit_file_path() -> String {
+    "/var/log/ratewatch/audit.log".to_string()
+}
+
+fn default_audit_max_segment_bytes() -> u64 {
+    100 * 1024 * 1024
+}
+
+fn default_audit_rotate_daily() -> bool {
+    true
+}
+
+fn default_audit_compress() -> bool {
+    true
+}
+
+impl Default for FileAuditConfig {
+    fn default() -> Self {
+        Self {
+            path: default_audit_file_path(),
+            max_segment_bytes: default_audit_max_segment_bytes(),
+            rotate_daily: default_audit_rotate_daily(),
+            compress: default_audit_compress(),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+#[cfg_attr(feature = "config-schema", derive(schemars::JsonSchema))]
 pub struct ThreatDetectionConfig {
     pub enabled: bool,
     pub behavioral_analysis: bool,
     pub ip_reputation: bool,
+    /// Score requests with the online anomaly model configured in `ml`
     pub ml_engine: bool,
     #[validate(range(min = 0.0, max = 1.0))]
     pub threat_threshold: f64,
+    /// Weight of each analyzer's score in the combined score, by analyzer ID
+    /// (`ip_reputation`, `behavior_analysis`, `asn_reputation`, `ml_anomaly`,
+    /// `tls_fingerprint`). Unlisted analyzers weigh 1.0; 0 keeps an analyzer
+    /// running without letting it affect the verdict.
+    #[serde(default)]
+    #[validate(custom(function = "validate_analyzer_weights"))]
+    pub analyzer_weights: HashMap<String, f64>,
+    /// Longest a whole analysis may hold a request. Analyzers still running
+    /// when it's spent are left out and the result is marked partial.
+    #[serde(default = "default_max_total_analysis_time_ms")]
+    #[validate(range(min = 1))]
+    pub max_total_analysis_time_ms: u64,
+    #[serde(default)]
+    pub mode: ThreatAnalysisMode,
+    /// Requests waiting for background analysis in `async` mode; more are
+    /// let through unanalyzed
+    #[serde(default = "default_async_queue_size")]
+    #[validate(range(min = 1))]
+    pub async_queue_size: usize,
+    /// What happens to a request whose inline analysis fails, e.g. because
+    /// an analyzer lost Redis
+    #[serde(default)]
+    pub failure_mode: SecurityFailureMode,
+    #[serde(default)]
+    #[validate(nested)]
+    pub ml: MlEngineConfig,
+    #[serde(default)]
+    #[validate(nested)]
+    pub geoip: GeoIpConfig,
+    #[serde(default)]
+    #[validate(nested)]
+    pub asn_reputation: AsnReputationConfig,
+    #[serde(default)]
+    #[validate(nested)]
+    pub tls_fingerprint: TlsFingerprintConfig,
+    #[serde(default)]
+    #[validate(nested)]
+    pub behavior_patterns: BehaviorPatternsConfig,
+    #[serde(default)]
+    #[validate(nested)]
+    pub challenge: ChallengeConfig,
+}
+
+fn default_max_total_analysis_time_ms() -> u64 {
+    5000
+}
+
+fn default_async_queue_size() -> usize {
+    10_000
+}
+
+/// Whether threat analysis holds up the request it's analyzing
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "config-schema", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum ThreatAnalysisMode {
+    /// Analyze before the request is handled, and refuse it if it's a threat
+    #[default]
+    Inline,
+    /// Let the request through and analyze it in the background; a ban
+    /// applies to the client's later requests
+    Async,
+}
+
+/// Whether a request is let through when threat detection can't analyze it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "config-schema", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum SecurityFailureMode {
+    /// Allow it, judged on whatever analyzers did finish
+    #[default]
+    FailOpen,
+    /// Refuse it with 503 and `security_unavailable`
+    FailClosed,
+}
+
+pub(crate) fn validate_analyzer_weights(weights: &HashMap<String, f64>) -> Result<(), validator::ValidationError> {
+    if weights.values().all(|weight| weight.is_finite() && *weight >= 0.0) {
+        Ok(())
+    } else {
+        Err(validator::ValidationError::new("negative_analyzer_weight"))
+    }
+}
+
+/// Country lookups for SIEM events and geographic anomaly detection; needs
+/// the `geoip` feature
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+#[cfg_attr(feature = "config-schema", derive(schemars::JsonSchema))]
+pub struct GeoIpConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// MaxMind GeoLite2/GeoIP2 Country or City database (`.mmdb`)
+    #[serde(default = "default_geoip_database_path")]
+    #[validate(length(min = 1))]
+    pub database_path: String,
+    /// Resolved IPs kept in memory
+    #[serde(default = "default_geoip_cache_size")]
+    #[validate(range(min = 1))]
+    pub cache_size: usize,
+}
+
+fn default_geoip_database_path() -> String {
+    "/usr/share/GeoIP/GeoLite2-Country.mmdb".to_string()
+}
+
+fn default_geoip_cache_size() -> usize {
+    10_000
+}
+
+impl Default for GeoIpConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            database_path: default_geoip_database_path(),
+            cache_size: default_geoip_cache_size(),
+        }
+    }
+}
+
+/// Scores requests by the autonomous system their IP belongs to. ASNs come
+/// from a MaxMind ASN database (needs the `geoip` feature), the `prefixes`
+/// table, or both; IPs neither knows about score as neutral.
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+#[cfg_attr(feature = "config-schema", derive(schemars::JsonSchema))]
+pub struct AsnReputationConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// MaxMind GeoLite2/GeoIP2 ASN database (`.mmdb`)
+    #[serde(default)]
+    pub database_path: Option<String>,
+    /// Static prefix-to-ASN entries, checked before the database
+    #[serde(default)]
+    #[validate(nested)]
+    pub prefixes: Vec<AsnPrefixConfig>,
+    /// ASNs known for hosting abuse (bulletproof hosters and the like)
+    #[serde(default)]
+    pub high_risk_asns: Vec<u32>,
+    /// Hosting/cloud ASNs, in addition to those recognised by organization name
+    #[serde(default)]
+    pub datacenter_asns: Vec<u32>,
+    /// Score for traffic from a high-risk ASN
+    #[serde(default = "default_asn_high_risk_score")]
+    #[validate(range(min = 0.0, max = 1.0))]
+    pub high_risk_score: f64,
+    /// Score for traffic from a datacenter rather than a residential network
+    #[serde(default = "default_asn_datacenter_score")]
+    #[validate(range(min = 0.0, max = 1.0))]
+    pub datacenter_score: f64,
+    /// Resolved IPs kept in memory
+    #[serde(default = "default_geoip_cache_size")]
+    #[validate(range(min = 1))]
+    pub cache_size: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+#[cfg_attr(feature = "config-schema", derive(schemars::JsonSchema))]
+pub struct AsnPrefixConfig {
+    /// IPv4 or IPv6 network, e.g. `203.0.113.0/24`
+    #[validate(length(min = 1))]
+    pub cidr: String,
+    pub asn: u32,
+    #[serde(default)]
+    pub organization: Option<String>,
+}
+
+fn default_asn_high_risk_score() -> f64 {
+    0.8
+}
+
+fn default_asn_datacenter_score() -> f64 {
+    0.3
+}
+
+impl Default for AsnReputationConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            database_path: None,
+            prefixes: Vec::new(),
+            high_risk_asns: Vec::new(),
+            datacenter_asns: Vec::new(),
+            high_risk_score: default_asn_high_risk_score(),
+            datacenter_score: default_asn_datacenter_score(),
+            cache_size: default_geoip_cache_size(),
+        }
+    }
+}
+
+/// JA3 client fingerprints as a threat signal. ratewatch doesn't terminate
+/// TLS itself, so the hash comes from a header set by the proxy that does;
+/// requests without it aren't scored.
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+#[cfg_attr(feature = "config-schema", derive(schemars::JsonSchema))]
+pub struct TlsFingerprintConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// Request header carrying the JA3 hash computed by the TLS terminator
+    #[serde(default = "default_ja3_header")]
+    #[validate(length(min = 1))]
+    pub header: String,
+    /// JA3 hashes of known scanners and malware
+    #[serde(default)]
+    pub known_bad: Vec<String>,
+    /// Distinct IPs sharing one fingerprint within the window before it's flagged
+    #[serde(default = "default_ja3_distinct_ip_threshold")]
+    #[validate(range(min = 2))]
+    pub distinct_ip_threshold: u64,
+    /// Distinct fingerprints from one client (API key, else IP) within the
+    /// window before it's flagged
+    #[serde(default = "default_ja3_distinct_fingerprint_threshold")]
+    #[validate(range(min = 2))]
+    pub distinct_fingerprint_threshold: u64,
+    #[serde(default = "default_ja3_window_seconds")]
+    #[validate(range(min = 1))]
+    pub window_seconds: u64,
+    /// Score for a fingerprint on the known-bad list
+    #[serde(default = "default_ja3_known_bad_score")]
+    #[validate(range(min = 0.0, max = 1.0))]
+    pub known_bad_score: f64,
+    /// Score for a fingerprint spread across too many IPs
+    #[serde(default = "default_ja3_spread_score")]
+    #[validate(range(min = 0.0, max = 1.0))]
+    pub spread_score: f64,
+    /// Score for a client switching between too many fingerprints
+    #[serde(default = "default_ja3_switch_score")]
+    #[validate(range(min = 0.0, max = 1.0))]
+    pub switch_score: f64,
+}
+
+fn default_ja3_header() -> String {
+    "x-ja3-fingerprint".to_string()
+}
+
+fn default_ja3_distinct_ip_threshold() -> u64 {
+    50
+}
+
+fn default_ja3_distinct_fingerprint_threshold() -> u64 {
+    4
+}
+
+fn default_ja3_window_seconds() -> u64 {
+    300
+}
+
+fn default_ja3_known_bad_score() -> f64 {
+    0.9
+}
+
+fn default_ja3_spread_score() -> f64 {
+    0.7
+}
+
+fn default_ja3_switch_score() -> f64 {
+    0.6
+}
+
+impl Default for TlsFingerprintConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            header: default_ja3_header(),
+            known_bad: Vec::new(),
+            distinct_ip_threshold: default_ja3_distinct_ip_threshold(),
+            distinct_fingerprint_threshold: default_ja3_distinct_fingerprint_threshold(),
+            window_seconds: default_ja3_window_seconds(),
+            known_bad_score: default_ja3_known_bad_score(),
+            spread_score: default_ja3_spread_score(),
+            switch_score: default_ja3_switch_score(),
+        }
+    }
+}
+
+/// Operator-defined behavior patterns; see `crate::security::behavior_patterns`
+#[derive(Debug, Clone, Default, Serialize, Deserialize, Validate)]
+#[cfg_attr(feature = "config-schema", derive(schemars::JsonSchema))]
+pub struct BehaviorPatternsConfig {
+    /// JSON or YAML file of pattern definitions; none are loaded if unset
+    #[serde(default)]
+    #[validate(length(min = 1))]
+    pub file: Option<String>,
+    #[serde(default)]
+    pub mode: BehaviorPatternMode,
+}
+
+/// How the file's patterns combine with the built-in behavior checks
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "config-schema", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum BehaviorPatternMode {
+    /// Run alongside the built-in checks
+    #[default]
+    Merge,
+    /// Run instead of the built-in checks
+    Replace,
+}
+
+/// CAPTCHA challenges for borderline threat scores instead of blocking;
+/// see `crate::security::challenge`
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+#[cfg_attr(feature = "config-schema", derive(schemars::JsonSchema))]
+pub struct ChallengeConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default)]
+    pub provider: ChallengeProvider,
+    /// Public site key the client renders the widget with
+    #[serde(default)]
+    pub site_key: String,
+    /// Secret key for the provider's verification API
+    #[serde(default)]
+    pub secret_key: Option<SecretString>,
+    /// Scores from here up to `block_score` are challenged. Below
+    /// `threat_threshold` nothing happens, so lower values have no effect.
+    #[serde(default = "default_challenge_min_score")]
+    #[validate(range(min = 0.0, max = 1.0))]
+    pub min_score: f64,
+    /// Scores from here up are rejected, and the IP banned, without a challenge
+    #[serde(default = "default_challenge_block_score")]
+    #[validate(range(min = 0.0, max = 1.0))]
+    pub block_score: f64,
+    /// Path prefixes served to people, where a challenge can be solved;
+    /// empty challenges on every path
+    #[serde(default)]
+    pub paths: Vec<String>,
+    /// How long a client that solved a challenge isn't challenged again
+    #[serde(default = "default_challenge_solved_ttl_seconds")]
+    #[validate(range(min = 1))]
+    pub solved_ttl_seconds: u64,
+    /// How long an issued challenge token can be redeemed
+    #[serde(default = "default_challenge_token_ttl_seconds")]
+    #[validate(range(min = 1))]
+    pub token_ttl_seconds: u64,
+}
+
+/// CAPTCHA service whose responses are verified
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "config-schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum ChallengeProvider {
+    #[default]
+    Turnstile,
+    Hcaptcha,
+}
+
+fn default_challenge_min_score() -> f64 {
+    0.6
+}
+
+fn default_challenge_block_score() -> f64 {
+    0.8
+}
+
+fn default_challenge_solved_ttl_seconds() -> u64 {
+    3600
+}
+
+fn default_challenge_token_ttl_seconds() -> u64 {
+    300
+}
+
+impl Default for ChallengeConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            provider: ChallengeProvider::default(),
+            site_key: String::new(),
+            secret_key: None,
+            min_score: default_challenge_min_score(),
+            block_score: default_challenge_block_score(),
+            paths: Vec::new(),
+            solved_ttl_seconds: default_challenge_solved_ttl_seconds(),
+            token_ttl_seconds: default_challenge_token_ttl_seconds(),
+        }
+    }
+}
+
+/// Per-client anomaly model behind `ml_engine`; see `crate::security::anomaly_engine`
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+#[cfg_attr(feature = "config-schema", derive(schemars::JsonSchema))]
+pub struct MlEngineConfig {
+    /// Features the model tracks; a request is anomalous if any one is
+    #[serde(default = "default_ml_features")]
+    #[validate(length(min = 1))]
+    pub features: Vec<AnomalyFeature>,
+    /// Requests a client must make before its baseline is trusted
+    #[serde(default = "default_ml_min_samples")]
+    #[validate(range(min = 1))]
+    pub min_samples: u64,
+    /// Deviations from the baseline, in standard deviations, that count as anomalous
+    #[serde(default = "default_ml_z_threshold")]
+    #[validate(range(min = 0.5))]
+    pub z_threshold: f64,
+    /// Weight of each new sample in the moving baseline
+    #[serde(default = "default_ml_smoothing")]
+    #[validate(range(min = 0.001, max = 1.0))]
+    pub smoothing: f64,
+    /// Recent requests per client used to compute rate and entropy features
+    #[serde(default = "default_ml_history_size")]
+    #[validate(range(min = 2, max = 10000))]
+    pub history_size: usize,
+}
+
+/// A behavioral feature the anomaly model tracks per client
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "config-schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum AnomalyFeature {
+    /// Requests in the last minute
+    RequestRate,
+    /// Shannon entropy of the endpoints in recent requests
+    EndpointEntropy,
+    /// Share of the client's previous requests that failed
+    ErrorRate,
+    /// Shannon entropy of the user agents in recent requests
+    UserAgentEntropy,
+}
+
+fn default_ml_features() -> Vec<AnomalyFeature> {
+    vec![
+        AnomalyFeature::RequestRate,
+        AnomalyFeature::EndpointEntropy,
+        AnomalyFeature::ErrorRate,
+        AnomalyFeature::UserAgentEntropy,
+    ]
+}
+
+fn default_ml_min_samples() -> u64 {
+    50
+}
+
+fn default_ml_z_threshold() -> f64 {
+    3.0
+}
+
+fn default_ml_smoothing() -> f64 {
+    0.05
+}
+
+fn default_ml_history_size() -> usize {
+    100
+}
+
+impl Default for MlEngineConfig {
+    fn default() -> Self {
+        Self {
+            features: default_ml_features(),
+            min_samples: default_ml_min_samples(),
+            z_threshold: default_ml_z_threshold(),
+            smoothing: default_ml_smoothing(),
+            history_size: default_ml_history_size(),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+#[cfg_attr(feature = "config-schema", derive(schemars::JsonSchema))]
 pub struct SecretConfig {
     #[validate(length(min = 1))]
     pub provider: String,
@@ -284,31 +1539,35 @@ pub struct SecretConfig {
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+#[cfg_attr(feature = "config-schema", derive(schemars::JsonSchema))]
 pub struct VaultConfig {
     #[validate(url)]
     pub address: String,
-    pub token: Option<String>,
+    pub token: Option<SecretString>,
     pub role_id: Option<String>,
-    pub secret_id: Option<String>,
+    pub secret_id: Option<SecretString>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+#[cfg_attr(feature = "config-schema", derive(schemars::JsonSchema))]
 pub struct AwsSecretsConfig {
     pub region: String,
     pub access_key_id: Option<String>,
-    pub secret_access_key: Option<String>,
+    pub secret_access_key: Option<SecretString>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+#[cfg_attr(feature = "config-schema", derive(schemars::JsonSchema))]
 pub struct AzureKeyVaultConfig {
     #[validate(url)]
     pub vault_url: String,
     pub client_id: Option<String>,
-    pub client_secret: Option<String>,
+    pub client_secret: Option<SecretString>,
     pub tenant_id: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+#[cfg_attr(feature = "config-schema", derive(schemars::JsonSchema))]
 pub struct ComplianceConfig {
     pub gdpr_enabled: bool,
     pub ccpa_enabled: bool,
@@ -318,6 +1577,7 @@ pub struct ComplianceConfig {
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+#[cfg_attr(feature = "config-schema", derive(schemars::JsonSchema))]
 pub struct ObservabilityConfig {
     #[validate(nested)]
     pub metrics: MetricsConfig,
@@ -327,34 +1587,87 @@ pub struct ObservabilityConfig {
     pub alerting: AlertingConfig,
     #[validate(nested)]
     pub logging: LoggingConfig,
+    #[serde(default)]
+    #[validate(nested)]
+    pub analytics: AnalyticsConfig,
+}
+
+/// Request analytics kept in Redis for `/v1/analytics`
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+#[cfg_attr(feature = "config-schema", derive(schemars::JsonSchema))]
+pub struct AnalyticsConfig {
+    /// How long the per-minute counters are kept, allowed and denied
+    /// requests and denials by reason among them. At least the hour
+    /// `/v1/analytics/stats` sums up.
+    #[serde(default = "default_analytics_retention_seconds")]
+    #[validate(range(min = 3600, max = 2592000))]
+    pub retention_seconds: u64,
+}
+
+fn default_analytics_retention_seconds() -> u64 {
+    crate::analytics::DEFAULT_RETENTION.as_secs()
+}
+
+impl Default for AnalyticsConfig {
+    fn default() -> Self {
+        Self {
+            retention_seconds: default_analytics_retention_seconds(),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+#[cfg_attr(feature = "config-schema", derive(schemars::JsonSchema))]
 pub struct MetricsConfig {
     pub enabled: bool,
     #[validate(length(min = 1))]
     pub endpoint: String,
     pub push_gateway: Option<String>,
     pub collection_interval_seconds: u64,
+    /// Distinct values tracked per metric label; beyond this, values are hashed into overflow buckets
+    #[serde(default = "default_max_label_values")]
+    pub max_label_values: usize,
+}
+
+fn default_max_label_values() -> usize {
+    crate::metrics::DEFAULT_MAX_LABEL_VALUES
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+#[cfg_attr(feature = "config-schema", derive(schemars::JsonSchema))]
 pub struct TracingConfig {
     pub enabled: bool,
     #[validate(length(min = 1))]
     pub service_name: String,
-    pub jaeger_endpoint: Option<String>,
+    /// OTLP collector; exported to when built with the `otel` feature
+    pub otlp_endpoint: Option<String>,
     pub sampling_rate: f64,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+#[cfg_attr(feature = "config-schema", derive(schemars::JsonSchema))]
 pub struct AlertingConfig {
     pub enabled: bool,
     pub channels: Vec<AlertChannel>,
     pub thresholds: AlertThresholds,
+    #[serde(default = "default_evaluation_interval_seconds")]
+    #[validate(range(min = 1))]
+    pub evaluation_interval_seconds: u64,
+    /// Minimum time between repeated alerts for the same metric
+    #[serde(default = "default_alert_cooldown_seconds")]
+    pub cooldown_seconds: u64,
+}
+
+fn default_evaluation_interval_seconds() -> u64 {
+    60
+}
+
+fn default_alert_cooldown_seconds() -> u64 {
+    300
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "config-schema", derive(schemars::JsonSchema))]
 pub struct AlertChannel {
     pub name: String,
     pub channel_type: String,
@@ -362,6 +1675,7 @@ pub struct AlertChannel {
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+#[cfg_attr(feature = "config-schema", derive(schemars::JsonSchema))]
 pub struct AlertThresholds {
     #[validate(range(min = 0.0))]
     pub error_rate: f64,
@@ -374,6 +1688,7 @@ pub struct AlertThresholds {
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+#[cfg_attr(feature = "config-schema", derive(schemars::JsonSchema))]
 pub struct LoggingConfig {
     #[validate(length(min = 1))]
     pub level: String,
@@ -384,6 +1699,7 @@ pub struct LoggingConfig {
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+#[cfg_attr(feature = "config-schema", derive(schemars::JsonSchema))]
 pub struct TenancyConfig {
     pub enabled: bool,
     #[validate(nested)]
@@ -393,6 +1709,7 @@ pub struct TenancyConfig {
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+#[cfg_attr(feature = "config-schema", derive(schemars::JsonSchema))]
 pub struct ResourceQuotas {
     #[validate(range(min = 1))]
     pub max_requests_per_second: u64,
@@ -405,6 +1722,7 @@ pub struct ResourceQuotas {
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "config-schema", derive(schemars::JsonSchema))]
 pub enum IsolationLevel {
     Strict,
     Moderate,
@@ -412,15 +1730,17 @@ pub enum IsolationLevel {
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+#[cfg_attr(feature = "config-schema", derive(schemars::JsonSchema))]
 pub struct BillingConfig {
     #[validate(length(min = 1))]
     pub provider: String,
     #[validate(url)]
     pub webhook_url: String,
-    pub api_key: String,
+    pub api_key: SecretString,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+#[cfg_attr(feature = "config-schema", derive(schemars::JsonSchema))]
 pub struct DisasterRecoveryConfig {
     #[validate(nested)]
     pub backup: BackupConfig,
@@ -431,6 +1751,7 @@ pub struct DisasterRecoveryConfig {
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+#[cfg_attr(feature = "config-schema", derive(schemars::JsonSchema))]
 pub struct BackupConfig {
     pub enabled: bool,
     pub storage_backends: Vec<String>,
@@ -442,6 +1763,7 @@ pub struct BackupConfig {
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+#[cfg_attr(feature = "config-schema", derive(schemars::JsonSchema))]
 pub struct ReplicationConfig {
     pub enabled: bool,
     pub replicas: Vec<ReplicaConfig>,
@@ -449,6 +1771,7 @@ pub struct ReplicationConfig {
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+#[cfg_attr(feature = "config-schema", derive(schemars::JsonSchema))]
 pub struct ReplicaConfig {
     #[validate(length(min = 1))]
     pub name: String,
@@ -458,12 +1781,14 @@ pub struct ReplicaConfig {
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "config-schema", derive(schemars::JsonSchema))]
 pub enum SyncMode {
     Synchronous,
     Asynchronous,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+#[cfg_attr(feature = "config-schema", derive(schemars::JsonSchema))]
 pub struct FailoverConfig {
     pub enabled: bool,
     #[validate(range(min = 1))]
@@ -474,6 +1799,7 @@ pub struct FailoverConfig {
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+#[cfg_attr(feature = "config-schema", derive(schemars::JsonSchema))]
 pub struct InfrastructureConfig {
     #[validate(nested)]
     pub cloud_providers: Vec<CloudProviderConfig>,
@@ -484,6 +1810,7 @@ pub struct InfrastructureConfig {
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+#[cfg_attr(feature = "config-schema", derive(schemars::JsonSchema))]
 pub struct CloudProviderConfig {
     #[validate(length(min = 1))]
     pub name: String,
@@ -493,6 +1820,7 @@ pub struct CloudProviderConfig {
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+#[cfg_attr(feature = "config-schema", derive(schemars::JsonSchema))]
 pub struct AutoScalingConfig {
     pub enabled: bool,
     #[validate(range(min = 1))]
@@ -503,6 +1831,7 @@ pub struct AutoScalingConfig {
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+#[cfg_attr(feature = "config-schema", derive(schemars::JsonSchema))]
 pub struct ScalingMetric {
     #[validate(length(min = 1))]
     pub name: String,
@@ -513,6 +1842,7 @@ pub struct ScalingMetric {
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+#[cfg_attr(feature = "config-schema", derive(schemars::JsonSchema))]
 pub struct DeploymentConfig {
     #[validate(length(min = 1))]
     pub strategy: String,
@@ -522,6 +1852,7 @@ pub struct DeploymentConfig {
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+#[cfg_attr(feature = "config-schema", derive(schemars::JsonSchema))]
 pub struct BlueGreenConfig {
     #[validate(range(min = 1))]
     pub health_check_timeout_seconds: u64,
@@ -530,6 +1861,7 @@ pub struct BlueGreenConfig {
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+#[cfg_attr(feature = "config-schema", derive(schemars::JsonSchema))]
 pub struct CanaryConfig {
     #[validate(range(min = 1, max = 100))]
     pub initial_traffic_percent: u8,
@@ -540,6 +1872,7 @@ pub struct CanaryConfig {
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+#[cfg_attr(feature = "config-schema", derive(schemars::JsonSchema))]
 pub struct RollbackConfig {
     pub automatic: bool,
     #[validate(range(min = 1))]
@@ -551,11 +1884,22 @@ pub struct RollbackConfig {
 impl Default for EnterpriseConfig {
     fn default() -> Self {
         Self {
+            schema_version: migration::CONFIG_SCHEMA_VERSION,
             server: ServerConfig {
                 port: 8081,
                 host: "0.0.0.0".to_string(),
-                worker_threads: 4,
+                worker_threads: None,
+                max_blocking_threads: None,
                 tls: None,
+                trusted_proxies: Vec::new(),
+                ipv6_prefix_length: default_ipv6_prefix_length(),
+                max_body_bytes: default_max_body_bytes(),
+                request_timeout_ms: default_request_timeout_ms(),
+                route_request_limits: Vec::new(),
+                shutdown_timeout_seconds: default_shutdown_timeout_seconds(),
+                grpc: GrpcConfig::default(),
+                cors: CorsConfig::default(),
+                compression: CompressionConfig::default(),
             },
             security: SecurityConfig {
                 audit: AuditConfig {
@@ -563,6 +1907,8 @@ impl Default for EnterpriseConfig {
                     storage_backend: "redis".to_string(),
                     digital_signing: true,
                     retention_days: 90,
+                    file: FileAuditConfig::default(),
+                    buffer: AuditBufferConfig::default(),
                 },
                 threat_detection: ThreatDetectionConfig {
                     enabled: true,
@@ -570,6 +1916,17 @@ impl Default for EnterpriseConfig {
                     ip_reputation: true,
                     ml_engine: false,
                     threat_threshold: 0.7,
+                    analyzer_weights: HashMap::new(),
+                    max_total_analysis_time_ms: default_max_total_analysis_time_ms(),
+                    mode: ThreatAnalysisMode::default(),
+                    async_queue_size: default_async_queue_size(),
+                    failure_mode: SecurityFailureMode::default(),
+                    ml: MlEngineConfig::default(),
+                    geoip: GeoIpConfig::default(),
+                    asn_reputation: AsnReputationConfig::default(),
+                    tls_fingerprint: TlsFingerprintConfig::default(),
+                    behavior_patterns: BehaviorPatternsConfig::default(),
+                    challenge: ChallengeConfig::default(),
                 },
                 secrets: SecretConfig {
                     provider: "env".to_string(),
@@ -583,6 +1940,10 @@ impl Default for EnterpriseConfig {
                     data_residency: None,
                     retention_days: 30,
                 },
+                request_signing: RequestSigningConfig::default(),
+                ip_allowlist: IpAllowlistConfig::default(),
+                ip_denylist: IpDenylistConfig::default(),
+                siem: crate::security::siem_integration::SiemConfig::default(),
             },
             observability: ObservabilityConfig {
                 metrics: MetricsConfig {
@@ -590,11 +1951,12 @@ impl Default for EnterpriseConfig {
                     endpoint: "/metrics".to_string(),
                     push_gateway: None,
                     collection_interval_seconds: 15,
+                    max_label_values: default_max_label_values(),
                 },
                 tracing: TracingConfig {
                     enabled: true,
                     service_name: "ratewatch".to_string(),
-                    jaeger_endpoint: None,
+                    otlp_endpoint: None,
                     sampling_rate: 0.1,
                 },
                 alerting: AlertingConfig {
@@ -606,6 +1968,8 @@ impl Default for EnterpriseConfig {
                         cpu_usage: 80.0,
                         memory_usage: 85.0,
                     },
+                    evaluation_interval_seconds: default_evaluation_interval_seconds(),
+                    cooldown_seconds: default_alert_cooldown_seconds(),
                 },
                 logging: LoggingConfig {
                     level: "info".to_string(),
@@ -613,6 +1977,7 @@ impl Default for EnterpriseConfig {
                     structured: true,
                     file_path: None,
                 },
+                analytics: AnalyticsConfig::default(),
             },
             tenancy: TenancyConfig {
                 enabled: false,
@@ -664,6 +2029,9 @@ impl Default for EnterpriseConfig {
                     },
                 },
             },
+            rate_limiting: RateLimitingConfig::default(),
+            redis: RedisConfig::default(),
+            config_sources: ConfigSourcesConfig::default(),
         }
     }
 }
@@ -673,14 +2041,50 @@ impl TryFrom<ConfigMap> for EnterpriseConfig {
 
     fn try_from(config_map: ConfigMap) -> Result<Self> {
         // Convert flattened config map to nested structure
-        let nested_config = Self::unflatten_config(config_map)?;
-        
-        serde_json::from_value(nested_config)
-            .context("Failed to deserialize configuration")
+        let mut nested_config = Self::unflatten_config(config_map)?;
+        migration::migrate(&mut nested_config)?;
+
+        let config = EnterpriseConfig::deserialize(&nested_config)
+            .context("Failed to deserialize configuration");
+
+        // Resolved secrets now live in `SecretString` fields; don't leave plaintext copies behind
+        zeroize_value(&mut nested_config);
+        config
     }
 }
 
 impl EnterpriseConfig {
+    /// JSON Schema for the full config, including the `validator` constraints
+    #[cfg(feature = "config-schema")]
+    pub fn json_schema() -> serde_json::Value {
+        serde_json::to_value(schemars::schema_for!(EnterpriseConfig)).expect("config schema serializes")
+    }
+
+    /// Dotted keys whose values differ between two configs, sorted.
+    ///
+    /// Secrets serialize as `***`, so a rotated secret on its own isn't listed.
+    pub fn changed_keys(&self, other: &EnterpriseConfig) -> Vec<String> {
+        let leaves = |config: &EnterpriseConfig| -> HashMap<String, serde_json::Value> {
+            let mut out = Vec::new();
+            if let Ok(serde_json::Value::Object(map)) = serde_json::to_value(config) {
+                for (key, value) in map {
+                    flatten_leaves(key, value, &mut out);
+                }
+            }
+            out.into_iter().collect()
+        };
+
+        let (before, after) = (leaves(self), leaves(other));
+        let mut changed: Vec<String> = before
+            .keys()
+            .chain(after.keys().filter(|key| !before.contains_key(*key)))
+            .filter(|key| before.get(*key) != after.get(*key))
+            .cloned()
+            .collect();
+        changed.sort();
+        changed
+    }
+
     fn unflatten_config(config_map: ConfigMap) -> Result<serde_json::Value> {
         let mut nested = serde_json::Map::new();
         
@@ -715,14 +2119,332 @@ impl EnterpriseConfig {
     }
 }
 
+const SECRET_REF_PREFIX: &str = "secret://";
+
+/// Collect every `secret://` string under `value` with its dotted/indexed path
+fn collect_secret_refs<'a>(
+    path: String,
+    value: &'a mut serde_json::Value,
+    out: &mut Vec<(String, &'a mut serde_json::Value)>,
+) {
+    if value.as_str().is_some_and(|s| s.starts_with(SECRET_REF_PREFIX)) {
+        out.push((path, value));
+        return;
+    }
+
+    match value {
+        serde_json::Value::Object(map) => {
+            for (key, child) in map.iter_mut() {
+                collect_secret_refs(format!("{path}.{key}"), child, out);
+            }
+        }
+        serde_json::Value::Array(items) => {
+            for (index, child) in items.iter_mut().enumerate() {
+                collect_secret_refs(format!("{path}[{index}]"), child, out);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Key that selects the merge strategy; read from the sources themselves
+const MERGE_STRATEGY_KEY: &str = "config_sources.merge_strategy";
+
+/// Merge source maps in order using the strategy the sources ask for.
+///
+/// The last source that sets `config_sources.merge_strategy` decides, so a
+/// file can opt back into `override` without code changes.
+fn merge_sources(configs: Vec<ConfigMap>) -> ConfigMap {
+    let strategy = configs
+        .iter()
+        .rev()
+        .find_map(|config| config.get(MERGE_STRATEGY_KEY))
+        .and_then(|value| serde_json::from_value(value.clone()).ok())
+        .unwrap_or_default();
+
+    let mut merged = ConfigMap::new();
+    for config in configs {
+        merged.merge(config, strategy);
+    }
+    merged
+}
+
 trait ConfigMapExt {
-    fn merge(&mut self, other: ConfigMap);
+    fn merge(&mut self, other: ConfigMap, strategy: MergeStrategy);
 }
 
 impl ConfigMapExt for ConfigMap {
-    fn merge(&mut self, other: ConfigMap) {
-        for (key, value) in other {
-            self.insert(key, value);
+    fn merge(&mut self, other: ConfigMap, strategy: MergeStrategy) {
+        match strategy {
+            MergeStrategy::Override => {
+                for (key, value) in other {
+                    self.insert(key, value);
+                }
+            }
+            MergeStrategy::DeepMerge => {
+                for (key, value) in other {
+                    let mut leaves = Vec::new();
+                    flatten_leaves(key, value, &mut leaves);
+                    for (key, value) in leaves {
+                        // A leaf replaces anything it overlaps: a scalar parent
+                        // or the children of what is now a scalar
+                        self.retain(|existing, _| {
+                            !(key.starts_with(&format!("{existing}.")) || existing.starts_with(&format!("{key}.")))
+                        });
+                        self.insert(key, value);
+                    }
+                }
+            }
         }
     }
-}
\ No newline at end of file
+}
+
+/// Split object values into dotted leaf keys; arrays and empty objects are leaves
+fn flatten_leaves(key: String, value: serde_json::Value, out: &mut Vec<(String, serde_json::Value)>) {
+    match value {
+        serde_json::Value::Object(map) if !map.is_empty() => {
+            for (child, value) in map {
+                flatten_leaves(format!("{key}.{child}"), value, out);
+            }
+        }
+        value => out.push((key, value)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn source(values: &[(&str, serde_json::Value)]) -> ConfigMap {
+        values.iter().map(|(k, v)| (k.to_string(), v.clone())).collect()
+    }
+
+    #[tokio::test]
+    async fn test_resolved_secrets_are_redacted() {
+        std::env::set_var("RATEWATCH_TEST_VAULT_TOKEN", "s.plaintext-token");
+
+        let defaults = serde_json::to_value(EnterpriseConfig::default())
+            .unwrap()
+            .as_object()
+            .unwrap()
+            .clone()
+            .into_iter()
+            .collect::<ConfigMap>();
+        std::env::set_var("RATEWATCH_TEST_BILLING_KEY", "sk_plaintext-billing");
+        std::env::set_var("RATEWATCH_TEST_REDIS_URL", "redis://:plaintext-password@cache:6379");
+        let vault = source(&[
+            (
+                "security.secrets.vault_config",
+                json!({ "address": "https://vault.example.com", "token": "secret://env:RATEWATCH_TEST_VAULT_TOKEN" }),
+            ),
+            (
+                "tenancy.billing_integration",
+                json!({
+                    "provider": "stripe",
+                    "webhook_url": "https://billing.example.com/hook",
+                    "api_key": "secret://env:RATEWATCH_TEST_BILLING_KEY",
+                }),
+            ),
+            ("redis.url", json!("secret://env:RATEWATCH_TEST_REDIS_URL")),
+        ]);
+        let mut merged = merge_sources(vec![defaults, vault]);
+        ConfigManager::resolve_secrets(&mut merged, &SecretManager::new().await.unwrap())
+            .await
+            .unwrap();
+        let config = EnterpriseConfig::try_from(merged).unwrap();
+
+        let token = config.security.secrets.vault_config.as_ref().unwrap().token.as_ref().unwrap();
+        assert_eq!(token.expose_secret(), "s.plaintext-token");
+        let billing = config.tenancy.billing_integration.as_ref().unwrap();
+        assert_eq!(billing.api_key.expose_secret(), "sk_plaintext-billing");
+        assert_eq!(config.redis.url.expose_secret(), "redis://:plaintext-password@cache:6379");
+
+        let debug = format!("{config:?}");
+        assert!(debug.contains("token: Some(***)"));
+        assert!(debug.contains("api_key: ***"));
+        assert!(debug.contains("url: ***"));
+        assert!(!debug.contains("plaintext"));
+
+        let dumped = serde_json::to_string(&config).unwrap();
+        assert!(dumped.contains(r#""token":"***""#));
+        assert!(dumped.contains(r#""api_key":"***""#));
+        assert!(!dumped.contains("plaintext"));
+    }
+
+    #[tokio::test]
+    async fn test_nested_and_array_secrets_are_resolved() {
+        std::env::set_var("RATEWATCH_TEST_NESTED_TOKEN", "s.nested");
+        std::env::set_var("RATEWATCH_TEST_REPLICA_URL", "https://replica.example.com");
+        let secret_manager = SecretManager::new().await.unwrap();
+
+        let mut config = source(&[
+            (
+                "security",
+                json!({ "secrets": { "vault_config": { "token": "secret://env:RATEWATCH_TEST_NESTED_TOKEN" } } }),
+            ),
+            (
+                "disaster_recovery.replication.replicas",
+                json!([{ "name": "east", "endpoint": "secret://env:RATEWATCH_TEST_REPLICA_URL" }]),
+            ),
+        ]);
+        ConfigManager::resolve_secrets(&mut config, &secret_manager).await.unwrap();
+        assert_eq!(config["security"]["secrets"]["vault_config"]["token"], json!("s.nested"));
+        assert_eq!(
+            config["disaster_recovery.replication.replicas"][0]["endpoint"],
+            json!("https://replica.example.com")
+        );
+        assert_eq!(config["disaster_recovery.replication.replicas"][0]["name"], json!("east"));
+
+        let mut missing = source(&[(
+            "disaster_recovery.replication.replicas",
+            json!([{ "endpoint": "https://ok.example.com" }, { "endpoint": "secret://env:RATEWATCH_TEST_UNSET_SECRET" }]),
+        )]);
+        let err = ConfigManager::resolve_secrets(&mut missing, &secret_manager).await.unwrap_err();
+        assert!(format!("{err:#}").contains("'disaster_recovery.replication.replicas[1].endpoint'"), "{err:#}");
+    }
+
+    #[test]
+    fn test_v1_document_is_migrated_on_load() {
+        // The shipped config as it looked before schema_version existed
+        let current: serde_json::Value = toml::from_str(include_str!("../../config.toml")).unwrap();
+        let mut v1 = current.as_object().unwrap().clone();
+        v1.remove("schema_version");
+        v1["observability"]["tracing"]["jaeger_endpoint"] = json!("http://collector:4317");
+
+        let merged = merge_sources(vec![v1.into_iter().collect()]);
+        let config = EnterpriseConfig::try_from(merged).unwrap();
+        assert_eq!(config.schema_version, migration::CONFIG_SCHEMA_VERSION);
+        assert_eq!(config.observability.tracing.otlp_endpoint.as_deref(), Some("http://collector:4317"));
+
+        let newer = source(&[("schema_version", json!(migration::CONFIG_SCHEMA_VERSION + 1))]);
+        let err = EnterpriseConfig::try_from(newer).unwrap_err();
+        assert!(format!("{err:#}").contains("newer than this build supports"));
+    }
+
+    #[test]
+    fn test_siem_settings_are_read_from_config() {
+        let shipped: serde_json::Value = toml::from_str(include_str!("../../config.toml")).unwrap();
+        let load = |document: &serde_json::Value| {
+            let merged = merge_sources(vec![document.as_object().unwrap().clone().into_iter().collect()]);
+            EnterpriseConfig::try_from(merged).unwrap()
+        };
+
+        // Left out, the integration is off
+        assert!(!load(&shipped).security.siem.enabled);
+
+        let mut document = shipped.clone();
+        document["security"]["siem"] = json!({
+            "enabled": true,
+            "batch_size": 50,
+            "flush_interval_seconds": 5,
+            "max_queue_size": 2000,
+            "retry_attempts": 2,
+            "providers": [{
+                "name": "webhook",
+                "provider_type": "Webhook",
+                "enabled": true,
+                "config": { "url": "https://siem.example.com/events" },
+                "event_filters": [{ "field": "endpoint", "operator": "Regex", "value": "^/v1/admin/" }]
+            }]
+        });
+        let siem = load(&document).security.siem;
+        assert!(siem.enabled);
+        assert_eq!(siem.batch_size, 50);
+        assert_eq!(siem.max_queue_size, 2000);
+        assert_eq!(siem.providers.len(), 1);
+        assert_eq!(siem.providers[0].event_filters[0].value, "^/v1/admin/");
+        assert_eq!(siem.compile_event_filters().unwrap()["webhook"].len(), 1);
+    }
+
+    #[test]
+    fn test_siem_delivery_settings_apply_on_their_own() {
+        let mut document: serde_json::Value = toml::from_str(include_str!("../../config.toml")).unwrap();
+        document["security"]["siem"] = json!({
+            "enabled": true,
+            "max_queue_size": 500,
+            "queue_overflow": "drop_oldest",
+            "retry_attempts": 5,
+            "retry_delay_ms": 250
+        });
+        let merged = merge_sources(vec![document.as_object().unwrap().clone().into_iter().collect()]);
+        let siem = EnterpriseConfig::try_from(merged).unwrap().security.siem;
+
+        assert_eq!(siem.max_queue_size, 500);
+        assert_eq!(siem.queue_overflow, crate::security::siem_queue::QueueOverflowPolicy::DropOldest);
+        assert_eq!(siem.retry_attempts, 5);
+        assert_eq!(siem.retry_delay_ms, 250);
+        // The rest keep their defaults
+        let defaults = crate::security::siem_integration::SiemConfig::default();
+        assert_eq!(siem.batch_size, defaults.batch_size);
+        assert_eq!(siem.flush_interval_seconds, defaults.flush_interval_seconds);
+        assert!(siem.providers.is_empty());
+    }
+
+    #[test]
+    fn test_deep_merge_keeps_disjoint_sub_keys() {
+        // Env-style dotted key and a file that provides the parent as an object
+        let env = source(&[("server.port", json!(9000))]);
+        let file = source(&[("server", json!({ "host": "10.0.0.1", "tls": { "enabled": false } }))]);
+
+        let merged = merge_sources(vec![env, file]);
+        assert_eq!(merged["server.port"], json!(9000));
+        assert_eq!(merged["server.host"], json!("10.0.0.1"));
+        assert_eq!(merged["server.tls.enabled"], json!(false));
+
+        let nested = EnterpriseConfig::unflatten_config(merged).unwrap();
+        assert_eq!(nested["server"]["port"], json!(9000));
+        assert_eq!(nested["server"]["host"], json!("10.0.0.1"));
+    }
+
+    #[test]
+    fn test_deep_merge_later_source_wins_per_leaf() {
+        let first = source(&[("server", json!({ "host": "a", "port": 1 }))]);
+        let second = source(&[("server", json!({ "port": 2 }))]);
+
+        let merged = merge_sources(vec![first, second]);
+        assert_eq!(merged["server.host"], json!("a"));
+        assert_eq!(merged["server.port"], json!(2));
+
+        // A scalar replaces an object and the other way round
+        let merged = merge_sources(vec![
+            source(&[("tags", json!({ "team": "core" }))]),
+            source(&[("tags", json!("none"))]),
+        ]);
+        assert_eq!(merged.len(), 1);
+        assert_eq!(merged["tags"], json!("none"));
+    }
+
+    #[test]
+    fn test_changed_keys() {
+        let before = EnterpriseConfig::default();
+        let mut after = before.clone();
+        after.server.port = 9090;
+        after.redis.cluster_nodes = vec!["redis://10.0.0.1:6379".to_string()];
+
+        assert_eq!(before.changed_keys(&after), vec!["redis.cluster_nodes", "server.port"]);
+        assert!(before.changed_keys(&before).is_empty());
+    }
+
+    #[test]
+    fn test_override_strategy_replaces_objects_wholesale() {
+        let first = source(&[("server", json!({ "host": "a", "port": 1 }))]);
+        let second = source(&[
+            ("server", json!({ "port": 2 })),
+            (MERGE_STRATEGY_KEY, json!("override")),
+        ]);
+
+        let merged = merge_sources(vec![first, second]);
+        assert_eq!(merged["server"], json!({ "port": 2 }));
+    }
+    #[cfg(feature = "config-schema")]
+    #[test]
+    fn test_schema_includes_validation_constraints() {
+        let schema = EnterpriseConfig::json_schema();
+
+        assert_eq!(schema["properties"]["server"]["$ref"], "#/definitions/ServerConfig");
+        let port = &schema["definitions"]["ServerConfig"]["properties"]["port"];
+        assert_eq!(port["minimum"].as_f64(), Some(1024.0));
+        assert_eq!(port["maximum"].as_f64(), Some(65535.0));
+    }
+}
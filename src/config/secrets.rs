@@ -1,7 +1,101 @@
 use anyhow::{Context, Result};
 use async_trait::async_trait;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::collections::HashMap;
 use std::env;
+use zeroize::Zeroize;
+
+/// What `Debug`, `Display` and `Serialize` print in place of a secret
+pub const REDACTED: &str = "***";
+
+/// A resolved secret value.
+///
+/// The plaintext is only reachable through `expose_secret`. Formatting and
+/// serializing print `***`, so a secret can't leak into logs or config dumps
+/// by accident, and the memory is zeroed when the value is dropped.
+/// Deserializing reads the plaintext as a normal string.
+#[derive(Clone, Default, PartialEq, Eq)]
+pub struct SecretString(String);
+
+impl SecretString {
+    pub fn new(secret: String) -> Self {
+        Self(secret)
+    }
+
+    pub fn expose_secret(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<String> for SecretString {
+    fn from(secret: String) -> Self {
+        Self(secret)
+    }
+}
+
+impl From<&str> for SecretString {
+    fn from(secret: &str) -> Self {
+        Self(secret.to_string())
+    }
+}
+
+impl Drop for SecretString {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+impl std::fmt::Debug for SecretString {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(REDACTED)
+    }
+}
+
+impl std::fmt::Display for SecretString {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(REDACTED)
+    }
+}
+
+impl Serialize for SecretString {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.serialize_str(REDACTED)
+    }
+}
+
+impl<'de> Deserialize<'de> for SecretString {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        String::deserialize(deserializer).map(Self)
+    }
+}
+
+/// A plain string in the schema, marked `writeOnly` since it never reads back
+#[cfg(feature = "config-schema")]
+impl schemars::JsonSchema for SecretString {
+    fn schema_name() -> String {
+        "SecretString".to_string()
+    }
+
+    fn is_referenceable() -> bool {
+        false
+    }
+
+    fn json_schema(gen: &mut schemars::gen::SchemaGenerator) -> schemars::schema::Schema {
+        let mut schema = <String as schemars::JsonSchema>::json_schema(gen).into_object();
+        schema.metadata().write_only = true;
+        schema.into()
+    }
+}
+
+/// Zero every string in `value`, e.g. a config map that held resolved secrets
+pub(crate) fn zeroize_value(value: &mut serde_json::Value) {
+    match value {
+        serde_json::Value::String(s) => s.zeroize(),
+        serde_json::Value::Array(items) => items.iter_mut().for_each(zeroize_value),
+        serde_json::Value::Object(map) => map.values_mut().for_each(zeroize_value),
+        _ => {}
+    }
+}
 
 /// Secret manager with pluggable providers
 pub struct SecretManager {
@@ -68,7 +162,7 @@ impl SecretManager {
         })
     }
 
-    pub async fn get_secret(&self, key: &str) -> Result<String> {
+    pub async fn get_secret(&self, key: &str) -> Result<SecretString> {
         // Parse provider from key if specified (e.g., "vault:my-secret")
         let (provider_name, secret_key) = if key.contains(':') {
             let parts: Vec<&str> = key.splitn(2, ':').collect();
@@ -107,7 +201,7 @@ impl SecretManager {
 /// Secret provider trait
 #[async_trait]
 pub trait SecretProvider: Send + Sync {
-    async fn get_secret(&self, key: &str) -> Result<String>;
+    async fn get_secret(&self, key: &str) -> Result<SecretString>;
     async fn rotate_secret(&self, key: &str) -> Result<()>;
     fn name(&self) -> &str;
 }
@@ -123,8 +217,9 @@ impl EnvSecretProvider {
 
 #[async_trait]
 impl SecretProvider for EnvSecretProvider {
-    async fn get_secret(&self, key: &str) -> Result<String> {
+    async fn get_secret(&self, key: &str) -> Result<SecretString> {
         env::var(key)
+            .map(SecretString::new)
             .with_context(|| format!("Environment variable '{}' not found", key))
     }
 
@@ -179,7 +274,7 @@ impl VaultSecretProvider {
 
 #[async_trait]
 impl SecretProvider for VaultSecretProvider {
-    async fn get_secret(&self, key: &str) -> Result<String> {
+    async fn get_secret(&self, key: &str) -> Result<SecretString> {
         let client = self.client.as_ref()
             .ok_or_else(|| anyhow::anyhow!("Vault client not initialized"))?;
 
@@ -189,10 +284,10 @@ impl SecretProvider for VaultSecretProvider {
         // Extract the secret value (assuming it's stored under a "value" key)
         if let Some(data) = secret.data {
             if let Some(value) = data.get("value") {
-                Ok(value.clone())
+                Ok(SecretString::from(value.as_str()))
             } else if let Some((_, value)) = data.iter().next() {
                 // If no "value" key, use the first key-value pair
-                Ok(value.clone())
+                Ok(SecretString::from(value.as_str()))
             } else {
                 Err(anyhow::anyhow!("Secret '{}' has no data", key))
             }
@@ -223,7 +318,7 @@ pub struct AwsSecretsProvider {
 
 impl AwsSecretsProvider {
     pub async fn new() -> Result<Self> {
-        let config = aws_config::load_from_env().await;
+        let config = aws_config::load_defaults(aws_config::BehaviorVersion::latest()).await;
         let client = aws_sdk_secretsmanager::Client::new(&config);
 
         Ok(Self {
@@ -234,7 +329,7 @@ impl AwsSecretsProvider {
 
 #[async_trait]
 impl SecretProvider for AwsSecretsProvider {
-    async fn get_secret(&self, key: &str) -> Result<String> {
+    async fn get_secret(&self, key: &str) -> Result<SecretString> {
         let client = self.client.as_ref()
             .ok_or_else(|| anyhow::anyhow!("AWS Secrets Manager client not initialized"))?;
 
@@ -247,7 +342,7 @@ impl SecretProvider for AwsSecretsProvider {
 
         response.secret_string()
             .ok_or_else(|| anyhow::anyhow!("Secret '{}' has no string value", key))
-            .map(|s| s.to_string())
+            .map(SecretString::from)
     }
 
     async fn rotate_secret(&self, key: &str) -> Result<()> {
@@ -293,7 +388,7 @@ impl AzureKeyVaultProvider {
 
 #[async_trait]
 impl SecretProvider for AzureKeyVaultProvider {
-    async fn get_secret(&self, key: &str) -> Result<String> {
+    async fn get_secret(&self, key: &str) -> Result<SecretString> {
         // Azure Key Vault implementation would go here
         tracing::warn!("Azure Key Vault provider not fully implemented");
         Err(anyhow::anyhow!("Azure Key Vault provider not implemented"))
@@ -356,8 +451,16 @@ mod aws_config {
     use anyhow::Result;
     
     pub struct SdkConfig;
-    
-    pub async fn load_from_env() -> SdkConfig {
+
+    pub struct BehaviorVersion;
+
+    impl BehaviorVersion {
+        pub fn latest() -> Self {
+            Self
+        }
+    }
+
+    pub async fn load_defaults(_version: BehaviorVersion) -> SdkConfig {
         SdkConfig
     }
 }
@@ -421,4 +524,30 @@ mod aws_sdk_secretsmanager {
 #[cfg(not(feature = "azure"))]
 mod azure_security_keyvault {
     pub struct KeyVaultClient;
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_secret_string_is_redacted() {
+        let secret = SecretString::from("hunter2-but-longer");
+        assert_eq!(secret.expose_secret(), "hunter2-but-longer");
+        assert_eq!(format!("{secret:?}"), REDACTED);
+        assert_eq!(secret.to_string(), REDACTED);
+        assert_eq!(serde_json::to_value(&secret).unwrap(), json!("***"));
+
+        // Reading config still yields the real value
+        let parsed: SecretString = serde_json::from_value(json!("hunter2-but-longer")).unwrap();
+        assert_eq!(parsed, secret);
+    }
+
+    #[test]
+    fn test_zeroize_value_clears_nested_strings() {
+        let mut value = json!({ "vault": { "token": "s.abc", "ttl": 60 }, "keys": ["k1"] });
+        zeroize_value(&mut value);
+        assert_eq!(value, json!({ "vault": { "token": "", "ttl": 60 }, "keys": [""] }));
+    }
+}
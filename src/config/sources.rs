@@ -1,10 +1,14 @@
 use super::{ConfigMap, ConfigSource, ConfigChange, ConfigChangeType};
+use super::k8s_api::{K8sForbidden, K8sObjectApi, K8sObjectKind};
 use anyhow::{Context, Result};
 use async_trait::async_trait;
 use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::env;
-use std::path::Path;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
 use tokio::fs;
 use tokio::sync::mpsc;
 
@@ -111,21 +115,7 @@ impl ConfigSource for FileConfigSource {
         let content = fs::read_to_string(&self.file_path).await
             .with_context(|| format!("Failed to read config file: {}", self.file_path))?;
 
-        let config: ConfigMap = if self.file_path.ends_with(".toml") {
-            let toml_value: toml::Value = toml::from_str(&content)
-                .with_context(|| format!("Failed to parse TOML config: {}", self.file_path))?;
-            self.flatten_toml_value("", &toml_value)
-        } else if self.file_path.ends_with(".yaml") || self.file_path.ends_with(".yml") {
-            let yaml_value: serde_yaml::Value = serde_yaml::from_str(&content)
-                .with_context(|| format!("Failed to parse YAML config: {}", self.file_path))?;
-            self.flatten_yaml_value("", &yaml_value)
-        } else if self.file_path.ends_with(".json") {
-            let json_value: serde_json::Value = serde_json::from_str(&content)
-                .with_context(|| format!("Failed to parse JSON config: {}", self.file_path))?;
-            self.flatten_json_value("", &json_value)
-        } else {
-            return Err(anyhow::anyhow!("Unsupported config file format: {}", self.file_path));
-        };
+        let config = Self::parse_document(&self.file_path, &content)?;
 
         tracing::debug!("Loaded {} configuration values from file: {}", 
             config.len(), self.file_path);
@@ -183,7 +173,27 @@ impl ConfigSource for FileConfigSource {
 }
 
 impl FileConfigSource {
-    fn flatten_toml_value(&self, prefix: &str, value: &toml::Value) -> ConfigMap {
+    /// Parse a TOML, YAML or JSON document, picked by `name`'s extension, into
+    /// dotted keys
+    fn parse_document(name: &str, content: &str) -> Result<ConfigMap> {
+        if name.ends_with(".toml") {
+            let toml_value: toml::Value = toml::from_str(content)
+                .with_context(|| format!("Failed to parse TOML config: {}", name))?;
+            Ok(Self::flatten_toml_value("", &toml_value))
+        } else if name.ends_with(".yaml") || name.ends_with(".yml") {
+            let yaml_value: serde_yaml::Value = serde_yaml::from_str(content)
+                .with_context(|| format!("Failed to parse YAML config: {}", name))?;
+            Ok(Self::flatten_yaml_value("", &yaml_value))
+        } else if name.ends_with(".json") {
+            let json_value: serde_json::Value = serde_json::from_str(content)
+                .with_context(|| format!("Failed to parse JSON config: {}", name))?;
+            Ok(Self::flatten_json_value("", &json_value))
+        } else {
+            Err(anyhow::anyhow!("Unsupported config file format: {}", name))
+        }
+    }
+
+    fn flatten_toml_value(prefix: &str, value: &toml::Value) -> ConfigMap {
         let mut config = ConfigMap::new();
         
         match value {
@@ -194,7 +204,7 @@ impl FileConfigSource {
                     } else {
                         format!("{}.{}", prefix, key)
                     };
-                    config.extend(self.flatten_toml_value(&new_prefix, val));
+                    config.extend(Self::flatten_toml_value(&new_prefix, val));
                 }
             }
             _ => {
@@ -206,7 +216,7 @@ impl FileConfigSource {
         config
     }
 
-    fn flatten_yaml_value(&self, prefix: &str, value: &serde_yaml::Value) -> ConfigMap {
+    fn flatten_yaml_value(prefix: &str, value: &serde_yaml::Value) -> ConfigMap {
         let mut config = ConfigMap::new();
         
         match value {
@@ -218,7 +228,7 @@ impl FileConfigSource {
                         } else {
                             format!("{}.{}", prefix, key_str)
                         };
-                        config.extend(self.flatten_yaml_value(&new_prefix, val));
+                        config.extend(Self::flatten_yaml_value(&new_prefix, val));
                     }
                 }
             }
@@ -231,7 +241,7 @@ impl FileConfigSource {
         config
     }
 
-    fn flatten_json_value(&self, prefix: &str, value: &serde_json::Value) -> ConfigMap {
+    fn flatten_json_value(prefix: &str, value: &serde_json::Value) -> ConfigMap {
         let mut config = ConfigMap::new();
         
         match value {
@@ -242,7 +252,7 @@ impl FileConfigSource {
                     } else {
                         format!("{}.{}", prefix, key)
                     };
-                    config.extend(self.flatten_json_value(&new_prefix, val));
+                    config.extend(Self::flatten_json_value(&new_prefix, val));
                 }
             }
             _ => {
@@ -254,11 +264,25 @@ impl FileConfigSource {
     }
 }
 
-/// HashiCorp Vault configuration source
+/// Default time between Vault metadata polls
+pub const DEFAULT_VAULT_POLL_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Default upper bound on the random delay added to each poll
+pub const DEFAULT_VAULT_POLL_JITTER: Duration = Duration::from_secs(10);
+
+/// HashiCorp Vault configuration source.
+///
+/// Vault has no change notifications, so `watch_changes` polls the KV v2
+/// metadata of the config path and reports a change only when its version
+/// moves. Each poll waits the interval plus a random jitter so many replicas
+/// don't hit Vault in lockstep. Interval and jitter come from
+/// `VAULT_POLL_INTERVAL_SECONDS` and `VAULT_POLL_JITTER_SECONDS`.
 pub struct VaultConfigSource {
-    client: Option<vault::Client>,
+    client: Option<Arc<vault::Client>>,
     mount_path: String,
     secret_path: String,
+    poll_interval: Duration,
+    poll_jitter: Duration,
 }
 
 impl VaultConfigSource {
@@ -285,12 +309,84 @@ impl VaultConfigSource {
             ));
         }
 
+        let seconds = |name: &str, default: Duration| {
+            env::var(name)
+                .ok()
+                .and_then(|value| value.parse().ok())
+                .map(Duration::from_secs)
+                .unwrap_or(default)
+        };
+
         Ok(Self {
-            client: Some(client),
+            client: Some(Arc::new(client)),
             mount_path: env::var("VAULT_MOUNT_PATH").unwrap_or_else(|_| "secret".to_string()),
             secret_path: env::var("VAULT_SECRET_PATH").unwrap_or_else(|_| "ratewatch/config".to_string()),
+            poll_interval: seconds("VAULT_POLL_INTERVAL_SECONDS", DEFAULT_VAULT_POLL_INTERVAL),
+            poll_jitter: seconds("VAULT_POLL_JITTER_SECONDS", DEFAULT_VAULT_POLL_JITTER),
         })
     }
+
+    /// How often to check Vault for a new version of the config path
+    pub fn with_poll_interval(mut self, interval: Duration, jitter: Duration) -> Self {
+        self.poll_interval = interval.max(Duration::from_secs(1));
+        self.poll_jitter = jitter;
+        self
+    }
+}
+
+/// Poll `fetch_version` forever and send a change whenever the version differs
+/// from the last one seen. Stops once the receiver is dropped.
+async fn poll_for_changes<F, Fut, V>(
+    source: &'static str,
+    mut last_version: Option<V>,
+    interval: Duration,
+    jitter: Duration,
+    fetch_version: F,
+    tx: mpsc::Sender<ConfigChange>,
+) where
+    F: Fn() -> Fut,
+    Fut: Future<Output = Result<V>>,
+    V: PartialEq + std::fmt::Display,
+{
+    loop {
+        let delay = {
+            use rand::Rng;
+            let jitter_ms = jitter.as_millis() as u64;
+            interval + Duration::from_millis(rand::thread_rng().gen_range(0..=jitter_ms))
+        };
+        tokio::select! {
+            _ = tokio::time::sleep(delay) => {}
+            _ = tx.closed() => return,
+        }
+
+        let version = match fetch_version().await {
+            Ok(version) => version,
+            Err(e) => {
+                tracing::warn!("Failed to poll {} for config changes: {:#}", source, e);
+                continue;
+            }
+        };
+        if last_version.as_ref() == Some(&version) {
+            continue;
+        }
+
+        tracing::info!(
+            "{} config changed (version {} -> {})",
+            source,
+            last_version.as_ref().map_or_else(|| "unknown".to_string(), |v| v.to_string()),
+            version
+        );
+        last_version = Some(version);
+
+        let change = ConfigChange {
+            source: source.to_string(),
+            change_type: ConfigChangeType::Modified,
+            affected_keys: vec![], // A version bump doesn't say which keys changed
+        };
+        if tx.send(change).await.is_err() {
+            return;
+        }
+    }
 }
 
 #[async_trait]
@@ -317,10 +413,33 @@ impl ConfigSource for VaultConfigSource {
 
     async fn watch_changes(&self) -> Result<mpsc::Receiver<ConfigChange>> {
         let (tx, rx) = mpsc::channel(100);
-        
-        // Vault doesn't have built-in change notifications, but we could implement polling
-        tracing::debug!("Vault config source doesn't support real-time change watching");
-        
+        let client = self.client.clone()
+            .ok_or_else(|| anyhow::anyhow!("Vault client not initialized"))?;
+        let (mount_path, secret_path) = (self.mount_path.clone(), self.secret_path.clone());
+
+        let fetch_version = move || {
+            let (client, mount_path, secret_path) = (client.clone(), mount_path.clone(), secret_path.clone());
+            async move {
+                vault::kv2::read_metadata(&client, &mount_path, &secret_path)
+                    .await
+                    .map(|metadata| metadata.current_version)
+                    .context("Failed to read config metadata from Vault")
+            }
+        };
+
+        // The version the running config was loaded at; a failure here just
+        // means the first successful poll triggers one reload
+        let initial_version = fetch_version().await.ok();
+        tokio::spawn(poll_for_changes(
+            "vault",
+            initial_version,
+            self.poll_interval,
+            self.poll_jitter,
+            fetch_version,
+            tx,
+        ));
+
+        tracing::debug!("Polling Vault for config changes every {:?}", self.poll_interval);
         Ok(rx)
     }
 
@@ -329,11 +448,156 @@ impl ConfigSource for VaultConfigSource {
     }
 }
 
-/// Kubernetes ConfigMap/Secret configuration source
+/// Default time between S3 object polls
+pub const DEFAULT_S3_POLL_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Default upper bound on the random delay added to each S3 poll
+pub const DEFAULT_S3_POLL_JITTER: Duration = Duration::from_secs(10);
+
+/// Amazon S3 configuration source.
+///
+/// Fetches one object, `S3_CONFIG_KEY` in `S3_CONFIG_BUCKET`, and parses it as
+/// TOML, YAML or JSON depending on the key's extension. Credentials and region
+/// come from the standard AWS chain (environment, profile, IMDS/IRSA);
+/// `S3_CONFIG_REGION` overrides the region. A missing object loads as an empty
+/// map so the other sources still apply. `watch_changes` polls the object's
+/// version ID (or ETag on unversioned buckets) every
+/// `S3_POLL_INTERVAL_SECONDS` plus up to `S3_POLL_JITTER_SECONDS`.
+pub struct S3ConfigSource {
+    client: aws_sdk_s3::Client,
+    bucket: String,
+    key: String,
+    poll_interval: Duration,
+    poll_jitter: Duration,
+}
+
+impl S3ConfigSource {
+    pub async fn new() -> Result<Self> {
+        let bucket = env::var("S3_CONFIG_BUCKET")
+            .context("S3_CONFIG_BUCKET environment variable required for S3 config source")?;
+        let key = env::var("S3_CONFIG_KEY").unwrap_or_else(|_| "ratewatch/config.toml".to_string());
+
+        let mut loader = aws_config::defaults(aws_config::BehaviorVersion::latest());
+        if let Ok(region) = env::var("S3_CONFIG_REGION") {
+            loader = loader.region(aws_config::Region::new(region));
+        }
+        let client = aws_sdk_s3::Client::new(&loader.load().await);
+
+        let seconds = |name: &str, default: Duration| {
+            env::var(name)
+                .ok()
+                .and_then(|value| value.parse().ok())
+                .map(Duration::from_secs)
+                .unwrap_or(default)
+        };
+
+        Ok(Self {
+            client,
+            bucket,
+            key,
+            poll_interval: seconds("S3_POLL_INTERVAL_SECONDS", DEFAULT_S3_POLL_INTERVAL),
+            poll_jitter: seconds("S3_POLL_JITTER_SECONDS", DEFAULT_S3_POLL_JITTER),
+        })
+    }
+
+    /// How often to check the object for a new version
+    pub fn with_poll_interval(mut self, interval: Duration, jitter: Duration) -> Self {
+        self.poll_interval = interval.max(Duration::from_secs(1));
+        self.poll_jitter = jitter;
+        self
+    }
+
+    /// Version ID, falling back to the ETag, or `None` if the object doesn't exist
+    async fn object_version(client: &aws_sdk_s3::Client, bucket: &str, key: &str) -> Result<Option<String>> {
+        match client.head_object().bucket(bucket).key(key).send().await {
+            Ok(head) => Ok(head.version_id().or(head.e_tag()).map(str::to_string)),
+            Err(e) if e.as_service_error().is_some_and(|e| e.is_not_found()) => Ok(None),
+            Err(e) => Err(e).with_context(|| format!("Failed to read metadata of s3://{}/{}", bucket, key)),
+        }
+    }
+}
+
+#[async_trait]
+impl ConfigSource for S3ConfigSource {
+    async fn load_config(&self) -> Result<ConfigMap> {
+        let object = match self.client.get_object().bucket(&self.bucket).key(&self.key).send().await {
+            Ok(object) => object,
+            Err(e) if e.as_service_error().is_some_and(|e| e.is_no_such_key()) => {
+                tracing::warn!("Configuration object s3://{}/{} does not exist", self.bucket, self.key);
+                return Ok(ConfigMap::new());
+            }
+            Err(e) => {
+                return Err(e).with_context(|| format!("Failed to fetch s3://{}/{}", self.bucket, self.key));
+            }
+        };
+
+        let body = object.body.collect().await
+            .with_context(|| format!("Failed to read s3://{}/{}", self.bucket, self.key))?
+            .into_bytes();
+        let content = std::str::from_utf8(&body)
+            .with_context(|| format!("s3://{}/{} is not valid UTF-8", self.bucket, self.key))?;
+
+        let config = FileConfigSource::parse_document(&self.key, content)?;
+        tracing::debug!("Loaded {} configuration values from s3://{}/{}",
+            config.len(), self.bucket, self.key);
+        Ok(config)
+    }
+
+    async fn watch_changes(&self) -> Result<mpsc::Receiver<ConfigChange>> {
+        let (tx, rx) = mpsc::channel(100);
+        let (client, bucket, key) = (self.client.clone(), self.bucket.clone(), self.key.clone());
+
+        // Deleting or creating the object counts as a change too
+        let fetch_version = move || {
+            let (client, bucket, key) = (client.clone(), bucket.clone(), key.clone());
+            async move {
+                Self::object_version(&client, &bucket, &key)
+                    .await
+                    .map(|version| version.unwrap_or_else(|| "missing".to_string()))
+            }
+        };
+
+        let initial_version = fetch_version().await.ok();
+        tokio::spawn(poll_for_changes(
+            "s3",
+            initial_version,
+            self.poll_interval,
+            self.poll_jitter,
+            fetch_version,
+            tx,
+        ));
+
+        tracing::debug!("Polling S3 for config changes every {:?}", self.poll_interval);
+        Ok(rx)
+    }
+
+    fn name(&self) -> &str {
+        "s3"
+    }
+}
+
+/// Default quiet period before a burst of mounted-volume events becomes one reload
+pub const DEFAULT_K8S_DEBOUNCE: Duration = Duration::from_millis(500);
+
+/// Kubernetes ConfigMap/Secret configuration source.
+///
+/// By default reads the volumes the ConfigMap and Secret are mounted as, one
+/// file per key. The kubelet updates a mount by writing a new timestamped
+/// directory and swapping the `..data` symlink, so `watch_changes` watches the
+/// mount directory itself and collapses the burst of events from one update
+/// into a single change once they've been quiet for the debounce period.
+///
+/// With `K8S_CONFIG_MODE=api` (or `with_api`) the objects are instead read
+/// and watched through the Kubernetes API, for pods without the mounts. When
+/// RBAC denies that, the denial is logged and the mounts are used instead.
 pub struct K8sConfigSource {
     namespace: String,
     configmap_name: Option<String>,
     secret_name: Option<String>,
+    configmap_root: PathBuf,
+    secret_root: PathBuf,
+    debounce: Duration,
+    api: Option<Arc<dyn K8sObjectApi>>,
 }
 
 impl K8sConfigSource {
@@ -350,12 +614,91 @@ impl K8sConfigSource {
             ));
         }
 
+        let api = match env::var("K8S_CONFIG_MODE").as_deref() {
+            Ok("api") => Some(Self::default_api().await?),
+            Ok("volume") | Err(_) => None,
+            Ok(other) => return Err(anyhow::anyhow!("Unknown K8S_CONFIG_MODE '{}', expected 'volume' or 'api'", other)),
+        };
+
         Ok(Self {
             namespace,
             configmap_name,
             secret_name,
+            configmap_root: PathBuf::from("/etc/config"),
+            secret_root: PathBuf::from("/etc/secrets"),
+            debounce: DEFAULT_K8S_DEBOUNCE,
+            api,
         })
     }
+
+    #[cfg(feature = "kubernetes")]
+    async fn default_api() -> Result<Arc<dyn K8sObjectApi>> {
+        Ok(Arc::new(super::k8s_api::KubeObjectApi::try_default().await?))
+    }
+
+    #[cfg(not(feature = "kubernetes"))]
+    async fn default_api() -> Result<Arc<dyn K8sObjectApi>> {
+        Err(anyhow::anyhow!("K8S_CONFIG_MODE=api needs the `kubernetes` feature"))
+    }
+
+    /// Read and watch the ConfigMap and Secret through `api` rather than their mounts
+    pub fn with_api(mut self, api: Arc<dyn K8sObjectApi>) -> Self {
+        self.api = Some(api);
+        self
+    }
+
+    /// Directories the ConfigMap and Secret volumes are mounted under
+    /// (default `/etc/config` and `/etc/secrets`)
+    pub fn with_mount_roots(mut self, configmap_root: impl Into<PathBuf>, secret_root: impl Into<PathBuf>) -> Self {
+        self.configmap_root = configmap_root.into();
+        self.secret_root = secret_root.into();
+        self
+    }
+
+    /// How long mounted-volume events must be quiet before reporting a change
+    pub fn with_debounce(mut self, debounce: Duration) -> Self {
+        self.debounce = debounce;
+        self
+    }
+
+    fn mount_dirs(&self) -> Vec<PathBuf> {
+        let configmap = self.configmap_name.as_ref().map(|name| self.configmap_root.join(name));
+        let secret = self.secret_name.as_ref().map(|name| self.secret_root.join(name));
+        configmap.into_iter().chain(secret).collect()
+    }
+
+    fn objects(&self) -> Vec<(K8sObjectKind, &str)> {
+        let configmap = self.configmap_name.as_deref().map(|name| (K8sObjectKind::ConfigMap, name));
+        let secret = self.secret_name.as_deref().map(|name| (K8sObjectKind::Secret, name));
+        configmap.into_iter().chain(secret).collect()
+    }
+}
+
+/// Forward one change per burst of `events`: after the first event, wait until
+/// none has arrived for `quiet` before sending. Stops once either side closes.
+async fn debounce_changes(
+    source: &'static str,
+    mut events: mpsc::Receiver<()>,
+    quiet: Duration,
+    tx: mpsc::Sender<ConfigChange>,
+) {
+    while events.recv().await.is_some() {
+        loop {
+            match tokio::time::timeout(quiet, events.recv()).await {
+                Ok(Some(())) => continue,
+                Ok(None) | Err(_) => break,
+            }
+        }
+
+        let change = ConfigChange {
+            source: source.to_string(),
+            change_type: ConfigChangeType::Modified,
+            affected_keys: vec![], // A volume swap doesn't say which keys changed
+        };
+        if tx.send(change).await.is_err() {
+            return;
+        }
+    }
 }
 
 #[async_trait]
@@ -384,12 +727,48 @@ impl ConfigSource for K8sConfigSource {
     }
 
     async fn watch_changes(&self) -> Result<mpsc::Receiver<ConfigChange>> {
+        if let Some(api) = &self.api {
+            return self.watch_objects(api.as_ref()).await;
+        }
+
         let (tx, rx) = mpsc::channel(100);
-        
-        // Kubernetes watch implementation would go here
-        // For now, we'll just return an empty channel
-        tracing::debug!("Kubernetes config source change watching not yet implemented");
-        
+        let (event_tx, event_rx) = mpsc::channel(100);
+
+        let mut watcher = RecommendedWatcher::new(
+            move |res: notify::Result<Event>| {
+                if let Ok(event) = res {
+                    if !matches!(event.kind, EventKind::Access(_)) {
+                        // A full channel already has a reload pending
+                        let _ = event_tx.try_send(());
+                    }
+                }
+            },
+            notify::Config::default(),
+        )
+        .context("Failed to create Kubernetes volume watcher")?;
+
+        let mut watched = 0;
+        for dir in self.mount_dirs() {
+            // Watch the mount directory, not the files: the symlink swap
+            // replaces them rather than modifying them in place
+            match watcher.watch(&dir, RecursiveMode::NonRecursive) {
+                Ok(()) => watched += 1,
+                Err(e) => tracing::warn!("Failed to watch Kubernetes volume {}: {}", dir.display(), e),
+            }
+        }
+        if watched == 0 {
+            tracing::debug!("No mounted Kubernetes config volumes to watch");
+            return Ok(rx);
+        }
+
+        let debounce = self.debounce;
+        tokio::spawn(async move {
+            // Keep the watcher alive for as long as changes are being forwarded
+            let _watcher = watcher;
+            debounce_changes("kubernetes", event_rx, debounce, tx).await;
+        });
+
+        tracing::debug!("Watching {} Kubernetes config volume(s) in namespace {}", watched, self.namespace);
         Ok(rx)
     }
 
@@ -399,12 +778,74 @@ impl ConfigSource for K8sConfigSource {
 }
 
 impl K8sConfigSource {
+    /// Watch the ConfigMap and Secret through the API, debouncing like the
+    /// volume watch. An object RBAC won't let us watch is logged and left out.
+    async fn watch_objects(&self, api: &dyn K8sObjectApi) -> Result<mpsc::Receiver<ConfigChange>> {
+        let (tx, rx) = mpsc::channel(100);
+        let (event_tx, event_rx) = mpsc::channel(100);
+
+        let mut watched = 0;
+        for (kind, name) in self.objects() {
+            let mut changes = match api.watch(kind, &self.namespace, name).await {
+                Ok(changes) => changes,
+                Err(e) => {
+                    match e.downcast_ref::<K8sForbidden>() {
+                        Some(forbidden) => tracing::error!("Config changes won't be hot-reloaded: {}", forbidden),
+                        None => tracing::warn!("Failed to watch {} {}: {}", kind, name, e),
+                    }
+                    continue;
+                }
+            };
+            let event_tx = event_tx.clone();
+            tokio::spawn(async move {
+                while changes.recv().await.is_some() {
+                    if event_tx.send(()).await.is_err() {
+                        return;
+                    }
+                }
+            });
+            watched += 1;
+        }
+        if watched == 0 {
+            return Ok(rx);
+        }
+        drop(event_tx);
+
+        tokio::spawn(debounce_changes("kubernetes", event_rx, self.debounce, tx));
+        tracing::debug!("Watching {} Kubernetes config object(s) in namespace {}", watched, self.namespace);
+        Ok(rx)
+    }
+
+    /// The object's keys through the API, or `None` to read the mount instead
+    /// because RBAC denied the read
+    async fn read_object(&self, kind: K8sObjectKind, name: &str) -> Result<Option<BTreeMap<String, String>>> {
+        let Some(api) = &self.api else {
+            return Ok(None);
+        };
+        match api.read(kind, &self.namespace, name).await {
+            Ok(data) => Ok(Some(data.unwrap_or_else(|| {
+                tracing::warn!("{} {}/{} not found", kind, self.namespace, name);
+                BTreeMap::new()
+            }))),
+            Err(e) => match e.downcast_ref::<K8sForbidden>() {
+                Some(forbidden) => {
+                    tracing::error!("{}; reading the mounted volume instead", forbidden);
+                    Ok(None)
+                }
+                None => Err(e),
+            },
+        }
+    }
+
     async fn load_from_configmap(&self, name: &str) -> Result<ConfigMap> {
-        // This would use the Kubernetes API to load ConfigMap data
-        // For now, we'll simulate by reading from mounted volumes
-        let configmap_path = format!("/etc/config/{}", name);
+        if let Some(data) = self.read_object(K8sObjectKind::ConfigMap, name).await? {
+            return Ok(data.into_iter().map(|(key, content)| (key, Self::configmap_value(content))).collect());
+        }
+
+        // Read from the mounted volume
+        let configmap_path = self.configmap_root.join(name);
         
-        if !Path::new(&configmap_path).exists() {
+        if !configmap_path.exists() {
             return Ok(ConfigMap::new());
         }
 
@@ -412,27 +853,32 @@ impl K8sConfigSource {
         let mut entries = fs::read_dir(&configmap_path).await?;
         
         while let Some(entry) = entries.next_entry().await? {
-            if entry.file_type().await?.is_file() {
+            // Mounted keys are symlinks into `..data`, so follow them; the
+            // `..data` link and timestamped directories aren't files
+            if fs::metadata(entry.path()).await?.is_file() {
                 let key = entry.file_name().to_string_lossy().to_string();
                 let content = fs::read_to_string(entry.path()).await?;
-                
-                // Try to parse as JSON, fall back to string
-                let value = serde_json::from_str(&content)
-                    .unwrap_or_else(|_| serde_json::Value::String(content));
-                
-                config.insert(key, value);
+                config.insert(key, Self::configmap_value(content));
             }
         }
 
         Ok(config)
     }
 
+    /// Try to parse a ConfigMap value as JSON, fall back to string
+    fn configmap_value(content: String) -> serde_json::Value {
+        serde_json::from_str(&content).unwrap_or_else(|_| serde_json::Value::String(content))
+    }
+
     async fn load_from_secret(&self, name: &str) -> Result<ConfigMap> {
-        // This would use the Kubernetes API to load Secret data
-        // For now, we'll simulate by reading from mounted volumes
-        let secret_path = format!("/etc/secrets/{}", name);
+        if let Some(data) = self.read_object(K8sObjectKind::Secret, name).await? {
+            return Ok(data.into_iter().map(|(key, content)| (key, serde_json::Value::String(content))).collect());
+        }
+
+        // Read from the mounted volume
+        let secret_path = self.secret_root.join(name);
         
-        if !Path::new(&secret_path).exists() {
+        if !secret_path.exists() {
             return Ok(ConfigMap::new());
         }
 
@@ -440,11 +886,12 @@ impl K8sConfigSource {
         let mut entries = fs::read_dir(&secret_path).await?;
         
         while let Some(entry) = entries.next_entry().await? {
-            if entry.file_type().await?.is_file() {
+            // Follow the key symlinks, as for ConfigMaps
+            if fs::metadata(entry.path()).await?.is_file() {
                 let key = entry.file_name().to_string_lossy().to_string();
                 let content = fs::read_to_string(entry.path()).await?;
                 
-                // Secrets are typically base64 encoded, but mounted secrets are decoded
+                // Secrets are base64 encoded in the API, but mounted secrets are decoded
                 let value = serde_json::Value::String(content);
                 config.insert(key, value);
             }
@@ -494,5 +941,396 @@ mod vault {
         pub async fn read(_client: &Client, _mount: &str, _path: &str) -> Result<Secret> {
             Err(anyhow::anyhow!("Vault support not compiled in"))
         }
+
+        pub struct SecretMetadata {
+            pub current_version: u64,
+        }
+
+        pub async fn read_metadata(_client: &Client, _mount: &str, _path: &str) -> Result<SecretMetadata> {
+            Err(anyhow::anyhow!("Vault support not compiled in"))
+        }
+    }
+}
+// Mock AWS modules for compilation (replaced by aws-config and aws-sdk-s3 with the `aws` feature)
+#[cfg(not(feature = "aws"))]
+mod aws_config {
+    pub struct SdkConfig;
+
+    pub struct BehaviorVersion;
+
+    impl BehaviorVersion {
+        pub fn latest() -> Self {
+            Self
+        }
+    }
+
+    pub struct Region(#[allow(dead_code)] String);
+
+    impl Region {
+        pub fn new(region: String) -> Self {
+            Self(region)
+        }
+    }
+
+    pub struct ConfigLoader;
+
+    impl ConfigLoader {
+        pub fn region(self, _region: Region) -> Self {
+            self
+        }
+
+        pub async fn load(self) -> SdkConfig {
+            SdkConfig
+        }
+    }
+
+    pub fn defaults(_version: BehaviorVersion) -> ConfigLoader {
+        ConfigLoader
+    }
+}
+
+#[cfg(not(feature = "aws"))]
+mod aws_sdk_s3 {
+    use super::aws_config::SdkConfig;
+
+    #[derive(Debug)]
+    pub struct SdkError<E>(E);
+
+    impl<E> SdkError<E> {
+        pub fn as_service_error(&self) -> Option<&E> {
+            None
+        }
+    }
+
+    impl<E: std::fmt::Debug> std::fmt::Display for SdkError<E> {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            write!(f, "AWS SDK not compiled in")
+        }
+    }
+
+    impl<E: std::fmt::Debug> std::error::Error for SdkError<E> {}
+
+    #[derive(Debug)]
+    pub struct GetObjectError;
+
+    impl GetObjectError {
+        pub fn is_no_such_key(&self) -> bool {
+            false
+        }
+    }
+
+    #[derive(Debug)]
+    pub struct HeadObjectError;
+
+    impl HeadObjectError {
+        pub fn is_not_found(&self) -> bool {
+            false
+        }
+    }
+
+    #[derive(Clone)]
+    pub struct Client;
+
+    impl Client {
+        pub fn new(_config: &SdkConfig) -> Self {
+            Self
+        }
+
+        pub fn get_object(&self) -> ObjectRequest<GetObjectError> {
+            ObjectRequest(GetObjectError)
+        }
+
+        pub fn head_object(&self) -> ObjectRequest<HeadObjectError> {
+            ObjectRequest(HeadObjectError)
+        }
+    }
+
+    pub struct ObjectRequest<E>(E);
+
+    impl<E> ObjectRequest<E> {
+        pub fn bucket(self, _bucket: &str) -> Self {
+            self
+        }
+
+        pub fn key(self, _key: &str) -> Self {
+            self
+        }
+    }
+
+    impl ObjectRequest<GetObjectError> {
+        pub async fn send(self) -> Result<GetObjectOutput, SdkError<GetObjectError>> {
+            Err(SdkError(self.0))
+        }
+    }
+
+    impl ObjectRequest<HeadObjectError> {
+        pub async fn send(self) -> Result<HeadObjectOutput, SdkError<HeadObjectError>> {
+            Err(SdkError(self.0))
+        }
+    }
+
+    pub struct GetObjectOutput {
+        pub body: ByteStream,
     }
-}
\ No newline at end of file
+
+    pub struct ByteStream;
+
+    impl ByteStream {
+        pub async fn collect(self) -> anyhow::Result<AggregatedBytes> {
+            Err(anyhow::anyhow!("AWS SDK not compiled in"))
+        }
+    }
+
+    pub struct AggregatedBytes;
+
+    impl AggregatedBytes {
+        pub fn into_bytes(self) -> Vec<u8> {
+            Vec::new()
+        }
+    }
+
+    pub struct HeadObjectOutput;
+
+    impl HeadObjectOutput {
+        pub fn version_id(&self) -> Option<&str> {
+            None
+        }
+
+        pub fn e_tag(&self) -> Option<&str> {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    #[tokio::test]
+    async fn test_poll_reports_only_version_changes() {
+        let version = Arc::new(AtomicU64::new(3));
+        let (tx, mut rx) = mpsc::channel(10);
+
+        let fetch = {
+            let version = version.clone();
+            move || {
+                let version = version.load(Ordering::SeqCst);
+                async move { Ok(version) }
+            }
+        };
+        let poller = tokio::spawn(poll_for_changes(
+            "vault",
+            Some(3),
+            Duration::from_millis(10),
+            Duration::ZERO,
+            fetch,
+            tx,
+        ));
+
+        // Same version as at load time: nothing to reload
+        tokio::time::sleep(Duration::from_millis(60)).await;
+        assert!(rx.try_recv().is_err());
+
+        version.store(4, Ordering::SeqCst);
+        let change = tokio::time::timeout(Duration::from_secs(1), rx.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(change.source, "vault");
+
+        // Reported once, not on every poll
+        tokio::time::sleep(Duration::from_millis(60)).await;
+        assert!(rx.try_recv().is_err());
+
+        drop(rx);
+        tokio::time::timeout(Duration::from_secs(1), poller).await.unwrap().unwrap();
+    }
+
+    #[test]
+    fn test_document_format_follows_object_key_suffix() {
+        let yaml = FileConfigSource::parse_document(
+            "ratewatch/prod/config.yaml",
+            "server:\n  port: 9090\nredis:\n  url: redis://cache:6379\n",
+        )
+        .unwrap();
+        assert_eq!(yaml["server.port"], serde_json::json!(9090));
+        assert_eq!(yaml["redis.url"], serde_json::json!("redis://cache:6379"));
+
+        let json = FileConfigSource::parse_document("config.json", r#"{"server": {"port": 9090}}"#).unwrap();
+        assert_eq!(json.len(), 1);
+        assert_eq!(json["server.port"], serde_json::json!(9090));
+
+        assert!(FileConfigSource::parse_document("config.ini", "port = 9090").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_debounce_collapses_bursts() {
+        let (event_tx, event_rx) = mpsc::channel(100);
+        let (tx, mut rx) = mpsc::channel(10);
+        let debouncer = tokio::spawn(debounce_changes("kubernetes", event_rx, Duration::from_millis(50), tx));
+
+        // One ConfigMap update touches several entries at once
+        for _ in 0..5 {
+            event_tx.send(()).await.unwrap();
+        }
+        let change = tokio::time::timeout(Duration::from_secs(1), rx.recv()).await.unwrap().unwrap();
+        assert_eq!(change.source, "kubernetes");
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(rx.try_recv().is_err());
+
+        event_tx.send(()).await.unwrap();
+        assert!(tokio::time::timeout(Duration::from_secs(1), rx.recv()).await.unwrap().is_some());
+
+        drop(event_tx);
+        tokio::time::timeout(Duration::from_secs(1), debouncer).await.unwrap().unwrap();
+    }
+
+    /// Lay out a mounted ConfigMap the way the kubelet does: keys are symlinks
+    /// through `..data` into a timestamped directory
+    #[cfg(unix)]
+    fn write_configmap_version(mount: &Path, version: &str, values: &[(&str, &str)]) {
+        use std::os::unix::fs::symlink;
+
+        let data_dir = mount.join(format!("..{version}"));
+        std::fs::create_dir_all(&data_dir).unwrap();
+        for (key, value) in values {
+            std::fs::write(data_dir.join(key), value).unwrap();
+            let link = mount.join(key);
+            if std::fs::symlink_metadata(&link).is_err() {
+                symlink(Path::new("..data").join(key), &link).unwrap();
+            }
+        }
+        let tmp = mount.join("..data_tmp");
+        symlink(format!("..{version}"), &tmp).unwrap();
+        std::fs::rename(&tmp, mount.join("..data")).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_k8s_volume_swap_triggers_one_change() {
+        let root = std::env::temp_dir().join(format!("ratewatch-k8s-{}", uuid::Uuid::new_v4()));
+        let mount = root.join("ratewatch");
+        std::fs::create_dir_all(&mount).unwrap();
+        write_configmap_version(&mount, "v1", &[("server.port", "8081"), ("redis.pool_size", "10")]);
+
+        let source = K8sConfigSource {
+            namespace: "default".to_string(),
+            configmap_name: Some("ratewatch".to_string()),
+            secret_name: None,
+            configmap_root: root.clone(),
+            secret_root: root.join("secrets"),
+            debounce: Duration::from_millis(100),
+            api: None,
+        };
+        let config = source.load_config().await.unwrap();
+        assert_eq!(config["server.port"], 8081);
+        assert_eq!(config.len(), 2);
+
+        let mut changes = source.watch_changes().await.unwrap();
+        write_configmap_version(&mount, "v2", &[("server.port", "9090"), ("redis.pool_size", "20")]);
+
+        let change = tokio::time::timeout(Duration::from_secs(5), changes.recv()).await.unwrap().unwrap();
+        assert_eq!(change.source, "kubernetes");
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        assert!(changes.try_recv().is_err(), "one swap should produce one change");
+        assert_eq!(source.load_config().await.unwrap()["server.port"], 9090);
+
+        let _ = std::fs::remove_dir_all(root);
+    }
+
+    /// Stands in for the cluster: serves `data` for every object and sends
+    /// a watch event whenever `modify` is called. A denied verb fails as RBAC would.
+    struct MockK8sApi {
+        data: std::sync::Mutex<BTreeMap<String, String>>,
+        watchers: std::sync::Mutex<Vec<mpsc::Sender<()>>>,
+        denied: Option<&'static str>,
+    }
+
+    impl MockK8sApi {
+        fn new(values: &[(&str, &str)], denied: Option<&'static str>) -> Arc<Self> {
+            Arc::new(Self {
+                data: std::sync::Mutex::new(values.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()),
+                watchers: std::sync::Mutex::new(Vec::new()),
+                denied,
+            })
+        }
+
+        async fn modify(&self, key: &str, value: &str) {
+            self.data.lock().unwrap().insert(key.to_string(), value.to_string());
+            let watchers = self.watchers.lock().unwrap().clone();
+            for watcher in watchers {
+                let _ = watcher.send(()).await;
+            }
+        }
+
+        fn check(&self, kind: K8sObjectKind, namespace: &str, name: &str, verb: &'static str) -> Result<()> {
+            if self.denied == Some(verb) {
+                return Err(K8sForbidden { kind, namespace: namespace.to_string(), name: name.to_string(), verb }.into());
+            }
+            Ok(())
+        }
+    }
+
+    #[async_trait]
+    impl K8sObjectApi for MockK8sApi {
+        async fn read(&self, kind: K8sObjectKind, namespace: &str, name: &str) -> Result<Option<BTreeMap<String, String>>> {
+            self.check(kind, namespace, name, "get")?;
+            Ok(Some(self.data.lock().unwrap().clone()))
+        }
+
+        async fn watch(&self, kind: K8sObjectKind, namespace: &str, name: &str) -> Result<mpsc::Receiver<()>> {
+            self.check(kind, namespace, name, "watch")?;
+            let (tx, rx) = mpsc::channel(16);
+            self.watchers.lock().unwrap().push(tx);
+            Ok(rx)
+        }
+    }
+
+    fn api_source(api: Arc<MockK8sApi>, mount_root: PathBuf) -> K8sConfigSource {
+        K8sConfigSource {
+            namespace: "ratewatch".to_string(),
+            configmap_name: Some("ratewatch".to_string()),
+            secret_name: None,
+            configmap_root: mount_root.clone(),
+            secret_root: mount_root.join("secrets"),
+            debounce: Duration::from_millis(50),
+            api: None,
+        }
+        .with_api(api)
+    }
+
+    #[tokio::test]
+    async fn test_k8s_api_modify_event_triggers_change() {
+        let api = MockK8sApi::new(&[("server.port", "8081")], None);
+        let source = api_source(api.clone(), PathBuf::from("/nonexistent"));
+
+        assert_eq!(source.load_config().await.unwrap()["server.port"], 8081);
+
+        let mut changes = source.watch_changes().await.unwrap();
+        api.modify("server.port", "9090").await;
+
+        let change = tokio::time::timeout(Duration::from_secs(1), changes.recv()).await.unwrap().unwrap();
+        assert_eq!(change.source, "kubernetes");
+        assert!(matches!(change.change_type, ConfigChangeType::Modified));
+        assert_eq!(source.load_config().await.unwrap()["server.port"], 9090);
+    }
+
+    #[tokio::test]
+    async fn test_k8s_api_denied_by_rbac_falls_back_to_mount() {
+        let root = std::env::temp_dir().join(format!("ratewatch-k8s-rbac-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(root.join("ratewatch")).unwrap();
+        std::fs::write(root.join("ratewatch").join("server.port"), "7070").unwrap();
+
+        let source = api_source(MockK8sApi::new(&[("server.port", "8081")], Some("get")), root.clone());
+        assert_eq!(source.load_config().await.unwrap()["server.port"], 7070);
+
+        // Without watch permission there's nothing to report, but no error either
+        let api = MockK8sApi::new(&[], Some("watch"));
+        let mut changes = api_source(api.clone(), root.clone()).watch_changes().await.unwrap();
+        api.modify("server.port", "9090").await;
+        assert!(tokio::time::timeout(Duration::from_millis(200), changes.recv()).await.unwrap_or(None).is_none());
+
+        let _ = std::fs::remove_dir_all(root);
+    }
+}
@@ -1,6 +1,6 @@
 use super::EnterpriseConfig;
 use anyhow::{Context, Result};
-use validator::Validate;
+use validator::{Validate, ValidationErrors, ValidationErrorsKind};
 
 /// Configuration validator
 pub struct ConfigValidator {
@@ -24,10 +24,75 @@ impl ConfigValidator {
         NetworkValidator.validate(config)?;
         ResourceValidator.validate(config)?;
         ComplianceValidator.validate(config)?;
+        ConsistencyValidator.validate(config)?;
 
         tracing::debug!("Configuration validation passed");
         Ok(())
     }
+
+    /// Every problem in `config`, one line each.
+    ///
+    /// Unlike `validate`, this doesn't stop at the first failure, so a report
+    /// can list everything that needs fixing at once.
+    pub fn validate_all(&self, config: &EnterpriseConfig) -> Vec<String> {
+        let mut problems = Vec::new();
+        if let Err(errors) = config.validate() {
+            flatten_errors("", &errors, &mut problems);
+            problems.sort();
+        }
+
+        let validators: [&dyn CustomValidator; 5] = [
+            &SecurityValidator,
+            &NetworkValidator,
+            &ResourceValidator,
+            &ComplianceValidator,
+            &ConsistencyValidator,
+        ];
+        for validator in validators {
+            if let Err(e) = validator.validate(config) {
+                problems.push(format!("{}: {:#}", validator.name(), e));
+            }
+        }
+
+        problems
+    }
+}
+
+/// Render derive errors as `dotted.path: code (param = value, ...)`
+fn flatten_errors(prefix: &str, errors: &ValidationErrors, out: &mut Vec<String>) {
+    for (field, kind) in errors.errors() {
+        let path = if prefix.is_empty() {
+            field.to_string()
+        } else {
+            format!("{prefix}.{field}")
+        };
+
+        match kind {
+            ValidationErrorsKind::Struct(nested) => flatten_errors(&path, nested, out),
+            ValidationErrorsKind::List(items) => {
+                for (index, nested) in items {
+                    flatten_errors(&format!("{path}[{index}]"), nested, out);
+                }
+            }
+            ValidationErrorsKind::Field(field_errors) => {
+                for error in field_errors {
+                    let mut params: Vec<String> = error
+                        .params
+                        .iter()
+                        .map(|(name, value)| format!("{name} = {value}"))
+                        .collect();
+                    params.sort();
+
+                    let reason = error.message.as_deref().unwrap_or(&error.code);
+                    if params.is_empty() {
+                        out.push(format!("{path}: {reason}"));
+                    } else {
+                        out.push(format!("{path}: {reason} ({})", params.join(", ")));
+                    }
+                }
+            }
+        }
+    }
 }
 
 /// Custom validator trait
@@ -107,20 +172,40 @@ impl CustomValidator for NetworkValidator {
             }
         }
 
-        // Validate Redis URL format
-        if let Ok(redis_url) = std::env::var("REDIS_URL") {
-            if !redis_url.starts_with("redis://") && !redis_url.starts_with("rediss://") {
+        // Validate Redis URL format and topology
+        let redis_url = std::env::var("REDIS_URL").unwrap_or_else(|_| config.redis.url.expose_secret().to_string());
+        crate::redis_backend::RedisTopology::from_config(&redis_url, &config.redis)?;
+
+        // Validate external service URLs
+        if let Some(otlp_endpoint) = &config.observability.tracing.otlp_endpoint {
+            if !otlp_endpoint.starts_with("http://") && !otlp_endpoint.starts_with("https://") {
                 return Err(anyhow::anyhow!(
-                    "REDIS_URL must start with redis:// or rediss://, got: {}", redis_url
+                    "OTLP endpoint must be a valid HTTP/HTTPS URL: {}", otlp_endpoint
                 ));
             }
         }
 
-        // Validate external service URLs
-        if let Some(jaeger_endpoint) = &config.observability.tracing.jaeger_endpoint {
-            if !jaeger_endpoint.starts_with("http://") && !jaeger_endpoint.starts_with("https://") {
+        // Validate CORS configuration
+        let cors = &config.server.cors;
+        let wildcard = |values: &[String]| values.iter().any(|v| v == "*");
+        if wildcard(&cors.allowed_origins) && cors.allowed_origins.len() > 1 {
+            return Err(anyhow::anyhow!(
+                "CORS allowed_origins cannot mix \"*\" with explicit origins"
+            ));
+        }
+        if cors.allow_credentials
+            && (wildcard(&cors.allowed_origins)
+                || wildcard(&cors.allowed_methods)
+                || wildcard(&cors.allowed_headers))
+        {
+            return Err(anyhow::anyhow!(
+                "CORS allow_credentials cannot be combined with \"*\" origins, methods or headers"
+            ));
+        }
+        for origin in cors.allowed_origins.iter().filter(|o| o.as_str() != "*") {
+            if !origin.starts_with("http://") && !origin.starts_with("https://") {
                 return Err(anyhow::anyhow!(
-                    "Jaeger endpoint must be a valid HTTP/HTTPS URL: {}", jaeger_endpoint
+                    "CORS origin must be an HTTP/HTTPS origin: {}", origin
                 ));
             }
         }
@@ -140,10 +225,10 @@ impl CustomValidator for ResourceValidator {
     fn validate(&self, config: &EnterpriseConfig) -> Result<()> {
         // Validate worker thread count
         let cpu_count = num_cpus::get();
-        if config.server.worker_threads > cpu_count * 2 {
+        if let Some(worker_threads) = config.server.worker_threads.filter(|threads| *threads > cpu_count * 2) {
             tracing::warn!(
                 "Worker thread count ({}) is more than 2x CPU count ({}), this may cause performance issues",
-                config.server.worker_threads, cpu_count
+                worker_threads, cpu_count
             );
         }
 
@@ -160,17 +245,10 @@ impl CustomValidator for ResourceValidator {
             }
         }
 
-        // Validate auto-scaling configuration
+        // Validate auto-scaling configuration; min/max ordering is checked by ConsistencyValidator
         if config.infrastructure.auto_scaling.enabled {
             let auto_scaling = &config.infrastructure.auto_scaling;
-            
-            if auto_scaling.min_instances >= auto_scaling.max_instances {
-                return Err(anyhow::anyhow!(
-                    "Auto-scaling min_instances ({}) must be less than max_instances ({})",
-                    auto_scaling.min_instances, auto_scaling.max_instances
-                ));
-            }
-            
+
             if auto_scaling.max_instances > 100 {
                 tracing::warn!("Very high max_instances may lead to unexpected costs: {}", auto_scaling.max_instances);
             }
@@ -245,6 +323,111 @@ impl CustomValidator for ComplianceValidator {
     }
 }
 
+/// Cross-field invariants the per-field derive checks can't express
+pub struct ConsistencyValidator;
+
+impl CustomValidator for ConsistencyValidator {
+    fn validate(&self, config: &EnterpriseConfig) -> Result<()> {
+        // Auto-scaling bounds must be ordered, and leave room to scale when enabled
+        let auto_scaling = &config.infrastructure.auto_scaling;
+        if auto_scaling.min_instances > auto_scaling.max_instances {
+            return Err(anyhow::anyhow!(
+                "infrastructure.auto_scaling.min_instances ({}) must not exceed infrastructure.auto_scaling.max_instances ({})",
+                auto_scaling.min_instances, auto_scaling.max_instances
+            ));
+        }
+        if auto_scaling.enabled && auto_scaling.min_instances == auto_scaling.max_instances {
+            return Err(anyhow::anyhow!(
+                "infrastructure.auto_scaling.min_instances and max_instances are both {}; auto-scaling needs max_instances to be larger",
+                auto_scaling.max_instances
+            ));
+        }
+
+        // A canary has to start below full traffic and its first step can't overshoot 100%
+        if let Some(canary) = &config.infrastructure.deployment.canary {
+            let first_step = u16::from(canary.initial_traffic_percent) + u16::from(canary.traffic_increment_percent);
+            if first_step > 100 {
+                return Err(anyhow::anyhow!(
+                    "infrastructure.deployment.canary.initial_traffic_percent ({}) plus traffic_increment_percent ({}) exceeds 100",
+                    canary.initial_traffic_percent, canary.traffic_increment_percent
+                ));
+            }
+        }
+
+        // Replication and failover need somewhere to send data
+        let dr = &config.disaster_recovery;
+        if dr.replication.enabled && dr.replication.replicas.is_empty() {
+            return Err(anyhow::anyhow!(
+                "disaster_recovery.replication.enabled is true but disaster_recovery.replication.replicas is empty"
+            ));
+        }
+        let mut names = std::collections::HashSet::new();
+        if let Some(replica) = dr.replication.replicas.iter().find(|replica| !names.insert(replica.name.as_str())) {
+            return Err(anyhow::anyhow!(
+                "disaster_recovery.replication.replicas has more than one replica named {:?}",
+                replica.name
+            ));
+        }
+        if dr.failover.enabled && !dr.replication.enabled {
+            return Err(anyhow::anyhow!(
+                "disaster_recovery.failover.enabled requires disaster_recovery.replication.enabled"
+            ));
+        }
+
+        // A server-side leaky-bucket wait has to finish before the request times out
+        if config.rate_limiting.max_wait_ms >= config.server.request_timeout_ms {
+            return Err(anyhow::anyhow!(
+                "rate_limiting.max_wait_ms ({}) must be less than server.request_timeout_ms ({})",
+                config.rate_limiting.max_wait_ms, config.server.request_timeout_ms
+            ));
+        }
+
+        // Challenges sit below blocking, and need keys to be verified
+        let challenge = &config.security.threat_detection.challenge;
+        if challenge.min_score > challenge.block_score {
+            return Err(anyhow::anyhow!(
+                "security.threat_detection.challenge.min_score ({}) must not exceed block_score ({})",
+                challenge.min_score, challenge.block_score
+            ));
+        }
+        if challenge.enabled && (challenge.site_key.is_empty() || challenge.secret_key.is_none()) {
+            return Err(anyhow::anyhow!(
+                "security.threat_detection.challenge.enabled requires site_key and secret_key"
+            ));
+        }
+
+        // Policy patterns and methods are parsed when the router is built
+        let mut routes = std::collections::HashSet::new();
+        for policy in &config.rate_limiting.policies {
+            let route = crate::policy::validate_policy(policy).map_err(|e| {
+                anyhow::anyhow!("rate_limiting.policies entry {:?}: {:#}", policy.pattern, e)
+            })?;
+            // The first one listed always wins, so a repeat would never apply
+            if !routes.insert(route) {
+                return Err(anyhow::anyhow!(
+                    "rate_limiting.policies entry {:?} has the same pattern and methods as an earlier one",
+                    policy.pattern
+                ));
+            }
+        }
+
+        // SIEM event filters are otherwise only compiled when the integration starts
+        for provider in &config.security.siem.providers {
+            for filter in &provider.event_filters {
+                filter.compile().map_err(|e| {
+                    anyhow::anyhow!("security.siem.providers entry {:?}: {:#}", provider.name, e)
+                })?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn name(&self) -> &str {
+        "consistency"
+    }
+}
+
 /// Environment-specific validator
 pub struct EnvironmentValidator {
     environment: String,
@@ -319,4 +502,175 @@ mod num_cpus {
             .map(|n| n.get())
             .unwrap_or(4)
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{CanaryConfig, ReplicaConfig};
+
+    fn consistency_error(config: &EnterpriseConfig) -> String {
+        format!("{:#}", ConsistencyValidator.validate(config).unwrap_err())
+    }
+
+    fn replica(name: &str) -> ReplicaConfig {
+        ReplicaConfig {
+            name: name.to_string(),
+            endpoint: format!("https://{name}.example.com"),
+            priority: 1,
+        }
+    }
+
+    #[test]
+    fn test_default_config_is_consistent() {
+        assert!(ConsistencyValidator.validate(&EnterpriseConfig::default()).is_ok());
+    }
+
+    #[test]
+    fn test_auto_scaling_bounds() {
+        let mut config = EnterpriseConfig::default();
+        config.infrastructure.auto_scaling.min_instances = 5;
+        config.infrastructure.auto_scaling.max_instances = 2;
+        // Rejected even while disabled, so enabling it later can't surprise anyone
+        let error = consistency_error(&config);
+        assert!(error.contains("infrastructure.auto_scaling.min_instances (5)"));
+        assert!(error.contains("infrastructure.auto_scaling.max_instances (2)"));
+
+        config.infrastructure.auto_scaling.max_instances = 5;
+        assert!(ConsistencyValidator.validate(&config).is_ok());
+        config.infrastructure.auto_scaling.enabled = true;
+        assert!(consistency_error(&config).contains("auto-scaling needs max_instances to be larger"));
+    }
+
+    #[test]
+    fn test_max_wait_fits_in_request_timeout() {
+        let mut config = EnterpriseConfig::default();
+        config.rate_limiting.max_wait_ms = config.server.request_timeout_ms;
+        assert!(consistency_error(&config).contains("rate_limiting.max_wait_ms"));
+
+        config.rate_limiting.max_wait_ms = config.server.request_timeout_ms - 1;
+        assert!(ConsistencyValidator.validate(&config).is_ok());
+    }
+
+    #[test]
+    fn test_challenge_band_and_keys() {
+        let mut config = EnterpriseConfig::default();
+        let challenge = &mut config.security.threat_detection.challenge;
+        challenge.min_score = 0.9;
+        assert!(consistency_error(&config).contains("challenge.min_score (0.9)"));
+
+        let challenge = &mut config.security.threat_detection.challenge;
+        challenge.min_score = 0.6;
+        challenge.enabled = true;
+        assert!(consistency_error(&config).contains("requires site_key and secret_key"));
+
+        let challenge = &mut config.security.threat_detection.challenge;
+        challenge.site_key = "site".to_string();
+        challenge.secret_key = Some("secret".into());
+        assert!(ConsistencyValidator.validate(&config).is_ok());
+    }
+
+    #[test]
+    fn test_canary_first_step_stays_within_100_percent() {
+        let mut canary = CanaryConfig {
+            initial_traffic_percent: 90,
+            traffic_increment_percent: 20,
+            evaluation_interval_seconds: 60,
+        };
+        let mut config = EnterpriseConfig::default();
+        config.infrastructure.deployment.canary = Some(canary.clone());
+        let error = consistency_error(&config);
+        assert!(error.contains("canary.initial_traffic_percent (90)"));
+        assert!(error.contains("traffic_increment_percent (20)"));
+
+        canary.traffic_increment_percent = 10;
+        config.infrastructure.deployment.canary = Some(canary);
+        assert!(ConsistencyValidator.validate(&config).is_ok());
+    }
+
+    #[test]
+    fn test_replication_needs_unique_replicas() {
+        let mut config = EnterpriseConfig::default();
+        config.disaster_recovery.replication.enabled = true;
+        assert!(consistency_error(&config).contains("disaster_recovery.replication.replicas is empty"));
+
+        config.disaster_recovery.replication.replicas = vec![replica("east"), replica("east")];
+        assert!(consistency_error(&config).contains("more than one replica named \"east\""));
+
+        config.disaster_recovery.replication.replicas = vec![replica("east"), replica("west")];
+        assert!(ConsistencyValidator.validate(&config).is_ok());
+    }
+
+    #[test]
+    fn test_failover_requires_replication() {
+        let mut config = EnterpriseConfig::default();
+        config.disaster_recovery.failover.enabled = true;
+        assert!(consistency_error(&config).contains("disaster_recovery.failover.enabled requires"));
+
+        config.disaster_recovery.replication.enabled = true;
+        config.disaster_recovery.replication.replicas = vec![replica("east")];
+        assert!(ConsistencyValidator.validate(&config).is_ok());
+    }
+
+    #[test]
+    fn test_rate_limit_policy_patterns_are_checked() {
+        let mut config = EnterpriseConfig::default();
+        config.rate_limiting.policies = vec![crate::config::RateLimitPolicyConfig {
+            pattern: "api/*".to_string(),
+            methods: vec![],
+            limits: crate::config::PolicyLimitsConfig {
+                limit: 10,
+                window: 60,
+                algorithm: None,
+                mode: None,
+                cost: 1,
+                key: Default::default(),
+                ip_limit: None,
+            },
+        }];
+        assert!(consistency_error(&config).contains("rate_limiting.policies entry \"api/*\": pattern must start with '/'"));
+
+        config.rate_limiting.policies[0].pattern = "/api/*".to_string();
+        assert!(ConsistencyValidator.validate(&config).is_ok());
+
+        let mut repeat = config.rate_limiting.policies[0].clone();
+        repeat.pattern = "/api/*/".to_string();
+        config.rate_limiting.policies.push(repeat);
+        assert!(consistency_error(&config).contains("same pattern and methods as an earlier one"));
+
+        config.rate_limiting.policies[1].methods = vec!["POST".to_string()];
+        assert!(ConsistencyValidator.validate(&config).is_ok());
+    }
+
+    #[test]
+    fn test_siem_event_filters_are_checked() {
+        use crate::security::siem_integration::{EventFilter, FilterOperator, SiemProviderConfig, SiemProviderType};
+
+        let mut config = EnterpriseConfig::default();
+        config.security.siem.providers = vec![SiemProviderConfig {
+            name: "webhook".to_string(),
+            provider_type: SiemProviderType::Webhook,
+            enabled: true,
+            config: Default::default(),
+            event_filters: vec![EventFilter {
+                field: "source".to_string(),
+                operator: FilterOperator::Regex,
+                value: "ratewatch-(".to_string(),
+            }],
+        }];
+        let err = consistency_error(&config);
+        assert!(err.contains("security.siem.providers entry \"webhook\""), "{err}");
+        assert!(err.contains("Invalid regex"), "{err}");
+
+        config.security.siem.providers[0].event_filters[0].value = "^ratewatch-".to_string();
+        assert!(ConsistencyValidator.validate(&config).is_ok());
+    }
+
+    #[test]
+    fn test_validate_all_reports_each_invariant() {
+        let mut config = EnterpriseConfig::default();
+        config.infrastructure.auto_scaling.min_instances = 20;
+        let problems = ConfigValidator::new().validate_all(&config);
+        assert!(problems.iter().any(|p| p.starts_with("consistency: infrastructure.auto_scaling")));
+    }
+}
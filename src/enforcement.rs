@@ -0,0 +1,290 @@
+//! Global kill-switch for rate limit enforcement.
+//!
+//! During an incident an operator can stop enforcing limits on every
+//! instance at once with `POST /v1/admin/enforcement/disable`, without a
+//! redeploy. The flag lives in Redis under `ratewatch:enforcement:enabled`;
+//! while it's off, `/v1/check`, `/v1/limit/batch`, the gRPC checks and the
+//! route policies allow every request, and analytics count them as
+//! `bypassed_killswitch`. Auth, the denylists and threat detection still
+//! apply.
+//!
+//! Each instance caches the flag for a second, so a change reaches the fleet
+//! within the cache TTL. A missing key means enforcing, and if Redis can't be
+//! read the last value keeps being used.
+
+use axum::{
+    extract::State,
+    response::Json,
+    routing::{get, post},
+    Extension, Router,
+};
+use redis::AsyncCommands;
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+
+use crate::api_error::ApiError;
+use crate::audit::{audit_event::{ActorInfo, AuditOutcome}, AuditLogger};
+use crate::auth::ApiKeyIdentity;
+use crate::redis_backend::RedisConnector;
+
+const ENFORCEMENT_KEY: &str = "ratewatch:enforcement:enabled";
+
+/// How long an instance uses its copy of the flag before rereading it
+pub const DEFAULT_CACHE_TTL: Duration = Duration::from_secs(1);
+
+pub struct EnforcementSwitch {
+    redis: RedisConnector,
+    cache_ttl: Duration,
+    /// The flag and when it was read
+    cache: RwLock<Option<(bool, Instant)>>,
+}
+
+impl EnforcementSwitch {
+    pub fn new(redis: RedisConnector) -> Self {
+        Self {
+            redis,
+            cache_ttl: DEFAULT_CACHE_TTL,
+            cache: RwLock::new(None),
+        }
+    }
+
+    pub fn with_cache_ttl(mut self, cache_ttl: Duration) -> Self {
+        self.cache_ttl = cache_ttl;
+        self
+    }
+
+    /// A flag held in memory, never read from Redis
+    #[cfg(test)]
+    pub(crate) fn with_enabled(enabled: bool) -> Self {
+        let switch = Self::new(RedisConnector::open("redis://127.0.0.1:1").unwrap()).with_cache_ttl(Duration::MAX);
+        switch.set_cached(enabled);
+        switch
+    }
+
+    /// Flip the in-memory flag, as `set_enabled` does after writing Redis
+    #[cfg(test)]
+    pub(crate) fn set_cached(&self, enabled: bool) {
+        *self.cache.write().unwrap() = Some((enabled, Instant::now()));
+    }
+
+    /// Whether limits are being enforced, rereading the flag if the cached
+    /// copy is stale
+    pub async fn is_enabled(&self) -> bool {
+        if let Some((enabled, fetched_at)) = *self.cache.read().unwrap() {
+            if fetched_at.elapsed() < self.cache_ttl {
+                return enabled;
+            }
+        }
+
+        match self.fetch().await {
+            Ok(enabled) => {
+                *self.cache.write().unwrap() = Some((enabled, Instant::now()));
+                enabled
+            }
+            Err(e) => {
+                tracing::warn!("Failed to refresh enforcement flag, using cached copy: {:#}", e);
+                self.cache.read().unwrap().map_or(true, |(enabled, _)| enabled)
+            }
+        }
+    }
+
+    async fn fetch(&self) -> anyhow::Result<bool> {
+        let mut conn = self.redis.get_async_connection().await?;
+        let value: Option<String> = conn.get(ENFORCEMENT_KEY).await?;
+        Ok(value.as_deref() != Some("0"))
+    }
+
+    /// Turn enforcement on or off across the fleet; false if it already was
+    pub async fn set_enabled(&self, enabled: bool) -> anyhow::Result<bool> {
+        let mut conn = self.redis.get_async_connection().await?;
+        let previous: Option<String> = conn.getset(ENFORCEMENT_KEY, if enabled { "1" } else { "0" }).await?;
+        // This instance sees the change straight away
+        *self.cache.write().unwrap() = Some((enabled, Instant::now()));
+        Ok((previous.as_deref() != Some("0")) != enabled)
+    }
+}
+
+pub struct EnforcementApiState {
+    pub switch: Arc<EnforcementSwitch>,
+    pub audit: Arc<AuditLogger>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct EnforcementChange {
+    /// Kept in the audit trail
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct EnforcementResponse {
+    pub enabled: bool,
+    /// False if enforcement was already in this state
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub changed: Option<bool>,
+}
+
+/// Admin routes for the kill-switch; mount behind `admin_auth_middleware`
+pub fn create_enforcement_router(switch: Arc<EnforcementSwitch>, audit: Arc<AuditLogger>) -> Router {
+    Router::new()
+        .route("/v1/admin/enforcement", get(get_enforcement))
+        .route("/v1/admin/enforcement/enable", post(enable_enforcement))
+        .route("/v1/admin/enforcement/disable", post(disable_enforcement))
+        .with_state(Arc::new(EnforcementApiState { switch, audit }))
+}
+
+#[cfg_attr(
+    feature = "openapi",
+    utoipa::path(
+        get,
+        path = "/v1/admin/enforcement",
+        tag = "admin",
+        responses(
+            (status = 200, description = "Whether rate limits are enforced", body = EnforcementResponse),
+            (status = 401, description = "Missing or invalid API key"),
+            (status = 403, description = "API key is not an admin key"),
+        ),
+        security(("api_key" = [])),
+    )
+)]
+async fn get_enforcement(State(state): State<Arc<EnforcementApiState>>) -> Json<EnforcementResponse> {
+    Json(EnforcementResponse {
+        enabled: state.switch.is_enabled().await,
+        changed: None,
+    })
+}
+
+#[cfg_attr(
+    feature = "openapi",
+    utoipa::path(
+        post,
+        path = "/v1/admin/enforcement/enable",
+        tag = "admin",
+        request_body(content = EnforcementChange, description = "Optional"),
+        responses(
+            (status = 200, description = "Rate limits are enforced", body = EnforcementResponse),
+            (status = 500, description = "Internal error"),
+            (status = 401, description = "Missing or invalid API key"),
+            (status = 403, description = "API key is not an admin key"),
+        ),
+        security(("api_key" = [])),
+    )
+)]
+async fn enable_enforcement(
+    State(state): State<Arc<EnforcementApiState>>,
+    Extension(identity): Extension<ApiKeyIdentity>,
+    change: Option<Json<EnforcementChange>>,
+) -> Result<Json<EnforcementResponse>, ApiError> {
+    set_enforcement(&state, identity, true, change.map(|Json(change)| change).unwrap_or_default()).await
+}
+
+#[cfg_attr(
+    feature = "openapi",
+    utoipa::path(
+        post,
+        path = "/v1/admin/enforcement/disable",
+        tag = "admin",
+        request_body(content = EnforcementChange, description = "Optional"),
+        responses(
+            (status = 200, description = "Rate limits are no longer enforced", body = EnforcementResponse),
+            (status = 500, description = "Internal error"),
+            (status = 401, description = "Missing or invalid API key"),
+            (status = 403, description = "API key is not an admin key"),
+        ),
+        security(("api_key" = [])),
+    )
+)]
+async fn disable_enforcement(
+    State(state): State<Arc<EnforcementApiState>>,
+    Extension(identity): Extension<ApiKeyIdentity>,
+    change: Option<Json<EnforcementChange>>,
+) -> Result<Json<EnforcementResponse>, ApiError> {
+    set_enforcement(&state, identity, false, change.map(|Json(change)| change).unwrap_or_default()).await
+}
+
+async fn set_enforcement(
+    state: &EnforcementApiState,
+    identity: ApiKeyIdentity,
+    enabled: bool,
+    change: EnforcementChange,
+) -> Result<Json<EnforcementResponse>, ApiError> {
+    let result = state.switch.set_enabled(enabled).await;
+    if enabled {
+        tracing::info!(key_id = %identity.key_id, "Rate limit enforcement enabled");
+    } else {
+        tracing::warn!(key_id = %identity.key_id, "Rate limit enforcement disabled fleet-wide");
+    }
+
+    let action = if enabled { "enable_enforcement" } else { "disable_enforcement" };
+    let (outcome, details) = match &result {
+        Ok(changed) => (
+            AuditOutcome::Success,
+            serde_json::json!({ "enabled": enabled, "changed": changed, "reason": change.reason }),
+        ),
+        Err(e) => (
+            AuditOutcome::Failure,
+            serde_json::json!({ "enabled": enabled, "reason": change.reason, "error": e.to_string() }),
+        ),
+    };
+    if let Err(e) = state
+        .audit
+        .log_admin_action(
+            ActorInfo::new().with_api_key(identity.key_id),
+            action,
+            "rate_limit_enforcement",
+            None,
+            outcome,
+            None,
+            Some(details),
+        )
+        .await
+    {
+        tracing::error!("Failed to audit enforcement change: {}", e);
+    }
+
+    result
+        .map(|changed| {
+            Json(EnforcementResponse {
+                enabled,
+                changed: Some(changed),
+            })
+        })
+        .map_err(|e| {
+            tracing::error!("Failed to update enforcement flag: {}", e);
+            ApiError::internal("Failed to update enforcement flag")
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_stale_flag_is_kept_when_redis_is_unreachable() {
+        let switch = EnforcementSwitch::with_enabled(false).with_cache_ttl(Duration::ZERO);
+        assert!(!switch.is_enabled().await);
+
+        // Never read, so limits are enforced
+        let switch = EnforcementSwitch::new(RedisConnector::open("redis://127.0.0.1:1").unwrap());
+        assert!(switch.is_enabled().await);
+    }
+
+    #[tokio::test]
+    async fn test_flag_is_shared_through_redis() {
+        let redis = RedisConnector::open("redis://127.0.0.1:6379").unwrap();
+        let ours = EnforcementSwitch::new(redis.clone()).with_cache_ttl(Duration::ZERO);
+        let theirs = EnforcementSwitch::new(redis).with_cache_ttl(Duration::ZERO);
+        let Ok(changed) = ours.set_enabled(false).await else {
+            // Redis not available
+            return;
+        };
+
+        assert!(changed);
+        assert!(!theirs.is_enabled().await);
+        assert!(!ours.set_enabled(false).await.unwrap());
+        assert!(ours.set_enabled(true).await.unwrap());
+        assert!(theirs.is_enabled().await);
+    }
+}
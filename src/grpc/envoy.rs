@@ -0,0 +1,206 @@
+//! Envoy `RateLimitService` (`envoy.service.ratelimit.v3`) so Envoy's ratelimit
+//! filter can call RateWatch directly.
+//!
+//! Each descriptor becomes one rate limit key: `domain:k1=v1:k2=v2`. Descriptors
+//! with a `limit` override use it; others fall back to the configured defaults.
+//! All descriptors in a call are checked atomically in one batch.
+
+use std::sync::Arc;
+use tonic::{Request, Response, Status};
+
+use super::RateLimitService;
+use crate::rate_limiter::{self, RateLimitRequest};
+
+pub mod proto {
+    tonic::include_proto!("envoy.service.ratelimit.v3");
+}
+
+use proto::rate_limit_response::{rate_limit::Unit, Code, DescriptorStatus, RateLimit};
+use proto::rate_limit_service_server::{RateLimitService as EnvoyRateLimit, RateLimitServiceServer};
+use proto::{RateLimitDescriptor, RateLimitRequest as EnvoyRequest, RateLimitResponse as EnvoyResponse};
+
+pub struct EnvoyRateLimitService {
+    inner: Arc<RateLimitService>,
+}
+
+impl EnvoyRateLimitService {
+    pub fn new(inner: Arc<RateLimitService>) -> Self {
+        Self { inner }
+    }
+
+    pub fn into_server(self) -> RateLimitServiceServer<Self> {
+        RateLimitServiceServer::new(self)
+    }
+
+    fn to_request(
+        &self,
+        domain: &str,
+        descriptor: &RateLimitDescriptor,
+        hits_addend: u32,
+    ) -> Result<(RateLimitRequest, RateLimit), Status> {
+        let mut key = domain.to_string();
+        for entry in &descriptor.entries {
+            key.push(':');
+            key.push_str(&entry.key);
+            key.push('=');
+            key.push_str(&entry.value);
+        }
+
+        let (limit, unit) = match &descriptor.limit {
+            Some(limit) => {
+                let unit = Unit::try_from(limit.unit)
+                    .ok()
+                    .filter(|unit| *unit != Unit::Unknown)
+                    .ok_or_else(|| Status::invalid_argument("descriptor limit has no unit"))?;
+                (limit.requests_per_unit as u64, unit)
+            }
+            None => (self.inner.envoy_default_limit, Unit::Unknown),
+        };
+
+        let window = match unit {
+            Unit::Second => 1,
+            Unit::Minute => 60,
+            Unit::Hour => 3600,
+            Unit::Day => 86400,
+            Unit::Unknown => self.inner.envoy_default_window,
+        };
+
+        let current_limit = RateLimit {
+            name: key.clone(),
+            requests_per_unit: u32::try_from(limit).unwrap_or(u32::MAX),
+            unit: unit as i32,
+        };
+
+        Ok((
+            RateLimitRequest {
+                key,
+                limit,
+                window,
+                cost: hits_addend.max(1) as u64,
+            },
+            current_limit,
+        ))
+    }
+}
+
+#[tonic::async_trait]
+impl EnvoyRateLimit for EnvoyRateLimitService {
+    async fn should_rate_limit(
+        &self,
+        request: Request<EnvoyRequest>,
+    ) -> Result<Response<EnvoyResponse>, Status> {
+        self.inner.authorize(&request).await?;
+        let request = request.into_inner();
+
+        let (reqs, limits): (Vec<_>, Vec<_>) = request
+            .descriptors
+            .iter()
+            .map(|descriptor| self.to_request(&request.domain, descriptor, request.hits_addend))
+            .collect::<Result<Vec<_>, Status>>()?
+            .into_iter()
+            .unzip();
+
+        rate_limiter::validate_batch(&reqs, self.inner.max_batch_size)
+            .map_err(|e| Status::invalid_argument(e.to_string()))?;
+
+        let responses = self.inner.rate_limiter.check_batch(&reqs).await.map_err(|e| {
+            tracing::error!("Envoy rate limit check failed: {}", e);
+            Status::internal("rate limit check failed")
+        })?;
+
+        let mut overall_code = Code::Ok;
+        let mut statuses = Vec::with_capacity(responses.len());
+        for ((req, current_limit), response) in reqs.iter().zip(limits).zip(responses) {
+            self.inner.record(req, &response).await;
+
+            let code = if response.allowed {
+                Code::Ok
+            } else {
+                overall_code = Code::OverLimit;
+                Code::OverLimit
+            };
+
+            statuses.push(DescriptorStatus {
+                code: code as i32,
+                current_limit: Some(current_limit),
+                limit_remaining: u32::try_from(response.remaining).unwrap_or(u32::MAX),
+                duration_until_reset: Some(prost_types::Duration {
+                    seconds: response.reset_in as i64,
+                    nanos: 0,
+                }),
+            });
+        }
+
+        Ok(Response::new(EnvoyResponse {
+            overall_code: overall_code as i32,
+            statuses,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proto::rate_limit_descriptor::{Entry, RateLimitOverride};
+
+    fn service() -> EnvoyRateLimitService {
+        let inner = RateLimitService::new(
+            Arc::new(crate::rate_limiter::RateLimiter::new("redis://127.0.0.1:6379").unwrap()),
+            Arc::new(crate::auth::ApiKeyValidator::new("test_secret".to_string())),
+            Arc::new(crate::analytics::AnalyticsManager::new(
+                crate::redis_backend::RedisConnector::open("redis://127.0.0.1:6379").unwrap(),
+            )),
+            100,
+        )
+        .with_envoy_defaults(10, 30);
+        EnvoyRateLimitService::new(Arc::new(inner))
+    }
+
+    fn descriptor(limit: Option<RateLimitOverride>) -> RateLimitDescriptor {
+        RateLimitDescriptor {
+            entries: vec![
+                Entry {
+                    key: "remote_address".to_string(),
+                    value: "10.0.0.1".to_string(),
+                },
+                Entry {
+                    key: "path".to_string(),
+                    value: "/api".to_string(),
+                },
+            ],
+            limit,
+        }
+    }
+
+    #[test]
+    fn test_descriptor_key_and_defaults() {
+        let (req, current_limit) = service().to_request("edge", &descriptor(None), 0).unwrap();
+
+        assert_eq!(req.key, "edge:remote_address=10.0.0.1:path=/api");
+        assert_eq!(req.limit, 10);
+        assert_eq!(req.window, 30);
+        assert_eq!(req.cost, 1);
+        assert_eq!(current_limit.requests_per_unit, 10);
+    }
+
+    #[test]
+    fn test_descriptor_limit_override() {
+        let limit = RateLimitOverride {
+            requests_per_unit: 5,
+            unit: Unit::Minute as i32,
+        };
+        let (req, _) = service().to_request("edge", &descriptor(Some(limit)), 3).unwrap();
+
+        assert_eq!(req.limit, 5);
+        assert_eq!(req.window, 60);
+        assert_eq!(req.cost, 3);
+
+        let missing_unit = RateLimitOverride {
+            requests_per_unit: 5,
+            unit: Unit::Unknown as i32,
+        };
+        assert!(service()
+            .to_request("edge", &descriptor(Some(missing_unit)), 1)
+            .is_err());
+    }
+}
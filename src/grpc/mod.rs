@@ -0,0 +1,444 @@
+//! gRPC front end for rate limit checks (enabled with the `grpc` feature).
+//!
+//! Shares the `RateLimiter`, `ApiKeyValidator` and analytics recording with the
+//! HTTP API so both transports make and record identical decisions. The same
+//! listener also serves Envoy's `RateLimitService` (see [`envoy`]).
+
+pub mod envoy;
+
+use anyhow::Result;
+use std::{future::Future, net::SocketAddr, sync::Arc, time::Instant};
+use tonic::{transport::Server, Request, Response, Status};
+
+use crate::analytics::AnalyticsManager;
+use crate::auth::ApiKeyValidator;
+use crate::key_access::KeyAccess;
+use crate::metrics;
+use crate::rate_limiter::{self, RateLimitRequest, RateLimitResponse, RateLimiter};
+
+pub mod proto {
+    tonic::include_proto!("ratewatch.v1");
+}
+
+use proto::rate_limit_server::{RateLimit, RateLimitServer};
+use proto::{BatchCheckRequest, BatchCheckResponse, CheckRequest, CheckResponse};
+
+pub struct RateLimitService {
+    rate_limiter: Arc<RateLimiter>,
+    api_key_validator: Arc<ApiKeyValidator>,
+    analytics: Arc<AnalyticsManager>,
+    max_batch_size: usize,
+    envoy_default_limit: u64,
+    envoy_default_window: u64,
+}
+
+impl RateLimitService {
+    pub fn new(
+        rate_limiter: Arc<RateLimiter>,
+        api_key_validator: Arc<ApiKeyValidator>,
+        analytics: Arc<AnalyticsManager>,
+        max_batch_size: usize,
+    ) -> Self {
+        Self {
+            rate_limiter,
+            api_key_validator,
+            analytics,
+            max_batch_size,
+            envoy_default_limit: 100,
+            envoy_default_window: 60,
+        }
+    }
+
+    /// Limit and window used for Envoy descriptors without a `limit` override
+    pub fn with_envoy_defaults(mut self, limit: u64, window: u64) -> Self {
+        self.envoy_default_limit = limit;
+        self.envoy_default_window = window;
+        self
+    }
+
+    pub fn into_server(self) -> RateLimitServer<Self> {
+        RateLimitServer::new(self)
+    }
+
+    /// Same Bearer token and allow/deny list check as the HTTP `auth_middleware`,
+    /// read from call metadata
+    async fn authorize<T>(&self, request: &Request<T>) -> Result<KeyAccess, Status> {
+        let api_key = request
+            .metadata()
+            .get("authorization")
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .ok_or_else(|| {
+                tracing::warn!("Missing or invalid authorization metadata on gRPC call");
+                Status::unauthenticated("missing bearer token")
+            })?;
+
+        if !self.api_key_validator.validate_key(api_key) {
+            tracing::warn!("API key validation failed");
+            return Err(Status::unauthenticated("invalid API key"));
+        }
+
+        let identity = self.api_key_validator.identity(api_key);
+        let access = self.api_key_validator.key_access(&identity).await;
+        if access == KeyAccess::Denied {
+            if let Some(list) = self.api_key_validator.key_access_list() {
+                list.record_denial(&identity, "grpc").await;
+            }
+            return Err(Status::permission_denied("API key is denylisted"));
+        }
+        Ok(access)
+    }
+
+    async fn record(&self, req: &RateLimitRequest, response: &RateLimitResponse) {
+        if response.allowed {
+            metrics::RATE_LIMIT_HITS.inc();
+        } else {
+            metrics::RATE_LIMIT_MISSES.inc();
+        }
+
+        if let Some(failure_mode) = response.failure_mode {
+            let _ = self
+                .analytics
+                .record_backend_failure(Some(&req.key), failure_mode)
+                .await;
+            return;
+        }
+
+        if !response.allowed {
+            metrics::record_denial(&req.key);
+        }
+        let _ = self
+            .analytics
+            .record_check(&req.key, response, req.window)
+            .await;
+    }
+}
+
+impl From<CheckRequest> for RateLimitRequest {
+    fn from(req: CheckRequest) -> Self {
+        Self {
+            key: req.key,
+            limit: req.limit,
+            window: req.window,
+            cost: req.cost,
+        }
+    }
+}
+
+fn to_proto(key: &str, response: RateLimitResponse) -> CheckResponse {
+    CheckResponse {
+        key: key.to_string(),
+        allowed: response.allowed,
+        remaining: response.remaining,
+        reset_in: response.reset_in,
+        retry_after: response.retry_after,
+        deny_reason: response.deny_reason.map(|reason| reason.as_str().to_string()),
+    }
+}
+
+#[tonic::async_trait]
+impl RateLimit for RateLimitService {
+    async fn check(&self, request: Request<CheckRequest>) -> Result<Response<CheckResponse>, Status> {
+        let access = self.authorize(&request).await?;
+        let start_time = Instant::now();
+        let req = RateLimitRequest::from(request.into_inner());
+
+        rate_limiter::validate_request(&req).map_err(|e| Status::invalid_argument(e.to_string()))?;
+
+        if !self.rate_limiter.enforcing().await {
+            metrics::RATE_LIMIT_HITS.inc();
+            let _ = self.analytics.record_killswitch_bypass(&req.key, req.window).await;
+            return Ok(Response::new(to_proto(&req.key, RateLimitResponse::bypassed(&req))));
+        }
+        if access == KeyAccess::Allowlisted {
+            metrics::RATE_LIMIT_HITS.inc();
+            let _ = self.analytics.record_bypass(&req.key, req.window).await;
+            return Ok(Response::new(to_proto(&req.key, RateLimitResponse::bypassed(&req))));
+        }
+
+        let response = self.rate_limiter.check(req.clone()).await.map_err(|e| {
+            tracing::error!("Rate limit check failed: {}", e);
+            Status::internal("rate limit check failed")
+        })?;
+
+        metrics::REQUEST_DURATION.observe(start_time.elapsed().as_secs_f64());
+        self.record(&req, &response).await;
+
+        Ok(Response::new(to_proto(&req.key, response)))
+    }
+
+    async fn peek(&self, request: Request<CheckRequest>) -> Result<Response<CheckResponse>, Status> {
+        self.authorize(&request).await?;
+        let req = RateLimitRequest::from(request.into_inner());
+
+        rate_limiter::validate_request(&req).map_err(|e| Status::invalid_argument(e.to_string()))?;
+
+        let response = self.rate_limiter.peek(&req).await.map_err(|e| {
+            tracing::error!("Rate limit peek failed: {}", e);
+            Status::internal("rate limit peek failed")
+        })?;
+
+        Ok(Response::new(to_proto(&req.key, response)))
+    }
+
+    async fn batch_check(
+        &self,
+        request: Request<BatchCheckRequest>,
+    ) -> Result<Response<BatchCheckResponse>, Status> {
+        let access = self.authorize(&request).await?;
+        let start_time = Instant::now();
+        let reqs: Vec<RateLimitRequest> = request
+            .into_inner()
+            .requests
+            .into_iter()
+            .map(RateLimitRequest::from)
+            .collect();
+
+        if let Err(e) = rate_limiter::validate_batch(&reqs, self.max_batch_size) {
+            return Err(if reqs.len() > self.max_batch_size {
+                Status::resource_exhausted(e.to_string())
+            } else {
+                Status::invalid_argument(e.to_string())
+            });
+        }
+
+        let enforcing = self.rate_limiter.enforcing().await;
+        if !enforcing || access == KeyAccess::Allowlisted {
+            let mut results = Vec::with_capacity(reqs.len());
+            for req in &reqs {
+                metrics::RATE_LIMIT_HITS.inc();
+                let _ = if enforcing {
+                    self.analytics.record_bypass(&req.key, req.window).await
+                } else {
+                    self.analytics.record_killswitch_bypass(&req.key, req.window).await
+                };
+                results.push(to_proto(&req.key, RateLimitResponse::bypassed(req)));
+            }
+            return Ok(Response::new(BatchCheckResponse { results }));
+        }
+
+        let responses = self.rate_limiter.check_batch(&reqs).await.map_err(|e| {
+            tracing::error!("Batch rate limit check failed: {}", e);
+            Status::internal("batch rate limit check failed")
+        })?;
+
+        metrics::REQUEST_DURATION.observe(start_time.elapsed().as_secs_f64());
+
+        let mut results = Vec::with_capacity(responses.len());
+        for (req, response) in reqs.iter().zip(responses) {
+            self.record(req, &response).await;
+            results.push(to_proto(&req.key, response));
+        }
+
+        Ok(Response::new(BatchCheckResponse { results }))
+    }
+}
+
+/// Serve the gRPC API until `shutdown` resolves
+pub async fn serve(
+    service: RateLimitService,
+    addr: SocketAddr,
+    shutdown: impl Future<Output = ()>,
+) -> Result<()> {
+    tracing::info!("🚀 gRPC server listening on {}", addr);
+
+    let service = Arc::new(service);
+
+    Server::builder()
+        .add_service(RateLimitServer::from_arc(service.clone()))
+        .add_service(envoy::EnvoyRateLimitService::new(service).into_server())
+        .serve_with_shutdown(addr, shutdown)
+        .await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::redis_backend::RedisConnector;
+    use axum::{body::Body, http::Request as HttpRequest};
+    use proto::rate_limit_client::RateLimitClient;
+    use tokio_stream::wrappers::TcpListenerStream;
+    use tower::ServiceExt;
+
+    const REDIS_URL: &str = "redis://127.0.0.1:6379";
+    const API_KEY: &str = "rw_1234567890abcdef1234567890abcdef";
+
+    async fn shared_components() -> Option<(Arc<RateLimiter>, Arc<ApiKeyValidator>, Arc<AnalyticsManager>)> {
+        let rate_limiter = Arc::new(RateLimiter::new(REDIS_URL).ok()?);
+        if rate_limiter.health_check().await.is_err() {
+            return None;
+        }
+
+        Some((
+            rate_limiter,
+            Arc::new(ApiKeyValidator::new("test_secret".to_string())),
+            Arc::new(AnalyticsManager::new(RedisConnector::open(REDIS_URL).ok()?)),
+        ))
+    }
+
+    async fn http_router(
+        rate_limiter: Arc<RateLimiter>,
+        api_key_validator: Arc<ApiKeyValidator>,
+        analytics: Arc<AnalyticsManager>,
+    ) -> axum::Router {
+        let config = crate::config::EnterpriseConfig::default();
+        let redis = || RedisConnector::open(REDIS_URL).unwrap();
+        let audit = crate::audit::initialize_audit_system(
+            "redis",
+            Some(redis()),
+            None,
+            None,
+            "test-audit-signing-key-that-is-at-least-32-chars",
+            &[],
+        )
+        .await
+        .unwrap();
+
+        crate::api::create_secure_router(
+            rate_limiter.clone(),
+            api_key_validator,
+            Arc::new(crate::privacy::PrivacyManager::new(redis())),
+            analytics,
+            Arc::new(crate::health::HealthCheckManager::new(rate_limiter)),
+            audit.clone(),
+            crate::security::initialize_security_system(redis(), &config.security, audit)
+                .await
+                .unwrap(),
+            Arc::new(tokio::sync::Mutex::new(
+                crate::tenant::TenantManager::new(REDIS_URL, "ratewatch".to_string()).unwrap(),
+            )),
+            Arc::new(crate::config::ConfigManager::with_sources(Vec::new()).await.unwrap()),
+            &config,
+        )
+    }
+
+    async fn http_check(router: &axum::Router, key: &str, limit: u64) -> bool {
+        let body = serde_json::json!({ "key": key, "limit": limit, "window": 60, "cost": 1 });
+        let response = router
+            .clone()
+            .oneshot(
+                HttpRequest::post("/v1/check")
+                    .header("authorization", format!("Bearer {API_KEY}"))
+                    .header("content-type", "application/json")
+                    .body(Body::from(body.to_string()))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let value: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        value["allowed"].as_bool().unwrap()
+    }
+
+    fn grpc_request<T>(message: T) -> Request<T> {
+        let mut request = Request::new(message);
+        request
+            .metadata_mut()
+            .insert("authorization", format!("Bearer {API_KEY}").parse().unwrap());
+        request
+    }
+
+    #[tokio::test]
+    async fn test_grpc_check_matches_http() {
+        let (rate_limiter, api_key_validator, analytics) = match shared_components().await {
+            Some(components) => components,
+            None => {
+                println!("Skipping gRPC test - Redis not available");
+                return;
+            }
+        };
+
+        let service = RateLimitService::new(
+            rate_limiter.clone(),
+            api_key_validator.clone(),
+            analytics.clone(),
+            100,
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(
+            Server::builder()
+                .add_service(service.into_server())
+                .serve_with_incoming(TcpListenerStream::new(listener)),
+        );
+
+        let mut client = RateLimitClient::connect(format!("http://{addr}")).await.unwrap();
+        let router = http_router(rate_limiter, api_key_validator, analytics).await;
+
+        let limit = 3;
+        let grpc_key = format!("test_grpc_{}", uuid::Uuid::new_v4());
+        let http_key = format!("test_grpc_http_{}", uuid::Uuid::new_v4());
+
+        for _ in 0..limit + 2 {
+            let grpc_allowed = client
+                .check(grpc_request(CheckRequest {
+                    key: grpc_key.clone(),
+                    limit,
+                    window: 60,
+                    cost: 1,
+                }))
+                .await
+                .unwrap()
+                .into_inner()
+                .allowed;
+            let http_allowed = http_check(&router, &http_key, limit).await;
+
+            assert_eq!(grpc_allowed, http_allowed);
+        }
+
+        // Peek reports the exhausted key without consuming anything
+        let peeked = client
+            .peek(grpc_request(CheckRequest {
+                key: grpc_key.clone(),
+                limit,
+                window: 60,
+                cost: 1,
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        assert!(!peeked.allowed);
+        assert_eq!(peeked.remaining, 0);
+
+        let batch = client
+            .batch_check(grpc_request(BatchCheckRequest {
+                requests: vec![
+                    CheckRequest {
+                        key: grpc_key.clone(),
+                        limit,
+                        window: 60,
+                        cost: 1,
+                    },
+                    CheckRequest {
+                        key: format!("test_grpc_fresh_{}", uuid::Uuid::new_v4()),
+                        limit,
+                        window: 60,
+                        cost: 1,
+                    },
+                ],
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(batch.results.len(), 2);
+        assert!(!batch.results[0].allowed);
+        assert!(batch.results[1].allowed);
+
+        // Calls without a key are rejected like the HTTP API
+        let status = client
+            .check(Request::new(CheckRequest {
+                key: grpc_key,
+                limit,
+                window: 60,
+                cost: 1,
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::Unauthenticated);
+    }
+}
@@ -182,8 +182,8 @@ impl HealthCheckManager {
 
         // Check Redis URL format
         if let Ok(redis_url) = std::env::var("REDIS_URL") {
-            if !redis_url.starts_with("redis://") && !redis_url.starts_with("rediss://") {
-                errors.push("REDIS_URL must start with redis:// or rediss://");
+            if crate::redis_backend::RedisTopology::from_url(&redis_url).is_err() {
+                errors.push("REDIS_URL must use redis://, rediss://, redis+cluster:// or redis+sentinel://");
             }
         }
 
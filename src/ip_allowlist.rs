@@ -0,0 +1,289 @@
+//! Client IPs and CIDR ranges that skip threat detection and rate limiting.
+//!
+//! `[security.ip_allowlist] entries` lists trusted internal subnets and
+//! monitoring probes. `ip_allowlist_middleware` runs ahead of the route
+//! policies, auth and threat detection and marks requests from those clients
+//! with `IpAllowlisted`: threat detection doesn't analyze them, and
+//! `/v1/check`, `/v1/limit/batch` and the route policies allow them without
+//! counting. They're still recorded in analytics, as allowlisted, so what
+//! bypassed the limits can be audited. Authentication still applies, and a
+//! client that's also on the IP denylist is refused.
+//!
+//! The client IP is the one every layer uses, from `crate::request_context`,
+//! so forwarding headers only count from `server.trusted_proxies`.
+
+use anyhow::{Context, Result};
+use axum::{
+    extract::{Request, State},
+    middleware::Next,
+    response::{Json, Response},
+    routing::get,
+    Router,
+};
+use ipnet::IpNet;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::net::IpAddr;
+use std::sync::Arc;
+
+use crate::config::IpAllowlistConfig;
+
+/// Set on requests whose client IP is allowlisted
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IpAllowlisted(pub IpAddr);
+
+/// The allowlisted networks, grouped by prefix length so a lookup is one
+/// hash probe per distinct length rather than one comparison per entry
+#[derive(Default)]
+pub struct IpAllowlist {
+    entries: Vec<IpNet>,
+    v4: Vec<(u8, HashSet<u32>)>,
+    v6: Vec<(u8, HashSet<u128>)>,
+}
+
+fn mask_v4(address: u32, prefix_len: u8) -> u32 {
+    address & u32::MAX.checked_shl(32 - prefix_len as u32).unwrap_or(0)
+}
+
+fn mask_v6(address: u128, prefix_len: u8) -> u128 {
+    address & u128::MAX.checked_shl(128 - prefix_len as u32).unwrap_or(0)
+}
+
+/// An IP address or CIDR range, with any host bits cleared
+pub fn parse_network(entry: &str) -> Result<IpNet> {
+    let entry = entry.trim();
+    let network = entry
+        .parse::<IpNet>()
+        .or_else(|_| entry.parse::<IpAddr>().map(IpNet::from))
+        .with_context(|| format!("'{}' is not an IP address or CIDR range", entry))?;
+    Ok(network.trunc())
+}
+
+/// An IPv4 client reached over IPv6 as its IPv4 address, so it matches IPv4 entries
+pub fn canonical_ip(ip: IpAddr) -> IpAddr {
+    match ip {
+        IpAddr::V6(v6) => v6.to_ipv4_mapped().map(IpAddr::V4).unwrap_or(ip),
+        ip => ip,
+    }
+}
+
+/// The network a client is tracked as: its canonical address, or for IPv6
+/// its `/ipv6_prefix_len` network, since one client usually holds a whole
+/// prefix and could otherwise rotate addresses to get a fresh limit
+pub fn client_network(ip: IpAddr, ipv6_prefix_len: u8) -> IpNet {
+    let ip = canonical_ip(ip);
+    match ip {
+        IpAddr::V6(_) => IpNet::new(ip, ipv6_prefix_len).map_or(IpNet::from(ip), |network| network.trunc()),
+        IpAddr::V4(_) => IpNet::from(ip),
+    }
+}
+
+/// `client_network` as a key segment: `203.0.113.7`, `2001-db8--1`, or
+/// `2001-db8-1-2--/64` when grouped. Every spelling of an address gives the
+/// same segment, and it has no `:` to be confused with a key separator.
+pub fn ip_key(ip: IpAddr, ipv6_prefix_len: u8) -> String {
+    let network = client_network(ip, ipv6_prefix_len);
+    let address = network.addr().to_string().replace(':', "-");
+    match network {
+        IpNet::V6(v6) if v6.prefix_len() < 128 => format!("{}/{}", address, v6.prefix_len()),
+        _ => address,
+    }
+}
+
+impl IpAllowlist {
+    /// Parse `entries`, each an IP address or a CIDR range
+    pub fn new<S: AsRef<str>>(entries: &[S]) -> Result<Self> {
+        let networks = entries
+            .iter()
+            .map(|entry| parse_network(entry.as_ref()).context("Invalid IP allowlist entry"))
+            .collect::<Result<Vec<_>>>()?;
+
+        let mut v4: Vec<(u8, HashSet<u32>)> = Vec::new();
+        let mut v6: Vec<(u8, HashSet<u128>)> = Vec::new();
+        for network in &networks {
+            match network {
+                IpNet::V4(network) => {
+                    let prefix_len = network.prefix_len();
+                    let address = u32::from(network.network());
+                    match v4.iter_mut().find(|(len, _)| *len == prefix_len) {
+                        Some((_, set)) => {
+                            set.insert(address);
+                        }
+                        None => v4.push((prefix_len, HashSet::from([address]))),
+                    }
+                }
+                IpNet::V6(network) => {
+                    let prefix_len = network.prefix_len();
+                    let address = u128::from(network.network());
+                    match v6.iter_mut().find(|(len, _)| *len == prefix_len) {
+                        Some((_, set)) => {
+                            set.insert(address);
+                        }
+                        None => v6.push((prefix_len, HashSet::from([address]))),
+                    }
+                }
+            }
+        }
+
+        Ok(Self { entries: networks, v4, v6 })
+    }
+
+    /// The allowlist for `[security.ip_allowlist]`, or `None` if it's empty
+    pub fn from_config(config: &IpAllowlistConfig) -> Result<Option<Self>> {
+        if config.entries.is_empty() {
+            return Ok(None);
+        }
+        let allowlist = Self::new(&config.entries)?;
+        tracing::info!("IP allowlist enabled with {} entries", allowlist.entries.len());
+        Ok(Some(allowlist))
+    }
+
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        match canonical_ip(ip) {
+            IpAddr::V4(v4) => {
+                let address = u32::from(v4);
+                self.v4.iter().any(|(len, set)| set.contains(&mask_v4(address, *len)))
+            }
+            IpAddr::V6(v6) => {
+                let address = u128::from(v6);
+                self.v6.iter().any(|(len, set)| set.contains(&mask_v6(address, *len)))
+            }
+        }
+    }
+
+    pub fn entries(&self) -> &[IpNet] {
+        &self.entries
+    }
+}
+
+/// Mark requests from allowlisted clients with `IpAllowlisted`
+pub async fn ip_allowlist_middleware(
+    State(allowlist): State<Arc<IpAllowlist>>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    if let Some(ip) = crate::request_context::client_ip(&request).filter(|ip| allowlist.contains(*ip)) {
+        tracing::debug!(ip = %ip, "Request from allowlisted IP");
+        request.extensions_mut().insert(IpAllowlisted(ip));
+    }
+    next.run(request).await
+}
+
+#[derive(Debug, Serialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct IpAllowlistResponse {
+    /// Allowlisted networks in CIDR notation, single addresses as /32 or /128
+    pub entries: Vec<String>,
+}
+
+/// Read-only admin route for the allowlist; mount behind `admin_auth_middleware`
+pub fn create_ip_allowlist_router(allowlist: Arc<IpAllowlist>) -> Router {
+    Router::new()
+        .route("/v1/admin/ip-allowlist", get(get_ip_allowlist))
+        .with_state(allowlist)
+}
+
+#[cfg_attr(
+    feature = "openapi",
+    utoipa::path(
+        get,
+        path = "/v1/admin/ip-allowlist",
+        tag = "admin",
+        responses(
+            (status = 200, description = "Allowlisted IP ranges", body = IpAllowlistResponse),
+            (status = 401, description = "Missing or invalid API key"),
+            (status = 403, description = "API key is not an admin key"),
+        ),
+        security(("api_key" = [])),
+    )
+)]
+async fn get_ip_allowlist(State(allowlist): State<Arc<IpAllowlist>>) -> Json<IpAllowlistResponse> {
+    Json(IpAllowlistResponse {
+        entries: allowlist.entries().iter().map(ToString::to_string).collect(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Body, http::StatusCode, middleware, routing::get, Extension};
+    use tower::ServiceExt;
+
+    #[test]
+    fn test_matches_ipv4_and_ipv6_ranges() {
+        let allowlist = IpAllowlist::new(&["10.0.0.0/8", "192.0.2.10", "2001:db8::/32", "172.16.5.77/16"]).unwrap();
+
+        assert!(allowlist.contains("10.200.3.4".parse().unwrap()));
+        assert!(allowlist.contains("192.0.2.10".parse().unwrap()));
+        assert!(!allowlist.contains("192.0.2.11".parse().unwrap()));
+        assert!(allowlist.contains("2001:db8:ffff::1".parse().unwrap()));
+        assert!(!allowlist.contains("2001:db9::1".parse().unwrap()));
+        // Host bits in an entry are ignored
+        assert!(allowlist.contains("172.16.200.1".parse().unwrap()));
+        // IPv4-mapped IPv6
+        assert!(allowlist.contains("::ffff:10.1.2.3".parse().unwrap()));
+        assert!(!allowlist.contains("11.0.0.1".parse().unwrap()));
+
+        assert_eq!(
+            allowlist.entries().iter().map(ToString::to_string).collect::<Vec<_>>(),
+            ["10.0.0.0/8", "192.0.2.10/32", "2001:db8::/32", "172.16.0.0/16"]
+        );
+        assert!(IpAllowlist::new(&["10.0.0.0/33"]).is_err());
+        assert!(IpAllowlist::new(&["not-an-ip"]).is_err());
+    }
+
+    #[test]
+    fn test_ip_keys() {
+        let key = |ip: &str, prefix_len| ip_key(ip.parse().unwrap(), prefix_len);
+
+        assert_eq!(key("203.0.113.7", 64), "203.0.113.7");
+        assert_eq!(key("::ffff:203.0.113.7", 128), "203.0.113.7");
+        assert_eq!(key("2001:DB8:0:0:0:0:0:1", 128), "2001-db8--1");
+        assert_eq!(key("2001:db8::1", 128), key("2001:0db8::0001", 128));
+        assert_ne!(key("2001:db8::1", 128), key("2001:db8::2", 128));
+
+        // A client rotating within its /64 keeps one key
+        assert_eq!(key("2001:db8:1:2::1", 64), "2001-db8-1-2--/64");
+        assert_eq!(key("2001:db8:1:2:aaaa:bbbb:cccc:dddd", 64), "2001-db8-1-2--/64");
+        assert_ne!(key("2001:db8:1:3::1", 64), "2001-db8-1-2--/64");
+        assert_eq!(client_network("2001:db8:1:2::1".parse().unwrap(), 64).to_string(), "2001:db8:1:2::/64");
+        assert!(!key("2001:db8:1:2::1", 64).contains(':'));
+    }
+
+    #[test]
+    fn test_whole_address_space() {
+        let allowlist = IpAllowlist::new(&["0.0.0.0/0"]).unwrap();
+        assert!(allowlist.contains("203.0.113.9".parse().unwrap()));
+        assert!(!allowlist.contains("2001:db8::1".parse().unwrap()));
+    }
+
+    #[tokio::test]
+    async fn test_middleware_marks_allowlisted_clients() {
+        let allowlist = Arc::new(IpAllowlist::new(&["10.0.0.0/8"]).unwrap());
+        let app = Router::new()
+            .route(
+                "/",
+                get(|allowlisted: Option<Extension<IpAllowlisted>>| async move {
+                    if allowlisted.is_some() { "allowlisted" } else { "not allowlisted" }
+                }),
+            )
+            .layer(middleware::from_fn_with_state(allowlist, ip_allowlist_middleware));
+
+        // Without trusted proxies only the last hop counts, so a spoofed allowlisted hop doesn't
+        for (forwarded_for, expected) in [
+            ("10.1.2.3", "allowlisted"),
+            ("198.51.100.1", "not allowlisted"),
+            ("10.1.2.3, 198.51.100.1", "not allowlisted"),
+        ] {
+            let request = axum::http::Request::builder()
+                .uri("/")
+                .header("x-forwarded-for", forwarded_for)
+                .body(Body::empty())
+                .unwrap();
+            let response = app.clone().oneshot(request).await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+            assert_eq!(body, expected);
+        }
+    }
+}
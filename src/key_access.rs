@@ -0,0 +1,472 @@
+//! Operator-managed allow and deny lists of API keys.
+//!
+//! Keys are listed by their `ApiKeyIdentity::key_id`, the hash prefix that
+//! audit events already record, in the Redis sets `api_keys:allow` and
+//! `api_keys:deny`. `ApiKeyValidator` consults them on every authenticated
+//! request: a denylisted key is refused with 403 and audited, and an
+//! allowlisted key skips rate limiting on `/v1/check` and `/v1/limit/batch`
+//! (recorded in analytics as bypassed). A key on both lists is denied.
+//!
+//! Each instance caches both sets for a few seconds, so a change made through
+//! the admin endpoints reaches every instance within the cache TTL, without a
+//! restart. If Redis can't be read the last copy keeps being used, or no key
+//! is listed if there never was one.
+
+use axum::{
+    extract::{Path, State},
+    response::Json,
+    routing::{delete, get, post},
+    Extension, Router,
+};
+use redis::AsyncCommands;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+
+use crate::api_error::ApiError;
+use crate::audit::{audit_event::{ActorInfo, AuditOutcome}, AuditLogger};
+use crate::auth::{ApiKeyIdentity, ApiKeyValidator};
+use crate::redis_backend::RedisConnector;
+
+const ALLOWLIST_KEY: &str = "api_keys:allow";
+const DENYLIST_KEY: &str = "api_keys:deny";
+
+/// How long an instance uses its copy of the lists before rereading them
+pub const DEFAULT_CACHE_TTL: Duration = Duration::from_secs(5);
+
+/// What the lists say about one key
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyAccess {
+    /// On neither list
+    Default,
+    /// Bypasses rate limits
+    Allowlisted,
+    /// Refused before anything else runs
+    Denied,
+}
+
+/// One of the two lists
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum KeyList {
+    Allow,
+    Deny,
+}
+
+impl KeyList {
+    fn redis_key(&self) -> &'static str {
+        match self {
+            KeyList::Allow => ALLOWLIST_KEY,
+            KeyList::Deny => DENYLIST_KEY,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            KeyList::Allow => "allow",
+            KeyList::Deny => "deny",
+        }
+    }
+}
+
+pub struct KeyAccessList {
+    redis: RedisConnector,
+    cache_ttl: Duration,
+    cache: RwLock<Option<Snapshot>>,
+    audit: Option<Arc<AuditLogger>>,
+}
+
+/// Both lists as read from Redis at `fetched_at`
+struct Snapshot {
+    allow: HashSet<String>,
+    deny: HashSet<String>,
+    fetched_at: Instant,
+}
+
+impl Snapshot {
+    fn access(&self, key_id: &str) -> KeyAccess {
+        // Deny is checked first so a key on both lists is refused
+        if self.deny.contains(key_id) {
+            KeyAccess::Denied
+        } else if self.allow.contains(key_id) {
+            KeyAccess::Allowlisted
+        } else {
+            KeyAccess::Default
+        }
+    }
+}
+
+impl KeyAccessList {
+    pub fn new(redis: RedisConnector) -> Self {
+        Self {
+            redis,
+            cache_ttl: DEFAULT_CACHE_TTL,
+            cache: RwLock::new(None),
+            audit: None,
+        }
+    }
+
+    pub fn with_cache_ttl(mut self, cache_ttl: Duration) -> Self {
+        self.cache_ttl = cache_ttl;
+        self
+    }
+
+    /// Record refused requests as security events
+    pub fn with_audit(mut self, audit: Arc<AuditLogger>) -> Self {
+        self.audit = Some(audit);
+        self
+    }
+
+    /// Lists fixed in memory, never read from Redis
+    #[cfg(test)]
+    pub(crate) fn with_lists(allow: &[&str], deny: &[&str]) -> Self {
+        let list = Self::new(RedisConnector::open("redis://127.0.0.1:1").unwrap()).with_cache_ttl(Duration::MAX);
+        *list.cache.write().unwrap() = Some(Snapshot {
+            allow: allow.iter().map(|key_id| key_id.to_string()).collect(),
+            deny: deny.iter().map(|key_id| key_id.to_string()).collect(),
+            fetched_at: Instant::now(),
+        });
+        list
+    }
+
+    /// Look up a key, rereading the lists if the cached copy is stale
+    pub async fn access(&self, key_id: &str) -> KeyAccess {
+        if let Some(snapshot) = self.cache.read().unwrap().as_ref() {
+            if snapshot.fetched_at.elapsed() < self.cache_ttl {
+                return snapshot.access(key_id);
+            }
+        }
+
+        match self.fetch().await {
+            Ok(snapshot) => {
+                let access = snapshot.access(key_id);
+                *self.cache.write().unwrap() = Some(snapshot);
+                access
+            }
+            Err(e) => {
+                tracing::warn!("Failed to refresh API key access lists, using cached copy: {:#}", e);
+                self.cache
+                    .read()
+                    .unwrap()
+                    .as_ref()
+                    .map_or(KeyAccess::Default, |snapshot| snapshot.access(key_id))
+            }
+        }
+    }
+
+    async fn fetch(&self) -> anyhow::Result<Snapshot> {
+        let mut conn = self.redis.get_async_connection().await?;
+        let (allow, deny): (HashSet<String>, HashSet<String>) = redis::pipe()
+            .smembers(ALLOWLIST_KEY)
+            .smembers(DENYLIST_KEY)
+            .query_async(&mut conn)
+            .await?;
+        Ok(Snapshot {
+            allow,
+            deny,
+            fetched_at: Instant::now(),
+        })
+    }
+
+    /// Key IDs on a list, sorted
+    pub async fn members(&self, list: KeyList) -> anyhow::Result<Vec<String>> {
+        let mut conn = self.redis.get_async_connection().await?;
+        let mut members: Vec<String> = conn.smembers(list.redis_key()).await?;
+        members.sort();
+        Ok(members)
+    }
+
+    /// Put a key on a list; false if it was already there
+    pub async fn add(&self, list: KeyList, key_id: &str) -> anyhow::Result<bool> {
+        let mut conn = self.redis.get_async_connection().await?;
+        let added: u64 = conn.sadd(list.redis_key(), key_id).await?;
+        self.invalidate();
+        Ok(added > 0)
+    }
+
+    /// Take a key off a list; false if it wasn't there
+    pub async fn remove(&self, list: KeyList, key_id: &str) -> anyhow::Result<bool> {
+        let mut conn = self.redis.get_async_connection().await?;
+        let removed: u64 = conn.srem(list.redis_key(), key_id).await?;
+        self.invalidate();
+        Ok(removed > 0)
+    }
+
+    /// Make this instance reread the lists on the next lookup
+    fn invalidate(&self) {
+        *self.cache.write().unwrap() = None;
+    }
+
+    /// Audit a request refused because its key is denylisted
+    pub async fn record_denial(&self, identity: &ApiKeyIdentity, path: &str) {
+        tracing::warn!(key_id = %identity.key_id, path, "Denylisted API key refused");
+
+        let Some(audit) = &self.audit else {
+            return;
+        };
+        if let Err(e) = audit
+            .log_security_event(
+                ActorInfo::new().with_api_key(identity.key_id.clone()),
+                "api_key_denied",
+                "authentication",
+                AuditOutcome::Success, // Successfully refused the request
+                None,
+                Some("medium"),
+                Some(&format!("Denylisted API key refused on {path}")),
+                None,
+            )
+            .await
+        {
+            tracing::error!("Failed to audit denylisted key: {}", e);
+        }
+    }
+}
+
+/// Whether `key_id` looks like an `ApiKeyIdentity::key_id`
+fn is_key_id(key_id: &str) -> bool {
+    key_id.len() == 16 && key_id.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+pub struct KeyAccessApiState {
+    pub access: Arc<KeyAccessList>,
+    pub validator: Arc<ApiKeyValidator>,
+    pub audit: Arc<AuditLogger>,
+}
+
+/// The key to put on a list: its key ID, or the key itself to have it hashed
+#[derive(Debug, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct KeyAccessEntry {
+    pub key_id: Option<String>,
+    pub api_key: Option<String>,
+    /// Kept in the audit trail
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct KeyAccessListsResponse {
+    pub allow: Vec<String>,
+    pub deny: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct KeyAccessChangeResponse {
+    pub list: String,
+    pub key_id: String,
+    /// False if the list already had (or didn't have) the key
+    pub changed: bool,
+}
+
+/// Admin routes for the lists; mount behind `admin_auth_middleware`
+pub fn create_key_access_router(
+    access: Arc<KeyAccessList>,
+    validator: Arc<ApiKeyValidator>,
+    audit: Arc<AuditLogger>,
+) -> Router {
+    Router::new()
+        .route("/v1/admin/keys", get(get_key_access_lists))
+        .route("/v1/admin/keys/:list", post(add_key_to_list))
+        .route("/v1/admin/keys/:list/:key_id", delete(remove_key_from_list))
+        .with_state(Arc::new(KeyAccessApiState { access, validator, audit }))
+}
+
+#[cfg_attr(
+    feature = "openapi",
+    utoipa::path(
+        get,
+        path = "/v1/admin/keys",
+        tag = "admin",
+        responses(
+            (status = 200, description = "Allowlisted and denylisted key IDs", body = KeyAccessListsResponse),
+            (status = 500, description = "Internal error"),
+            (status = 401, description = "Missing or invalid API key"),
+            (status = 403, description = "API key is not an admin key"),
+        ),
+        security(("api_key" = [])),
+    )
+)]
+async fn get_key_access_lists(
+    State(state): State<Arc<KeyAccessApiState>>,
+) -> Result<Json<KeyAccessListsResponse>, ApiError> {
+    let read = async {
+        Ok::<_, anyhow::Error>(KeyAccessListsResponse {
+            allow: state.access.members(KeyList::Allow).await?,
+            deny: state.access.members(KeyList::Deny).await?,
+        })
+    };
+    read.await.map(Json).map_err(|e| {
+        tracing::error!("Failed to read API key access lists: {}", e);
+        ApiError::internal("Failed to read API key access lists")
+    })
+}
+
+#[cfg_attr(
+    feature = "openapi",
+    utoipa::path(
+        post,
+        path = "/v1/admin/keys/{list}",
+        tag = "admin",
+        params(("list" = String, Path, description = "`allow` or `deny`")),
+        request_body = KeyAccessEntry,
+        responses(
+            (status = 200, description = "Key is on the list", body = KeyAccessChangeResponse),
+            (status = 400, description = "Unknown list, or not exactly one of key_id and api_key"),
+            (status = 500, description = "Internal error"),
+            (status = 401, description = "Missing or invalid API key"),
+            (status = 403, description = "API key is not an admin key"),
+        ),
+        security(("api_key" = [])),
+    )
+)]
+async fn add_key_to_list(
+    State(state): State<Arc<KeyAccessApiState>>,
+    Extension(identity): Extension<ApiKeyIdentity>,
+    Path(list): Path<KeyList>,
+    Json(entry): Json<KeyAccessEntry>,
+) -> Result<Json<KeyAccessChangeResponse>, ApiError> {
+    let key_id = match (&entry.key_id, &entry.api_key) {
+        (Some(key_id), None) if is_key_id(key_id) => key_id.to_ascii_lowercase(),
+        (None, Some(api_key)) if state.validator.validate_key(api_key) => state.validator.identity(api_key).key_id,
+        _ => {
+            return Err(ApiError::bad_request(
+                "INVALID_KEY",
+                "Give exactly one of key_id, a 16-character key ID, or api_key, a valid API key",
+            ))
+        }
+    };
+
+    let result = state.access.add(list, &key_id).await;
+    audit_change(&state, identity, "add_key_to_list", list, &key_id, entry.reason.as_deref(), &result).await;
+
+    result
+        .map(|changed| {
+            Json(KeyAccessChangeResponse {
+                list: list.as_str().to_string(),
+                key_id,
+                changed,
+            })
+        })
+        .map_err(|e| {
+            tracing::error!("Failed to update API key {}list: {}", list.as_str(), e);
+            ApiError::internal(format!("Failed to update API key {}list", list.as_str()))
+        })
+}
+
+#[cfg_attr(
+    feature = "openapi",
+    utoipa::path(
+        delete,
+        path = "/v1/admin/keys/{list}/{key_id}",
+        tag = "admin",
+        params(
+            ("list" = String, Path, description = "`allow` or `deny`"),
+            ("key_id" = String, Path, description = "Key ID as recorded in audit events"),
+        ),
+        responses(
+            (status = 200, description = "Key is off the list", body = KeyAccessChangeResponse),
+            (status = 400, description = "Unknown list or malformed key ID"),
+            (status = 500, description = "Internal error"),
+            (status = 401, description = "Missing or invalid API key"),
+            (status = 403, description = "API key is not an admin key"),
+        ),
+        security(("api_key" = [])),
+    )
+)]
+async fn remove_key_from_list(
+    State(state): State<Arc<KeyAccessApiState>>,
+    Extension(identity): Extension<ApiKeyIdentity>,
+    Path((list, key_id)): Path<(KeyList, String)>,
+) -> Result<Json<KeyAccessChangeResponse>, ApiError> {
+    if !is_key_id(&key_id) {
+        return Err(ApiError::bad_request("INVALID_KEY", "key_id must be a 16-character key ID"));
+    }
+    let key_id = key_id.to_ascii_lowercase();
+
+    let result = state.access.remove(list, &key_id).await;
+    audit_change(&state, identity, "remove_key_from_list", list, &key_id, None, &result).await;
+
+    result
+        .map(|changed| {
+            Json(KeyAccessChangeResponse {
+                list: list.as_str().to_string(),
+                key_id,
+                changed,
+            })
+        })
+        .map_err(|e| {
+            tracing::error!("Failed to update API key {}list: {}", list.as_str(), e);
+            ApiError::internal(format!("Failed to update API key {}list", list.as_str()))
+        })
+}
+
+async fn audit_change(
+    state: &KeyAccessApiState,
+    identity: ApiKeyIdentity,
+    action: &str,
+    list: KeyList,
+    key_id: &str,
+    reason: Option<&str>,
+    result: &anyhow::Result<bool>,
+) {
+    let (outcome, details) = match result {
+        Ok(changed) => (
+            AuditOutcome::Success,
+            serde_json::json!({ "list": list.as_str(), "changed": changed, "reason": reason }),
+        ),
+        Err(e) => (
+            AuditOutcome::Failure,
+            serde_json::json!({ "list": list.as_str(), "reason": reason, "error": e.to_string() }),
+        ),
+    };
+    if let Err(e) = state
+        .audit
+        .log_admin_action(
+            ActorInfo::new().with_api_key(identity.key_id),
+            action,
+            "api_key",
+            Some(key_id),
+            outcome,
+            None,
+            Some(details),
+        )
+        .await
+    {
+        tracing::error!("Failed to audit API key list change: {}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_deny_takes_precedence_over_allow() {
+        let list = KeyAccessList::with_lists(&["0123456789abcdef", "aaaaaaaaaaaaaaaa"], &["0123456789abcdef"]);
+
+        assert_eq!(list.access("0123456789abcdef").await, KeyAccess::Denied);
+        assert_eq!(list.access("aaaaaaaaaaaaaaaa").await, KeyAccess::Allowlisted);
+        assert_eq!(list.access("bbbbbbbbbbbbbbbb").await, KeyAccess::Default);
+    }
+
+    #[tokio::test]
+    async fn test_stale_copy_is_kept_when_redis_is_unreachable() {
+        let list = KeyAccessList::with_lists(&[], &["0123456789abcdef"]).with_cache_ttl(Duration::ZERO);
+
+        // The refresh fails, so the denylist still applies
+        assert_eq!(list.access("0123456789abcdef").await, KeyAccess::Denied);
+
+        list.invalidate();
+        assert_eq!(list.access("0123456789abcdef").await, KeyAccess::Default);
+    }
+
+    #[test]
+    fn test_key_ids_are_hash_prefixes() {
+        let validator = ApiKeyValidator::new("test_secret".to_string());
+        assert!(is_key_id(&validator.identity("rw_1234567890abcdef1234567890abcdef").key_id));
+        assert!(!is_key_id("rw_1234567890abcdef1234567890abcdef"));
+        assert!(!is_key_id("0123456789abcdeg"));
+    }
+}
@@ -0,0 +1,361 @@
+//! Tower middleware that applies a `RateLimiter` in front of any service.
+//!
+//! ```ignore
+//! let limiter = Arc::new(RateLimiter::new(redis_url)?.with_strategy(RateLimitStrategy::SlidingWindow));
+//! let app = Router::new()
+//!     .route("/api", get(handler))
+//!     .layer(RateLimitLayer::new(limiter, 100, 60, |req: &Request<Body>| {
+//!         req.headers().get("x-api-key")?.to_str().ok().map(str::to_string)
+//!     }));
+//! ```
+//!
+//! Any `KeyExtractor` works in place of the closure, e.g. `ByIp` or a
+//! `Composite` of several. Routes declared under `[[rate_limiting.routes]]`
+//! get their tiers and extractor from config with
+//! `RateLimitLayer::for_route(limiter, &route)`.
+
+use axum::{
+    body::Body,
+    http::{header, HeaderName, HeaderValue, Request},
+    response::{IntoResponse, Response},
+};
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
+use tower::{Layer, Service};
+
+use crate::api_error::ApiError;
+use crate::config::RouteLimitsConfig;
+use crate::key_extractor::KeyExtractor;
+use crate::rate_limiter::{RateLimitRequest, RateLimitResponse, RateLimitTier, RateLimiter};
+
+pub const RATE_LIMIT_LIMIT_HEADER: &str = "x-ratelimit-limit";
+pub const RATE_LIMIT_REMAINING_HEADER: &str = "x-ratelimit-remaining";
+pub const RATE_LIMIT_RESET_HEADER: &str = "x-ratelimit-reset";
+
+/// Rate limits requests by a key derived from each request.
+///
+/// Requests for which the extractor returns `None` are passed through
+/// unlimited. Denied requests get 429 without reaching the inner service. The
+/// algorithm is whatever strategy the shared `RateLimiter` was built with.
+/// With several tiers a request must fit in all of them, and the headers
+/// describe the tier closest to its limit.
+pub struct RateLimitLayer<K: ?Sized> {
+    limiter: Arc<RateLimiter>,
+    key_extractor: Arc<K>,
+    /// (limit, window) pairs
+    tiers: Arc<[(u64, u64)]>,
+    cost: u64,
+}
+
+impl<K: KeyExtractor> RateLimitLayer<K> {
+    pub fn new(limiter: Arc<RateLimiter>, limit: u64, window: u64, key_extractor: K) -> Self {
+        Self {
+            limiter,
+            key_extractor: Arc::new(key_extractor),
+            tiers: Arc::new([(limit, window)]),
+            cost: 1,
+        }
+    }
+
+    /// Enforce several limits at once, e.g. 10 per second and 1000 per hour
+    pub fn with_tiers(limiter: Arc<RateLimiter>, tiers: &[RateLimitTier], key_extractor: K) -> Self {
+        Self {
+            limiter,
+            key_extractor: Arc::new(key_extractor),
+            tiers: tiers.iter().map(|tier| (tier.limit, tier.window)).collect(),
+            cost: 1,
+        }
+    }
+
+}
+
+impl RateLimitLayer<dyn KeyExtractor> {
+    /// Tiers and key extractor taken from a `[[rate_limiting.routes]]` entry
+    pub fn for_route(limiter: Arc<RateLimiter>, route: &RouteLimitsConfig) -> Self {
+        Self {
+            limiter,
+            key_extractor: Arc::from(route.key.build()),
+            tiers: route.limits.iter().map(|tier| (tier.limit, tier.window)).collect(),
+            cost: 1,
+        }
+    }
+}
+
+impl<K: ?Sized> RateLimitLayer<K> {
+    /// Units consumed per request (default 1)
+    pub fn with_cost(mut self, cost: u64) -> Self {
+        self.cost = cost;
+        self
+    }
+}
+
+impl<K: ?Sized> Clone for RateLimitLayer<K> {
+    fn clone(&self) -> Self {
+        Self {
+            limiter: self.limiter.clone(),
+            key_extractor: self.key_extractor.clone(),
+            tiers: self.tiers.clone(),
+            cost: self.cost,
+        }
+    }
+}
+
+impl<S, K: ?Sized> Layer<S> for RateLimitLayer<K> {
+    type Service = RateLimitService<S, K>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RateLimitService {
+            inner,
+            config: self.clone(),
+        }
+    }
+}
+
+pub struct RateLimitService<S, K: ?Sized> {
+    inner: S,
+    config: RateLimitLayer<K>,
+}
+
+impl<S: Clone, K: ?Sized> Clone for RateLimitService<S, K> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            config: self.config.clone(),
+        }
+    }
+}
+
+impl<S, K> Service<Request<Body>> for RateLimitService<S, K>
+where
+    S: Service<Request<Body>, Response = Response> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+    K: KeyExtractor + ?Sized,
+{
+    type Response = Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<Body>) -> Self::Future {
+        // Use the service that was polled ready and leave a fresh clone in its place
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+
+        let key = self.config.key_extractor.extract(&request);
+        let limiter = self.config.limiter.clone();
+        let (tiers, cost) = (self.config.tiers.clone(), self.config.cost);
+
+        Box::pin(async move {
+            let key = match key {
+                Some(key) => key,
+                None => return inner.call(request).await,
+            };
+
+            let decision = match *tiers {
+                [(limit, window)] => limiter
+                    .check(RateLimitRequest {
+                        key,
+                        limit,
+                        window,
+                        cost,
+                    })
+                    .await
+                    .map(|decision| (limit, decision)),
+                _ => limiter.check_multi(&key, &tiers, cost).await.map(|mut multi| {
+                    let binding = multi.binding_tier();
+                    (tiers[binding].0, multi.tiers.swap_remove(binding))
+                }),
+            };
+
+            match decision {
+                Ok((limit, decision)) if !decision.allowed => Ok(denied_response(limit, &decision)),
+                Ok((limit, decision)) => {
+                    let mut response = inner.call(request).await?;
+                    insert_rate_limit_headers(&mut response, limit, &decision);
+                    Ok(response)
+                }
+                Err(e) => {
+                    // Redis errors are already resolved by the limiter's failure
+                    // mode; this is an invalid check such as an empty key
+                    tracing::warn!("Rate limit check failed, allowing request: {}", e);
+                    inner.call(request).await
+                }
+            }
+        })
+    }
+}
+
+pub(crate) fn insert_rate_limit_headers(response: &mut Response, limit: u64, decision: &RateLimitResponse) {
+    let headers = response.headers_mut();
+    for (name, value) in [
+        (RATE_LIMIT_LIMIT_HEADER, limit),
+        (RATE_LIMIT_REMAINING_HEADER, decision.remaining),
+        (RATE_LIMIT_RESET_HEADER, decision.reset_in),
+    ] {
+        headers.insert(HeaderName::from_static(name), HeaderValue::from(value));
+    }
+}
+
+pub(crate) fn denied_response(limit: u64, decision: &RateLimitResponse) -> Response {
+    let mut error = ApiError::too_many_requests("RATE_LIMITED", "Rate limit exceeded");
+    if let Some(reason) = decision.deny_reason {
+        error = error.with_deny_reason(reason);
+    }
+    let mut response = error.into_response();
+
+    insert_rate_limit_headers(&mut response, limit, decision);
+    if let Some(retry_after) = decision.retry_after {
+        response
+            .headers_mut()
+            .insert(header::RETRY_AFTER, HeaderValue::from(retry_after));
+    }
+
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{http::StatusCode, routing::get, Router};
+    use tower::ServiceExt;
+
+    fn app(limiter: RateLimiter, limit: u64) -> Router {
+        Router::new().route("/", get(|| async { "ok" })).layer(RateLimitLayer::new(
+            Arc::new(limiter),
+            limit,
+            60,
+            |request: &Request<Body>| {
+                request
+                    .headers()
+                    .get("x-client-id")
+                    .and_then(|value| value.to_str().ok())
+                    .map(|value| format!("test_layer:{value}"))
+            },
+        ))
+    }
+
+    fn request(client_id: Option<&str>) -> Request<Body> {
+        let mut builder = Request::builder().uri("/");
+        if let Some(client_id) = client_id {
+            builder = builder.header("x-client-id", client_id);
+        }
+        builder.body(Body::empty()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_requests_without_key_pass_through() {
+        // Nothing listens here; no key means Redis is never contacted
+        let limiter = RateLimiter::new("redis://127.0.0.1:1").unwrap();
+        let response = app(limiter, 1).oneshot(request(None)).await.unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(response.headers().get(RATE_LIMIT_REMAINING_HEADER).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_layer_denies_over_limit() {
+        let limiter = match RateLimiter::new("redis://127.0.0.1:6379") {
+            Ok(limiter) => limiter,
+            Err(_) => return,
+        };
+        if limiter.health_check().await.is_err() {
+            println!("Skipping layer test - Redis not available");
+            return;
+        }
+
+        let app = app(limiter, 2);
+        let client_id = uuid::Uuid::new_v4().to_string();
+
+        for remaining in ["1", "0"] {
+            let response = app.clone().oneshot(request(Some(&client_id))).await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+            assert_eq!(response.headers()[RATE_LIMIT_REMAINING_HEADER], remaining);
+        }
+
+        let response = app.oneshot(request(Some(&client_id))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert!(response.headers().get(header::RETRY_AFTER).is_some());
+    }
+
+    #[tokio::test]
+    async fn test_layer_enforces_every_tier() {
+        let limiter = match RateLimiter::new("redis://127.0.0.1:6379") {
+            Ok(limiter) => limiter,
+            Err(_) => return,
+        };
+        if limiter.health_check().await.is_err() {
+            println!("Skipping multi-tier layer test - Redis not available");
+            return;
+        }
+
+        let tiers = [
+            RateLimitTier { limit: 5, window: 1 },
+            RateLimitTier { limit: 2, window: 3600 },
+        ];
+        let app = Router::new().route("/", get(|| async { "ok" })).layer(RateLimitLayer::with_tiers(
+            Arc::new(limiter),
+            &tiers,
+            |request: &Request<Body>| {
+                request
+                    .headers()
+                    .get("x-client-id")
+                    .and_then(|value| value.to_str().ok())
+                    .map(|value| format!("test_layer:{value}"))
+            },
+        ));
+        let client_id = uuid::Uuid::new_v4().to_string();
+
+        for remaining in ["1", "0"] {
+            let response = app.clone().oneshot(request(Some(&client_id))).await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+            // Headers follow the hourly tier, the one closest to its limit
+            assert_eq!(response.headers()[RATE_LIMIT_LIMIT_HEADER], "2");
+            assert_eq!(response.headers()[RATE_LIMIT_REMAINING_HEADER], remaining);
+        }
+
+        let response = app.oneshot(request(Some(&client_id))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+    }
+
+    #[tokio::test]
+    async fn test_ipv6_client_cant_rotate_within_its_prefix() {
+        let limiter = match RateLimiter::new("redis://127.0.0.1:6379") {
+            Ok(limiter) => limiter,
+            Err(_) => return,
+        };
+        if limiter.health_check().await.is_err() {
+            println!("Skipping IPv6 layer test - Redis not available");
+            return;
+        }
+
+        let app = Router::new()
+            .route("/", get(|| async { "ok" }))
+            .layer(RateLimitLayer::new(Arc::new(limiter), 2, 60, crate::key_extractor::ByIp))
+            .layer(axum::middleware::from_fn_with_state(
+                Arc::new(crate::request_context::ClientIps::default().with_ipv6_prefix_len(64)),
+                crate::request_context::request_context_middleware,
+            ));
+        // A fresh /64 per run
+        let [a, b, ..] = uuid::Uuid::new_v4().into_bytes();
+        let prefix = format!("2001:db8:{:x}:{:x}", a, b);
+        let from = |ip: String| Request::builder().uri("/").header("x-forwarded-for", ip).body(Body::empty()).unwrap();
+
+        for host in ["1", "2"] {
+            let response = app.clone().oneshot(from(format!("{prefix}::{host}"))).await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+        }
+        let response = app.clone().oneshot(from(format!("{prefix}:ffff::3"))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+
+        // The neighbouring /64 is another client
+        let response = app.oneshot(from(format!("2001:db8:{:x}:{:x}::1", a, b as u16 + 0x100))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+}
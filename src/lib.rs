@@ -0,0 +1,37 @@
+//! RateWatch as a library.
+//!
+//! The `ratewatch` binary is built from these modules. Other axum apps can
+//! use them too, most often to rate limit their own routes with
+//! [`layer::RateLimitLayer`], keyed by a [`key_extractor::KeyExtractor`] and
+//! backed by a shared [`rate_limiter::RateLimiter`].
+
+pub mod alerting;
+pub mod analytics;
+#[cfg(feature = "websocket")]
+pub mod analytics_stream;
+pub mod api;
+pub mod api_error;
+pub mod audit;
+pub mod auth;
+pub mod config;
+pub mod enforcement;
+#[cfg(feature = "grpc")]
+pub mod grpc;
+pub mod health;
+pub mod ip_allowlist;
+pub mod key_access;
+pub mod key_extractor;
+pub mod layer;
+pub mod metrics;
+#[cfg(feature = "openapi")]
+pub mod openapi;
+pub mod policy;
+pub mod privacy;
+pub mod rate_limiter;
+pub mod redis_backend;
+pub mod request_context;
+pub mod request_limits;
+pub mod request_signing;
+pub mod security;
+pub mod telemetry;
+pub mod tenant;
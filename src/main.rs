@@ -1,57 +1,103 @@
-mod analytics;
-mod api;
-mod audit;
-mod auth;
-mod config;
-mod health;
-mod metrics;
-mod privacy;
-mod rate_limiter;
-mod security;
-mod tenant;
+mod cli;
 
 use anyhow::Result;
 use dotenvy::dotenv;
 use std::{env, sync::Arc};
 use tokio::net::TcpListener;
 
-use analytics::AnalyticsManager;
-use auth::ApiKeyValidator;
-use config::ConfigManager;
-use health::HealthCheckManager;
-use privacy::PrivacyManager;
-use security::ThreatDetector;
-use tenant::TenantManager;
+#[cfg(feature = "grpc")]
+use ratewatch::grpc;
+use ratewatch::{
+    alerting, api, audit, config, enforcement, key_access, metrics, rate_limiter, request_signing, security,
+    telemetry, tenant,
+};
+
+use ratewatch::analytics::AnalyticsManager;
+use ratewatch::auth::ApiKeyValidator;
+use ratewatch::config::ConfigManager;
+use ratewatch::health::HealthCheckManager;
+use ratewatch::privacy::PrivacyManager;
+use ratewatch::redis_backend::RedisConnector;
+use ratewatch::tenant::TenantManager;
+
+fn main() -> Result<()> {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.iter().any(|arg| arg == "--print-config-schema") {
+        return cli::print_config_schema();
+    }
 
-#[tokio::main]
-async fn main() -> Result<()> {
     // Load environment variables
     dotenv().ok();
 
+    if args.first().map(String::as_str) == Some("signing-secret") {
+        return cli::print_signing_secret();
+    }
+
+    build_runtime(&config::ConfigManager::startup_server_config())?.block_on(run(args))
+}
+
+/// The Tokio runtime, sized by `server.worker_threads` and
+/// `server.max_blocking_threads`, or Tokio's defaults where they're unset
+fn build_runtime(server: &config::ServerConfig) -> Result<tokio::runtime::Runtime> {
+    let mut builder = tokio::runtime::Builder::new_multi_thread();
+    builder.enable_all();
+    if let Some(worker_threads) = server.worker_threads {
+        builder.worker_threads(worker_threads);
+    }
+    if let Some(max_blocking_threads) = server.max_blocking_threads {
+        builder.max_blocking_threads(max_blocking_threads);
+    }
+    Ok(builder.build()?)
+}
+
+async fn run(args: Vec<String>) -> Result<()> {
+    if args.first().map(String::as_str) == Some("validate-config") {
+        let (code, report) = cli::validate_config(&args[1..]).await;
+        if code == 0 {
+            println!("{report}");
+        } else {
+            eprintln!("{report}");
+        }
+        std::process::exit(code);
+    }
+
     // Initialize enterprise configuration management
     tracing::info!("🔧 Initializing enterprise configuration management...");
-    let config_manager = ConfigManager::new().await?;
+    let config_manager = Arc::new(ConfigManager::new().await?);
     let enterprise_config = config_manager.get_config().await;
 
-    // Initialize structured logging based on configuration
-    let log_level = enterprise_config.observability.logging.level.as_str();
-    let log_format = &enterprise_config.observability.logging.format;
-    
-    if log_format == "json" && enterprise_config.observability.logging.structured {
-        tracing_subscriber::fmt()
-            .json()
-            .with_env_filter(log_level)
-            .init();
-    } else {
-        tracing_subscriber::fmt()
-            .with_env_filter(log_level)
-            .init();
-    }
+    // Initialize structured logging and span export based on configuration
+    telemetry::init_tracing(&enterprise_config.observability)?;
 
     tracing::info!("✅ Enterprise configuration loaded and validated");
+    tracing::info!(
+        worker_threads = tokio::runtime::Handle::current().metrics().num_workers(),
+        "Async runtime started"
+    );
+
+    // Hot-reload on config file edits and Vault version changes; the watcher
+    // logs each outcome, so the events only need draining
+    match config_manager.watch_changes().await {
+        Ok(mut config_events) => {
+            tokio::spawn(async move { while config_events.recv().await.is_some() {} });
+        }
+        Err(e) => tracing::warn!("Configuration hot-reload disabled: {}", e),
+    }
+
+    metrics::set_max_label_values(enterprise_config.observability.metrics.max_label_values);
+    let push_gateway = metrics::PushGatewayHandle::from_config(&enterprise_config.observability.metrics);
+    if push_gateway.is_some() {
+        tracing::info!("📤 Pushing metrics to Prometheus Pushgateway");
+    }
+    let alert_evaluator = alerting::start_alerting(&enterprise_config.observability.alerting)?;
+    if alert_evaluator.is_some() {
+        tracing::info!("🚨 Alert evaluation enabled");
+    }
 
     // Extract configuration values
-    let redis_url = env::var("REDIS_URL").unwrap_or_else(|_| "redis://127.0.0.1:6379".to_string());
+    let redis_url = env::var("REDIS_URL").unwrap_or_else(|_| enterprise_config.redis.url.expose_secret().to_string());
+    let redis = RedisConnector::from_config(&redis_url, &enterprise_config.redis)?;
+    tracing::info!("🗄️ Using {} Redis topology", redis.topology().name());
     let port = enterprise_config.server.port;
     let api_key_secret = env::var("API_KEY_SECRET").unwrap_or_else(|_| {
         tracing::warn!("Using default API_KEY_SECRET - change this in production!");
@@ -59,7 +105,16 @@ async fn main() -> Result<()> {
     });
 
     // Initialize rate limiter
-    let rate_limiter = Arc::new(rate_limiter::RateLimiter::new(&redis_url)?);
+    let rate_limiter = Arc::new(
+        rate_limiter::RateLimiter::from_connector(redis.clone())
+            .with_strategy(enterprise_config.rate_limiting.strategy)
+            .with_failure_mode(enterprise_config.rate_limiting.failure_mode)
+            .with_mode(enterprise_config.rate_limiting.mode)
+            .with_max_wait(std::time::Duration::from_millis(enterprise_config.rate_limiting.max_wait_ms))
+            .with_idempotency_ttl(std::time::Duration::from_secs(enterprise_config.rate_limiting.idempotency_ttl_seconds))
+            .with_enforcement(Arc::new(enforcement::EnforcementSwitch::new(redis.clone()))),
+    );
+    rate_limiter.validate_topology()?;
 
     // Initialize health check manager
     let health_manager = Arc::new(HealthCheckManager::new(rate_limiter.clone()));
@@ -83,12 +138,22 @@ async fn main() -> Result<()> {
         tracing::warn!("Using default AUDIT_SIGNING_KEY - change this in production!");
         "change-this-audit-signing-key-in-production-must-be-at-least-32-chars".to_string()
     });
+    // Earlier signing keys, comma-separated, so events they signed still verify
+    let retired_audit_signing_keys: Vec<String> = env::var("AUDIT_RETIRED_SIGNING_KEYS")
+        .unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|key| !key.is_empty())
+        .map(str::to_string)
+        .collect();
     
     let audit_logger = audit::initialize_audit_system(
-        "redis", // Use Redis for audit storage
-        Some(redis::Client::open(redis_url.as_str())?),
-        None,
+        &enterprise_config.security.audit.storage_backend,
+        Some(redis.clone()),
+        Some(&enterprise_config.security.audit.file),
+        Some(&enterprise_config.security.audit.buffer),
         &audit_signing_key,
+        &retired_audit_signing_keys,
     ).await?;
     
     tracing::info!("✅ Enterprise audit system initialized");
@@ -96,27 +161,59 @@ async fn main() -> Result<()> {
     // Initialize threat detection system
     tracing::info!("🛡️ Initializing threat detection system...");
     let threat_detector = security::initialize_security_system(
-        redis::Client::open(redis_url.as_str())?,
+        redis.clone(),
         &enterprise_config.security,
+        audit_logger.clone(),
     ).await?;
     
     tracing::info!("✅ Threat detection system initialized");
 
     // Initialize tenant management system
     tracing::info!("🏢 Initializing multi-tenant management system...");
-    let tenant_manager = Arc::new(tokio::sync::Mutex::new(
-        TenantManager::new(&redis_url, "ratewatch".to_string())?
-    ));
+    let mut tenant_manager = TenantManager::from_connector(redis.clone(), "ratewatch".to_string())
+        .with_audit(audit_logger.clone());
+    if let Some(siem) = threat_detector.siem() {
+        tenant_manager = tenant_manager.with_siem(siem.clone());
+    }
+    let tenant_manager = Arc::new(tokio::sync::Mutex::new(tenant_manager));
     tracing::info!("✅ Multi-tenant management system initialized");
 
     // Initialize security components
-    let api_key_validator = Arc::new(ApiKeyValidator::new(api_key_secret));
-    let privacy_manager = Arc::new(PrivacyManager::new(redis::Client::open(
-        redis_url.as_str(),
-    )?));
-    let analytics_manager = Arc::new(AnalyticsManager::new(redis::Client::open(
-        redis_url.as_str(),
-    )?));
+    let admin_api_keys = env::var("ADMIN_API_KEYS").unwrap_or_default();
+    let key_access = Arc::new(key_access::KeyAccessList::new(redis.clone()).with_audit(audit_logger.clone()));
+    let mut api_key_validator = ApiKeyValidator::new(api_key_secret)
+        .with_admin_keys(admin_api_keys.split(','))
+        .with_key_access(key_access)
+        // Tenant key routes are always mounted, so their keys always need resolving
+        .with_tenant_keys(Arc::new(tenant::api_keys::TenantKeyResolver::new(redis.clone())));
+    if let Some(verifier) =
+        request_signing::RequestVerifier::from_config(redis.clone(), &enterprise_config.security.request_signing)
+    {
+        tracing::info!("✍️ Signed requests required on authenticated routes");
+        api_key_validator = api_key_validator.with_request_verifier(Arc::new(verifier));
+    }
+    let api_key_validator = Arc::new(api_key_validator);
+    let privacy_manager = Arc::new(PrivacyManager::new(redis.clone()));
+    let analytics_manager = Arc::new(AnalyticsManager::new(redis).with_retention(std::time::Duration::from_secs(
+        enterprise_config.observability.analytics.retention_seconds,
+    )));
+
+    // Start the gRPC interface alongside HTTP, sharing the same limiter and auth
+    let (grpc_shutdown, grpc_shutdown_rx) = tokio::sync::watch::channel(false);
+    let grpc_server = if enterprise_config.server.grpc.enabled {
+        start_grpc_server(
+            &enterprise_config,
+            rate_limiter.clone(),
+            api_key_validator.clone(),
+            analytics_manager.clone(),
+            grpc_shutdown_rx,
+        )
+    } else {
+        None
+    };
+
+    // Kept for the shutdown flush; the router takes the detector itself
+    let siem = threat_detector.siem().cloned();
 
     // Create secure router
     let app = api::create_secure_router(
@@ -125,9 +222,11 @@ async fn main() -> Result<()> {
         privacy_manager,
         analytics_manager,
         health_manager,
-        audit_logger,
+        audit_logger.clone(),
         threat_detector,
         tenant_manager,
+        config_manager,
+        &enterprise_config,
     );
 
     // Start server
@@ -138,7 +237,129 @@ async fn main() -> Result<()> {
     );
     tracing::info!("🔒 Security features: API key auth, GDPR compliance, secure headers");
 
-    axum::serve(listener, app).await?;
+    // On a signal, stop accepting connections and let in-flight requests
+    // finish, for up to the drain timeout
+    let drain_timeout = std::time::Duration::from_secs(enterprise_config.server.shutdown_timeout_seconds);
+    let mut draining = grpc_shutdown.subscribe();
+    // The peer address decides whether forwarding headers are believed
+    let server = axum::serve(listener, app.into_make_service_with_connect_info::<std::net::SocketAddr>())
+        .with_graceful_shutdown(async move {
+            shutdown_signal().await;
+            tracing::info!(
+                timeout_seconds = drain_timeout.as_secs(),
+                "Shutdown signal received, draining in-flight requests"
+            );
+            let _ = grpc_shutdown.send(true);
+        });
+    tokio::select! {
+        result = std::future::IntoFuture::into_future(server) => {
+            result?;
+            tracing::info!("In-flight requests drained");
+        }
+        _ = async {
+            let _ = draining.wait_for(|draining| *draining).await;
+            tokio::time::sleep(drain_timeout).await;
+        } => {
+            tracing::warn!(
+                timeout_seconds = drain_timeout.as_secs(),
+                "Requests still in flight after the drain timeout, shutting down anyway"
+            );
+        }
+    }
+
+    if let Some(grpc_server) = grpc_server {
+        let _ = grpc_server.await;
+    }
+    if let Some(siem) = siem {
+        let (queued_events, pending_batches) = siem.backlog();
+        tracing::info!(queued_events, pending_batches, "Flushing SIEM events");
+        if tokio::time::timeout(drain_timeout, siem.flush()).await.is_err() {
+            let (queued_events, pending_batches) = siem.backlog();
+            tracing::warn!(queued_events, pending_batches, "SIEM flush timed out, undelivered events are lost");
+        }
+    }
+    if let Some(push_gateway) = push_gateway {
+        push_gateway.stop().await;
+    }
+    if let Some(alert_evaluator) = alert_evaluator {
+        alert_evaluator.stop().await;
+    }
+    // Buffered audit events must reach storage before the process exits
+    tracing::info!("Flushing audit events");
+    audit_logger.shutdown().await;
+    tracing::info!("Shutdown complete");
+    telemetry::shutdown_tracing();
 
     Ok(())
 }
+
+/// Resolves on Ctrl-C, or on SIGTERM from an orchestrator stopping the process
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            tracing::error!("Failed to listen for Ctrl-C: {}", e);
+            std::future::pending::<()>().await;
+        }
+    };
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut sigterm) => {
+                sigterm.recv().await;
+            }
+            Err(e) => {
+                tracing::error!("Failed to listen for SIGTERM: {}", e);
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {}
+        _ = terminate => {}
+    }
+}
+
+#[cfg(feature = "grpc")]
+fn start_grpc_server(
+    config: &config::EnterpriseConfig,
+    rate_limiter: Arc<rate_limiter::RateLimiter>,
+    api_key_validator: Arc<ApiKeyValidator>,
+    analytics: Arc<AnalyticsManager>,
+    mut shutdown: tokio::sync::watch::Receiver<bool>,
+) -> Option<tokio::task::JoinHandle<()>> {
+    let addr = std::net::SocketAddr::from(([0, 0, 0, 0], config.server.grpc.port));
+    let service = grpc::RateLimitService::new(
+        rate_limiter,
+        api_key_validator,
+        analytics,
+        config.rate_limiting.max_batch_size,
+    )
+    .with_envoy_defaults(
+        config.server.grpc.envoy_default_limit,
+        config.server.grpc.envoy_default_window,
+    );
+
+    Some(tokio::spawn(async move {
+        let shutdown = async move {
+            let _ = shutdown.changed().await;
+        };
+        if let Err(e) = grpc::serve(service, addr, shutdown).await {
+            tracing::error!("gRPC server failed: {}", e);
+        }
+    }))
+}
+
+#[cfg(not(feature = "grpc"))]
+fn start_grpc_server(
+    _config: &config::EnterpriseConfig,
+    _rate_limiter: Arc<rate_limiter::RateLimiter>,
+    _api_key_validator: Arc<ApiKeyValidator>,
+    _analytics: Arc<AnalyticsManager>,
+    _shutdown: tokio::sync::watch::Receiver<bool>,
+) -> Option<tokio::task::JoinHandle<()>> {
+    tracing::warn!("gRPC is enabled in configuration but ratewatch was built without the `grpc` feature");
+    None
+}
@@ -1,37 +1,334 @@
-use axum::{http::StatusCode, response::Response, routing::get, Router};
+use axum::{
+    extract::{MatchedPath, Request},
+    http::StatusCode,
+    middleware::Next,
+    response::Response,
+    routing::get,
+    Router,
+};
 use once_cell::sync::Lazy;
-use prometheus::{Counter, Histogram, HistogramOpts, IntCounter, IntGauge, Registry, TextEncoder};
+use prometheus::{
+    Counter, Histogram, HistogramOpts, HistogramVec, IntCounter, IntCounterVec, IntGauge, Opts,
+    Registry, TextEncoder,
+};
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::{sync::watch, task::JoinHandle};
+
+use crate::config::MetricsConfig;
+
+/// Default number of distinct values tracked per label before bucketing kicks in
+pub const DEFAULT_MAX_LABEL_VALUES: usize = 100;
+
+/// Number of hash buckets that high-cardinality label values are folded into
+const OVERFLOW_BUCKETS: u8 = 16;
+
+/// Register a collector, tolerating duplicates so repeated router/test setup is safe
+fn register_collector<C>(registry: &Registry, collector: C)
+where
+    C: prometheus::core::Collector + 'static,
+{
+    match registry.register(Box::new(collector)) {
+        Ok(()) | Err(prometheus::Error::AlreadyReg) => {}
+        Err(e) => tracing::warn!("Failed to register metric: {}", e),
+    }
+}
 
 // Global metrics
 pub static REGISTRY: Lazy<Registry> = Lazy::new(|| {
     let registry = Registry::new();
 
     // Register default metrics
-    registry.register(Box::new(REQUEST_TOTAL.clone())).unwrap();
-    registry
-        .register(Box::new(REQUEST_DURATION.clone()))
-        .unwrap();
-    registry
-        .register(Box::new(RATE_LIMIT_HITS.clone()))
-        .unwrap();
-    registry
-        .register(Box::new(RATE_LIMIT_MISSES.clone()))
-        .unwrap();
-    registry
-        .register(Box::new(ACTIVE_CONNECTIONS.clone()))
-        .unwrap();
-    registry
-        .register(Box::new(REDIS_OPERATIONS.clone()))
-        .unwrap();
+    register_collector(&registry, REQUEST_TOTAL.clone());
+    register_collector(&registry, REQUEST_DURATION.clone());
+    register_collector(&registry, RATE_LIMIT_HITS.clone());
+    register_collector(&registry, RATE_LIMIT_MISSES.clone());
+    register_collector(&registry, ACTIVE_CONNECTIONS.clone());
+    register_collector(&registry, REDIS_OPERATIONS.clone());
+    register_collector(&registry, DENIED_TOTAL.clone());
+
+    // Core operation metrics
+    register_collector(&registry, RATE_LIMIT_DECISIONS.clone());
+    register_collector(&registry, THREAT_SCORES.clone());
+    register_collector(&registry, THREAT_ANALYSES.clone());
+    register_collector(&registry, THREAT_ANALYSES_DROPPED.clone());
+    register_collector(&registry, THREAT_ANALYSIS_FAILURES.clone());
+    register_collector(&registry, THREAT_ANALYSIS_DURATION.clone());
+    register_collector(&registry, THREAT_ANALYZER_RUNS.clone());
+    register_collector(&registry, THREATS_DETECTED.clone());
+    register_collector(&registry, THREAT_ACTIONS.clone());
+    register_collector(&registry, AUDIT_EVENTS_LOGGED.clone());
+    register_collector(&registry, SIEM_EVENTS.clone());
+    register_collector(&registry, REDIS_COMMAND_DURATION.clone());
 
     registry
 });
 
-pub static REQUEST_TOTAL: Lazy<IntCounter> = Lazy::new(|| {
-    IntCounter::new("ratewatch_requests_total", "Total number of requests")
-        .expect("metric can be created")
+/// Force registration of all metrics. Safe to call any number of times.
+pub fn register_metrics() {
+    Lazy::force(&REGISTRY);
+}
+
+pub static REQUEST_TOTAL: Lazy<IntCounterVec> = Lazy::new(|| {
+    IntCounterVec::new(
+        Opts::new("ratewatch_requests_total", "Total number of requests"),
+        &["outcome", "endpoint"],
+    )
+    .expect("metric can be created")
+});
+
+pub static DENIED_TOTAL: Lazy<IntCounterVec> = Lazy::new(|| {
+    IntCounterVec::new(
+        Opts::new(
+            "ratewatch_denied_total",
+            "Total number of rate limit denials by key prefix",
+        ),
+        &["key_prefix"],
+    )
+    .expect("metric can be created")
+});
+
+pub static RATE_LIMIT_DECISIONS: Lazy<IntCounterVec> = Lazy::new(|| {
+    IntCounterVec::new(
+        Opts::new(
+            "ratewatch_rate_limit_decisions_total",
+            "Rate limit decisions by strategy and outcome",
+        ),
+        &["strategy", "outcome"],
+    )
+    .expect("metric can be created")
+});
+
+pub static THREAT_SCORES: Lazy<HistogramVec> = Lazy::new(|| {
+    HistogramVec::new(
+        HistogramOpts::new(
+            "ratewatch_threat_score",
+            "Threat scores produced by each analyzer",
+        )
+        .buckets(vec![0.0, 0.1, 0.2, 0.3, 0.4, 0.5, 0.6, 0.7, 0.8, 0.9, 1.0]),
+        &["analyzer_id"],
+    )
+    .expect("metric can be created")
+});
+
+pub static THREAT_ANALYSES: Lazy<IntCounter> = Lazy::new(|| {
+    IntCounter::new(
+        "ratewatch_threat_analyses_total",
+        "Requests analyzed by threat detection",
+    )
+    .expect("metric can be created")
+});
+
+pub static THREAT_ANALYSES_DROPPED: Lazy<IntCounter> = Lazy::new(|| {
+    IntCounter::new(
+        "ratewatch_threat_analyses_dropped_total",
+        "Requests left unanalyzed because the background analysis queue was full",
+    )
+    .expect("metric can be created")
+});
+
+pub static THREAT_ANALYSIS_FAILURES: Lazy<IntCounterVec> = Lazy::new(|| {
+    IntCounterVec::new(
+        Opts::new(
+            "ratewatch_threat_analysis_failures_total",
+            "Requests whose threat analysis failed, by what the failure mode decided",
+        ),
+        &["decision"],
+    )
+    .expect("metric can be created")
+});
+
+pub static THREAT_ANALYSIS_DURATION: Lazy<Histogram> = Lazy::new(|| {
+    Histogram::with_opts(
+        HistogramOpts::new(
+            "ratewatch_threat_analysis_duration_seconds",
+            "Time spent analyzing a request for threats, in seconds",
+        )
+        .buckets(vec![0.0005, 0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 5.0]),
+    )
+    .expect("metric can be created")
+});
+
+pub static THREAT_ANALYZER_RUNS: Lazy<IntCounterVec> = Lazy::new(|| {
+    IntCounterVec::new(
+        Opts::new(
+            "ratewatch_threat_analyzer_runs_total",
+            "Threat analyzer runs by analyzer and outcome",
+        ),
+        &["analyzer_id", "outcome"],
+    )
+    .expect("metric can be created")
+});
+
+pub static THREATS_DETECTED: Lazy<IntCounter> = Lazy::new(|| {
+    IntCounter::new(
+        "ratewatch_threats_detected_total",
+        "Requests scoring over the threat and confidence thresholds",
+    )
+    .expect("metric can be created")
+});
+
+pub static THREAT_ACTIONS: Lazy<IntCounterVec> = Lazy::new(|| {
+    IntCounterVec::new(
+        Opts::new(
+            "ratewatch_threat_actions_total",
+            "Defensive actions taken by threat detection",
+        ),
+        &["action"],
+    )
+    .expect("metric can be created")
+});
+
+pub static AUDIT_EVENTS_LOGGED: Lazy<IntCounterVec> = Lazy::new(|| {
+    IntCounterVec::new(
+        Opts::new(
+            "ratewatch_audit_events_total",
+            "Audit events processed by outcome",
+        ),
+        &["outcome"],
+    )
+    .expect("metric can be created")
+});
+
+pub static SIEM_EVENTS: Lazy<IntCounterVec> = Lazy::new(|| {
+    IntCounterVec::new(
+        Opts::new(
+            "ratewatch_siem_events_total",
+            "Security events handled by the SIEM integration by outcome",
+        ),
+        &["outcome"],
+    )
+    .expect("metric can be created")
+});
+
+pub static REDIS_COMMAND_DURATION: Lazy<HistogramVec> = Lazy::new(|| {
+    HistogramVec::new(
+        HistogramOpts::new(
+            "ratewatch_redis_command_duration_seconds",
+            "Redis command latency in seconds",
+        )
+        .buckets(vec![
+            0.0001, 0.00025, 0.0005, 0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.5,
+        ]),
+        &["operation"],
+    )
+    .expect("metric can be created")
 });
 
+/// Record the latency of a Redis command
+pub fn observe_redis_command(operation: &str, started: std::time::Instant) {
+    REDIS_OPERATIONS.inc();
+    REDIS_COMMAND_DURATION
+        .with_label_values(&[operation])
+        .observe(started.elapsed().as_secs_f64());
+}
+
+static ENDPOINT_LABELS: Lazy<LabelLimiter> =
+    Lazy::new(|| LabelLimiter::new(DEFAULT_MAX_LABEL_VALUES));
+
+static KEY_PREFIX_LABELS: Lazy<LabelLimiter> =
+    Lazy::new(|| LabelLimiter::new(DEFAULT_MAX_LABEL_VALUES));
+
+/// Marker placed in response extensions when a rate limit check denies a key
+#[derive(Debug, Clone)]
+pub struct RateLimitDenial {
+    pub key: String,
+}
+
+/// Caps the number of distinct values a label can take.
+///
+/// Once the cap is reached, unseen values are hashed into a fixed set of
+/// `overflow_XX` buckets so high-cardinality keys cannot explode the series count.
+pub struct LabelLimiter {
+    limit: AtomicUsize,
+    seen: Mutex<HashSet<String>>,
+}
+
+impl LabelLimiter {
+    pub fn new(limit: usize) -> Self {
+        Self {
+            limit: AtomicUsize::new(limit),
+            seen: Mutex::new(HashSet::new()),
+        }
+    }
+
+    pub fn set_limit(&self, limit: usize) {
+        self.limit.store(limit, Ordering::Relaxed);
+    }
+
+    pub fn label(&self, value: &str) -> String {
+        let mut seen = self.seen.lock().unwrap_or_else(|e| e.into_inner());
+
+        if seen.contains(value) {
+            return value.to_string();
+        }
+
+        if seen.len() < self.limit.load(Ordering::Relaxed) {
+            seen.insert(value.to_string());
+            return value.to_string();
+        }
+
+        let bucket = blake3::hash(value.as_bytes()).as_bytes()[0] % OVERFLOW_BUCKETS;
+        format!("overflow_{:02x}", bucket)
+    }
+}
+
+/// Apply the label cardinality cap from `MetricsConfig`
+pub fn set_max_label_values(limit: usize) {
+    ENDPOINT_LABELS.set_limit(limit);
+    KEY_PREFIX_LABELS.set_limit(limit);
+}
+
+/// Reduce a rate-limit key to the prefix used as a metric label (`user:123` -> `user`)
+pub fn key_prefix(key: &str) -> String {
+    let prefix = key.split(':').next().unwrap_or(key);
+    prefix.chars().take(64).collect()
+}
+
+/// Count a rate limit denial under the (capped) key prefix label
+pub fn record_denial(key: &str) {
+    let prefix = KEY_PREFIX_LABELS.label(&key_prefix(key));
+    DENIED_TOTAL.with_label_values(&[&prefix]).inc();
+}
+
+fn outcome_for_status(status: StatusCode) -> &'static str {
+    if status == StatusCode::TOO_MANY_REQUESTS {
+        "denied"
+    } else if status.is_server_error() {
+        "error"
+    } else if status.is_client_error() {
+        "rejected"
+    } else {
+        "success"
+    }
+}
+
+/// Middleware recording labeled request and denial counters
+pub async fn metrics_middleware(request: Request, next: Next) -> Response {
+    let endpoint = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|path| path.as_str().to_string())
+        .unwrap_or_else(|| "unmatched".to_string());
+
+    let response = next.run(request).await;
+
+    let outcome = match response.extensions().get::<RateLimitDenial>() {
+        Some(denial) => {
+            record_denial(&denial.key);
+            "denied"
+        }
+        None => outcome_for_status(response.status()),
+    };
+
+    let endpoint = ENDPOINT_LABELS.label(&endpoint);
+    REQUEST_TOTAL.with_label_values(&[outcome, &endpoint]).inc();
+
+    response
+}
+
 pub static REQUEST_DURATION: Lazy<Histogram> = Lazy::new(|| {
     Histogram::with_opts(
         HistogramOpts::new(
@@ -78,14 +375,17 @@ pub static REDIS_OPERATIONS: Lazy<Counter> = Lazy::new(|| {
 });
 
 pub fn create_metrics_router() -> Router {
-    Router::new().route("/metrics", get(metrics_handler))
+    create_metrics_router_at("/metrics")
 }
 
-async fn metrics_handler() -> Result<Response<String>, StatusCode> {
-    let encoder = TextEncoder::new();
-    let metric_families = REGISTRY.gather();
+/// Metrics router serving Prometheus output at the configured endpoint
+pub fn create_metrics_router_at(endpoint: &str) -> Router {
+    register_metrics();
+    Router::new().route(endpoint, get(metrics_handler))
+}
 
-    match encoder.encode_to_string(&metric_families) {
+async fn metrics_handler() -> Result<Response<String>, StatusCode> {
+    match encode_registry() {
         Ok(output) => {
             let response = Response::builder()
                 .status(200)
@@ -97,3 +397,311 @@ async fn metrics_handler() -> Result<Response<String>, StatusCode> {
         Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
     }
 }
+
+/// Encode the registry in Prometheus text format
+pub fn encode_registry() -> anyhow::Result<String> {
+    register_metrics();
+    let encoder = TextEncoder::new();
+    Ok(encoder.encode_to_string(&REGISTRY.gather())?)
+}
+
+/// Background task pushing the registry to a Prometheus Pushgateway.
+///
+/// Used for short-lived or batch deployments that cannot be scraped. Failed
+/// pushes are logged and retried on the next interval.
+pub struct PushGatewayHandle {
+    shutdown: watch::Sender<bool>,
+    task: JoinHandle<()>,
+}
+
+impl PushGatewayHandle {
+    /// Start pushing when `push_gateway` is configured
+    pub fn from_config(config: &MetricsConfig) -> Option<Self> {
+        let gateway = config.push_gateway.as_ref()?;
+        if !config.enabled {
+            return None;
+        }
+
+        Some(Self::spawn(
+            gateway,
+            &instance_name(),
+            Duration::from_secs(config.collection_interval_seconds.max(1)),
+        ))
+    }
+
+    pub fn spawn(gateway: &str, instance: &str, interval: Duration) -> Self {
+        let url = format!(
+            "{}/metrics/job/ratewatch/instance/{}",
+            gateway.trim_end_matches('/'),
+            instance
+        );
+        let (shutdown, mut shutdown_rx) = watch::channel(false);
+
+        let task = tokio::spawn(async move {
+            let client = reqwest::Client::new();
+            let mut ticker = tokio::time::interval(interval);
+
+            loop {
+                tokio::select! {
+                    _ = ticker.tick() => {
+                        if let Err(e) = push_once(&client, &url).await {
+                            tracing::warn!("Pushgateway push to {} failed, retrying next interval: {}", url, e);
+                        }
+                    }
+                    _ = shutdown_rx.changed() => break,
+                }
+            }
+
+            // Final push so the last interval's samples are not lost
+            if let Err(e) = push_once(&client, &url).await {
+                tracing::debug!("Final Pushgateway push failed: {}", e);
+            }
+        });
+
+        Self { shutdown, task }
+    }
+
+    /// Stop the push loop and wait for the final push to complete
+    pub async fn stop(self) {
+        let _ = self.shutdown.send(true);
+        let _ = self.task.await;
+    }
+}
+
+async fn push_once(client: &reqwest::Client, url: &str) -> anyhow::Result<()> {
+    let body = encode_registry()?;
+    let response = client
+        .put(url)
+        .header("content-type", "text/plain; version=0.0.4")
+        .body(body)
+        .timeout(Duration::from_secs(10))
+        .send()
+        .await?;
+
+    if !response.status().is_success() {
+        return Err(anyhow::anyhow!(
+            "Pushgateway returned status {}",
+            response.status()
+        ));
+    }
+
+    Ok(())
+}
+
+fn instance_name() -> String {
+    std::env::var("HOSTNAME")
+        .ok()
+        .or_else(|| std::fs::read_to_string("/etc/hostname").ok())
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Body, response::IntoResponse};
+    use tower::ServiceExt;
+
+    async fn denied_handler() -> Response {
+        let mut response = "denied".into_response();
+        response.extensions_mut().insert(RateLimitDenial {
+            key: "metrics-test-tenant:user-1".to_string(),
+        });
+        response
+    }
+
+    fn test_router() -> Router {
+        Router::new()
+            .route("/test/metrics/ok", get(|| async { "ok" }))
+            .route("/test/metrics/denied", get(denied_handler))
+            .route(
+                "/test/metrics/error",
+                get(|| async { StatusCode::INTERNAL_SERVER_ERROR }),
+            )
+            .layer(axum::middleware::from_fn(metrics_middleware))
+            .merge(create_metrics_router())
+    }
+
+    async fn drive(router: &Router, path: &str) {
+        router
+            .clone()
+            .oneshot(Request::builder().uri(path).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+    }
+
+    async fn scrape(router: &Router) -> String {
+        let response = router
+            .clone()
+            .oneshot(Request::builder().uri("/metrics").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        String::from_utf8(body.to_vec()).unwrap()
+    }
+
+    fn series_value(output: &str, metric: &str, labels: &[&str]) -> Option<u64> {
+        output
+            .lines()
+            .filter(|line| line.starts_with(metric))
+            .find(|line| labels.iter().all(|label| line.contains(label)))
+            .and_then(|line| line.rsplit(' ').next())
+            .and_then(|value| value.parse().ok())
+    }
+
+    #[tokio::test]
+    async fn test_labeled_series_after_traffic() {
+        let router = test_router();
+
+        drive(&router, "/test/metrics/ok").await;
+        drive(&router, "/test/metrics/ok").await;
+        drive(&router, "/test/metrics/denied").await;
+        drive(&router, "/test/metrics/error").await;
+
+        let output = scrape(&router).await;
+
+        assert_eq!(
+            series_value(
+                &output,
+                "ratewatch_requests_total",
+                &["endpoint=\"/test/metrics/ok\"", "outcome=\"success\""]
+            ),
+            Some(2)
+        );
+        assert_eq!(
+            series_value(
+                &output,
+                "ratewatch_requests_total",
+                &["endpoint=\"/test/metrics/denied\"", "outcome=\"denied\""]
+            ),
+            Some(1)
+        );
+        assert_eq!(
+            series_value(
+                &output,
+                "ratewatch_requests_total",
+                &["endpoint=\"/test/metrics/error\"", "outcome=\"error\""]
+            ),
+            Some(1)
+        );
+        assert_eq!(
+            series_value(
+                &output,
+                "ratewatch_denied_total",
+                &["key_prefix=\"metrics-test-tenant\""]
+            ),
+            Some(1)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_core_operation_metrics_exposed() {
+        // Building several routers must not fail on duplicate registration
+        let _ = create_metrics_router();
+        let router = create_metrics_router_at("/custom-metrics");
+        register_metrics();
+
+        RATE_LIMIT_DECISIONS
+            .with_label_values(&["metrics_test", "allowed"])
+            .inc();
+        THREAT_SCORES
+            .with_label_values(&["metrics_test_analyzer"])
+            .observe(0.42);
+        AUDIT_EVENTS_LOGGED.with_label_values(&["stored"]).inc();
+        observe_redis_command("METRICS_TEST", std::time::Instant::now());
+
+        let response = router
+            .oneshot(
+                Request::builder()
+                    .uri("/custom-metrics")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let output = String::from_utf8(body.to_vec()).unwrap();
+
+        assert_eq!(
+            series_value(
+                &output,
+                "ratewatch_rate_limit_decisions_total",
+                &["strategy=\"metrics_test\"", "outcome=\"allowed\""]
+            ),
+            Some(1)
+        );
+        assert!(output.contains("ratewatch_threat_score_bucket{analyzer_id=\"metrics_test_analyzer\""));
+        assert!(output.contains("ratewatch_audit_events_total"));
+        assert!(output.contains("ratewatch_redis_command_duration_seconds_bucket{operation=\"METRICS_TEST\",le=\"0.0001\"}"));
+    }
+
+    #[tokio::test]
+    async fn test_push_gateway_pushes_on_interval() {
+        use axum::{extract::Path, routing::put};
+        use std::sync::Arc;
+
+        let pushes: Arc<Mutex<Vec<(String, String)>>> = Arc::new(Mutex::new(Vec::new()));
+        let recorded = pushes.clone();
+
+        let gateway = Router::new().route(
+            "/metrics/job/ratewatch/instance/:instance",
+            put(move |Path(instance): Path<String>, body: String| {
+                let recorded = recorded.clone();
+                async move {
+                    recorded.lock().unwrap().push((instance, body));
+                    StatusCode::OK
+                }
+            }),
+        );
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, gateway).await.unwrap();
+        });
+
+        REQUEST_TOTAL
+            .with_label_values(&["success", "/test/metrics/push"])
+            .inc();
+
+        let handle = PushGatewayHandle::spawn(
+            &format!("http://{}", addr),
+            "test-instance",
+            Duration::from_millis(50),
+        );
+        tokio::time::sleep(Duration::from_millis(180)).await;
+        handle.stop().await;
+
+        let pushes = pushes.lock().unwrap();
+        assert!(pushes.len() >= 3, "expected periodic pushes, got {}", pushes.len());
+        assert!(pushes.iter().all(|(instance, _)| instance == "test-instance"));
+        assert!(pushes[0].1.contains("ratewatch_requests_total"));
+    }
+
+    #[test]
+    fn test_label_cardinality_cap() {
+        let limiter = LabelLimiter::new(2);
+
+        assert_eq!(limiter.label("a"), "a");
+        assert_eq!(limiter.label("b"), "b");
+        assert_eq!(limiter.label("a"), "a");
+
+        let bucketed = limiter.label("c");
+        assert!(bucketed.starts_with("overflow_"));
+        assert_eq!(limiter.label("c"), bucketed);
+    }
+
+    #[test]
+    fn test_key_prefix() {
+        assert_eq!(key_prefix("user:123"), "user");
+        assert_eq!(key_prefix("plain"), "plain");
+        assert_eq!(key_prefix(&"x".repeat(100)).len(), 64);
+    }
+}
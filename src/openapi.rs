@@ -0,0 +1,240 @@
+//! OpenAPI 3 description of the HTTP API (enabled with the `openapi` feature).
+//!
+//! Paths and schemas come from the `utoipa` annotations on the route handlers
+//! and their serde types. The document is served at `/openapi.json` with a
+//! Swagger UI at `/docs`.
+
+use axum::{
+    response::{Html, Json},
+    routing::get,
+    Router,
+};
+use utoipa::{
+    openapi::security::{Http, HttpAuthScheme, SecurityScheme},
+    Modify, OpenApi,
+};
+
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "RateWatch API",
+        description = "Distributed API rate limiting with analytics, audit, threat detection and multi-tenancy"
+    ),
+    paths(
+        crate::api::check_rate_limit,
+        crate::api::check_rate_limit_batch,
+        crate::api::delete_user_data,
+        crate::api::get_user_data_summary,
+        crate::api::health_check,
+        crate::api::detailed_health_check,
+        crate::api::readiness_check,
+        crate::analytics::get_stats,
+        crate::analytics::get_top_keys,
+        crate::analytics::get_recent_activity,
+        crate::analytics::get_request_rate,
+        crate::audit::api::query_audit_events,
+        crate::audit::api::get_audit_statistics,
+        crate::audit::api::audit_system_health,
+        crate::audit::api::verify_audit_log,
+        crate::audit::api::rotate_signing_key,
+        crate::security::api::get_threat_detection_status,
+        crate::security::api::get_threat_detection_config,
+        crate::security::api::update_threat_detection_config,
+        crate::security::api::get_threat_detection_statistics,
+        crate::security::api::get_threat_detection_health,
+        crate::security::api::enable_threat_detection,
+        crate::security::api::disable_threat_detection,
+        crate::security::api::evaluate_request,
+        crate::security::api::get_behavior_patterns,
+        crate::security::api::update_behavior_patterns,
+        crate::security::api::submit_threat_feedback,
+        crate::security::api::get_siem_dead_letters,
+        crate::security::api::replay_siem_dead_letters,
+        crate::tenant::api::create_tenant,
+        crate::tenant::api::import_tenants,
+        crate::tenant::api::batch_create_tenants,
+        crate::tenant::api::issue_tenant_key,
+        crate::tenant::api::list_tenant_keys,
+        crate::tenant::api::revoke_tenant_key,
+        crate::tenant::api::list_tenants,
+        crate::tenant::api::get_tenant,
+        crate::tenant::api::get_tenant_by_slug,
+        crate::tenant::api::update_tenant,
+        crate::tenant::api::delete_tenant,
+        crate::tenant::api::suspend_tenant,
+        crate::tenant::api::reactivate_tenant,
+        crate::tenant::api::health_check_tenant,
+        crate::tenant::api::get_tenant_quotas,
+        crate::config::api::reload_config,
+        crate::key_access::get_key_access_lists,
+        crate::key_access::add_key_to_list,
+        crate::key_access::remove_key_from_list,
+        crate::enforcement::get_enforcement,
+        crate::enforcement::enable_enforcement,
+        crate::enforcement::disable_enforcement,
+        crate::api::get_limit_key,
+        crate::api::reset_limit_key,
+        crate::ip_allowlist::get_ip_allowlist,
+    ),
+    components(schemas(
+        crate::api_error::ProblemDetails,
+        crate::rate_limiter::RateLimitRequest,
+        crate::rate_limiter::RateLimitResponse,
+        crate::rate_limiter::RateLimitFailureMode,
+        crate::rate_limiter::DenyReason,
+        crate::api::BatchCheckResult,
+        crate::privacy::DataDeletionRequest,
+        crate::audit::api::AuditQueryResponse,
+        crate::audit::api::AuditQueryInfo,
+        crate::audit::audit_logger::AuditVerificationReport,
+        crate::audit::audit_logger::SigningKeyRotation,
+        crate::audit::api::SigningKeyRotationRequest,
+        crate::audit::api::SigningKeyRotationResponse,
+        crate::audit::audit_logger::ChainStatus,
+        crate::security::api::ThreatConfigUpdate,
+        crate::security::api::ThreatFeedbackRequest,
+        crate::security::api::ThreatEvaluationRequest,
+        crate::security::api::BehaviorPatternsUpdate,
+        crate::security::behavior_patterns::PatternDefinition,
+        crate::security::behavior_patterns::IndicatorDefinition,
+        crate::security::behavior_patterns::Indicator,
+        crate::security::behavior_patterns::Comparison,
+        crate::security::api::DeadLetterReplayRequest,
+        crate::tenant::api::CreateTenantRequest,
+        crate::tenant::api::UpdateTenantRequest,
+        crate::tenant::api::SuspendTenantRequest,
+        crate::tenant::api::TenantResponse,
+        crate::tenant::api::TenantsListResponse,
+        crate::tenant::api::TenantImportResponse,
+        crate::tenant::api::IssueTenantKeyRequest,
+        crate::tenant::api::IssuedTenantKeyResponse,
+        crate::tenant::api::TenantKeysResponse,
+        crate::tenant::api_keys::TenantApiKey,
+        crate::config::api::ConfigReloadResponse,
+        crate::key_access::KeyAccessEntry,
+        crate::key_access::KeyAccessListsResponse,
+        crate::key_access::KeyAccessChangeResponse,
+        crate::enforcement::EnforcementChange,
+        crate::enforcement::EnforcementResponse,
+        crate::rate_limiter::RateLimitStrategy,
+        crate::rate_limiter::KeyStateEntry,
+        crate::api::KeyStateResponse,
+        crate::api::KeyResetResponse,
+        crate::ip_allowlist::IpAllowlistResponse,
+    )),
+    modifiers(&ApiKeyAuth),
+    tags(
+        (name = "rate-limit", description = "Rate limit checks"),
+        (name = "analytics", description = "Usage analytics"),
+        (name = "audit", description = "Audit log queries"),
+        (name = "security", description = "Threat detection"),
+        (name = "tenants", description = "Tenant management"),
+        (name = "privacy", description = "GDPR data requests"),
+        (name = "system", description = "Health checks"),
+        (name = "admin", description = "Operator endpoints requiring an admin API key"),
+    )
+)]
+pub struct ApiDoc;
+
+/// Paths that exist only with the `config-schema` feature
+#[cfg(feature = "config-schema")]
+#[derive(OpenApi)]
+#[openapi(paths(crate::config::api::get_config_schema))]
+struct ConfigSchemaDoc;
+
+/// The full spec for the features this binary was built with
+pub fn api_doc() -> utoipa::openapi::OpenApi {
+    #[allow(unused_mut)]
+    let mut doc = ApiDoc::openapi();
+    #[cfg(feature = "config-schema")]
+    doc.merge(ConfigSchemaDoc::openapi());
+    doc
+}
+
+/// Registers the `api_key` scheme referenced by protected paths
+struct ApiKeyAuth;
+
+impl Modify for ApiKeyAuth {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme(
+            "api_key",
+            SecurityScheme::Http(Http::new(HttpAuthScheme::Bearer)),
+        );
+    }
+}
+
+const SWAGGER_UI: &str = r##"<!DOCTYPE html>
+<html lang="en">
+<head>
+  <meta charset="utf-8" />
+  <title>RateWatch API</title>
+  <link rel="stylesheet" href="https://unpkg.com/swagger-ui-dist@5/swagger-ui.css" />
+</head>
+<body>
+  <div id="swagger-ui"></div>
+  <script src="https://unpkg.com/swagger-ui-dist@5/swagger-ui-bundle.js" crossorigin></script>
+  <script>
+    window.ui = SwaggerUIBundle({ url: "/openapi.json", dom_id: "#swagger-ui" });
+  </script>
+</body>
+</html>
+"##;
+
+/// Public routes serving the spec and Swagger UI
+pub fn create_openapi_router<S>() -> Router<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    Router::new()
+        .route("/openapi.json", get(|| async { Json(api_doc()) }))
+        .route("/docs", get(|| async { Html(SWAGGER_UI) }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_spec_covers_core_routes() {
+        let spec = serde_json::to_value(api_doc()).unwrap();
+        let paths = spec["paths"].as_object().unwrap();
+
+        for path in [
+            "/v1/check",
+            "/v1/limit/batch",
+            "/v1/analytics/stats",
+            "/v1/audit/events",
+            "/v1/security/threat-detection/config",
+            "/tenants/{tenant_id}",
+        ] {
+            assert!(paths.contains_key(path), "missing {path}");
+        }
+
+        assert!(spec["components"]["schemas"]["RateLimitRequest"].is_object());
+        assert!(spec["components"]["securitySchemes"]["api_key"].is_object());
+    }
+
+    #[test]
+    fn test_auth_requirements_per_route() {
+        let spec = serde_json::to_value(api_doc()).unwrap();
+
+        assert_eq!(spec["paths"]["/v1/check"]["post"]["security"][0]["api_key"], serde_json::json!([]));
+        assert!(spec["paths"]["/health"]["get"].get("security").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_docs_page_loads_spec() {
+        use axum::{body::Body, http::{Request, StatusCode}};
+        use tower::ServiceExt;
+
+        let response = create_openapi_router::<()>()
+            .oneshot(Request::builder().uri("/docs").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body = String::from_utf8(body.to_vec()).unwrap();
+        assert!(body.contains(r##"url: "/openapi.json", dom_id: "#swagger-ui""##));
+    }
+}
@@ -0,0 +1,558 @@
+//! Server-side rate limit policies chosen by route.
+//!
+//! `[[rate_limiting.policies]]` maps path patterns and methods to a limit, so
+//! clients don't have to send limits with their requests.
+//! `RateLimitPolicies` compiles the table once and
+//! `rate_limit_policy_middleware` enforces the policy that applies to each
+//! incoming request, answering 429 with the same headers as `RateLimitLayer`.
+//!
+//! When several policies match, the most specific wins: more literal segments
+//! first, then an exact pattern over one ending in `*`, then a policy naming
+//! the method over one for all methods. Remaining ties go to the policy listed
+//! first. Requests no policy matches use `rate_limiting.default_policy`, or
+//! pass through unlimited without one.
+//!
+//! A policy with an `ip_limit` also limits each client IP, as
+//! `crate::request_context` resolves it, whether or not the request has a
+//! key. Public routes such as signup or password reset can then be limited
+//! before any API key exists, while keyed requests must fit both limits. The
+//! two are counted apart, under `policy:{name}:limit:ip:` and
+//! `policy:{name}:limit:key:`.
+//!
+//! A policy in `monitor` mode lets over-limit requests through and records
+//! them as would-deny in analytics instead of answering 429. Requests from
+//! allowlisted client IPs (`crate::ip_allowlist`) aren't limited.
+
+use anyhow::Result;
+use axum::{
+    extract::{Request, State},
+    http::Method,
+    middleware::Next,
+    response::Response,
+};
+use std::sync::Arc;
+
+use crate::analytics::AnalyticsManager;
+use crate::config::{PolicyLimitsConfig, RateLimitPolicyConfig, RateLimitingConfig};
+use crate::ip_allowlist::IpAllowlisted;
+use crate::key_extractor::{ByIp, KeyExtractor};
+use crate::layer::{denied_response, insert_rate_limit_headers};
+use crate::rate_limiter::{DenyReason, RateLimitRequest, RateLimiter};
+
+/// The compiled policy table
+pub struct RateLimitPolicies {
+    policies: Vec<RateLimitPolicy>,
+    default_policy: Option<RateLimitPolicy>,
+    analytics: Option<Arc<AnalyticsManager>>,
+}
+
+pub struct RateLimitPolicy {
+    /// Pattern and methods as configured, or `default`; also namespaces the Redis keys
+    pub name: String,
+    segments: Vec<Segment>,
+    methods: Vec<Method>,
+    limit: u64,
+    window: u64,
+    cost: u64,
+    limiter: Arc<RateLimiter>,
+    key_extractor: Box<dyn KeyExtractor>,
+    /// (limit, window) per client IP
+    ip_limit: Option<(u64, u64)>,
+}
+
+#[derive(Debug, PartialEq)]
+enum Segment {
+    Literal(String),
+    Wildcard,
+}
+
+impl RateLimitPolicies {
+    /// The table for `[rate_limiting]`, or `None` when no policies are configured.
+    ///
+    /// Entries with an invalid pattern or method are skipped with a warning;
+    /// config validation rejects them before this runs.
+    pub fn from_config(config: &RateLimitingConfig, limiter: &Arc<RateLimiter>) -> Option<Self> {
+        if config.policies.is_empty() && config.default_policy.is_none() {
+            return None;
+        }
+
+        let policies = config
+            .policies
+            .iter()
+            .filter_map(|policy| {
+                RateLimitPolicy::from_config(policy, limiter)
+                    .map_err(|e| tracing::warn!("Ignoring rate limit policy {:?}: {:#}", policy.pattern, e))
+                    .ok()
+            })
+            .collect();
+        let default_policy = config
+            .default_policy
+            .as_ref()
+            .map(|limits| RateLimitPolicy::new("default".to_string(), Vec::new(), Vec::new(), limits, limiter));
+
+        tracing::info!(
+            policies = config.policies.len(),
+            default_policy = config.default_policy.is_some(),
+            "Rate limit policies enabled"
+        );
+        Some(Self {
+            policies,
+            default_policy,
+            analytics: None,
+        })
+    }
+
+    /// Record every decision in analytics, including monitor mode's would-deny ones
+    pub fn with_analytics(mut self, analytics: Arc<AnalyticsManager>) -> Self {
+        self.analytics = Some(analytics);
+        self
+    }
+
+    /// The policy that applies to a request, most specific first
+    pub fn resolve(&self, method: &Method, path: &str) -> Option<&RateLimitPolicy> {
+        let path: Vec<&str> = split_path(path).collect();
+
+        let mut best: Option<&RateLimitPolicy> = None;
+        for policy in self.policies.iter().filter(|policy| policy.matches(method, &path)) {
+            if best.map_or(true, |best| policy.specificity() > best.specificity()) {
+                best = Some(policy);
+            }
+        }
+        best.or(self.default_policy.as_ref())
+    }
+}
+
+impl RateLimitPolicy {
+    fn from_config(config: &RateLimitPolicyConfig, limiter: &Arc<RateLimiter>) -> Result<Self> {
+        let (segments, methods) = parse_policy(config)?;
+        let name = if methods.is_empty() {
+            config.pattern.clone()
+        } else {
+            format!("{} {}", config.methods.join(",").to_ascii_uppercase(), config.pattern)
+        };
+        Ok(Self::new(name, segments, methods, &config.limits, limiter))
+    }
+
+    fn new(
+        name: String,
+        segments: Vec<Segment>,
+        methods: Vec<Method>,
+        limits: &PolicyLimitsConfig,
+        limiter: &Arc<RateLimiter>,
+    ) -> Self {
+        let strategy = limits.algorithm.unwrap_or(limiter.strategy());
+        let mode = limits.mode.unwrap_or(limiter.mode());
+        let limiter = if strategy != limiter.strategy() || mode != limiter.mode() {
+            Arc::new(limiter.for_strategy(strategy).with_mode(mode))
+        } else {
+            limiter.clone()
+        };
+
+        Self {
+            name,
+            segments,
+            methods,
+            limit: limits.limit,
+            window: limits.window,
+            cost: limits.cost,
+            limiter,
+            key_extractor: limits.key.build(),
+            ip_limit: limits.ip_limit.as_ref().map(|ip_limit| (ip_limit.limit, ip_limit.window)),
+        }
+    }
+
+    fn matches(&self, method: &Method, path: &[&str]) -> bool {
+        if !self.methods.is_empty() && !self.methods.contains(method) {
+            return false;
+        }
+
+        let mut path = path.iter();
+        for (index, segment) in self.segments.iter().enumerate() {
+            match segment {
+                // A trailing wildcard takes the rest of the path, but at least one segment
+                Segment::Wildcard if index == self.segments.len() - 1 => return path.next().is_some(),
+                Segment::Wildcard => {
+                    if path.next().is_none() {
+                        return false;
+                    }
+                }
+                Segment::Literal(literal) => {
+                    if path.next() != Some(&literal.as_str()) {
+                        return false;
+                    }
+                }
+            }
+        }
+        path.next().is_none()
+    }
+
+    /// Ordering key for precedence: literal segments, exactness, method-specific
+    fn specificity(&self) -> (usize, bool, bool) {
+        let literals = self
+            .segments
+            .iter()
+            .filter(|segment| matches!(segment, Segment::Literal(_)))
+            .count();
+        let exact = self.segments.last() != Some(&Segment::Wildcard);
+        (literals, exact, !self.methods.is_empty())
+    }
+}
+
+fn split_path(path: &str) -> impl Iterator<Item = &str> {
+    path.split('/').filter(|segment| !segment.is_empty())
+}
+
+/// Check a `[[rate_limiting.policies]]` entry's pattern and methods, and
+/// return the route it covers in a normal form, e.g. `GET,POST /api/*`, so
+/// two entries for the same route can be caught
+pub fn validate_policy(config: &RateLimitPolicyConfig) -> Result<String> {
+    let (segments, methods) = parse_policy(config)?;
+    let mut methods: Vec<&str> = methods.iter().map(Method::as_str).collect();
+    methods.sort_unstable();
+    methods.dedup();
+    let path: Vec<&str> = segments
+        .iter()
+        .map(|segment| match segment {
+            Segment::Literal(literal) => literal.as_str(),
+            Segment::Wildcard => "*",
+        })
+        .collect();
+    Ok(format!("{} /{}", methods.join(","), path.join("/")))
+}
+
+fn parse_policy(config: &RateLimitPolicyConfig) -> Result<(Vec<Segment>, Vec<Method>)> {
+    if !config.pattern.starts_with('/') {
+        return Err(anyhow::anyhow!("pattern must start with '/'"));
+    }
+    let segments = split_path(&config.pattern)
+        .map(|segment| match segment {
+            "*" => Ok(Segment::Wildcard),
+            _ if segment.contains('*') => Err(anyhow::anyhow!("'*' must be a whole path segment")),
+            _ => Ok(Segment::Literal(segment.to_string())),
+        })
+        .collect::<Result<_>>()?;
+
+    let methods = config
+        .methods
+        .iter()
+        .map(|method| {
+            Method::from_bytes(method.to_ascii_uppercase().as_bytes())
+                .map_err(|_| anyhow::anyhow!("invalid HTTP method {:?}", method))
+        })
+        .collect::<Result<_>>()?;
+
+    Ok((segments, methods))
+}
+
+/// Enforce the resolved policy on each request
+pub async fn rate_limit_policy_middleware(
+    State(policies): State<Arc<RateLimitPolicies>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let Some(policy) = policies.resolve(request.method(), request.uri().path()) else {
+        return next.run(request).await;
+    };
+
+    // Checked in this order, each (key, limit, window) with its own counter
+    let mut checks = Vec::new();
+    if let (Some((limit, window)), Some(ip)) = (policy.ip_limit, ByIp.extract(&request)) {
+        checks.push((format!("policy:{}:limit:{}", policy.name, ip), limit, window));
+    }
+    if let Some(key) = policy.key_extractor.extract(&request) {
+        checks.push((format!("policy:{}:limit:key:{}", policy.name, key), policy.limit, policy.window));
+    }
+    if checks.is_empty() {
+        return next.run(request).await;
+    }
+
+    if !policy.limiter.enforcing().await {
+        if let Some(analytics) = &policies.analytics {
+            for (key, _, window) in &checks {
+                let _ = analytics.record_killswitch_bypass(key, *window).await;
+            }
+        }
+        return next.run(request).await;
+    }
+    if request.extensions().get::<IpAllowlisted>().is_some() {
+        if let Some(analytics) = &policies.analytics {
+            for (key, _, window) in &checks {
+                let _ = analytics.record_allowlisted(key, *window).await;
+            }
+        }
+        return next.run(request).await;
+    }
+
+    let mut allowed = Vec::new();
+    for (key, limit, window) in checks {
+        let decision = policy
+            .limiter
+            .check(RateLimitRequest {
+                key: key.clone(),
+                limit,
+                window,
+                cost: policy.cost,
+            })
+            .await
+            .map(|mut decision| {
+                if decision.deny_reason == Some(DenyReason::KeyLimit) {
+                    decision.deny_reason = Some(DenyReason::PolicyLimit);
+                }
+                decision
+            });
+
+        if let (Some(analytics), Ok(decision)) = (&policies.analytics, &decision) {
+            if decision.failure_mode.is_none() {
+                let _ = analytics.record_check(&key, decision, window).await;
+            }
+        }
+
+        match decision {
+            Ok(decision) if !decision.allowed => return denied_response(limit, &decision),
+            Ok(decision) => allowed.push((limit, decision)),
+            // Backend errors are already resolved by the failure mode
+            Err(e) => tracing::warn!(policy = %policy.name, "Rate limit policy check failed, allowing request: {}", e),
+        }
+    }
+
+    let mut response = next.run(request).await;
+    // The headers describe the limit closest to running out
+    if let Some((limit, decision)) = allowed.iter().min_by_key(|(_, decision)| decision.remaining) {
+        insert_rate_limit_headers(&mut response, *limit, decision);
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::key_extractor::KeyExtractorConfig;
+    use crate::layer::RATE_LIMIT_LIMIT_HEADER;
+    use crate::rate_limiter::{RateLimitMode, RateLimitStrategy};
+    use axum::{body::Body, http::StatusCode, middleware, routing::get, Router};
+    use tower::ServiceExt;
+
+    fn limits(limit: u64) -> PolicyLimitsConfig {
+        PolicyLimitsConfig {
+            limit,
+            window: 60,
+            algorithm: None,
+            mode: None,
+            cost: 1,
+            key: KeyExtractorConfig::Header { name: "x-client-id".to_string() },
+            ip_limit: None,
+        }
+    }
+
+    fn policy(pattern: &str, methods: &[&str], limit: u64) -> RateLimitPolicyConfig {
+        RateLimitPolicyConfig {
+            pattern: pattern.to_string(),
+            methods: methods.iter().map(|method| method.to_string()).collect(),
+            limits: limits(limit),
+        }
+    }
+
+    fn table(policies: Vec<RateLimitPolicyConfig>, default_policy: Option<PolicyLimitsConfig>) -> RateLimitPolicies {
+        let config = RateLimitingConfig {
+            policies,
+            default_policy,
+            ..Default::default()
+        };
+        // Nothing listens here; resolving never touches Redis
+        let limiter = Arc::new(RateLimiter::new("redis://127.0.0.1:1").unwrap());
+        RateLimitPolicies::from_config(&config, &limiter).unwrap()
+    }
+
+    fn resolved<'a>(policies: &'a RateLimitPolicies, method: Method, path: &str) -> Option<&'a str> {
+        policies.resolve(&method, path).map(|policy| policy.name.as_str())
+    }
+
+    #[test]
+    fn test_most_specific_pattern_wins() {
+        // Listed broadest first so declaration order can't be what decides
+        let policies = table(
+            vec![
+                policy("/api/*", &[], 100),
+                policy("/api/admin/*", &[], 10),
+                policy("/api/admin/users", &[], 5),
+                policy("/api/*/export", &[], 1),
+            ],
+            None,
+        );
+
+        assert_eq!(resolved(&policies, Method::GET, "/api/search"), Some("/api/*"));
+        assert_eq!(resolved(&policies, Method::GET, "/api/v2/search/deep"), Some("/api/*"));
+        assert_eq!(resolved(&policies, Method::GET, "/api/admin/tenants"), Some("/api/admin/*"));
+        assert_eq!(resolved(&policies, Method::GET, "/api/admin/tenants/42"), Some("/api/admin/*"));
+        assert_eq!(resolved(&policies, Method::GET, "/api/admin/users"), Some("/api/admin/users"));
+        assert_eq!(resolved(&policies, Method::GET, "/api/admin/users/"), Some("/api/admin/users"));
+        assert_eq!(resolved(&policies, Method::GET, "/api/reports/export"), Some("/api/*/export"));
+
+        // A trailing wildcard needs at least one more segment
+        assert_eq!(resolved(&policies, Method::GET, "/api"), None);
+        assert_eq!(resolved(&policies, Method::GET, "/v1/check"), None);
+    }
+
+    #[test]
+    fn test_method_specific_policy_beats_any_method() {
+        let policies = table(
+            vec![policy("/api/*", &[], 100), policy("/api/*", &["post", "PUT"], 10)],
+            Some(limits(1000)),
+        );
+
+        assert_eq!(resolved(&policies, Method::POST, "/api/items"), Some("POST,PUT /api/*"));
+        assert_eq!(resolved(&policies, Method::GET, "/api/items"), Some("/api/*"));
+        // Unmatched requests fall back to the default policy
+        assert_eq!(resolved(&policies, Method::GET, "/health"), Some("default"));
+    }
+
+    #[test]
+    fn test_policy_mode_overrides_global_mode() {
+        let mut monitored = policy("/api/beta/*", &[], 10);
+        monitored.limits.mode = Some(RateLimitMode::Monitor);
+        let policies = table(vec![policy("/api/*", &[], 100), monitored], None);
+
+        let mode = |path| policies.resolve(&Method::GET, path).unwrap().limiter.mode();
+        assert_eq!(mode("/api/beta/search"), RateLimitMode::Monitor);
+        assert_eq!(mode("/api/search"), RateLimitMode::Enforce);
+    }
+
+    #[test]
+    fn test_invalid_policies_are_rejected() {
+        assert!(validate_policy(&policy("/api/*", &["GET"], 1)).is_ok());
+        assert_eq!(
+            validate_policy(&policy("/api//*/", &["post", "GET"], 1)).unwrap(),
+            validate_policy(&policy("/api/*", &["GET", "POST"], 1)).unwrap()
+        );
+        assert_ne!(
+            validate_policy(&policy("/api/*", &[], 1)).unwrap(),
+            validate_policy(&policy("/api/*", &["GET"], 1)).unwrap()
+        );
+        assert!(validate_policy(&policy("api/*", &[], 1)).is_err());
+        assert!(validate_policy(&policy("/api/us*", &[], 1)).is_err());
+        assert!(validate_policy(&policy("/api", &["GE T"], 1)).is_err());
+
+        // Skipped at runtime rather than taking the router down
+        let policies = table(vec![policy("api/*", &[], 1), policy("/api/*", &[], 2)], None);
+        assert_eq!(resolved(&policies, Method::GET, "/api/x"), Some("/api/*"));
+    }
+
+    #[test]
+    fn test_policy_algorithm_overrides_limiter_strategy() {
+        let mut sliding = policy("/api/*", &[], 10);
+        sliding.limits.algorithm = Some(RateLimitStrategy::SlidingWindow);
+        let policies = table(vec![sliding, policy("/other/*", &[], 10)], None);
+
+        let strategy = |path| policies.resolve(&Method::GET, path).unwrap().limiter.strategy();
+        assert_eq!(strategy("/api/x"), RateLimitStrategy::SlidingWindow);
+        assert_eq!(strategy("/other/x"), RateLimitStrategy::FixedWindow);
+    }
+
+    #[test]
+    fn test_no_policies_means_no_table() {
+        let limiter = Arc::new(RateLimiter::new("redis://127.0.0.1:1").unwrap());
+        assert!(RateLimitPolicies::from_config(&RateLimitingConfig::default(), &limiter).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_middleware_enforces_resolved_policy() {
+        let limiter = match RateLimiter::new("redis://127.0.0.1:6379") {
+            Ok(limiter) => Arc::new(limiter),
+            Err(_) => return,
+        };
+        if limiter.health_check().await.is_err() {
+            println!("Skipping policy middleware test - Redis not available");
+            return;
+        }
+
+        let config = RateLimitingConfig {
+            policies: vec![policy("/api/*", &[], 5), policy("/api/admin/*", &[], 1)],
+            ..Default::default()
+        };
+        let policies = Arc::new(RateLimitPolicies::from_config(&config, &limiter).unwrap());
+        let app = Router::new()
+            .route("/api/search", get(|| async { "ok" }))
+            .route("/api/admin/users", get(|| async { "ok" }))
+            .layer(middleware::from_fn_with_state(policies, rate_limit_policy_middleware));
+
+        let client_id = uuid::Uuid::new_v4().to_string();
+        let request = |path: &str| {
+            Request::builder()
+                .uri(path)
+                .header("x-client-id", client_id.as_str())
+                .body(Body::empty())
+                .unwrap()
+        };
+
+        let response = app.clone().oneshot(request("/api/admin/users")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[RATE_LIMIT_LIMIT_HEADER], "1");
+        let response = app.clone().oneshot(request("/api/admin/users")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(response.headers()[crate::rate_limiter::DENY_REASON_HEADER], "policy_limit");
+
+        // The broader policy keeps its own counter
+        let response = app.oneshot(request("/api/search")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[RATE_LIMIT_LIMIT_HEADER], "5");
+    }
+
+    #[tokio::test]
+    async fn test_ip_limit_applies_without_an_api_key() {
+        let limiter = match RateLimiter::new("redis://127.0.0.1:6379") {
+            Ok(limiter) => Arc::new(limiter),
+            Err(_) => return,
+        };
+        if limiter.health_check().await.is_err() {
+            println!("Skipping IP limit test - Redis not available");
+            return;
+        }
+
+        let mut signup = policy("/signup", &["POST"], 3);
+        signup.limits.key = KeyExtractorConfig::ApiKey;
+        signup.limits.ip_limit = Some(crate::config::IpLimitConfig { limit: 2, window: 60 });
+        let config = RateLimitingConfig {
+            policies: vec![signup],
+            ..Default::default()
+        };
+        let policies = Arc::new(RateLimitPolicies::from_config(&config, &limiter).unwrap());
+        let app = Router::new()
+            .route("/signup", axum::routing::post(|| async { "ok" }))
+            .layer(middleware::from_fn_with_state(policies, rate_limit_policy_middleware));
+
+        // A fresh client IP and API key per run
+        let [a, b, c, d, ..] = uuid::Uuid::new_v4().into_bytes();
+        let api_key = format!("rw_{}", uuid::Uuid::new_v4().simple());
+        let request = |ip: String, api_key: Option<&str>| {
+            let mut builder = Request::builder().method("POST").uri("/signup").header("x-forwarded-for", ip);
+            if let Some(api_key) = api_key {
+                builder = builder.header("authorization", format!("Bearer {api_key}"));
+            }
+            builder.body(Body::empty()).unwrap()
+        };
+        let ip = format!("2001:db8:{:x}{:02x}:{:x}{:02x}::1", a, b, c, d);
+
+        for _ in 0..2 {
+            let response = app.clone().oneshot(request(ip.clone(), None)).await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+            assert_eq!(response.headers()[RATE_LIMIT_LIMIT_HEADER], "2");
+        }
+        let response = app.clone().oneshot(request(ip.clone(), None)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(response.headers()[crate::rate_limiter::DENY_REASON_HEADER], "policy_limit");
+
+        // A key doesn't lift the IP's limit
+        let response = app.clone().oneshot(request(ip, Some(&api_key))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+
+        // From other IPs the key's own limit applies as well
+        let other_ip = |n: u8| format!("2001:db8:{:x}{:02x}:{:x}{:02x}::{:x}", a, b, c, d ^ 0xff, n + 1);
+        for n in 0..3 {
+            let response = app.clone().oneshot(request(other_ip(n), Some(&api_key))).await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+        }
+        let response = app.oneshot(request(other_ip(3), Some(&api_key))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(response.headers()[RATE_LIMIT_LIMIT_HEADER], "3");
+    }
+}
@@ -1,7 +1,10 @@
-use redis::{AsyncCommands, Client};
+use redis::AsyncCommands;
 use serde::{Deserialize, Serialize};
 
+use crate::redis_backend::{scan_keys, RedisConnector};
+
 #[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 pub struct DataDeletionRequest {
     pub user_id: String,
     pub reason: String,
@@ -15,11 +18,11 @@ pub struct DataDeletionResponse {
 }
 
 pub struct PrivacyManager {
-    redis: Client,
+    redis: RedisConnector,
 }
 
 impl PrivacyManager {
-    pub fn new(redis: Client) -> Self {
+    pub fn new(redis: RedisConnector) -> Self {
         Self { redis }
     }
 
@@ -27,8 +30,8 @@ impl PrivacyManager {
         let mut conn = self.redis.get_async_connection().await?;
 
         // Find all keys for this user using pattern matching
-        let pattern = format!("rate_limit:{user_id}:*");
-        let keys: Vec<String> = conn.keys(pattern).await?;
+        let pattern = format!("rate_limit:{}:*", self.redis.hash_tag(user_id));
+        let keys = scan_keys(&mut conn, &pattern).await?;
 
         let deleted_count = keys.len() as u64;
 
@@ -57,8 +60,8 @@ impl PrivacyManager {
     pub async fn get_user_data_summary(&self, user_id: &str) -> anyhow::Result<UserDataSummary> {
         let mut conn = self.redis.get_async_connection().await?;
 
-        let pattern = format!("rate_limit:{user_id}:*");
-        let keys: Vec<String> = conn.keys(pattern).await?;
+        let pattern = format!("rate_limit:{}:*", self.redis.hash_tag(user_id));
+        let keys = scan_keys(&mut conn, &pattern).await?;
 
         let mut total_requests = 0u64;
         let mut active_windows = 0u64;
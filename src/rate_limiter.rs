@@ -1,8 +1,14 @@
-use redis::{AsyncCommands, Client, RedisResult};
+use redis::{AsyncCommands, RedisResult};
 use serde::{Deserialize, Serialize};
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tracing::Instrument;
+
+use crate::enforcement::EnforcementSwitch;
+use crate::redis_backend::{ensure_same_slot, key_slot, LuaScript, RedisConnector};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 pub struct RateLimitRequest {
     pub key: String,
     pub limit: u64,
@@ -10,37 +16,1739 @@ pub struct RateLimitRequest {
     pub cost: u64,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct RateLimitResponse {
-    pub allowed: bool,
-    pub remaining: u64,
-    pub reset_in: u64,
-    pub retry_after: Option<u64>,
-}
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct RateLimitResponse {
+    pub allowed: bool,
+    pub remaining: u64,
+    pub reset_in: u64,
+    pub retry_after: Option<u64>,
+    /// Set when Redis was unavailable and the decision came from the failure mode
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub failure_mode: Option<RateLimitFailureMode>,
+    /// Set in monitor mode when the request was over the limit but allowed anyway
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub would_deny: bool,
+    /// Leaky bucket only: the request was admitted, but the caller should
+    /// wait this many milliseconds before sending it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub wait_for: Option<u64>,
+    /// Why the request was denied; only set when `allowed` is false
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deny_reason: Option<DenyReason>,
+}
+
+/// Response header naming the `DenyReason` of a denied request
+pub const DENY_REASON_HEADER: &str = "x-ratelimit-deny-reason";
+
+/// Why a request was denied, in the response body and `DENY_REASON_HEADER`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum DenyReason {
+    /// The key's own limit is exhausted
+    KeyLimit,
+    /// The tenant's API call quota is exhausted
+    TenantLimit,
+    /// A server-side route policy's limit is exhausted
+    PolicyLimit,
+    /// Redis was unavailable and the limiter fails closed
+    BackendUnavailable,
+    /// The client's IP is on the IP denylist
+    Banned,
+    /// The API key is on the key denylist
+    KeyDenied,
+    /// Threat detection wants a CAPTCHA solved first
+    Challenged,
+    /// Threat detection rejected the request
+    ThreatDetected,
+    /// Threat detection failed and its failure mode is `fail_closed`
+    SecurityUnavailable,
+}
+
+impl DenyReason {
+    pub const ALL: [DenyReason; 9] = [
+        DenyReason::KeyLimit,
+        DenyReason::TenantLimit,
+        DenyReason::PolicyLimit,
+        DenyReason::BackendUnavailable,
+        DenyReason::Banned,
+        DenyReason::KeyDenied,
+        DenyReason::Challenged,
+        DenyReason::ThreatDetected,
+        DenyReason::SecurityUnavailable,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DenyReason::KeyLimit => "key_limit",
+            DenyReason::TenantLimit => "tenant_limit",
+            DenyReason::PolicyLimit => "policy_limit",
+            DenyReason::BackendUnavailable => "backend_unavailable",
+            DenyReason::Banned => "banned",
+            DenyReason::KeyDenied => "key_denied",
+            DenyReason::Challenged => "challenged",
+            DenyReason::ThreatDetected => "threat_detected",
+            DenyReason::SecurityUnavailable => "security_unavailable",
+        }
+    }
+
+    pub fn message(&self) -> &'static str {
+        match self {
+            DenyReason::KeyLimit | DenyReason::PolicyLimit => "Rate limit exceeded",
+            DenyReason::TenantLimit => "Tenant quota exceeded",
+            DenyReason::BackendUnavailable => "Rate limiter unavailable",
+            DenyReason::Banned => "Client IP is banned",
+            DenyReason::KeyDenied => "API key is denylisted",
+            DenyReason::Challenged => "Challenge required",
+            DenyReason::ThreatDetected => "Request blocked by threat detection",
+            DenyReason::SecurityUnavailable => "Threat detection unavailable",
+        }
+    }
+
+    /// Error response for a request refused before reaching a handler: a
+    /// problem document with `deny_reason`, see `ApiError::denied`. The
+    /// reason is also set as a response extension, for
+    /// `record_denials_middleware`.
+    pub fn response(self, status: axum::http::StatusCode) -> axum::response::Response {
+        use axum::response::IntoResponse;
+
+        let mut response = crate::api_error::ApiError::denied(self, status).into_response();
+        response.extensions_mut().insert(self);
+        response
+    }
+}
+
+impl RateLimitResponse {
+    /// Decision for a request exempt from limits: allowed, with the full limit left
+    pub fn bypassed(req: &RateLimitRequest) -> Self {
+        Self {
+            allowed: true,
+            remaining: req.limit,
+            reset_in: req.window,
+            retry_after: None,
+            failure_mode: None,
+            would_deny: false,
+            wait_for: None,
+            deny_reason: None,
+        }
+    }
+}
+
+/// Algorithm used to enforce limits
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "config-schema", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum RateLimitStrategy {
+    /// Counter per aligned window; cheap, but allows bursts at window boundaries
+    #[default]
+    FixedWindow,
+    /// Sliding log evaluated by a single Lua script on Redis.
+    ///
+    /// Check-and-increment is atomic on the Redis server and uses the Redis
+    /// clock, so the limit holds globally no matter how many instances share
+    /// the same Redis.
+    SlidingWindow,
+    /// Requests drain at an even `limit / window` rate, with no bursts.
+    ///
+    /// A plain check denies a request that would have to queue behind
+    /// earlier ones; `check_with_wait` admits it with `wait_for` set, and
+    /// `check_and_wait` sleeps that long before returning.
+    LeakyBucket,
+}
+
+impl RateLimitStrategy {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RateLimitStrategy::FixedWindow => "fixed_window",
+            RateLimitStrategy::SlidingWindow => "sliding_window",
+            RateLimitStrategy::LeakyBucket => "leaky_bucket",
+        }
+    }
+}
+
+/// Decision to return when Redis cannot be reached or a command fails
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "config-schema", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum RateLimitFailureMode {
+    /// Fail open: allow the request so a Redis outage doesn't take the API down
+    #[default]
+    Allow,
+    /// Fail closed: deny the request until Redis is back
+    Deny,
+}
+
+impl RateLimitFailureMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RateLimitFailureMode::Allow => "fail_open",
+            RateLimitFailureMode::Deny => "fail_closed",
+        }
+    }
+}
+
+/// Whether limits are enforced or only reported
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "config-schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum RateLimitMode {
+    /// Deny requests over the limit
+    #[default]
+    Enforce,
+    /// Dry run: allow every request, flagging the ones enforce mode would
+    /// deny, so limits can be sized against real traffic first
+    Monitor,
+}
+
+impl RateLimitMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RateLimitMode::Enforce => "enforce",
+            RateLimitMode::Monitor => "monitor",
+        }
+    }
+}
+
+/// Default cap on how long `check_with_wait` may ask a caller to wait
+pub const DEFAULT_MAX_WAIT: Duration = Duration::from_secs(5);
+
+/// Retry hint for requests denied by `RateLimitFailureMode::Deny`
+const FAILURE_RETRY_AFTER_SECS: u64 = 1;
+
+/// Default time a check's result is kept for retries with the same idempotency key
+pub const DEFAULT_IDEMPOTENCY_TTL: Duration = Duration::from_secs(60);
+
+/// How long a retry waits for the check it repeats to finish
+const IDEMPOTENCY_WAIT: Duration = Duration::from_secs(1);
+const IDEMPOTENCY_POLL: Duration = Duration::from_millis(50);
+
+/// Outcome of `RateLimiter::check_idempotent`
+#[derive(Debug)]
+pub enum IdempotentCheck {
+    /// Checked now and counted against the limit
+    Checked(RateLimitResponse),
+    /// The result of an earlier check with the same idempotency key; nothing
+    /// was counted this time
+    Replayed(RateLimitResponse),
+    /// An earlier check with the same idempotency key hasn't finished
+    InProgress,
+}
+
+/// Where a claimed idempotency key stands
+enum IdempotencyClaim {
+    Claimed,
+    Done(String),
+    InProgress,
+}
+
+/// Atomic sliding-window check-and-increment for one or more keys.
+///
+/// Each admitted request is one sorted-set member, `<id>#<cost>`, scored by
+/// when it was admitted; a companion key holds the running total of units,
+/// so a check costs the same whatever its cost.
+///
+/// KEYS[2i-1], KEYS[2i] = sorted set and units total for item i; ARGV holds
+/// (limit, window_ms, cost, member id) for each item in the same order.
+/// Returns a flat {allowed, remaining, reset_in_ms} triple per item.
+static SLIDING_WINDOW_SCRIPT: LuaScript = LuaScript::new(r#"
+-- Units a member stands for; members from before weights were recorded count one
+local function weight(member)
+    return tonumber(string.match(member, '#(%d+)$')) or 1
+end
+
+-- Drop members that left the window and return the units still in it,
+-- keeping the running total in units_key in step
+local function prune(key, units_key, now, window)
+    local expired = redis.call('ZRANGEBYSCORE', key, '-inf', now - window)
+    if #expired > 0 then
+        redis.call('ZREMRANGEBYSCORE', key, '-inf', now - window)
+    end
+    if redis.call('EXISTS', key) == 0 then
+        redis.call('DEL', units_key)
+        return 0
+    end
+
+    local total = tonumber(redis.call('GET', units_key))
+    if total == nil then
+        total = 0
+        for _, m in ipairs(redis.call('ZRANGE', key, 0, -1)) do
+            total = total + weight(m)
+        end
+    elseif #expired > 0 then
+        for _, m in ipairs(expired) do
+            total = total - weight(m)
+        end
+    else
+        return total
+    end
+    redis.call('SET', units_key, total, 'PX', window)
+    return total
+end
+
+local time = redis.call('TIME')
+local now = tonumber(time[1]) * 1000 + math.floor(tonumber(time[2]) / 1000)
+local results = {}
+
+for i = 1, #KEYS / 2 do
+    local key = KEYS[i * 2 - 1]
+    local units_key = KEYS[i * 2]
+    local base = (i - 1) * 4
+    local limit = tonumber(ARGV[base + 1])
+    local window = tonumber(ARGV[base + 2])
+    local cost = tonumber(ARGV[base + 3])
+    local member = ARGV[base + 4]
+
+    local current = prune(key, units_key, now, window)
+
+    local reset_in = window
+    local oldest = redis.call('ZRANGE', key, 0, 0, 'WITHSCORES')
+    if oldest[2] then
+        reset_in = tonumber(oldest[2]) + window - now
+    end
+
+    if current + cost <= limit then
+        redis.call('ZADD', key, now, member .. '#' .. cost)
+        redis.call('PEXPIRE', key, window)
+        redis.call('SET', units_key, current + cost, 'PX', window)
+        table.insert(results, 1)
+        table.insert(results, limit - current - cost)
+    else
+        table.insert(results, 0)
+        table.insert(results, 0)
+    end
+    table.insert(results, reset_in)
+end
+
+return results
+"#);
+
+/// Fixed-window check-and-increment for a batch of keys.
+///
+/// KEYS[i] = window counter for item i; ARGV holds (limit, window_s, cost)
+/// for each item. Returns a flat {allowed, remaining} pair per item.
+static FIXED_WINDOW_BATCH_SCRIPT: LuaScript = LuaScript::new(r#"
+local results = {}
+
+for i, key in ipairs(KEYS) do
+    local base = (i - 1) * 3
+    local limit = tonumber(ARGV[base + 1])
+    local window = tonumber(ARGV[base + 2])
+    local cost = tonumber(ARGV[base + 3])
+    local current = tonumber(redis.call('GET', key) or '0')
+
+    if current + cost <= limit then
+        redis.call('INCRBY', key, cost)
+        redis.call('EXPIRE', key, window)
+        table.insert(results, 1)
+        table.insert(results, limit - current - cost)
+    else
+        table.insert(results, 0)
+        table.insert(results, 0)
+    end
+end
+
+return results
+"#);
+
+/// All-or-nothing sliding-window check of several tiers for one key.
+///
+/// Stored as in the single-tier script. KEYS[2i-1], KEYS[2i] = sorted set
+/// and units total for tier i; ARGV = (cost, member id) followed by
+/// (limit, window_ms) per tier. Units are only added when every tier has
+/// room. Returns a flat {allowed, remaining, reset_in_ms} triple per tier.
+static SLIDING_WINDOW_MULTI_SCRIPT: LuaScript = LuaScript::new(r#"
+-- Units a member stands for; members from before weights were recorded count one
+local function weight(member)
+    return tonumber(string.match(member, '#(%d+)$')) or 1
+end
+
+-- Drop members that left the window and return the units still in it,
+-- keeping the running total in units_key in step
+local function prune(key, units_key, now, window)
+    local expired = redis.call('ZRANGEBYSCORE', key, '-inf', now - window)
+    if #expired > 0 then
+        redis.call('ZREMRANGEBYSCORE', key, '-inf', now - window)
+    end
+    if redis.call('EXISTS', key) == 0 then
+        redis.call('DEL', units_key)
+        return 0
+    end
+
+    local total = tonumber(redis.call('GET', units_key))
+    if total == nil then
+        total = 0
+        for _, m in ipairs(redis.call('ZRANGE', key, 0, -1)) do
+            total = total + weight(m)
+        end
+    elseif #expired > 0 then
+        for _, m in ipairs(expired) do
+            total = total - weight(m)
+        end
+    else
+        return total
+    end
+    redis.call('SET', units_key, total, 'PX', window)
+    return total
+end
+
+local time = redis.call('TIME')
+local now = tonumber(time[1]) * 1000 + math.floor(tonumber(time[2]) / 1000)
+local cost = tonumber(ARGV[1])
+local member = ARGV[2]
+local tiers = #KEYS / 2
+local counts, resets = {}, {}
+local all_allowed = true
+
+for i = 1, tiers do
+    local key = KEYS[i * 2 - 1]
+    local limit = tonumber(ARGV[i * 2 + 1])
+    local window = tonumber(ARGV[i * 2 + 2])
+
+    counts[i] = prune(key, KEYS[i * 2], now, window)
+
+    resets[i] = window
+    local oldest = redis.call('ZRANGE', key, 0, 0, 'WITHSCORES')
+    if oldest[2] then
+        resets[i] = tonumber(oldest[2]) + window - now
+    end
+
+    if counts[i] + cost > limit then
+        all_allowed = false
+    end
+end
+
+local results = {}
+for i = 1, tiers do
+    local key = KEYS[i * 2 - 1]
+    local limit = tonumber(ARGV[i * 2 + 1])
+    local window = tonumber(ARGV[i * 2 + 2])
+    local allowed = counts[i] + cost <= limit
+    local remaining = 0
+
+    if all_allowed then
+        redis.call('ZADD', key, now, member .. '#' .. cost)
+        redis.call('PEXPIRE', key, window)
+        redis.call('SET', KEYS[i * 2], counts[i] + cost, 'PX', window)
+        remaining = limit - counts[i] - cost
+    elseif allowed then
+        remaining = limit - counts[i]
+    end
+
+    table.insert(results, allowed and 1 or 0)
+    table.insert(results, remaining)
+    table.insert(results, resets[i])
+end
+
+return results
+"#);
+
+/// All-or-nothing fixed-window check of several tiers for one key.
+///
+/// KEYS[i] = window counter for tier i; ARGV = cost followed by
+/// (limit, window_s) per tier. Counters are only incremented when every
+/// tier has room. Returns a flat {allowed, remaining} pair per tier.
+static FIXED_WINDOW_MULTI_SCRIPT: LuaScript = LuaScript::new(r#"
+local cost = tonumber(ARGV[1])
+local counts = {}
+local all_allowed = true
+
+for i, key in ipairs(KEYS) do
+    counts[i] = tonumber(redis.call('GET', key) or '0')
+    if counts[i] + cost > tonumber(ARGV[i * 2]) then
+        all_allowed = false
+    end
+end
+
+local results = {}
+for i, key in ipairs(KEYS) do
+    local limit = tonumber(ARGV[i * 2])
+    local window = tonumber(ARGV[i * 2 + 1])
+    local allowed = counts[i] + cost <= limit
+    local remaining = 0
+
+    if all_allowed then
+        redis.call('INCRBY', key, cost)
+        redis.call('EXPIRE', key, window)
+        remaining = limit - counts[i] - cost
+    elseif allowed then
+        remaining = limit - counts[i]
+    end
+
+    table.insert(results, allowed and 1 or 0)
+    table.insert(results, remaining)
+end
+
+return results
+"#);
+
+/// Read-only view of a sliding window: KEYS[1] = sorted set, KEYS[2] = units
+/// total, ARGV[1] = window_ms. Returns {current units, reset_in_ms}.
+static SLIDING_WINDOW_PEEK_SCRIPT: LuaScript = LuaScript::new(r#"
+local function weight(member)
+    return tonumber(string.match(member, '#(%d+)$')) or 1
+end
+
+local time = redis.call('TIME')
+local now = tonumber(time[1]) * 1000 + math.floor(tonumber(time[2]) / 1000)
+local window = tonumber(ARGV[1])
+
+-- The stored total still includes members that left the window but
+-- haven't been pruned; without one, add up what's in the window
+local current = tonumber(redis.call('GET', KEYS[2]))
+if current == nil then
+    current = 0
+    for _, m in ipairs(redis.call('ZRANGEBYSCORE', KEYS[1], '(' .. (now - window), '+inf')) do
+        current = current + weight(m)
+    end
+else
+    for _, m in ipairs(redis.call('ZRANGEBYSCORE', KEYS[1], '-inf', now - window)) do
+        current = current - weight(m)
+    end
+    current = math.max(current, 0)
+end
+
+local reset_in = window
+local oldest = redis.call('ZRANGEBYSCORE', KEYS[1], '(' .. (now - window), '+inf', 'WITHSCORES', 'LIMIT', 0, 1)
+if oldest[2] then
+    reset_in = tonumber(oldest[2]) + window - now
+end
+
+return {current, reset_in}
+"#);
+
+/// Leaky-bucket admission for one or more keys, as virtual scheduling.
+///
+/// KEYS[i] = the time, in ms, at which item i's queue drains; ARGV[1] =
+/// how long a request may wait in the queue, in ms, then (limit, window_ms,
+/// cost) per item. A request is admitted if its turn comes within the wait
+/// bound and the queue stays within one window; admitting it pushes the
+/// drain time back by `cost * window / limit`. Returns a flat
+/// {allowed, remaining, reset_in_ms, wait_ms} quad per item, where wait_ms
+/// is how long to wait when admitted and how long to back off when not.
+static LEAKY_BUCKET_SCRIPT: LuaScript = LuaScript::new(r#"
+local time = redis.call('TIME')
+local now = tonumber(time[1]) * 1000 + math.floor(tonumber(time[2]) / 1000)
+local max_wait = tonumber(ARGV[1])
+local results = {}
+
+for i, key in ipairs(KEYS) do
+    local base = 1 + (i - 1) * 3
+    local limit = tonumber(ARGV[base + 1])
+    local window = tonumber(ARGV[base + 2])
+    local cost = tonumber(ARGV[base + 3])
+    local interval = window / limit
+
+    local drain = math.max(tonumber(redis.call('GET', key) or '0'), now)
+    local wait = drain - now
+    local queued = wait + cost * interval
+
+    if wait <= max_wait and queued <= window then
+        redis.call('SET', key, string.format('%.3f', drain + cost * interval), 'PX', math.ceil(queued))
+        table.insert(results, 1)
+        table.insert(results, math.max(0, math.floor((window - queued) / interval)))
+        table.insert(results, math.ceil(queued))
+        table.insert(results, math.ceil(wait))
+    else
+        table.insert(results, 0)
+        table.insert(results, math.max(0, math.floor((window - wait) / interval)))
+        table.insert(results, math.ceil(wait))
+        table.insert(results, math.ceil(math.max(wait - max_wait, queued - window)))
+    end
+end
+
+return results
+"#);
+
+/// All-or-nothing leaky-bucket check of several tiers for one key, without
+/// queueing: a request is admitted only if no tier makes it wait.
+///
+/// KEYS[i] = drain time for tier i; ARGV = cost followed by (limit,
+/// window_ms) per tier. Returns a flat {allowed, remaining, reset_in_ms}
+/// triple per tier.
+static LEAKY_BUCKET_MULTI_SCRIPT: LuaScript = LuaScript::new(r#"
+local time = redis.call('TIME')
+local now = tonumber(time[1]) * 1000 + math.floor(tonumber(time[2]) / 1000)
+local cost = tonumber(ARGV[1])
+local drains, allowed = {}, {}
+local all_allowed = true
+
+for i, key in ipairs(KEYS) do
+    local limit = tonumber(ARGV[i * 2])
+    local window = tonumber(ARGV[i * 2 + 1])
+    drains[i] = math.max(tonumber(redis.call('GET', key) or '0'), now)
+    allowed[i] = drains[i] == now and cost * window / limit <= window
+    if not allowed[i] then
+        all_allowed = false
+    end
+end
+
+local results = {}
+for i, key in ipairs(KEYS) do
+    local limit = tonumber(ARGV[i * 2])
+    local window = tonumber(ARGV[i * 2 + 1])
+    local interval = window / limit
+    local queued = drains[i] - now
+
+    if all_allowed then
+        queued = queued + cost * interval
+        redis.call('SET', key, string.format('%.3f', now + queued), 'PX', math.ceil(queued))
+    end
+
+    table.insert(results, allowed[i] and 1 or 0)
+    table.insert(results, math.max(0, math.floor((window - queued) / interval)))
+    table.insert(results, math.ceil(queued))
+end
+
+return results
+"#);
+
+/// Largest limit one check accepts
+pub const MAX_LIMIT: u64 = 1_000_000_000;
+
+/// Largest cost one check may consume
+pub const MAX_COST: u64 = 1_000_000;
+
+/// Reject malformed requests before touching Redis
+pub fn validate_request(req: &RateLimitRequest) -> anyhow::Result<()> {
+    if req.window == 0 {
+        return Err(anyhow::anyhow!("Window size cannot be zero"));
+    }
+    if req.limit == 0 {
+        return Err(anyhow::anyhow!("Limit cannot be zero"));
+    }
+    if req.limit > MAX_LIMIT {
+        return Err(anyhow::anyhow!("Limit {} exceeds maximum of {}", req.limit, MAX_LIMIT));
+    }
+    if req.cost > MAX_COST {
+        return Err(anyhow::anyhow!("Cost {} exceeds maximum of {}", req.cost, MAX_COST));
+    }
+    if req.key.is_empty() {
+        return Err(anyhow::anyhow!("Key cannot be empty"));
+    }
+    Ok(())
+}
+
+/// Reject keys that aren't a single exact key. Inspecting or resetting
+/// `user:*` must not touch every user.
+pub fn validate_exact_key(key: &str) -> anyhow::Result<()> {
+    if key.is_empty() {
+        return Err(anyhow::anyhow!("Key cannot be empty"));
+    }
+    if key.contains(['*', '?', '[']) {
+        return Err(anyhow::anyhow!("Key must be exact; wildcards are not allowed"));
+    }
+    Ok(())
+}
+
+/// Validate a batch against the configured size cap
+pub fn validate_batch(reqs: &[RateLimitRequest], max_batch_size: usize) -> anyhow::Result<()> {
+    if reqs.is_empty() {
+        return Err(anyhow::anyhow!("Batch cannot be empty"));
+    }
+    if reqs.len() > max_batch_size {
+        return Err(anyhow::anyhow!(
+            "Batch size {} exceeds maximum of {}",
+            reqs.len(),
+            max_batch_size
+        ));
+    }
+    reqs.iter().try_for_each(validate_request)
+}
+
+/// Most tiers accepted by one `check_multi` call
+pub const MAX_LIMIT_TIERS: usize = 10;
+
+/// One tier of a layered limit such as "10 per second and 1000 per hour"
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "config-schema", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct RateLimitTier {
+    pub limit: u64,
+    /// Window in seconds
+    pub window: u64,
+}
+
+/// Result of checking every tier of a layered limit
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct MultiRateLimitResponse {
+    /// True only if every tier had room; otherwise nothing was consumed
+    pub allowed: bool,
+    /// Index of the denying tier with the longest retry-after
+    pub failed_tier: Option<usize>,
+    pub retry_after: Option<u64>,
+    /// Per-tier results, in the order the limits were given
+    pub tiers: Vec<RateLimitResponse>,
+}
+
+impl MultiRateLimitResponse {
+    fn from_tiers(tiers: Vec<RateLimitResponse>) -> Self {
+        let failed_tier = tiers
+            .iter()
+            .enumerate()
+            .filter(|(_, tier)| !tier.allowed)
+            .max_by_key(|(_, tier)| tier.retry_after)
+            .map(|(index, _)| index);
+
+        Self {
+            allowed: failed_tier.is_none(),
+            failed_tier,
+            retry_after: failed_tier.and_then(|index| tiers[index].retry_after),
+            tiers,
+        }
+    }
+
+    /// Whether monitor mode let this through over the limit
+    pub fn would_deny(&self) -> bool {
+        self.tiers.iter().any(|tier| tier.would_deny)
+    }
+
+    /// The tier that decided the outcome: the failed tier when denied,
+    /// otherwise the one with the least remaining
+    pub fn binding_tier(&self) -> usize {
+        self.failed_tier.unwrap_or_else(|| {
+            self.tiers
+                .iter()
+                .enumerate()
+                .min_by_key(|(_, tier)| tier.remaining)
+                .map(|(index, _)| index)
+                .unwrap_or(0)
+        })
+    }
+}
+
+/// Validate the tiers of a `check_multi` call
+fn validate_tiers(reqs: &[RateLimitRequest]) -> anyhow::Result<()> {
+    if reqs.is_empty() {
+        return Err(anyhow::anyhow!("At least one limit is required"));
+    }
+    if reqs.len() > MAX_LIMIT_TIERS {
+        return Err(anyhow::anyhow!(
+            "{} limits exceeds maximum of {}",
+            reqs.len(),
+            MAX_LIMIT_TIERS
+        ));
+    }
+    reqs.iter().try_for_each(validate_request)?;
+
+    let mut windows: Vec<u64> = reqs.iter().map(|req| req.window).collect();
+    windows.sort_unstable();
+    if windows.windows(2).any(|pair| pair[0] == pair[1]) {
+        return Err(anyhow::anyhow!("Each limit must use a different window"));
+    }
+    Ok(())
+}
+
+/// One Redis entry holding a key's rate limit state
+#[derive(Debug, Clone, Serialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct KeyStateEntry {
+    pub redis_key: String,
+    /// Strategy that wrote the entry
+    pub strategy: RateLimitStrategy,
+    /// Window of a layered limit tier, in seconds
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub window: Option<u64>,
+    /// Start of a fixed window, as a Unix timestamp
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub window_start: Option<u64>,
+    /// Units counted in a fixed or sliding window
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub count: Option<u64>,
+    /// How long a leaky bucket's queue takes to drain, in milliseconds
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub queued_ms: Option<u64>,
+    /// Time until Redis expires the entry, in milliseconds
+    pub ttl_ms: Option<u64>,
+}
+
+/// Escape `*`, `?`, `[`, `]` and `\` so `value` only matches itself in SCAN
+fn escape_glob(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if matches!(c, '*' | '?' | '[' | ']' | '\\') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Strategy, tier window and fixed window start of an entry, from the part
+/// of its name after `rate_limit:{key}:`. `None` if it belongs to a longer
+/// key that shares the prefix, such as `user:1:sliding` under `user`.
+fn parse_state_suffix(suffix: &str) -> Option<(RateLimitStrategy, Option<u64>, Option<u64>)> {
+    let is_number = |s: &str| !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit());
+    let (window, rest) = match suffix.split_once("s:") {
+        Some((window, rest)) if is_number(window) => (Some(window.parse().ok()?), rest),
+        _ => (None, suffix),
+    };
+    match rest {
+        "sliding" => Some((RateLimitStrategy::SlidingWindow, window, None)),
+        "leaky" => Some((RateLimitStrategy::LeakyBucket, window, None)),
+        start if is_number(start) => Some((RateLimitStrategy::FixedWindow, window, Some(start.parse().ok()?))),
+        _ => None,
+    }
+}
+
+pub struct RateLimiter {
+    redis: RedisConnector,
+    strategy: RateLimitStrategy,
+    failure_mode: RateLimitFailureMode,
+    mode: RateLimitMode,
+    max_wait: Duration,
+    idempotency_ttl: Duration,
+    enforcement: Option<Arc<EnforcementSwitch>>,
+}
+
+impl RateLimiter {
+    pub fn new(redis_url: &str) -> anyhow::Result<Self> {
+        Ok(Self::from_connector(RedisConnector::open(redis_url)?))
+    }
+
+    pub fn from_connector(redis: RedisConnector) -> Self {
+        Self {
+            redis,
+            strategy: RateLimitStrategy::default(),
+            failure_mode: RateLimitFailureMode::default(),
+            mode: RateLimitMode::default(),
+            max_wait: DEFAULT_MAX_WAIT,
+            idempotency_ttl: DEFAULT_IDEMPOTENCY_TTL,
+            enforcement: None,
+        }
+    }
+
+    pub fn with_strategy(mut self, strategy: RateLimitStrategy) -> Self {
+        self.strategy = strategy;
+        self
+    }
+
+    pub fn strategy(&self) -> RateLimitStrategy {
+        self.strategy
+    }
+
+    /// A limiter sharing this one's Redis, failure mode, mode, wait bound and
+    /// kill-switch but using `strategy`
+    pub fn for_strategy(&self, strategy: RateLimitStrategy) -> Self {
+        Self {
+            redis: self.redis.clone(),
+            strategy,
+            failure_mode: self.failure_mode,
+            mode: self.mode,
+            max_wait: self.max_wait,
+            idempotency_ttl: self.idempotency_ttl,
+            enforcement: self.enforcement.clone(),
+        }
+    }
+
+    /// Enforce limits, or only report what would be denied
+    pub fn with_mode(mut self, mode: RateLimitMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    pub fn mode(&self) -> RateLimitMode {
+        self.mode
+    }
+
+    /// Turn a denial into a flagged allow in monitor mode.
+    ///
+    /// Failure-mode decisions are left alone: they're about backend health,
+    /// not the limit.
+    fn apply_mode(&self, response: &mut RateLimitResponse) {
+        if self.mode == RateLimitMode::Monitor && !response.allowed && response.failure_mode.is_none() {
+            response.allowed = true;
+            response.retry_after = None;
+            response.would_deny = true;
+        }
+    }
+
+    /// Say why a denied response was denied
+    fn set_deny_reason(response: &mut RateLimitResponse) {
+        response.deny_reason = match (response.allowed, response.failure_mode) {
+            (true, _) => None,
+            (false, Some(_)) => Some(DenyReason::BackendUnavailable),
+            (false, None) => Some(DenyReason::KeyLimit),
+        };
+    }
+
+    /// Longest a leaky-bucket request may be asked to wait, whatever the
+    /// caller offers to `check_with_wait`
+    pub fn with_max_wait(mut self, max_wait: Duration) -> Self {
+        self.max_wait = max_wait;
+        self
+    }
+
+    /// How long a check's result is kept for `check_idempotent`
+    pub fn with_idempotency_ttl(mut self, idempotency_ttl: Duration) -> Self {
+        self.idempotency_ttl = idempotency_ttl;
+        self
+    }
+
+    /// Stop enforcing limits while `enforcement` is switched off. Callers ask
+    /// `enforcing` before checking, so they can record the bypass.
+    pub fn with_enforcement(mut self, enforcement: Arc<EnforcementSwitch>) -> Self {
+        self.enforcement = Some(enforcement);
+        self
+    }
+
+    pub fn enforcement(&self) -> Option<&Arc<EnforcementSwitch>> {
+        self.enforcement.as_ref()
+    }
+
+    /// False while the global kill-switch is off
+    pub async fn enforcing(&self) -> bool {
+        match &self.enforcement {
+            Some(enforcement) => enforcement.is_enabled().await,
+            None => true,
+        }
+    }
+
+    /// Whether checks are allowed or denied while Redis is unavailable
+    pub fn with_failure_mode(mut self, failure_mode: RateLimitFailureMode) -> Self {
+        self.failure_mode = failure_mode;
+        self
+    }
+
+    /// Decisions for `reqs` after the backend failed, per the failure mode
+    fn failure_responses(&self, reqs: &[RateLimitRequest], err: &anyhow::Error) -> Vec<RateLimitResponse> {
+        tracing::warn!(
+            failure_mode = self.failure_mode.as_str(),
+            checks = reqs.len(),
+            "Rate limit backend unavailable, {} requests: {:#}",
+            match self.failure_mode {
+                RateLimitFailureMode::Allow => "allowing",
+                RateLimitFailureMode::Deny => "denying",
+            },
+            err
+        );
+
+        reqs.iter()
+            .map(|req| match self.failure_mode {
+                RateLimitFailureMode::Allow => RateLimitResponse {
+                    allowed: true,
+                    remaining: req.limit.saturating_sub(req.cost),
+                    reset_in: req.window,
+                    retry_after: None,
+                    failure_mode: Some(self.failure_mode),
+                    would_deny: false,
+                    wait_for: None,
+                    deny_reason: None,
+                },
+                RateLimitFailureMode::Deny => RateLimitResponse {
+                    allowed: false,
+                    remaining: 0,
+                    reset_in: FAILURE_RETRY_AFTER_SECS,
+                    retry_after: Some(FAILURE_RETRY_AFTER_SECS),
+                    failure_mode: Some(self.failure_mode),
+                    would_deny: false,
+                    wait_for: None,
+                    deny_reason: None,
+                },
+            })
+            .collect()
+    }
+
+    fn sliding_key(&self, key: &str) -> String {
+        format!("rate_limit:{}:sliding", self.redis.hash_tag(key))
+    }
+
+    /// Running total of units in a sliding window's sorted set, kept beside it
+    fn units_key(sliding_key: &str) -> String {
+        format!("{}:units", sliding_key)
+    }
+
+    fn leaky_key(&self, key: &str) -> String {
+        format!("rate_limit:{}:leaky", self.redis.hash_tag(key))
+    }
+
+    fn fixed_key(&self, key: &str, window_start: u64) -> String {
+        format!("rate_limit:{}:{}", self.redis.hash_tag(key), window_start)
+    }
+
+    /// Keys a single check touches in one script, for the configured strategy
+    fn check_keys(&self, key: &str, window_start: u64) -> Vec<String> {
+        match self.strategy {
+            RateLimitStrategy::FixedWindow => vec![self.fixed_key(key, window_start)],
+            RateLimitStrategy::SlidingWindow => {
+                let sliding_key = self.sliding_key(key);
+                let units_key = Self::units_key(&sliding_key);
+                vec![sliding_key, units_key]
+            }
+            RateLimitStrategy::LeakyBucket => vec![self.leaky_key(key)],
+        }
+    }
+
+    /// Verify at startup that the key layout works with the Redis topology.
+    ///
+    /// On a cluster every key touched by one check must share a slot, or the
+    /// scripts would fail with CROSSSLOT on every request.
+    pub fn validate_topology(&self) -> anyhow::Result<()> {
+        if !self.redis.is_cluster() {
+            return Ok(());
+        }
+        ensure_same_slot(self.strategy.as_str(), &self.check_keys("ratewatch:probe", 0))
+    }
+
+    /// Split batch items into groups that can each run as one script.
+    ///
+    /// Standalone and Sentinel run the whole batch together. On a cluster
+    /// items are grouped by slot; items for the same key always share a
+    /// group, so per-key ordering is unchanged.
+    fn script_groups(&self, keys: &[String]) -> Vec<Vec<usize>> {
+        if !self.redis.is_cluster() {
+            return vec![(0..keys.len()).collect()];
+        }
+
+        let mut groups: std::collections::BTreeMap<u16, Vec<usize>> = Default::default();
+        for (index, key) in keys.iter().enumerate() {
+            groups.entry(key_slot(key)).or_default().push(index);
+        }
+        groups.into_values().collect()
+    }
+
+    /// Check rate limit using the configured strategy with automatic TTL for GDPR compliance
+    pub async fn check(&self, req: RateLimitRequest) -> anyhow::Result<RateLimitResponse> {
+        self.check_within(req, Duration::ZERO).await
+    }
+
+    /// Like `check`, but a leaky-bucket request that would have to queue for
+    /// up to `max_wait` (capped by `with_max_wait`) is admitted with
+    /// `wait_for` set instead of denied. The caller must wait that long
+    /// before going ahead; its place in the queue is already taken. Other
+    /// strategies never queue, so this is the same as `check` for them.
+    pub async fn check_with_wait(&self, req: RateLimitRequest, max_wait: Duration) -> anyhow::Result<RateLimitResponse> {
+        self.check_within(req, max_wait.min(self.max_wait)).await
+    }
+
+    /// `check_with_wait`, sleeping out any `wait_for` before returning, so an
+    /// allowed response means the request may go ahead now
+    pub async fn check_and_wait(&self, req: RateLimitRequest, max_wait: Duration) -> anyhow::Result<RateLimitResponse> {
+        let mut response = self.check_with_wait(req, max_wait).await?;
+        if let Some(wait_for) = response.wait_for.take() {
+            tokio::time::sleep(Duration::from_millis(wait_for)).await;
+        }
+        Ok(response)
+    }
+
+    /// Run `check` for `key` once per `idempotency_key`, so a client retrying
+    /// after a timeout isn't counted twice.
+    ///
+    /// The first call claims the idempotency key, runs the check and keeps
+    /// its result for the idempotency TTL; later calls get that result back
+    /// without touching the limit. A retry that arrives while the first check
+    /// is still running waits briefly for it, then gets `InProgress`. The
+    /// entry is per rate limit key, so the same idempotency key sent for
+    /// another rate limit key is a separate check. Decisions made by the
+    /// failure mode aren't kept, since nothing was counted, and without Redis
+    /// the check simply runs.
+    pub async fn check_idempotent<F>(
+        &self,
+        key: &str,
+        idempotency_key: &str,
+        check: F,
+    ) -> anyhow::Result<IdempotentCheck>
+    where
+        F: std::future::Future<Output = anyhow::Result<RateLimitResponse>>,
+    {
+        let entry = self.idempotency_key(key, idempotency_key);
+        match self.claim_idempotency(&entry).await {
+            Ok(IdempotencyClaim::Claimed) => {}
+            Ok(IdempotencyClaim::Done(stored)) => return Ok(IdempotentCheck::Replayed(serde_json::from_str(&stored)?)),
+            Ok(IdempotencyClaim::InProgress) => return Ok(IdempotentCheck::InProgress),
+            Err(e) => {
+                tracing::warn!("Idempotency key unavailable, checking without it: {}", e);
+                return check.await.map(IdempotentCheck::Checked);
+            }
+        }
+
+        let result = check.await;
+        let stored = match &result {
+            Ok(response) if response.failure_mode.is_none() => self.store_idempotent(&entry, response).await,
+            _ => self.release_idempotency(&entry).await,
+        };
+        if let Err(e) = stored {
+            tracing::warn!("Failed to record idempotent check: {}", e);
+        }
+        result.map(IdempotentCheck::Checked)
+    }
+
+    /// The idempotency key is hashed, so it can't run into the rate limit key
+    fn idempotency_key(&self, key: &str, idempotency_key: &str) -> String {
+        use sha2::{Digest, Sha256};
+
+        let digest = hex::encode(Sha256::digest(idempotency_key.as_bytes()));
+        format!("idempotency:{}:{}", self.redis.hash_tag(key), digest)
+    }
+
+    /// Claim `entry` with an empty placeholder, or find what the check that
+    /// claimed it returned
+    async fn claim_idempotency(&self, entry: &str) -> anyhow::Result<IdempotencyClaim> {
+        let mut conn = self.redis.get_async_connection().await?;
+        let ttl_ms = self.idempotency_ttl.as_millis() as u64;
+        let deadline = tokio::time::Instant::now() + IDEMPOTENCY_WAIT;
+        loop {
+            let claimed: Option<String> = redis::cmd("SET")
+                .arg(entry)
+                .arg("")
+                .arg("NX")
+                .arg("PX")
+                .arg(ttl_ms)
+                .query_async(&mut conn)
+                .await?;
+            if claimed.is_some() {
+                return Ok(IdempotencyClaim::Claimed);
+            }
+
+            let stored: Option<String> = conn.get(entry).await?;
+            match stored {
+                Some(stored) if !stored.is_empty() => return Ok(IdempotencyClaim::Done(stored)),
+                _ if tokio::time::Instant::now() >= deadline => return Ok(IdempotencyClaim::InProgress),
+                Some(_) => tokio::time::sleep(IDEMPOTENCY_POLL).await,
+                // The check that held it failed and let it go
+                None => {}
+            }
+        }
+    }
+
+    async fn store_idempotent(&self, entry: &str, response: &RateLimitResponse) -> anyhow::Result<()> {
+        let mut conn = self.redis.get_async_connection().await?;
+        redis::cmd("SET")
+            .arg(entry)
+            .arg(serde_json::to_string(response)?)
+            .arg("XX")
+            .arg("PX")
+            .arg(self.idempotency_ttl.as_millis() as u64)
+            .query_async::<_, Option<String>>(&mut conn)
+            .await?;
+        Ok(())
+    }
+
+    async fn release_idempotency(&self, entry: &str) -> anyhow::Result<()> {
+        let mut conn = self.redis.get_async_connection().await?;
+        conn.del::<_, ()>(entry).await?;
+        Ok(())
+    }
+
+    #[tracing::instrument(
+        name = "rate_limit.check",
+        skip(self, req),
+        fields(
+            key = %req.key,
+            limit = req.limit,
+            window = req.window,
+            strategy = self.strategy.as_str(),
+            allowed = tracing::field::Empty
+        )
+    )]
+    async fn check_within(&self, req: RateLimitRequest, max_wait: Duration) -> anyhow::Result<RateLimitResponse> {
+        // Validate input parameters
+        validate_request(&req)?;
+
+        let result = match self.strategy {
+            RateLimitStrategy::FixedWindow => self.check_fixed_window(&req).await,
+            RateLimitStrategy::SlidingWindow => self.check_sliding_window(&req).await,
+            RateLimitStrategy::LeakyBucket => self
+                .check_leaky_bucket_batch(std::slice::from_ref(&req), max_wait)
+                .await
+                .and_then(|mut responses| {
+                    responses.pop().ok_or_else(|| anyhow::anyhow!("Leaky bucket script returned no result"))
+                }),
+        };
+        let mut response = match result {
+            Ok(response) => response,
+            Err(e) => self.failure_responses(std::slice::from_ref(&req), &e).remove(0),
+        };
+        self.apply_mode(&mut response);
+        Self::set_deny_reason(&mut response);
+
+        tracing::Span::current().record("allowed", response.allowed);
+        self.record_decision(&response);
+
+        Ok(response)
+    }
+
+    /// Check layered limits on one key, e.g. `&[(10, 1), (1000, 3600)]`.
+    ///
+    /// All tiers are evaluated by a single Lua script: the request is denied
+    /// if any tier is exceeded, and nothing is consumed from any tier unless
+    /// all of them allow it.
+    #[tracing::instrument(
+        name = "rate_limit.check_multi",
+        skip(self, limits),
+        fields(key = %key, tiers = limits.len(), strategy = self.strategy.as_str())
+    )]
+    pub async fn check_multi(
+        &self,
+        key: &str,
+        limits: &[(u64, u64)],
+        cost: u64,
+    ) -> anyhow::Result<MultiRateLimitResponse> {
+        let reqs: Vec<RateLimitRequest> = limits
+            .iter()
+            .map(|&(limit, window)| RateLimitRequest {
+                key: key.to_string(),
+                limit,
+                window,
+                cost,
+            })
+            .collect();
+        validate_tiers(&reqs)?;
+
+        let result = match self.strategy {
+            RateLimitStrategy::FixedWindow => self.check_fixed_window_multi(&reqs).await,
+            RateLimitStrategy::SlidingWindow => self.check_sliding_window_multi(&reqs).await,
+            RateLimitStrategy::LeakyBucket => self.check_leaky_bucket_multi(&reqs).await,
+        };
+        let mut response = MultiRateLimitResponse::from_tiers(
+            result.unwrap_or_else(|e| self.failure_responses(&reqs, &e)),
+        );
+        let binding_tier = response.binding_tier();
+        if self.mode == RateLimitMode::Monitor && response.tiers.iter().all(|tier| tier.failure_mode.is_none()) {
+            response.tiers.iter_mut().for_each(|tier| self.apply_mode(tier));
+            response.allowed = true;
+            response.failed_tier = None;
+            response.retry_after = None;
+        }
+        response.tiers.iter_mut().for_each(Self::set_deny_reason);
+
+        self.record_decision(&response.tiers[binding_tier]);
+        Ok(response)
+    }
+
+    /// Check many keys in one Redis round trip.
+    ///
+    /// The whole batch runs as a single Lua script, so every item is evaluated
+    /// atomically and in order; results are returned in request order. On a
+    /// cluster the batch runs as one script per hash slot.
+    #[tracing::instrument(
+        name = "rate_limit.check_batch",
+        skip(self, reqs),
+        fields(batch_size = reqs.len(), strategy = self.strategy.as_str())
+    )]
+    pub async fn check_batch(&self, reqs: &[RateLimitRequest]) -> anyhow::Result<Vec<RateLimitResponse>> {
+        reqs.iter().try_for_each(validate_request)?;
+        if reqs.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let result = match self.strategy {
+            RateLimitStrategy::FixedWindow => self.check_fixed_window_batch(reqs).await,
+            RateLimitStrategy::SlidingWindow => self.check_sliding_window_batch(reqs).await,
+            RateLimitStrategy::LeakyBucket => self.check_leaky_bucket_batch(reqs, Duration::ZERO).await,
+        };
+        let mut responses = result.unwrap_or_else(|e| self.failure_responses(reqs, &e));
+
+        for response in &mut responses {
+            self.apply_mode(response);
+            Self::set_deny_reason(response);
+            self.record_decision(response);
+        }
+
+        Ok(responses)
+    }
+
+    /// Report whether a request would be allowed without consuming any quota
+    #[tracing::instrument(
+        name = "rate_limit.peek",
+        skip(self, req),
+        fields(key = %req.key, strategy = self.strategy.as_str())
+    )]
+    pub async fn peek(&self, req: &RateLimitRequest) -> anyhow::Result<RateLimitResponse> {
+        validate_request(req)?;
+
+        let mut conn = self
+            .redis
+            .get_async_connection()
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to connect to Redis: {}", e))?;
+
+        let redis_started = std::time::Instant::now();
+        let (current, reset_in) = match self.strategy {
+            RateLimitStrategy::FixedWindow => {
+                let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+                let window_start = now - (now % req.window);
+                let current: Option<u64> = conn
+                    .get(self.fixed_key(&req.key, window_start))
+                    .instrument(tracing::info_span!("redis.command", db.operation = "GET"))
+                    .await?;
+                crate::metrics::observe_redis_command("GET", redis_started);
+                (current.unwrap_or(0), req.window - (now % req.window))
+            }
+            RateLimitStrategy::SlidingWindow => {
+                let sliding_key = self.sliding_key(&req.key);
+                let (current, reset_in_ms): (u64, u64) = SLIDING_WINDOW_PEEK_SCRIPT
+                    .prepare_invoke()
+                    .key(&sliding_key)
+                    .key(Self::units_key(&sliding_key))
+                    .arg(req.window.saturating_mul(1000))
+                    .invoke_async(&mut conn)
+                    .instrument(tracing::info_span!("redis.command", db.operation = "EVALSHA"))
+                    .await
+                    .map_err(|e| anyhow::anyhow!("Sliding window peek failed: {}", e))?;
+                crate::metrics::observe_redis_command("EVALSHA", redis_started);
+                (current, reset_in_ms.div_ceil(1000))
+            }
+            RateLimitStrategy::LeakyBucket => {
+                let drain: Option<f64> = conn
+                    .get(self.leaky_key(&req.key))
+                    .instrument(tracing::info_span!("redis.command", db.operation = "GET"))
+                    .await?;
+                crate::metrics::observe_redis_command("GET", redis_started);
+
+                // A plain check is allowed only when nothing is queued
+                let now_ms = SystemTime::now().duration_since(UNIX_EPOCH)?.as_millis() as f64;
+                let queued_ms = (drain.unwrap_or(0.0) - now_ms).max(0.0);
+                let interval_ms = req.window as f64 * 1000.0 / req.limit as f64;
+                let reset_in = (queued_ms / 1000.0).ceil() as u64;
+                let allowed = queued_ms == 0.0 && req.cost <= req.limit;
+                return Ok(RateLimitResponse {
+                    allowed,
+                    remaining: req.limit.saturating_sub((queued_ms / interval_ms).ceil() as u64),
+                    reset_in,
+                    retry_after: if allowed { None } else { Some(reset_in.max(1)) },
+                    failure_mode: None,
+                    would_deny: false,
+                    wait_for: None,
+                    deny_reason: (!allowed).then_some(DenyReason::KeyLimit),
+                });
+            }
+        };
+
+        let allowed = current + req.cost <= req.limit;
+        Ok(RateLimitResponse {
+            allowed,
+            remaining: req.limit.saturating_sub(current),
+            reset_in,
+            retry_after: if allowed { None } else { Some(reset_in.max(1)) },
+            failure_mode: None,
+            would_deny: false,
+            wait_for: None,
+            deny_reason: (!allowed).then_some(DenyReason::KeyLimit),
+        })
+    }
+
+    fn record_decision(&self, response: &RateLimitResponse) {
+        let outcome = match response.failure_mode {
+            Some(failure_mode) => failure_mode.as_str(),
+            None if response.would_deny => "would_deny",
+            None if response.allowed => "allowed",
+            None => "denied",
+        };
+        crate::metrics::RATE_LIMIT_DECISIONS
+            .with_label_values(&[self.strategy.as_str(), outcome])
+            .inc();
+    }
+
+    async fn check_sliding_window(&self, req: &RateLimitRequest) -> anyhow::Result<RateLimitResponse> {
+        self.check_sliding_window_batch(std::slice::from_ref(req))
+            .await?
+            .pop()
+            .ok_or_else(|| anyhow::anyhow!("Sliding window script returned no result"))
+    }
+
+    async fn check_sliding_window_batch(
+        &self,
+        reqs: &[RateLimitRequest],
+    ) -> anyhow::Result<Vec<RateLimitResponse>> {
+        let mut conn = self
+            .redis
+            .get_async_connection()
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to connect to Redis: {}", e))?;
+
+        let keys: Vec<String> = reqs.iter().map(|req| self.sliding_key(&req.key)).collect();
+        let script = &SLIDING_WINDOW_SCRIPT;
+        let mut raw = vec![0u64; reqs.len() * 3];
+
+        for group in self.script_groups(&keys) {
+            let mut invocation = script.prepare_invoke();
+            for &i in &group {
+                let req = &reqs[i];
+                invocation
+                    .key(&keys[i])
+                    .key(Self::units_key(&keys[i]))
+                    .arg(req.limit)
+                    .arg(req.window.saturating_mul(1000))
+                    .arg(req.cost)
+                    .arg(uuid::Uuid::new_v4().to_string());
+            }
+
+            let redis_started = std::time::Instant::now();
+            let results: Vec<u64> = invocation
+                .invoke_async(&mut conn)
+                .instrument(tracing::info_span!("redis.command", db.operation = "EVALSHA"))
+                .await
+                .map_err(|e| anyhow::anyhow!("Sliding window script failed: {}", e))?;
+            crate::metrics::observe_redis_command("EVALSHA", redis_started);
+
+            if results.len() != group.len() * 3 {
+                return Err(anyhow::anyhow!("Unexpected sliding window script result length"));
+            }
+            for (&i, item) in group.iter().zip(results.chunks(3)) {
+                raw[i * 3..i * 3 + 3].copy_from_slice(item);
+            }
+        }
+
+        Ok(reqs
+            .iter()
+            .zip(raw.chunks(3))
+            .map(|(req, item)| {
+                // Round up so clients never retry before the window has actually moved
+                let reset_in = item[2].div_ceil(1000);
+
+                if item[0] == 1 {
+                    RateLimitResponse {
+                        allowed: true,
+                        remaining: item[1],
+                        reset_in,
+                        retry_after: None,
+                        failure_mode: None,
+                        would_deny: false,
+                        wait_for: None,
+                        deny_reason: None,
+                    }
+                } else {
+                    tracing::debug!(
+                        "Rate limit exceeded for key: {} (sliding window, limit: {})",
+                        req.key,
+                        req.limit
+                    );
+
+                    RateLimitResponse {
+                        allowed: false,
+                        remaining: 0,
+                        reset_in,
+                        retry_after: Some(reset_in.max(1)),
+                        failure_mode: None,
+                        would_deny: false,
+                        wait_for: None,
+                        deny_reason: None,
+                    }
+                }
+            })
+            .collect())
+    }
+
+    async fn check_fixed_window_batch(
+        &self,
+        reqs: &[RateLimitRequest],
+    ) -> anyhow::Result<Vec<RateLimitResponse>> {
+        let mut conn = self
+            .redis
+            .get_async_connection()
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to connect to Redis: {}", e))?;
+
+        let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+
+        let keys: Vec<String> = reqs
+            .iter()
+            .map(|req| self.fixed_key(&req.key, now - (now % req.window)))
+            .collect();
+        let script = &FIXED_WINDOW_BATCH_SCRIPT;
+        let mut raw = vec![0u64; reqs.len() * 2];
+
+        for group in self.script_groups(&keys) {
+            let mut invocation = script.prepare_invoke();
+            for &i in &group {
+                let req = &reqs[i];
+                invocation.key(&keys[i]).arg(req.limit).arg(req.window).arg(req.cost);
+            }
+
+            let redis_started = std::time::Instant::now();
+            let results: Vec<u64> = invocation
+                .invoke_async(&mut conn)
+                .instrument(tracing::info_span!("redis.command", db.operation = "EVALSHA"))
+                .await
+                .map_err(|e| anyhow::anyhow!("Fixed window batch script failed: {}", e))?;
+            crate::metrics::observe_redis_command("EVALSHA", redis_started);
+
+            if results.len() != group.len() * 2 {
+                return Err(anyhow::anyhow!("Unexpected fixed window script result length"));
+            }
+            for (&i, item) in group.iter().zip(results.chunks(2)) {
+                raw[i * 2..i * 2 + 2].copy_from_slice(item);
+            }
+        }
+
+        Ok(reqs
+            .iter()
+            .zip(raw.chunks(2))
+            .map(|(req, item)| {
+                let reset_in = req.window - (now % req.window);
+                let allowed = item[0] == 1;
+
+                RateLimitResponse {
+                    allowed,
+                    remaining: item[1],
+                    reset_in,
+                    retry_after: if allowed { None } else { Some(reset_in) },
+                    failure_mode: None,
+                    would_deny: false,
+                    wait_for: None,
+                    deny_reason: None,
+                }
+            })
+            .collect())
+    }
+
+    /// Tier keys carry the window so tiers of one key never share a counter
+    fn tier_key(&self, req: &RateLimitRequest, suffix: &str) -> String {
+        format!("rate_limit:{}:{}s:{}", self.redis.hash_tag(&req.key), req.window, suffix)
+    }
+
+    async fn check_sliding_window_multi(
+        &self,
+        reqs: &[RateLimitRequest],
+    ) -> anyhow::Result<Vec<RateLimitResponse>> {
+        let mut conn = self
+            .redis
+            .get_async_connection()
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to connect to Redis: {}", e))?;
+
+        let script = &SLIDING_WINDOW_MULTI_SCRIPT;
+        let mut invocation = script.prepare_invoke();
+        invocation
+            .arg(reqs[0].cost)
+            .arg(uuid::Uuid::new_v4().to_string());
+        for req in reqs {
+            let sliding_key = self.tier_key(req, "sliding");
+            invocation
+                .key(&sliding_key)
+                .key(Self::units_key(&sliding_key))
+                .arg(req.limit)
+                .arg(req.window.saturating_mul(1000));
+        }
+
+        let redis_started = std::time::Instant::now();
+        let raw: Vec<u64> = invocation
+            .invoke_async(&mut conn)
+            .instrument(tracing::info_span!("redis.command", db.operation = "EVALSHA"))
+            .await
+            .map_err(|e| anyhow::anyhow!("Sliding window multi-tier script failed: {}", e))?;
+        crate::metrics::observe_redis_command("EVALSHA", redis_started);
+
+        if raw.len() != reqs.len() * 3 {
+            return Err(anyhow::anyhow!("Unexpected sliding window script result length"));
+        }
 
-pub struct RateLimiter {
-    redis: Client,
-}
+        Ok(raw
+            .chunks(3)
+            .map(|item| {
+                let reset_in = item[2].div_ceil(1000);
+                let allowed = item[0] == 1;
 
-impl RateLimiter {
-    pub fn new(redis_url: &str) -> anyhow::Result<Self> {
-        let redis = Client::open(redis_url)?;
-        Ok(Self { redis })
+                RateLimitResponse {
+                    allowed,
+                    remaining: item[1],
+                    reset_in,
+                    retry_after: if allowed { None } else { Some(reset_in.max(1)) },
+                    failure_mode: None,
+                    would_deny: false,
+                    wait_for: None,
+                    deny_reason: None,
+                }
+            })
+            .collect())
     }
 
-    /// Check rate limit using Redis sliding window algorithm with automatic TTL for GDPR compliance
-    pub async fn check(&self, req: RateLimitRequest) -> anyhow::Result<RateLimitResponse> {
-        // Validate input parameters
-        if req.window == 0 {
-            return Err(anyhow::anyhow!("Window size cannot be zero"));
+    async fn check_fixed_window_multi(
+        &self,
+        reqs: &[RateLimitRequest],
+    ) -> anyhow::Result<Vec<RateLimitResponse>> {
+        let mut conn = self
+            .redis
+            .get_async_connection()
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to connect to Redis: {}", e))?;
+
+        let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+
+        let script = &FIXED_WINDOW_MULTI_SCRIPT;
+        let mut invocation = script.prepare_invoke();
+        invocation.arg(reqs[0].cost);
+        for req in reqs {
+            let window_start = now - (now % req.window);
+            invocation
+                .key(self.tier_key(req, &window_start.to_string()))
+                .arg(req.limit)
+                .arg(req.window);
+        }
+
+        let redis_started = std::time::Instant::now();
+        let raw: Vec<u64> = invocation
+            .invoke_async(&mut conn)
+            .instrument(tracing::info_span!("redis.command", db.operation = "EVALSHA"))
+            .await
+            .map_err(|e| anyhow::anyhow!("Fixed window multi-tier script failed: {}", e))?;
+        crate::metrics::observe_redis_command("EVALSHA", redis_started);
+
+        if raw.len() != reqs.len() * 2 {
+            return Err(anyhow::anyhow!("Unexpected fixed window script result length"));
+        }
+
+        Ok(reqs
+            .iter()
+            .zip(raw.chunks(2))
+            .map(|(req, item)| {
+                let reset_in = req.window - (now % req.window);
+                let allowed = item[0] == 1;
+
+                RateLimitResponse {
+                    allowed,
+                    remaining: item[1],
+                    reset_in,
+                    retry_after: if allowed { None } else { Some(reset_in) },
+                    failure_mode: None,
+                    would_deny: false,
+                    wait_for: None,
+                    deny_reason: None,
+                }
+            })
+            .collect())
+    }
+
+    async fn check_leaky_bucket_batch(
+        &self,
+        reqs: &[RateLimitRequest],
+        max_wait: Duration,
+    ) -> anyhow::Result<Vec<RateLimitResponse>> {
+        let mut conn = self
+            .redis
+            .get_async_connection()
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to connect to Redis: {}", e))?;
+
+        let keys: Vec<String> = reqs.iter().map(|req| self.leaky_key(&req.key)).collect();
+        let script = &LEAKY_BUCKET_SCRIPT;
+        let mut raw = vec![0u64; reqs.len() * 4];
+
+        for group in self.script_groups(&keys) {
+            let mut invocation = script.prepare_invoke();
+            invocation.arg(max_wait.as_millis() as u64);
+            for &i in &group {
+                let req = &reqs[i];
+                invocation
+                    .key(&keys[i])
+                    .arg(req.limit)
+                    .arg(req.window.saturating_mul(1000))
+                    .arg(req.cost);
+            }
+
+            let redis_started = std::time::Instant::now();
+            let results: Vec<u64> = invocation
+                .invoke_async(&mut conn)
+                .instrument(tracing::info_span!("redis.command", db.operation = "EVALSHA"))
+                .await
+                .map_err(|e| anyhow::anyhow!("Leaky bucket script failed: {}", e))?;
+            crate::metrics::observe_redis_command("EVALSHA", redis_started);
+
+            if results.len() != group.len() * 4 {
+                return Err(anyhow::anyhow!("Unexpected leaky bucket script result length"));
+            }
+            for (&i, item) in group.iter().zip(results.chunks(4)) {
+                raw[i * 4..i * 4 + 4].copy_from_slice(item);
+            }
         }
-        if req.limit == 0 {
-            return Err(anyhow::anyhow!("Limit cannot be zero"));
+
+        Ok(reqs
+            .iter()
+            .zip(raw.chunks(4))
+            .map(|(req, item)| {
+                let reset_in = item[2].div_ceil(1000);
+
+                if item[0] == 1 {
+                    RateLimitResponse {
+                        allowed: true,
+                        remaining: item[1],
+                        reset_in,
+                        retry_after: None,
+                        failure_mode: None,
+                        would_deny: false,
+                        wait_for: (item[3] > 0).then_some(item[3]),
+                        deny_reason: None,
+                    }
+                } else {
+                    tracing::debug!(
+                        "Rate limit exceeded for key: {} (leaky bucket, limit: {}, wait: {}ms)",
+                        req.key,
+                        req.limit,
+                        item[2]
+                    );
+
+                    RateLimitResponse {
+                        allowed: false,
+                        remaining: item[1],
+                        reset_in,
+                        retry_after: Some(item[3].div_ceil(1000).max(1)),
+                        failure_mode: None,
+                        would_deny: false,
+                        wait_for: None,
+                        deny_reason: None,
+                    }
+                }
+            })
+            .collect())
+    }
+
+    async fn check_leaky_bucket_multi(
+        &self,
+        reqs: &[RateLimitRequest],
+    ) -> anyhow::Result<Vec<RateLimitResponse>> {
+        let mut conn = self
+            .redis
+            .get_async_connection()
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to connect to Redis: {}", e))?;
+
+        let script = &LEAKY_BUCKET_MULTI_SCRIPT;
+        let mut invocation = script.prepare_invoke();
+        invocation.arg(reqs[0].cost);
+        for req in reqs {
+            invocation
+                .key(self.tier_key(req, "leaky"))
+                .arg(req.limit)
+                .arg(req.window.saturating_mul(1000));
         }
-        if req.key.is_empty() {
-            return Err(anyhow::anyhow!("Key cannot be empty"));
+
+        let redis_started = std::time::Instant::now();
+        let raw: Vec<u64> = invocation
+            .invoke_async(&mut conn)
+            .instrument(tracing::info_span!("redis.command", db.operation = "EVALSHA"))
+            .await
+            .map_err(|e| anyhow::anyhow!("Leaky bucket multi-tier script failed: {}", e))?;
+        crate::metrics::observe_redis_command("EVALSHA", redis_started);
+
+        if raw.len() != reqs.len() * 3 {
+            return Err(anyhow::anyhow!("Unexpected leaky bucket script result length"));
         }
 
+        Ok(raw
+            .chunks(3)
+            .map(|item| {
+                let reset_in = item[2].div_ceil(1000);
+                let allowed = item[0] == 1;
+
+                RateLimitResponse {
+                    allowed,
+                    remaining: item[1],
+                    reset_in,
+                    retry_after: if allowed { None } else { Some(reset_in.max(1)) },
+                    failure_mode: None,
+                    would_deny: false,
+                    wait_for: None,
+                    deny_reason: None,
+                }
+            })
+            .collect())
+    }
+
+    async fn check_fixed_window(&self, req: &RateLimitRequest) -> anyhow::Result<RateLimitResponse> {
         let mut conn = self
             .redis
             .get_async_connection()
@@ -49,16 +1757,19 @@ impl RateLimiter {
 
         let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
 
-        // Use sliding window approach - each window is aligned to the window size
+        // Fixed window approach - each window is aligned to the window size
         let window_start = now - (now % req.window);
-        let redis_key = format!("rate_limit:{}:{}", req.key, window_start);
+        let redis_key = self.fixed_key(&req.key, window_start);
 
         // Use Redis pipeline for atomic operations
+        let redis_started = std::time::Instant::now();
         let result: RedisResult<(u64,)> = redis::pipe()
             .atomic()
             .get(&redis_key)
             .query_async(&mut conn)
+            .instrument(tracing::info_span!("redis.command", db.operation = "GET"))
             .await;
+        crate::metrics::observe_redis_command("GET", redis_started);
 
         let current = match result {
             Ok((count,)) => count,
@@ -67,18 +1778,25 @@ impl RateLimiter {
 
         if current + req.cost <= req.limit {
             // Allow request - increment counter and set TTL
+            let redis_started = std::time::Instant::now();
             let _: RedisResult<()> = redis::pipe()
                 .atomic()
                 .incr(&redis_key, req.cost)
                 .expire(&redis_key, req.window as i64)
                 .query_async(&mut conn)
+                .instrument(tracing::info_span!("redis.command", db.operation = "INCRBY EXPIRE"))
                 .await;
+            crate::metrics::observe_redis_command("INCRBY", redis_started);
 
             Ok(RateLimitResponse {
                 allowed: true,
                 remaining: req.limit.saturating_sub(current + req.cost),
                 reset_in: req.window - (now % req.window),
                 retry_after: None,
+                failure_mode: None,
+                would_deny: false,
+                wait_for: None,
+                deny_reason: None,
             })
         } else {
             // Deny request - don't increment counter
@@ -94,6 +1812,10 @@ impl RateLimiter {
                 remaining: 0,
                 reset_in: req.window - (now % req.window),
                 retry_after: Some(req.window - (now % req.window)),
+                failure_mode: None,
+                would_deny: false,
+                wait_for: None,
+                deny_reason: None,
             })
         }
     }
@@ -123,7 +1845,7 @@ impl RateLimiter {
     #[allow(dead_code)]
     pub async fn cleanup_expired_keys(&self, pattern: &str) -> anyhow::Result<u64> {
         let mut conn = self.redis.get_async_connection().await?;
-        let keys: Vec<String> = conn.keys(pattern).await?;
+        let keys = crate::redis_backend::scan_keys(&mut conn, pattern).await?;
 
         if keys.is_empty() {
             return Ok(0);
@@ -133,6 +1855,95 @@ impl RateLimiter {
         tracing::info!("Cleaned up {} expired rate limit keys", deleted);
         Ok(deleted)
     }
+
+    /// Names of the Redis entries holding state for exactly `key`, under any
+    /// strategy or tier
+    async fn state_keys(
+        &self,
+        conn: &mut crate::redis_backend::RedisConnection,
+        key: &str,
+    ) -> anyhow::Result<Vec<(String, (RateLimitStrategy, Option<u64>, Option<u64>))>> {
+        let prefix = format!("rate_limit:{}:", self.redis.hash_tag(key));
+        let names = crate::redis_backend::scan_keys(conn, &format!("{}*", escape_glob(&prefix))).await?;
+        Ok(names
+            .into_iter()
+            .filter_map(|name| {
+                let parsed = parse_state_suffix(name.strip_prefix(&prefix)?)?;
+                Some((name, parsed))
+            })
+            .collect())
+    }
+
+    /// Current rate limit state of `key` on every strategy and tier that has
+    /// counted it. Limits aren't stored, so they're not included.
+    pub async fn key_state(&self, key: &str) -> anyhow::Result<Vec<KeyStateEntry>> {
+        let mut conn = self.redis.get_async_connection().await?;
+        let mut entries = Vec::new();
+
+        for (redis_key, (strategy, window, window_start)) in self.state_keys(&mut conn, key).await? {
+            let ttl_ms: i64 = conn.pttl(&redis_key).await?;
+            if ttl_ms == -2 {
+                // Expired since the scan
+                continue;
+            }
+            let (count, queued_ms) = match strategy {
+                RateLimitStrategy::FixedWindow => {
+                    let count: Option<u64> = conn.get(&redis_key).await?;
+                    (Some(count.unwrap_or(0)), None)
+                }
+                RateLimitStrategy::SlidingWindow => {
+                    let units: Option<u64> = conn.get(Self::units_key(&redis_key)).await?;
+                    let count = match units {
+                        Some(units) => units,
+                        None => conn.zcard(&redis_key).await?,
+                    };
+                    (Some(count), None)
+                }
+                RateLimitStrategy::LeakyBucket => {
+                    let drain: Option<f64> = conn.get(&redis_key).await?;
+                    let now_ms = SystemTime::now().duration_since(UNIX_EPOCH)?.as_millis() as f64;
+                    (None, Some((drain.unwrap_or(0.0) - now_ms).max(0.0).ceil() as u64))
+                }
+            };
+            entries.push(KeyStateEntry {
+                redis_key,
+                strategy,
+                window,
+                window_start,
+                count,
+                queued_ms,
+                ttl_ms: u64::try_from(ttl_ms).ok(),
+            });
+        }
+        Ok(entries)
+    }
+
+    /// Clear every counter for exactly `key`, so its next check starts with
+    /// the full limit on all instances. The entries and the sliding windows'
+    /// units totals go in one pipeline, which shares a slot on a cluster.
+    /// Returns how many counters were removed.
+    pub async fn reset_key(&self, key: &str) -> anyhow::Result<u64> {
+        let mut conn = self.redis.get_async_connection().await?;
+        let state = self.state_keys(&mut conn, key).await?;
+        if state.is_empty() {
+            return Ok(0);
+        }
+
+        let units: Vec<String> = state
+            .iter()
+            .filter(|(_, (strategy, _, _))| *strategy == RateLimitStrategy::SlidingWindow)
+            .map(|(name, _)| Self::units_key(name))
+            .collect();
+        let names: Vec<String> = state.into_iter().map(|(name, _)| name).collect();
+
+        let mut pipe = redis::pipe();
+        pipe.del(&names);
+        if !units.is_empty() {
+            pipe.del(&units).ignore();
+        }
+        let (cleared,): (u64,) = pipe.query_async(&mut conn).await?;
+        Ok(cleared)
+    }
 }
 
 #[cfg(test)]
@@ -171,6 +1982,11 @@ mod tests {
     #[tokio::test]
     async fn test_rate_limit_denies_over_limit() {
         if let Ok(limiter) = RateLimiter::new("redis://127.0.0.1:6379") {
+            // Without Redis the check fails open instead of erroring
+            if limiter.health_check().await.is_err() {
+                println!("Skipping test - Redis not available");
+                return;
+            }
             let req = create_test_request("test_user_2", 1, 60);
 
             // First request should be allowed
@@ -182,6 +1998,7 @@ mod tests {
                     assert!(!response2.allowed);
                     assert_eq!(response2.remaining, 0);
                     assert!(response2.retry_after.is_some());
+                    assert_eq!(response2.deny_reason, Some(DenyReason::KeyLimit));
                 }
             }
         }
@@ -202,6 +2019,502 @@ mod tests {
         }
     }
 
+    #[tokio::test]
+    async fn test_unreachable_redis_fails_open_by_default() {
+        // Nothing listens here
+        let limiter = RateLimiter::new("redis://127.0.0.1:1").unwrap();
+
+        let response = limiter.check(create_test_request("test_fail_open", 10, 60)).await.unwrap();
+        assert!(response.allowed);
+        assert_eq!(response.failure_mode, Some(RateLimitFailureMode::Allow));
+        assert_eq!(response.deny_reason, None);
+
+        let responses = limiter
+            .check_batch(&[create_test_request("a", 10, 60), create_test_request("b", 10, 60)])
+            .await
+            .unwrap();
+        assert!(responses.iter().all(|r| r.allowed && r.failure_mode.is_some()));
+
+        // Invalid requests are still rejected rather than failing open
+        assert!(limiter.check(create_test_request("", 10, 60)).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_unreachable_redis_fails_closed_when_configured() {
+        for strategy in [RateLimitStrategy::FixedWindow, RateLimitStrategy::SlidingWindow] {
+            let limiter = RateLimiter::new("redis://127.0.0.1:1")
+                .unwrap()
+                .with_strategy(strategy)
+                .with_failure_mode(RateLimitFailureMode::Deny);
+
+            let response = limiter.check(create_test_request("test_fail_closed", 10, 60)).await.unwrap();
+            assert!(!response.allowed);
+            assert_eq!(response.remaining, 0);
+            assert_eq!(response.retry_after, Some(FAILURE_RETRY_AFTER_SECS));
+            assert_eq!(response.failure_mode, Some(RateLimitFailureMode::Deny));
+            assert_eq!(response.deny_reason, Some(DenyReason::BackendUnavailable));
+        }
+    }
+
+    #[test]
+    fn test_validate_request_bounds() {
+        let request = |limit, cost| RateLimitRequest {
+            cost,
+            ..create_test_request("k", limit, 60)
+        };
+
+        assert!(validate_request(&request(MAX_LIMIT, MAX_COST)).is_ok());
+        assert!(validate_request(&request(MAX_LIMIT + 1, 1)).is_err());
+        assert!(validate_request(&request(10, MAX_COST + 1)).is_err());
+    }
+
+    #[test]
+    fn test_validate_tiers() {
+        let tiers = |limits: &[(u64, u64)]| -> Vec<RateLimitRequest> {
+            limits.iter().map(|&(limit, window)| create_test_request("k", limit, window)).collect()
+        };
+
+        assert!(validate_tiers(&tiers(&[(10, 1), (1000, 3600)])).is_ok());
+        assert!(validate_tiers(&[]).is_err());
+        assert!(validate_tiers(&tiers(&[(10, 60), (20, 60)])).is_err());
+        assert!(validate_tiers(&tiers(&[(10, 1), (0, 60)])).is_err());
+        let too_many: Vec<(u64, u64)> = (1..=MAX_LIMIT_TIERS as u64 + 1).map(|w| (10, w)).collect();
+        assert!(validate_tiers(&tiers(&too_many)).is_err());
+    }
+
+    #[test]
+    fn test_multi_response_reports_most_restrictive_tier() {
+        let tier = |allowed, remaining, retry_after: Option<u64>| RateLimitResponse {
+            allowed,
+            remaining,
+            reset_in: retry_after.unwrap_or(60),
+            retry_after,
+            failure_mode: None,
+            would_deny: false,
+            wait_for: None,
+            deny_reason: None,
+        };
+
+        let response = MultiRateLimitResponse::from_tiers(vec![
+            tier(false, 0, Some(1)),
+            tier(true, 40, None),
+            tier(false, 0, Some(3000)),
+        ]);
+        assert!(!response.allowed);
+        assert_eq!(response.failed_tier, Some(2));
+        assert_eq!(response.retry_after, Some(3000));
+        assert_eq!(response.binding_tier(), 2);
+
+        let response = MultiRateLimitResponse::from_tiers(vec![tier(true, 9, None), tier(true, 3, None)]);
+        assert!(response.allowed);
+        assert_eq!(response.failed_tier, None);
+        assert_eq!(response.binding_tier(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_check_multi_denies_when_any_tier_exceeded() {
+        let probe = match RateLimiter::new("redis://127.0.0.1:6379") {
+            Ok(limiter) => limiter,
+            Err(_) => return,
+        };
+        if probe.health_check().await.is_err() {
+            println!("Skipping multi-tier test - Redis not available");
+            return;
+        }
+
+        for strategy in [RateLimitStrategy::FixedWindow, RateLimitStrategy::SlidingWindow] {
+            let limiter = RateLimiter::new("redis://127.0.0.1:6379")
+                .unwrap()
+                .with_strategy(strategy);
+            let key = format!("test_multi_{}", uuid::Uuid::new_v4());
+            let limits = [(3, 60), (5, 3600)];
+
+            for _ in 0..3 {
+                assert!(limiter.check_multi(&key, &limits, 1).await.unwrap().allowed);
+            }
+
+            let denied = limiter.check_multi(&key, &limits, 1).await.unwrap();
+            assert!(!denied.allowed);
+            assert_eq!(denied.failed_tier, Some(0));
+            assert!(denied.retry_after.is_some());
+            // The hourly tier wasn't charged for the denied request
+            assert_eq!(denied.tiers[1].remaining, 2);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_monitor_mode_allows_over_limit_as_would_deny() {
+        let probe = match RateLimiter::new("redis://127.0.0.1:6379") {
+            Ok(limiter) => limiter,
+            Err(_) => return,
+        };
+        if probe.health_check().await.is_err() {
+            println!("Skipping monitor mode test - Redis not available");
+            return;
+        }
+
+        for strategy in [RateLimitStrategy::FixedWindow, RateLimitStrategy::SlidingWindow] {
+            let would_deny = || {
+                crate::metrics::RATE_LIMIT_DECISIONS
+                    .with_label_values(&[strategy.as_str(), "would_deny"])
+                    .get()
+            };
+            let key = format!("test_monitor_{}", uuid::Uuid::new_v4());
+            let monitor = probe.for_strategy(strategy).with_mode(RateLimitMode::Monitor);
+            let before = would_deny();
+
+            for _ in 0..2 {
+                let response = monitor.check(create_test_request(&key, 2, 60)).await.unwrap();
+                assert!(response.allowed);
+                assert!(!response.would_deny);
+            }
+            for _ in 0..2 {
+                let response = monitor.check(create_test_request(&key, 2, 60)).await.unwrap();
+                assert!(response.allowed);
+                assert!(response.would_deny);
+                assert!(response.retry_after.is_none());
+            }
+            // Counted by this test; other tests only add to other keys' counts
+            assert!(would_deny() >= before + 2);
+
+            // The same traffic in enforce mode is denied
+            let enforce = probe.for_strategy(strategy);
+            let response = enforce.check(create_test_request(&key, 2, 60)).await.unwrap();
+            assert!(!response.allowed);
+            assert!(!response.would_deny);
+            assert!(response.retry_after.is_some());
+
+            let multi_key = format!("{key}:multi");
+            let limits = [(1, 60), (100, 3600)];
+            assert!(!monitor.check_multi(&multi_key, &limits, 1).await.unwrap().would_deny());
+            let multi = monitor.check_multi(&multi_key, &limits, 1).await.unwrap();
+            assert!(multi.allowed);
+            assert!(multi.would_deny());
+            assert_eq!(multi.failed_tier, None);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_monitor_mode_keeps_failure_mode_decisions() {
+        let limiter = RateLimiter::new("redis://127.0.0.1:1")
+            .unwrap()
+            .with_failure_mode(RateLimitFailureMode::Deny)
+            .with_mode(RateLimitMode::Monitor);
+
+        let response = limiter.check(create_test_request("test_monitor_down", 10, 60)).await.unwrap();
+        assert!(!response.allowed);
+        assert!(!response.would_deny);
+        assert_eq!(response.failure_mode, Some(RateLimitFailureMode::Deny));
+    }
+
+    #[tokio::test]
+    async fn test_check_multi_applies_failure_mode() {
+        let limiter = RateLimiter::new("redis://127.0.0.1:1")
+            .unwrap()
+            .with_failure_mode(RateLimitFailureMode::Deny);
+
+        let response = limiter.check_multi("test_multi_down", &[(10, 1), (100, 60)], 1).await.unwrap();
+        assert!(!response.allowed);
+        assert!(response.tiers.iter().all(|tier| tier.failure_mode == Some(RateLimitFailureMode::Deny)));
+    }
+
+    #[tokio::test]
+    async fn test_sliding_window_global_limit_across_instances() {
+        // Requires Redis; each limiter simulates a separate app instance
+        let probe = match RateLimiter::new("redis://127.0.0.1:6379") {
+            Ok(limiter) => limiter,
+            Err(_) => return,
+        };
+        if probe.health_check().await.is_err() {
+            println!("Skipping sliding window test - Redis not available");
+            return;
+        }
+
+        let key = format!("test_sliding_{}", uuid::Uuid::new_v4());
+        let limit = 25;
+        let mut tasks = tokio::task::JoinSet::new();
+
+        for _instance in 0..5 {
+            let limiter = std::sync::Arc::new(
+                RateLimiter::new("redis://127.0.0.1:6379")
+                    .unwrap()
+                    .with_strategy(RateLimitStrategy::SlidingWindow),
+            );
+
+            for _ in 0..20 {
+                let limiter = limiter.clone();
+                let req = create_test_request(&key, limit, 60);
+                tasks.spawn(async move { limiter.check(req).await.map(|r| r.allowed) });
+            }
+        }
+
+        let mut allowed = 0;
+        while let Some(result) = tasks.join_next().await {
+            if let Ok(Ok(true)) = result {
+                allowed += 1;
+            }
+        }
+
+        assert_eq!(allowed, limit);
+    }
+
+    #[tokio::test]
+    async fn test_sliding_window_stores_one_member_per_request() {
+        let limiter = match RateLimiter::new("redis://127.0.0.1:6379") {
+            Ok(limiter) => limiter.with_strategy(RateLimitStrategy::SlidingWindow),
+            Err(_) => return,
+        };
+        if limiter.health_check().await.is_err() {
+            println!("Skipping sliding window cost test - Redis not available");
+            return;
+        }
+
+        let key = format!("test_sliding_cost_{}", uuid::Uuid::new_v4());
+        let heavy = RateLimitRequest {
+            cost: 400,
+            ..create_test_request(&key, 1000, 60)
+        };
+
+        let first = limiter.check(heavy.clone()).await.unwrap();
+        assert!(first.allowed);
+        assert_eq!(first.remaining, 600);
+        assert_eq!(limiter.check(heavy.clone()).await.unwrap().remaining, 200);
+        assert!(!limiter.check(heavy.clone()).await.unwrap().allowed);
+
+        // Two requests, however many units they stood for
+        let mut conn = limiter.redis.get_async_connection().await.unwrap();
+        let members: u64 = conn.zcard(limiter.sliding_key(&key)).await.unwrap();
+        assert_eq!(members, 2);
+
+        assert_eq!(limiter.peek(&heavy).await.unwrap().remaining, 200);
+        let state = limiter.key_state(&key).await.unwrap();
+        assert_eq!(state.len(), 1);
+        assert_eq!(state[0].count, Some(800));
+
+        assert_eq!(limiter.reset_key(&key).await.unwrap(), 1);
+        let units: Option<u64> = conn.get(RateLimiter::units_key(&limiter.sliding_key(&key))).await.unwrap();
+        assert_eq!(units, None);
+    }
+
+    #[tokio::test]
+    async fn test_batch_mixed_results() {
+        let limiter = match RateLimiter::new("redis://127.0.0.1:6379") {
+            Ok(limiter) => limiter,
+            Err(_) => return,
+        };
+        if limiter.health_check().await.is_err() {
+            println!("Skipping batch test - Redis not available");
+            return;
+        }
+
+        for strategy in [RateLimitStrategy::FixedWindow, RateLimitStrategy::SlidingWindow] {
+            let limiter = RateLimiter::new("redis://127.0.0.1:6379")
+                .unwrap()
+                .with_strategy(strategy);
+            let open_key = format!("test_batch_open_{}", uuid::Uuid::new_v4());
+            let tight_key = format!("test_batch_tight_{}", uuid::Uuid::new_v4());
+
+            let batch = vec![
+                create_test_request(&open_key, 10, 60),
+                create_test_request(&tight_key, 1, 60),
+                create_test_request(&tight_key, 1, 60),
+                create_test_request(&open_key, 10, 60),
+            ];
+
+            let results = limiter.check_batch(&batch).await.unwrap();
+
+            assert_eq!(results.len(), 4);
+            assert!(results[0].allowed);
+            assert_eq!(results[0].remaining, 9);
+            assert!(results[1].allowed);
+            assert!(!results[2].allowed);
+            assert!(results[2].retry_after.is_some());
+            assert!(results[3].allowed);
+            assert_eq!(results[3].remaining, 8);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_peek_does_not_consume() {
+        let probe = match RateLimiter::new("redis://127.0.0.1:6379") {
+            Ok(limiter) => limiter,
+            Err(_) => return,
+        };
+        if probe.health_check().await.is_err() {
+            println!("Skipping peek test - Redis not available");
+            return;
+        }
+
+        for strategy in [RateLimitStrategy::FixedWindow, RateLimitStrategy::SlidingWindow] {
+            let limiter = RateLimiter::new("redis://127.0.0.1:6379")
+                .unwrap()
+                .with_strategy(strategy);
+            let req = create_test_request(&format!("test_peek_{}", uuid::Uuid::new_v4()), 2, 60);
+
+            limiter.check(req.clone()).await.unwrap();
+            for _ in 0..3 {
+                let peeked = limiter.peek(&req).await.unwrap();
+                assert!(peeked.allowed);
+                assert_eq!(peeked.remaining, 1);
+            }
+
+            limiter.check(req.clone()).await.unwrap();
+            let peeked = limiter.peek(&req).await.unwrap();
+            assert!(!peeked.allowed);
+            assert_eq!(peeked.remaining, 0);
+        }
+    }
+
+    #[test]
+    fn test_state_entries_belong_to_exact_key() {
+        assert_eq!(parse_state_suffix("sliding"), Some((RateLimitStrategy::SlidingWindow, None, None)));
+        assert_eq!(
+            parse_state_suffix("1700000000"),
+            Some((RateLimitStrategy::FixedWindow, None, Some(1700000000)))
+        );
+        assert_eq!(parse_state_suffix("60s:leaky"), Some((RateLimitStrategy::LeakyBucket, Some(60), None)));
+        // Entries of `user:1` seen from `user`
+        assert_eq!(parse_state_suffix("1:sliding"), None);
+        assert_eq!(parse_state_suffix("1:1700000000"), None);
+
+        assert_eq!(escape_glob("user:[a]*"), "user:\\[a\\]\\*");
+        assert!(validate_exact_key("user:*").is_err());
+        assert!(validate_exact_key("").is_err());
+        assert!(validate_exact_key("user:1").is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_reset_key_clears_counters() {
+        let probe = match RateLimiter::new("redis://127.0.0.1:6379") {
+            Ok(limiter) => limiter,
+            Err(_) => return,
+        };
+        if probe.health_check().await.is_err() {
+            println!("Skipping reset test - Redis not available");
+            return;
+        }
+
+        for strategy in [RateLimitStrategy::FixedWindow, RateLimitStrategy::SlidingWindow] {
+            let limiter = probe.for_strategy(strategy);
+            let key = format!("test_reset_{}", uuid::Uuid::new_v4());
+            let req = create_test_request(&key, 1, 60);
+            let neighbour = create_test_request(&format!("{key}:1"), 1, 60);
+
+            for req in [&req, &neighbour] {
+                assert!(limiter.check(req.clone()).await.unwrap().allowed);
+                assert!(!limiter.check(req.clone()).await.unwrap().allowed);
+            }
+
+            let state = limiter.key_state(&key).await.unwrap();
+            assert_eq!(state.len(), 1);
+            assert_eq!(state[0].strategy, strategy);
+            assert_eq!(state[0].count, Some(1));
+            assert!(state[0].ttl_ms.is_some());
+
+            assert_eq!(limiter.reset_key(&key).await.unwrap(), 1);
+            assert!(limiter.key_state(&key).await.unwrap().is_empty());
+            assert!(limiter.check(req.clone()).await.unwrap().allowed);
+            // Only the exact key is reset
+            assert!(!limiter.check(neighbour.clone()).await.unwrap().allowed);
+            assert_eq!(limiter.reset_key(&key).await.unwrap(), 1);
+            assert_eq!(limiter.reset_key(&neighbour.key).await.unwrap(), 1);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_leaky_bucket_waits_up_to_bound() {
+        let limiter = match RateLimiter::new("redis://127.0.0.1:6379") {
+            Ok(limiter) => limiter
+                .with_strategy(RateLimitStrategy::LeakyBucket)
+                .with_max_wait(Duration::from_millis(250)),
+            Err(_) => return,
+        };
+        if limiter.health_check().await.is_err() {
+            println!("Skipping leaky bucket test - Redis not available");
+            return;
+        }
+
+        // 10 per second drains one request every 100ms
+        let req = create_test_request(&format!("test_leaky_{}", uuid::Uuid::new_v4()), 10, 1);
+        let offered = Duration::from_secs(10);
+
+        let first = limiter.check_with_wait(req.clone(), offered).await.unwrap();
+        assert!(first.allowed);
+        assert_eq!(first.wait_for, None);
+        assert_eq!(first.deny_reason, None);
+
+        // A plain check doesn't queue
+        let plain = limiter.check(req.clone()).await.unwrap();
+        assert!(!plain.allowed);
+        assert_eq!(plain.retry_after, Some(1));
+        assert_eq!(plain.deny_reason, Some(DenyReason::KeyLimit));
+
+        let second = limiter.check_with_wait(req.clone(), offered).await.unwrap();
+        assert!(second.allowed);
+        assert!(matches!(second.wait_for, Some(wait) if wait > 0 && wait <= 100));
+
+        let third = limiter.check_with_wait(req.clone(), offered).await.unwrap();
+        assert!(matches!(third.wait_for, Some(wait) if wait > 100 && wait <= 200));
+
+        // The fourth would wait about 300ms, past the limiter's 250ms cap
+        let fourth = limiter.check_with_wait(req.clone(), offered).await.unwrap();
+        assert!(!fourth.allowed);
+        assert_eq!(fourth.wait_for, None);
+        assert_eq!(fourth.retry_after, Some(1));
+    }
+
+    #[tokio::test]
+    async fn test_check_and_wait_sleeps_out_the_wait() {
+        let limiter = match RateLimiter::new("redis://127.0.0.1:6379") {
+            Ok(limiter) => limiter.with_strategy(RateLimitStrategy::LeakyBucket),
+            Err(_) => return,
+        };
+        if limiter.health_check().await.is_err() {
+            println!("Skipping leaky bucket test - Redis not available");
+            return;
+        }
+
+        let req = create_test_request(&format!("test_leaky_wait_{}", uuid::Uuid::new_v4()), 10, 1);
+        let max_wait = Duration::from_millis(500);
+        assert!(limiter.check_and_wait(req.clone(), max_wait).await.unwrap().allowed);
+
+        let started = std::time::Instant::now();
+        let response = limiter.check_and_wait(req.clone(), max_wait).await.unwrap();
+        assert!(response.allowed);
+        assert_eq!(response.wait_for, None);
+        assert!(started.elapsed() >= Duration::from_millis(50));
+
+        // Nothing to wait for at all on other strategies
+        let fixed = limiter.for_strategy(RateLimitStrategy::FixedWindow);
+        let response = fixed.check_with_wait(req, max_wait).await.unwrap();
+        assert!(response.allowed);
+        assert_eq!(response.wait_for, None);
+    }
+
+    #[test]
+    fn test_batch_size_limit() {
+        let batch: Vec<RateLimitRequest> = (0..5)
+            .map(|i| create_test_request(&format!("key_{}", i), 10, 60))
+            .collect();
+
+        assert!(validate_batch(&batch, 5).is_ok());
+        assert!(validate_batch(&batch, 4).is_err());
+        assert!(validate_batch(&[], 5).is_err());
+
+        let mut invalid = batch.clone();
+        invalid[2].window = 0;
+        assert!(validate_batch(&invalid, 5).is_err());
+    }
+
+    #[test]
+    fn test_rate_limit_strategy_serialization() {
+        let strategy: RateLimitStrategy = serde_json::from_str("\"sliding_window\"").unwrap();
+        assert_eq!(strategy, RateLimitStrategy::SlidingWindow);
+        let strategy: RateLimitStrategy = serde_json::from_str("\"leaky_bucket\"").unwrap();
+        assert_eq!(strategy, RateLimitStrategy::LeakyBucket);
+        assert_eq!(RateLimitStrategy::default(), RateLimitStrategy::FixedWindow);
+    }
+
     #[test]
     fn test_rate_limit_request_serialization() {
         let req = create_test_request("user:123", 100, 3600);
@@ -221,6 +2534,10 @@ mod tests {
             remaining: 99,
             reset_in: 3542,
             retry_after: None,
+            failure_mode: None,
+            would_deny: false,
+            wait_for: None,
+            deny_reason: None,
         };
 
         let json = serde_json::to_string(&response).unwrap();
@@ -231,4 +2548,89 @@ mod tests {
         assert_eq!(response.reset_in, deserialized.reset_in);
         assert_eq!(response.retry_after, deserialized.retry_after);
     }
+
+    /// Needs a running cluster, e.g.
+    /// `RATEWATCH_TEST_CLUSTER_URL=redis+cluster://127.0.0.1:7000 cargo test --features cluster -- --ignored cluster`
+    #[cfg(feature = "cluster")]
+    #[tokio::test]
+    #[ignore]
+    async fn test_cluster_checks_route_to_key_slot() {
+        let url = std::env::var("RATEWATCH_TEST_CLUSTER_URL")
+            .unwrap_or_else(|_| "redis+cluster://127.0.0.1:7000,127.0.0.1:7001,127.0.0.1:7002".to_string());
+
+        for strategy in [RateLimitStrategy::FixedWindow, RateLimitStrategy::SlidingWindow] {
+            let limiter = RateLimiter::new(&url).unwrap().with_strategy(strategy);
+            limiter.validate_topology().unwrap();
+
+            let key = format!("cluster_test:{}", uuid::Uuid::new_v4());
+            let response = limiter.check(create_test_request(&key, 5, 60)).await.unwrap();
+            assert!(response.allowed);
+            assert!(response.failure_mode.is_none());
+
+            // The server agrees on the slot of every key one check touches
+            let mut conn = limiter.redis.get_async_connection().await.unwrap();
+            for redis_key in limiter.check_keys(&key, 0) {
+                let slot: u16 = redis::cmd("CLUSTER")
+                    .arg("KEYSLOT")
+                    .arg(&redis_key)
+                    .query_async(&mut conn)
+                    .await
+                    .unwrap();
+                assert_eq!(slot, key_slot(&redis_key));
+                assert_eq!(slot, key_slot(&limiter.redis.hash_tag(&key)));
+            }
+
+            // A batch spanning slots is split per slot and still answers in order
+            let keys: Vec<String> = (0..8).map(|n| format!("{key}:{n}")).collect();
+            let batch: Vec<_> = keys.iter().map(|k| create_test_request(k, 1, 60)).collect();
+            let results = limiter.check_batch(&batch).await.unwrap();
+            assert!(results.iter().all(|r| r.allowed));
+            let results = limiter.check_batch(&batch).await.unwrap();
+            assert!(results.iter().all(|r| !r.allowed));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_repeated_idempotency_key_is_counted_once() {
+        let limiter = match RateLimiter::new("redis://127.0.0.1:6379") {
+            Ok(limiter) => limiter,
+            Err(_) => return,
+        };
+        if limiter.health_check().await.is_err() {
+            println!("Skipping idempotency test - Redis not available");
+            return;
+        }
+        let req = create_test_request(&format!("test_idempotent_{}", uuid::Uuid::new_v4()), 5, 60);
+        let idempotency_key = uuid::Uuid::new_v4().to_string();
+        let check = |req: &RateLimitRequest, idempotency_key: &str| {
+            let req = req.clone();
+            let idempotency_key = idempotency_key.to_string();
+            let limiter = &limiter;
+            async move {
+                limiter
+                    .check_idempotent(&req.key, &idempotency_key, limiter.check(req.clone()))
+                    .await
+                    .unwrap()
+            }
+        };
+
+        let IdempotentCheck::Checked(first) = check(&req, &idempotency_key).await else {
+            panic!("first check wasn't run");
+        };
+        assert!(first.allowed);
+        assert_eq!(first.remaining, 4);
+
+        // The retry gets the same answer and nothing more is taken
+        for _ in 0..3 {
+            let IdempotentCheck::Replayed(retry) = check(&req, &idempotency_key).await else {
+                panic!("retry was checked again");
+            };
+            assert_eq!((retry.allowed, retry.remaining, retry.reset_in), (first.allowed, first.remaining, first.reset_in));
+        }
+        assert_eq!(limiter.check(req.clone()).await.unwrap().remaining, 3);
+
+        // Another rate limit key with the same idempotency key is its own check
+        let other = create_test_request(&format!("test_idempotent_{}", uuid::Uuid::new_v4()), 5, 60);
+        assert!(matches!(check(&other, &idempotency_key).await, IdempotentCheck::Checked(r) if r.remaining == 4));
+    }
 }
@@ -0,0 +1,1163 @@
+//! Redis connections for standalone, Cluster and Sentinel deployments.
+//!
+//! Every component that talks to Redis holds a [`RedisConnector`] instead of a
+//! `redis::Client`. The topology is picked from the URL scheme or the `[redis]`
+//! config section:
+//!
+//! - `redis://host:6379` / `rediss://host:6379` - a single server
+//! - `redis+cluster://node1:6379,node2:6379` - Redis Cluster seed nodes
+//!   (requires the `cluster` feature)
+//! - `redis+sentinel://sentinel1:26379,sentinel2:26379/mymaster` - the master
+//!   currently elected by Sentinel for `mymaster`
+//!
+//! `redis-cluster://` and `redis-sentinel://` are accepted as aliases.
+//!
+//! In a cluster every key in one Lua script or transaction must hash to the
+//! same slot. Components that run multi-key scripts wrap the shared part of
+//! their keys with [`RedisConnector::hash_tag`] and check the layout with
+//! [`ensure_same_slot`].
+//!
+//! Lua scripts are run through [`LuaScript`], which sends only the script's
+//! SHA1 with `EVALSHA`. When a server doesn't have the script, as after a
+//! restart, a failover or `SCRIPT FLUSH`, the `NOSCRIPT` reply is answered
+//! with a single `EVAL`, which runs the script and caches it on that server
+//! again.
+
+use anyhow::{anyhow, Result};
+use redis::{
+    aio::{ConnectionLike, MultiplexedConnection},
+    sentinel::{SentinelClient, SentinelNodeConnectionInfo, SentinelServerType},
+    Cmd, ErrorKind, FromRedisValue, Pipeline, RedisConnectionInfo, RedisError, RedisFuture, RedisResult, ToRedisArgs,
+    TlsMode, Value,
+};
+use std::future::Future;
+use std::sync::{
+    atomic::{AtomicU64, AtomicUsize, Ordering},
+    Arc, OnceLock,
+};
+use std::time::Duration;
+use tokio::sync::{Mutex, RwLock};
+
+use crate::config::RedisConfig;
+
+/// Number of hash slots in a Redis Cluster
+pub const CLUSTER_SLOTS: u16 = 16384;
+
+/// Multiplexed connections kept open per connector
+pub const DEFAULT_POOL_SIZE: usize = 4;
+
+pub const DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_secs(2);
+
+pub const DEFAULT_RESPONSE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Keys Redis is asked to examine per `SCAN` round trip
+pub const SCAN_COUNT: usize = 500;
+
+/// How the Redis backend is deployed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RedisTopology {
+    Standalone { url: String },
+    Cluster { nodes: Vec<String> },
+    Sentinel {
+        master_name: String,
+        sentinels: Vec<String>,
+        /// Credentials and TLS used for the master itself
+        master_url: Option<String>,
+    },
+}
+
+impl RedisTopology {
+    /// Detect the topology from a connection URL's scheme
+    pub fn from_url(url: &str) -> Result<Self> {
+        let (scheme, rest) = url
+            .split_once("://")
+            .ok_or_else(|| anyhow!("Invalid Redis URL '{}': missing scheme", url))?;
+
+        match scheme {
+            "redis" | "rediss" | "redis+unix" | "unix" => Ok(Self::Standalone {
+                url: url.to_string(),
+            }),
+            "redis+cluster" | "rediss+cluster" | "redis-cluster" | "rediss-cluster" => {
+                let base = &scheme[..scheme.len() - "+cluster".len()];
+                let (auth, hosts) = split_auth(rest);
+                let hosts = hosts.split('/').next().unwrap_or_default();
+                let nodes = node_urls(base, auth, hosts);
+                if nodes.is_empty() {
+                    return Err(anyhow!("Redis cluster URL '{}' lists no nodes", url));
+                }
+                Ok(Self::Cluster { nodes })
+            }
+            "redis+sentinel" | "rediss+sentinel" | "redis-sentinel" | "rediss-sentinel" => {
+                let base = &scheme[..scheme.len() - "+sentinel".len()];
+                let (auth, rest) = split_auth(rest);
+                let (hosts, master_name) = rest.split_once('/').unwrap_or((rest, ""));
+                if master_name.is_empty() {
+                    return Err(anyhow!(
+                        "Redis sentinel URL '{}' must end with the master name, e.g. redis+sentinel://host:26379/mymaster",
+                        url
+                    ));
+                }
+                let sentinels = node_urls("redis", None, hosts);
+                if sentinels.is_empty() {
+                    return Err(anyhow!("Redis sentinel URL '{}' lists no sentinels", url));
+                }
+                let master_url = (auth.is_some() || base == "rediss")
+                    .then(|| format!("{}://{}localhost", base, auth.map(|a| format!("{a}@")).unwrap_or_default()));
+                Ok(Self::Sentinel {
+                    master_name: master_name.to_string(),
+                    sentinels,
+                    master_url,
+                })
+            }
+            other => Err(anyhow!(
+                "Unsupported Redis URL scheme '{}'; expected redis://, rediss://, redis+cluster:// or redis+sentinel://",
+                other
+            )),
+        }
+    }
+
+    /// Topology from the `[redis]` section; explicit cluster or sentinel
+    /// settings take precedence over `url`
+    pub fn from_config(url: &str, config: &RedisConfig) -> Result<Self> {
+        match (&config.sentinel, config.cluster_nodes.is_empty()) {
+            (Some(_), false) => Err(anyhow!(
+                "Configure either redis.cluster_nodes or redis.sentinel, not both"
+            )),
+            (Some(sentinel), true) => {
+                if sentinel.master_name.is_empty() || sentinel.addresses.is_empty() {
+                    return Err(anyhow!(
+                        "redis.sentinel needs a master_name and at least one address"
+                    ));
+                }
+                Ok(Self::Sentinel {
+                    master_name: sentinel.master_name.clone(),
+                    sentinels: sentinel.addresses.clone(),
+                    master_url: None,
+                })
+            }
+            (None, false) => Ok(Self::Cluster {
+                nodes: config.cluster_nodes.clone(),
+            }),
+            (None, true) => Self::from_url(url),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Standalone { .. } => "standalone",
+            Self::Cluster { .. } => "cluster",
+            Self::Sentinel { .. } => "sentinel",
+        }
+    }
+
+    pub fn is_cluster(&self) -> bool {
+        matches!(self, Self::Cluster { .. })
+    }
+}
+
+/// Split `user:pass@hosts` into the credentials and the host part
+fn split_auth(rest: &str) -> (Option<&str>, &str) {
+    match rest.rsplit_once('@') {
+        Some((auth, hosts)) => (Some(auth), hosts),
+        None => (None, rest),
+    }
+}
+
+fn node_urls(scheme: &str, auth: Option<&str>, hosts: &str) -> Vec<String> {
+    hosts
+        .split(',')
+        .map(str::trim)
+        .filter(|host| !host.is_empty())
+        .map(|host| match auth {
+            Some(auth) => format!("{scheme}://{auth}@{host}"),
+            None => format!("{scheme}://{host}"),
+        })
+        .collect()
+}
+
+/// Where new connections come from
+enum Upstream {
+    Client(redis::Client),
+    // Sentinel clients cache their connections to the sentinels and need &mut
+    Sentinel(Mutex<SentinelClient>),
+}
+
+impl Upstream {
+    async fn connect(&self) -> RedisResult<MultiplexedConnection> {
+        match self {
+            Self::Client(client) => client.get_multiplexed_tokio_connection().await,
+            // Resolves the current master, so reconnecting follows a failover
+            Self::Sentinel(client) => client.lock().await.get_async_connection().await,
+        }
+    }
+}
+
+/// One long-lived multiplexed connection, re-established after it breaks
+struct PooledSlot {
+    upstream: Arc<Upstream>,
+    // Generation counter so a stale failure can't drop a fresh connection
+    current: RwLock<Option<(u64, MultiplexedConnection)>>,
+    generation: AtomicU64,
+}
+
+impl PooledSlot {
+    fn new(upstream: Arc<Upstream>) -> Self {
+        Self {
+            upstream,
+            current: RwLock::new(None),
+            generation: AtomicU64::new(0),
+        }
+    }
+
+    async fn get(self: &Arc<Self>) -> RedisResult<PooledConnection> {
+        if let Some((generation, conn)) = self.current.read().await.as_ref() {
+            return Ok(self.wrap(*generation, conn.clone()));
+        }
+
+        let mut current = self.current.write().await;
+        if let Some((generation, conn)) = current.as_ref() {
+            return Ok(self.wrap(*generation, conn.clone()));
+        }
+
+        let conn = self.upstream.connect().await?;
+        let generation = self.generation.fetch_add(1, Ordering::Relaxed) + 1;
+        *current = Some((generation, conn.clone()));
+        Ok(self.wrap(generation, conn))
+    }
+
+    fn wrap(self: &Arc<Self>, generation: u64, conn: MultiplexedConnection) -> PooledConnection {
+        PooledConnection {
+            conn,
+            generation,
+            slot: self.clone(),
+        }
+    }
+
+    /// Forget the connection so the next caller reconnects
+    async fn invalidate(&self, generation: u64) {
+        let mut current = self.current.write().await;
+        if matches!(current.as_ref(), Some((g, _)) if *g == generation) {
+            tracing::warn!("Redis connection lost; reconnecting on next use");
+            *current = None;
+        }
+    }
+}
+
+enum Backend {
+    Pooled(Vec<Arc<PooledSlot>>),
+    #[cfg(feature = "cluster")]
+    Cluster {
+        client: redis::cluster::ClusterClient,
+        // Cluster connections are multiplexed and refresh slots themselves
+        connection: tokio::sync::OnceCell<redis::cluster_async::ClusterConnection>,
+        // Credentials and TLS for connecting to single nodes, see `scan_keys`
+        seed: Arc<redis::ConnectionInfo>,
+    },
+}
+
+/// Hands out connections for whichever topology is configured.
+///
+/// Standalone and Sentinel backends keep `pool_size` multiplexed connections
+/// open and hand out clones round-robin, so commands reuse warm connections
+/// instead of dialing Redis per call. A connection that fails with an I/O
+/// error or times out is dropped and transparently re-established by the
+/// next caller.
+#[derive(Clone)]
+pub struct RedisConnector {
+    backend: Arc<Backend>,
+    topology: Arc<RedisTopology>,
+    next_slot: Arc<AtomicUsize>,
+    connect_timeout: Duration,
+    response_timeout: Duration,
+}
+
+// URLs may carry credentials, so only the topology kind is shown
+impl std::fmt::Debug for RedisConnector {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RedisConnector")
+            .field("topology", &self.topology.name())
+            .finish_non_exhaustive()
+    }
+}
+
+impl RedisConnector {
+    /// Connector for a URL, detecting the topology from its scheme
+    pub fn open(url: &str) -> Result<Self> {
+        Self::connect(RedisTopology::from_url(url)?, DEFAULT_POOL_SIZE)
+    }
+
+    pub fn from_config(url: &str, config: &RedisConfig) -> Result<Self> {
+        Ok(Self::connect(RedisTopology::from_config(url, config)?, config.pool_size)?.with_timeouts(
+            Duration::from_millis(config.connect_timeout_ms),
+            Duration::from_millis(config.response_timeout_ms),
+        ))
+    }
+
+    /// Connections are opened lazily on first use
+    pub fn connect(topology: RedisTopology, pool_size: usize) -> Result<Self> {
+        let upstream = match &topology {
+            RedisTopology::Standalone { url } => Upstream::Client(redis::Client::open(url.as_str())?),
+            RedisTopology::Sentinel {
+                master_name,
+                sentinels,
+                master_url,
+            } => {
+                let node_info = master_url
+                    .as_deref()
+                    .map(sentinel_node_info)
+                    .transpose()?;
+                Upstream::Sentinel(Mutex::new(SentinelClient::build(
+                    sentinels.clone(),
+                    master_name.clone(),
+                    node_info,
+                    SentinelServerType::Master,
+                )?))
+            }
+            #[cfg(feature = "cluster")]
+            RedisTopology::Cluster { nodes } => {
+                let seed = nodes
+                    .first()
+                    .ok_or_else(|| anyhow!("Redis Cluster needs at least one node"))?;
+                return Ok(Self::with_backend(
+                    Backend::Cluster {
+                        client: redis::cluster::ClusterClient::new(nodes.clone())?,
+                        connection: tokio::sync::OnceCell::new(),
+                        seed: Arc::new(redis::IntoConnectionInfo::into_connection_info(seed.as_str())?),
+                    },
+                    topology,
+                ))
+            }
+            #[cfg(not(feature = "cluster"))]
+            RedisTopology::Cluster { .. } => {
+                return Err(anyhow!(
+                    "Redis Cluster support is not compiled in; rebuild with `--features cluster`"
+                ))
+            }
+        };
+
+        let upstream = Arc::new(upstream);
+        let slots = (0..pool_size.max(1))
+            .map(|_| Arc::new(PooledSlot::new(upstream.clone())))
+            .collect();
+        Ok(Self::with_backend(Backend::Pooled(slots), topology))
+    }
+
+    fn with_backend(backend: Backend, topology: RedisTopology) -> Self {
+        Self {
+            backend: Arc::new(backend),
+            topology: Arc::new(topology),
+            next_slot: Arc::new(AtomicUsize::new(0)),
+            connect_timeout: DEFAULT_CONNECT_TIMEOUT,
+            response_timeout: DEFAULT_RESPONSE_TIMEOUT,
+        }
+    }
+
+    /// Bound how long acquiring a connection and each command may take
+    pub fn with_timeouts(mut self, connect: Duration, response: Duration) -> Self {
+        self.connect_timeout = connect;
+        self.response_timeout = response;
+        self
+    }
+
+    /// Connections opened since the connector was created, reconnects included.
+    ///
+    /// Stays at `pool_size` or below while connections are being reused.
+    pub fn connections_opened(&self) -> u64 {
+        match self.backend.as_ref() {
+            Backend::Pooled(slots) => slots.iter().map(|slot| slot.generation.load(Ordering::Relaxed)).sum(),
+            #[cfg(feature = "cluster")]
+            Backend::Cluster { connection, .. } => connection.initialized() as u64,
+        }
+    }
+
+    pub fn topology(&self) -> &RedisTopology {
+        &self.topology
+    }
+
+    pub fn is_cluster(&self) -> bool {
+        self.topology.is_cluster()
+    }
+
+    /// Borrow a pooled connection, connecting first if it isn't open yet
+    pub async fn get_async_connection(&self) -> RedisResult<RedisConnection> {
+        let inner = match self.backend.as_ref() {
+            Backend::Pooled(slots) => {
+                let index = self.next_slot.fetch_add(1, Ordering::Relaxed) % slots.len();
+                with_timeout(self.connect_timeout, "connecting to Redis", slots[index].get())
+                    .await
+                    .map(Connection::Pooled)
+            }
+            #[cfg(feature = "cluster")]
+            Backend::Cluster { client, connection, seed } => with_timeout(
+                self.connect_timeout,
+                "connecting to Redis Cluster",
+                connection.get_or_try_init(|| client.get_async_connection()),
+            )
+            .await
+            .map(|conn| Connection::Cluster {
+                conn: conn.clone(),
+                seed: seed.clone(),
+            }),
+        }?;
+
+        Ok(RedisConnection {
+            inner,
+            #[cfg(feature = "cluster")]
+            connect_timeout: self.connect_timeout,
+            response_timeout: self.response_timeout,
+        })
+    }
+
+    /// Wrap `key` in a `{...}` hash tag when running against a cluster, so
+    /// every Redis key built around it lands on the same slot
+    pub fn hash_tag(&self, key: &str) -> String {
+        if self.is_cluster() {
+            format!("{{{key}}}")
+        } else {
+            key.to_string()
+        }
+    }
+}
+
+fn sentinel_node_info(master_url: &str) -> Result<SentinelNodeConnectionInfo> {
+    let info = redis::IntoConnectionInfo::into_connection_info(master_url)?;
+    Ok(SentinelNodeConnectionInfo {
+        tls_mode: master_url.starts_with("rediss://").then_some(TlsMode::Secure),
+        redis_connection_info: Some(RedisConnectionInfo {
+            db: info.redis.db,
+            username: info.redis.username,
+            password: info.redis.password,
+        }),
+    })
+}
+
+/// Clone of a pooled connection that reports breakage back to its slot
+pub struct PooledConnection {
+    conn: MultiplexedConnection,
+    generation: u64,
+    slot: Arc<PooledSlot>,
+}
+
+impl PooledConnection {
+    async fn check<T>(&self, result: RedisResult<T>) -> RedisResult<T> {
+        if let Err(e) = &result {
+            if needs_reconnect(e) {
+                self.slot.invalidate(self.generation).await;
+            }
+        }
+        result
+    }
+}
+
+/// Errors after which the connection is dropped and the next use reconnects.
+///
+/// Besides broken sockets this includes READONLY: after a Sentinel failover
+/// the old master stays reachable but has been demoted to a replica, and
+/// reconnecting asks Sentinel for the new master.
+fn needs_reconnect(e: &RedisError) -> bool {
+    e.is_io_error() || e.is_connection_dropped() || e.is_connection_refusal() || e.kind() == ErrorKind::ReadOnly
+}
+
+/// A connection to any supported topology; every command is bounded by the
+/// connector's response timeout
+pub struct RedisConnection {
+    inner: Connection,
+    // For the per-node connections of a cluster `scan_keys`
+    #[cfg(feature = "cluster")]
+    connect_timeout: Duration,
+    response_timeout: Duration,
+}
+
+enum Connection {
+    Pooled(PooledConnection),
+    #[cfg(feature = "cluster")]
+    Cluster {
+        conn: redis::cluster_async::ClusterConnection,
+        seed: Arc<redis::ConnectionInfo>,
+    },
+}
+
+impl ConnectionLike for RedisConnection {
+    fn req_packed_command<'a>(&'a mut self, cmd: &'a Cmd) -> RedisFuture<'a, Value> {
+        let timeout = self.response_timeout;
+        match &mut self.inner {
+            Connection::Pooled(pooled) => Box::pin(async move {
+                let result = with_timeout(timeout, "waiting for a Redis reply", pooled.conn.req_packed_command(cmd)).await;
+                pooled.check(result).await
+            }),
+            #[cfg(feature = "cluster")]
+            Connection::Cluster { conn, .. } => {
+                Box::pin(with_timeout(timeout, "waiting for a Redis reply", conn.req_packed_command(cmd)))
+            }
+        }
+    }
+
+    fn req_packed_commands<'a>(
+        &'a mut self,
+        cmd: &'a Pipeline,
+        offset: usize,
+        count: usize,
+    ) -> RedisFuture<'a, Vec<Value>> {
+        let timeout = self.response_timeout;
+        match &mut self.inner {
+            Connection::Pooled(pooled) => Box::pin(async move {
+                let result = with_timeout(
+                    timeout,
+                    "waiting for a Redis reply",
+                    pooled.conn.req_packed_commands(cmd, offset, count),
+                )
+                .await;
+                pooled.check(result).await
+            }),
+            #[cfg(feature = "cluster")]
+            Connection::Cluster { conn, .. } => Box::pin(with_timeout(
+                timeout,
+                "waiting for a Redis reply",
+                conn.req_packed_commands(cmd, offset, count),
+            )),
+        }
+    }
+
+    fn get_db(&self) -> i64 {
+        match &self.inner {
+            Connection::Pooled(pooled) => pooled.conn.get_db(),
+            #[cfg(feature = "cluster")]
+            Connection::Cluster { conn, .. } => conn.get_db(),
+        }
+    }
+}
+
+/// Every key matching `pattern`, sorted. Uses cursor-based `SCAN` rather
+/// than `KEYS`, which blocks the server for as long as it takes to walk the
+/// whole keyspace. `SCAN` may report a key twice, so the result is deduplicated.
+///
+/// A cluster connection doesn't route `SCAN`, and each node's cursor only
+/// means something to that node, so on a cluster every primary is scanned
+/// to the end over its own connection.
+pub async fn scan_keys(conn: &mut RedisConnection, pattern: &str) -> RedisResult<Vec<String>> {
+    let mut keys = std::collections::BTreeSet::new();
+
+    #[cfg(feature = "cluster")]
+    if let Connection::Cluster { seed, .. } = &conn.inner {
+        let seed = seed.clone();
+        let slots: Value = redis::cmd("CLUSTER").arg("SLOTS").query_async(conn).await?;
+        for (host, port) in cluster_primaries(&slots)? {
+            let mut info = (*seed).clone();
+            match &mut info.addr {
+                redis::ConnectionAddr::Tcp(node_host, node_port)
+                | redis::ConnectionAddr::TcpTls {
+                    host: node_host,
+                    port: node_port,
+                    ..
+                } => {
+                    *node_host = host;
+                    *node_port = port;
+                }
+                _ => return Err((ErrorKind::InvalidClientConfig, "Cluster nodes must be reached over TCP").into()),
+            }
+            let client = redis::Client::open(info)?;
+            let mut node = with_timeout(
+                conn.connect_timeout,
+                "connecting to a Redis Cluster node",
+                client.get_multiplexed_tokio_connection(),
+            )
+            .await?;
+            scan_node(&mut node, pattern, conn.response_timeout, &mut keys).await?;
+        }
+        return Ok(keys.into_iter().collect());
+    }
+
+    let timeout = conn.response_timeout;
+    scan_node(conn, pattern, timeout, &mut keys).await?;
+    Ok(keys.into_iter().collect())
+}
+
+/// Walk one server's `SCAN` cursor to the end
+async fn scan_node<C: ConnectionLike>(
+    conn: &mut C,
+    pattern: &str,
+    timeout: Duration,
+    keys: &mut std::collections::BTreeSet<String>,
+) -> RedisResult<()> {
+    let mut cursor: u64 = 0;
+    loop {
+        let (next, batch): (u64, Vec<String>) = with_timeout(
+            timeout,
+            "waiting for a Redis reply",
+            redis::cmd("SCAN")
+                .arg(cursor)
+                .arg("MATCH")
+                .arg(pattern)
+                .arg("COUNT")
+                .arg(SCAN_COUNT)
+                .query_async(conn),
+        )
+        .await?;
+        keys.extend(batch);
+        if next == 0 {
+            return Ok(());
+        }
+        cursor = next;
+    }
+}
+
+/// Host and port of each primary in a `CLUSTER SLOTS` reply, once each
+#[cfg(feature = "cluster")]
+fn cluster_primaries(slots: &Value) -> RedisResult<Vec<(String, u16)>> {
+    let invalid = || RedisError::from((ErrorKind::TypeError, "Unexpected CLUSTER SLOTS reply"));
+    let Value::Bulk(ranges) = slots else {
+        return Err(invalid());
+    };
+
+    let mut primaries = std::collections::BTreeSet::new();
+    for range in ranges {
+        // [start, end, [host, port, id, ...], replicas...]
+        let Value::Bulk(range) = range else {
+            return Err(invalid());
+        };
+        let Some(Value::Bulk(primary)) = range.get(2) else {
+            return Err(invalid());
+        };
+        let (Some(host), Some(port)) = (primary.first(), primary.get(1)) else {
+            return Err(invalid());
+        };
+        primaries.insert((redis::from_redis_value(host)?, redis::from_redis_value(port)?));
+    }
+    Ok(primaries.into_iter().collect())
+}
+
+/// A Lua script, run by its SHA1 and reloaded when Redis doesn't have it.
+/// The hash is worked out on first use and kept, so scripts are statics.
+pub struct LuaScript {
+    code: &'static str,
+    hash: OnceLock<String>,
+    reloads: AtomicU64,
+}
+
+impl LuaScript {
+    pub const fn new(code: &'static str) -> Self {
+        Self {
+            code,
+            hash: OnceLock::new(),
+            reloads: AtomicU64::new(0),
+        }
+    }
+
+    pub fn hash(&self) -> &str {
+        self.hash.get_or_init(|| redis::Script::new(self.code).get_hash().to_string())
+    }
+
+    /// Times a server answered `NOSCRIPT` and the script was sent again
+    pub fn reloads(&self) -> u64 {
+        self.reloads.load(Ordering::Relaxed)
+    }
+
+    /// Start a call, to be given its keys and arguments
+    pub fn prepare_invoke(&self) -> LuaInvocation<'_> {
+        LuaInvocation {
+            script: self,
+            keys: Vec::new(),
+            args: Vec::new(),
+        }
+    }
+}
+
+/// One call of a [`LuaScript`]
+pub struct LuaInvocation<'a> {
+    script: &'a LuaScript,
+    keys: Vec<Vec<u8>>,
+    args: Vec<Vec<u8>>,
+}
+
+impl LuaInvocation<'_> {
+    pub fn key<T: ToRedisArgs>(&mut self, key: T) -> &mut Self {
+        self.keys.extend(key.to_redis_args());
+        self
+    }
+
+    pub fn arg<T: ToRedisArgs>(&mut self, arg: T) -> &mut Self {
+        self.args.extend(arg.to_redis_args());
+        self
+    }
+
+    /// `EVALSHA`, or `EVAL` if the server answers `NOSCRIPT`
+    pub async fn invoke_async<C: ConnectionLike, T: FromRedisValue>(&self, conn: &mut C) -> RedisResult<T> {
+        match self.command("EVALSHA", self.script.hash()).query_async(conn).await {
+            Err(e) if e.kind() == ErrorKind::NoScriptError => {
+                self.script.reloads.fetch_add(1, Ordering::Relaxed);
+                tracing::debug!(sha = self.script.hash(), "Redis doesn't have the Lua script, sending it again");
+                self.command("EVAL", self.script.code).query_async(conn).await
+            }
+            result => result,
+        }
+    }
+
+    fn command(&self, name: &str, script: &str) -> Cmd {
+        let mut cmd = redis::cmd(name);
+        cmd.arg(script).arg(self.keys.len());
+        for key in &self.keys {
+            cmd.arg(key.as_slice());
+        }
+        for arg in &self.args {
+            cmd.arg(arg.as_slice());
+        }
+        cmd
+    }
+}
+
+/// `TimedOut` I/O error if `future` takes longer than `timeout`, so a stalled
+/// pooled connection is treated like a broken one
+async fn with_timeout<T>(
+    timeout: Duration,
+    action: &str,
+    future: impl Future<Output = RedisResult<T>>,
+) -> RedisResult<T> {
+    match tokio::time::timeout(timeout, future).await {
+        Ok(result) => result,
+        Err(_) => Err(std::io::Error::new(
+            std::io::ErrorKind::TimedOut,
+            format!("timed out after {}ms {}", timeout.as_millis(), action),
+        )
+        .into()),
+    }
+}
+
+/// CRC16/XMODEM as used by Redis Cluster key hashing
+fn crc16(data: &[u8]) -> u16 {
+    data.iter().fold(0u16, |mut crc, &byte| {
+        crc ^= (byte as u16) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 { (crc << 1) ^ 0x1021 } else { crc << 1 };
+        }
+        crc
+    })
+}
+
+/// Cluster hash slot of a key, honouring `{hash tags}`
+pub fn key_slot(key: &str) -> u16 {
+    let bytes = key.as_bytes();
+    let tag = bytes.iter().position(|&b| b == b'{').and_then(|open| {
+        let rest = &bytes[open + 1..];
+        rest.iter()
+            .position(|&b| b == b'}')
+            .filter(|&close| close > 0)
+            .map(|close| &rest[..close])
+    });
+
+    crc16(tag.unwrap_or(bytes)) % CLUSTER_SLOTS
+}
+
+/// Fail if the keys one script touches would span more than one cluster slot
+pub fn ensure_same_slot(script: &str, keys: &[String]) -> Result<()> {
+    let Some(first) = keys.first() else {
+        return Ok(());
+    };
+    let slot = key_slot(first);
+
+    match keys.iter().find(|key| key_slot(key) != slot) {
+        Some(other) => Err(anyhow!(
+            "Redis script '{}' touches keys in different cluster slots ('{}' in slot {}, '{}' in slot {}); \
+             keys used together must share a {{hash tag}}",
+            script,
+            first,
+            slot,
+            other,
+            key_slot(other)
+        )),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::RedisSentinelConfig;
+
+    /// A server whose script cache can be emptied, answering every script with 1
+    #[derive(Default)]
+    struct ScriptCache {
+        scripts: std::collections::HashSet<String>,
+        commands: Vec<String>,
+    }
+
+    impl ConnectionLike for ScriptCache {
+        fn req_packed_command<'a>(&'a mut self, cmd: &'a Cmd) -> RedisFuture<'a, Value> {
+            let args: Vec<String> = cmd
+                .args_iter()
+                .map(|arg| match arg {
+                    redis::Arg::Simple(bytes) => String::from_utf8_lossy(bytes).into_owned(),
+                    redis::Arg::Cursor => String::new(),
+                })
+                .collect();
+            self.commands.push(args[0].clone());
+            let result = match args[0].as_str() {
+                "EVALSHA" if !self.scripts.contains(&args[1]) => Err(RedisError::from((
+                    ErrorKind::NoScriptError,
+                    "NOSCRIPT",
+                    "No matching script".to_string(),
+                ))),
+                "EVAL" => {
+                    self.scripts.insert(redis::Script::new(&args[1]).get_hash().to_string());
+                    Ok(Value::Int(1))
+                }
+                _ => Ok(Value::Int(1)),
+            };
+            Box::pin(async move { result })
+        }
+
+        fn req_packed_commands<'a>(&'a mut self, _cmd: &'a Pipeline, _offset: usize, _count: usize) -> RedisFuture<'a, Vec<Value>> {
+            Box::pin(async { Err(RedisError::from((ErrorKind::ClientError, "not supported"))) })
+        }
+
+        fn get_db(&self) -> i64 {
+            0
+        }
+    }
+
+    #[tokio::test]
+    async fn test_lua_script_is_reloaded_after_noscript() {
+        static SCRIPT: LuaScript = LuaScript::new("return 1");
+        async fn call(server: &mut ScriptCache) -> Vec<String> {
+            server.commands.clear();
+            let result: i64 = SCRIPT.prepare_invoke().key("ratewatch:test").arg(10u64).invoke_async(server).await.unwrap();
+            assert_eq!(result, 1);
+            std::mem::take(&mut server.commands)
+        }
+        let mut server = ScriptCache::default();
+
+        // The first call loads it, later ones only send the hash
+        assert_eq!(call(&mut server).await, ["EVALSHA", "EVAL"]);
+        assert_eq!(call(&mut server).await, ["EVALSHA"]);
+        assert_eq!(SCRIPT.reloads(), 1);
+
+        // Restarted, so the cache is empty
+        server.scripts.clear();
+        assert_eq!(call(&mut server).await, ["EVALSHA", "EVAL"]);
+        assert_eq!(call(&mut server).await, ["EVALSHA"]);
+        assert_eq!(SCRIPT.reloads(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_lua_script_runs_on_redis() {
+        static SCRIPT: LuaScript = LuaScript::new("return {KEYS[1], ARGV[1]}");
+        let Ok(mut conn) = RedisConnector::open("redis://127.0.0.1:6379").unwrap().get_async_connection().await else {
+            // Redis not available
+            return;
+        };
+
+        let result: (String, u64) = SCRIPT.prepare_invoke().key("ratewatch:lua").arg(7u64).invoke_async(&mut conn).await.unwrap();
+        assert_eq!(result, ("ratewatch:lua".to_string(), 7));
+        assert_eq!(SCRIPT.hash(), redis::Script::new("return {KEYS[1], ARGV[1]}").get_hash());
+    }
+
+    #[test]
+    fn test_topology_from_url_scheme() {
+        assert_eq!(
+            RedisTopology::from_url("redis://127.0.0.1:6379").unwrap(),
+            RedisTopology::Standalone {
+                url: "redis://127.0.0.1:6379".to_string()
+            }
+        );
+
+        assert_eq!(
+            RedisTopology::from_url("redis+cluster://:secret@10.0.0.1:6379,10.0.0.2:6379").unwrap(),
+            RedisTopology::Cluster {
+                nodes: vec![
+                    "redis://:secret@10.0.0.1:6379".to_string(),
+                    "redis://:secret@10.0.0.2:6379".to_string(),
+                ]
+            }
+        );
+
+        match RedisTopology::from_url("redis+sentinel://s1:26379,s2:26379/mymaster").unwrap() {
+            RedisTopology::Sentinel {
+                master_name,
+                sentinels,
+                master_url,
+            } => {
+                assert_eq!(master_name, "mymaster");
+                assert_eq!(sentinels, vec!["redis://s1:26379", "redis://s2:26379"]);
+                assert!(master_url.is_none());
+            }
+            other => panic!("unexpected topology {other:?}"),
+        }
+
+        assert_eq!(
+            RedisTopology::from_url("rediss-cluster://10.0.0.1:6379").unwrap(),
+            RedisTopology::Cluster {
+                nodes: vec!["rediss://10.0.0.1:6379".to_string()]
+            }
+        );
+        assert_eq!(
+            RedisTopology::from_url("redis-sentinel://s1:26379/mymaster").unwrap().name(),
+            "sentinel"
+        );
+
+        assert!(RedisTopology::from_url("redis+sentinel://s1:26379").is_err());
+        assert!(RedisTopology::from_url("redis+cluster://").is_err());
+        assert!(RedisTopology::from_url("memcached://localhost").is_err());
+    }
+
+    #[test]
+    fn test_topology_from_config() {
+        let url = "redis://127.0.0.1:6379";
+        let mut config = RedisConfig::default();
+        assert_eq!(RedisTopology::from_config(url, &config).unwrap().name(), "standalone");
+
+        config.sentinel = Some(RedisSentinelConfig {
+            master_name: "mymaster".to_string(),
+            addresses: vec!["redis://10.0.0.1:26379".to_string()],
+        });
+        assert_eq!(RedisTopology::from_config(url, &config).unwrap().name(), "sentinel");
+
+        config.cluster_nodes = vec!["redis://10.0.0.1:6379".to_string()];
+        assert!(RedisTopology::from_config(url, &config).is_err());
+
+        config.sentinel = None;
+        assert!(RedisTopology::from_config(url, &config).unwrap().is_cluster());
+    }
+
+    #[test]
+    fn test_key_slot_matches_redis() {
+        // Reference values from CLUSTER KEYSLOT
+        assert_eq!(key_slot("foo"), 12182);
+        assert_eq!(key_slot("123456789"), 12739);
+        assert_eq!(key_slot("{user1000}.following"), key_slot("{user1000}.followers"));
+        assert_eq!(key_slot("foo{}{bar}"), crc16(b"foo{}{bar}") % CLUSTER_SLOTS);
+    }
+
+    #[test]
+    fn test_ensure_same_slot() {
+        let tagged = vec!["rate_limit:{user:1}:sliding".to_string(), "rate_limit:{user:1}:burst".to_string()];
+        assert!(ensure_same_slot("sliding_window", &tagged).is_ok());
+
+        let untagged = vec!["rate_limit:foo".to_string(), "rate_limit:bar".to_string()];
+        let err = ensure_same_slot("sliding_window", &untagged).unwrap_err().to_string();
+        assert!(err.contains("different cluster slots"), "{err}");
+    }
+
+    #[test]
+    fn test_readonly_errors_trigger_reconnect() {
+        // A demoted master answers writes with READONLY after a failover
+        assert!(needs_reconnect(&RedisError::from((ErrorKind::ReadOnly, "READONLY"))));
+        assert!(needs_reconnect(&RedisError::from(std::io::Error::from(std::io::ErrorKind::ConnectionReset))));
+        assert!(!needs_reconnect(&RedisError::from((ErrorKind::ResponseError, "ERR wrong number of arguments"))));
+    }
+
+    async fn local_connector() -> Option<RedisConnector> {
+        let connector = RedisConnector::open("redis://127.0.0.1:6379").ok()?;
+        let mut conn = connector.get_async_connection().await.ok()?;
+        redis::cmd("PING").query_async::<_, String>(&mut conn).await.ok()?;
+        Some(connector)
+    }
+
+    #[tokio::test]
+    async fn test_pool_reconnects_after_connection_loss() {
+        let Some(connector) = local_connector().await else {
+            println!("Skipping pool test - Redis not available");
+            return;
+        };
+        let slots = match connector.backend.as_ref() {
+            Backend::Pooled(slots) => slots,
+            #[cfg(feature = "cluster")]
+            Backend::Cluster { .. } => unreachable!("standalone URL builds a pooled backend"),
+        };
+
+        let first = slots[0].get().await.unwrap();
+        assert_eq!(slots[0].get().await.unwrap().generation, first.generation);
+
+        // What a failed command does after Redis restarts
+        slots[0].invalidate(first.generation).await;
+        let mut second = slots[0].get().await.unwrap();
+        assert!(second.generation > first.generation);
+
+        // A late failure on the old connection must not drop the new one
+        slots[0].invalidate(first.generation).await;
+        assert_eq!(slots[0].get().await.unwrap().generation, second.generation);
+
+        let pong: String = redis::cmd("PING").query_async(&mut second.conn).await.unwrap();
+        assert_eq!(pong, "PONG");
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_commands_reuse_pooled_connections() {
+        let Some(connector) = local_connector().await else {
+            println!("Skipping pool reuse test - Redis not available");
+            return;
+        };
+
+        let mut tasks = tokio::task::JoinSet::new();
+        for _ in 0..200 {
+            let connector = connector.clone();
+            tasks.spawn(async move {
+                let mut conn = connector.get_async_connection().await.unwrap();
+                redis::cmd("PING").query_async::<_, String>(&mut conn).await.unwrap()
+            });
+        }
+        while let Some(pong) = tasks.join_next().await {
+            assert_eq!(pong.unwrap(), "PONG");
+        }
+
+        // 201 commands, but no more sockets than the pool holds
+        assert_eq!(connector.connections_opened(), DEFAULT_POOL_SIZE as u64);
+    }
+
+    #[tokio::test]
+    async fn test_scan_keys_returns_every_match() {
+        let Some(connector) = local_connector().await else {
+            println!("Skipping scan test - Redis not available");
+            return;
+        };
+        let mut conn = connector.get_async_connection().await.unwrap();
+        let prefix = format!("scan_test:{}", uuid::Uuid::new_v4());
+
+        // Several SCAN pages' worth, plus keys the pattern must skip
+        let mut expected = Vec::new();
+        let mut pipe = redis::pipe();
+        for i in 0..(SCAN_COUNT * 3 + 17) {
+            let key = format!("{}:match:{:05}", prefix, i);
+            pipe.cmd("SET").arg(&key).arg(i).arg("EX").arg(60).ignore();
+            expected.push(key);
+        }
+        for i in 0..50 {
+            pipe.cmd("SET").arg(format!("{}:other:{}", prefix, i)).arg(i).arg("EX").arg(60).ignore();
+        }
+        let _: () = pipe.query_async(&mut conn).await.unwrap();
+
+        let keys = scan_keys(&mut conn, &format!("{}:match:*", prefix)).await.unwrap();
+        assert_eq!(keys, expected);
+
+        let _: () = redis::cmd("DEL").arg(&keys).query_async(&mut conn).await.unwrap();
+    }
+
+    #[cfg(feature = "cluster")]
+    #[test]
+    fn test_cluster_primaries_from_slots_reply() {
+        let node = |host: &str, port: i64, id: &str| {
+            Value::Bulk(vec![
+                Value::Data(host.as_bytes().to_vec()),
+                Value::Int(port),
+                Value::Data(id.as_bytes().to_vec()),
+            ])
+        };
+        let slots = Value::Bulk(vec![
+            Value::Bulk(vec![Value::Int(0), Value::Int(5460), node("10.0.0.1", 6379, "a"), node("10.0.0.4", 6379, "d")]),
+            Value::Bulk(vec![Value::Int(5461), Value::Int(10922), node("10.0.0.2", 6379, "b")]),
+            // A primary serving two ranges is scanned once
+            Value::Bulk(vec![Value::Int(10923), Value::Int(16000), node("10.0.0.3", 6380, "c")]),
+            Value::Bulk(vec![Value::Int(16001), Value::Int(16383), node("10.0.0.3", 6380, "c")]),
+        ]);
+
+        assert_eq!(
+            cluster_primaries(&slots).unwrap(),
+            vec![
+                ("10.0.0.1".to_string(), 6379),
+                ("10.0.0.2".to_string(), 6379),
+                ("10.0.0.3".to_string(), 6380),
+            ]
+        );
+        assert!(cluster_primaries(&Value::Nil).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_unresponsive_server_times_out() {
+        // Accepts connections but never replies
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let mut held = Vec::new();
+            while let Ok((socket, _)) = listener.accept().await {
+                held.push(socket);
+            }
+        });
+
+        let connector = RedisConnector::open(&format!("redis://{addr}"))
+            .unwrap()
+            .with_timeouts(Duration::from_millis(200), Duration::from_millis(200));
+
+        let started = std::time::Instant::now();
+        let result = async {
+            let mut conn = connector.get_async_connection().await?;
+            redis::cmd("PING").query_async::<_, String>(&mut conn).await
+        }
+        .await;
+
+        let err = result.unwrap_err();
+        assert!(needs_reconnect(&err), "{err}");
+        assert!(err.to_string().contains("timed out"), "{err}");
+        assert!(started.elapsed() < Duration::from_secs(2));
+    }
+
+    /// Per-call connections vs the pool; see docs/BENCHMARKS.md.
+    /// Run with `cargo test --release redis_pool_benchmark -- --ignored --nocapture`
+    #[tokio::test]
+    #[ignore]
+    async fn redis_pool_benchmark() {
+        use redis::AsyncCommands;
+        use std::time::{Duration, Instant};
+
+        const TASKS: usize = 50;
+        const OPS_PER_TASK: usize = 200;
+
+        let Some(pooled) = local_connector().await else {
+            println!("Skipping benchmark - Redis not available");
+            return;
+        };
+        let client = redis::Client::open("redis://127.0.0.1:6379").unwrap();
+
+        async fn run<F, Fut>(label: &str, op: F)
+        where
+            F: Fn(String) -> Fut + Clone + Send + 'static,
+            Fut: std::future::Future<Output = Duration> + Send,
+        {
+            let started = Instant::now();
+            let mut tasks = tokio::task::JoinSet::new();
+            for task in 0..TASKS {
+                let op = op.clone();
+                tasks.spawn(async move {
+                    let mut latencies = Vec::with_capacity(OPS_PER_TASK);
+                    for n in 0..OPS_PER_TASK {
+                        latencies.push(op(format!("bench:pool:{task}:{n}")).await);
+                    }
+                    latencies
+                });
+            }
+
+            let mut latencies = Vec::new();
+            while let Some(result) = tasks.join_next().await {
+                latencies.extend(result.unwrap());
+            }
+            latencies.sort();
+
+            let elapsed = started.elapsed();
+            println!(
+                "{label:>10}: {:>8.0} ops/s  p50 {:>6}us  p99 {:>6}us",
+                latencies.len() as f64 / elapsed.as_secs_f64(),
+                latencies[latencies.len() / 2].as_micros(),
+                latencies[latencies.len() * 99 / 100].as_micros(),
+            );
+        }
+
+        run("per-call", move |key| {
+            let client = client.clone();
+            async move {
+                let started = Instant::now();
+                let mut conn = client.get_async_connection().await.unwrap();
+                let _: () = conn.set_ex(&key, 1, 10).await.unwrap();
+                started.elapsed()
+            }
+        })
+        .await;
+
+        run("pooled", move |key| {
+            let pooled = pooled.clone();
+            async move {
+                let started = Instant::now();
+                let mut conn = pooled.get_async_connection().await.unwrap();
+                let _: () = conn.set_ex(&key, 1, 10).await.unwrap();
+                started.elapsed()
+            }
+        })
+        .await;
+    }
+}
@@ -0,0 +1,282 @@
+//! The canonical view of an incoming request, built once per request.
+//!
+//! `request_context_middleware` runs just inside `correlation_id_middleware`
+//! and stashes a `RequestContext` in the request extensions: the client IP,
+//! the correlation ID, the user agent, the headers and the body size. Later layers read it
+//! with `request_context` instead of parsing the request again, so the IP
+//! allowlist, auth, threat detection, tenant checks, audit and SIEM all see
+//! the same client and the same correlation ID. `auth_middleware` adds the
+//! caller's API key and tenant once the key is known.
+//!
+//! The client IP is the peer address, unless the peer is one of
+//! `server.trusted_proxies`. Then the forwarding headers are believed:
+//! `X-Forwarded-For`, else `Forwarded`, else `X-Real-IP` or
+//! `CF-Connecting-IP`. The chain is read from the nearest hop outwards and
+//! the first hop that isn't a trusted proxy is the client, so a client can't
+//! pick its own address by sending the headers itself. Without a peer
+//! address, as when a router is served without connect info, the headers are
+//! all there is and are believed.
+//!
+//! The IP is kept in canonical form, with IPv4-mapped IPv6 addresses as
+//! IPv4. With `server.ipv6_prefix_length` below 128, IPv6 clients are
+//! tracked by their network rather than their address: rate limit keys,
+//! behavior profiles and bans then cover the whole prefix, so a client
+//! can't rotate through its /64 to start afresh.
+
+use anyhow::Context;
+use axum::{
+    body::HttpBody,
+    extract::{ConnectInfo, Request, State},
+    http::HeaderMap,
+    middleware::Next,
+    response::Response,
+};
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+
+use crate::auth::ApiKeyIdentity;
+use crate::ip_allowlist::{canonical_ip, IpAllowlist};
+use crate::security::threat_analyzer::RequestContext;
+use crate::telemetry::CorrelationId;
+
+/// How the client IP is resolved: the proxies whose forwarding headers are
+/// believed, and how IPv6 clients are grouped
+pub struct ClientIps {
+    trusted_proxies: IpAllowlist,
+    ipv6_prefix_len: u8,
+}
+
+impl Default for ClientIps {
+    fn default() -> Self {
+        Self {
+            trusted_proxies: IpAllowlist::default(),
+            ipv6_prefix_len: 128,
+        }
+    }
+}
+
+impl ClientIps {
+    /// Parse `trusted_proxies`, each an IP address or a CIDR range
+    pub fn new<S: AsRef<str>>(trusted_proxies: &[S]) -> anyhow::Result<Self> {
+        Ok(Self {
+            trusted_proxies: IpAllowlist::new(trusted_proxies).context("Invalid trusted proxy")?,
+            ..Self::default()
+        })
+    }
+
+    /// Track IPv6 clients by their network of this length
+    pub fn with_ipv6_prefix_len(mut self, ipv6_prefix_len: u8) -> Self {
+        self.ipv6_prefix_len = ipv6_prefix_len;
+        self
+    }
+
+    pub fn client_ip(&self, request: &Request) -> Option<IpAddr> {
+        let peer = request
+            .extensions()
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(addr)| canonical_ip(addr.ip()));
+        match peer {
+            Some(peer) if !self.trusted_proxies.contains(peer) => Some(peer),
+            _ => self.forwarded_client(request.headers()).or(peer),
+        }
+    }
+
+    fn forwarded_client(&self, headers: &HeaderMap) -> Option<IpAddr> {
+        let header = |name| headers.get(name).and_then(|value| value.to_str().ok());
+        let hops: Vec<&str> = if let Some(forwarded_for) = header("x-forwarded-for") {
+            forwarded_for.split(',').map(str::trim).collect()
+        } else if let Some(forwarded) = header("forwarded") {
+            forwarded.split(',').filter_map(forwarded_for).collect()
+        } else {
+            return header("x-real-ip")
+                .or_else(|| header("cf-connecting-ip"))
+                .and_then(|ip| ip.trim().parse().ok())
+                .map(canonical_ip);
+        };
+
+        // Hops left of the first untrusted one could be made up by the client
+        let mut client = None;
+        for hop in hops.iter().rev() {
+            let Ok(ip) = hop.parse::<IpAddr>() else {
+                break;
+            };
+            client = Some(canonical_ip(ip));
+            if !self.trusted_proxies.contains(ip) {
+                break;
+            }
+        }
+        client
+    }
+}
+
+/// The address in one `Forwarded` element's `for=` parameter, without
+/// quotes, brackets or port
+fn forwarded_for(element: &str) -> Option<&str> {
+    let value = element.split(';').find_map(|pair| {
+        let (name, value) = pair.trim().split_once('=')?;
+        name.eq_ignore_ascii_case("for").then(|| value.trim().trim_matches('"'))
+    })?;
+    if let Some(v6) = value.strip_prefix('[') {
+        return v6.split(']').next();
+    }
+    // An IPv4 address may carry a port; a bare IPv6 address has several colons
+    match value.split_once(':') {
+        Some((v4, port)) if !port.contains(':') => Some(v4),
+        _ => Some(value),
+    }
+}
+
+/// The client's IP as resolved by `request_context_middleware`
+pub fn client_ip(request: &Request) -> Option<IpAddr> {
+    match request.extensions().get::<RequestContext>() {
+        Some(context) => context.ip_address.parse().ok(),
+        None => ClientIps::default().client_ip(request),
+    }
+}
+
+fn build(request: &Request, client_ips: &ClientIps) -> RequestContext {
+    let ip_address = client_ips.client_ip(request).map_or_else(|| "unknown".to_string(), |ip| ip.to_string());
+    let mut context = RequestContext::new(ip_address, request.uri().path().to_string(), request.method().to_string())
+        .with_ipv6_prefix_len(client_ips.ipv6_prefix_len);
+
+    // Reuse the inbound correlation ID so audit/SIEM events line up with upstream logs
+    if let Some(correlation_id) = request.extensions().get::<CorrelationId>() {
+        context = context.with_correlation_id(correlation_id.0);
+    }
+    for (name, value) in request.headers() {
+        if let Ok(value) = value.to_str() {
+            context = context.with_header(name.to_string(), value.to_string());
+        }
+    }
+    context.user_agent = context.headers.get("user-agent").cloned();
+    // Known for a body with a Content-Length; a chunked one has no size yet
+    context.payload_size = request.body().size_hint().exact();
+    if let Some(identity) = request.extensions().get::<ApiKeyIdentity>() {
+        set_identity(&mut context, identity);
+    }
+    context
+}
+
+fn set_identity(context: &mut RequestContext, identity: &ApiKeyIdentity) {
+    context.api_key_id = Some(identity.key_id.clone());
+    context.tenant_id = identity.tenant_id.map(|tenant_id| tenant_id.to_string());
+}
+
+/// Build the request's `RequestContext` and keep it in the extensions
+pub async fn request_context_middleware(
+    State(client_ips): State<Arc<ClientIps>>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    let context = build(&request, &client_ips);
+    request.extensions_mut().insert(context);
+    next.run(request).await
+}
+
+/// The request's context, or a fresh one if the router was mounted without
+/// `request_context_middleware`
+pub fn request_context(request: &Request) -> RequestContext {
+    request
+        .extensions()
+        .get::<RequestContext>()
+        .cloned()
+        .unwrap_or_else(|| build(request, &ClientIps::default()))
+}
+
+/// Record the caller's identity on the stashed context, once auth knows it
+pub fn record_identity(request: &mut Request, identity: &ApiKeyIdentity) {
+    if let Some(context) = request.extensions_mut().get_mut::<RequestContext>() {
+        set_identity(context, identity);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Body, middleware, routing::get, Extension, Router};
+    use tower::ServiceExt;
+
+    fn request(headers: &[(&str, &str)]) -> Request {
+        let mut builder = Request::builder().uri("/v1/check");
+        for (name, value) in headers {
+            builder = builder.header(*name, *value);
+        }
+        builder.body(Body::empty()).unwrap()
+    }
+
+    fn from_peer(peer: [u8; 4], headers: &[(&str, &str)]) -> Request {
+        let mut request = request(headers);
+        request.extensions_mut().insert(ConnectInfo(SocketAddr::from((peer, 4000))));
+        request
+    }
+
+    #[test]
+    fn test_forwarding_headers_need_a_trusted_peer() {
+        let client_ips = ClientIps::new(&["10.0.0.0/8"]).unwrap();
+        let ip = |request: Request| client_ips.client_ip(&request).map(|ip| ip.to_string());
+
+        // Spoofed by a client connecting directly
+        assert_eq!(ip(from_peer([198, 51, 100, 9], &[("x-forwarded-for", "203.0.113.7")])).as_deref(), Some("198.51.100.9"));
+        assert_eq!(ip(from_peer([198, 51, 100, 9], &[("x-real-ip", "203.0.113.7")])).as_deref(), Some("198.51.100.9"));
+
+        // Through our proxies, the nearest hop they don't run is the client
+        let chain = [("x-forwarded-for", "192.0.2.66, 203.0.113.7, 10.0.0.2")];
+        assert_eq!(ip(from_peer([10, 0, 0, 5], &chain)).as_deref(), Some("203.0.113.7"));
+        let forwarded = [("forwarded", "for=192.0.2.60;proto=http, for=\"[2001:db8::17]:4711\"")];
+        assert_eq!(ip(from_peer([10, 0, 0, 5], &forwarded)).as_deref(), Some("2001:db8::17"));
+        assert_eq!(ip(from_peer([10, 0, 0, 5], &[("forwarded", "for=192.0.2.60:8080")])).as_deref(), Some("192.0.2.60"));
+        assert_eq!(ip(from_peer([10, 0, 0, 5], &[("x-real-ip", "203.0.113.8")])).as_deref(), Some("203.0.113.8"));
+        assert_eq!(ip(from_peer([10, 0, 0, 5], &[])).as_deref(), Some("10.0.0.5"));
+
+        // Without a peer address the headers are all there is
+        assert_eq!(ip(request(&[("x-forwarded-for", "garbage"), ("x-real-ip", "198.51.100.1")])).as_deref(), None);
+        assert_eq!(ip(request(&[("cf-connecting-ip", "2001:db8::1")])).as_deref(), Some("2001:db8::1"));
+        assert_eq!(ip(request(&[])), None);
+    }
+
+    #[test]
+    fn test_ipv6_clients_are_canonical_and_grouped() {
+        let client_ips = ClientIps::default().with_ipv6_prefix_len(64);
+        let mut request = request(&[("x-forwarded-for", "2001:db8::1")]);
+        request
+            .extensions_mut()
+            .insert(ConnectInfo(SocketAddr::from(("2001:DB8:1:2:0:0:0:ABCD".parse::<IpAddr>().unwrap(), 4000))));
+
+        let context = build(&request, &client_ips);
+        assert_eq!(context.ip_address, "2001:db8:1:2::abcd");
+        assert_eq!(context.ip_key(), "2001-db8-1-2--/64");
+        assert_eq!(context.client_network().unwrap().to_string(), "2001:db8:1:2::/64");
+
+        // IPv4 clients are unaffected, however they're written
+        let context = build(&self::request(&[("x-real-ip", "::ffff:203.0.113.7")]), &client_ips);
+        assert_eq!(context.ip_address, "203.0.113.7");
+        assert_eq!(context.ip_key(), "203.0.113.7");
+    }
+
+    #[tokio::test]
+    async fn test_one_correlation_id_per_request() {
+        let app = Router::new()
+            .route(
+                "/v1/check",
+                get(|Extension(context): Extension<RequestContext>| async move {
+                    format!("{} {} {}", context.correlation_id, context.ip_address, context.user_agent.unwrap_or_default())
+                }),
+            )
+            .layer(middleware::from_fn_with_state(
+                Arc::new(ClientIps::default()),
+                request_context_middleware,
+            ))
+            .layer(middleware::from_fn(crate::telemetry::correlation_id_middleware));
+
+        let response = app
+            .oneshot(request(&[("x-forwarded-for", "203.0.113.7"), ("user-agent", "curl/8.4.0")]))
+            .await
+            .unwrap();
+        let correlation_id = response.headers()["x-correlation-id"].to_str().unwrap().to_string();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(
+            String::from_utf8(body.to_vec()).unwrap(),
+            format!("{} 203.0.113.7 curl/8.4.0", correlation_id)
+        );
+    }
+}
@@ -0,0 +1,206 @@
+//! Body size and time limits for every request.
+//!
+//! `server.max_body_bytes` and `server.request_timeout_ms` apply to all
+//! routes. `[[server.route_request_limits]]` raises or lowers either one
+//! under a path prefix, for endpoints that legitimately take large uploads
+//! or long exports; the longest matching prefix wins.
+//!
+//! A body over the limit gets 413, whether its declared length is already
+//! too large or it only turns out to be while being read. A request still
+//! running at its deadline gets 408. Both are problem documents. The body's
+//! declared size is also kept on the request's `RequestContext`, for threat
+//! detection's `payload_size` indicator.
+
+use axum::{
+    body::{Body, HttpBody},
+    extract::{Request, State},
+    http::StatusCode,
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use http_body_util::Limited;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::api_error::ApiError;
+use crate::config::ServerConfig;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Limits {
+    max_body_bytes: usize,
+    timeout: Duration,
+}
+
+/// The limits for each path
+#[derive(Debug, Clone)]
+pub struct RequestLimits {
+    default: Limits,
+    /// Longest prefix first
+    routes: Vec<(String, Limits)>,
+}
+
+impl RequestLimits {
+    pub fn new(max_body_bytes: usize, timeout: Duration) -> Self {
+        Self {
+            default: Limits {
+                max_body_bytes,
+                timeout,
+            },
+            routes: Vec::new(),
+        }
+    }
+
+    pub fn from_config(config: &ServerConfig) -> Self {
+        config.route_request_limits.iter().fold(
+            Self::new(config.max_body_bytes, Duration::from_millis(config.request_timeout_ms)),
+            |limits, route| {
+                limits.with_route(
+                    &route.path,
+                    route.max_body_bytes,
+                    route.request_timeout_ms.map(Duration::from_millis),
+                )
+            },
+        )
+    }
+
+    /// Override the defaults under `path`; `None` keeps the default
+    pub fn with_route(mut self, path: &str, max_body_bytes: Option<usize>, timeout: Option<Duration>) -> Self {
+        let limits = Limits {
+            max_body_bytes: max_body_bytes.unwrap_or(self.default.max_body_bytes),
+            timeout: timeout.unwrap_or(self.default.timeout),
+        };
+        self.routes.push((path.trim_end_matches('/').to_string(), limits));
+        self.routes.sort_by_key(|(prefix, _)| std::cmp::Reverse(prefix.len()));
+        self
+    }
+
+    fn for_path(&self, path: &str) -> Limits {
+        self.routes
+            .iter()
+            .find(|(prefix, _)| {
+                path.strip_prefix(prefix.as_str())
+                    .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+            })
+            .map_or(self.default, |(_, limits)| *limits)
+    }
+}
+
+fn payload_too_large() -> Response {
+    ApiError::payload_too_large("Request body too large").into_response()
+}
+
+/// Apply the path's body limit and deadline
+pub async fn request_limits_middleware(
+    State(limits): State<Arc<RequestLimits>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let limits = limits.for_path(request.uri().path());
+    if request.body().size_hint().lower() > limits.max_body_bytes as u64 {
+        return payload_too_large();
+    }
+
+    let request = request.map(|body| Body::new(Limited::new(body, limits.max_body_bytes)));
+    match tokio::time::timeout(limits.timeout, next.run(request)).await {
+        // An extractor that ran into the limit answers 413 in plain text
+        Ok(response)
+            if response.status() == StatusCode::PAYLOAD_TOO_LARGE
+                && response.extensions().get::<ApiError>().is_none() =>
+        {
+            payload_too_large()
+        }
+        Ok(response) => response,
+        Err(_) => ApiError::new(StatusCode::REQUEST_TIMEOUT, "REQUEST_TIMEOUT", "Request timed out").into_response(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{
+        body::Bytes,
+        middleware,
+        routing::{get, post},
+        Router,
+    };
+    use tower::ServiceExt;
+
+    fn router(limits: RequestLimits) -> Router {
+        Router::new()
+            .route("/v1/check", post(|body: Bytes| async move { body.len().to_string() }))
+            .route("/v1/uploads/audit", post(|body: Bytes| async move { body.len().to_string() }))
+            .route(
+                "/v1/export",
+                get(|| async {
+                    tokio::time::sleep(Duration::from_millis(100)).await;
+                    "done"
+                }),
+            )
+            .layer(middleware::from_fn_with_state(Arc::new(limits), request_limits_middleware))
+            .layer(axum::extract::DefaultBodyLimit::disable())
+    }
+
+    fn post_body(uri: &str, body: Body) -> Request {
+        Request::builder().method("POST").uri(uri).body(body).unwrap()
+    }
+
+    #[test]
+    fn test_longest_prefix_wins() {
+        let limits = RequestLimits::new(1024, Duration::from_secs(30))
+            .with_route("/v1/uploads", Some(4096), None)
+            .with_route("/v1/uploads/audit/", None, Some(Duration::from_secs(60)));
+
+        assert_eq!(limits.for_path("/v1/check").max_body_bytes, 1024);
+        assert_eq!(limits.for_path("/v1/uploads").max_body_bytes, 4096);
+        assert_eq!(limits.for_path("/v1/uploads/tenants").max_body_bytes, 4096);
+        // Whole segments only
+        assert_eq!(limits.for_path("/v1/uploadsX").max_body_bytes, 1024);
+        let audit = limits.for_path("/v1/uploads/audit/2024");
+        assert_eq!((audit.max_body_bytes, audit.timeout), (1024, Duration::from_secs(60)));
+    }
+
+    #[tokio::test]
+    async fn test_route_override_allows_larger_body() {
+        let app = router(RequestLimits::new(64, Duration::from_secs(5)).with_route("/v1/uploads", Some(4096), None));
+
+        let response = app.clone().oneshot(post_body("/v1/check", Body::from(vec![b'x'; 100]))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+
+        let response = app
+            .oneshot(post_body("/v1/uploads/audit", Body::from(vec![b'x'; 3000])))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_undeclared_body_is_limited_while_read() {
+        let chunks = futures_util::stream::iter(
+            (0..10).map(|_| Ok::<_, std::io::Error>(Bytes::from_static(&[b'x'; 16]))),
+        );
+        let response = router(RequestLimits::new(64, Duration::from_secs(5)))
+            .oneshot(post_body("/v1/check", Body::from_stream(chunks)))
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["code"], "PAYLOAD_TOO_LARGE");
+    }
+
+    #[tokio::test]
+    async fn test_route_override_extends_deadline() {
+        let limits = RequestLimits::new(64, Duration::from_millis(20));
+        let request = || Request::builder().uri("/v1/export").body(Body::empty()).unwrap();
+
+        let response = router(limits.clone()).oneshot(request()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::REQUEST_TIMEOUT);
+
+        let response = router(limits.with_route("/v1/export", None, Some(Duration::from_secs(5))))
+            .oneshot(request())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+}
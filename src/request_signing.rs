@@ -0,0 +1,281 @@
+//! HMAC-signed requests with replay protection.
+//!
+//! With `[security.request_signing] enabled`, every authenticated HTTP
+//! request must carry, next to its Bearer API key:
+//!
+//! - `X-Timestamp`: Unix time in seconds, within `max_clock_skew_seconds` of
+//!   the server's clock
+//! - `X-Nonce`: a value the client never reuses, such as a UUID
+//! - `X-Signature`: hex HMAC-SHA256 over `METHOD\nPATH\nTIMESTAMP\nNONCE\n`
+//!   followed by the raw body, where `PATH` includes any query string
+//!
+//! The HMAC key is the API key's signing secret, which
+//! `ApiKeyValidator::signing_secret` derives from the key and the server's
+//! `API_KEY_SECRET` (`ratewatch signing-secret` prints it). It never travels
+//! with requests, so someone who captures a request can't sign a new one.
+//!
+//! Nonces are claimed in Redis once a signature checks out and kept for twice
+//! the skew window, so a replay is refused until its timestamp is too old to
+//! be accepted anyway. If Redis can't be reached signed requests are refused
+//! with 503 rather than let a replay through.
+
+use axum::{
+    http::{HeaderMap, Method, StatusCode},
+    response::{IntoResponse, Response},
+};
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use std::time::Duration;
+
+use crate::api_error::ApiError;
+use crate::config::RequestSigningConfig;
+use crate::redis_backend::RedisConnector;
+
+type HmacSha256 = Hmac<Sha256>;
+
+pub const SIGNATURE_HEADER: &str = "x-signature";
+pub const TIMESTAMP_HEADER: &str = "x-timestamp";
+pub const NONCE_HEADER: &str = "x-nonce";
+
+const MAX_NONCE_LEN: usize = 128;
+
+/// Why a request's signature was refused
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SignatureError {
+    /// One of the signature headers is absent
+    Missing,
+    MalformedTimestamp,
+    /// Outside the clock-skew window
+    StaleTimestamp,
+    MalformedNonce,
+    InvalidSignature,
+    /// The nonce was already used within the window
+    ReplayedNonce,
+    /// Seen nonces couldn't be checked
+    Unavailable,
+}
+
+impl SignatureError {
+    pub fn status(&self) -> StatusCode {
+        match self {
+            SignatureError::Unavailable => StatusCode::SERVICE_UNAVAILABLE,
+            _ => StatusCode::UNAUTHORIZED,
+        }
+    }
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            SignatureError::Missing => "SIGNATURE_REQUIRED",
+            SignatureError::MalformedTimestamp => "INVALID_TIMESTAMP",
+            SignatureError::StaleTimestamp => "STALE_TIMESTAMP",
+            SignatureError::MalformedNonce => "INVALID_NONCE",
+            SignatureError::InvalidSignature => "INVALID_SIGNATURE",
+            SignatureError::ReplayedNonce => "REPLAYED_NONCE",
+            SignatureError::Unavailable => "SIGNATURE_CHECK_UNAVAILABLE",
+        }
+    }
+
+    pub fn message(&self) -> &'static str {
+        match self {
+            SignatureError::Missing => "Signed requests require X-Signature, X-Timestamp and X-Nonce headers",
+            SignatureError::MalformedTimestamp => "X-Timestamp must be Unix time in seconds",
+            SignatureError::StaleTimestamp => "X-Timestamp is outside the allowed clock skew",
+            SignatureError::MalformedNonce => "X-Nonce must be 1 to 128 characters",
+            SignatureError::InvalidSignature => "X-Signature does not match the request",
+            SignatureError::ReplayedNonce => "X-Nonce has already been used",
+            SignatureError::Unavailable => "Request signatures cannot be checked right now",
+        }
+    }
+}
+
+impl IntoResponse for SignatureError {
+    fn into_response(self) -> Response {
+        ApiError::new(self.status(), self.code(), self.message()).into_response()
+    }
+}
+
+/// Checks signature headers and claims nonces
+pub struct RequestVerifier {
+    redis: RedisConnector,
+    max_clock_skew: Duration,
+}
+
+impl RequestVerifier {
+    pub fn new(redis: RedisConnector) -> Self {
+        Self {
+            redis,
+            max_clock_skew: Duration::from_secs(300),
+        }
+    }
+
+    /// The verifier for `[security.request_signing]`, or `None` if signing isn't required
+    pub fn from_config(redis: RedisConnector, config: &RequestSigningConfig) -> Option<Self> {
+        config
+            .enabled
+            .then(|| Self::new(redis).with_max_clock_skew(Duration::from_secs(config.max_clock_skew_seconds)))
+    }
+
+    pub fn with_max_clock_skew(mut self, max_clock_skew: Duration) -> Self {
+        self.max_clock_skew = max_clock_skew;
+        self
+    }
+
+    /// Check a request against the caller's signing secret, then claim its nonce
+    pub async fn verify(
+        &self,
+        signing_secret: &str,
+        key_id: &str,
+        method: &Method,
+        path_and_query: &str,
+        headers: &HeaderMap,
+        body: &[u8],
+    ) -> Result<(), SignatureError> {
+        let header = |name: &str| {
+            headers
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .ok_or(SignatureError::Missing)
+        };
+        let signature = header(SIGNATURE_HEADER)?;
+        let timestamp = header(TIMESTAMP_HEADER)?;
+        let nonce = header(NONCE_HEADER)?;
+
+        let sent_at: i64 = timestamp.parse().map_err(|_| SignatureError::MalformedTimestamp)?;
+        if Utc::now().timestamp().abs_diff(sent_at) > self.max_clock_skew.as_secs() {
+            return Err(SignatureError::StaleTimestamp);
+        }
+        if nonce.is_empty() || nonce.len() > MAX_NONCE_LEN {
+            return Err(SignatureError::MalformedNonce);
+        }
+
+        let expected = sign(signing_secret, method.as_str(), path_and_query, timestamp, nonce, body);
+        if !constant_time_eq::constant_time_eq(signature.to_ascii_lowercase().as_bytes(), expected.as_bytes()) {
+            return Err(SignatureError::InvalidSignature);
+        }
+
+        // Only after the signature checks out, so forged requests can't use up nonces
+        self.claim_nonce(key_id, nonce).await
+    }
+
+    async fn claim_nonce(&self, key_id: &str, nonce: &str) -> Result<(), SignatureError> {
+        let key = format!("request_nonce:{}:{}", key_id, nonce);
+        let ttl = self.max_clock_skew.as_secs() * 2;
+
+        let claimed: redis::RedisResult<Option<String>> = async {
+            let mut conn = self.redis.get_async_connection().await?;
+            redis::cmd("SET")
+                .arg(&key)
+                .arg(1)
+                .arg("NX")
+                .arg("EX")
+                .arg(ttl)
+                .query_async(&mut conn)
+                .await
+        }
+        .await;
+
+        match claimed {
+            Ok(Some(_)) => Ok(()),
+            Ok(None) => Err(SignatureError::ReplayedNonce),
+            Err(e) => {
+                tracing::warn!("Failed to record request nonce: {}", e);
+                Err(SignatureError::Unavailable)
+            }
+        }
+    }
+}
+
+/// Hex HMAC-SHA256 a client sends as `X-Signature`
+pub fn sign(signing_secret: &str, method: &str, path_and_query: &str, timestamp: &str, nonce: &str, body: &[u8]) -> String {
+    let mut mac = HmacSha256::new_from_slice(signing_secret.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(format!("{}\n{}\n{}\n{}\n", method, path_and_query, timestamp, nonce).as_bytes());
+    mac.update(body);
+    hex::encode(mac.finalize().into_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::{auth_middleware, ApiKeyValidator};
+    use axum::{body::Body, routing::post, Router};
+    use std::sync::Arc;
+    use tower::ServiceExt;
+
+    const API_KEY: &str = "rw_1234567890abcdef1234567890abcdef";
+
+    fn app(redis_url: &str) -> (Router, Arc<ApiKeyValidator>) {
+        let verifier = RequestVerifier::new(RedisConnector::open(redis_url).unwrap());
+        let validator = Arc::new(ApiKeyValidator::new("test_secret".to_string()).with_request_verifier(Arc::new(verifier)));
+        let app = Router::new()
+            .route("/v1/check", post(|body: String| async move { body }))
+            .layer(axum::middleware::from_fn_with_state(validator.clone(), auth_middleware));
+        (app, validator)
+    }
+
+    fn signed_request(validator: &ApiKeyValidator, timestamp: i64, nonce: &str) -> axum::http::Request<Body> {
+        let body = r#"{"key":"user:1","limit":10,"window":60}"#;
+        let timestamp = timestamp.to_string();
+        let signature = sign(&validator.signing_secret(API_KEY), "POST", "/v1/check?dry_run=1", &timestamp, nonce, body.as_bytes());
+        axum::http::Request::builder()
+            .method("POST")
+            .uri("/v1/check?dry_run=1")
+            .header("authorization", format!("Bearer {API_KEY}"))
+            .header(SIGNATURE_HEADER, signature)
+            .header(TIMESTAMP_HEADER, timestamp)
+            .header(NONCE_HEADER, nonce)
+            .body(Body::from(body))
+            .unwrap()
+    }
+
+    async fn error_code(response: Response) -> String {
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        serde_json::from_slice::<serde_json::Value>(&body).unwrap()["code"].as_str().unwrap().to_string()
+    }
+
+    #[tokio::test]
+    async fn test_signed_request_is_accepted_once() {
+        let (app, validator) = app("redis://127.0.0.1:6379");
+        let nonce = uuid::Uuid::new_v4().to_string();
+
+        let response = app.clone().oneshot(signed_request(&validator, Utc::now().timestamp(), &nonce)).await.unwrap();
+        if response.status() == StatusCode::SERVICE_UNAVAILABLE {
+            println!("Skipping request signing test - Redis not available");
+            return;
+        }
+        assert_eq!(response.status(), StatusCode::OK);
+        // The handler still sees the body the signature covered
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert!(body.starts_with(br#"{"key":"user:1""#));
+
+        let response = app.oneshot(signed_request(&validator, Utc::now().timestamp(), &nonce)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(error_code(response).await, "REPLAYED_NONCE");
+    }
+
+    #[tokio::test]
+    async fn test_stale_or_tampered_requests_are_refused() {
+        // Each is refused before a nonce would be claimed, so Redis is never asked
+        let (app, validator) = app("redis://127.0.0.1:1");
+
+        let stale = signed_request(&validator, Utc::now().timestamp() - 301, "nonce-1");
+        let response = app.clone().oneshot(stale).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(error_code(response).await, "STALE_TIMESTAMP");
+
+        let mut tampered = signed_request(&validator, Utc::now().timestamp(), "nonce-2");
+        *tampered.uri_mut() = "/v1/check?dry_run=0".parse().unwrap();
+        let response = app.clone().oneshot(tampered).await.unwrap();
+        assert_eq!(error_code(response).await, "INVALID_SIGNATURE");
+
+        let unsigned = axum::http::Request::builder()
+            .method("POST")
+            .uri("/v1/check")
+            .header("authorization", format!("Bearer {API_KEY}"))
+            .body(Body::empty())
+            .unwrap();
+        let response = app.oneshot(unsigned).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(error_code(response).await, "SIGNATURE_REQUIRED");
+    }
+}
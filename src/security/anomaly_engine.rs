@@ -0,0 +1,356 @@
+//! Online anomaly scoring behind `threat_detection.ml_engine`.
+//!
+//! Each client (its API key, or its IP when unauthenticated) gets a small
+//! model in Redis: its recent requests, and for every configured feature an
+//! exponentially weighted mean and variance. A request's features are
+//! compared with that baseline as a z-score; any feature beyond
+//! `z_threshold` makes the request anomalous. Clients are only scored once
+//! they've made `min_samples` requests, and anomalous samples aren't folded
+//! into the baseline, so a burst can't teach the model that it's normal.
+
+use crate::config::{AnomalyFeature, MlEngineConfig};
+use crate::redis_backend::RedisConnector;
+use crate::security::threat_analyzer::{AnalyzerHealth, RequestContext, ThreatAnalyzer, ThreatScore};
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, Utc};
+use redis::AsyncCommands;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use tracing::{info, warn};
+
+/// How long an idle client's model is kept
+const MODEL_TTL_SECONDS: u64 = 86400 * 7;
+
+/// Floor on the baseline's standard deviation, so a client whose feature
+/// never moved doesn't turn the smallest change into an infinite z-score
+const MIN_STD_DEV: f64 = 0.5;
+
+pub struct AnomalyEngine {
+    redis_client: RedisConnector,
+    config: MlEngineConfig,
+    enabled: bool,
+}
+
+/// One client's baseline, as stored in Redis
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct AnomalyModel {
+    samples: u64,
+    baselines: HashMap<AnomalyFeature, FeatureBaseline>,
+    recent: VecDeque<RecentRequest>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct FeatureBaseline {
+    mean: f64,
+    variance: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct RecentRequest {
+    timestamp: DateTime<Utc>,
+    endpoint: String,
+    user_agent: Option<String>,
+}
+
+/// A feature's value on one request and how far it is from the baseline
+#[derive(Debug, Clone, Serialize)]
+struct FeatureDeviation {
+    value: f64,
+    mean: f64,
+    z_score: f64,
+}
+
+impl AnomalyModel {
+    /// Remember `context` and return its feature values
+    fn record(&mut self, context: &RequestContext, features: &[AnomalyFeature], history_size: usize) -> HashMap<AnomalyFeature, f64> {
+        self.recent.push_back(RecentRequest {
+            timestamp: context.timestamp,
+            endpoint: context.endpoint.clone(),
+            user_agent: context.user_agent.clone(),
+        });
+        while self.recent.len() > history_size {
+            self.recent.pop_front();
+        }
+
+        features
+            .iter()
+            .map(|feature| {
+                let value = match feature {
+                    AnomalyFeature::RequestRate => {
+                        let since = context.timestamp - Duration::minutes(1);
+                        self.recent.iter().filter(|request| request.timestamp > since).count() as f64
+                    }
+                    AnomalyFeature::EndpointEntropy => {
+                        entropy(self.recent.iter().map(|request| request.endpoint.as_str()))
+                    }
+                    AnomalyFeature::ErrorRate => {
+                        let previous = &context.previous_requests;
+                        if previous.is_empty() {
+                            0.0
+                        } else {
+                            previous.iter().filter(|request| request.status_code >= 400).count() as f64
+                                / previous.len() as f64
+                        }
+                    }
+                    AnomalyFeature::UserAgentEntropy => entropy(
+                        self.recent
+                            .iter()
+                            .map(|request| request.user_agent.as_deref().unwrap_or("")),
+                    ),
+                };
+                (*feature, value)
+            })
+            .collect()
+    }
+
+    /// How far each value is from its baseline
+    fn deviations(&self, values: &HashMap<AnomalyFeature, f64>) -> HashMap<AnomalyFeature, FeatureDeviation> {
+        values
+            .iter()
+            .map(|(feature, value)| {
+                let baseline = self.baselines.get(feature).cloned().unwrap_or_default();
+                let std_dev = baseline.variance.sqrt().max(MIN_STD_DEV);
+                let deviation = FeatureDeviation {
+                    value: *value,
+                    mean: baseline.mean,
+                    z_score: (value - baseline.mean).abs() / std_dev,
+                };
+                (*feature, deviation)
+            })
+            .collect()
+    }
+
+    /// Fold `values` into the baselines. The first sample seeds the mean.
+    fn learn(&mut self, values: &HashMap<AnomalyFeature, f64>, smoothing: f64) {
+        for (feature, value) in values {
+            let baseline = self.baselines.entry(*feature).or_default();
+            if self.samples == 0 {
+                baseline.mean = *value;
+                continue;
+            }
+            // Exponentially weighted mean and variance
+            let delta = value - baseline.mean;
+            baseline.mean += smoothing * delta;
+            baseline.variance = (1.0 - smoothing) * (baseline.variance + smoothing * delta * delta);
+        }
+        self.samples += 1;
+    }
+}
+
+/// Shannon entropy, in bits, of the values' distribution
+fn entropy<'a>(values: impl Iterator<Item = &'a str>) -> f64 {
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    let mut total = 0usize;
+    for value in values {
+        *counts.entry(value).or_insert(0) += 1;
+        total += 1;
+    }
+    if total == 0 {
+        return 0.0;
+    }
+    counts
+        .values()
+        .map(|&count| {
+            let p = count as f64 / total as f64;
+            -p * p.log2()
+        })
+        .sum()
+}
+
+impl AnomalyEngine {
+    pub fn new(redis_client: RedisConnector, config: MlEngineConfig) -> Self {
+        Self {
+            redis_client,
+            config,
+            enabled: true,
+        }
+    }
+
+    fn model_key(context: &RequestContext) -> String {
+        match &context.api_key_id {
+            Some(key_id) => format!("anomaly:model:key:{}", key_id),
+            None => format!("anomaly:model:ip:{}", context.ip_key()),
+        }
+    }
+
+    async fn load_model(&self, key: &str) -> Result<AnomalyModel> {
+        let mut conn = self.redis_client.get_async_connection().await?;
+        let data: Option<String> = conn.get(key).await?;
+        Ok(match data.map(|data| serde_json::from_str::<AnomalyModel>(&data)) {
+            Some(Ok(model)) => model,
+            Some(Err(e)) => {
+                warn!(key, error = %e, "Discarding unreadable anomaly model");
+                AnomalyModel::default()
+            }
+            None => AnomalyModel::default(),
+        })
+    }
+
+    async fn save_model(&self, key: &str, model: &AnomalyModel) -> Result<()> {
+        let mut conn = self.redis_client.get_async_connection().await?;
+        let _: () = conn.set_ex(key, serde_json::to_string(model)?, MODEL_TTL_SECONDS).await?;
+        Ok(())
+    }
+
+    /// Score `context` against `model` and update the model with it
+    fn score(&self, model: &mut AnomalyModel, context: &RequestContext) -> ThreatScore {
+        let values = model.record(context, &self.config.features, self.config.history_size);
+
+        if model.samples < self.config.min_samples {
+            model.learn(&values, self.config.smoothing);
+            return ThreatScore::new("ml_anomaly".to_string(), 0.0, 0.0)
+                .with_reason(format!(
+                    "Anomaly model warming up ({}/{} samples)",
+                    model.samples, self.config.min_samples
+                ))
+                .with_metadata("samples".to_string(), serde_json::json!(model.samples));
+        }
+
+        let deviations = model.deviations(&values);
+        let mut anomalous: Vec<(&AnomalyFeature, &FeatureDeviation)> = deviations
+            .iter()
+            .filter(|(_, deviation)| deviation.z_score >= self.config.z_threshold)
+            .collect();
+        anomalous.sort_by(|a, b| b.1.z_score.partial_cmp(&a.1.z_score).unwrap_or(std::cmp::Ordering::Equal));
+
+        let features = serde_json::to_value(&deviations).unwrap_or_default();
+        let samples = model.samples;
+
+        // A fully trusted baseline is twice the warmup
+        let confidence = (samples as f64 / (2 * self.config.min_samples) as f64).clamp(0.5, 0.9);
+
+        let score = match anomalous.first() {
+            None => {
+                model.learn(&values, self.config.smoothing);
+                ThreatScore::new("ml_anomaly".to_string(), 0.0, confidence)
+                    .with_reason("Anomaly model: within baseline".to_string())
+            }
+            Some((_, worst)) => {
+                let score = (worst.z_score / (2.0 * self.config.z_threshold)).min(1.0);
+                let reasons = anomalous
+                    .iter()
+                    .map(|(feature, deviation)| {
+                        format!(
+                            "Anomalous {:?}: {:.2} vs baseline {:.2} (z={:.1})",
+                            feature, deviation.value, deviation.mean, deviation.z_score
+                        )
+                    })
+                    .collect();
+                ThreatScore::new("ml_anomaly".to_string(), score, confidence).with_reasons(reasons)
+            }
+        };
+
+        score
+            .with_metadata("features".to_string(), features)
+            .with_metadata("samples".to_string(), serde_json::json!(samples))
+    }
+}
+
+#[async_trait]
+impl ThreatAnalyzer for AnomalyEngine {
+    async fn analyze(&self, context: &RequestContext) -> Result<ThreatScore> {
+        let key = Self::model_key(context);
+        let mut model = self.load_model(&key).await?;
+        let score = self.score(&mut model, context);
+        if !context.evaluate_only {
+            self.save_model(&key, &model).await?;
+        }
+        Ok(score)
+    }
+
+    fn analyzer_id(&self) -> &str {
+        "ml_anomaly"
+    }
+
+    fn name(&self) -> &str {
+        "ML Anomaly Engine"
+    }
+
+    fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    async fn update_config(&mut self, config: serde_json::Value) -> Result<()> {
+        self.config = serde_json::from_value(config)?;
+        info!("Anomaly engine configuration updated");
+        Ok(())
+    }
+
+    async fn health_check(&self) -> Result<AnalyzerHealth> {
+        Ok(AnalyzerHealth::of_redis(self.analyzer_id(), &self.redis_client).await)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn engine(config: MlEngineConfig) -> AnomalyEngine {
+        AnomalyEngine::new(RedisConnector::open("redis://127.0.0.1:6379").unwrap(), config)
+    }
+
+    fn request_at(endpoint: &str, timestamp: DateTime<Utc>) -> RequestContext {
+        let mut context = RequestContext::new("198.51.100.7".to_string(), endpoint.to_string(), "POST".to_string())
+            .with_user_agent("client/1.0".to_string());
+        context.timestamp = timestamp;
+        context
+    }
+
+    #[test]
+    fn test_entropy() {
+        assert_eq!(entropy(["a", "a", "a"].into_iter()), 0.0);
+        assert_eq!(entropy(["a", "b"].into_iter()), 1.0);
+        assert_eq!(entropy(["a", "b", "c", "d"].into_iter()), 2.0);
+        assert_eq!(entropy(std::iter::empty()), 0.0);
+    }
+
+    #[test]
+    fn test_no_score_during_warmup() {
+        let engine = engine(MlEngineConfig {
+            min_samples: 10,
+            ..MlEngineConfig::default()
+        });
+        let mut model = AnomalyModel::default();
+        let start = Utc::now() - Duration::hours(1);
+
+        for i in 0..10 {
+            let score = engine.score(&mut model, &request_at("/v1/check", start + Duration::seconds(30 * i)));
+            assert_eq!(score.score, 0.0);
+            assert_eq!(score.confidence, 0.0);
+        }
+        assert_eq!(model.samples, 10);
+    }
+
+    #[test]
+    fn test_burst_is_anomalous_and_not_learned() {
+        let engine = engine(MlEngineConfig {
+            min_samples: 20,
+            ..MlEngineConfig::default()
+        });
+        let mut model = AnomalyModel::default();
+        let start = Utc::now() - Duration::hours(1);
+
+        // Two requests a minute to one endpoint builds a quiet baseline
+        for i in 0..60 {
+            engine.score(&mut model, &request_at("/v1/check", start + Duration::seconds(30 * i)));
+        }
+        let last = start + Duration::seconds(30 * 60);
+        assert_eq!(engine.score(&mut model, &request_at("/v1/check", last)).score, 0.0);
+
+        // Then a scan: many endpoints within seconds
+        let samples_before = model.samples;
+        let (burst_index, flagged) = (0..30)
+            .find_map(|i| {
+                let context = request_at(&format!("/admin/{}", i), last + Duration::milliseconds(1000 + 100 * i));
+                let score = engine.score(&mut model, &context);
+                (score.score > 0.0).then_some((i as u64, score))
+            })
+            .expect("burst should be flagged");
+
+        assert!(flagged.reasons.iter().any(|reason| reason.contains("RequestRate")));
+        assert!(flagged.metadata.contains_key("features"));
+        // Only the requests before the flagged one were learned
+        assert_eq!(model.samples, samples_before + burst_index);
+    }
+}
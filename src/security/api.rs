@@ -1,4 +1,11 @@
-use crate::security::{ThreatDetector, threat_detector::{ThreatDetectorConfig, ThreatAnalysisResult}};
+use crate::api_error::ApiError;
+use crate::security::{
+    behavior_patterns::PatternDefinition,
+    threat_analyzer::{HealthStatus, RequestContext},
+    threat_detector::{overall_health, ThreatAnalysisResult, ThreatDetectorConfig},
+    tls_fingerprint::normalize_ja3,
+    ThreatDetector,
+};
 use axum::{
     extract::{Query, State},
     http::StatusCode,
@@ -12,15 +19,73 @@ use std::sync::Arc;
 use tracing::{error, info};
 
 #[derive(Debug, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 pub struct ThreatConfigUpdate {
     pub enabled: Option<bool>,
     pub threat_threshold: Option<f64>,
     pub confidence_threshold: Option<f64>,
     pub auto_response_enabled: Option<bool>,
     pub max_analysis_time_ms: Option<u64>,
+    /// Budget for a whole analysis; analyzers still running when it's spent
+    /// are left out of the result
+    pub max_total_analysis_time_ms: Option<u64>,
+    /// `inline` holds each request for analysis; `async` lets it through
+    /// and analyzes it in the background
+    pub mode: Option<crate::config::ThreatAnalysisMode>,
+    /// `fail_open` lets a request through when its analysis fails;
+    /// `fail_closed` refuses it
+    pub failure_mode: Option<crate::config::SecurityFailureMode>,
+    /// Replaces the weights of the analyzers listed; others keep theirs.
+    /// A weight of 0 stops an analyzer affecting the combined score.
+    pub analyzer_weights: Option<std::collections::HashMap<String, f64>>,
+}
+
+#[derive(Debug, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct ThreatFeedbackRequest {
+    /// Correlation ID of the analyzed request, as returned in `x-correlation-id`
+    pub correlation_id: uuid::Uuid,
+    pub was_false_positive: bool,
+}
+
+/// A request to score as if it had arrived
+#[derive(Debug, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct ThreatEvaluationRequest {
+    pub ip_address: String,
+    pub endpoint: String,
+    /// Defaults to GET
+    pub method: Option<String>,
+    /// Taken from the `user-agent` header if omitted
+    pub user_agent: Option<String>,
+    pub api_key_id: Option<String>,
+    pub tenant_id: Option<String>,
+    /// Lowercase header names, as the threat detection middleware sees them
+    #[serde(default)]
+    pub headers: std::collections::HashMap<String, String>,
+    /// JA3 hash; taken from the configured fingerprint header if omitted
+    pub tls_fingerprint: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct BehaviorPatternsUpdate {
+    /// Replaces the whole custom set; an empty list removes every pattern
+    pub patterns: Vec<PatternDefinition>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct DeadLetterReplayRequest {
+    /// Only this provider's batches; all providers if omitted
+    pub provider: Option<String>,
+    /// Oldest batches to replay per provider (default 100)
+    pub max_batches: Option<usize>,
+}
+
+#[derive(Debug, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::IntoParams))]
+#[cfg_attr(feature = "openapi", into_params(parameter_in = Query))]
 pub struct ThreatStatsQuery {
     pub include_analyzers: Option<bool>,
     pub include_health: Option<bool>,
@@ -30,17 +95,43 @@ pub fn create_security_router(threat_detector: Arc<ThreatDetector>) -> Router {
     Router::new()
         .route("/v1/security/threat-detection/status", get(get_threat_detection_status))
         .route("/v1/security/threat-detection/config", get(get_threat_detection_config))
-        .route("/v1/security/threat-detection/config", put(update_threat_detection_config))
         .route("/v1/security/threat-detection/statistics", get(get_threat_detection_statistics))
         .route("/v1/security/threat-detection/health", get(get_threat_detection_health))
         .route("/v1/security/threat-detection/enable", post(enable_threat_detection))
         .route("/v1/security/threat-detection/disable", post(disable_threat_detection))
+        .route("/v1/security/threat-detection/analyze", post(evaluate_request))
+        .route("/v1/security/patterns", get(get_behavior_patterns))
+        .with_state(threat_detector)
+}
+
+/// Operator routes that change detection for every tenant or expose other
+/// tenants' security events; mount behind `admin_auth_middleware`
+pub fn create_security_admin_router(threat_detector: Arc<ThreatDetector>) -> Router {
+    Router::new()
+        .route("/v1/security/threat-detection/config", put(update_threat_detection_config))
+        .route("/v1/security/patterns", put(update_behavior_patterns))
+        .route("/v1/security/feedback", post(submit_threat_feedback))
+        .route("/v1/security/siem/deadletter", get(get_siem_dead_letters))
+        .route("/v1/security/siem/deadletter/replay", post(replay_siem_dead_letters))
         .with_state(threat_detector)
 }
 
+#[cfg_attr(
+    feature = "openapi",
+    utoipa::path(
+        get,
+        path = "/v1/security/threat-detection/status",
+        tag = "security",
+        responses(
+            (status = 200, description = "Threat detection status", body = Object),
+            (status = 401, description = "Missing or invalid API key"),
+        ),
+        security(("api_key" = [])),
+    )
+)]
 async fn get_threat_detection_status(
     State(threat_detector): State<Arc<ThreatDetector>>,
-) -> Result<Json<Value>, StatusCode> {
+) -> Result<Json<Value>, ApiError> {
     let config = threat_detector.get_config().await;
     let statistics = threat_detector.get_statistics().await;
 
@@ -65,9 +156,22 @@ async fn get_threat_detection_status(
     Ok(Json(response))
 }
 
+#[cfg_attr(
+    feature = "openapi",
+    utoipa::path(
+        get,
+        path = "/v1/security/threat-detection/config",
+        tag = "security",
+        responses(
+            (status = 200, description = "Threat detection configuration", body = Object),
+            (status = 401, description = "Missing or invalid API key"),
+        ),
+        security(("api_key" = [])),
+    )
+)]
 async fn get_threat_detection_config(
     State(threat_detector): State<Arc<ThreatDetector>>,
-) -> Result<Json<Value>, StatusCode> {
+) -> Result<Json<Value>, ApiError> {
     let config = threat_detector.get_config().await;
 
     let response = json!({
@@ -77,16 +181,36 @@ async fn get_threat_detection_config(
         "auto_response_enabled": config.auto_response_enabled,
         "analyzer_weights": config.analyzer_weights,
         "max_analysis_time_ms": config.max_analysis_time_ms,
+        "max_total_analysis_time_ms": config.max_total_analysis_time_ms,
+        "mode": config.mode,
+        "failure_mode": config.failure_mode,
         "timestamp": chrono::Utc::now().to_rfc3339()
     });
 
     Ok(Json(response))
 }
 
+#[cfg_attr(
+    feature = "openapi",
+    utoipa::path(
+        put,
+        path = "/v1/security/threat-detection/config",
+        tag = "security",
+        request_body = ThreatConfigUpdate,
+        responses(
+            (status = 200, description = "Updated configuration", body = Object),
+            (status = 400, description = "Invalid configuration", body = crate::api_error::ProblemDetails),
+            (status = 500, description = "Internal error", body = crate::api_error::ProblemDetails),
+            (status = 401, description = "Missing or invalid API key"),
+            (status = 403, description = "Not an admin key"),
+        ),
+        security(("api_key" = [])),
+    )
+)]
 async fn update_threat_detection_config(
     State(threat_detector): State<Arc<ThreatDetector>>,
     Json(update): Json<ThreatConfigUpdate>,
-) -> Result<Json<Value>, StatusCode> {
+) -> Result<Json<Value>, ApiError> {
     let mut current_config = threat_detector.get_config().await;
 
     // Apply updates
@@ -105,6 +229,21 @@ async fn update_threat_detection_config(
     if let Some(max_time) = update.max_analysis_time_ms {
         current_config.max_analysis_time_ms = max_time;
     }
+    if let Some(budget) = update.max_total_analysis_time_ms {
+        current_config.max_total_analysis_time_ms = budget;
+    }
+    if let Some(mode) = update.mode {
+        current_config.mode = mode;
+    }
+    if let Some(failure_mode) = update.failure_mode {
+        current_config.failure_mode = failure_mode;
+    }
+    if let Some(weights) = update.analyzer_weights {
+        if let Err(e) = crate::config::validate_analyzer_weights(&weights) {
+            return Err(ApiError::bad_request("INVALID_CONFIG", format!("{e:#}")));
+        }
+        current_config.analyzer_weights.extend(weights);
+    }
 
     match threat_detector.update_config(current_config.clone()).await {
         Ok(_) => {
@@ -118,7 +257,11 @@ async fn update_threat_detection_config(
                     "threat_threshold": current_config.threat_threshold,
                     "confidence_threshold": current_config.confidence_threshold,
                     "auto_response_enabled": current_config.auto_response_enabled,
-                    "max_analysis_time_ms": current_config.max_analysis_time_ms
+                    "analyzer_weights": current_config.analyzer_weights,
+                    "max_analysis_time_ms": current_config.max_analysis_time_ms,
+                    "max_total_analysis_time_ms": current_config.max_total_analysis_time_ms,
+                    "mode": current_config.mode,
+                    "failure_mode": current_config.failure_mode
                 },
                 "timestamp": chrono::Utc::now().to_rfc3339()
             });
@@ -127,15 +270,29 @@ async fn update_threat_detection_config(
         }
         Err(e) => {
             error!(error = %e, "Failed to update threat detection configuration");
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
+            Err(ApiError::internal("Failed to update threat detection configuration"))
         }
     }
 }
 
+#[cfg_attr(
+    feature = "openapi",
+    utoipa::path(
+        get,
+        path = "/v1/security/threat-detection/statistics",
+        tag = "security",
+        params(ThreatStatsQuery),
+        responses(
+            (status = 200, description = "Threat detection statistics", body = Object),
+            (status = 401, description = "Missing or invalid API key"),
+        ),
+        security(("api_key" = [])),
+    )
+)]
 async fn get_threat_detection_statistics(
     State(threat_detector): State<Arc<ThreatDetector>>,
     Query(query): Query<ThreatStatsQuery>,
-) -> Result<Json<Value>, StatusCode> {
+) -> Result<Json<Value>, ApiError> {
     let statistics = threat_detector.get_statistics().await;
     let mut response = json!({
         "statistics": {
@@ -144,18 +301,24 @@ async fn get_threat_detection_statistics(
             "total_analyses": statistics.total_analyses,
             "threats_detected": statistics.threats_detected,
             "actions_taken": statistics.actions_taken,
-            "average_analysis_time_ms": statistics.average_analysis_time_ms
+            "average_analysis_time_ms": statistics.average_analysis_time_ms,
+            "analyzer_errors": statistics.analyzer_errors
         },
         "timestamp": chrono::Utc::now().to_rfc3339()
     });
 
+    if query.include_analyzers.unwrap_or(false) {
+        response["analyzers"] = json!(statistics.analyzers);
+    }
+
     // Include analyzer health if requested
     if query.include_health.unwrap_or(false) {
         match threat_detector.health_check().await {
             Ok(health_statuses) => {
                 response["health"] = json!({
-                    "analyzers": health_statuses,
-                    "overall_healthy": health_statuses.iter().all(|status| status.healthy)
+                    "status": overall_health(&health_statuses),
+                    "overall_healthy": health_statuses.iter().all(|status| status.healthy),
+                    "analyzers": health_statuses
                 });
             }
             Err(e) => {
@@ -170,38 +333,69 @@ async fn get_threat_detection_statistics(
     Ok(Json(response))
 }
 
+#[cfg_attr(
+    feature = "openapi",
+    utoipa::path(
+        get,
+        path = "/v1/security/threat-detection/health",
+        tag = "security",
+        responses(
+            (status = 200, description = "Analyzers healthy or degraded", body = Object),
+            (status = 503, description = "An enabled analyzer is unhealthy", body = Object),
+            (status = 500, description = "Internal error", body = crate::api_error::ProblemDetails),
+            (status = 401, description = "Missing or invalid API key"),
+        ),
+        security(("api_key" = [])),
+    )
+)]
 async fn get_threat_detection_health(
     State(threat_detector): State<Arc<ThreatDetector>>,
-) -> Result<Json<Value>, StatusCode> {
+) -> Result<(StatusCode, Json<Value>), ApiError> {
     match threat_detector.health_check().await {
         Ok(health_statuses) => {
-            let overall_healthy = health_statuses.iter().all(|status| status.healthy);
+            let status = overall_health(&health_statuses);
             let healthy_count = health_statuses.iter().filter(|status| status.healthy).count();
-            
+
             let response = json!({
-                "overall_healthy": overall_healthy,
+                "status": status,
+                "overall_healthy": status == HealthStatus::Healthy,
                 "healthy_analyzers": healthy_count,
                 "total_analyzers": health_statuses.len(),
                 "analyzers": health_statuses,
                 "timestamp": chrono::Utc::now().to_rfc3339()
             });
 
-            if overall_healthy {
-                Ok(Json(response))
+            // Degraded analyzers still score requests, so only unhealthy ones fail the check
+            let code = if status == HealthStatus::Unhealthy {
+                StatusCode::SERVICE_UNAVAILABLE
             } else {
-                Err(StatusCode::SERVICE_UNAVAILABLE)
-            }
+                StatusCode::OK
+            };
+            Ok((code, Json(response)))
         }
         Err(e) => {
             error!(error = %e, "Threat detection health check failed");
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
+            Err(ApiError::internal("Threat detection health check failed"))
         }
     }
 }
 
+#[cfg_attr(
+    feature = "openapi",
+    utoipa::path(
+        post,
+        path = "/v1/security/threat-detection/enable",
+        tag = "security",
+        responses(
+            (status = 200, description = "Threat detection enabled", body = Object),
+            (status = 401, description = "Missing or invalid API key"),
+        ),
+        security(("api_key" = [])),
+    )
+)]
 async fn enable_threat_detection(
     State(threat_detector): State<Arc<ThreatDetector>>,
-) -> Result<Json<Value>, StatusCode> {
+) -> Result<Json<Value>, ApiError> {
     threat_detector.set_enabled(true).await;
     
     let response = json!({
@@ -215,9 +409,22 @@ async fn enable_threat_detection(
     Ok(Json(response))
 }
 
+#[cfg_attr(
+    feature = "openapi",
+    utoipa::path(
+        post,
+        path = "/v1/security/threat-detection/disable",
+        tag = "security",
+        responses(
+            (status = 200, description = "Threat detection disabled", body = Object),
+            (status = 401, description = "Missing or invalid API key"),
+        ),
+        security(("api_key" = [])),
+    )
+)]
 async fn disable_threat_detection(
     State(threat_detector): State<Arc<ThreatDetector>>,
-) -> Result<Json<Value>, StatusCode> {
+) -> Result<Json<Value>, ApiError> {
     threat_detector.set_enabled(false).await;
     
     let response = json!({
@@ -231,12 +438,290 @@ async fn disable_threat_detection(
     Ok(Json(response))
 }
 
+#[cfg_attr(
+    feature = "openapi",
+    utoipa::path(
+        post,
+        path = "/v1/security/threat-detection/analyze",
+        tag = "security",
+        request_body = ThreatEvaluationRequest,
+        responses(
+            (status = 200, description = "Scores and the actions that would have been taken", body = Object),
+            (status = 400, description = "Invalid IP address or TLS fingerprint", body = crate::api_error::ProblemDetails),
+            (status = 500, description = "Internal error", body = crate::api_error::ProblemDetails),
+            (status = 401, description = "Missing or invalid API key"),
+        ),
+        security(("api_key" = [])),
+    )
+)]
+async fn evaluate_request(
+    State(threat_detector): State<Arc<ThreatDetector>>,
+    Json(request): Json<ThreatEvaluationRequest>,
+) -> Result<Json<ThreatAnalysisResult>, ApiError> {
+    if request.ip_address.parse::<std::net::IpAddr>().is_err() {
+        return Err(ApiError::bad_request(
+            "INVALID_IP_ADDRESS",
+            format!("'{}' is not an IP address", request.ip_address),
+        ));
+    }
+
+    let mut context = RequestContext::new(
+        request.ip_address,
+        request.endpoint,
+        request.method.unwrap_or_else(|| "GET".to_string()),
+    )
+    .evaluate_only();
+
+    let fingerprint = match request.tls_fingerprint {
+        Some(fingerprint) => Some(normalize_ja3(&fingerprint).ok_or_else(|| {
+            ApiError::bad_request("INVALID_TLS_FINGERPRINT", "tls_fingerprint must be a JA3 hash")
+        })?),
+        None => threat_detector
+            .tls_fingerprint_header()
+            .and_then(|header| request.headers.get(header))
+            .and_then(|value| normalize_ja3(value)),
+    };
+    if let Some(fingerprint) = fingerprint {
+        context = context.with_tls_fingerprint(fingerprint);
+    }
+    if let Some(user_agent) = request.user_agent.or_else(|| request.headers.get("user-agent").cloned()) {
+        context = context.with_user_agent(user_agent);
+    }
+    if let Some(api_key_id) = request.api_key_id {
+        context = context.with_api_key(api_key_id);
+    }
+    if let Some(tenant_id) = request.tenant_id {
+        context = context.with_tenant_id(tenant_id);
+    }
+    context.headers = request.headers;
+    if let Some(geoip) = threat_detector.geoip() {
+        geoip.enrich(&mut context);
+    }
+
+    match threat_detector.analyze_request(&context).await {
+        Ok(result) => Ok(Json(result)),
+        Err(e) => {
+            error!(error = %e, "Threat evaluation failed");
+            Err(ApiError::internal("Threat evaluation failed"))
+        }
+    }
+}
+
+#[cfg_attr(
+    feature = "openapi",
+    utoipa::path(
+        get,
+        path = "/v1/security/patterns",
+        tag = "security",
+        responses(
+            (status = 200, description = "Custom behavior patterns and how they combine with the built-in checks", body = Object),
+            (status = 503, description = "Behavior patterns are not enabled", body = crate::api_error::ProblemDetails),
+            (status = 401, description = "Missing or invalid API key"),
+        ),
+        security(("api_key" = [])),
+    )
+)]
+async fn get_behavior_patterns(
+    State(threat_detector): State<Arc<ThreatDetector>>,
+) -> Result<Json<Value>, ApiError> {
+    let patterns = threat_detector
+        .behavior_patterns()
+        .ok_or_else(|| ApiError::not_enabled("Behavior patterns are not enabled"))?;
+
+    Ok(Json(json!({
+        "mode": patterns.mode(),
+        "patterns": patterns.definitions(),
+        "timestamp": chrono::Utc::now().to_rfc3339()
+    })))
+}
+
+#[cfg_attr(
+    feature = "openapi",
+    utoipa::path(
+        put,
+        path = "/v1/security/patterns",
+        tag = "security",
+        request_body = BehaviorPatternsUpdate,
+        responses(
+            (status = 200, description = "Pattern set replaced", body = Object),
+            (status = 422, description = "A pattern is invalid; the active set is unchanged", body = crate::api_error::ProblemDetails),
+            (status = 503, description = "Behavior patterns are not enabled", body = crate::api_error::ProblemDetails),
+            (status = 401, description = "Missing or invalid API key"),
+            (status = 403, description = "API key is not an admin key"),
+        ),
+        security(("api_key" = [])),
+    )
+)]
+async fn update_behavior_patterns(
+    State(threat_detector): State<Arc<ThreatDetector>>,
+    Json(update): Json<BehaviorPatternsUpdate>,
+) -> Result<Json<Value>, ApiError> {
+    let patterns = threat_detector
+        .behavior_patterns()
+        .ok_or_else(|| ApiError::not_enabled("Behavior patterns are not enabled"))?;
+    let count = update.patterns.len();
+
+    match patterns.replace(update.patterns) {
+        Ok(()) => {
+            info!(count, "Behavior patterns replaced");
+            Ok(Json(json!({
+                "success": true,
+                "mode": patterns.mode(),
+                "patterns": patterns.definitions(),
+                "timestamp": chrono::Utc::now().to_rfc3339()
+            })))
+        }
+        Err(e) => Err(ApiError::unprocessable("INVALID_PATTERN", format!("{e:#}"))),
+    }
+}
+
+#[cfg_attr(
+    feature = "openapi",
+    utoipa::path(
+        post,
+        path = "/v1/security/feedback",
+        tag = "security",
+        request_body = ThreatFeedbackRequest,
+        responses(
+            (status = 200, description = "Feedback recorded and pattern weights adjusted", body = Object),
+            (status = 404, description = "No flagged analysis for this correlation ID", body = crate::api_error::ProblemDetails),
+            (status = 503, description = "Feedback is not enabled", body = crate::api_error::ProblemDetails),
+            (status = 500, description = "Internal error", body = crate::api_error::ProblemDetails),
+            (status = 401, description = "Missing or invalid API key"),
+            (status = 403, description = "API key is not an admin key"),
+        ),
+        security(("api_key" = [])),
+    )
+)]
+async fn submit_threat_feedback(
+    State(threat_detector): State<Arc<ThreatDetector>>,
+    Json(feedback): Json<ThreatFeedbackRequest>,
+) -> Result<Json<Value>, ApiError> {
+    let store = threat_detector
+        .feedback()
+        .ok_or_else(|| ApiError::not_enabled("Threat feedback is not enabled"))?;
+
+    match store.submit(feedback.correlation_id, feedback.was_false_positive).await {
+        Ok(Some(outcome)) => {
+            info!(
+                correlation_id = %feedback.correlation_id,
+                was_false_positive = feedback.was_false_positive,
+                applied = outcome.applied,
+                "Threat feedback recorded"
+            );
+
+            Ok(Json(json!({
+                "success": true,
+                "applied": outcome.applied,
+                "correlation_id": outcome.record.correlation_id,
+                "was_false_positive": outcome.record.was_false_positive,
+                "false_positives": outcome.false_positives,
+                "weight_multipliers": outcome.weight_multipliers,
+                "timestamp": chrono::Utc::now().to_rfc3339()
+            })))
+        }
+        Ok(None) => Err(ApiError::not_found(format!(
+            "No flagged analysis for correlation ID {}",
+            feedback.correlation_id
+        ))),
+        Err(e) => {
+            error!(error = %e, "Failed to record threat feedback");
+            Err(ApiError::internal("Failed to record threat feedback"))
+        }
+    }
+}
+
+#[cfg_attr(
+    feature = "openapi",
+    utoipa::path(
+        get,
+        path = "/v1/security/siem/deadletter",
+        tag = "security",
+        responses(
+            (status = 200, description = "Dead-lettered batches and events per SIEM provider", body = Object),
+            (status = 503, description = "SIEM integration is not enabled", body = crate::api_error::ProblemDetails),
+            (status = 500, description = "Internal error", body = crate::api_error::ProblemDetails),
+            (status = 401, description = "Missing or invalid API key"),
+            (status = 403, description = "API key is not an admin key"),
+        ),
+        security(("api_key" = [])),
+    )
+)]
+async fn get_siem_dead_letters(
+    State(threat_detector): State<Arc<ThreatDetector>>,
+) -> Result<Json<Value>, ApiError> {
+    let siem = threat_detector
+        .siem()
+        .ok_or_else(|| ApiError::not_enabled("SIEM integration is not enabled"))?;
+
+    match siem.dead_letter_counts().await {
+        Ok(providers) => Ok(Json(json!({
+            "providers": providers,
+            "timestamp": chrono::Utc::now().to_rfc3339()
+        }))),
+        Err(e) => {
+            error!(error = %e, "Failed to read SIEM dead-letter");
+            Err(ApiError::internal("Failed to read SIEM dead-letter"))
+        }
+    }
+}
+
+#[cfg_attr(
+    feature = "openapi",
+    utoipa::path(
+        post,
+        path = "/v1/security/siem/deadletter/replay",
+        tag = "security",
+        request_body = DeadLetterReplayRequest,
+        responses(
+            (status = 200, description = "Batches handed back to the providers that failed them", body = Object),
+            (status = 400, description = "Unknown provider", body = crate::api_error::ProblemDetails),
+            (status = 503, description = "SIEM integration is not enabled", body = crate::api_error::ProblemDetails),
+            (status = 500, description = "Internal error", body = crate::api_error::ProblemDetails),
+            (status = 401, description = "Missing or invalid API key"),
+            (status = 403, description = "API key is not an admin key"),
+        ),
+        security(("api_key" = [])),
+    )
+)]
+async fn replay_siem_dead_letters(
+    State(threat_detector): State<Arc<ThreatDetector>>,
+    request: Option<Json<DeadLetterReplayRequest>>,
+) -> Result<Json<Value>, ApiError> {
+    let siem = threat_detector
+        .siem()
+        .ok_or_else(|| ApiError::not_enabled("SIEM integration is not enabled"))?;
+    let Json(request) = request.unwrap_or_default();
+    if let Some(provider) = request.provider.as_ref().filter(|provider| !siem.has_provider(provider)) {
+        return Err(ApiError::bad_request(
+            "UNKNOWN_PROVIDER",
+            format!("No SIEM provider named '{}'", provider),
+        ));
+    }
+
+    match siem
+        .replay_dead_letters(request.provider.as_deref(), request.max_batches.unwrap_or(100))
+        .await
+    {
+        Ok((batches, events)) => Ok(Json(json!({
+            "success": true,
+            "replayed_batches": batches,
+            "replayed_events": events,
+            "timestamp": chrono::Utc::now().to_rfc3339()
+        }))),
+        Err(e) => {
+            error!(error = %e, "Failed to replay SIEM dead-letter");
+            Err(ApiError::internal("Failed to replay SIEM dead-letter"))
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::security::{
         response_engine::ResponseEngine,
-        threat_analyzer::{ThreatAnalyzer, ThreatScore, RequestContext},
+        threat_analyzer::{AnalyzerHealth, ThreatAnalyzer, ThreatScore, RequestContext},
     };
     use async_trait::async_trait;
     use axum::{
@@ -270,6 +755,285 @@ mod tests {
         }
     }
 
+    /// Reports whatever health it's given
+    struct HealthReporter(HealthStatus);
+
+    #[async_trait]
+    impl ThreatAnalyzer for HealthReporter {
+        async fn analyze(&self, _context: &RequestContext) -> anyhow::Result<ThreatScore> {
+            Ok(ThreatScore::new("reporter".to_string(), 0.0, 0.8))
+        }
+
+        fn analyzer_id(&self) -> &str {
+            "reporter"
+        }
+
+        fn name(&self) -> &str {
+            "Health Reporter"
+        }
+
+        fn is_enabled(&self) -> bool {
+            true
+        }
+
+        async fn update_config(&mut self, _config: serde_json::Value) -> anyhow::Result<()> {
+            Ok(())
+        }
+
+        async fn health_check(&self) -> anyhow::Result<AnalyzerHealth> {
+            let health = AnalyzerHealth::new("reporter".to_string());
+            Ok(match self.0 {
+                HealthStatus::Healthy => health,
+                HealthStatus::Degraded => health.degraded("Slow upstream".to_string()),
+                HealthStatus::Unhealthy => health.unhealthy("Redis unavailable".to_string()),
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_health_endpoint_reflects_analyzers() {
+        for (reported, expected_code) in [
+            (HealthStatus::Healthy, StatusCode::OK),
+            (HealthStatus::Degraded, StatusCode::OK),
+            (HealthStatus::Unhealthy, StatusCode::SERVICE_UNAVAILABLE),
+        ] {
+            let analyzers: Vec<Box<dyn ThreatAnalyzer>> =
+                vec![Box::new(MockThreatAnalyzer), Box::new(HealthReporter(reported))];
+            let response_engine = Arc::new(ResponseEngine::new(Default::default()));
+            let app = create_security_router(Arc::new(ThreatDetector::new(analyzers, response_engine, None)));
+
+            let response = app
+                .oneshot(
+                    Request::builder()
+                        .uri("/v1/security/threat-detection/health")
+                        .body(Body::empty())
+                        .unwrap(),
+                )
+                .await
+                .unwrap();
+            assert_eq!(response.status(), expected_code);
+
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+            let body: Value = serde_json::from_slice(&body).unwrap();
+            assert_eq!(body["status"], json!(reported));
+            assert_eq!(body["overall_healthy"], reported == HealthStatus::Healthy);
+            assert_eq!(body["analyzers"][1]["status"], json!(reported));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_evaluate_request_reports_would_be_actions() {
+        let analyzers: Vec<Box<dyn ThreatAnalyzer>> = vec![Box::new(MockThreatAnalyzer)];
+        let response_engine = Arc::new(ResponseEngine::new(Default::default()));
+        let threat_detector = Arc::new(ThreatDetector::new(analyzers, response_engine, None));
+        threat_detector
+            .update_config(ThreatDetectorConfig {
+                threat_threshold: 0.4,
+                ..threat_detector.get_config().await
+            })
+            .await
+            .unwrap();
+        let app = create_security_router(threat_detector.clone());
+
+        let evaluate = |body: Value| {
+            Request::builder()
+                .method("POST")
+                .uri("/v1/security/threat-detection/analyze")
+                .header("content-type", "application/json")
+                .body(Body::from(body.to_string()))
+                .unwrap()
+        };
+
+        let response = app
+            .clone()
+            .oneshot(evaluate(json!({ "ip_address": "192.0.2.7", "endpoint": "/v1/check", "method": "POST" })))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["overall_score"]["score"], json!(0.5));
+        assert_eq!(body["individual_scores"][0]["analyzer_id"], "mock");
+        assert_eq!(body["threat_detected"], true);
+        assert_eq!(body["actions_taken"], json!(["RejectRequest"]));
+        assert_eq!(threat_detector.get_statistics().await.total_analyses, 0);
+
+        let response = app
+            .oneshot(evaluate(json!({ "ip_address": "not-an-ip", "endpoint": "/v1/check" })))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["code"], "INVALID_IP_ADDRESS");
+    }
+
+    #[tokio::test]
+    async fn test_patterns_are_replaced_only_when_valid() {
+        use crate::config::BehaviorPatternMode;
+        use crate::security::behavior_patterns::BehaviorPatterns;
+
+        let analyzers: Vec<Box<dyn ThreatAnalyzer>> = vec![Box::new(MockThreatAnalyzer)];
+        let response_engine = Arc::new(ResponseEngine::new(Default::default()));
+        let patterns = Arc::new(BehaviorPatterns::new(BehaviorPatternMode::Merge, Vec::new()).unwrap());
+        let threat_detector = Arc::new(
+            ThreatDetector::new(analyzers, response_engine, None).with_behavior_patterns(patterns.clone()),
+        );
+        let app = create_security_router(threat_detector.clone()).merge(create_security_admin_router(threat_detector));
+        let put = |patterns: Value| {
+            Request::builder()
+                .method("PUT")
+                .uri("/v1/security/patterns")
+                .header("content-type", "application/json")
+                .body(Body::from(json!({ "patterns": patterns }).to_string()))
+                .unwrap()
+        };
+
+        let response = app
+            .clone()
+            .oneshot(put(json!([{
+                "id": "env_probe",
+                "risk_score": 0.8,
+                "indicators": [{ "indicator": "endpoint", "comparison": "contains", "value": ".env" }]
+            }])))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(patterns.definitions()[0].id, "env_probe");
+
+        let response = app
+            .clone()
+            .oneshot(put(json!([{
+                "id": "rate_regex",
+                "risk_score": 0.8,
+                "indicators": [{ "indicator": "request_rate", "comparison": "regex", "value": "1+" }]
+            }])))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["code"], "INVALID_PATTERN");
+        assert!(body["detail"].as_str().unwrap().contains("rate_regex"));
+        assert_eq!(patterns.definitions()[0].id, "env_probe");
+
+        let response = app
+            .oneshot(Request::builder().uri("/v1/security/patterns").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["mode"], "merge");
+        assert_eq!(body["patterns"][0]["indicators"][0]["value"], ".env");
+    }
+
+    const ADMIN_KEY: &str = "rw_admin0000000000000000000000000000";
+    const USER_KEY: &str = "rw_1234567890abcdef1234567890abcdef";
+
+    /// Both routers behind their middleware, as `create_secure_router` mounts them
+    fn mounted(threat_detector: Arc<ThreatDetector>) -> Router {
+        use crate::auth::{admin_auth_middleware, auth_middleware, ApiKeyValidator};
+        use axum::middleware;
+
+        let validator = Arc::new(ApiKeyValidator::new("test_secret".to_string()).with_admin_keys([ADMIN_KEY]));
+        create_security_router(threat_detector.clone())
+            .layer(middleware::from_fn_with_state(validator.clone(), auth_middleware))
+            .merge(
+                create_security_admin_router(threat_detector)
+                    .layer(middleware::from_fn_with_state(validator, admin_auth_middleware)),
+            )
+    }
+
+    fn with_key(method: &str, uri: &str, key: &str, body: Value) -> Request<Body> {
+        Request::builder()
+            .method(method)
+            .uri(uri)
+            .header("authorization", format!("Bearer {key}"))
+            .header("content-type", "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_operator_changes_require_admin_key() {
+        use crate::config::{BehaviorPatternMode, SecurityFailureMode, ThreatAnalysisMode};
+        use crate::security::behavior_patterns::BehaviorPatterns;
+
+        let analyzers: Vec<Box<dyn ThreatAnalyzer>> = vec![Box::new(MockThreatAnalyzer)];
+        let response_engine = Arc::new(ResponseEngine::new(Default::default()));
+        let patterns = Arc::new(BehaviorPatterns::new(BehaviorPatternMode::Merge, Vec::new()).unwrap());
+        let threat_detector = Arc::new(
+            ThreatDetector::new(analyzers, response_engine, None)
+                .with_behavior_patterns(patterns)
+                .with_failure_mode(SecurityFailureMode::FailClosed),
+        );
+        let app = mounted(threat_detector.clone());
+        let update = json!({ "patterns": [] });
+
+        let response = app.clone().oneshot(with_key("GET", "/v1/security/patterns", USER_KEY, Value::Null)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = app
+            .clone()
+            .oneshot(with_key("PUT", "/v1/security/patterns", USER_KEY, update.clone()))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+        let response = app
+            .clone()
+            .oneshot(with_key("PUT", "/v1/security/patterns", ADMIN_KEY, update))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        // Weights apply to every tenant's verdicts
+        let config_uri = "/v1/security/threat-detection/config";
+        let weights = json!({ "analyzer_weights": { "mock": 0.0 } });
+        let response = app.clone().oneshot(with_key("GET", config_uri, USER_KEY, Value::Null)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let response = app.clone().oneshot(with_key("PUT", config_uri, USER_KEY, weights.clone())).await.unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        let response = app.clone().oneshot(with_key("PUT", config_uri, ADMIN_KEY, weights)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        // Failing open would let every tenant's requests through unanalyzed
+        let fail_open = json!({ "failure_mode": "fail_open" });
+        let response = app.clone().oneshot(with_key("PUT", config_uri, USER_KEY, fail_open)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        assert_eq!(threat_detector.get_config().await.failure_mode, SecurityFailureMode::FailClosed);
+
+        // As would analyzing off the request path or with no time to do it
+        for update in [json!({ "mode": "async" }), json!({ "max_total_analysis_time_ms": 1 })] {
+            let response = app.clone().oneshot(with_key("PUT", config_uri, USER_KEY, update)).await.unwrap();
+            assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        }
+        let config = threat_detector.get_config().await;
+        assert_eq!(config.mode, ThreatAnalysisMode::Inline);
+        assert_ne!(config.max_total_analysis_time_ms, 1);
+
+        // Otherwise a client could clear its own detections
+        let feedback = json!({ "correlation_id": uuid::Uuid::new_v4(), "was_false_positive": true });
+        let response = app
+            .clone()
+            .oneshot(with_key("POST", "/v1/security/feedback", USER_KEY, feedback))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+        // Dead-lettered events belong to every tenant
+        for (method, uri) in [("GET", "/v1/security/siem/deadletter"), ("POST", "/v1/security/siem/deadletter/replay")] {
+            let response = app.clone().oneshot(with_key(method, uri, USER_KEY, json!({}))).await.unwrap();
+            assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        }
+        // No SIEM configured, but the admin key gets that far
+        let response = app
+            .oneshot(with_key("GET", "/v1/security/siem/deadletter", ADMIN_KEY, Value::Null))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    }
+
     #[tokio::test]
     async fn test_threat_detection_status_endpoint() {
         let analyzers: Vec<Box<dyn ThreatAnalyzer>> = vec![Box::new(MockThreatAnalyzer)];
@@ -328,4 +1092,77 @@ mod tests {
 
         assert_eq!(response.status(), StatusCode::OK);
     }
+
+    fn feedback_request(correlation_id: uuid::Uuid) -> Request<Body> {
+        Request::builder()
+            .method("POST")
+            .uri("/v1/security/feedback")
+            .header("content-type", "application/json")
+            .body(Body::from(
+                json!({ "correlation_id": correlation_id, "was_false_positive": true }).to_string(),
+            ))
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_feedback_without_store_is_unavailable() {
+        let analyzers: Vec<Box<dyn ThreatAnalyzer>> = vec![Box::new(MockThreatAnalyzer)];
+        let response_engine = Arc::new(ResponseEngine::new(Default::default()));
+        let threat_detector = Arc::new(ThreatDetector::new(analyzers, response_engine, None));
+
+        let response = create_security_admin_router(threat_detector)
+            .oneshot(feedback_request(uuid::Uuid::new_v4()))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["code"], "NOT_ENABLED");
+    }
+
+    #[tokio::test]
+    async fn test_feedback_decays_flagged_pattern_weights() {
+        use crate::security::feedback::{FeedbackStore, FlaggedAnalysis, FALSE_POSITIVE_DECAY};
+
+        let redis = crate::redis_backend::RedisConnector::open("redis://127.0.0.1:6379").unwrap();
+        if redis.get_async_connection().await.is_err() {
+            println!("Skipping feedback endpoint test - Redis not available");
+            return;
+        }
+        let store = FeedbackStore::new(redis).with_key_prefix(format!("test:feedback:{}", uuid::Uuid::new_v4()));
+        let analyzers: Vec<Box<dyn ThreatAnalyzer>> = vec![Box::new(MockThreatAnalyzer)];
+        let response_engine = Arc::new(ResponseEngine::new(Default::default()));
+        let threat_detector = Arc::new(
+            ThreatDetector::new(analyzers, response_engine, None).with_feedback(Arc::new(store.clone())),
+        );
+        let app = create_security_admin_router(threat_detector);
+
+        let unknown = app.clone().oneshot(feedback_request(uuid::Uuid::new_v4())).await.unwrap();
+        assert_eq!(unknown.status(), StatusCode::NOT_FOUND);
+        let bytes = axum::body::to_bytes(unknown.into_body(), usize::MAX).await.unwrap();
+        let body: Value = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(body["code"], "NOT_FOUND");
+
+        let correlation_id = uuid::Uuid::new_v4();
+        store
+            .record_analysis(
+                correlation_id,
+                &FlaggedAnalysis {
+                    ip_address: "203.0.113.9".to_string(),
+                    patterns: vec!["RapidRequests".to_string()],
+                    score: 0.8,
+                },
+            )
+            .await
+            .unwrap();
+
+        let response = app.oneshot(feedback_request(correlation_id)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body: Value = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(body["applied"], true);
+        assert_eq!(body["false_positives"], 1);
+        assert_eq!(body["weight_multipliers"]["RapidRequests"], FALSE_POSITIVE_DECAY);
+        assert_eq!(store.false_positive_count().await.unwrap(), 1);
+    }
 }
\ No newline at end of file
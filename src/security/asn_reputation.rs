@@ -0,0 +1,427 @@
+//! Reputation by autonomous system.
+//!
+//! `AsnReputationAnalyzer` resolves the request IP to its ASN and scores the
+//! network rather than the address: ASNs on the configured high-risk list
+//! (bulletproof hosters and the like) score highest, and hosting/cloud
+//! networks score above residential ones, since browsers rarely live in a
+//! datacenter. The ASN and organization go into the score's metadata so SIEM
+//! events carry them. IPs whose ASN isn't known score as neutral.
+
+use crate::config::AsnReputationConfig;
+use crate::security::geoip::is_public;
+use crate::security::threat_analyzer::{RequestContext, ThreatAnalyzer, ThreatScore};
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use ipnet::IpNet;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::net::IpAddr;
+use std::sync::Mutex;
+use tracing::{debug, info};
+
+/// Organization names that mark a hosting or cloud network
+const DATACENTER_KEYWORDS: &[&str] = &[
+    "hosting", "host", "cloud", "datacenter", "data center", "server", "vps", "colo",
+    "amazon", "aws", "google", "microsoft", "azure", "digitalocean", "linode", "akamai",
+    "ovh", "hetzner", "vultr", "choopa", "leaseweb", "contabo", "scaleway", "oracle",
+];
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AsnInfo {
+    pub number: u32,
+    pub organization: Option<String>,
+}
+
+/// A database that maps IPs to ASNs; `Ok(None)` for IPs it doesn't cover
+pub trait AsnDatabase: Send + Sync {
+    fn lookup(&self, ip: IpAddr) -> Result<Option<AsnInfo>>;
+}
+
+/// A MaxMind GeoLite2/GeoIP2 ASN `.mmdb` file
+#[cfg(feature = "geoip")]
+pub struct MaxMindAsnDatabase(maxminddb::Reader<Vec<u8>>);
+
+#[cfg(feature = "geoip")]
+impl MaxMindAsnDatabase {
+    pub fn open(path: &str) -> Result<Self> {
+        let reader = maxminddb::Reader::open_readfile(path)
+            .with_context(|| format!("Failed to open ASN database: {}", path))?;
+        Ok(Self(reader))
+    }
+}
+
+#[cfg(feature = "geoip")]
+impl AsnDatabase for MaxMindAsnDatabase {
+    fn lookup(&self, ip: IpAddr) -> Result<Option<AsnInfo>> {
+        let record = match self.0.lookup::<maxminddb::geoip2::Asn>(ip) {
+            Ok(record) => record,
+            Err(maxminddb::MaxMindDBError::AddressNotFoundError(_)) => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+
+        Ok(record.autonomous_system_number.map(|number| AsnInfo {
+            number,
+            organization: record.autonomous_system_organization.map(str::to_string),
+        }))
+    }
+}
+
+/// Prefix-to-ASN entries from configuration; the most specific prefix wins
+pub struct AsnLookupTable {
+    prefixes: Vec<(IpNet, AsnInfo)>,
+}
+
+impl AsnLookupTable {
+    pub fn new(prefixes: Vec<(IpNet, AsnInfo)>) -> Self {
+        let mut prefixes = prefixes;
+        prefixes.sort_by(|(a, _), (b, _)| b.prefix_len().cmp(&a.prefix_len()));
+        Self { prefixes }
+    }
+}
+
+impl AsnDatabase for AsnLookupTable {
+    fn lookup(&self, ip: IpAddr) -> Result<Option<AsnInfo>> {
+        Ok(self
+            .prefixes
+            .iter()
+            .find(|(network, _)| network.contains(&ip))
+            .map(|(_, asn)| asn.clone()))
+    }
+}
+
+/// Bounded map of past lookups, evicting the oldest entry when full. Misses
+/// are cached too so unknown IPs don't hit the databases on every request.
+struct LookupCache {
+    entries: HashMap<IpAddr, Option<AsnInfo>>,
+    order: VecDeque<IpAddr>,
+    capacity: usize,
+}
+
+pub struct AsnReputationAnalyzer {
+    table: AsnLookupTable,
+    database: Option<Box<dyn AsnDatabase>>,
+    cache: Mutex<LookupCache>,
+    high_risk_asns: HashSet<u32>,
+    datacenter_asns: HashSet<u32>,
+    high_risk_score: f64,
+    datacenter_score: f64,
+    enabled: bool,
+}
+
+/// Settings accepted by `update_config`
+#[derive(Debug, Deserialize)]
+struct AsnScoringUpdate {
+    high_risk_asns: Option<Vec<u32>>,
+    datacenter_asns: Option<Vec<u32>>,
+    high_risk_score: Option<f64>,
+    datacenter_score: Option<f64>,
+    enabled: Option<bool>,
+}
+
+impl AsnReputationAnalyzer {
+    pub fn new(table: AsnLookupTable, database: Option<Box<dyn AsnDatabase>>, config: &AsnReputationConfig) -> Self {
+        Self {
+            table,
+            database,
+            cache: Mutex::new(LookupCache {
+                entries: HashMap::new(),
+                order: VecDeque::new(),
+                capacity: config.cache_size.max(1),
+            }),
+            high_risk_asns: config.high_risk_asns.iter().copied().collect(),
+            datacenter_asns: config.datacenter_asns.iter().copied().collect(),
+            high_risk_score: config.high_risk_score,
+            datacenter_score: config.datacenter_score,
+            enabled: true,
+        }
+    }
+
+    /// The analyzer for `[security.threat_detection.asn_reputation]`, or `None` if it's disabled
+    pub fn from_config(config: &AsnReputationConfig) -> Result<Option<Self>> {
+        if !config.enabled {
+            return Ok(None);
+        }
+
+        let prefixes = config
+            .prefixes
+            .iter()
+            .map(|prefix| {
+                let network: IpNet = prefix
+                    .cidr
+                    .parse()
+                    .with_context(|| format!("Invalid ASN prefix: {}", prefix.cidr))?;
+                Ok((
+                    network,
+                    AsnInfo {
+                        number: prefix.asn,
+                        organization: prefix.organization.clone(),
+                    },
+                ))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let database: Option<Box<dyn AsnDatabase>> = match &config.database_path {
+            #[cfg(feature = "geoip")]
+            Some(path) => Some(Box::new(MaxMindAsnDatabase::open(path)?)),
+            #[cfg(not(feature = "geoip"))]
+            Some(_) => {
+                tracing::warn!("An ASN database is configured but ratewatch was built without the `geoip` feature");
+                None
+            }
+            None => None,
+        };
+
+        info!(
+            prefixes = prefixes.len(),
+            database = database.is_some(),
+            high_risk_asns = config.high_risk_asns.len(),
+            "ASN reputation analysis enabled"
+        );
+        Ok(Some(Self::new(AsnLookupTable::new(prefixes), database, config)))
+    }
+
+    /// ASN of `ip`, or `None` if it's unparseable or neither source knows it.
+    /// The database is only asked about publicly routable addresses.
+    pub fn resolve(&self, ip: &str) -> Option<AsnInfo> {
+        let ip: IpAddr = ip.trim().parse().ok()?;
+
+        if let Some(cached) = self.cache.lock().unwrap().entries.get(&ip) {
+            return cached.clone();
+        }
+
+        let mut asn = self.table.lookup(ip).unwrap_or_default();
+        if asn.is_none() && is_public(ip) {
+            if let Some(database) = &self.database {
+                match database.lookup(ip) {
+                    Ok(found) => asn = found,
+                    Err(e) => {
+                        // Not cached, so a transient failure doesn't stick
+                        debug!(ip_address = %ip, error = %e, "ASN lookup failed");
+                        return None;
+                    }
+                }
+            }
+        }
+
+        let mut cache = self.cache.lock().unwrap();
+        if cache.entries.len() >= cache.capacity {
+            if let Some(oldest) = cache.order.pop_front() {
+                cache.entries.remove(&oldest);
+            }
+        }
+        if cache.entries.insert(ip, asn.clone()).is_none() {
+            cache.order.push_back(ip);
+        }
+        asn
+    }
+
+    /// Whether `asn` looks like a hosting or cloud network rather than a residential one
+    pub fn is_datacenter(&self, asn: &AsnInfo) -> bool {
+        if self.datacenter_asns.contains(&asn.number) {
+            return true;
+        }
+        let Some(organization) = &asn.organization else {
+            return false;
+        };
+        let organization = organization.to_lowercase();
+        organization
+            .split(|c: char| !c.is_alphanumeric())
+            .any(|word| DATACENTER_KEYWORDS.contains(&word))
+            || DATACENTER_KEYWORDS
+                .iter()
+                .filter(|keyword| keyword.contains(' '))
+                .any(|keyword| organization.contains(keyword))
+    }
+}
+
+#[async_trait]
+impl ThreatAnalyzer for AsnReputationAnalyzer {
+    async fn analyze(&self, context: &RequestContext) -> Result<ThreatScore> {
+        let Some(asn) = self.resolve(&context.ip_address) else {
+            return Ok(ThreatScore::new("asn_reputation".to_string(), 0.0, 0.0)
+                .with_reason("ASN reputation: unknown ASN".to_string()));
+        };
+
+        let datacenter = self.is_datacenter(&asn);
+        let high_risk = self.high_risk_asns.contains(&asn.number);
+        let label = match &asn.organization {
+            Some(organization) => format!("AS{} ({})", asn.number, organization),
+            None => format!("AS{}", asn.number),
+        };
+
+        let score = if high_risk {
+            ThreatScore::new("asn_reputation".to_string(), self.high_risk_score, 0.9)
+                .with_reason(format!("ASN reputation: {} is a high-risk network", label))
+        } else if datacenter {
+            ThreatScore::new("asn_reputation".to_string(), self.datacenter_score, 0.6)
+                .with_reason(format!("ASN reputation: {} is a datacenter network", label))
+        } else {
+            ThreatScore::new("asn_reputation".to_string(), 0.0, 0.6)
+                .with_reason(format!("ASN reputation: {}", label))
+        };
+
+        Ok(score
+            .with_metadata("asn".to_string(), serde_json::json!(asn.number))
+            .with_metadata("asn_org".to_string(), serde_json::json!(asn.organization))
+            .with_metadata("datacenter".to_string(), serde_json::json!(datacenter))
+            .with_metadata("high_risk_asn".to_string(), serde_json::json!(high_risk)))
+    }
+
+    fn analyzer_id(&self) -> &str {
+        "asn_reputation"
+    }
+
+    fn name(&self) -> &str {
+        "ASN Reputation Analyzer"
+    }
+
+    fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    async fn update_config(&mut self, config: serde_json::Value) -> Result<()> {
+        let update: AsnScoringUpdate = serde_json::from_value(config)?;
+        if let Some(asns) = update.high_risk_asns {
+            self.high_risk_asns = asns.into_iter().collect();
+        }
+        if let Some(asns) = update.datacenter_asns {
+            self.datacenter_asns = asns.into_iter().collect();
+        }
+        if let Some(score) = update.high_risk_score {
+            self.high_risk_score = score.clamp(0.0, 1.0);
+        }
+        if let Some(score) = update.datacenter_score {
+            self.datacenter_score = score.clamp(0.0, 1.0);
+        }
+        if let Some(enabled) = update.enabled {
+            self.enabled = enabled;
+        }
+        info!("ASN reputation analyzer configuration updated");
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::AsnPrefixConfig;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    fn config() -> AsnReputationConfig {
+        AsnReputationConfig {
+            enabled: true,
+            prefixes: vec![
+                AsnPrefixConfig {
+                    cidr: "203.0.113.0/24".to_string(),
+                    asn: 64500,
+                    organization: Some("Bulletproof Networks".to_string()),
+                },
+                AsnPrefixConfig {
+                    cidr: "198.51.100.0/24".to_string(),
+                    asn: 64501,
+                    organization: Some("Example Cloud Hosting Ltd".to_string()),
+                },
+                AsnPrefixConfig {
+                    cidr: "192.0.2.0/24".to_string(),
+                    asn: 64502,
+                    organization: Some("Example Broadband".to_string()),
+                },
+            ],
+            high_risk_asns: vec![64500],
+            ..AsnReputationConfig::default()
+        }
+    }
+
+    fn context(ip: &str) -> RequestContext {
+        RequestContext::new(ip.to_string(), "/v1/check".to_string(), "POST".to_string())
+    }
+
+    /// Every public IP is in AS64496; counts how often it's asked
+    struct CountingDatabase(Arc<AtomicUsize>);
+
+    impl AsnDatabase for CountingDatabase {
+        fn lookup(&self, _ip: IpAddr) -> Result<Option<AsnInfo>> {
+            self.0.fetch_add(1, Ordering::SeqCst);
+            Ok(Some(AsnInfo {
+                number: 64496,
+                organization: None,
+            }))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_scores_high_risk_datacenter_and_residential_networks() {
+        let analyzer = AsnReputationAnalyzer::from_config(&config()).unwrap().unwrap();
+
+        let high_risk = analyzer.analyze(&context("203.0.113.7")).await.unwrap();
+        assert_eq!(high_risk.score, 0.8);
+        assert_eq!(high_risk.metadata["asn"], serde_json::json!(64500));
+        assert_eq!(high_risk.metadata["asn_org"], serde_json::json!("Bulletproof Networks"));
+        assert_eq!(high_risk.metadata["high_risk_asn"], serde_json::json!(true));
+
+        let datacenter = analyzer.analyze(&context("198.51.100.20")).await.unwrap();
+        assert_eq!(datacenter.score, 0.3);
+        assert_eq!(datacenter.metadata["datacenter"], serde_json::json!(true));
+
+        let residential = analyzer.analyze(&context("192.0.2.44")).await.unwrap();
+        assert_eq!(residential.score, 0.0);
+        assert_eq!(residential.metadata["asn"], serde_json::json!(64502));
+    }
+
+    #[tokio::test]
+    async fn test_unknown_asns_are_neutral() {
+        let analyzer = AsnReputationAnalyzer::from_config(&config()).unwrap().unwrap();
+
+        for ip in ["8.8.8.8", "10.0.0.1", "not-an-ip"] {
+            let score = analyzer.analyze(&context(ip)).await.unwrap();
+            assert_eq!(score.score, 0.0);
+            assert_eq!(score.confidence, 0.0);
+            assert!(!score.metadata.contains_key("asn"));
+        }
+    }
+
+    #[test]
+    fn test_lookups_are_cached_and_private_ips_skip_the_database() {
+        let lookups = Arc::new(AtomicUsize::new(0));
+        let analyzer = AsnReputationAnalyzer::new(
+            AsnLookupTable::new(Vec::new()),
+            Some(Box::new(CountingDatabase(lookups.clone()))),
+            &config(),
+        );
+
+        assert_eq!(analyzer.resolve("8.8.8.8").unwrap().number, 64496);
+        analyzer.resolve("8.8.8.8");
+        assert_eq!(lookups.load(Ordering::SeqCst), 1);
+
+        assert!(analyzer.resolve("192.168.1.1").is_none());
+        assert_eq!(lookups.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_most_specific_prefix_wins() {
+        let table = AsnLookupTable::new(vec![
+            ("198.51.0.0/16".parse().unwrap(), AsnInfo { number: 1, organization: None }),
+            ("198.51.100.0/24".parse().unwrap(), AsnInfo { number: 2, organization: None }),
+        ]);
+
+        assert_eq!(table.lookup("198.51.100.1".parse().unwrap()).unwrap().unwrap().number, 2);
+        assert_eq!(table.lookup("198.51.7.1".parse().unwrap()).unwrap().unwrap().number, 1);
+    }
+
+    #[test]
+    fn test_datacenter_heuristic_matches_whole_words() {
+        let analyzer = AsnReputationAnalyzer::new(AsnLookupTable::new(Vec::new()), None, &config());
+        let asn = |organization: &str| AsnInfo {
+            number: 1,
+            organization: Some(organization.to_string()),
+        };
+
+        assert!(analyzer.is_datacenter(&asn("DIGITALOCEAN-ASN")));
+        assert!(analyzer.is_datacenter(&asn("Hetzner Online GmbH")));
+        assert!(analyzer.is_datacenter(&asn("Example Data Center LLC")));
+        assert!(!analyzer.is_datacenter(&asn("Comcast Cable Communications")));
+        // "ghostnet" contains "host" but isn't a hosting network
+        assert!(!analyzer.is_datacenter(&asn("Ghostnet Residential")));
+    }
+}
@@ -0,0 +1,542 @@
+//! Operator-defined behavior patterns.
+//!
+//! `[security.threat_detection.behavior_patterns] file` points at a JSON or
+//! YAML list of pattern definitions. A pattern matches when all of its
+//! indicators hold for a request, and then counts towards the behavior
+//! analyzer's score like a built-in pattern, weighted by `pattern_weights`
+//! under its `id`. With `mode = "merge"` the patterns run alongside the
+//! built-in checks; with `"replace"` only they run.
+//!
+//! Definitions are checked when loaded: every regex must compile, scores must
+//! be within 0 to 1, and each indicator must support its comparison. The set
+//! can be replaced at runtime through `PUT /v1/security/patterns`, which
+//! applies to this instance until it restarts.
+
+use crate::config::{BehaviorPatternMode, BehaviorPatternsConfig};
+use crate::security::behavioral_analyzer::{BehaviorPattern, PatternType};
+use crate::security::threat_analyzer::RequestContext;
+use anyhow::{bail, Context, Result};
+use chrono::Timelike;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::sync::{Arc, RwLock};
+
+/// A request property a pattern can test
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum Indicator {
+    /// Requests per minute over the analysis window
+    RequestRate,
+    /// Share of the client's requests that failed, 0 to 1
+    ErrorRate,
+    /// Distinct endpoints the client has called
+    UniqueEndpoints,
+    /// Requests seen from the client
+    RequestCount,
+    /// UTC hour the request arrived, 0 to 23
+    HourOfDay,
+    /// Request body size in bytes, as measured for the request limits or
+    /// else from `content-length`
+    PayloadSize,
+    /// The client's average response time in milliseconds
+    ResponseTime,
+    Endpoint,
+    Method,
+    UserAgent,
+    /// Country code, when GeoIP is enabled
+    Country,
+    /// The request header named in `header`
+    Header,
+}
+
+impl Indicator {
+    fn is_numeric(self) -> bool {
+        matches!(
+            self,
+            Indicator::RequestRate
+                | Indicator::ErrorRate
+                | Indicator::UniqueEndpoints
+                | Indicator::RequestCount
+                | Indicator::HourOfDay
+                | Indicator::PayloadSize
+                | Indicator::ResponseTime
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum Comparison {
+    GreaterThan,
+    LessThan,
+    /// Between `[min, max]`, inclusive
+    Range,
+    Equals,
+    Contains,
+    Regex,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct IndicatorDefinition {
+    pub indicator: Indicator,
+    pub comparison: Comparison,
+    /// A number, a `[min, max]` pair for `range`, or a string
+    #[cfg_attr(feature = "openapi", schema(value_type = Object))]
+    pub value: serde_json::Value,
+    /// Header name, for the `header` indicator
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub header: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct PatternDefinition {
+    /// Names the pattern in scores, feedback and `pattern_weights`
+    pub id: String,
+    #[serde(default)]
+    pub description: String,
+    /// Risk when the pattern matches, 0 to 1
+    pub risk_score: f64,
+    /// Confidence when the pattern matches, 0 to 1
+    #[serde(default = "default_pattern_confidence")]
+    pub confidence: f64,
+    /// All must hold for the pattern to match
+    pub indicators: Vec<IndicatorDefinition>,
+}
+
+fn default_pattern_confidence() -> f64 {
+    0.8
+}
+
+/// What a pattern is evaluated against
+pub struct PatternInput<'a> {
+    pub context: &'a RequestContext,
+    pub request_rate: f64,
+    pub error_rate: f64,
+    pub unique_endpoints: usize,
+    pub request_count: u64,
+    pub average_response_time_ms: f64,
+}
+
+#[derive(Debug)]
+enum Test {
+    GreaterThan(f64),
+    LessThan(f64),
+    Range(f64, f64),
+    EqualsNumber(f64),
+    EqualsText(String),
+    Contains(String),
+    Regex(Regex),
+}
+
+#[derive(Debug)]
+struct CompiledIndicator {
+    indicator: Indicator,
+    /// Lowercased, for the `header` indicator
+    header: Option<String>,
+    test: Test,
+}
+
+#[derive(Debug)]
+struct CompiledPattern {
+    definition: PatternDefinition,
+    indicators: Vec<CompiledIndicator>,
+}
+
+/// A `[min, max]` pair, or a `"min-max"` string such as `"9-17"`
+fn range_bounds(value: &serde_json::Value) -> Option<(f64, f64)> {
+    if let Some(text) = value.as_str() {
+        let (min, max) = text.split_once('-')?;
+        return Some((min.trim().parse().ok()?, max.trim().parse().ok()?));
+    }
+    match value.as_array()?.as_slice() {
+        [min, max] => Some((min.as_f64()?, max.as_f64()?)),
+        _ => None,
+    }
+}
+
+fn compile_indicator(definition: &IndicatorDefinition) -> Result<CompiledIndicator> {
+    let IndicatorDefinition { indicator, comparison, value, header } = definition;
+    let number = || value.as_f64().with_context(|| format!("{:?} needs a number, got {}", comparison, value));
+    let text = || {
+        value
+            .as_str()
+            .map(str::to_string)
+            .with_context(|| format!("{:?} needs a string, got {}", comparison, value))
+    };
+
+    let test = match (indicator.is_numeric(), comparison) {
+        (true, Comparison::GreaterThan) => Test::GreaterThan(number()?),
+        (true, Comparison::LessThan) => Test::LessThan(number()?),
+        (true, Comparison::Equals) => Test::EqualsNumber(number()?),
+        (true, Comparison::Range) => {
+            let Some((min, max)) = range_bounds(value).filter(|(min, max)| min.is_finite() && max.is_finite()) else {
+                bail!("range needs a [min, max] pair or a \"min-max\" string, got {}", value);
+            };
+            if min > max {
+                bail!("range minimum {} is above its maximum {}", min, max);
+            }
+            Test::Range(min, max)
+        }
+        (false, Comparison::Equals) => Test::EqualsText(text()?),
+        (false, Comparison::Contains) => Test::Contains(text()?),
+        (false, Comparison::Regex) => Test::Regex(Regex::new(&text()?).context("Invalid regex")?),
+        (_, comparison) => bail!("{:?} can't be compared with {:?}", indicator, comparison),
+    };
+
+    let header = match (indicator, header) {
+        (Indicator::Header, Some(name)) if !name.trim().is_empty() => Some(name.trim().to_lowercase()),
+        (Indicator::Header, _) => bail!("the header indicator needs a header name"),
+        (_, Some(_)) => bail!("only the header indicator takes a header name"),
+        (_, None) => None,
+    };
+
+    Ok(CompiledIndicator { indicator: *indicator, header, test })
+}
+
+fn compile_pattern(definition: PatternDefinition, ids: &mut HashSet<String>) -> Result<CompiledPattern> {
+    if definition.id.trim().is_empty() {
+        bail!("id is empty");
+    }
+    if !ids.insert(definition.id.clone()) {
+        bail!("id is used by another pattern");
+    }
+    if !(0.0..=1.0).contains(&definition.risk_score) {
+        bail!("risk_score must be between 0 and 1");
+    }
+    if !(0.0..=1.0).contains(&definition.confidence) {
+        bail!("confidence must be between 0 and 1");
+    }
+    if definition.indicators.is_empty() {
+        bail!("no indicators");
+    }
+    let indicators = definition
+        .indicators
+        .iter()
+        .enumerate()
+        .map(|(i, indicator)| compile_indicator(indicator).with_context(|| format!("indicator {}", i)))
+        .collect::<Result<Vec<_>>>()?;
+    Ok(CompiledPattern { definition, indicators })
+}
+
+fn compile(definitions: Vec<PatternDefinition>) -> Result<Vec<CompiledPattern>> {
+    let mut ids = HashSet::new();
+    definitions
+        .into_iter()
+        .map(|definition| {
+            let id = definition.id.clone();
+            compile_pattern(definition, &mut ids).with_context(|| format!("Invalid behavior pattern '{}'", id))
+        })
+        .collect()
+}
+
+impl CompiledIndicator {
+    /// Evidence for the match if the indicator holds
+    fn check(&self, input: &PatternInput) -> Option<String> {
+        let context = input.context;
+        let name = match &self.header {
+            Some(header) => format!("header {}", header),
+            None => serde_json::to_value(self.indicator).ok()?.as_str()?.to_string(),
+        };
+
+        if self.indicator.is_numeric() {
+            let actual = match self.indicator {
+                Indicator::RequestRate => input.request_rate,
+                Indicator::ErrorRate => input.error_rate,
+                Indicator::UniqueEndpoints => input.unique_endpoints as f64,
+                Indicator::RequestCount => input.request_count as f64,
+                Indicator::ResponseTime => input.average_response_time_ms,
+                // A request without a declared size doesn't match
+                Indicator::PayloadSize => context.payload_size.or_else(|| {
+                    context
+                        .headers
+                        .iter()
+                        .find(|(name, _)| name.eq_ignore_ascii_case("content-length"))
+                        .and_then(|(_, value)| value.trim().parse::<u64>().ok())
+                })? as f64,
+                _ => context.timestamp.hour() as f64,
+            };
+            let holds = match &self.test {
+                Test::GreaterThan(limit) => actual > *limit,
+                Test::LessThan(limit) => actual < *limit,
+                Test::Range(min, max) => (*min..=*max).contains(&actual),
+                Test::EqualsNumber(expected) => actual == *expected,
+                _ => false,
+            };
+            return holds.then(|| format!("{}: {:.2}", name, actual));
+        }
+
+        let actual = match self.indicator {
+            Indicator::Endpoint => Some(context.endpoint.as_str()),
+            Indicator::Method => Some(context.method.as_str()),
+            Indicator::UserAgent => context.user_agent.as_deref(),
+            Indicator::Country => context.geolocation.as_ref().map(|geolocation| geolocation.country.as_str()),
+            _ => self.header.as_ref().and_then(|header| {
+                context
+                    .headers
+                    .iter()
+                    .find(|(name, _)| name.eq_ignore_ascii_case(header))
+                    .map(|(_, value)| value.as_str())
+            }),
+        }?;
+        let holds = match &self.test {
+            Test::EqualsText(expected) => actual == expected,
+            Test::Contains(needle) => actual.contains(needle.as_str()),
+            Test::Regex(regex) => regex.is_match(actual),
+            _ => false,
+        };
+        holds.then(|| format!("{}: {}", name, actual))
+    }
+}
+
+/// The active pattern set, shared by the behavior analyzer and the API
+#[derive(Debug)]
+pub struct BehaviorPatterns {
+    mode: BehaviorPatternMode,
+    patterns: RwLock<Arc<Vec<CompiledPattern>>>,
+}
+
+impl BehaviorPatterns {
+    pub fn new(mode: BehaviorPatternMode, definitions: Vec<PatternDefinition>) -> Result<Self> {
+        Ok(Self {
+            mode,
+            patterns: RwLock::new(Arc::new(compile(definitions)?)),
+        })
+    }
+
+    /// The patterns in `[security.threat_detection.behavior_patterns] file`;
+    /// an empty set if no file is configured
+    pub fn from_config(config: &BehaviorPatternsConfig) -> Result<Self> {
+        let Some(path) = &config.file else {
+            return Self::new(config.mode, Vec::new());
+        };
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read behavior patterns from {}", path))?;
+        let definitions: Vec<PatternDefinition> = if path.ends_with(".json") {
+            serde_json::from_str(&content).with_context(|| format!("Invalid behavior patterns in {}", path))?
+        } else {
+            serde_yaml::from_str(&content).with_context(|| format!("Invalid behavior patterns in {}", path))?
+        };
+
+        let patterns = Self::new(config.mode, definitions)?;
+        tracing::info!(path = %path, mode = ?config.mode, count = patterns.definitions().len(), "Behavior patterns loaded");
+        Ok(patterns)
+    }
+
+    pub fn mode(&self) -> BehaviorPatternMode {
+        self.mode
+    }
+
+    /// Whether the built-in checks still run
+    pub fn keeps_builtin(&self) -> bool {
+        self.mode == BehaviorPatternMode::Merge
+    }
+
+    pub fn definitions(&self) -> Vec<PatternDefinition> {
+        let patterns = self.patterns.read().unwrap_or_else(|e| e.into_inner()).clone();
+        patterns.iter().map(|pattern| pattern.definition.clone()).collect()
+    }
+
+    /// Swap in a new set. Nothing changes if any definition is invalid.
+    pub fn replace(&self, definitions: Vec<PatternDefinition>) -> Result<()> {
+        let compiled = Arc::new(compile(definitions)?);
+        *self.patterns.write().unwrap_or_else(|e| e.into_inner()) = compiled;
+        Ok(())
+    }
+
+    /// The patterns that match `input`
+    pub fn matches(&self, input: &PatternInput) -> Vec<BehaviorPattern> {
+        let patterns = self.patterns.read().unwrap_or_else(|e| e.into_inner()).clone();
+        patterns
+            .iter()
+            .filter_map(|pattern| {
+                let evidence = pattern
+                    .indicators
+                    .iter()
+                    .map(|indicator| indicator.check(input))
+                    .collect::<Option<Vec<_>>>()?;
+                let definition = &pattern.definition;
+                let description = if definition.description.is_empty() {
+                    format!("Matched behavior pattern '{}'", definition.id)
+                } else {
+                    definition.description.clone()
+                };
+                Some(BehaviorPattern {
+                    pattern_type: PatternType::Custom(definition.id.clone()),
+                    confidence: definition.confidence,
+                    description,
+                    risk_score: definition.risk_score,
+                    evidence,
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn definition(id: &str, indicators: serde_json::Value) -> PatternDefinition {
+        serde_json::from_value(json!({ "id": id, "risk_score": 0.7, "indicators": indicators })).unwrap()
+    }
+
+    fn input(context: &RequestContext, request_rate: f64) -> PatternInput<'_> {
+        PatternInput {
+            context,
+            request_rate,
+            error_rate: 0.0,
+            unique_endpoints: 3,
+            request_count: 40,
+            average_response_time_ms: 120.0,
+        }
+    }
+
+    #[test]
+    fn test_all_indicators_must_hold() {
+        let patterns = BehaviorPatterns::new(
+            BehaviorPatternMode::Merge,
+            vec![definition(
+                "admin_scan",
+                json!([
+                    { "indicator": "endpoint", "comparison": "regex", "value": "^/(admin|wp-admin|\\.env)" },
+                    { "indicator": "request_rate", "comparison": "greater_than", "value": 20 },
+                    { "indicator": "header", "header": "X-Scanner", "comparison": "equals", "value": "yes" },
+                ]),
+            )],
+        )
+        .unwrap();
+
+        let context = RequestContext::new("192.0.2.1".to_string(), "/wp-admin/setup.php".to_string(), "GET".to_string())
+            .with_header("x-scanner".to_string(), "yes".to_string());
+        let matched = patterns.matches(&input(&context, 30.0));
+        assert_eq!(matched.len(), 1);
+        assert_eq!(matched[0].pattern_type.name(), "admin_scan");
+        assert_eq!(matched[0].risk_score, 0.7);
+        assert_eq!(matched[0].evidence.len(), 3);
+
+        assert!(patterns.matches(&input(&context, 10.0)).is_empty());
+        let other = RequestContext::new("192.0.2.1".to_string(), "/v1/check".to_string(), "GET".to_string());
+        assert!(patterns.matches(&input(&other, 30.0)).is_empty());
+    }
+
+    #[test]
+    fn test_payload_size_and_response_time() {
+        let patterns = BehaviorPatterns::new(
+            BehaviorPatternMode::Merge,
+            vec![
+                definition("large_upload", json!([{ "indicator": "payload_size", "comparison": "greater_than", "value": 1048576 }])),
+                definition("slow_client", json!([{ "indicator": "response_time", "comparison": "range", "value": [100, 500] }])),
+            ],
+        )
+        .unwrap();
+
+        let context = RequestContext::new("192.0.2.1".to_string(), "/upload".to_string(), "POST".to_string())
+            .with_header("Content-Length".to_string(), "5000000".to_string());
+        let matched: Vec<String> = patterns.matches(&input(&context, 0.0)).iter().map(|p| p.pattern_type.name()).collect();
+        assert_eq!(matched, ["large_upload", "slow_client"]);
+
+        // No content-length, no payload size to compare
+        let context = RequestContext::new("192.0.2.1".to_string(), "/upload".to_string(), "POST".to_string());
+        let matched: Vec<String> = patterns.matches(&input(&context, 0.0)).iter().map(|p| p.pattern_type.name()).collect();
+        assert_eq!(matched, ["slow_client"]);
+
+        // The measured size wins over a header claiming less
+        let context = RequestContext::new("192.0.2.1".to_string(), "/upload".to_string(), "POST".to_string())
+            .with_header("content-length".to_string(), "10".to_string())
+            .with_payload_size(5_000_000);
+        let matched: Vec<String> = patterns.matches(&input(&context, 0.0)).iter().map(|p| p.pattern_type.name()).collect();
+        assert_eq!(matched, ["large_upload", "slow_client"]);
+    }
+
+    #[test]
+    fn test_numeric_range() {
+        let patterns = BehaviorPatterns::new(
+            BehaviorPatternMode::Merge,
+            vec![definition("mid_upload", json!([{ "indicator": "payload_size", "comparison": "range", "value": "1048576-10485760" }]))],
+        )
+        .unwrap();
+        let upload = |size: &str| {
+            RequestContext::new("192.0.2.1".to_string(), "/upload".to_string(), "POST".to_string())
+                .with_header("content-length".to_string(), size.to_string())
+        };
+
+        assert_eq!(patterns.matches(&input(&upload("2000000"), 0.0)).len(), 1);
+        assert_eq!(patterns.matches(&input(&upload("10485760"), 0.0)).len(), 1);
+        assert!(patterns.matches(&input(&upload("20000000"), 0.0)).is_empty());
+        assert!(patterns.matches(&input(&upload("512"), 0.0)).is_empty());
+
+        for malformed in [json!("1MB-10MB"), json!("10-1"), json!([1]), json!([1, "x"]), json!("5")] {
+            let indicators = json!([{ "indicator": "payload_size", "comparison": "range", "value": malformed }]);
+            assert!(BehaviorPatterns::new(BehaviorPatternMode::Merge, vec![definition("bad", indicators)]).is_err(), "{malformed}");
+        }
+    }
+
+    #[test]
+    fn test_invalid_definitions_are_rejected() {
+        let invalid = [
+            json!([{ "indicator": "endpoint", "comparison": "regex", "value": "(" }]),
+            json!([{ "indicator": "user_agent", "comparison": "greater_than", "value": 3 }]),
+            json!([{ "indicator": "request_rate", "comparison": "contains", "value": "x" }]),
+            json!([{ "indicator": "request_rate", "comparison": "greater_than", "value": "fast" }]),
+            json!([{ "indicator": "hour_of_day", "comparison": "range", "value": [22, 4] }]),
+            json!([{ "indicator": "header", "comparison": "equals", "value": "yes" }]),
+            json!([]),
+        ];
+        for indicators in invalid {
+            let error = BehaviorPatterns::new(BehaviorPatternMode::Merge, vec![definition("bad", indicators.clone())])
+                .unwrap_err();
+            assert!(format!("{:#}", error).contains("'bad'"), "{indicators}");
+        }
+
+        let mut out_of_range = definition("risky", json!([{ "indicator": "method", "comparison": "equals", "value": "PUT" }]));
+        out_of_range.risk_score = 1.5;
+        assert!(BehaviorPatterns::new(BehaviorPatternMode::Merge, vec![out_of_range]).is_err());
+
+        let twice = definition("twice", json!([{ "indicator": "method", "comparison": "equals", "value": "PUT" }]));
+        assert!(BehaviorPatterns::new(BehaviorPatternMode::Merge, vec![twice.clone(), twice]).is_err());
+    }
+
+    #[test]
+    fn test_failed_replace_keeps_active_set() {
+        let patterns = BehaviorPatterns::new(
+            BehaviorPatternMode::Replace,
+            vec![definition("night", json!([{ "indicator": "hour_of_day", "comparison": "range", "value": [0, 23] }]))],
+        )
+        .unwrap();
+        assert!(!patterns.keeps_builtin());
+
+        let invalid = definition("broken", json!([{ "indicator": "country", "comparison": "less_than", "value": 1 }]));
+        assert!(patterns.replace(vec![invalid]).is_err());
+        assert_eq!(patterns.definitions()[0].id, "night");
+
+        patterns.replace(Vec::new()).unwrap();
+        assert!(patterns.definitions().is_empty());
+    }
+
+    #[test]
+    fn test_loads_yaml_file() {
+        let path = std::env::temp_dir().join(format!("patterns-{}.yaml", uuid::Uuid::new_v4()));
+        std::fs::write(
+            &path,
+            "- id: curl_posts\n  risk_score: 0.5\n  indicators:\n    - { indicator: user_agent, comparison: contains, value: curl }\n    - { indicator: method, comparison: equals, value: POST }\n",
+        )
+        .unwrap();
+        let config = BehaviorPatternsConfig {
+            file: Some(path.to_string_lossy().into_owned()),
+            mode: BehaviorPatternMode::Merge,
+        };
+
+        let patterns = BehaviorPatterns::from_config(&config).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let context = RequestContext::new("192.0.2.1".to_string(), "/v1/check".to_string(), "POST".to_string())
+            .with_user_agent("curl/8.4.0".to_string());
+        assert_eq!(patterns.matches(&input(&context, 0.0))[0].confidence, 0.8);
+        assert!(BehaviorPatterns::from_config(&BehaviorPatternsConfig::default()).unwrap().definitions().is_empty());
+    }
+}
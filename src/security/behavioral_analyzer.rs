@@ -1,17 +1,23 @@
-use crate::security::threat_analyzer::{ThreatAnalyzer, ThreatScore, RequestContext};
+use crate::redis_backend::RedisConnector;
+use crate::security::behavior_patterns::{BehaviorPatterns, PatternInput};
+use crate::security::feedback::{FeedbackStore, FlaggedAnalysis};
+use crate::security::threat_analyzer::{AnalyzerHealth, ThreatAnalyzer, ThreatScore, RequestContext};
 use anyhow::Result;
 use async_trait::async_trait;
 use chrono::{DateTime, Duration, Utc};
-use redis::{AsyncCommands, Client};
+use redis::AsyncCommands;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::sync::Arc;
 use tracing::{debug, error, info, warn};
 
 #[derive(Debug, Clone)]
 pub struct BehaviorAnalyzer {
-    redis_client: Client,
+    redis_client: RedisConnector,
     config: BehaviorAnalysisConfig,
     enabled: bool,
+    feedback: Option<FeedbackStore>,
+    patterns: Option<Arc<BehaviorPatterns>>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -21,9 +27,17 @@ pub struct BehaviorAnalysisConfig {
     pub anomaly_threshold: f64,
     pub pattern_weights: HashMap<String, f64>,
     pub enable_ml_detection: bool,
+    /// Score every IP as zero until its profile is `learning_period_hours` old,
+    /// so fresh deployments don't flag traffic before a baseline exists
+    #[serde(default = "default_learning_mode")]
+    pub learning_mode: bool,
     pub learning_period_hours: i64,
 }
 
+fn default_learning_mode() -> bool {
+    true
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BehaviorPattern {
     pub pattern_type: PatternType,
@@ -43,6 +57,18 @@ pub enum PatternType {
     GeographicAnomaly,
     SessionAnomaly,
     ErrorRateAnomaly,
+    /// An operator-defined pattern, by ID
+    Custom(String),
+}
+
+impl PatternType {
+    /// Key for the pattern in `pattern_weights` and feedback
+    pub fn name(&self) -> String {
+        match self {
+            PatternType::Custom(id) => id.clone(),
+            builtin => format!("{:?}", builtin),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -68,30 +94,51 @@ struct BehaviorProfile {
     pub error_count: u32,
     pub total_response_time: u64,
     pub request_sizes: Vec<u32>,
+    /// Requests per country code, when GeoIP is enabled
+    #[serde(default)]
+    pub geographic_locations: HashMap<String, u32>,
 }
 
 impl BehaviorAnalyzer {
-    pub async fn new(redis_client: Client) -> Result<Self> {
+    pub async fn new(redis_client: RedisConnector) -> Result<Self> {
         let config = BehaviorAnalysisConfig::default();
         
         Ok(Self {
             redis_client,
             config,
             enabled: true,
+            feedback: None,
+            patterns: None,
         })
     }
 
-    pub async fn with_config(redis_client: Client, config: BehaviorAnalysisConfig) -> Result<Self> {
+    pub async fn with_config(redis_client: RedisConnector, config: BehaviorAnalysisConfig) -> Result<Self> {
         Ok(Self {
             redis_client,
             config,
             enabled: true,
+            feedback: None,
+            patterns: None,
         })
     }
 
-    async fn get_behavior_profile(&self, ip_address: &str) -> Result<Option<BehaviorProfile>> {
+    /// Scale pattern weights by operator feedback and record flagged requests so
+    /// they can be reported as false positives
+    pub fn with_feedback(mut self, feedback: FeedbackStore) -> Self {
+        self.feedback = Some(feedback);
+        self
+    }
+
+    /// Also score requests against operator-defined patterns
+    pub fn with_patterns(mut self, patterns: Arc<BehaviorPatterns>) -> Self {
+        self.patterns = Some(patterns);
+        self
+    }
+
+    /// The profile of the client with `ip_key`, see `RequestContext::ip_key`
+    async fn get_behavior_profile(&self, ip_key: &str) -> Result<Option<BehaviorProfile>> {
         let mut conn = self.redis_client.get_async_connection().await?;
-        let key = format!("behavior:profile:{}", ip_address);
+        let key = format!("behavior:profile:{}", ip_key);
         
         let profile_data: Option<String> = conn.get(&key).await?;
         
@@ -100,7 +147,7 @@ impl BehaviorAnalyzer {
                 Ok(profile) => Ok(Some(profile)),
                 Err(e) => {
                     warn!(
-                        ip_address = ip_address,
+                        ip_key = ip_key,
                         error = %e,
                         "Failed to deserialize behavior profile"
                     );
@@ -114,10 +161,11 @@ impl BehaviorAnalyzer {
 
     async fn update_behavior_profile(&self, context: &RequestContext) -> Result<()> {
         let mut conn = self.redis_client.get_async_connection().await?;
-        let key = format!("behavior:profile:{}", context.ip_address);
+        let ip_key = context.ip_key();
+        let key = format!("behavior:profile:{}", ip_key);
         
         // Get existing profile or create new one
-        let mut profile = self.get_behavior_profile(&context.ip_address)
+        let mut profile = self.get_behavior_profile(&ip_key)
             .await?
             .unwrap_or_else(|| BehaviorProfile {
                 ip_address: context.ip_address.clone(),
@@ -130,6 +178,7 @@ impl BehaviorAnalyzer {
                 error_count: 0,
                 total_response_time: 0,
                 request_sizes: Vec::new(),
+                geographic_locations: HashMap::new(),
             });
 
         // Update profile with current request
@@ -144,6 +193,10 @@ impl BehaviorAnalyzer {
             *profile.user_agents.entry(ua.clone()).or_insert(0) += 1;
         }
         
+        if let Some(geolocation) = &context.geolocation {
+            *profile.geographic_locations.entry(geolocation.country.clone()).or_insert(0) += 1;
+        }
+        
         // Update hourly distribution
         let hour = context.timestamp.hour() as usize;
         if hour < 24 {
@@ -168,6 +221,20 @@ impl BehaviorAnalyzer {
 
     async fn analyze_patterns(&self, context: &RequestContext, profile: &BehaviorProfile) -> Vec<BehaviorPattern> {
         let mut patterns = Vec::new();
+
+        if let Some(custom) = &self.patterns {
+            patterns.extend(custom.matches(&PatternInput {
+                context,
+                request_rate: self.requests_per_minute(context),
+                error_rate: profile.error_count as f64 / profile.request_count.max(1) as f64,
+                unique_endpoints: profile.endpoints.len(),
+                request_count: profile.request_count,
+                average_response_time_ms: profile.total_response_time as f64 / profile.request_count.max(1) as f64,
+            }));
+            if !custom.keeps_builtin() {
+                return patterns;
+            }
+        }
         
         // Analyze request frequency
         patterns.extend(self.analyze_request_frequency(context, profile).await);
@@ -183,21 +250,28 @@ impl BehaviorAnalyzer {
         
         // Analyze error patterns
         patterns.extend(self.analyze_error_patterns(context, profile).await);
+        
+        // Analyze geographic patterns
+        patterns.extend(self.analyze_geographic_patterns(context, profile).await);
 
         patterns
     }
 
-    async fn analyze_request_frequency(&self, context: &RequestContext, profile: &BehaviorProfile) -> Vec<BehaviorPattern> {
-        let mut patterns = Vec::new();
-        
-        // Calculate requests per minute over the analysis window
+    /// Requests per minute over the analysis window
+    fn requests_per_minute(&self, context: &RequestContext) -> f64 {
         let window_start = context.timestamp - Duration::minutes(self.config.analysis_window_minutes);
         let recent_requests = context.previous_requests
             .iter()
             .filter(|req| req.timestamp > window_start)
             .count() as f64;
+
+        recent_requests / self.config.analysis_window_minutes as f64
+    }
+
+    async fn analyze_request_frequency(&self, context: &RequestContext, profile: &BehaviorProfile) -> Vec<BehaviorPattern> {
+        let mut patterns = Vec::new();
         
-        let requests_per_minute = recent_requests / self.config.analysis_window_minutes as f64;
+        let requests_per_minute = self.requests_per_minute(context);
         
         // Define thresholds based on historical behavior
         let avg_requests_per_minute = profile.request_count as f64 / 
@@ -384,6 +458,129 @@ impl BehaviorAnalyzer {
         patterns
     }
 
+    /// Whether the profile is still inside its learning window at `now`
+    fn is_learning(&self, profile: &BehaviorProfile, now: DateTime<Utc>) -> bool {
+        self.config.learning_mode && now - profile.first_seen < Duration::hours(self.config.learning_period_hours)
+    }
+
+    async fn score_profile(
+        &self,
+        context: &RequestContext,
+        profile: &BehaviorProfile,
+        weight_multipliers: &HashMap<String, f64>,
+    ) -> Result<ThreatScore> {
+        // Profiles are still updated while learning; only scoring waits for the baseline
+        if self.is_learning(profile, context.timestamp) {
+            let remaining = profile.first_seen + Duration::hours(self.config.learning_period_hours) - context.timestamp;
+            return Ok(ThreatScore::new(
+                "behavior_analysis".to_string(),
+                0.0,
+                0.3,
+            )
+            .with_reason("learning".to_string())
+            .with_metadata("request_count".to_string(), serde_json::Value::Number(profile.request_count.into()))
+            .with_metadata("learning_remaining_minutes".to_string(), serde_json::Value::Number(
+                remaining.num_minutes().into()
+            )));
+        }
+
+        // Need minimum requests for meaningful analysis
+        if profile.request_count < self.config.min_requests_for_analysis as u64 {
+            return Ok(ThreatScore::new(
+                "behavior_analysis".to_string(),
+                0.0,
+                0.3,
+            ).with_reason("Insufficient data for behavior analysis".to_string()));
+        }
+
+        // Analyze behavior patterns
+        let patterns = self.analyze_patterns(context, profile).await;
+        
+        if patterns.is_empty() {
+            return Ok(ThreatScore::new(
+                "behavior_analysis".to_string(),
+                0.0,
+                0.8,
+            ).with_reason("No suspicious behavior patterns detected".to_string()));
+        }
+
+        // Calculate combined risk score
+        let risk_score = self.calculate_combined_risk_score(&patterns, weight_multipliers);
+        let confidence = patterns.iter().map(|p| p.confidence).sum::<f64>() / patterns.len() as f64;
+        
+        let reasons: Vec<String> = patterns.iter().map(|p| p.description.clone()).collect();
+        
+        let mut threat_score = ThreatScore::new(
+            "behavior_analysis".to_string(),
+            risk_score,
+            confidence,
+        ).with_reasons(reasons);
+
+        // Add metadata
+        threat_score = threat_score
+            .with_metadata("patterns_detected".to_string(), serde_json::Value::Number(patterns.len().into()))
+            .with_metadata("pattern_types".to_string(), patterns.iter().map(|p| p.pattern_type.name()).collect())
+            .with_metadata("request_count".to_string(), serde_json::Value::Number(profile.request_count.into()))
+            .with_metadata("profile_age_hours".to_string(), serde_json::Value::Number(
+                (profile.last_seen - profile.first_seen).num_hours().into()
+            ));
+
+        debug!(
+            ip_address = context.ip_address,
+            risk_score = risk_score,
+            patterns_count = patterns.len(),
+            "Behavior analysis completed"
+        );
+
+        Ok(threat_score)
+    }
+
+    async fn analyze_geographic_patterns(&self, context: &RequestContext, profile: &BehaviorProfile) -> Vec<BehaviorPattern> {
+        let mut patterns = Vec::new();
+        
+        let Some(geolocation) = &context.geolocation else {
+            return patterns;
+        };
+        
+        // The profile already counts this request, so a first visit from a country is 1
+        let current_country_requests = profile.geographic_locations.get(&geolocation.country).copied().unwrap_or(0);
+        let other_country_requests: u32 = profile.geographic_locations
+            .iter()
+            .filter(|(country, _)| **country != geolocation.country)
+            .map(|(_, count)| count)
+            .sum();
+        
+        // Only meaningful once the IP has an established location history
+        if current_country_requests <= 1 && other_country_requests >= self.config.min_requests_for_analysis {
+            let mut previous_countries: Vec<&str> = profile.geographic_locations
+                .keys()
+                .map(String::as_str)
+                .filter(|country| *country != geolocation.country)
+                .collect();
+            previous_countries.sort_unstable();
+            let confidence = (other_country_requests as f64 / (other_country_requests as f64 + 10.0)).min(0.95);
+            
+            patterns.push(BehaviorPattern {
+                pattern_type: PatternType::GeographicAnomaly,
+                confidence,
+                description: format!(
+                    "Sudden location change: first request from {} after {} requests from {}",
+                    geolocation.country,
+                    other_country_requests,
+                    previous_countries.join(", ")
+                ),
+                risk_score: 0.7,
+                evidence: vec![
+                    format!("Current country: {}", geolocation.country),
+                    format!("Previous countries: {}", previous_countries.join(", ")),
+                    format!("Requests from previous countries: {}", other_country_requests),
+                ],
+            });
+        }
+        
+        patterns
+    }
+
     fn calculate_entropy(&self, distribution: &HashMap<String, u32>) -> f64 {
         let total: u32 = distribution.values().sum();
         if total == 0 {
@@ -418,7 +615,10 @@ impl BehaviorAnalyzer {
         entropy
     }
 
-    fn calculate_combined_risk_score(&self, patterns: &[BehaviorPattern]) -> f64 {
+    /// Weighted average of pattern risk. Feedback multipliers scale each
+    /// pattern's contribution rather than its share of the average, so a
+    /// pattern that keeps producing false positives lowers the score itself
+    fn calculate_combined_risk_score(&self, patterns: &[BehaviorPattern], weight_multipliers: &HashMap<String, f64>) -> f64 {
         if patterns.is_empty() {
             return 0.0;
         }
@@ -428,13 +628,15 @@ impl BehaviorAnalyzer {
         let mut weighted_score = 0.0;
         
         for pattern in patterns {
+            let pattern_type = pattern.pattern_type.name();
             let weight = self.config.pattern_weights
-                .get(&format!("{:?}", pattern.pattern_type))
+                .get(&pattern_type)
                 .copied()
                 .unwrap_or(1.0);
+            let multiplier = weight_multipliers.get(&pattern_type).copied().unwrap_or(1.0);
             
             total_weight += weight;
-            weighted_score += pattern.risk_score * weight;
+            weighted_score += pattern.risk_score * weight * multiplier;
         }
         
         if total_weight > 0.0 {
@@ -456,17 +658,19 @@ impl ThreatAnalyzer for BehaviorAnalyzer {
             ).with_reason("Behavior analyzer disabled".to_string()));
         }
 
-        // Update behavior profile
-        if let Err(e) = self.update_behavior_profile(context).await {
-            error!(
-                ip_address = context.ip_address,
-                error = %e,
-                "Failed to update behavior profile"
-            );
+        // Update behavior profile; an evaluation is scored against it as it stands
+        if !context.evaluate_only {
+            if let Err(e) = self.update_behavior_profile(context).await {
+                error!(
+                    ip_address = context.ip_address,
+                    error = %e,
+                    "Failed to update behavior profile"
+                );
+            }
         }
 
         // Get current behavior profile
-        let profile = match self.get_behavior_profile(&context.ip_address).await? {
+        let profile = match self.get_behavior_profile(&context.ip_key()).await? {
             Some(profile) => profile,
             None => {
                 return Ok(ThreatScore::new(
@@ -477,53 +681,29 @@ impl ThreatAnalyzer for BehaviorAnalyzer {
             }
         };
 
-        // Need minimum requests for meaningful analysis
-        if profile.request_count < self.config.min_requests_for_analysis as u64 {
-            return Ok(ThreatScore::new(
-                "behavior_analysis".to_string(),
-                0.0,
-                0.3,
-            ).with_reason("Insufficient data for behavior analysis".to_string()));
-        }
+        let weight_multipliers = match &self.feedback {
+            Some(feedback) => feedback.weight_multipliers().await.unwrap_or_else(|e| {
+                warn!(error = %e, "Failed to load feedback weight multipliers");
+                HashMap::new()
+            }),
+            None => HashMap::new(),
+        };
+        let threat_score = self.score_profile(context, &profile, &weight_multipliers).await?;
 
-        // Analyze behavior patterns
-        let patterns = self.analyze_patterns(context, &profile).await;
-        
-        if patterns.is_empty() {
-            return Ok(ThreatScore::new(
-                "behavior_analysis".to_string(),
-                0.0,
-                0.8,
-            ).with_reason("No suspicious behavior patterns detected".to_string()));
+        let feedback = self.feedback.as_ref().filter(|_| !context.evaluate_only);
+        if let (Some(feedback), Some(serde_json::Value::Array(pattern_types))) =
+            (feedback, threat_score.metadata.get("pattern_types"))
+        {
+            let analysis = FlaggedAnalysis {
+                ip_address: context.ip_address.clone(),
+                patterns: pattern_types.iter().filter_map(|p| p.as_str().map(str::to_string)).collect(),
+                score: threat_score.score,
+            };
+            if let Err(e) = feedback.record_analysis(context.correlation_id, &analysis).await {
+                warn!(error = %e, "Failed to record flagged analysis for feedback");
+            }
         }
 
-        // Calculate combined risk score
-        let risk_score = self.calculate_combined_risk_score(&patterns);
-        let confidence = patterns.iter().map(|p| p.confidence).sum::<f64>() / patterns.len() as f64;
-        
-        let reasons: Vec<String> = patterns.iter().map(|p| p.description.clone()).collect();
-        
-        let mut threat_score = ThreatScore::new(
-            "behavior_analysis".to_string(),
-            risk_score,
-            confidence,
-        ).with_reasons(reasons);
-
-        // Add metadata
-        threat_score = threat_score
-            .with_metadata("patterns_detected".to_string(), serde_json::Value::Number(patterns.len().into()))
-            .with_metadata("request_count".to_string(), serde_json::Value::Number(profile.request_count.into()))
-            .with_metadata("profile_age_hours".to_string(), serde_json::Value::Number(
-                (profile.last_seen - profile.first_seen).num_hours().into()
-            ));
-
-        debug!(
-            ip_address = context.ip_address,
-            risk_score = risk_score,
-            patterns_count = patterns.len(),
-            "Behavior analysis completed"
-        );
-
         Ok(threat_score)
     }
 
@@ -546,6 +726,10 @@ impl ThreatAnalyzer for BehaviorAnalyzer {
         }
         Ok(())
     }
+
+    async fn health_check(&self) -> Result<AnalyzerHealth> {
+        Ok(AnalyzerHealth::of_redis(self.analyzer_id(), &self.redis_client).await)
+    }
 }
 
 impl Default for BehaviorAnalysisConfig {
@@ -556,6 +740,7 @@ impl Default for BehaviorAnalysisConfig {
         pattern_weights.insert("SuspiciousUserAgent".to_string(), 1.0);
         pattern_weights.insert("TimeBasedAnomaly".to_string(), 0.8);
         pattern_weights.insert("ErrorRateAnomaly".to_string(), 1.3);
+        pattern_weights.insert("GeographicAnomaly".to_string(), 1.1);
         
         Self {
             analysis_window_minutes: 15,
@@ -563,6 +748,7 @@ impl Default for BehaviorAnalysisConfig {
             anomaly_threshold: 0.6,
             pattern_weights,
             enable_ml_detection: false,
+            learning_mode: true,
             learning_period_hours: 24,
         }
     }
@@ -576,9 +762,11 @@ mod tests {
     #[test]
     fn test_entropy_calculation() {
         let analyzer = BehaviorAnalyzer {
-            redis_client: redis::Client::open("redis://127.0.0.1:6379").unwrap(),
+            redis_client: RedisConnector::open("redis://127.0.0.1:6379").unwrap(),
             config: BehaviorAnalysisConfig::default(),
             enabled: true,
+            feedback: None,
+            patterns: None,
         };
 
         // Test uniform distribution (high entropy)
@@ -605,9 +793,11 @@ mod tests {
     #[test]
     fn test_pattern_risk_calculation() {
         let analyzer = BehaviorAnalyzer {
-            redis_client: redis::Client::open("redis://127.0.0.1:6379").unwrap(),
+            redis_client: RedisConnector::open("redis://127.0.0.1:6379").unwrap(),
             config: BehaviorAnalysisConfig::default(),
             enabled: true,
+            feedback: None,
+            patterns: None,
         };
 
         let patterns = vec![
@@ -627,7 +817,167 @@ mod tests {
             },
         ];
 
-        let combined_score = analyzer.calculate_combined_risk_score(&patterns);
+        let combined_score = analyzer.calculate_combined_risk_score(&patterns, &HashMap::new());
         assert!(combined_score > 0.0 && combined_score <= 1.0);
+
+        // Feedback on one pattern lowers the combined score
+        let multipliers = HashMap::from([("SuspiciousUserAgent".to_string(), 0.5)]);
+        assert!(analyzer.calculate_combined_risk_score(&patterns, &multipliers) < combined_score);
+    }
+
+    fn suspicious_profile(first_seen: DateTime<Utc>, now: DateTime<Utc>) -> (RequestContext, BehaviorProfile) {
+        let context = RequestContext::new("203.0.113.9".to_string(), "/api/check".to_string(), "POST".to_string())
+            .with_user_agent("python-requests scanner bot".to_string());
+        let context = RequestContext { timestamp: now, ..context };
+        let profile = BehaviorProfile {
+            ip_address: context.ip_address.clone(),
+            first_seen,
+            last_seen: now,
+            request_count: 100,
+            endpoints: HashMap::from([("/api/check".to_string(), 100)]),
+            user_agents: HashMap::from([("python-requests scanner bot".to_string(), 100)]),
+            hourly_distribution: [0; 24],
+            error_count: 80,
+            total_response_time: 0,
+            request_sizes: Vec::new(),
+            geographic_locations: HashMap::new(),
+        };
+        (context, profile)
+    }
+
+    #[tokio::test]
+    async fn test_learning_mode_scores_zero_until_baseline_matures() {
+        let analyzer = BehaviorAnalyzer {
+            redis_client: RedisConnector::open("redis://127.0.0.1:6379").unwrap(),
+            config: BehaviorAnalysisConfig::default(),
+            enabled: true,
+            feedback: None,
+            patterns: None,
+        };
+        let first_seen = Utc::now();
+
+        // Suspicious user agent and error rate, but the IP is still being learned
+        for hours in [0, 1, 23] {
+            let (context, profile) = suspicious_profile(first_seen, first_seen + Duration::hours(hours));
+            let score = analyzer.score_profile(&context, &profile, &HashMap::new()).await.unwrap();
+            assert_eq!(score.score, 0.0);
+            assert_eq!(score.reasons, vec!["learning".to_string()]);
+        }
+
+        let (context, profile) = suspicious_profile(first_seen, first_seen + Duration::hours(24));
+        let score = analyzer.score_profile(&context, &profile, &HashMap::new()).await.unwrap();
+        assert!(score.score > 0.0);
+        assert!(!score.reasons.contains(&"learning".to_string()));
     }
-}
\ No newline at end of file
+
+    #[tokio::test]
+    async fn test_learning_mode_disabled_scores_immediately() {
+        let analyzer = BehaviorAnalyzer {
+            redis_client: RedisConnector::open("redis://127.0.0.1:6379").unwrap(),
+            config: BehaviorAnalysisConfig {
+                learning_mode: false,
+                ..BehaviorAnalysisConfig::default()
+            },
+            enabled: true,
+            feedback: None,
+            patterns: None,
+        };
+        let now = Utc::now();
+
+        let (context, profile) = suspicious_profile(now, now);
+        assert!(analyzer.score_profile(&context, &profile, &HashMap::new()).await.unwrap().score > 0.0);
+    }
+
+    #[tokio::test]
+    async fn test_custom_patterns_merge_with_or_replace_builtin() {
+        use crate::config::BehaviorPatternMode;
+        use crate::security::behavior_patterns::PatternDefinition;
+
+        let definition: PatternDefinition = serde_json::from_value(serde_json::json!({
+            "id": "failing_posts",
+            "risk_score": 0.9,
+            "indicators": [
+                { "indicator": "method", "comparison": "equals", "value": "POST" },
+                { "indicator": "error_rate", "comparison": "greater_than", "value": 0.5 },
+            ],
+        }))
+        .unwrap();
+        let now = Utc::now();
+        let (context, profile) = suspicious_profile(now, now);
+
+        for (mode, expected) in [
+            (BehaviorPatternMode::Merge, serde_json::json!(["failing_posts", "SuspiciousUserAgent", "ErrorRateAnomaly"])),
+            (BehaviorPatternMode::Replace, serde_json::json!(["failing_posts"])),
+        ] {
+            let analyzer = BehaviorAnalyzer {
+                redis_client: RedisConnector::open("redis://127.0.0.1:6379").unwrap(),
+                config: BehaviorAnalysisConfig {
+                    learning_mode: false,
+                    ..BehaviorAnalysisConfig::default()
+                },
+                enabled: true,
+                feedback: None,
+                patterns: Some(Arc::new(BehaviorPatterns::new(mode, vec![definition.clone()]).unwrap())),
+            };
+            let score = analyzer.score_profile(&context, &profile, &HashMap::new()).await.unwrap();
+            assert_eq!(score.metadata["pattern_types"], expected);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_sudden_country_change_is_a_geographic_anomaly() {
+        use crate::security::geoip::GeolocationInfo;
+
+        let analyzer = BehaviorAnalyzer {
+            redis_client: RedisConnector::open("redis://127.0.0.1:6379").unwrap(),
+            config: BehaviorAnalysisConfig {
+                learning_mode: false,
+                ..BehaviorAnalysisConfig::default()
+            },
+            enabled: true,
+            feedback: None,
+            patterns: None,
+        };
+        let located = |country: &str| {
+            RequestContext::new("81.2.69.142".to_string(), "/api/check".to_string(), "GET".to_string())
+                .with_geolocation(GeolocationInfo {
+                    country: country.to_string(),
+                    region: None,
+                    city: None,
+                    latitude: None,
+                    longitude: None,
+                })
+        };
+        let now = Utc::now();
+        let profile = |locations: &[(&str, u32)]| BehaviorProfile {
+            ip_address: "81.2.69.142".to_string(),
+            first_seen: now - Duration::days(3),
+            last_seen: now,
+            request_count: locations.iter().map(|(_, count)| *count as u64).sum(),
+            endpoints: HashMap::from([("/api/check".to_string(), 1)]),
+            user_agents: HashMap::new(),
+            hourly_distribution: [0; 24],
+            error_count: 0,
+            total_response_time: 0,
+            request_sizes: Vec::new(),
+            geographic_locations: locations.iter().map(|(country, count)| (country.to_string(), *count)).collect(),
+        };
+
+        // After 50 requests from GB, the first one from CN stands out
+        let score = analyzer
+            .score_profile(&located("CN"), &profile(&[("GB", 50), ("CN", 1)]), &HashMap::new())
+            .await
+            .unwrap();
+        assert!(score.score > 0.0);
+        assert_eq!(score.metadata["pattern_types"], serde_json::json!(["GeographicAnomaly"]));
+
+        // Staying in the usual country, or a country seen before, is not anomalous
+        for (country, locations) in [("GB", vec![("GB", 51)]), ("CN", vec![("GB", 50), ("CN", 5)])] {
+            let score = analyzer
+                .score_profile(&located(country), &profile(&locations), &HashMap::new())
+                .await
+                .unwrap();
+            assert_eq!(score.score, 0.0);
+        }
+    }
+}
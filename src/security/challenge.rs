@@ -0,0 +1,262 @@
+//! CAPTCHA challenges for borderline threat scores.
+//!
+//! A request scoring in `[min_score, block_score)` on a challenged path gets
+//! a 403 with a challenge token instead of being rejected outright. The
+//! client solves the configured provider's widget (Turnstile or hCaptcha)
+//! and retries with the token in `x-challenge-token` and the widget's
+//! response in `x-challenge-response`. Once the provider confirms it, the
+//! client's IP is recorded as solved in Redis for `solved_ttl_seconds`, and
+//! borderline scores from it are let through until that runs out. Scores at
+//! `block_score` and above are still blocked.
+
+use crate::config::secrets::SecretString;
+use crate::config::{ChallengeConfig, ChallengeProvider};
+use crate::redis_backend::RedisConnector;
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use redis::AsyncCommands;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Header carrying the token from the challenge response
+pub const TOKEN_HEADER: &str = "x-challenge-token";
+/// Header carrying the provider's widget response
+pub const RESPONSE_HEADER: &str = "x-challenge-response";
+
+const VERIFY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Checks a widget response with the CAPTCHA provider
+#[async_trait]
+pub trait CaptchaVerifier: Send + Sync {
+    async fn verify(&self, response: &str, remote_ip: &str) -> Result<bool>;
+}
+
+/// The `siteverify` API that Turnstile and hCaptcha both implement
+pub struct SiteVerifyClient {
+    http: reqwest::Client,
+    url: &'static str,
+    secret_key: SecretString,
+}
+
+#[derive(Deserialize)]
+struct SiteVerifyResponse {
+    success: bool,
+    #[serde(default, rename = "error-codes")]
+    error_codes: Vec<String>,
+}
+
+impl SiteVerifyClient {
+    pub fn new(provider: ChallengeProvider, secret_key: SecretString) -> Result<Self> {
+        let url = match provider {
+            ChallengeProvider::Turnstile => "https://challenges.cloudflare.com/turnstile/v0/siteverify",
+            ChallengeProvider::Hcaptcha => "https://api.hcaptcha.com/siteverify",
+        };
+        let http = reqwest::Client::builder()
+            .timeout(VERIFY_TIMEOUT)
+            .build()
+            .context("Failed to create CAPTCHA verification client")?;
+        Ok(Self { http, url, secret_key })
+    }
+}
+
+#[async_trait]
+impl CaptchaVerifier for SiteVerifyClient {
+    async fn verify(&self, response: &str, remote_ip: &str) -> Result<bool> {
+        let result: SiteVerifyResponse = self
+            .http
+            .post(self.url)
+            .form(&[
+                ("secret", self.secret_key.expose_secret()),
+                ("response", response),
+                ("remoteip", remote_ip),
+            ])
+            .send()
+            .await
+            .context("CAPTCHA verification request failed")?
+            .error_for_status()?
+            .json()
+            .await
+            .context("Unreadable CAPTCHA verification response")?;
+
+        if !result.success {
+            tracing::debug!(errors = ?result.error_codes, "CAPTCHA response rejected by provider");
+        }
+        Ok(result.success)
+    }
+}
+
+/// What a challenged client needs to render the widget and retry
+#[derive(Debug, Clone, Serialize)]
+pub struct ChallengeDetails {
+    pub token: String,
+    pub provider: ChallengeProvider,
+    pub site_key: String,
+    pub token_header: &'static str,
+    pub response_header: &'static str,
+}
+
+pub struct ChallengeManager {
+    redis: RedisConnector,
+    verifier: Arc<dyn CaptchaVerifier>,
+    config: ChallengeConfig,
+}
+
+impl ChallengeManager {
+    pub fn new(redis: RedisConnector, verifier: Arc<dyn CaptchaVerifier>, config: ChallengeConfig) -> Self {
+        Self { redis, verifier, config }
+    }
+
+    /// The manager for `[security.threat_detection.challenge]`, or `None` if it's disabled
+    pub fn from_config(redis: RedisConnector, config: &ChallengeConfig) -> Result<Option<Self>> {
+        if !config.enabled {
+            return Ok(None);
+        }
+        let secret_key = config
+            .secret_key
+            .clone()
+            .context("security.threat_detection.challenge.secret_key is required")?;
+        let verifier = Arc::new(SiteVerifyClient::new(config.provider, secret_key)?);
+        tracing::info!(provider = ?config.provider, "CAPTCHA challenges enabled");
+        Ok(Some(Self::new(redis, verifier, config.clone())))
+    }
+
+    fn token_key(token: &str) -> String {
+        format!("challenge:token:{}", token)
+    }
+
+    fn solved_key(ip_address: &str) -> String {
+        format!("challenge:solved:{}", ip_address)
+    }
+
+    /// Whether a request scoring `score` on `endpoint` should be challenged
+    /// rather than rejected
+    pub fn applies_to(&self, score: f64, endpoint: &str) -> bool {
+        score >= self.config.min_score
+            && score < self.config.block_score
+            && (self.config.paths.is_empty() || self.config.paths.iter().any(|path| endpoint.starts_with(path.as_str())))
+    }
+
+    /// Whether the client solved a challenge recently. Redis errors count
+    /// as unsolved, so the client is challenged again.
+    pub async fn is_solved(&self, ip_address: &str) -> bool {
+        let result: Result<bool> = async {
+            let mut conn = self.redis.get_async_connection().await?;
+            Ok(conn.exists(Self::solved_key(ip_address)).await?)
+        }
+        .await;
+        result.unwrap_or_else(|e| {
+            tracing::warn!(ip_address, "Failed to look up solved challenge: {:#}", e);
+            false
+        })
+    }
+
+    /// Issue a challenge token that only `ip_address` can redeem
+    pub async fn issue(&self, ip_address: &str) -> Result<String> {
+        let token = uuid::Uuid::new_v4().simple().to_string();
+        let mut conn = self.redis.get_async_connection().await?;
+        let _: () = conn
+            .set_ex(Self::token_key(&token), ip_address, self.config.token_ttl_seconds)
+            .await?;
+        Ok(token)
+    }
+
+    pub fn details(&self, token: String) -> ChallengeDetails {
+        ChallengeDetails {
+            token,
+            provider: self.config.provider,
+            site_key: self.config.site_key.clone(),
+            token_header: TOKEN_HEADER,
+            response_header: RESPONSE_HEADER,
+        }
+    }
+
+    /// Redeem `token` with the provider's widget `response`. On success the
+    /// client is recorded as solved and true is returned. A token can be
+    /// redeemed once, and only from the IP it was issued to.
+    pub async fn redeem(&self, ip_address: &str, token: &str, response: &str) -> Result<bool> {
+        let mut conn = self.redis.get_async_connection().await?;
+        let key = Self::token_key(token);
+        let (issued_to,): (Option<String>,) = redis::pipe()
+            .atomic()
+            .get(&key)
+            .del(&key)
+            .ignore()
+            .query_async(&mut conn)
+            .await?;
+        if issued_to.as_deref() != Some(ip_address) {
+            return Ok(false);
+        }
+
+        if !self.verifier.verify(response, ip_address).await? {
+            return Ok(false);
+        }
+        let _: () = conn
+            .set_ex(Self::solved_key(ip_address), 1, self.config.solved_ttl_seconds)
+            .await?;
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Accepts exactly one widget response
+    struct StaticVerifier(&'static str);
+
+    #[async_trait]
+    impl CaptchaVerifier for StaticVerifier {
+        async fn verify(&self, response: &str, _remote_ip: &str) -> Result<bool> {
+            Ok(response == self.0)
+        }
+    }
+
+    fn manager(config: ChallengeConfig) -> ChallengeManager {
+        ChallengeManager::new(
+            RedisConnector::open("redis://127.0.0.1:6379").unwrap(),
+            Arc::new(StaticVerifier("solved")),
+            config,
+        )
+    }
+
+    #[test]
+    fn test_challenge_band_and_paths() {
+        let manager = manager(ChallengeConfig {
+            paths: vec!["/login".to_string(), "/signup".to_string()],
+            ..ChallengeConfig::default()
+        });
+
+        assert!(manager.applies_to(0.6, "/login"));
+        assert!(manager.applies_to(0.79, "/signup/confirm"));
+        assert!(!manager.applies_to(0.8, "/login"));
+        assert!(!manager.applies_to(0.5, "/login"));
+        assert!(!manager.applies_to(0.7, "/v1/check"));
+    }
+
+    #[tokio::test]
+    async fn test_solved_challenge_is_remembered_per_ip() {
+        let manager = manager(ChallengeConfig::default());
+        let ip = format!("198.51.100.{}", rand::random::<u8>());
+        let Ok(token) = manager.issue(&ip).await else {
+            // Redis not available
+            return;
+        };
+
+        assert!(!manager.is_solved(&ip).await);
+        // Wrong response; the token is used up either way
+        assert!(!manager.redeem(&ip, &token, "guess").await.unwrap());
+        assert!(!manager.redeem(&ip, &token, "solved").await.unwrap());
+
+        // Another IP can't redeem someone else's token
+        let token = manager.issue(&ip).await.unwrap();
+        assert!(!manager.redeem("203.0.113.1", &token, "solved").await.unwrap());
+
+        let token = manager.issue(&ip).await.unwrap();
+        assert!(manager.redeem(&ip, &token, "solved").await.unwrap());
+        assert!(manager.is_solved(&ip).await);
+
+        let mut conn = manager.redis.get_async_connection().await.unwrap();
+        let _: () = conn.del(ChallengeManager::solved_key(&ip)).await.unwrap();
+    }
+}
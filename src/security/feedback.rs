@@ -0,0 +1,236 @@
+use crate::redis_backend::RedisConnector;
+use anyhow::Result;
+use redis::AsyncCommands;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+/// Each confirmed false positive multiplies the weight of the patterns involved by this
+pub const FALSE_POSITIVE_DECAY: f64 = 0.8;
+/// Weights never decay below this, so a pattern can't be silenced entirely
+pub const MIN_WEIGHT_MULTIPLIER: f64 = 0.2;
+
+/// Flagged analyses and their feedback expire after this long
+const FEEDBACK_TTL_SECONDS: u64 = 86400 * 30;
+
+/// What an analyzer flagged for one request, kept so feedback can find it later
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FlaggedAnalysis {
+    pub ip_address: String,
+    pub patterns: Vec<String>,
+    pub score: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FeedbackRecord {
+    pub correlation_id: Uuid,
+    pub was_false_positive: bool,
+    pub ip_address: String,
+    pub patterns: Vec<String>,
+    pub submitted_at: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FeedbackOutcome {
+    pub record: FeedbackRecord,
+    /// False if feedback for this correlation ID had already been applied
+    pub applied: bool,
+    pub false_positives: u64,
+    /// Effective multiplier of each pattern in the analysis after this feedback
+    pub weight_multipliers: HashMap<String, f64>,
+}
+
+/// Operator feedback on threat verdicts, and the pattern weight multipliers it tunes
+#[derive(Debug, Clone)]
+pub struct FeedbackStore {
+    redis_client: RedisConnector,
+    key_prefix: String,
+}
+
+impl FeedbackStore {
+    pub fn new(redis_client: RedisConnector) -> Self {
+        Self {
+            redis_client,
+            key_prefix: "security:feedback".to_string(),
+        }
+    }
+
+    /// Keep all keys under `prefix` instead of `security:feedback`
+    pub fn with_key_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.key_prefix = prefix.into();
+        self
+    }
+
+    /// Remember which patterns flagged a request so feedback on it can tune them
+    pub async fn record_analysis(&self, correlation_id: Uuid, analysis: &FlaggedAnalysis) -> Result<()> {
+        let mut conn = self.redis_client.get_async_connection().await?;
+        let _: () = conn
+            .set_ex(self.analysis_key(correlation_id), serde_json::to_string(analysis)?, FEEDBACK_TTL_SECONDS)
+            .await?;
+        Ok(())
+    }
+
+    /// Apply feedback for a flagged request; `None` if no analysis is recorded for it.
+    ///
+    /// A false positive decays the multiplier of every pattern that fired; a
+    /// confirmed threat recovers them toward 1.0. Repeat feedback for the same
+    /// correlation ID is returned as-is without adjusting weights again.
+    pub async fn submit(&self, correlation_id: Uuid, was_false_positive: bool) -> Result<Option<FeedbackOutcome>> {
+        let mut conn = self.redis_client.get_async_connection().await?;
+
+        let existing: Option<String> = conn.get(self.feedback_key(correlation_id)).await?;
+        if let Some(existing) = existing {
+            return self.already_applied(&existing).await.map(Some);
+        }
+
+        let analysis: Option<String> = conn.get(self.analysis_key(correlation_id)).await?;
+        let analysis: FlaggedAnalysis = match analysis {
+            Some(analysis) => serde_json::from_str(&analysis)?,
+            None => return Ok(None),
+        };
+
+        let record = FeedbackRecord {
+            correlation_id,
+            was_false_positive,
+            ip_address: analysis.ip_address,
+            patterns: analysis.patterns,
+            submitted_at: chrono::Utc::now(),
+        };
+        // SET NX so concurrent submissions for one request only tune weights once
+        let stored: bool = redis::cmd("SET")
+            .arg(self.feedback_key(correlation_id))
+            .arg(serde_json::to_string(&record)?)
+            .arg("NX")
+            .arg("EX")
+            .arg(FEEDBACK_TTL_SECONDS)
+            .query_async::<_, Option<String>>(&mut conn)
+            .await?
+            .is_some();
+        if !stored {
+            let existing: String = conn.get(self.feedback_key(correlation_id)).await?;
+            return self.already_applied(&existing).await.map(Some);
+        }
+
+        let counter = if was_false_positive { "false_positives" } else { "true_positives" };
+        let _: u64 = conn.incr(format!("{}:{}", self.key_prefix, counter), 1).await?;
+
+        let mut multipliers = self.multipliers_for(&record.patterns).await?;
+        for (pattern, multiplier) in multipliers.iter_mut() {
+            *multiplier = adjust_multiplier(*multiplier, was_false_positive);
+            let _: () = conn.hset(self.weights_key(), pattern, *multiplier).await?;
+        }
+
+        Ok(Some(FeedbackOutcome {
+            false_positives: self.false_positive_count().await?,
+            weight_multipliers: multipliers,
+            record,
+            applied: true,
+        }))
+    }
+
+    /// Learned multiplier for every pattern that has received feedback
+    pub async fn weight_multipliers(&self) -> Result<HashMap<String, f64>> {
+        let mut conn = self.redis_client.get_async_connection().await?;
+        Ok(conn.hgetall(self.weights_key()).await?)
+    }
+
+    pub async fn false_positive_count(&self) -> Result<u64> {
+        let mut conn = self.redis_client.get_async_connection().await?;
+        let count: Option<u64> = conn.get(format!("{}:false_positives", self.key_prefix)).await?;
+        Ok(count.unwrap_or(0))
+    }
+
+    async fn already_applied(&self, stored_record: &str) -> Result<FeedbackOutcome> {
+        let record: FeedbackRecord = serde_json::from_str(stored_record)?;
+        Ok(FeedbackOutcome {
+            weight_multipliers: self.multipliers_for(&record.patterns).await?,
+            false_positives: self.false_positive_count().await?,
+            record,
+            applied: false,
+        })
+    }
+
+    async fn multipliers_for(&self, patterns: &[String]) -> Result<HashMap<String, f64>> {
+        let learned = self.weight_multipliers().await?;
+        Ok(patterns
+            .iter()
+            .map(|pattern| (pattern.clone(), learned.get(pattern).copied().unwrap_or(1.0)))
+            .collect())
+    }
+
+    fn analysis_key(&self, correlation_id: Uuid) -> String {
+        format!("{}:analysis:{}", self.key_prefix, correlation_id)
+    }
+
+    fn feedback_key(&self, correlation_id: Uuid) -> String {
+        format!("{}:{}", self.key_prefix, correlation_id)
+    }
+
+    fn weights_key(&self) -> String {
+        format!("{}:weights", self.key_prefix)
+    }
+}
+
+fn adjust_multiplier(multiplier: f64, was_false_positive: bool) -> f64 {
+    if was_false_positive {
+        (multiplier * FALSE_POSITIVE_DECAY).max(MIN_WEIGHT_MULTIPLIER)
+    } else {
+        (multiplier / FALSE_POSITIVE_DECAY).min(1.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_multiplier_decays_to_floor_and_recovers() {
+        let mut multiplier = 1.0;
+        for _ in 0..20 {
+            multiplier = adjust_multiplier(multiplier, true);
+        }
+        assert_eq!(multiplier, MIN_WEIGHT_MULTIPLIER);
+
+        for _ in 0..20 {
+            multiplier = adjust_multiplier(multiplier, false);
+        }
+        assert_eq!(multiplier, 1.0);
+    }
+
+    #[tokio::test]
+    async fn test_false_positive_feedback_is_counted_and_decays_weights() {
+        let redis = RedisConnector::open("redis://127.0.0.1:6379").unwrap();
+        if redis.get_async_connection().await.is_err() {
+            println!("Skipping feedback test - Redis not available");
+            return;
+        }
+        let store = FeedbackStore::new(redis).with_key_prefix(format!("test:feedback:{}", Uuid::new_v4()));
+
+        let correlation_id = Uuid::new_v4();
+        assert!(store.submit(correlation_id, true).await.unwrap().is_none());
+
+        store
+            .record_analysis(
+                correlation_id,
+                &FlaggedAnalysis {
+                    ip_address: "203.0.113.9".to_string(),
+                    patterns: vec!["SuspiciousUserAgent".to_string()],
+                    score: 0.7,
+                },
+            )
+            .await
+            .unwrap();
+
+        let outcome = store.submit(correlation_id, true).await.unwrap().unwrap();
+        assert!(outcome.applied);
+        assert_eq!(outcome.false_positives, 1);
+        assert_eq!(outcome.weight_multipliers["SuspiciousUserAgent"], FALSE_POSITIVE_DECAY);
+        assert_eq!(store.weight_multipliers().await.unwrap()["SuspiciousUserAgent"], FALSE_POSITIVE_DECAY);
+
+        // Resubmitting doesn't count or decay twice
+        let repeat = store.submit(correlation_id, true).await.unwrap().unwrap();
+        assert!(!repeat.applied);
+        assert_eq!(store.false_positive_count().await.unwrap(), 1);
+        assert_eq!(repeat.weight_multipliers["SuspiciousUserAgent"], FALSE_POSITIVE_DECAY);
+    }
+}
@@ -0,0 +1,249 @@
+//! Country lookups for request IPs.
+//!
+//! `GeoIpResolver` fills `RequestContext::geolocation` from a MaxMind
+//! GeoLite2/GeoIP2 database (with the `geoip` feature) so SIEM events carry a
+//! location and `BehaviorAnalyzer` can spot an IP's traffic suddenly coming
+//! from a new country. Private, loopback and other non-routable addresses are
+//! never looked up.
+
+use crate::config::GeoIpConfig;
+use crate::security::threat_analyzer::RequestContext;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::net::IpAddr;
+use std::sync::Mutex;
+use tracing::debug;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GeolocationInfo {
+    /// ISO 3166-1 alpha-2 country code
+    pub country: String,
+    pub region: Option<String>,
+    pub city: Option<String>,
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+}
+
+/// A database that maps IPs to locations; `Ok(None)` for IPs it doesn't cover
+pub trait GeoIpDatabase: Send + Sync {
+    fn lookup(&self, ip: IpAddr) -> Result<Option<GeolocationInfo>>;
+}
+
+/// A MaxMind `.mmdb` file, Country or City edition
+#[cfg(feature = "geoip")]
+pub struct MaxMindDatabase(maxminddb::Reader<Vec<u8>>);
+
+#[cfg(feature = "geoip")]
+impl MaxMindDatabase {
+    pub fn open(path: &str) -> Result<Self> {
+        use anyhow::Context;
+
+        let reader = maxminddb::Reader::open_readfile(path)
+            .with_context(|| format!("Failed to open GeoIP database: {}", path))?;
+        Ok(Self(reader))
+    }
+}
+
+#[cfg(feature = "geoip")]
+impl GeoIpDatabase for MaxMindDatabase {
+    fn lookup(&self, ip: IpAddr) -> Result<Option<GeolocationInfo>> {
+        // City records are a superset of Country ones, so this reads both editions
+        let record = match self.0.lookup::<maxminddb::geoip2::City>(ip) {
+            Ok(record) => record,
+            Err(maxminddb::MaxMindDBError::AddressNotFoundError(_)) => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+
+        let Some(country) = record.country.as_ref().and_then(|country| country.iso_code) else {
+            return Ok(None);
+        };
+        let english_name = |names: &Option<std::collections::BTreeMap<&str, &str>>| {
+            names.as_ref().and_then(|names| names.get("en")).map(|name| name.to_string())
+        };
+
+        Ok(Some(GeolocationInfo {
+            country: country.to_string(),
+            region: record
+                .subdivisions
+                .as_ref()
+                .and_then(|subdivisions| subdivisions.first())
+                .and_then(|subdivision| subdivision.iso_code)
+                .map(str::to_string),
+            city: record.city.as_ref().and_then(|city| english_name(&city.names)),
+            latitude: record.location.as_ref().and_then(|location| location.latitude),
+            longitude: record.location.as_ref().and_then(|location| location.longitude),
+        }))
+    }
+}
+
+/// Cached IP-to-location lookups in front of a `GeoIpDatabase`
+pub struct GeoIpResolver {
+    database: Box<dyn GeoIpDatabase>,
+    cache: Mutex<LookupCache>,
+}
+
+/// Bounded map of past lookups, evicting the oldest entry when full. Misses
+/// are cached too so unknown IPs don't hit the database on every request.
+struct LookupCache {
+    entries: HashMap<IpAddr, Option<GeolocationInfo>>,
+    order: VecDeque<IpAddr>,
+    capacity: usize,
+}
+
+impl GeoIpResolver {
+    pub fn new(database: impl GeoIpDatabase + 'static, cache_size: usize) -> Self {
+        Self {
+            database: Box::new(database),
+            cache: Mutex::new(LookupCache {
+                entries: HashMap::new(),
+                order: VecDeque::new(),
+                capacity: cache_size.max(1),
+            }),
+        }
+    }
+
+    /// The resolver for `[security.threat_detection.geoip]`, or `None` if it's disabled
+    pub fn from_config(config: &GeoIpConfig) -> Result<Option<Self>> {
+        if !config.enabled {
+            return Ok(None);
+        }
+
+        #[cfg(feature = "geoip")]
+        {
+            let database = MaxMindDatabase::open(&config.database_path)?;
+            tracing::info!(database_path = %config.database_path, "GeoIP enrichment enabled");
+            Ok(Some(Self::new(database, config.cache_size)))
+        }
+
+        #[cfg(not(feature = "geoip"))]
+        {
+            tracing::warn!("GeoIP is enabled in configuration but ratewatch was built without the `geoip` feature");
+            Ok(None)
+        }
+    }
+
+    /// Location of `ip`, or `None` if it's unparseable, non-routable or not in the database
+    pub fn resolve(&self, ip: &str) -> Option<GeolocationInfo> {
+        let ip: IpAddr = ip.trim().parse().ok()?;
+        if !is_public(ip) {
+            return None;
+        }
+
+        if let Some(cached) = self.cache.lock().unwrap().entries.get(&ip) {
+            return cached.clone();
+        }
+
+        let location = match self.database.lookup(ip) {
+            Ok(location) => location,
+            Err(e) => {
+                // Not cached, so a transient failure doesn't stick
+                debug!(ip_address = %ip, error = %e, "GeoIP lookup failed");
+                return None;
+            }
+        };
+
+        let mut cache = self.cache.lock().unwrap();
+        if cache.entries.len() >= cache.capacity {
+            if let Some(oldest) = cache.order.pop_front() {
+                cache.entries.remove(&oldest);
+            }
+        }
+        if cache.entries.insert(ip, location.clone()).is_none() {
+            cache.order.push_back(ip);
+        }
+        location
+    }
+
+    /// Set the context's geolocation from its IP address
+    pub fn enrich(&self, context: &mut RequestContext) {
+        context.geolocation = self.resolve(&context.ip_address);
+    }
+}
+
+/// Whether a GeoIP database could know where `ip` is
+pub(crate) fn is_public(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            !(ip.is_private()
+                || ip.is_loopback()
+                || ip.is_link_local()
+                || ip.is_unspecified()
+                || ip.is_broadcast()
+                || ip.is_documentation())
+        }
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(ip) => is_public(IpAddr::V4(ip)),
+            None => !(ip.is_loopback() || ip.is_unspecified() || ip.is_unique_local() || ip.is_unicast_link_local()),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    /// Every IP is in Sweden; counts how often it's asked
+    struct CountingDatabase(Arc<AtomicUsize>);
+
+    impl GeoIpDatabase for CountingDatabase {
+        fn lookup(&self, _ip: IpAddr) -> Result<Option<GeolocationInfo>> {
+            self.0.fetch_add(1, Ordering::SeqCst);
+            Ok(Some(GeolocationInfo {
+                country: "SE".to_string(),
+                region: None,
+                city: None,
+                latitude: None,
+                longitude: None,
+            }))
+        }
+    }
+
+    #[test]
+    fn test_non_routable_ips_are_not_looked_up() {
+        let lookups = Arc::new(AtomicUsize::new(0));
+        let resolver = GeoIpResolver::new(CountingDatabase(lookups.clone()), 10);
+
+        for ip in ["127.0.0.1", "10.1.2.3", "192.168.0.10", "169.254.1.1", "::1", "fd00::1", "::ffff:10.0.0.1", "unknown"] {
+            assert!(resolver.resolve(ip).is_none(), "{ip} should be skipped");
+        }
+        assert_eq!(lookups.load(Ordering::SeqCst), 0);
+
+        assert_eq!(resolver.resolve("89.160.20.112").unwrap().country, "SE");
+    }
+
+    #[test]
+    fn test_lookups_are_cached_and_bounded() {
+        let lookups = Arc::new(AtomicUsize::new(0));
+        let resolver = GeoIpResolver::new(CountingDatabase(lookups.clone()), 2);
+
+        resolver.resolve("89.160.20.1");
+        resolver.resolve("89.160.20.1");
+        assert_eq!(lookups.load(Ordering::SeqCst), 1);
+
+        // A third IP evicts the oldest
+        resolver.resolve("89.160.20.2");
+        resolver.resolve("89.160.20.3");
+        assert_eq!(resolver.cache.lock().unwrap().entries.len(), 2);
+        resolver.resolve("89.160.20.1");
+        assert_eq!(lookups.load(Ordering::SeqCst), 4);
+    }
+
+    #[cfg(feature = "geoip")]
+    #[test]
+    fn test_bundled_database_resolves_known_ips() {
+        // Built by scripts/generate_geoip_test_db.py
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/GeoIP2-Country-Test.mmdb");
+        let resolver = GeoIpResolver::new(MaxMindDatabase::open(path).unwrap(), 100);
+
+        assert_eq!(resolver.resolve("81.2.69.142").unwrap().country, "GB");
+        assert_eq!(resolver.resolve("175.16.199.10").unwrap().country, "CN");
+        assert!(resolver.resolve("8.8.8.8").is_none());
+
+        let mut context = RequestContext::new("216.160.83.56".to_string(), "/v1/check".to_string(), "POST".to_string());
+        resolver.enrich(&mut context);
+        assert_eq!(context.geolocation.unwrap().country, "US");
+    }
+}
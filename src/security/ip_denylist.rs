@@ -0,0 +1,400 @@
+//! Client IPs and CIDR ranges refused outright.
+//!
+//! Permanent bans come from `[security.ip_denylist] entries`. Temporary bans
+//! are added by the response engine's `BlockIp` action and kept in the Redis
+//! sorted set `security:ip_bans`, scored by expiry, so every instance refuses
+//! the client until the ban runs out. The threat detection middleware checks
+//! the denylist before analyzing a request and answers matches with 403.
+//!
+//! Both kinds are matched through a binary trie over the address bits, so a
+//! lookup costs at most one step per bit whatever the number of entries.
+//! Each instance caches the temporary bans for a few seconds, like the API
+//! key lists; if Redis can't be read the last copy keeps being used.
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, TimeZone, Utc};
+use ipnet::IpNet;
+use std::net::IpAddr;
+use std::sync::RwLock;
+use std::time::{Duration, Instant};
+
+use crate::config::IpDenylistConfig;
+use crate::ip_allowlist::{canonical_ip, parse_network};
+use crate::redis_backend::RedisConnector;
+use crate::security::siem_integration::{ActorInfo, SecurityEvent, SecurityEventSeverity, SecurityEventType, TargetInfo};
+use crate::security::threat_analyzer::RequestContext;
+
+const BANS_KEY: &str = "security:ip_bans";
+
+/// How long an instance uses its copy of the temporary bans before rereading them
+pub const DEFAULT_CACHE_TTL: Duration = Duration::from_secs(5);
+
+/// Expiry of a permanent entry
+const PERMANENT: i64 = i64::MAX;
+
+/// The entry that refused a client
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IpBan {
+    pub network: IpNet,
+    /// `None` for a permanent ban
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Default)]
+struct Node {
+    /// Indexes of the child nodes for a 0 and a 1 bit; 0 is no child, since
+    /// the root is never anyone's child
+    children: [u32; 2],
+    /// A network ends here, banned until this Unix time
+    expires_at: Option<i64>,
+}
+
+/// Networks indexed bit by bit from the most significant, one trie per
+/// address family
+struct CidrTrie {
+    v4: Vec<Node>,
+    v6: Vec<Node>,
+}
+
+impl Default for CidrTrie {
+    fn default() -> Self {
+        Self {
+            v4: vec![Node::default()],
+            v6: vec![Node::default()],
+        }
+    }
+}
+
+/// The address left-aligned in 128 bits, with the family's trie and width
+fn bits(ip: IpAddr) -> (u128, bool, u8) {
+    match ip {
+        IpAddr::V4(v4) => ((u32::from(v4) as u128) << 96, true, 32),
+        IpAddr::V6(v6) => (u128::from(v6), false, 128),
+    }
+}
+
+fn bit_at(address: u128, index: u8) -> usize {
+    ((address >> (127 - index as u32)) & 1) as usize
+}
+
+impl CidrTrie {
+    fn nodes(&self, v4: bool) -> &Vec<Node> {
+        if v4 { &self.v4 } else { &self.v6 }
+    }
+
+    /// Ban `network` until `expires_at`, keeping the later expiry if it's
+    /// already banned
+    fn insert(&mut self, network: IpNet, expires_at: i64) {
+        let (address, v4, _) = bits(network.network());
+        let nodes = if v4 { &mut self.v4 } else { &mut self.v6 };
+
+        let mut node = 0usize;
+        for index in 0..network.prefix_len() {
+            let bit = bit_at(address, index);
+            let child = nodes[node].children[bit] as usize;
+            node = if child == 0 {
+                nodes.push(Node::default());
+                let child = nodes.len() - 1;
+                nodes[node].children[bit] = child as u32;
+                child
+            } else {
+                child
+            };
+        }
+        let current = &mut nodes[node].expires_at;
+        *current = Some(current.map_or(expires_at, |current| current.max(expires_at)));
+    }
+
+    /// The most specific network containing `ip` that's still banned at `now`
+    fn lookup(&self, ip: IpAddr, now: i64) -> Option<(IpNet, i64)> {
+        let (address, v4, width) = bits(ip);
+        let nodes = self.nodes(v4);
+
+        let mut found = None;
+        let mut node = 0usize;
+        for depth in 0..=width {
+            if let Some(expires_at) = nodes[node].expires_at.filter(|expires_at| *expires_at > now) {
+                found = Some((depth, expires_at));
+            }
+            if depth == width {
+                break;
+            }
+            match nodes[node].children[bit_at(address, depth)] {
+                0 => break,
+                child => node = child as usize,
+            }
+        }
+
+        found.map(|(prefix_len, expires_at)| {
+            let network = IpNet::new(ip, prefix_len).expect("prefix is within the address width").trunc();
+            (network, expires_at)
+        })
+    }
+}
+
+pub struct IpDenylist {
+    redis: RedisConnector,
+    permanent: CidrTrie,
+    permanent_entries: usize,
+    cache_ttl: Duration,
+    bans: RwLock<Option<Snapshot>>,
+}
+
+/// The temporary bans as read from Redis at `fetched_at`
+struct Snapshot {
+    bans: CidrTrie,
+    fetched_at: Instant,
+}
+
+impl IpDenylist {
+    /// Parse the permanent `entries`, each an IP address or a CIDR range
+    pub fn new<S: AsRef<str>>(redis: RedisConnector, entries: &[S]) -> Result<Self> {
+        let mut permanent = CidrTrie::default();
+        for entry in entries {
+            let network = parse_network(entry.as_ref()).context("Invalid IP denylist entry")?;
+            permanent.insert(network, PERMANENT);
+        }
+        Ok(Self {
+            redis,
+            permanent,
+            permanent_entries: entries.len(),
+            cache_ttl: DEFAULT_CACHE_TTL,
+            bans: RwLock::new(None),
+        })
+    }
+
+    pub fn from_config(redis: RedisConnector, config: &IpDenylistConfig) -> Result<Self> {
+        let denylist = Self::new(redis, &config.entries)?;
+        if denylist.permanent_entries > 0 {
+            tracing::info!("IP denylist enabled with {} permanent entries", denylist.permanent_entries);
+        }
+        Ok(denylist)
+    }
+
+    pub fn with_cache_ttl(mut self, cache_ttl: Duration) -> Self {
+        self.cache_ttl = cache_ttl;
+        self
+    }
+
+    /// The ban refusing `ip`, if there is one
+    pub async fn check(&self, ip: IpAddr) -> Option<IpBan> {
+        let ip = canonical_ip(ip);
+        if let Some((network, _)) = self.permanent.lookup(ip, Utc::now().timestamp()) {
+            return Some(IpBan { network, expires_at: None });
+        }
+        self.temporary_ban(ip).await
+    }
+
+    async fn temporary_ban(&self, ip: IpAddr) -> Option<IpBan> {
+        let lookup = |snapshot: &Snapshot| {
+            snapshot.bans.lookup(ip, Utc::now().timestamp()).map(|(network, expires_at)| IpBan {
+                network,
+                expires_at: Utc.timestamp_opt(expires_at, 0).single(),
+            })
+        };
+
+        if let Some(snapshot) = self.bans.read().unwrap().as_ref() {
+            if snapshot.fetched_at.elapsed() < self.cache_ttl {
+                return lookup(snapshot);
+            }
+        }
+
+        match self.fetch().await {
+            Ok(snapshot) => {
+                let ban = lookup(&snapshot);
+                *self.bans.write().unwrap() = Some(snapshot);
+                ban
+            }
+            Err(e) => {
+                tracing::warn!("Failed to refresh IP bans, using cached copy: {:#}", e);
+                self.bans.read().unwrap().as_ref().and_then(lookup)
+            }
+        }
+    }
+
+    async fn fetch(&self) -> Result<Snapshot> {
+        let mut conn = self.redis.get_async_connection().await?;
+        let now = Utc::now().timestamp();
+        // Expired bans are dropped here rather than by a separate sweeper
+        let (bans,): (Vec<(String, i64)>,) = redis::pipe()
+            .cmd("ZREMRANGEBYSCORE").arg(BANS_KEY).arg("-inf").arg(now).ignore()
+            .cmd("ZRANGEBYSCORE").arg(BANS_KEY).arg(format!("({}", now)).arg("+inf").arg("WITHSCORES")
+            .query_async(&mut conn)
+            .await?;
+
+        let mut trie = CidrTrie::default();
+        for (network, expires_at) in bans {
+            match parse_network(&network) {
+                Ok(network) => trie.insert(network, expires_at),
+                Err(e) => tracing::warn!("Ignoring unreadable IP ban '{}': {:#}", network, e),
+            }
+        }
+        Ok(Snapshot {
+            bans: trie,
+            fetched_at: Instant::now(),
+        })
+    }
+
+    /// Ban `network` on every instance for `duration`. A longer ban already
+    /// in place is kept.
+    pub async fn ban(&self, network: IpNet, duration: Duration) -> Result<DateTime<Utc>> {
+        let network = network.trunc();
+        let expires_at = Utc::now() + chrono::Duration::from_std(duration)?;
+        let mut conn = self.redis.get_async_connection().await?;
+        let _: () = redis::cmd("ZADD")
+            .arg(BANS_KEY)
+            .arg("GT")
+            .arg(expires_at.timestamp())
+            .arg(network.to_string())
+            .query_async(&mut conn)
+            .await?;
+
+        // Refuse the client here at once rather than after the next refresh
+        if let Some(snapshot) = self.bans.write().unwrap().as_mut() {
+            snapshot.bans.insert(network, expires_at.timestamp());
+        }
+        Ok(expires_at)
+    }
+
+    /// Bans fixed in memory, never read from Redis
+    #[cfg(test)]
+    pub(crate) fn with_bans(entries: &[&str], bans: &[(&str, DateTime<Utc>)]) -> Self {
+        let denylist = Self::new(RedisConnector::open("redis://127.0.0.1:1").unwrap(), entries)
+            .unwrap()
+            .with_cache_ttl(Duration::MAX);
+        let mut trie = CidrTrie::default();
+        for (network, expires_at) in bans {
+            trie.insert(parse_network(network).unwrap(), expires_at.timestamp());
+        }
+        *denylist.bans.write().unwrap() = Some(Snapshot {
+            bans: trie,
+            fetched_at: Instant::now(),
+        });
+        denylist
+    }
+}
+
+/// The SIEM event for a request refused by `ban`
+pub fn denied_request_event(context: &RequestContext, ban: &IpBan) -> SecurityEvent {
+    let mut raw_data = std::collections::HashMap::new();
+    raw_data.insert("network".to_string(), serde_json::json!(ban.network.to_string()));
+    raw_data.insert("expires_at".to_string(), serde_json::json!(ban.expires_at));
+
+    let kind = if ban.expires_at.is_some() { "temporary" } else { "permanent" };
+    SecurityEvent {
+        event_id: uuid::Uuid::new_v4().to_string(),
+        timestamp: Utc::now(),
+        event_type: SecurityEventType::AttackBlocked,
+        severity: SecurityEventSeverity::Medium,
+        source: "ratewatch".to_string(),
+        title: "Request from denylisted IP refused".to_string(),
+        description: format!(
+            "Refused request from IP {}, which is in the {} ban on {}",
+            context.ip_address, kind, ban.network
+        ),
+        threat_score: 1.0,
+        confidence: 1.0,
+        actor: ActorInfo {
+            ip_address: context.ip_address.clone(),
+            user_agent: context.user_agent.clone(),
+            api_key_id: context.api_key_id.clone(),
+            tenant_id: context.tenant_id.clone(),
+            geolocation: context.geolocation.clone(),
+        },
+        target: TargetInfo {
+            resource_type: "api_endpoint".to_string(),
+            resource_id: None,
+            endpoint: context.endpoint.clone(),
+            method: context.method.clone(),
+        },
+        actions_taken: vec!["request_denied".to_string()],
+        raw_data,
+        tags: vec!["ratewatch".to_string(), "ip_denylist".to_string(), format!("ban:{}", kind)],
+        correlation_id: context.correlation_id.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ip(ip: &str) -> IpAddr {
+        ip.parse().unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_permanent_entries_match_ranges() {
+        let denylist = IpDenylist::with_bans(&["203.0.113.0/24", "198.51.100.7", "2001:db8:bad::/48"], &[]);
+
+        let ban = denylist.check(ip("203.0.113.200")).await.unwrap();
+        assert_eq!(ban.network.to_string(), "203.0.113.0/24");
+        assert_eq!(ban.expires_at, None);
+        assert!(denylist.check(ip("198.51.100.7")).await.is_some());
+        assert!(denylist.check(ip("198.51.100.8")).await.is_none());
+        assert!(denylist.check(ip("2001:db8:bad:1::5")).await.is_some());
+        assert!(denylist.check(ip("2001:db8:bae::5")).await.is_none());
+        // IPv4-mapped IPv6
+        assert!(denylist.check(ip("::ffff:203.0.113.9")).await.is_some());
+
+        assert!(IpDenylist::new(RedisConnector::open("redis://127.0.0.1:1").unwrap(), &["203.0.113.0/40"]).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_temporary_bans_expire() {
+        let now = Utc::now();
+        let denylist = IpDenylist::with_bans(
+            &[],
+            &[
+                ("192.0.2.1", now + chrono::Duration::minutes(5)),
+                ("192.0.2.2", now - chrono::Duration::seconds(1)),
+                ("2001:db8:1:2::/64", now + chrono::Duration::minutes(5)),
+            ],
+        );
+
+        let ban = denylist.check(ip("192.0.2.1")).await.unwrap();
+        assert_eq!(ban.network.to_string(), "192.0.2.1/32");
+        assert_eq!(ban.expires_at.unwrap().timestamp(), (now + chrono::Duration::minutes(5)).timestamp());
+        assert!(denylist.check(ip("192.0.2.2")).await.is_none());
+
+        // A banned IPv6 client can't rotate to another address in its /64
+        let ban = denylist.check(ip("2001:DB8:1:2:ffff::9")).await.unwrap();
+        assert_eq!(ban.network.to_string(), "2001:db8:1:2::/64");
+        assert!(denylist.check(ip("2001:db8:1:3::9")).await.is_none());
+    }
+
+    #[test]
+    fn test_trie_prefers_most_specific_live_entry() {
+        let mut trie = CidrTrie::default();
+        trie.insert(parse_network("10.0.0.0/8").unwrap(), 100);
+        trie.insert(parse_network("10.1.0.0/16").unwrap(), 200);
+        trie.insert(parse_network("0.0.0.0/0").unwrap(), 50);
+
+        assert_eq!(trie.lookup(ip("10.1.2.3"), 0).unwrap().0.to_string(), "10.1.0.0/16");
+        assert_eq!(trie.lookup(ip("10.2.0.1"), 0).unwrap().0.to_string(), "10.0.0.0/8");
+        // The /16 ban outlives the /8 one
+        assert_eq!(trie.lookup(ip("10.2.0.1"), 150), None);
+        assert_eq!(trie.lookup(ip("10.1.0.1"), 150).unwrap().1, 200);
+        assert_eq!(trie.lookup(ip("11.0.0.1"), 0).unwrap().0.to_string(), "0.0.0.0/0");
+        assert_eq!(trie.lookup(ip("2001:db8::1"), 0), None);
+    }
+
+    #[tokio::test]
+    async fn test_ban_shared_through_redis() {
+        let redis = RedisConnector::open("redis://127.0.0.1:6379").unwrap();
+        let banning = IpDenylist::new(redis.clone(), &[] as &[&str]).unwrap();
+        let other = IpDenylist::new(redis, &[] as &[&str]).unwrap().with_cache_ttl(Duration::ZERO);
+
+        let network: IpNet = "192.0.2.128/25".parse().unwrap();
+        let Ok(expires_at) = banning.ban(network, Duration::from_secs(60)).await else {
+            // Redis not available
+            return;
+        };
+
+        let ban = other.check(ip("192.0.2.200")).await.unwrap();
+        assert_eq!(ban.network, network);
+        assert_eq!(ban.expires_at.unwrap().timestamp(), expires_at.timestamp());
+
+        let mut conn = other.redis.get_async_connection().await.unwrap();
+        let _: () = redis::AsyncCommands::zrem(&mut conn, BANS_KEY, network.to_string()).await.unwrap();
+    }
+}
@@ -1,3 +1,4 @@
+use crate::ip_allowlist::canonical_ip;
 use crate::security::threat_analyzer::{ThreatAnalyzer, ThreatScore, RequestContext};
 use anyhow::Result;
 use async_trait::async_trait;
@@ -308,16 +309,18 @@ impl ThreatAnalyzer for IpReputationAnalyzer {
         }
 
         // Validate IP address
-        if context.ip_address.parse::<IpAddr>().is_err() {
+        let Ok(ip) = context.ip_address.parse::<IpAddr>() else {
             return Ok(ThreatScore::new(
                 "ip_reputation".to_string(),
                 0.0,
                 0.0,
             ).with_reason("Invalid IP address format".to_string()));
-        }
+        };
+        // One cache entry and one provider query however the address is written
+        let ip_address = canonical_ip(ip).to_string();
 
         // Check cache first
-        if let Some(cached_result) = self.check_cache(&context.ip_address).await {
+        if let Some(cached_result) = self.check_cache(&ip_address).await {
             let threat_score = ThreatScore::new(
                 "ip_reputation".to_string(),
                 cached_result.reputation_score,
@@ -334,7 +337,7 @@ impl ThreatAnalyzer for IpReputationAnalyzer {
         }
 
         // Query providers
-        let results = self.query_providers(&context.ip_address).await;
+        let results = self.query_providers(&ip_address).await;
         let combined_result = self.combine_reputation_results(results);
 
         // Cache the result
@@ -457,6 +460,19 @@ impl StaticThreatListProvider {
             },
         );
 
+        threat_ips.insert(
+            "2001:db8:bad::1".to_string(),
+            ReputationResult {
+                ip_address: "2001:db8:bad::1".to_string(),
+                reputation_score: 0.85,
+                confidence: 0.9,
+                categories: vec![ThreatCategory::Scanner],
+                provider: "static_list".to_string(),
+                last_seen: Some(Utc::now() - Duration::days(2)),
+                metadata: HashMap::new(),
+            },
+        );
+
         Self { threat_ips }
     }
 }
@@ -464,7 +480,11 @@ impl StaticThreatListProvider {
 #[async_trait]
 impl IpReputationProvider for StaticThreatListProvider {
     async fn check_reputation(&self, ip_address: &str) -> Result<ReputationResult> {
-        if let Some(result) = self.threat_ips.get(ip_address) {
+        // The list is keyed by canonical address
+        let canonical = ip_address
+            .parse::<IpAddr>()
+            .map_or_else(|_| ip_address.to_string(), |ip| canonical_ip(ip).to_string());
+        if let Some(result) = self.threat_ips.get(&canonical) {
             Ok(result.clone())
         } else {
             Ok(ReputationResult {
@@ -546,6 +566,14 @@ mod tests {
         let result = provider.check_reputation("8.8.8.8").await.unwrap();
         assert_eq!(result.reputation_score, 0.0);
         assert!(result.categories.is_empty());
+
+        // IPv6, in any spelling, and IPv4-mapped addresses
+        let result = provider.check_reputation("2001:DB8:BAD:0:0:0:0:1").await.unwrap();
+        assert!(result.reputation_score > 0.5);
+        let result = provider.check_reputation("::ffff:192.0.2.1").await.unwrap();
+        assert!(result.reputation_score > 0.5);
+        let result = provider.check_reputation("2001:db8:bad::2").await.unwrap();
+        assert_eq!(result.reputation_score, 0.0);
     }
 
     #[test]
@@ -1,10 +1,19 @@
-use crate::security::{ThreatDetector, threat_analyzer::RequestContext};
+use crate::audit::audit_event::{ActorInfo, AuditOutcome};
+use crate::config::{SecurityFailureMode, ThreatAnalysisMode};
+use crate::ip_allowlist::IpAllowlisted;
+use crate::rate_limiter::{DenyReason, DENY_REASON_HEADER};
+use crate::security::{
+    DefensiveAction, ThreatDetector, challenge, ip_denylist::denied_request_event,
+    threat_analyzer::RequestContext, tls_fingerprint::normalize_ja3,
+};
 use axum::{
     extract::{Request, State},
     http::StatusCode,
     middleware::Next,
-    response::Response,
+    response::{IntoResponse, Json, Response},
 };
+use serde_json::json;
+use std::net::IpAddr;
 use std::sync::Arc;
 use tracing::{debug, error, warn};
 use uuid::Uuid;
@@ -15,106 +24,399 @@ pub async fn threat_detection_middleware(
     mut request: Request,
     next: Next,
 ) -> Result<Response, StatusCode> {
-    // Extract request information for threat analysis
-    let ip_address = extract_ip_address(&request).unwrap_or_else(|| "unknown".to_string());
-    let user_agent = extract_user_agent(&request);
-    let method = request.method().to_string();
-    let path = request.uri().path().to_string();
-    
-    // Create request context for threat analysis
-    let mut context = RequestContext::new(ip_address.clone(), path.clone(), method.clone());
-    
-    if let Some(ua) = user_agent {
-        context = context.with_user_agent(ua);
-    }
-    
-    // Add headers to context
-    for (name, value) in request.headers().iter() {
-        if let Ok(value_str) = value.to_str() {
-            context = context.with_header(name.to_string(), value_str.to_string());
-        }
-    }
-    
-    // Perform threat analysis
-    match threat_detector.analyze_request(&context).await {
-        Ok(analysis_result) => {
-            debug!(
+    // Built by request_context_middleware; auth_middleware, which runs
+    // first, has added the caller's key and tenant
+    let mut context = crate::request_context::request_context(&request);
+    let ip_address = context.ip_address.clone();
+
+    // Banned clients are refused even if they're also allowlisted
+    if let (Some(denylist), Ok(ip)) = (threat_detector.ip_denylist(), ip_address.parse::<IpAddr>()) {
+        if let Some(ban) = denylist.check(ip).await {
+            warn!(
                 ip_address = ip_address,
-                threat_score = analysis_result.overall_score.score,
-                confidence = analysis_result.overall_score.confidence,
-                actions_taken = analysis_result.actions_taken.len(),
-                "Threat analysis completed"
+                network = %ban.network,
+                expires_at = ?ban.expires_at,
+                "Request refused from denylisted IP"
             );
-            
-            // Check if the request should be blocked
-            if analysis_result.requires_action() && !analysis_result.actions_taken.is_empty() {
-                warn!(
-                    ip_address = ip_address,
-                    threat_score = analysis_result.overall_score.score,
-                    reasons = ?analysis_result.overall_score.reasons,
-                    "Request blocked due to threat detection"
-                );
-                
-                return Err(StatusCode::TOO_MANY_REQUESTS);
+            if let Some(siem) = threat_detector.siem() {
+                siem.queue_event(denied_request_event(&context, &ban)).await;
+            }
+            return Ok(DenyReason::Banned.response(StatusCode::FORBIDDEN));
+        }
+    }
+
+    // Allowlisted clients aren't analyzed, so they can't be blocked
+    if request.extensions().get::<IpAllowlisted>().is_some() {
+        return Ok(next.run(request).await);
+    }
+
+    if let Some(geoip) = threat_detector.geoip() {
+        geoip.enrich(&mut context);
+    }
+
+    if let Some(header) = threat_detector.tls_fingerprint_header() {
+        if let Some(fingerprint) = request
+            .headers()
+            .get(header)
+            .and_then(|value| value.to_str().ok())
+            .and_then(normalize_ja3)
+        {
+            context = context.with_tls_fingerprint(fingerprint);
+        }
+    }
+
+    // In async mode the request goes ahead and is analyzed in the
+    // background; a ban from that refuses the client's later requests above
+    if threat_detector.mode().await == ThreatAnalysisMode::Async && threat_detector.queue_analysis(context.clone()) {
+        return Ok(next.run(request).await);
+    }
+
+    // A client retrying with a solved challenge is remembered before analysis,
+    // so a borderline score lets it through this time
+    if let Some(challenges) = threat_detector.challenges() {
+        // Owned copies, so no borrow of the request is held across the await
+        let (token, response) = {
+            let header = |name| request.headers().get(name).and_then(|value| value.to_str().ok()).map(str::to_string);
+            (header(challenge::TOKEN_HEADER), header(challenge::RESPONSE_HEADER))
+        };
+        if let (Some(token), Some(response)) = (token, response) {
+            match challenges.redeem(&ip_address, &token, &response).await {
+                Ok(true) => debug!(ip_address = ip_address, "Challenge solved"),
+                Ok(false) => debug!(ip_address = ip_address, "Challenge response rejected"),
+                Err(e) => error!(ip_address = ip_address, error = %e, "Failed to verify challenge"),
             }
-            
-            // Add analysis result to request extensions for downstream use
-            request.extensions_mut().insert(analysis_result);
         }
+    }
+
+    // Perform threat analysis
+    let analysis_result = match threat_detector.analyze_request(&context).await {
+        Ok(analysis_result) => analysis_result,
         Err(e) => {
             error!(
                 ip_address = ip_address,
                 error = %e,
                 "Threat analysis failed"
             );
-            // Continue processing even if threat analysis fails
+            return Ok(analysis_failed(&threat_detector, &context, &format!("{:#}", e), request, next).await);
         }
+    };
+    debug!(
+        ip_address = ip_address,
+        threat_score = analysis_result.overall_score.score,
+        confidence = analysis_result.overall_score.confidence,
+        actions_taken = analysis_result.actions_taken.len(),
+        "Threat analysis completed"
+    );
+
+    let challenge = analysis_result.actions_taken.iter().find_map(|action| match action {
+        DefensiveAction::Challenge { token } => Some(token.clone()),
+        _ => None,
+    });
+    if let (Some(token), Some(challenges)) = (challenge, threat_detector.challenges()) {
+        debug!(
+            ip_address = ip_address,
+            threat_score = analysis_result.overall_score.score,
+            "Request challenged"
+        );
+        let body = json!({
+            "error": "challenge_required",
+            "deny_reason": DenyReason::Challenged,
+            "challenge": challenges.details(token),
+        });
+        let mut response = (
+            StatusCode::FORBIDDEN,
+            [(DENY_REASON_HEADER, DenyReason::Challenged.as_str())],
+            Json(body),
+        )
+            .into_response();
+        response.extensions_mut().insert(DenyReason::Challenged);
+        return Ok(response);
+    }
+
+    // Check if the request should be blocked
+    if analysis_result.requires_action() && !analysis_result.actions_taken.is_empty() {
+        warn!(
+            ip_address = ip_address,
+            threat_score = analysis_result.overall_score.score,
+            reasons = ?analysis_result.overall_score.reasons,
+            "Request blocked due to threat detection"
+        );
+
+        return Ok(DenyReason::ThreatDetected.response(StatusCode::TOO_MANY_REQUESTS));
     }
-    
+
+    // A verdict missing some analyzers is a failed analysis too
+    let failed_analyzers = analysis_result.failed_analyzers.join(", ");
+
+    // Add analysis result to request extensions for downstream use
+    request.extensions_mut().insert(analysis_result);
+    if !failed_analyzers.is_empty() {
+        let error = format!("Threat analyzers failed: {}", failed_analyzers);
+        return Ok(analysis_failed(&threat_detector, &context, &error, request, next).await);
+    }
+
     // Continue with the request
     let response = next.run(request).await;
     Ok(response)
 }
 
-fn extract_ip_address(request: &Request) -> Option<String> {
-    let headers = request.headers();
-    
-    // Check X-Forwarded-For first (most common for proxied requests)
-    if let Some(forwarded_for) = headers.get("x-forwarded-for") {
-        if let Ok(value) = forwarded_for.to_str() {
-            // Take the first IP in the chain
-            if let Some(first_ip) = value.split(',').next() {
-                return Some(first_ip.trim().to_string());
-            }
+/// Set on the response of a request let through after its threat analysis
+/// failed, so analytics can count it
+#[derive(Debug, Clone, Copy)]
+pub struct AnalysisFailedOpen;
+
+/// Allow or refuse a request whose analysis failed, as the detector's
+/// `SecurityFailureMode` says, and record the decision
+async fn analysis_failed(
+    threat_detector: &ThreatDetector,
+    context: &RequestContext,
+    error: &str,
+    request: Request,
+    next: Next,
+) -> Response {
+    let failure_mode = threat_detector.failure_mode().await;
+    let (decision, outcome) = match failure_mode {
+        SecurityFailureMode::FailOpen => ("allowed", AuditOutcome::Success),
+        SecurityFailureMode::FailClosed => ("blocked", AuditOutcome::Failure),
+    };
+    crate::metrics::THREAT_ANALYSIS_FAILURES.with_label_values(&[decision]).inc();
+
+    if let Some(audit) = threat_detector.audit() {
+        let actor = ActorInfo::new().with_ip_address(context.ip_address.clone());
+        let actor = match &context.api_key_id {
+            Some(key_id) => actor.with_api_key(key_id.clone()),
+            None => actor,
+        };
+        let details = format!("{}; request {}", error, decision);
+        if let Err(e) = audit
+            .log_security_event(
+                actor,
+                "threat_analysis_failed",
+                "threat_detection",
+                outcome,
+                context.tenant_id.clone(),
+                None,
+                Some(&details),
+                Some(context.correlation_id),
+            )
+            .await
+        {
+            error!("Failed to audit threat analysis failure: {}", e);
         }
     }
-    
-    // Check X-Real-IP
-    if let Some(real_ip) = headers.get("x-real-ip") {
-        if let Ok(value) = real_ip.to_str() {
-            return Some(value.to_string());
+
+    match failure_mode {
+        SecurityFailureMode::FailOpen => {
+            let mut response = next.run(request).await;
+            response.extensions_mut().insert(AnalysisFailedOpen);
+            response
         }
-    }
-    
-    // Check CF-Connecting-IP (Cloudflare)
-    if let Some(cf_ip) = headers.get("cf-connecting-ip") {
-        if let Ok(value) = cf_ip.to_str() {
-            return Some(value.to_string());
+        SecurityFailureMode::FailClosed => {
+            warn!(ip_address = context.ip_address, error, "Request refused, threat analysis failed");
+            DenyReason::SecurityUnavailable.response(StatusCode::SERVICE_UNAVAILABLE)
         }
     }
-    
-    None
-}
-
-fn extract_user_agent(request: &Request) -> Option<String> {
-    request
-        .headers()
-        .get("user-agent")
-        .and_then(|value| value.to_str().ok())
-        .map(|s| s.to_string())
 }
 
 /// Extract threat analysis result from request extensions
 pub fn get_threat_analysis_result(request: &Request) -> Option<&crate::security::threat_detector::ThreatAnalysisResult> {
     request.extensions().get::<crate::security::threat_detector::ThreatAnalysisResult>()
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::security::{
+        ip_denylist::IpDenylist,
+        response_engine::ResponseEngine,
+        threat_analyzer::{RequestContext, ThreatAnalyzer, ThreatScore},
+    };
+    use async_trait::async_trait;
+    use axum::{body::Body, middleware, routing::get, Router};
+    use tower::ServiceExt;
+
+    /// Scores every request the same
+    struct FixedScore(f64);
+
+    #[async_trait]
+    impl ThreatAnalyzer for FixedScore {
+        async fn analyze(&self, _context: &RequestContext) -> anyhow::Result<ThreatScore> {
+            Ok(ThreatScore::new("fixed".to_string(), self.0, 0.9))
+        }
+
+        fn analyzer_id(&self) -> &str {
+            "fixed"
+        }
+
+        fn name(&self) -> &str {
+            "Fixed Score"
+        }
+
+        fn is_enabled(&self) -> bool {
+            true
+        }
+
+        async fn update_config(&mut self, _config: serde_json::Value) -> anyhow::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn app(score: f64) -> Router {
+        let detector = ThreatDetector::new(
+            vec![Box::new(FixedScore(score))],
+            Arc::new(ResponseEngine::new(Default::default())),
+            None,
+        )
+        .with_ip_denylist(Arc::new(IpDenylist::with_bans(&["203.0.113.0/24"], &[])));
+
+        Router::new()
+            .route("/", get(|| async { "ok" }))
+            .layer(middleware::from_fn_with_state(Arc::new(detector), threat_detection_middleware))
+    }
+
+    fn request(ip: &str) -> Request {
+        axum::http::Request::builder()
+            .uri("/")
+            .header("x-forwarded-for", ip)
+            .body(Body::empty())
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_denials_carry_their_reason() {
+        let response = app(0.0).oneshot(request("203.0.113.7")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        assert_eq!(response.headers()[DENY_REASON_HEADER], "banned");
+        assert_eq!(response.extensions().get::<DenyReason>(), Some(&DenyReason::Banned));
+
+        let response = app(0.95).oneshot(request("198.51.100.7")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(response.headers()[DENY_REASON_HEADER], "threat_detected");
+
+        let response = app(0.0).oneshot(request("198.51.100.7")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(response.headers().get(DENY_REASON_HEADER).is_none());
+    }
+
+    /// Fails every analysis, like an analyzer that lost Redis
+    struct Failing;
+
+    #[async_trait]
+    impl ThreatAnalyzer for Failing {
+        async fn analyze(&self, _context: &RequestContext) -> anyhow::Result<ThreatScore> {
+            anyhow::bail!("Redis unavailable")
+        }
+
+        fn analyzer_id(&self) -> &str {
+            "failing"
+        }
+
+        fn name(&self) -> &str {
+            "Failing"
+        }
+
+        fn is_enabled(&self) -> bool {
+            true
+        }
+
+        async fn update_config(&mut self, _config: serde_json::Value) -> anyhow::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_failure_mode_decides_failed_analyses() {
+        let app = |score: f64, failure_mode| {
+            let detector = ThreatDetector::new(
+                vec![Box::new(FixedScore(score)), Box::new(Failing)],
+                Arc::new(ResponseEngine::new(Default::default())),
+                None,
+            )
+            .with_failure_mode(failure_mode);
+            Router::new()
+                .route("/", get(|| async { "ok" }))
+                .layer(middleware::from_fn_with_state(Arc::new(detector), threat_detection_middleware))
+        };
+
+        let response = app(0.0, SecurityFailureMode::FailOpen).oneshot(request("198.51.100.7")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(response.extensions().get::<AnalysisFailedOpen>().is_some());
+
+        let response = app(0.0, SecurityFailureMode::FailClosed).oneshot(request("198.51.100.7")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(response.headers()[DENY_REASON_HEADER], "security_unavailable");
+        assert_eq!(response.extensions().get::<DenyReason>(), Some(&DenyReason::SecurityUnavailable));
+
+        // What the other analyzers did find still stands
+        let response = app(0.95, SecurityFailureMode::FailOpen).oneshot(request("198.51.100.7")).await.unwrap();
+        assert_eq!(response.headers()[DENY_REASON_HEADER], "threat_detected");
+    }
+
+    /// Scores after a delay
+    struct SlowScore(f64, std::time::Duration);
+
+    #[async_trait]
+    impl ThreatAnalyzer for SlowScore {
+        async fn analyze(&self, _context: &RequestContext) -> anyhow::Result<ThreatScore> {
+            tokio::time::sleep(self.1).await;
+            Ok(ThreatScore::new("slow".to_string(), self.0, 0.9))
+        }
+
+        fn analyzer_id(&self) -> &str {
+            "slow"
+        }
+
+        fn name(&self) -> &str {
+            "Slow Score"
+        }
+
+        fn is_enabled(&self) -> bool {
+            true
+        }
+
+        async fn update_config(&mut self, _config: serde_json::Value) -> anyhow::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_async_mode_bans_later_requests() {
+        use std::time::{Duration, Instant};
+
+        let redis = crate::redis_backend::RedisConnector::open("redis://127.0.0.1:6379").unwrap();
+        let Ok(mut conn) = redis.get_async_connection().await else {
+            // Redis not available
+            return;
+        };
+        let denylist = Arc::new(IpDenylist::new(redis, &[] as &[&str]).unwrap().with_cache_ttl(Duration::ZERO));
+        let detector = Arc::new(
+            ThreatDetector::new(
+                vec![Box::new(SlowScore(0.95, Duration::from_millis(300)))],
+                Arc::new(ResponseEngine::new(Default::default()).with_ip_denylist(denylist.clone())),
+                None,
+            )
+            .with_ip_denylist(denylist.clone())
+            .with_mode(ThreatAnalysisMode::Async),
+        );
+        detector.start_async_analysis(16);
+        let app = Router::new()
+            .route("/", get(|| async { "ok" }))
+            .layer(middleware::from_fn_with_state(detector, threat_detection_middleware));
+
+        // Let through without waiting for the 300ms analysis
+        let started = Instant::now();
+        let response = app.clone().oneshot(request("192.0.2.61")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(started.elapsed() < Duration::from_millis(150), "took {:?}", started.elapsed());
+
+        let ip = "192.0.2.61".parse().unwrap();
+        let deadline = Instant::now() + Duration::from_secs(3);
+        while denylist.check(ip).await.is_none() && Instant::now() < deadline {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        let response = app.oneshot(request("192.0.2.61")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        assert_eq!(response.headers()[DENY_REASON_HEADER], "banned");
+
+        let _: () = redis::AsyncCommands::zrem(&mut conn, "security:ip_bans", "192.0.2.61/32").await.unwrap();
+    }
+}
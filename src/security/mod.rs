@@ -1,17 +1,37 @@
 pub mod threat_detector;
 pub mod threat_analyzer;
 pub mod response_engine;
+pub mod ip_denylist;
+pub mod challenge;
 pub mod ip_reputation;
+pub mod asn_reputation;
 pub mod behavioral_analyzer;
+pub mod behavior_patterns;
+pub mod anomaly_engine;
+pub mod feedback;
+pub mod geoip;
+pub mod tls_fingerprint;
 pub mod siem_integration;
+pub mod siem_dead_letter;
+pub mod siem_queue;
+pub mod siem_sentinel;
+pub mod siem_kafka;
 pub mod middleware;
 pub mod api;
 
 pub use threat_detector::ThreatDetector;
 pub use threat_analyzer::{ThreatAnalyzer, ThreatScore, ThreatLevel};
 pub use response_engine::{ResponseEngine, DefensiveAction, ResponseConfig};
+pub use ip_denylist::IpDenylist;
+pub use challenge::ChallengeManager;
 pub use ip_reputation::{IpReputationAnalyzer, IpReputationProvider};
+pub use asn_reputation::AsnReputationAnalyzer;
 pub use behavioral_analyzer::{BehaviorAnalyzer, BehaviorPattern, BehaviorMetrics};
+pub use behavior_patterns::BehaviorPatterns;
+pub use anomaly_engine::AnomalyEngine;
+pub use feedback::FeedbackStore;
+pub use geoip::GeoIpResolver;
+pub use tls_fingerprint::TlsFingerprintAnalyzer;
 pub use siem_integration::{SiemIntegration, SiemProvider, SecurityEvent};
 
 use anyhow::Result;
@@ -19,36 +39,97 @@ use std::sync::Arc;
 
 /// Initialize the security system with threat detection and response capabilities
 pub async fn initialize_security_system(
-    redis_client: redis::Client,
+    redis_client: crate::redis_backend::RedisConnector,
     config: &crate::config::SecurityConfig,
+    audit_logger: Arc<crate::audit::AuditLogger>,
 ) -> Result<Arc<ThreatDetector>> {
     // Initialize IP reputation analyzer
     let ip_reputation = Arc::new(IpReputationAnalyzer::new().await?);
     
+    // Operator feedback on verdicts tunes the behavioral pattern weights
+    let feedback = FeedbackStore::new(redis_client.clone());
+
+    // Operator-defined patterns, checked alongside or instead of the built-in ones
+    let behavior_patterns = Arc::new(BehaviorPatterns::from_config(&config.threat_detection.behavior_patterns)?);
+
     // Initialize behavioral analyzer
-    let behavior_analyzer = Arc::new(BehaviorAnalyzer::new(redis_client.clone()).await?);
+    let behavior_analyzer = Arc::new(
+        BehaviorAnalyzer::new(redis_client.clone())
+            .await?
+            .with_feedback(feedback.clone())
+            .with_patterns(behavior_patterns.clone())
+    );
     
+    // Permanent bans from config, plus the temporary ones BlockIp adds
+    let ip_denylist = Arc::new(IpDenylist::from_config(redis_client.clone(), &config.ip_denylist)?);
+
+    // Borderline scores can be challenged with a CAPTCHA instead of rejected
+    let challenges = ChallengeManager::from_config(redis_client.clone(), &config.threat_detection.challenge)?
+        .map(Arc::new);
+
     // Initialize response engine
-    let response_engine = Arc::new(ResponseEngine::new(
-        ResponseConfig::from_security_config(config)
-    ));
+    let response_engine = ResponseEngine::new(ResponseConfig::from_security_config(config))
+        .with_ip_denylist(ip_denylist.clone());
+    let response_engine = Arc::new(match &challenges {
+        Some(challenges) => response_engine.with_challenges(challenges.clone()),
+        None => response_engine,
+    });
     
     // Initialize SIEM integration if configured
     let siem_integration = if config.siem.enabled {
-        Some(Arc::new(SiemIntegration::new(&config.siem).await?))
+        Some(Arc::new(SiemIntegration::new(&config.siem, redis_client.clone()).await?))
     } else {
         None
     };
     
     // Create threat detector with all analyzers
+    let mut analyzers: Vec<Box<dyn ThreatAnalyzer>> = vec![
+        Box::new(ip_reputation),
+        Box::new(behavior_analyzer),
+    ];
+    if let Some(asn_reputation) = AsnReputationAnalyzer::from_config(&config.threat_detection.asn_reputation)? {
+        analyzers.push(Box::new(asn_reputation));
+    }
+    if config.threat_detection.ml_engine {
+        analyzers.push(Box::new(AnomalyEngine::new(redis_client.clone(), config.threat_detection.ml.clone())));
+    }
+    let tls_fingerprint = &config.threat_detection.tls_fingerprint;
+    if let Some(analyzer) = TlsFingerprintAnalyzer::from_config(redis_client.clone(), tls_fingerprint) {
+        analyzers.push(Box::new(analyzer));
+    }
+
     let threat_detector = ThreatDetector::new(
-        vec![
-            Box::new(ip_reputation),
-            Box::new(behavior_analyzer),
-        ],
+        analyzers,
         response_engine,
         siem_integration,
-    );
-    
-    Ok(Arc::new(threat_detector))
+    )
+    .with_analyzer_weights(config.threat_detection.analyzer_weights.clone())
+    .with_max_total_analysis_time_ms(config.threat_detection.max_total_analysis_time_ms)
+    .with_mode(config.threat_detection.mode)
+    .with_failure_mode(config.threat_detection.failure_mode)
+    .with_audit(audit_logger)
+    .with_feedback(Arc::new(feedback))
+    .with_behavior_patterns(behavior_patterns)
+    .with_ip_denylist(ip_denylist);
+
+    let threat_detector = match GeoIpResolver::from_config(&config.threat_detection.geoip)? {
+        Some(geoip) => threat_detector.with_geoip(Arc::new(geoip)),
+        None => threat_detector,
+    };
+
+    let threat_detector = match challenges {
+        Some(challenges) => threat_detector.with_challenges(challenges),
+        None => threat_detector,
+    };
+
+    let threat_detector = if tls_fingerprint.enabled {
+        threat_detector.with_tls_fingerprint_header(tls_fingerprint.header.clone())
+    } else {
+        threat_detector
+    };
+
+    // Started in either mode, so the mode can be switched live
+    let threat_detector = Arc::new(threat_detector);
+    threat_detector.start_async_analysis(config.threat_detection.async_queue_size);
+    Ok(threat_detector)
 }
\ No newline at end of file
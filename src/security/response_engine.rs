@@ -0,0 +1,301 @@
+//! Defensive actions taken once threat detection flags a request.
+//!
+//! `ThreatDetector` calls `respond_to_threat` when a request's combined score
+//! passes the threat and confidence thresholds. With CAPTCHA challenges
+//! enabled, a score in the challenge band gets a `Challenge`, or nothing at
+//! all if the client solved one recently. Otherwise the request is rejected;
+//! at `block_threshold` and above the client's IP is also banned for
+//! `block_duration` through the `IpDenylist`, so every instance refuses it
+//! without analyzing it again. An IPv6 client grouped by
+//! `server.ipv6_prefix_length` is banned with its whole prefix.
+//!
+//! For an evaluate-only request nothing is carried out: the actions that
+//! would have been taken are returned, with an empty challenge token.
+
+use crate::config::SecurityConfig;
+use crate::security::challenge::ChallengeManager;
+use crate::security::ip_denylist::IpDenylist;
+use crate::security::threat_analyzer::{RequestContext, ThreatScore};
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{error, warn};
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum DefensiveAction {
+    /// This request was refused
+    RejectRequest,
+    /// The client's IP is refused on every instance until `expires_at`
+    BlockIp {
+        ip_address: String,
+        expires_at: DateTime<Utc>,
+    },
+    /// The client must solve a CAPTCHA and retry with `token`
+    Challenge { token: String },
+}
+
+impl DefensiveAction {
+    /// Name used for the action in metrics and statistics
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DefensiveAction::RejectRequest => "reject_request",
+            DefensiveAction::BlockIp { .. } => "block_ip",
+            DefensiveAction::Challenge { .. } => "challenge",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ResponseConfig {
+    /// Ban the client's IP when the score reaches `block_threshold`
+    pub block_ip_enabled: bool,
+    pub block_threshold: f64,
+    pub block_duration: Duration,
+}
+
+impl Default for ResponseConfig {
+    fn default() -> Self {
+        Self {
+            block_ip_enabled: true,
+            block_threshold: 0.8,
+            block_duration: Duration::from_secs(3600),
+        }
+    }
+}
+
+impl ResponseConfig {
+    pub fn from_security_config(config: &SecurityConfig) -> Self {
+        Self {
+            block_threshold: config.threat_detection.challenge.block_score,
+            block_duration: Duration::from_secs(config.ip_denylist.ban_duration_seconds),
+            ..Self::default()
+        }
+    }
+}
+
+pub struct ResponseEngine {
+    config: ResponseConfig,
+    ip_denylist: Option<Arc<IpDenylist>>,
+    challenges: Option<Arc<ChallengeManager>>,
+}
+
+impl ResponseEngine {
+    pub fn new(config: ResponseConfig) -> Self {
+        Self {
+            config,
+            ip_denylist: None,
+            challenges: None,
+        }
+    }
+
+    /// Challenge borderline scores instead of rejecting them
+    pub fn with_challenges(mut self, challenges: Arc<ChallengeManager>) -> Self {
+        self.challenges = Some(challenges);
+        self
+    }
+
+    /// Carry out `BlockIp` by banning the client in `ip_denylist`
+    pub fn with_ip_denylist(mut self, ip_denylist: Arc<IpDenylist>) -> Self {
+        self.ip_denylist = Some(ip_denylist);
+        self
+    }
+
+    pub async fn respond_to_threat(&self, context: &RequestContext, score: &ThreatScore) -> Result<Vec<DefensiveAction>> {
+        if let Some(challenges) = self.challenges.as_ref().filter(|c| c.applies_to(score.score, &context.endpoint)) {
+            if challenges.is_solved(&context.ip_address).await {
+                return Ok(Vec::new());
+            }
+            if context.evaluate_only {
+                // No token is issued for an evaluation
+                return Ok(vec![DefensiveAction::Challenge { token: String::new() }]);
+            }
+            match challenges.issue(&context.ip_address).await {
+                Ok(token) => return Ok(vec![DefensiveAction::Challenge { token }]),
+                // Falls back to rejecting the request
+                Err(e) => error!(ip_address = %context.ip_address, error = %e, "Failed to issue challenge"),
+            }
+        }
+
+        let mut actions = vec![DefensiveAction::RejectRequest];
+
+        if !self.config.block_ip_enabled || score.score < self.config.block_threshold {
+            return Ok(actions);
+        }
+        let Some(denylist) = &self.ip_denylist else {
+            return Ok(actions);
+        };
+        let Some(network) = context.client_network() else {
+            warn!(ip_address = %context.ip_address, "Can't block a client without a valid IP");
+            return Ok(actions);
+        };
+        // A single address is reported as just the address
+        let ip_address = if network.prefix_len() == network.max_prefix_len() {
+            network.addr().to_string()
+        } else {
+            network.to_string()
+        };
+        if context.evaluate_only {
+            actions.push(DefensiveAction::BlockIp {
+                ip_address,
+                expires_at: Utc::now() + chrono::Duration::from_std(self.config.block_duration)?,
+            });
+            return Ok(actions);
+        }
+
+        match denylist.ban(network, self.config.block_duration).await {
+            Ok(expires_at) => {
+                warn!(
+                    ip_address = %ip_address,
+                    threat_score = score.score,
+                    expires_at = %expires_at,
+                    "Blocked IP after threat detection"
+                );
+                actions.push(DefensiveAction::BlockIp { ip_address, expires_at });
+            }
+            // The request is still rejected; only the ban is lost
+            Err(e) => error!(ip_address = %ip_address, error = %e, "Failed to block IP"),
+        }
+        Ok(actions)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn context() -> RequestContext {
+        RequestContext::new("192.0.2.44".to_string(), "/v1/check".to_string(), "POST".to_string())
+    }
+
+    #[tokio::test]
+    async fn test_rejects_below_block_threshold() {
+        let engine = ResponseEngine::new(ResponseConfig::default())
+            .with_ip_denylist(Arc::new(IpDenylist::with_bans(&[], &[])));
+
+        let actions = engine
+            .respond_to_threat(&context(), &ThreatScore::new("test".to_string(), 0.7, 0.9))
+            .await
+            .unwrap();
+        assert_eq!(actions, vec![DefensiveAction::RejectRequest]);
+    }
+
+    #[tokio::test]
+    async fn test_evaluation_does_not_ban() {
+        let denylist = Arc::new(IpDenylist::with_bans(&[], &[]));
+        let engine = ResponseEngine::new(ResponseConfig::default()).with_ip_denylist(denylist.clone());
+
+        let actions = engine
+            .respond_to_threat(&context().evaluate_only(), &ThreatScore::new("test".to_string(), 0.95, 0.9))
+            .await
+            .unwrap();
+        assert_eq!(actions[0], DefensiveAction::RejectRequest);
+        assert!(matches!(&actions[1], DefensiveAction::BlockIp { ip_address, .. } if ip_address == "192.0.2.44"));
+        assert!(denylist.check("192.0.2.44".parse().unwrap()).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_grouped_ipv6_client_is_banned_with_its_prefix() {
+        let engine = ResponseEngine::new(ResponseConfig::default())
+            .with_ip_denylist(Arc::new(IpDenylist::with_bans(&[], &[])));
+        let context = |ipv6_prefix_len| {
+            RequestContext::new("2001:db8:1:2::abcd".to_string(), "/v1/check".to_string(), "POST".to_string())
+                .with_ipv6_prefix_len(ipv6_prefix_len)
+                .evaluate_only()
+        };
+        let blocked = |actions: Vec<DefensiveAction>| match actions.get(1) {
+            Some(DefensiveAction::BlockIp { ip_address, .. }) => ip_address.clone(),
+            _ => panic!("not blocked: {:?}", actions),
+        };
+
+        let score = ThreatScore::new("test".to_string(), 0.95, 0.9);
+        assert_eq!(blocked(engine.respond_to_threat(&context(64), &score).await.unwrap()), "2001:db8:1:2::/64");
+        assert_eq!(blocked(engine.respond_to_threat(&context(128), &score).await.unwrap()), "2001:db8:1:2::abcd");
+    }
+
+    #[tokio::test]
+    async fn test_challenges_borderline_scores() {
+        struct AcceptAll;
+
+        #[async_trait::async_trait]
+        impl crate::security::challenge::CaptchaVerifier for AcceptAll {
+            async fn verify(&self, _response: &str, _remote_ip: &str) -> Result<bool> {
+                Ok(true)
+            }
+        }
+
+        let challenges = Arc::new(ChallengeManager::new(
+            crate::redis_backend::RedisConnector::open("redis://127.0.0.1:6379").unwrap(),
+            Arc::new(AcceptAll),
+            crate::config::ChallengeConfig::default(),
+        ));
+        let engine = ResponseEngine::new(ResponseConfig::default())
+            .with_ip_denylist(Arc::new(IpDenylist::with_bans(&[], &[])))
+            .with_challenges(challenges.clone());
+        let context = RequestContext::new("192.0.2.45".to_string(), "/v1/check".to_string(), "POST".to_string());
+
+        let actions = engine
+            .respond_to_threat(&context, &ThreatScore::new("test".to_string(), 0.7, 0.9))
+            .await
+            .unwrap();
+        let Some(DefensiveAction::Challenge { token }) = actions.first() else {
+            // Redis not available, so the request is rejected instead
+            assert_eq!(actions, vec![DefensiveAction::RejectRequest]);
+            return;
+        };
+
+        assert!(challenges.redeem("192.0.2.45", token, "solved").await.unwrap());
+        let actions = engine
+            .respond_to_threat(&context, &ThreatScore::new("test".to_string(), 0.7, 0.9))
+            .await
+            .unwrap();
+        assert!(actions.is_empty());
+
+        // Above the challenge band the client is rejected even after solving one
+        let actions = engine
+            .respond_to_threat(&context, &ThreatScore::new("test".to_string(), 0.85, 0.9))
+            .await
+            .unwrap();
+        assert_eq!(actions.first(), Some(&DefensiveAction::RejectRequest));
+
+        let mut conn = crate::redis_backend::RedisConnector::open("redis://127.0.0.1:6379")
+            .unwrap()
+            .get_async_connection()
+            .await
+            .unwrap();
+        let _: () = redis::AsyncCommands::del(&mut conn, "challenge:solved:192.0.2.45").await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_block_ip_bans_client() {
+        let denylist = Arc::new(IpDenylist::new(
+            crate::redis_backend::RedisConnector::open("redis://127.0.0.1:6379").unwrap(),
+            &[] as &[&str],
+        ).unwrap().with_cache_ttl(Duration::ZERO));
+        let engine = ResponseEngine::new(ResponseConfig {
+            block_duration: Duration::from_secs(30),
+            ..ResponseConfig::default()
+        })
+        .with_ip_denylist(denylist.clone());
+
+        let actions = engine
+            .respond_to_threat(&context(), &ThreatScore::new("test".to_string(), 0.95, 0.9))
+            .await
+            .unwrap();
+        let Some(DefensiveAction::BlockIp { ip_address, .. }) = actions.get(1) else {
+            // Redis not available
+            return;
+        };
+        assert_eq!(ip_address, "192.0.2.44");
+        assert!(denylist.check("192.0.2.44".parse().unwrap()).await.is_some());
+        assert!(denylist.check("192.0.2.45".parse().unwrap()).await.is_none());
+
+        let mut conn = crate::redis_backend::RedisConnector::open("redis://127.0.0.1:6379")
+            .unwrap()
+            .get_async_connection()
+            .await
+            .unwrap();
+        let _: () = redis::AsyncCommands::zrem(&mut conn, "security:ip_bans", "192.0.2.44/32").await.unwrap();
+    }
+}
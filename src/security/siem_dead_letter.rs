@@ -0,0 +1,160 @@
+//! Batches a SIEM provider still refused after every retry.
+//!
+//! Each provider has its own Redis list, `siem:deadletter:{provider}`, of
+//! JSON `DeadLetterBatch`es, newest first and capped at
+//! `MAX_DEAD_LETTER_BATCHES`. Operators look at the counts with
+//! `GET /v1/security/siem/deadletter` and send the batches again, to the
+//! provider that failed them and no other, with
+//! `POST /v1/security/siem/deadletter/replay`.
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::redis_backend::RedisConnector;
+use crate::security::siem_integration::SecurityEvent;
+
+/// Oldest batches are discarded past this many per provider
+pub const MAX_DEAD_LETTER_BATCHES: isize = 1000;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeadLetterBatch {
+    pub provider: String,
+    pub failed_at: DateTime<Utc>,
+    /// Deliveries tried before giving up
+    pub attempts: u32,
+    pub error: String,
+    pub events: Vec<SecurityEvent>,
+}
+
+/// What's waiting for one provider
+#[derive(Debug, Clone, Default, Serialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct DeadLetterCounts {
+    pub batches: usize,
+    pub events: usize,
+    pub oldest_failed_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Clone)]
+pub struct SiemDeadLetter {
+    redis: RedisConnector,
+}
+
+impl SiemDeadLetter {
+    pub fn new(redis: RedisConnector) -> Self {
+        Self { redis }
+    }
+
+    fn key(provider: &str) -> String {
+        format!("siem:deadletter:{}", provider)
+    }
+
+    pub async fn push(&self, batch: &DeadLetterBatch) -> Result<()> {
+        let key = Self::key(&batch.provider);
+        let mut conn = self.redis.get_async_connection().await?;
+        redis::pipe()
+            .lpush(&key, serde_json::to_string(batch)?)
+            .ignore()
+            .ltrim(&key, 0, MAX_DEAD_LETTER_BATCHES - 1)
+            .ignore()
+            .query_async::<_, ()>(&mut conn)
+            .await?;
+        Ok(())
+    }
+
+    pub async fn counts(&self, provider: &str) -> Result<DeadLetterCounts> {
+        let mut conn = self.redis.get_async_connection().await?;
+        let entries: Vec<String> = redis::cmd("LRANGE")
+            .arg(Self::key(provider))
+            .arg(0)
+            .arg(-1)
+            .query_async(&mut conn)
+            .await?;
+
+        let mut counts = DeadLetterCounts::default();
+        for entry in entries {
+            let batch: DeadLetterBatch = serde_json::from_str(&entry)?;
+            counts.batches += 1;
+            counts.events += batch.events.len();
+            // Newest first, so the last one read is the oldest
+            counts.oldest_failed_at = Some(batch.failed_at);
+        }
+        Ok(counts)
+    }
+
+    /// Remove and return up to `max` of the provider's oldest batches
+    pub async fn take_oldest(&self, provider: &str, max: usize) -> Result<Vec<DeadLetterBatch>> {
+        let mut conn = self.redis.get_async_connection().await?;
+        let entries: Option<Vec<String>> = redis::cmd("RPOP")
+            .arg(Self::key(provider))
+            .arg(max)
+            .query_async(&mut conn)
+            .await?;
+
+        entries
+            .unwrap_or_default()
+            .iter()
+            .map(|entry| Ok(serde_json::from_str(entry)?))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn batch(provider: &str, events: usize, error: &str) -> DeadLetterBatch {
+        let event: SecurityEvent = serde_json::from_value(serde_json::json!({
+            "event_id": "evt-1",
+            "timestamp": Utc::now(),
+            "event_type": "ThreatDetected",
+            "severity": "High",
+            "source": "ratewatch",
+            "title": "Threat Detected",
+            "description": "test",
+            "threat_score": 0.9,
+            "confidence": 0.8,
+            "actor": { "ip_address": "203.0.113.7", "user_agent": null, "api_key_id": null, "tenant_id": null, "geolocation": null },
+            "target": { "resource_type": "api_endpoint", "resource_id": null, "endpoint": "/v1/check", "method": "POST" },
+            "actions_taken": [],
+            "raw_data": {},
+            "tags": [],
+            "correlation_id": "test",
+        }))
+        .unwrap();
+
+        DeadLetterBatch {
+            provider: provider.to_string(),
+            failed_at: Utc::now(),
+            attempts: 4,
+            error: error.to_string(),
+            events: vec![event; events],
+        }
+    }
+
+    #[tokio::test]
+    async fn test_dead_letters_are_counted_and_taken_oldest_first() {
+        let dead_letter = SiemDeadLetter::new(RedisConnector::open("redis://127.0.0.1:6379").unwrap());
+        let provider = format!("test-provider-{}", uuid::Uuid::new_v4());
+
+        if dead_letter.push(&batch(&provider, 2, "first")).await.is_err() {
+            println!("Skipping SIEM dead-letter test - Redis not available");
+            return;
+        }
+        dead_letter.push(&batch(&provider, 3, "second")).await.unwrap();
+
+        let counts = dead_letter.counts(&provider).await.unwrap();
+        assert_eq!(counts.batches, 2);
+        assert_eq!(counts.events, 5);
+
+        let taken = dead_letter.take_oldest(&provider, 1).await.unwrap();
+        assert_eq!(taken.len(), 1);
+        assert_eq!(taken[0].error, "first");
+
+        let taken = dead_letter.take_oldest(&provider, 10).await.unwrap();
+        assert_eq!(taken[0].error, "second");
+        assert_eq!(dead_letter.counts(&provider).await.unwrap().batches, 0);
+        assert!(dead_letter.take_oldest(&provider, 10).await.unwrap().is_empty());
+    }
+}
@@ -1,39 +1,84 @@
+pub use crate::security::geoip::GeolocationInfo;
+use crate::redis_backend::RedisConnector;
 use crate::security::{
     response_engine::DefensiveAction,
+    siem_dead_letter::{DeadLetterBatch, DeadLetterCounts, SiemDeadLetter},
+    siem_kafka::KafkaProvider,
+    siem_queue::{EventQueue, QueueOverflowPolicy},
+    siem_sentinel::SentinelProvider,
     threat_analyzer::{RequestContext, ThreatScore},
 };
-use anyhow::Result;
+use anyhow::{Context, Result};
 use chrono::{DateTime, Utc};
+use regex::Regex;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::future::Future;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::mpsc;
 use tracing::{debug, error, info, warn};
 
-#[derive(Debug)]
+/// Batches a provider can have waiting behind one it's still retrying before
+/// further batches go straight to the dead-letter
+const PROVIDER_BACKLOG_BATCHES: usize = 8;
+
+/// Longest wait between delivery attempts
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(60);
+
+#[derive(Clone)]
 pub struct SiemIntegration {
-    providers: Vec<SiemProviderType>,
+    providers: Vec<Arc<BuiltinProvider>>,
     config: SiemConfig,
-    event_queue: mpsc::UnboundedSender<SecurityEvent>,
+    /// Each provider's filters, compiled when the integration is created
+    event_filters: HashMap<String, Vec<CompiledEventFilter>>,
+    /// Holds at most `max_queue_size` events waiting to be flushed
+    event_queue: Arc<EventQueue<SecurityEvent>>,
+    /// Each provider delivers, retries and dead-letters on its own task, so a
+    /// failing one holds up nobody else
+    deliveries: HashMap<String, mpsc::Sender<Vec<SecurityEvent>>>,
+    /// Batches handed to a delivery task and not yet sent or dead-lettered
+    pending: Arc<AtomicUsize>,
+    dead_letter: SiemDeadLetter,
 }
 
+/// The providers `SiemProviderType` can currently be configured as
 #[derive(Debug, Clone)]
-pub enum SiemProviderType {
+pub enum BuiltinProvider {
     Syslog(SyslogProvider),
     Webhook(WebhookProvider),
     Splunk(SplunkProvider),
+    Sentinel(SentinelProvider),
+    Kafka(KafkaProvider),
 }
 
+/// `[security.siem]`; settings left out take their defaults
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "config-schema", derive(schemars::JsonSchema))]
+#[serde(default)]
 pub struct SiemConfig {
     pub enabled: bool,
     pub batch_size: usize,
     pub flush_interval_seconds: u64,
     pub max_queue_size: usize,
+    /// What to drop once `max_queue_size` events are waiting
+    #[serde(default)]
+    pub queue_overflow: QueueOverflowPolicy,
+    /// Retries after a failed delivery, doubling the delay from `retry_delay_ms`
+    /// each time, before the batch is dead-lettered
     pub retry_attempts: u32,
+    #[serde(default = "default_retry_delay_ms")]
+    pub retry_delay_ms: u64,
     pub providers: Vec<SiemProviderConfig>,
 }
 
+fn default_retry_delay_ms() -> u64 {
+    1000
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "config-schema", derive(schemars::JsonSchema))]
 pub struct SiemProviderConfig {
     pub name: String,
     pub provider_type: SiemProviderType,
@@ -43,6 +88,7 @@ pub struct SiemProviderConfig {
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "config-schema", derive(schemars::JsonSchema))]
 pub enum SiemProviderType {
     Splunk,
     ElasticSearch,
@@ -51,9 +97,11 @@ pub enum SiemProviderType {
     Sentinel,
     Syslog,
     Webhook,
+    Kafka,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "config-schema", derive(schemars::JsonSchema))]
 pub struct EventFilter {
     pub field: String,
     pub operator: FilterOperator,
@@ -61,6 +109,7 @@ pub struct EventFilter {
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "config-schema", derive(schemars::JsonSchema))]
 pub enum FilterOperator {
     Equals,
     NotEquals,
@@ -68,9 +117,147 @@ pub enum FilterOperator {
     NotContains,
     GreaterThan,
     LessThan,
+    /// `value` is a regular expression that must match somewhere in the field
     Regex,
 }
 
+/// The event field a filter looks at
+#[derive(Debug, Clone, PartialEq)]
+enum FilterField {
+    Severity,
+    EventType,
+    ThreatScore,
+    Confidence,
+    Source,
+    IpAddress,
+    TenantId,
+    ApiKeyId,
+    /// The request path the event is about
+    Endpoint,
+    Method,
+    /// Matches if any tag does; `Equals` and `Contains` test membership
+    Tags,
+    /// `raw_data.a.b` walks into `raw_data["a"]["b"]`; array elements are addressed by index
+    RawData(Vec<String>),
+}
+
+impl FilterField {
+    fn parse(field: &str) -> Result<Self> {
+        Ok(match field {
+            "severity" => FilterField::Severity,
+            "event_type" => FilterField::EventType,
+            "threat_score" => FilterField::ThreatScore,
+            "confidence" => FilterField::Confidence,
+            "source" => FilterField::Source,
+            "ip_address" => FilterField::IpAddress,
+            "tenant_id" => FilterField::TenantId,
+            "api_key_id" => FilterField::ApiKeyId,
+            "endpoint" => FilterField::Endpoint,
+            "method" => FilterField::Method,
+            "tags" => FilterField::Tags,
+            _ => match field.strip_prefix("raw_data.") {
+                Some(path) if path.split('.').all(|segment| !segment.is_empty()) => {
+                    FilterField::RawData(path.split('.').map(str::to_string).collect())
+                }
+                _ => anyhow::bail!(
+                    "Unknown event filter field '{}'; expected severity, event_type, threat_score, confidence, \
+                     source, ip_address, tenant_id, api_key_id, endpoint, method, tags or raw_data.<path>",
+                    field
+                ),
+            },
+        })
+    }
+
+    /// The field's values on `event`: none if it's unset, several for tags
+    fn values(&self, event: &SecurityEvent) -> Vec<String> {
+        match self {
+            FilterField::Severity => vec![format!("{:?}", event.severity)],
+            FilterField::EventType => vec![format!("{:?}", event.event_type)],
+            FilterField::ThreatScore => vec![event.threat_score.to_string()],
+            FilterField::Confidence => vec![event.confidence.to_string()],
+            FilterField::Source => vec![event.source.clone()],
+            FilterField::IpAddress => vec![event.actor.ip_address.clone()],
+            FilterField::TenantId => event.actor.tenant_id.iter().cloned().collect(),
+            FilterField::ApiKeyId => event.actor.api_key_id.iter().cloned().collect(),
+            FilterField::Endpoint => vec![event.target.endpoint.clone()],
+            FilterField::Method => vec![event.target.method.clone()],
+            FilterField::Tags => event.tags.clone(),
+            FilterField::RawData(path) => {
+                let mut value = event.raw_data.get(&path[0]);
+                for segment in &path[1..] {
+                    value = value.and_then(|value| match value {
+                        serde_json::Value::Array(items) => segment.parse::<usize>().ok().and_then(|i| items.get(i)),
+                        _ => value.get(segment),
+                    });
+                }
+                match value {
+                    None | Some(serde_json::Value::Null) => Vec::new(),
+                    Some(serde_json::Value::String(value)) => vec![value.clone()],
+                    Some(value) => vec![value.to_string()],
+                }
+            }
+        }
+    }
+}
+
+/// An `EventFilter` ready to apply, with its field resolved and its pattern
+/// compiled for `Regex`
+#[derive(Debug, Clone)]
+pub struct CompiledEventFilter {
+    filter: EventFilter,
+    field: FilterField,
+    regex: Option<Regex>,
+}
+
+impl EventFilter {
+    pub fn compile(&self) -> Result<CompiledEventFilter> {
+        let field = FilterField::parse(&self.field)?;
+        if field == FilterField::Tags && matches!(self.operator, FilterOperator::GreaterThan | FilterOperator::LessThan) {
+            anyhow::bail!("Event filters on 'tags' can't use {:?}", self.operator);
+        }
+        let regex = match self.operator {
+            FilterOperator::Regex => Some(Regex::new(&self.value).with_context(|| {
+                format!("Invalid regex {:?} in event filter on field '{}'", self.value, self.field)
+            })?),
+            _ => None,
+        };
+        Ok(CompiledEventFilter {
+            filter: self.clone(),
+            field,
+            regex,
+        })
+    }
+}
+
+impl SiemConfig {
+    /// Compile every enabled provider's event filters, failing on the first invalid one
+    pub fn compile_event_filters(&self) -> Result<HashMap<String, Vec<CompiledEventFilter>>> {
+        self.providers
+            .iter()
+            .filter(|provider| provider.enabled)
+            .map(|provider| {
+                let filters = provider
+                    .event_filters
+                    .iter()
+                    .map(EventFilter::compile)
+                    .collect::<Result<Vec<_>>>()
+                    .with_context(|| format!("Invalid event filters for SIEM provider '{}'", provider.name))?;
+                Ok((provider.name.clone(), filters))
+            })
+            .collect()
+    }
+}
+
+/// Provider reachability and queue pressure
+#[derive(Debug, Clone, Serialize)]
+pub struct SiemHealth {
+    pub providers: HashMap<String, bool>,
+    pub queued_events: usize,
+    pub queue_capacity: usize,
+    /// Since startup, because the queue was full
+    pub dropped_events: u64,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SecurityEvent {
     pub event_id: String,
@@ -128,15 +315,6 @@ pub struct TargetInfo {
     pub method: String,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct GeolocationInfo {
-    pub country: String,
-    pub region: Option<String>,
-    pub city: Option<String>,
-    pub latitude: Option<f64>,
-    pub longitude: Option<f64>,
-}
-
 pub trait SiemProvider: Send + Sync {
     fn provider_name(&self) -> &str;
     fn is_available(&self) -> bool;
@@ -146,9 +324,12 @@ pub trait SiemProvider: Send + Sync {
 }
 
 impl SiemIntegration {
-    pub async fn new(config: &SiemConfig) -> Result<Self> {
-        let (tx, mut rx) = mpsc::unbounded_channel::<SecurityEvent>();
-        let mut providers: Vec<Box<dyn SiemProvider>> = Vec::new();
+    pub async fn new(config: &SiemConfig, redis: RedisConnector) -> Result<Self> {
+        // Bad patterns fail here rather than when the first event arrives
+        let event_filters = config.compile_event_filters()?;
+        let event_queue = Arc::new(EventQueue::new(config.max_queue_size, config.queue_overflow));
+        let dead_letter = SiemDeadLetter::new(redis);
+        let mut providers = Vec::new();
 
         // Initialize providers based on configuration
         for provider_config in &config.providers {
@@ -156,36 +337,53 @@ impl SiemIntegration {
                 continue;
             }
 
-            match provider_config.provider_type {
-                SiemProviderType::Syslog => {
-                    providers.push(Box::new(SyslogProvider::new(provider_config)?));
-                }
-                SiemProviderType::Webhook => {
-                    providers.push(Box::new(WebhookProvider::new(provider_config)?));
-                }
-                SiemProviderType::Splunk => {
-                    providers.push(Box::new(SplunkProvider::new(provider_config)?));
-                }
+            let provider = match provider_config.provider_type {
+                SiemProviderType::Syslog => BuiltinProvider::Syslog(SyslogProvider::new(provider_config)?),
+                SiemProviderType::Webhook => BuiltinProvider::Webhook(WebhookProvider::new(provider_config)?),
+                SiemProviderType::Splunk => BuiltinProvider::Splunk(SplunkProvider::new(provider_config)?),
+                SiemProviderType::Sentinel => BuiltinProvider::Sentinel(SentinelProvider::new(provider_config)?),
+                SiemProviderType::Kafka => match KafkaProvider::from_config(provider_config)? {
+                    Some(provider) => BuiltinProvider::Kafka(provider),
+                    None => continue,
+                },
                 // Add other providers as needed
                 _ => {
                     warn!(
                         provider_type = ?provider_config.provider_type,
                         "SIEM provider type not yet implemented"
                     );
+                    continue;
                 }
-            }
+            };
+            providers.push(Arc::new(provider));
+        }
+
+        let retry = RetryPolicy {
+            attempts: config.retry_attempts,
+            base_delay: Duration::from_millis(config.retry_delay_ms),
+        };
+        let mut deliveries = HashMap::new();
+        let pending = Arc::new(AtomicUsize::new(0));
+        for provider in &providers {
+            let (tx, rx) = mpsc::channel(PROVIDER_BACKLOG_BATCHES);
+            deliveries.insert(provider.provider_name().to_string(), tx);
+            tokio::spawn(deliver_batches(provider.clone(), rx, retry, dead_letter.clone(), pending.clone()));
         }
 
         let siem = Self {
             providers,
             config: config.clone(),
-            event_queue: tx,
+            event_filters,
+            event_queue,
+            deliveries,
+            pending,
+            dead_letter,
         };
 
         // Start background event processor
         let siem_clone = siem.clone();
         tokio::spawn(async move {
-            siem_clone.process_events(rx).await;
+            siem_clone.process_events().await;
         });
 
         info!(
@@ -207,12 +405,23 @@ impl SiemIntegration {
         }
 
         let event = self.create_security_event(context, threat_score, actions_taken);
+        self.queue_event(event).await;
+
+        Ok(())
+    }
 
-        if let Err(e) = self.event_queue.send(event) {
-            error!(error = %e, "Failed to queue security event for SIEM");
+    /// Queue an event built outside threat detection, such as a tenant
+    /// isolation violation. Waits while the queue is full under the `block`
+    /// overflow policy.
+    pub async fn queue_event(&self, event: SecurityEvent) {
+        if !self.config.enabled {
+            return;
         }
 
-        Ok(())
+        // A full queue drops and counts events itself
+        if self.event_queue.push_or_wait(event).await {
+            crate::metrics::SIEM_EVENTS.with_label_values(&["queued"]).inc();
+        }
     }
 
     fn create_security_event(
@@ -234,7 +443,7 @@ impl SiemIntegration {
             user_agent: context.user_agent.clone(),
             api_key_id: context.api_key_id.clone(),
             tenant_id: context.tenant_id.clone(),
-            geolocation: None, // Would be populated from GeoIP service
+            geolocation: context.geolocation.clone(),
         };
 
         let target = TargetInfo {
@@ -253,6 +462,9 @@ impl SiemIntegration {
         raw_data.insert("threat_reasons".to_string(), serde_json::to_value(&threat_score.reasons).unwrap_or_default());
         raw_data.insert("threat_metadata".to_string(), serde_json::to_value(&threat_score.metadata).unwrap_or_default());
         raw_data.insert("request_headers".to_string(), serde_json::to_value(&context.headers).unwrap_or_default());
+        if let Some(fingerprint) = &context.tls_fingerprint {
+            raw_data.insert("tls_fingerprint".to_string(), serde_json::Value::String(fingerprint.clone()));
+        }
 
         let mut tags = vec![
             "ratewatch".to_string(),
@@ -289,36 +501,25 @@ impl SiemIntegration {
         }
     }
 
-    async fn process_events(&self, mut rx: mpsc::UnboundedReceiver<SecurityEvent>) {
-        let mut event_batch = Vec::new();
+    async fn process_events(&self) {
         let mut flush_interval = tokio::time::interval(
             tokio::time::Duration::from_secs(self.config.flush_interval_seconds)
         );
+        let batch_size = self.config.batch_size.max(1);
 
         loop {
             tokio::select! {
-                event = rx.recv() => {
-                    match event {
-                        Some(event) => {
-                            event_batch.push(event);
-                            
-                            // Flush if batch is full
-                            if event_batch.len() >= self.config.batch_size {
-                                self.flush_events(&mut event_batch).await;
-                            }
-                        }
-                        None => {
-                            // Channel closed, flush remaining events and exit
-                            if !event_batch.is_empty() {
-                                self.flush_events(&mut event_batch).await;
-                            }
-                            break;
-                        }
+                _ = self.event_queue.pushed() => {
+                    // Flush as soon as a full batch is waiting
+                    while self.event_queue.len() >= batch_size {
+                        let mut event_batch = self.event_queue.pop_batch(batch_size);
+                        self.flush_events(&mut event_batch).await;
                     }
                 }
                 _ = flush_interval.tick() => {
                     // Periodic flush
-                    if !event_batch.is_empty() {
+                    while !self.event_queue.is_empty() {
+                        let mut event_batch = self.event_queue.pop_batch(batch_size);
                         self.flush_events(&mut event_batch).await;
                     }
                 }
@@ -334,115 +535,249 @@ impl SiemIntegration {
         debug!(events_count = events.len(), "Flushing security events to SIEM");
 
         for provider in &self.providers {
-            if !provider.is_available() {
-                continue;
-            }
-
             // Filter events based on provider configuration
-            let filtered_events: Vec<&SecurityEvent> = events
+            let filtered_events: Vec<SecurityEvent> = events
                 .iter()
                 .filter(|event| self.should_send_to_provider(event, provider.provider_name()))
+                .cloned()
                 .collect();
 
             if filtered_events.is_empty() {
                 continue;
             }
+            self.hand_to_provider(provider.provider_name(), filtered_events).await;
+        }
 
-            // Convert to owned events for the provider
-            let owned_events: Vec<SecurityEvent> = filtered_events.into_iter().cloned().collect();
+        events.clear();
+    }
 
-            match provider.send_batch(&owned_events).await {
-                Ok(_) => {
-                    debug!(
-                        provider = provider.provider_name(),
-                        events_count = owned_events.len(),
-                        "Successfully sent events to SIEM provider"
-                    );
-                }
-                Err(e) => {
-                    error!(
-                        provider = provider.provider_name(),
-                        events_count = owned_events.len(),
-                        error = %e,
-                        "Failed to send events to SIEM provider"
-                    );
-                }
-            }
+    /// Queue a batch for one provider's delivery task, or dead-letter it if
+    /// that provider is too far behind to take it
+    async fn hand_to_provider(&self, provider: &str, events: Vec<SecurityEvent>) {
+        let Some(delivery) = self.deliveries.get(provider) else {
+            return;
+        };
+        self.pending.fetch_add(1, Ordering::SeqCst);
+        if let Err(mpsc::error::TrySendError::Full(events) | mpsc::error::TrySendError::Closed(events)) =
+            delivery.try_send(events)
+        {
+            self.pending.fetch_sub(1, Ordering::SeqCst);
+            warn!(provider, events_count = events.len(), "SIEM provider is backed up, dead-lettering batch");
+            dead_letter_batch(&self.dead_letter, provider, 0, "provider backlog full".to_string(), events).await;
         }
+    }
 
-        events.clear();
+    /// Send everything still queued and wait until each provider has sent or
+    /// dead-lettered it, for shutdown. Events arriving meanwhile go too.
+    pub async fn flush(&self) {
+        let batch_size = self.config.batch_size.max(1);
+        while !self.event_queue.is_empty() {
+            let mut event_batch = self.event_queue.pop_batch(batch_size);
+            self.flush_events(&mut event_batch).await;
+        }
+        while self.pending.load(Ordering::SeqCst) > 0 {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
     }
 
-    fn should_send_to_provider(&self, event: &SecurityEvent, provider_name: &str) -> bool {
-        // Find provider configuration
-        let provider_config = self
-            .config
-            .providers
-            .iter()
-            .find(|p| p.name == provider_name);
+    /// Events waiting to be flushed, and batches a provider has yet to deliver
+    pub fn backlog(&self) -> (usize, usize) {
+        (self.event_queue.len(), self.pending.load(Ordering::SeqCst))
+    }
 
-        if let Some(config) = provider_config {
-            // Apply event filters
-            for filter in &config.event_filters {
-                if !self.apply_event_filter(event, filter) {
-                    return false;
-                }
+    /// Whether `name` is a configured, enabled provider
+    pub fn has_provider(&self, name: &str) -> bool {
+        self.deliveries.contains_key(name)
+    }
+
+    /// Dead-lettered batches per provider
+    pub async fn dead_letter_counts(&self) -> Result<HashMap<String, DeadLetterCounts>> {
+        let mut counts = HashMap::new();
+        for provider in &self.providers {
+            let name = provider.provider_name();
+            counts.insert(name.to_string(), self.dead_letter.counts(name).await?);
+        }
+        Ok(counts)
+    }
+
+    /// Hand up to `max_batches` of the oldest dead-lettered batches back to
+    /// the provider that failed them, for every provider or just `provider`.
+    /// Returns how many batches and events were replayed.
+    pub async fn replay_dead_letters(&self, provider: Option<&str>, max_batches: usize) -> Result<(usize, usize)> {
+        if let Some(name) = provider {
+            if !self.deliveries.contains_key(name) {
+                anyhow::bail!("Unknown SIEM provider '{}'", name);
             }
         }
 
-        true
+        let (mut batches, mut events) = (0, 0);
+        for name in self.deliveries.keys().filter(|name| provider.map_or(true, |wanted| wanted == name.as_str())) {
+            for batch in self.dead_letter.take_oldest(name, max_batches).await? {
+                batches += 1;
+                events += batch.events.len();
+                self.hand_to_provider(name, batch.events).await;
+            }
+        }
+
+        info!(batches, events, "Replayed dead-lettered SIEM batches");
+        Ok((batches, events))
     }
 
-    fn apply_event_filter(&self, event: &SecurityEvent, filter: &EventFilter) -> bool {
-        let field_value = match filter.field.as_str() {
-            "severity" => format!("{:?}", event.severity),
-            "event_type" => format!("{:?}", event.event_type),
-            "threat_score" => event.threat_score.to_string(),
-            "source" => event.source.clone(),
-            "ip_address" => event.actor.ip_address.clone(),
-            _ => return true, // Unknown field, don't filter
+    fn should_send_to_provider(&self, event: &SecurityEvent, provider_name: &str) -> bool {
+        // Apply the provider's event filters
+        match self.event_filters.get(provider_name) {
+            Some(filters) => filters.iter().all(|filter| self.apply_event_filter(event, filter)),
+            None => true,
+        }
+    }
+
+    fn apply_event_filter(&self, event: &SecurityEvent, compiled: &CompiledEventFilter) -> bool {
+        let filter = &compiled.filter;
+        let values = compiled.field.values(event);
+        // Tags are a set: equality and containment both mean "has this tag"
+        let is_tags = compiled.field == FilterField::Tags;
+
+        let any = |test: &dyn Fn(&str) -> bool| values.iter().any(|value| test(value));
+        let numeric = |compare: fn(f64, f64) -> bool| {
+            filter
+                .value
+                .parse::<f64>()
+                .is_ok_and(|filter_num| any(&|value| value.parse::<f64>().is_ok_and(|num| compare(num, filter_num))))
         };
 
+        // A field the event doesn't have matches only the negated operators
         match filter.operator {
-            FilterOperator::Equals => field_value == filter.value,
-            FilterOperator::NotEquals => field_value != filter.value,
-            FilterOperator::Contains => field_value.contains(&filter.value),
-            FilterOperator::NotContains => !field_value.contains(&filter.value),
-            FilterOperator::GreaterThan => {
-                if let (Ok(field_num), Ok(filter_num)) = (field_value.parse::<f64>(), filter.value.parse::<f64>()) {
-                    field_num > filter_num
-                } else {
-                    false
-                }
-            }
-            FilterOperator::LessThan => {
-                if let (Ok(field_num), Ok(filter_num)) = (field_value.parse::<f64>(), filter.value.parse::<f64>()) {
-                    field_num < filter_num
-                } else {
-                    false
-                }
-            }
-            FilterOperator::Regex => {
-                // Would implement regex matching here
-                true
-            }
+            FilterOperator::Equals => any(&|value| value == filter.value),
+            FilterOperator::NotEquals => !any(&|value| value == filter.value),
+            FilterOperator::Contains if is_tags => any(&|value| value == filter.value),
+            FilterOperator::Contains => any(&|value| value.contains(&filter.value)),
+            FilterOperator::NotContains if is_tags => !any(&|value| value == filter.value),
+            FilterOperator::NotContains => !any(&|value| value.contains(&filter.value)),
+            FilterOperator::GreaterThan => numeric(|num, filter_num| num > filter_num),
+            FilterOperator::LessThan => numeric(|num, filter_num| num < filter_num),
+            FilterOperator::Regex => compiled
+                .regex
+                .as_ref()
+                .is_some_and(|regex| any(&|value| regex.is_match(value))),
         }
     }
 
-    pub async fn health_check(&self) -> Result<HashMap<String, bool>> {
-        let mut health_status = HashMap::new();
+    pub async fn health_check(&self) -> Result<SiemHealth> {
+        let mut providers = HashMap::new();
 
         for provider in &self.providers {
             let is_healthy = provider.health_check().await.unwrap_or(false);
-            health_status.insert(provider.provider_name().to_string(), is_healthy);
+            providers.insert(provider.provider_name().to_string(), is_healthy);
+        }
+
+        Ok(SiemHealth {
+            providers,
+            queued_events: self.event_queue.len(),
+            queue_capacity: self.event_queue.capacity(),
+            dropped_events: self.event_queue.dropped(),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct RetryPolicy {
+    /// Retries after the first attempt
+    attempts: u32,
+    base_delay: Duration,
+}
+
+impl RetryPolicy {
+    fn delay(&self, retry: u32) -> Duration {
+        self.base_delay
+            .saturating_mul(2u32.saturating_pow(retry))
+            .min(MAX_RETRY_DELAY)
+    }
+}
+
+/// Run `send` until it succeeds or the retries run out, backing off between
+/// attempts. On failure returns the attempts made and the last error.
+async fn send_with_retry<F, Fut>(retry: RetryPolicy, mut send: F) -> std::result::Result<(), (u32, anyhow::Error)>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<()>>,
+{
+    let mut attempt = 0;
+    loop {
+        match send().await {
+            Ok(()) => return Ok(()),
+            Err(e) if attempt >= retry.attempts => return Err((attempt + 1, e)),
+            Err(e) => {
+                let delay = retry.delay(attempt);
+                debug!(attempt = attempt + 1, error = %e, ?delay, "SIEM delivery failed, retrying");
+                tokio::time::sleep(delay).await;
+                attempt += 1;
+            }
         }
+    }
+}
 
-        Ok(health_status)
+/// One provider's delivery task: its batches go out in order, each retried
+/// before the next is tried
+async fn deliver_batches(
+    provider: Arc<BuiltinProvider>,
+    mut batches: mpsc::Receiver<Vec<SecurityEvent>>,
+    retry: RetryPolicy,
+    dead_letter: SiemDeadLetter,
+    pending: Arc<AtomicUsize>,
+) {
+    while let Some(events) = batches.recv().await {
+        match send_with_retry(retry, || provider.send_batch(&events)).await {
+            Ok(()) => {
+                crate::metrics::SIEM_EVENTS.with_label_values(&["sent"]).inc_by(events.len() as u64);
+                debug!(
+                    provider = provider.provider_name(),
+                    events_count = events.len(),
+                    "Successfully sent events to SIEM provider"
+                );
+            }
+            Err((attempts, e)) => {
+                error!(
+                    provider = provider.provider_name(),
+                    events_count = events.len(),
+                    attempts,
+                    error = %e,
+                    "Failed to send events to SIEM provider, dead-lettering batch"
+                );
+                dead_letter_batch(&dead_letter, provider.provider_name(), attempts, e.to_string(), events).await;
+            }
+        }
+        pending.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+async fn dead_letter_batch(
+    dead_letter: &SiemDeadLetter,
+    provider: &str,
+    attempts: u32,
+    error: String,
+    events: Vec<SecurityEvent>,
+) {
+    let count = events.len() as u64;
+    let batch = DeadLetterBatch {
+        provider: provider.to_string(),
+        failed_at: Utc::now(),
+        attempts,
+        error,
+        events,
+    };
+    match dead_letter.push(&batch).await {
+        Ok(()) => crate::metrics::SIEM_EVENTS.with_label_values(&["dead_lettered"]).inc_by(count),
+        Err(e) => {
+            // Nowhere left to keep them
+            crate::metrics::SIEM_EVENTS.with_label_values(&["lost"]).inc_by(count);
+            error!(provider, events_count = count, error = %e, "Failed to dead-letter SIEM batch");
+        }
     }
 }
 
 // Built-in SIEM providers
 
+#[derive(Debug, Clone)]
 pub struct SyslogProvider {
     name: String,
     facility: u8,
@@ -503,6 +838,7 @@ impl SiemProvider for SyslogProvider {
     }
 }
 
+#[derive(Debug, Clone)]
 pub struct WebhookProvider {
     name: String,
     url: String,
@@ -571,6 +907,7 @@ impl SiemProvider for WebhookProvider {
     }
 }
 
+#[derive(Debug, Clone)]
 pub struct SplunkProvider {
     name: String,
     hec_url: String,
@@ -641,42 +978,62 @@ impl Default for SiemConfig {
             batch_size: 100,
             flush_interval_seconds: 30,
             max_queue_size: 10000,
+            queue_overflow: QueueOverflowPolicy::default(),
             retry_attempts: 3,
+            retry_delay_ms: default_retry_delay_ms(),
             providers: Vec::new(),
         }
     }
 }
 
-impl SiemProvider for SiemProviderType {
-    async fn send_event(&self, event: &SecurityEvent) -> Result<()> {
+impl SiemProvider for BuiltinProvider {
+    fn provider_name(&self) -> &str {
         match self {
-            SiemProviderType::Syslog(provider) => provider.send_event(event).await,
-            SiemProviderType::Webhook(provider) => provider.send_event(event).await,
-            SiemProviderType::Splunk(provider) => provider.send_event(event).await,
+            BuiltinProvider::Syslog(provider) => provider.provider_name(),
+            BuiltinProvider::Webhook(provider) => provider.provider_name(),
+            BuiltinProvider::Splunk(provider) => provider.provider_name(),
+            BuiltinProvider::Sentinel(provider) => provider.provider_name(),
+            BuiltinProvider::Kafka(provider) => provider.provider_name(),
         }
     }
 
-    async fn send_batch(&self, events: &[SecurityEvent]) -> Result<()> {
+    fn is_available(&self) -> bool {
         match self {
-            SiemProviderType::Syslog(provider) => provider.send_batch(events).await,
-            SiemProviderType::Webhook(provider) => provider.send_batch(events).await,
-            SiemProviderType::Splunk(provider) => provider.send_batch(events).await,
+            BuiltinProvider::Syslog(provider) => provider.is_available(),
+            BuiltinProvider::Webhook(provider) => provider.is_available(),
+            BuiltinProvider::Splunk(provider) => provider.is_available(),
+            BuiltinProvider::Sentinel(provider) => provider.is_available(),
+            BuiltinProvider::Kafka(provider) => provider.is_available(),
         }
     }
 
-    async fn health_check(&self) -> Result<bool> {
+    async fn send_event(&self, event: &SecurityEvent) -> Result<()> {
+        match self {
+            BuiltinProvider::Syslog(provider) => provider.send_event(event).await,
+            BuiltinProvider::Webhook(provider) => provider.send_event(event).await,
+            BuiltinProvider::Splunk(provider) => provider.send_event(event).await,
+            BuiltinProvider::Sentinel(provider) => provider.send_event(event).await,
+            BuiltinProvider::Kafka(provider) => provider.send_event(event).await,
+        }
+    }
+
+    async fn send_batch(&self, events: &[SecurityEvent]) -> Result<()> {
         match self {
-            SiemProviderType::Syslog(provider) => provider.health_check().await,
-            SiemProviderType::Webhook(provider) => provider.health_check().await,
-            SiemProviderType::Splunk(provider) => provider.health_check().await,
+            BuiltinProvider::Syslog(provider) => provider.send_batch(events).await,
+            BuiltinProvider::Webhook(provider) => provider.send_batch(events).await,
+            BuiltinProvider::Splunk(provider) => provider.send_batch(events).await,
+            BuiltinProvider::Sentinel(provider) => provider.send_batch(events).await,
+            BuiltinProvider::Kafka(provider) => provider.send_batch(events).await,
         }
     }
 
-    fn provider_type(&self) -> &str {
+    async fn health_check(&self) -> Result<bool> {
         match self {
-            SiemProviderType::Syslog(provider) => provider.provider_type(),
-            SiemProviderType::Webhook(provider) => provider.provider_type(),
-            SiemProviderType::Splunk(provider) => provider.provider_type(),
+            BuiltinProvider::Syslog(provider) => provider.health_check().await,
+            BuiltinProvider::Webhook(provider) => provider.health_check().await,
+            BuiltinProvider::Splunk(provider) => provider.health_check().await,
+            BuiltinProvider::Sentinel(provider) => provider.health_check().await,
+            BuiltinProvider::Kafka(provider) => provider.health_check().await,
         }
     }
 }
@@ -685,6 +1042,19 @@ impl SiemProvider for SiemProviderType {
 mod tests {
     use super::*;
 
+    /// No providers and no background tasks
+    fn test_integration() -> SiemIntegration {
+        SiemIntegration {
+            providers: Vec::new(),
+            config: SiemConfig::default(),
+            event_filters: HashMap::new(),
+            event_queue: Arc::new(EventQueue::new(1, QueueOverflowPolicy::DropNewest)),
+            deliveries: HashMap::new(),
+            pending: Arc::new(AtomicUsize::new(0)),
+            dead_letter: SiemDeadLetter::new(RedisConnector::open("redis://127.0.0.1:1").unwrap()),
+        }
+    }
+
     #[test]
     fn test_security_event_creation() {
         let context = crate::security::threat_analyzer::RequestContext::new(
@@ -699,11 +1069,7 @@ mod tests {
             0.8,
         );
 
-        let siem = SiemIntegration {
-            providers: Vec::new(),
-            config: SiemConfig::default(),
-            event_queue: tokio::sync::mpsc::unbounded_channel().0,
-        };
+        let siem = test_integration();
 
         let event = siem.create_security_event(&context, &threat_score, &[]);
 
@@ -728,8 +1094,8 @@ mod tests {
             actor: ActorInfo {
                 ip_address: "192.168.1.1".to_string(),
                 user_agent: None,
-                api_key_id: None,
-                tenant_id: None,
+                api_key_id: Some("key_123".to_string()),
+                tenant_id: Some("acme".to_string()),
                 geolocation: None,
             },
             target: TargetInfo {
@@ -739,16 +1105,15 @@ mod tests {
                 method: "GET".to_string(),
             },
             actions_taken: Vec::new(),
-            raw_data: HashMap::new(),
-            tags: Vec::new(),
+            raw_data: HashMap::from([(
+                "threat_metadata".to_string(),
+                serde_json::json!({ "country": "NL", "asn": 64512, "reasons": ["tor_exit"] }),
+            )]),
+            tags: vec!["threat_detection".to_string(), "tenant:acme".to_string()],
             correlation_id: "test".to_string(),
         };
 
-        let siem = SiemIntegration {
-            providers: Vec::new(),
-            config: SiemConfig::default(),
-            event_queue: tokio::sync::mpsc::unbounded_channel().0,
-        };
+        let siem = test_integration();
 
         let filter = EventFilter {
             field: "severity".to_string(),
@@ -756,7 +1121,7 @@ mod tests {
             value: "High".to_string(),
         };
 
-        assert!(siem.apply_event_filter(&event, &filter));
+        assert!(siem.apply_event_filter(&event, &filter.compile().unwrap()));
 
         let filter2 = EventFilter {
             field: "threat_score".to_string(),
@@ -764,6 +1129,260 @@ mod tests {
             value: "0.5".to_string(),
         };
 
-        assert!(siem.apply_event_filter(&event, &filter2));
+        assert!(siem.apply_event_filter(&event, &filter2.compile().unwrap()));
+
+        let internal_ips = EventFilter {
+            field: "ip_address".to_string(),
+            operator: FilterOperator::Regex,
+            value: r"^192\.168\.\d+\.\d+$".to_string(),
+        };
+        assert!(siem.apply_event_filter(&event, &internal_ips.compile().unwrap()));
+
+        let severe = EventFilter {
+            field: "severity".to_string(),
+            operator: FilterOperator::Regex,
+            value: "^(Critical|Info)$".to_string(),
+        };
+        assert!(!siem.apply_event_filter(&event, &severe.compile().unwrap()));
+
+        let matches = |field: &str, operator: FilterOperator, value: &str| {
+            let filter = EventFilter {
+                field: field.to_string(),
+                operator,
+                value: value.to_string(),
+            };
+            siem.apply_event_filter(&event, &filter.compile().unwrap())
+        };
+
+        assert!(matches("tenant_id", FilterOperator::Equals, "acme"));
+        assert!(matches("api_key_id", FilterOperator::NotEquals, "key_456"));
+        assert!(matches("confidence", FilterOperator::GreaterThan, "0.75"));
+        assert!(matches("method", FilterOperator::Equals, "GET"));
+
+        // Route only admin traffic to a provider
+        let admin_only = EventFilter {
+            field: "endpoint".to_string(),
+            operator: FilterOperator::Regex,
+            value: "^/(admin|internal)/".to_string(),
+        }
+        .compile()
+        .unwrap();
+        assert!(!siem.apply_event_filter(&event, &admin_only));
+        let mut admin_event = event.clone();
+        admin_event.target.endpoint = "/admin/users".to_string();
+        assert!(siem.apply_event_filter(&admin_event, &admin_only));
+
+        // Tags match whole tags, not substrings of them
+        assert!(matches("tags", FilterOperator::Contains, "tenant:acme"));
+        assert!(!matches("tags", FilterOperator::Contains, "tenant"));
+        assert!(matches("tags", FilterOperator::NotContains, "tenant:globex"));
+        assert!(matches("tags", FilterOperator::Regex, "^tenant:"));
+
+        assert!(matches("raw_data.threat_metadata.country", FilterOperator::Equals, "NL"));
+        assert!(matches("raw_data.threat_metadata.asn", FilterOperator::LessThan, "65000"));
+        assert!(matches("raw_data.threat_metadata.reasons.0", FilterOperator::Equals, "tor_exit"));
+
+        // Fields the event doesn't have only satisfy the negated operators
+        assert!(!matches("raw_data.threat_metadata.city", FilterOperator::Equals, "Amsterdam"));
+        assert!(matches("raw_data.request_headers.host", FilterOperator::NotContains, "internal"));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_unknown_filter_field_is_rejected() {
+        let filter = |field: &str, operator: FilterOperator| EventFilter {
+            field: field.to_string(),
+            operator,
+            value: "1".to_string(),
+        };
+
+        let error = filter("tenant", FilterOperator::Equals).compile().unwrap_err().to_string();
+        assert!(error.contains("'tenant'"), "{error}");
+        assert!(filter("raw_data.", FilterOperator::Equals).compile().is_err());
+        assert!(filter("raw_data..country", FilterOperator::Equals).compile().is_err());
+        assert!(filter("tags", FilterOperator::GreaterThan).compile().is_err());
+    }
+
+    #[test]
+    fn test_invalid_regex_filter_is_rejected() {
+        let provider = |enabled: bool| -> SiemProviderConfig {
+            serde_json::from_value(serde_json::json!({
+                "name": "splunk-prod",
+                "provider_type": "Splunk",
+                "enabled": enabled,
+                "config": {},
+                "event_filters": [{ "field": "source", "operator": "Regex", "value": "ratewatch-(" }],
+            }))
+            .unwrap()
+        };
+
+        let config = SiemConfig {
+            providers: vec![provider(true)],
+            ..SiemConfig::default()
+        };
+        let error = format!("{:#}", config.compile_event_filters().unwrap_err());
+        assert!(error.contains("splunk-prod"), "{error}");
+        assert!(error.contains("ratewatch-("), "{error}");
+
+        // Disabled providers aren't loaded, so their filters aren't either
+        let config = SiemConfig {
+            providers: vec![provider(false)],
+            ..SiemConfig::default()
+        };
+        assert!(config.compile_event_filters().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_delivery_retries_with_backoff_then_gives_up() {
+        let retry = RetryPolicy {
+            attempts: 3,
+            base_delay: Duration::from_millis(100),
+        };
+        assert_eq!(retry.delay(0), Duration::from_millis(100));
+        assert_eq!(retry.delay(2), Duration::from_millis(400));
+        assert_eq!(retry.delay(20), MAX_RETRY_DELAY);
+
+        // Fails twice, then goes through on the third attempt
+        let calls = std::sync::atomic::AtomicU32::new(0);
+        let started = tokio::time::Instant::now();
+        let result = send_with_retry(retry, || async {
+            if calls.fetch_add(1, std::sync::atomic::Ordering::SeqCst) < 2 {
+                anyhow::bail!("provider unavailable")
+            }
+            Ok(())
+        })
+        .await;
+        assert!(result.is_ok());
+        assert_eq!(calls.load(std::sync::atomic::Ordering::SeqCst), 3);
+        assert!(started.elapsed() >= Duration::from_millis(300));
+
+        // Never succeeds: the first attempt plus three retries, then the error
+        let retry = RetryPolicy {
+            attempts: 3,
+            base_delay: Duration::from_millis(1),
+        };
+        let calls = std::sync::atomic::AtomicU32::new(0);
+        let result = send_with_retry(retry, || async {
+            calls.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            anyhow::bail!("provider unavailable")
+        })
+        .await;
+        let (attempts, error) = result.unwrap_err();
+        assert_eq!(attempts, 4);
+        assert_eq!(calls.load(std::sync::atomic::Ordering::SeqCst), 4);
+        assert_eq!(error.to_string(), "provider unavailable");
+    }
+
+    /// A Sentinel provider posting to a local server that answers 503 to the
+    /// first `failures` requests
+    async fn flaky_sentinel(name: &str, failures: u32) -> Arc<BuiltinProvider> {
+        use axum::{extract::State, http::StatusCode, routing::post, Router};
+        use std::sync::atomic::{AtomicU32, Ordering};
+
+        let app = Router::new()
+            .route(
+                "/api/logs",
+                post(|State(failures): State<Arc<AtomicU32>>| async move {
+                    match failures.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1)) {
+                        Ok(_) => StatusCode::SERVICE_UNAVAILABLE,
+                        Err(_) => StatusCode::OK,
+                    }
+                }),
+            )
+            .with_state(Arc::new(AtomicU32::new(failures)));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let endpoint = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let config: SiemProviderConfig = serde_json::from_value(serde_json::json!({
+            "name": name,
+            "provider_type": "Sentinel",
+            "enabled": true,
+            "config": {
+                "workspace_id": "ws",
+                "shared_key": "cmF0ZXdhdGNoLXRlc3Qtc2hhcmVkLWtleS0wMTIzNDU2Nzg5",
+                "endpoint": endpoint,
+            },
+            "event_filters": [],
+        }))
+        .unwrap();
+        Arc::new(BuiltinProvider::Sentinel(SentinelProvider::new(&config).unwrap()))
+    }
+
+    #[tokio::test]
+    async fn test_failed_batches_are_retried_then_dead_lettered() {
+        let dead_letter = SiemDeadLetter::new(RedisConnector::open("redis://127.0.0.1:6379").unwrap());
+        if dead_letter.counts("probe").await.is_err() {
+            println!("Skipping SIEM delivery test - Redis not available");
+            return;
+        }
+        let retry = RetryPolicy {
+            attempts: 2,
+            base_delay: Duration::from_millis(1),
+        };
+        let context = crate::security::threat_analyzer::RequestContext::new(
+            "192.0.2.9".to_string(),
+            "/v1/check".to_string(),
+            "POST".to_string(),
+        );
+        let event = test_integration().create_security_event(
+            &context,
+            &crate::security::threat_analyzer::ThreatScore::new("test".to_string(), 0.9, 0.9),
+            &[],
+        );
+
+        // Fails on all but the last attempt, so the batch goes through
+        let recovers = format!("recovers-{}", uuid::Uuid::new_v4());
+        // Never succeeds
+        let down = format!("down-{}", uuid::Uuid::new_v4());
+        for (name, failures) in [(&recovers, 2), (&down, u32::MAX)] {
+            let (tx, rx) = mpsc::channel(1);
+            tx.send(vec![event.clone(), event.clone()]).await.unwrap();
+            drop(tx);
+            let pending = Arc::new(AtomicUsize::new(1));
+            deliver_batches(flaky_sentinel(name, failures).await, rx, retry, dead_letter.clone(), pending).await;
+        }
+
+        assert_eq!(dead_letter.counts(&recovers).await.unwrap().batches, 0);
+        let batches = dead_letter.take_oldest(&down, 10).await.unwrap();
+        assert_eq!(batches.len(), 1);
+        assert_eq!(batches[0].attempts, 3);
+        assert_eq!(batches[0].events.len(), 2);
+        assert!(batches[0].error.contains("503"), "{}", batches[0].error);
+    }
+
+    #[tokio::test]
+    async fn test_flush_waits_for_delivery() {
+        let provider = flaky_sentinel("flush", 1).await;
+        let (tx, rx) = mpsc::channel(PROVIDER_BACKLOG_BATCHES);
+        let mut siem = test_integration();
+        siem.event_queue = Arc::new(EventQueue::new(10, QueueOverflowPolicy::DropNewest));
+        siem.providers = vec![provider.clone()];
+        siem.deliveries.insert("flush".to_string(), tx);
+        let retry = RetryPolicy {
+            attempts: 1,
+            base_delay: Duration::from_millis(200),
+        };
+        tokio::spawn(deliver_batches(provider, rx, retry, siem.dead_letter.clone(), siem.pending.clone()));
+
+        let context = crate::security::threat_analyzer::RequestContext::new(
+            "192.0.2.9".to_string(),
+            "/v1/check".to_string(),
+            "POST".to_string(),
+        );
+        let event = siem.create_security_event(
+            &context,
+            &crate::security::threat_analyzer::ThreatScore::new("test".to_string(), 0.9, 0.9),
+            &[],
+        );
+        for _ in 0..3 {
+            assert!(siem.event_queue.push(event.clone()));
+        }
+        assert_eq!(siem.backlog(), (3, 0));
+
+        // The first attempt fails, so flush has to sit out the retry
+        let started = tokio::time::Instant::now();
+        siem.flush().await;
+        assert!(started.elapsed() >= Duration::from_millis(200));
+        assert_eq!(siem.backlog(), (0, 0));
+    }
+}
@@ -0,0 +1,442 @@
+//! Kafka provider.
+//!
+//! Each security event is produced to `topic` as one JSON message, keyed by
+//! the event field named in `key` so a consumer sees a tenant's or a
+//! request's events in order on one partition: `correlation_id` (the
+//! default), `tenant_id`, `ip_address`, `api_key_id`, `event_id`, or `none`
+//! to spread events over the partitions. An event without the field is sent
+//! without a key.
+//!
+//! The rest of the provider's config map:
+//!
+//! - `brokers`: comma-separated bootstrap servers, required
+//! - `security_protocol`: `plaintext` (the default), `ssl`, `sasl_plaintext`
+//!   or `sasl_ssl`
+//! - `sasl_mechanism` (default `PLAIN`), `sasl_username`, `sasl_password`:
+//!   required with either SASL protocol
+//! - `ssl_ca_location`: CA bundle to verify the brokers with
+//! - `delivery_timeout_ms`: how long a batch may wait for its delivery
+//!   reports, default 30000
+//! - `rdkafka.<property>`: passed to librdkafka as `<property>`, e.g.
+//!   `rdkafka.compression.type = "lz4"`
+//!
+//! A batch is produced at once and succeeds only when every message's
+//! delivery report does. If any fails the whole batch is retried, so
+//! consumers may see some events twice. Producing needs a build with the
+//! `kafka` feature; without it a Kafka provider is skipped with a warning.
+
+use anyhow::{anyhow, bail, Context, Result};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::debug;
+
+use crate::security::siem_integration::{SecurityEvent, SiemProvider, SiemProviderConfig};
+
+const DEFAULT_DELIVERY_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// A message for the provider's topic
+#[derive(Debug, Clone, PartialEq)]
+pub struct KafkaRecord {
+    pub key: Option<String>,
+    pub payload: String,
+}
+
+/// Where records are produced; librdkafka in production
+#[async_trait::async_trait]
+pub trait KafkaProducer: Send + Sync {
+    /// Produce every record to `topic` and wait for their delivery reports,
+    /// failing if any message wasn't delivered
+    async fn produce(&self, topic: &str, records: &[KafkaRecord]) -> Result<()>;
+
+    /// Whether the brokers answer for `topic`
+    async fn health_check(&self, topic: &str) -> Result<bool>;
+}
+
+/// The event field messages are keyed by
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum KafkaKey {
+    CorrelationId,
+    TenantId,
+    IpAddress,
+    ApiKeyId,
+    EventId,
+    None,
+}
+
+impl KafkaKey {
+    fn parse(key: &str) -> Result<Self> {
+        Ok(match key {
+            "correlation_id" => KafkaKey::CorrelationId,
+            "tenant_id" => KafkaKey::TenantId,
+            "ip_address" => KafkaKey::IpAddress,
+            "api_key_id" => KafkaKey::ApiKeyId,
+            "event_id" => KafkaKey::EventId,
+            "none" => KafkaKey::None,
+            _ => bail!(
+                "Unknown Kafka key '{}'; expected correlation_id, tenant_id, ip_address, api_key_id, event_id or none",
+                key
+            ),
+        })
+    }
+
+    fn of(self, event: &SecurityEvent) -> Option<String> {
+        let key = match self {
+            KafkaKey::CorrelationId => Some(&event.correlation_id),
+            KafkaKey::TenantId => event.actor.tenant_id.as_ref(),
+            KafkaKey::IpAddress => Some(&event.actor.ip_address),
+            KafkaKey::ApiKeyId => event.actor.api_key_id.as_ref(),
+            KafkaKey::EventId => Some(&event.event_id),
+            KafkaKey::None => None,
+        };
+        key.filter(|key| !key.is_empty()).cloned()
+    }
+}
+
+#[derive(Clone)]
+pub struct KafkaProvider {
+    name: String,
+    topic: String,
+    key: KafkaKey,
+    producer: Arc<dyn KafkaProducer>,
+}
+
+impl std::fmt::Debug for KafkaProvider {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("KafkaProvider")
+            .field("name", &self.name)
+            .field("topic", &self.topic)
+            .field("key", &self.key)
+            .finish()
+    }
+}
+
+impl KafkaProvider {
+    /// The provider for `config`, or `None` if this build can't produce to Kafka
+    pub fn from_config(config: &SiemProviderConfig) -> Result<Option<Self>> {
+        // Checked either way, so a config mistake shows up in every build
+        let settings = client_settings(config)?;
+        let delivery_timeout = delivery_timeout(config)?;
+
+        #[cfg(feature = "kafka")]
+        {
+            let producer = RdKafkaProducer::new(&settings, delivery_timeout)
+                .with_context(|| format!("Failed to create Kafka producer for SIEM provider '{}'", config.name))?;
+            Self::with_producer(config, Arc::new(producer)).map(Some)
+        }
+
+        #[cfg(not(feature = "kafka"))]
+        {
+            let _ = (settings, delivery_timeout);
+            tracing::warn!(
+                provider = config.name,
+                "Kafka SIEM provider is configured but ratewatch was built without the `kafka` feature"
+            );
+            Ok(None)
+        }
+    }
+
+    /// The provider for `config`, producing through `producer`
+    pub fn with_producer(config: &SiemProviderConfig, producer: Arc<dyn KafkaProducer>) -> Result<Self> {
+        let topic = setting(config, "topic")
+            .ok_or_else(|| anyhow!("Kafka provider '{}' requires topic", config.name))?
+            .to_string();
+        let key = KafkaKey::parse(setting(config, "key").unwrap_or("correlation_id"))
+            .with_context(|| format!("Invalid key for Kafka provider '{}'", config.name))?;
+
+        Ok(Self {
+            name: config.name.clone(),
+            topic,
+            key,
+            producer,
+        })
+    }
+
+    fn record(&self, event: &SecurityEvent) -> Result<KafkaRecord> {
+        Ok(KafkaRecord {
+            key: self.key.of(event),
+            payload: serde_json::to_string(event)?,
+        })
+    }
+}
+
+fn setting<'a>(config: &'a SiemProviderConfig, key: &str) -> Option<&'a str> {
+    config.config.get(key).map(|value| value.trim()).filter(|value| !value.is_empty())
+}
+
+fn delivery_timeout(config: &SiemProviderConfig) -> Result<Duration> {
+    match setting(config, "delivery_timeout_ms") {
+        Some(ms) => ms
+            .parse()
+            .map(Duration::from_millis)
+            .with_context(|| format!("Kafka provider '{}' delivery_timeout_ms is not a number", config.name)),
+        None => Ok(DEFAULT_DELIVERY_TIMEOUT),
+    }
+}
+
+/// librdkafka properties for the provider's config map
+fn client_settings(config: &SiemProviderConfig) -> Result<Vec<(String, String)>> {
+    let required = |key: &str| {
+        setting(config, key).ok_or_else(|| anyhow!("Kafka provider '{}' requires {}", config.name, key))
+    };
+
+    let mut settings = vec![("bootstrap.servers".to_string(), required("brokers")?.to_string())];
+    let protocol = setting(config, "security_protocol").unwrap_or("plaintext").to_ascii_lowercase();
+    match protocol.as_str() {
+        "plaintext" | "ssl" => {}
+        "sasl_plaintext" | "sasl_ssl" => {
+            settings.push((
+                "sasl.mechanism".to_string(),
+                setting(config, "sasl_mechanism").unwrap_or("PLAIN").to_ascii_uppercase(),
+            ));
+            settings.push(("sasl.username".to_string(), required("sasl_username")?.to_string()));
+            settings.push(("sasl.password".to_string(), required("sasl_password")?.to_string()));
+        }
+        _ => bail!(
+            "Kafka provider '{}' security_protocol must be plaintext, ssl, sasl_plaintext or sasl_ssl",
+            config.name
+        ),
+    }
+    settings.push(("security.protocol".to_string(), protocol));
+    if let Some(ca) = setting(config, "ssl_ca_location") {
+        settings.push(("ssl.ca.location".to_string(), ca.to_string()));
+    }
+
+    // Sorted so the settings come out the same every time
+    let mut passthrough: Vec<_> = config
+        .config
+        .iter()
+        .filter_map(|(key, value)| Some((key.strip_prefix("rdkafka.")?.to_string(), value.clone())))
+        .collect();
+    passthrough.sort();
+    settings.extend(passthrough);
+    Ok(settings)
+}
+
+/// Produces through librdkafka
+#[cfg(feature = "kafka")]
+pub struct RdKafkaProducer {
+    producer: rdkafka::producer::FutureProducer,
+    delivery_timeout: Duration,
+}
+
+#[cfg(feature = "kafka")]
+impl RdKafkaProducer {
+    pub fn new(settings: &[(String, String)], delivery_timeout: Duration) -> Result<Self> {
+        let mut client_config = rdkafka::ClientConfig::new();
+        for (key, value) in settings {
+            client_config.set(key, value);
+        }
+        client_config.set("message.timeout.ms", delivery_timeout.as_millis().to_string());
+        Ok(Self {
+            producer: client_config.create()?,
+            delivery_timeout,
+        })
+    }
+}
+
+#[cfg(feature = "kafka")]
+#[async_trait::async_trait]
+impl KafkaProducer for RdKafkaProducer {
+    async fn produce(&self, topic: &str, records: &[KafkaRecord]) -> Result<()> {
+        use rdkafka::producer::FutureRecord;
+
+        // All enqueued before any report is awaited, so the batch goes out together
+        let deliveries = records.iter().map(|record| {
+            let mut message = FutureRecord::to(topic).payload(&record.payload);
+            if let Some(key) = &record.key {
+                message = message.key(key);
+            }
+            self.producer.send(message, self.delivery_timeout)
+        });
+        let failures: Vec<_> = futures_util::future::join_all(deliveries)
+            .await
+            .into_iter()
+            .filter_map(|result| result.err())
+            .collect();
+
+        if let Some((error, _)) = failures.first() {
+            bail!("Kafka didn't deliver {} of {} events: {}", failures.len(), records.len(), error);
+        }
+        Ok(())
+    }
+
+    async fn health_check(&self, topic: &str) -> Result<bool> {
+        use rdkafka::producer::Producer;
+
+        // Fetching metadata blocks, so it runs off the async workers
+        let producer = self.producer.clone();
+        let topic = topic.to_string();
+        let timeout = self.delivery_timeout;
+        tokio::task::spawn_blocking(move || -> Result<bool> {
+            let metadata = producer.client().fetch_metadata(Some(&topic), timeout)?;
+            Ok(metadata.topics().iter().any(|t| t.name() == topic && t.error().is_none()))
+        })
+        .await?
+    }
+}
+
+impl SiemProvider for KafkaProvider {
+    fn provider_name(&self) -> &str {
+        &self.name
+    }
+
+    fn is_available(&self) -> bool {
+        true
+    }
+
+    async fn send_event(&self, event: &SecurityEvent) -> Result<()> {
+        self.send_batch(std::slice::from_ref(event)).await
+    }
+
+    async fn send_batch(&self, events: &[SecurityEvent]) -> Result<()> {
+        let records = events.iter().map(|event| self.record(event)).collect::<Result<Vec<_>>>()?;
+        debug!(provider = self.name, topic = self.topic, events = records.len(), "KAFKA: Producing security event batch");
+        self.producer.produce(&self.topic, &records).await
+    }
+
+    async fn health_check(&self) -> Result<bool> {
+        self.producer.health_check(&self.topic).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::security::siem_integration::SiemProviderType;
+    use serde_json::{json, Value};
+    use tokio::sync::Mutex;
+
+    /// Keeps what was produced; fails every batch while `fail` is set
+    #[derive(Default)]
+    struct MockProducer {
+        produced: Mutex<Vec<(String, KafkaRecord)>>,
+        fail: std::sync::atomic::AtomicBool,
+    }
+
+    #[async_trait::async_trait]
+    impl KafkaProducer for MockProducer {
+        async fn produce(&self, topic: &str, records: &[KafkaRecord]) -> Result<()> {
+            if self.fail.load(std::sync::atomic::Ordering::SeqCst) {
+                bail!("Kafka didn't deliver {} of {} events: broker down", records.len(), records.len());
+            }
+            let mut produced = self.produced.lock().await;
+            produced.extend(records.iter().map(|record| (topic.to_string(), record.clone())));
+            Ok(())
+        }
+
+        async fn health_check(&self, _topic: &str) -> Result<bool> {
+            Ok(true)
+        }
+    }
+
+    fn provider_config(settings: &[(&str, &str)]) -> SiemProviderConfig {
+        SiemProviderConfig {
+            name: "kafka".to_string(),
+            provider_type: SiemProviderType::Kafka,
+            enabled: true,
+            config: settings.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect(),
+            event_filters: Vec::new(),
+        }
+    }
+
+    fn event(correlation_id: &str, tenant_id: Option<&str>) -> SecurityEvent {
+        serde_json::from_value(json!({
+            "event_id": format!("evt-{correlation_id}"),
+            "timestamp": chrono::Utc::now(),
+            "event_type": "ThreatDetected",
+            "severity": "High",
+            "source": "ratewatch",
+            "title": "Threat Detected",
+            "description": "test",
+            "threat_score": 0.9,
+            "confidence": 0.8,
+            "actor": { "ip_address": "203.0.113.7", "user_agent": null, "api_key_id": null, "tenant_id": tenant_id, "geolocation": null },
+            "target": { "resource_type": "api_endpoint", "resource_id": null, "endpoint": "/v1/check", "method": "POST" },
+            "actions_taken": [],
+            "raw_data": {},
+            "tags": ["brute_force"],
+            "correlation_id": correlation_id,
+        }))
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_events_are_produced_with_their_key() {
+        let producer = Arc::new(MockProducer::default());
+        let provider = KafkaProvider::with_producer(
+            &provider_config(&[("brokers", "kafka:9092"), ("topic", "security-events"), ("key", "tenant_id")]),
+            producer.clone(),
+        )
+        .unwrap();
+
+        let events = [event("req-1", Some("tenant-a")), event("req-2", None)];
+        provider.send_batch(&events).await.unwrap();
+
+        let produced = producer.produced.lock().await;
+        assert_eq!(produced.len(), 2);
+        assert!(produced.iter().all(|(topic, _)| topic == "security-events"));
+        assert_eq!(produced[0].1.key.as_deref(), Some("tenant-a"));
+        // No tenant, no key
+        assert_eq!(produced[1].1.key, None);
+        let payload: Value = serde_json::from_str(&produced[0].1.payload).unwrap();
+        assert_eq!(payload["correlation_id"], "req-1");
+        assert_eq!(payload["actor"]["tenant_id"], "tenant-a");
+        assert_eq!(payload["event_type"], "ThreatDetected");
+    }
+
+    #[tokio::test]
+    async fn test_correlation_id_is_the_default_key() {
+        let producer = Arc::new(MockProducer::default());
+        let provider =
+            KafkaProvider::with_producer(&provider_config(&[("topic", "security-events")]), producer.clone()).unwrap();
+
+        provider.send_event(&event("req-3", Some("tenant-a"))).await.unwrap();
+        assert_eq!(producer.produced.lock().await[0].1.key.as_deref(), Some("req-3"));
+    }
+
+    #[tokio::test]
+    async fn test_failed_delivery_fails_the_batch() {
+        let producer = Arc::new(MockProducer::default());
+        producer.fail.store(true, std::sync::atomic::Ordering::SeqCst);
+        let provider =
+            KafkaProvider::with_producer(&provider_config(&[("topic", "security-events")]), producer.clone()).unwrap();
+
+        // The error is what sends the batch to be retried and dead-lettered
+        let error = provider.send_batch(&[event("req-4", None)]).await.unwrap_err();
+        assert!(error.to_string().contains("broker down"));
+        assert!(producer.produced.lock().await.is_empty());
+    }
+
+    #[test]
+    fn test_config_maps_to_client_settings() {
+        let settings = client_settings(&provider_config(&[
+            ("brokers", "kafka-1:9093,kafka-2:9093"),
+            ("security_protocol", "SASL_SSL"),
+            ("sasl_mechanism", "scram-sha-512"),
+            ("sasl_username", "ratewatch"),
+            ("sasl_password", "secret"),
+            ("ssl_ca_location", "/etc/ssl/kafka-ca.pem"),
+            ("rdkafka.compression.type", "lz4"),
+        ]))
+        .unwrap();
+        let setting = |key: &str| settings.iter().find(|(k, _)| k == key).map(|(_, v)| v.as_str());
+        assert_eq!(setting("bootstrap.servers"), Some("kafka-1:9093,kafka-2:9093"));
+        assert_eq!(setting("security.protocol"), Some("sasl_ssl"));
+        assert_eq!(setting("sasl.mechanism"), Some("SCRAM-SHA-512"));
+        assert_eq!(setting("sasl.username"), Some("ratewatch"));
+        assert_eq!(setting("ssl.ca.location"), Some("/etc/ssl/kafka-ca.pem"));
+        assert_eq!(setting("compression.type"), Some("lz4"));
+
+        let settings = client_settings(&provider_config(&[("brokers", "kafka:9092")])).unwrap();
+        assert_eq!(settings.iter().find(|(k, _)| k == "security.protocol").unwrap().1, "plaintext");
+        assert!(!settings.iter().any(|(k, _)| k.starts_with("sasl.")));
+
+        assert!(client_settings(&provider_config(&[])).is_err());
+        assert!(client_settings(&provider_config(&[("brokers", "kafka:9092"), ("security_protocol", "sasl_ssl")])).is_err());
+        assert!(client_settings(&provider_config(&[("brokers", "kafka:9092"), ("security_protocol", "tls")])).is_err());
+
+        let producer: Arc<dyn KafkaProducer> = Arc::new(MockProducer::default());
+        assert!(KafkaProvider::with_producer(&provider_config(&[("brokers", "kafka:9092")]), producer.clone()).is_err());
+        assert!(KafkaProvider::with_producer(&provider_config(&[("topic", "t"), ("key", "user")]), producer).is_err());
+    }
+}
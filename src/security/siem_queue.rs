@@ -0,0 +1,228 @@
+//! Bounded buffer between threat detection and SIEM delivery.
+//!
+//! Events wait here until the next batch is flushed. When providers are slow
+//! or down the queue fills up, and rather than grow without limit (an attack
+//! is exactly when both the event rate and the backlog are highest) it drops
+//! events, or makes their senders wait, according to `QueueOverflowPolicy`.
+//! Drops are counted in
+//! `ratewatch_siem_events_total{outcome="dropped"}` and reported by
+//! `SiemIntegration::health_check`.
+
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tokio::sync::Notify;
+use tracing::warn;
+
+/// At most one "dropping events" warning per this long
+const DROP_WARNING_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Which event goes when the queue is full
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "config-schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum QueueOverflowPolicy {
+    /// Keep what's queued and discard the incoming event
+    #[default]
+    DropNewest,
+    /// Discard the oldest queued event to make room, favouring recent activity
+    DropOldest,
+    /// Wait for room, slowing down the requests that raise events until
+    /// delivery catches up. Nothing is dropped.
+    Block,
+}
+
+#[derive(Debug)]
+pub struct EventQueue<T> {
+    events: Mutex<VecDeque<T>>,
+    capacity: usize,
+    overflow: QueueOverflowPolicy,
+    notify: Notify,
+    /// Woken whenever events are taken off the queue
+    space: Notify,
+    dropped: AtomicU64,
+    /// When the last warning was logged, and the drop count at the time
+    last_warning: Mutex<Option<(Instant, u64)>>,
+}
+
+impl<T> EventQueue<T> {
+    pub fn new(capacity: usize, overflow: QueueOverflowPolicy) -> Self {
+        let capacity = capacity.max(1);
+        Self {
+            events: Mutex::new(VecDeque::with_capacity(capacity.min(1024))),
+            capacity,
+            overflow,
+            notify: Notify::new(),
+            space: Notify::new(),
+            dropped: AtomicU64::new(0),
+            last_warning: Mutex::new(None),
+        }
+    }
+
+    /// Queue `event`, dropping one per the overflow policy if full. Returns
+    /// false if it was `event` that got dropped. This never waits, so under
+    /// `Block` a full queue drops `event`; use `push_or_wait` instead.
+    pub fn push(&self, event: T) -> bool {
+        let (accepted, dropped_one) = {
+            let mut events = self.events.lock().unwrap();
+            if events.len() < self.capacity {
+                events.push_back(event);
+                (true, false)
+            } else {
+                match self.overflow {
+                    QueueOverflowPolicy::DropNewest | QueueOverflowPolicy::Block => (false, true),
+                    QueueOverflowPolicy::DropOldest => {
+                        events.pop_front();
+                        events.push_back(event);
+                        (true, true)
+                    }
+                }
+            }
+        };
+
+        if dropped_one {
+            self.record_drop();
+        }
+        self.notify.notify_one();
+        accepted
+    }
+
+    /// Queue `event`, waiting for room if the policy is `Block`; other
+    /// policies behave as `push`
+    pub async fn push_or_wait(&self, event: T) -> bool {
+        if self.overflow != QueueOverflowPolicy::Block {
+            return self.push(event);
+        }
+
+        loop {
+            // Registered before checking, so a pop in between isn't missed
+            let space = self.space.notified();
+            tokio::pin!(space);
+            space.as_mut().enable();
+
+            {
+                let mut events = self.events.lock().unwrap();
+                if events.len() < self.capacity {
+                    events.push_back(event);
+                    drop(events);
+                    self.notify.notify_one();
+                    return true;
+                }
+            }
+            space.await;
+        }
+    }
+
+    /// Up to `max` of the oldest events
+    pub fn pop_batch(&self, max: usize) -> Vec<T> {
+        let batch: Vec<T> = {
+            let mut events = self.events.lock().unwrap();
+            let count = max.min(events.len());
+            events.drain(..count).collect()
+        };
+        if !batch.is_empty() {
+            self.space.notify_waiters();
+        }
+        batch
+    }
+
+    /// Wait until something has been pushed since the last call
+    pub async fn pushed(&self) {
+        self.notify.notified().await
+    }
+
+    pub fn len(&self) -> usize {
+        self.events.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Events dropped since the queue was created
+    pub fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+
+    fn record_drop(&self) {
+        let dropped = self.dropped.fetch_add(1, Ordering::Relaxed) + 1;
+        crate::metrics::SIEM_EVENTS.with_label_values(&["dropped"]).inc();
+
+        let mut last_warning = self.last_warning.lock().unwrap();
+        let since_last = match *last_warning {
+            Some((at, _)) if at.elapsed() < DROP_WARNING_INTERVAL => return,
+            Some((_, dropped_then)) => dropped - dropped_then,
+            None => dropped,
+        };
+        *last_warning = Some((Instant::now(), dropped));
+        warn!(
+            dropped = since_last,
+            capacity = self.capacity,
+            policy = ?self.overflow,
+            "SIEM event queue is full, dropping events"
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_full_queue_drops_per_policy() {
+        let newest = EventQueue::new(3, QueueOverflowPolicy::DropNewest);
+        let oldest = EventQueue::new(3, QueueOverflowPolicy::DropOldest);
+        for event in 1..=5 {
+            newest.push(event);
+            oldest.push(event);
+        }
+
+        assert_eq!(newest.len(), 3);
+        assert_eq!(newest.dropped(), 2);
+        assert_eq!(newest.pop_batch(10), vec![1, 2, 3]);
+
+        assert_eq!(oldest.dropped(), 2);
+        assert_eq!(oldest.pop_batch(2), vec![3, 4]);
+        assert_eq!(oldest.pop_batch(2), vec![5]);
+        assert!(oldest.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_block_policy_waits_for_room() {
+        let queue = std::sync::Arc::new(EventQueue::new(2, QueueOverflowPolicy::Block));
+        for event in 1..=2 {
+            assert!(queue.push_or_wait(event).await);
+        }
+
+        let blocked = tokio::spawn({
+            let queue = queue.clone();
+            async move { queue.push_or_wait(3).await }
+        });
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(!blocked.is_finished());
+        assert_eq!(queue.len(), 2);
+
+        assert_eq!(queue.pop_batch(1), vec![1]);
+        assert!(tokio::time::timeout(Duration::from_secs(1), blocked).await.unwrap().unwrap());
+        assert_eq!(queue.pop_batch(10), vec![2, 3]);
+        assert_eq!(queue.dropped(), 0);
+    }
+
+    #[test]
+    fn test_push_reports_whether_event_was_kept() {
+        let queue = EventQueue::new(1, QueueOverflowPolicy::DropNewest);
+        assert!(queue.push("first"));
+        assert!(!queue.push("second"));
+
+        let queue = EventQueue::new(1, QueueOverflowPolicy::DropOldest);
+        assert!(queue.push("first"));
+        assert!(queue.push("second"));
+        assert_eq!(queue.pop_batch(1), vec!["second"]);
+    }
+}
@@ -1,3 +1,4 @@
+use crate::security::geoip::GeolocationInfo;
 use async_trait::async_trait;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
@@ -37,6 +38,88 @@ pub struct RequestContext {
     pub headers: HashMap<String, String>,
     pub rate_limit_key: Option<String>,
     pub previous_requests: Vec<PreviousRequest>,
+    /// Filled by `GeoIpResolver` when GeoIP is enabled
+    #[serde(default)]
+    pub geolocation: Option<GeolocationInfo>,
+    /// JA3 hash of the client's TLS handshake, when the terminating proxy reports one
+    #[serde(default)]
+    pub tls_fingerprint: Option<String>,
+    /// Score the request without changing anything: analyzers don't record
+    /// it and no defensive action is carried out
+    #[serde(default)]
+    pub evaluate_only: bool,
+    /// IPv6 clients are tracked by their network of this length; 128
+    /// tracks each address on its own
+    #[serde(default = "default_ipv6_prefix_len")]
+    pub ipv6_prefix_len: u8,
+    /// Size of the request body in bytes, when it's known up front
+    #[serde(default)]
+    pub payload_size: Option<u64>,
+}
+
+fn default_ipv6_prefix_len() -> u8 {
+    128
+}
+
+/// How able an analyzer is to score requests, from best to worst
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HealthStatus {
+    Healthy,
+    /// Scoring, but with reduced accuracy or recent failures
+    Degraded,
+    /// Can't score requests
+    Unhealthy,
+}
+
+/// An analyzer's report on its own health
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AnalyzerHealth {
+    pub analyzer_id: String,
+    pub status: HealthStatus,
+    pub message: Option<String>,
+}
+
+impl AnalyzerHealth {
+    pub fn new(analyzer_id: String) -> Self {
+        Self {
+            analyzer_id,
+            status: HealthStatus::Healthy,
+            message: None,
+        }
+    }
+
+    pub fn healthy(mut self) -> Self {
+        self.status = HealthStatus::Healthy;
+        self.message = None;
+        self
+    }
+
+    pub fn degraded(mut self, message: String) -> Self {
+        self.status = HealthStatus::Degraded;
+        self.message = Some(message);
+        self
+    }
+
+    pub fn unhealthy(mut self, message: String) -> Self {
+        self.status = HealthStatus::Unhealthy;
+        self.message = Some(message);
+        self
+    }
+
+    /// Health of an analyzer that keeps its state in `redis`: unhealthy if
+    /// Redis doesn't answer a PING
+    pub async fn of_redis(analyzer_id: &str, redis: &crate::redis_backend::RedisConnector) -> Self {
+        let ping: anyhow::Result<String> = async {
+            let mut conn = redis.get_async_connection().await?;
+            Ok(redis::cmd("PING").query_async(&mut conn).await?)
+        }
+        .await;
+        match ping {
+            Ok(_) => Self::new(analyzer_id.to_string()),
+            Err(e) => Self::new(analyzer_id.to_string()).unhealthy(format!("Redis unavailable: {:#}", e)),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -63,6 +146,11 @@ pub trait ThreatAnalyzer: Send + Sync {
     
     /// Update analyzer configuration
     async fn update_config(&mut self, config: serde_json::Value) -> anyhow::Result<()>;
+
+    /// Check the analyzer's dependencies; healthy unless overridden
+    async fn health_check(&self) -> anyhow::Result<AnalyzerHealth> {
+        Ok(AnalyzerHealth::new(self.analyzer_id().to_string()))
+    }
 }
 
 impl ThreatScore {
@@ -133,8 +221,10 @@ impl ThreatScore {
             }
         }
         
-        ThreatScore::new("combined".to_string(), weighted_score, weighted_confidence)
-            .with_reasons(combined_reasons)
+        let mut combined = ThreatScore::new("combined".to_string(), weighted_score, weighted_confidence)
+            .with_reasons(combined_reasons);
+        combined.metadata = combined_metadata;
+        combined
     }
     
     /// Check if this threat score indicates an actionable threat
@@ -203,9 +293,19 @@ impl RequestContext {
             headers: HashMap::new(),
             rate_limit_key: None,
             previous_requests: Vec::new(),
+            geolocation: None,
+            tls_fingerprint: None,
+            evaluate_only: false,
+            ipv6_prefix_len: default_ipv6_prefix_len(),
+            payload_size: None,
         }
     }
     
+    pub fn with_correlation_id(mut self, correlation_id: Uuid) -> Self {
+        self.correlation_id = correlation_id;
+        self
+    }
+
     pub fn with_user_agent(mut self, user_agent: String) -> Self {
         self.user_agent = Some(user_agent);
         self
@@ -236,6 +336,46 @@ impl RequestContext {
         self
     }
     
+    pub fn with_geolocation(mut self, geolocation: GeolocationInfo) -> Self {
+        self.geolocation = Some(geolocation);
+        self
+    }
+
+    pub fn with_payload_size(mut self, payload_size: u64) -> Self {
+        self.payload_size = Some(payload_size);
+        self
+    }
+
+    pub fn with_tls_fingerprint(mut self, fingerprint: String) -> Self {
+        self.tls_fingerprint = Some(fingerprint);
+        self
+    }
+
+    /// Analyze without recording the request or acting on the result
+    pub fn evaluate_only(mut self) -> Self {
+        self.evaluate_only = true;
+        self
+    }
+
+    pub fn with_ipv6_prefix_len(mut self, ipv6_prefix_len: u8) -> Self {
+        self.ipv6_prefix_len = ipv6_prefix_len;
+        self
+    }
+
+    /// The network the client is tracked as, if the IP is valid
+    pub fn client_network(&self) -> Option<ipnet::IpNet> {
+        let ip = self.ip_address.parse().ok()?;
+        Some(crate::ip_allowlist::client_network(ip, self.ipv6_prefix_len))
+    }
+
+    /// The client as a Redis key segment; see `crate::ip_allowlist::ip_key`
+    pub fn ip_key(&self) -> String {
+        match self.ip_address.parse() {
+            Ok(ip) => crate::ip_allowlist::ip_key(ip, self.ipv6_prefix_len),
+            Err(_) => self.ip_address.replace(':', "-"),
+        }
+    }
+    
     /// Get the request frequency over the last N minutes
     pub fn request_frequency(&self, minutes: i64) -> f64 {
         let cutoff = Utc::now() - chrono::Duration::minutes(minutes);
@@ -311,6 +451,18 @@ mod tests {
         assert_eq!(combined.confidence, 0.85); // (0.8 + 0.9) / 2
     }
 
+    #[test]
+    fn test_combined_score_keeps_analyzer_metadata() {
+        let scores = vec![
+            ThreatScore::new("asn_reputation".to_string(), 0.8, 0.9)
+                .with_metadata("asn".to_string(), serde_json::json!(64500)),
+            ThreatScore::new("ip_reputation".to_string(), 0.0, 0.8),
+        ];
+
+        let combined = ThreatScore::combine_scores(scores, None);
+        assert_eq!(combined.metadata["asn_reputation_asn"], serde_json::json!(64500));
+    }
+
     #[test]
     fn test_request_context_automation_detection() {
         let context = RequestContext::new(
@@ -1,22 +1,75 @@
 use crate::security::{
-    threat_analyzer::{ThreatAnalyzer, ThreatScore, RequestContext, ThreatLevel},
+    behavior_patterns::BehaviorPatterns,
+    challenge::ChallengeManager,
+    feedback::FeedbackStore,
+    geoip::GeoIpResolver,
+    ip_denylist::IpDenylist,
+    threat_analyzer::{AnalyzerHealth, HealthStatus, ThreatAnalyzer, ThreatScore, RequestContext, ThreatLevel},
     response_engine::{ResponseEngine, DefensiveAction},
     siem_integration::SiemIntegration,
 };
+use crate::audit::AuditLogger;
+use crate::config::{SecurityFailureMode, ThreatAnalysisMode};
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
-use std::sync::Arc;
-use tokio::sync::RwLock;
-use tracing::{error, info, warn};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, OnceLock, Weak};
+use tokio::sync::{mpsc, RwLock};
+use tracing::{error, info, warn, Instrument};
 use uuid::Uuid;
 
 pub struct ThreatDetector {
     analyzers: Vec<Box<dyn ThreatAnalyzer>>,
+    /// Run counts for `analyzers`, by position
+    analyzer_counters: Vec<AnalyzerCounters>,
+    counters: DetectorCounters,
     response_engine: Arc<ResponseEngine>,
     siem_integration: Option<Arc<SiemIntegration>>,
     config: Arc<RwLock<ThreatDetectorConfig>>,
+    feedback: Option<Arc<FeedbackStore>>,
+    geoip: Option<Arc<GeoIpResolver>>,
+    tls_fingerprint_header: Option<String>,
+    ip_denylist: Option<Arc<IpDenylist>>,
+    challenges: Option<Arc<ChallengeManager>>,
+    behavior_patterns: Option<Arc<BehaviorPatterns>>,
+    audit: Option<Arc<AuditLogger>>,
+    /// Requests waiting for `analyze_queued`, once it's started
+    async_queue: OnceLock<mpsc::Sender<RequestContext>>,
 }
 
+/// Running totals behind `get_statistics`. Each is updated together with its
+/// Prometheus counterpart, so the API and the dashboards report the same numbers.
+#[derive(Default)]
+struct DetectorCounters {
+    analyses: AtomicU64,
+    threats_detected: AtomicU64,
+    actions_taken: AtomicU64,
+    analysis_time_us: AtomicU64,
+}
+
+#[derive(Default)]
+struct AnalyzerCounters {
+    runs: AtomicU64,
+    errors: AtomicU64,
+    timeouts: AtomicU64,
+    /// Whether the most recent run failed or timed out
+    failing: AtomicBool,
+    last_error: std::sync::Mutex<Option<String>>,
+}
+
+impl AnalyzerCounters {
+    fn record_failure(&self, error: String) {
+        self.failing.store(true, Ordering::Relaxed);
+        *self.last_error.lock().unwrap_or_else(|e| e.into_inner()) = Some(error);
+    }
+}
+
+/// Longest an analyzer's health check may take before it counts as unhealthy
+const HEALTH_CHECK_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
+
+/// Queued requests analyzed at once in `async` mode
+const ASYNC_ANALYSIS_CONCURRENCY: usize = 32;
+
 #[derive(Debug, Clone)]
 pub struct ThreatDetectorConfig {
     pub enabled: bool,
@@ -24,14 +77,28 @@ pub struct ThreatDetectorConfig {
     pub confidence_threshold: f64,
     pub auto_response_enabled: bool,
     pub analyzer_weights: std::collections::HashMap<String, f64>,
+    /// Longest any one analyzer may run
     pub max_analysis_time_ms: u64,
+    /// Longest a whole analysis may take, defensive actions and the SIEM
+    /// hand-off included
+    pub max_total_analysis_time_ms: u64,
+    pub mode: ThreatAnalysisMode,
+    pub failure_mode: SecurityFailureMode,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct ThreatAnalysisResult {
     pub correlation_id: Uuid,
     pub overall_score: ThreatScore,
     pub individual_scores: Vec<ThreatScore>,
+    /// Whether the overall score passed the threat and confidence thresholds
+    pub threat_detected: bool,
+    /// Whether an analyzer or the defensive actions were cut off by a
+    /// deadline, so the result is based on what finished in time
+    pub partial: bool,
+    /// Analyzers that returned an error, and so aren't in the score
+    pub failed_analyzers: Vec<String>,
+    /// For an evaluate-only request, the actions that would have been taken
     pub actions_taken: Vec<DefensiveAction>,
     pub analysis_duration_ms: u64,
     pub timestamp: chrono::DateTime<chrono::Utc>,
@@ -50,42 +117,202 @@ impl ThreatDetector {
             auto_response_enabled: true,
             analyzer_weights: std::collections::HashMap::new(),
             max_analysis_time_ms: 5000,
+            max_total_analysis_time_ms: 5000,
+            mode: ThreatAnalysisMode::Inline,
+            failure_mode: SecurityFailureMode::FailOpen,
         };
 
         Self {
+            analyzer_counters: analyzers.iter().map(|_| AnalyzerCounters::default()).collect(),
+            counters: DetectorCounters::default(),
             analyzers,
             response_engine,
             siem_integration,
             config: Arc::new(RwLock::new(config)),
+            feedback: None,
+            geoip: None,
+            tls_fingerprint_header: None,
+            ip_denylist: None,
+            challenges: None,
+            behavior_patterns: None,
+            audit: None,
+            async_queue: OnceLock::new(),
         }
     }
 
-    /// Analyze a request for threats and optionally take defensive actions
+    /// Accept false-positive reports through the feedback API
+    pub fn with_feedback(mut self, feedback: Arc<FeedbackStore>) -> Self {
+        self.feedback = Some(feedback);
+        self
+    }
+
+    /// Where false-positive reports are stored, if feedback is enabled
+    pub fn feedback(&self) -> Option<&Arc<FeedbackStore>> {
+        self.feedback.as_ref()
+    }
+
+    /// Weigh analyzers' scores by ID when combining them; unlisted analyzers
+    /// weigh 1.0. Tunable later through `update_config`.
+    pub fn with_analyzer_weights(mut self, weights: std::collections::HashMap<String, f64>) -> Self {
+        Arc::get_mut(&mut self.config)
+            .expect("weights are set before the detector is shared")
+            .get_mut()
+            .analyzer_weights = weights;
+        self
+    }
+
+    /// Cap a whole analysis at `budget_ms`, on top of the per-analyzer limit.
+    /// Tunable later through `update_config`.
+    pub fn with_max_total_analysis_time_ms(mut self, budget_ms: u64) -> Self {
+        Arc::get_mut(&mut self.config)
+            .expect("the analysis budget is set before the detector is shared")
+            .get_mut()
+            .max_total_analysis_time_ms = budget_ms;
+        self
+    }
+
+    /// Analyze requests in `async` mode from the start; can be switched later
+    /// through `update_config`
+    pub fn with_mode(mut self, mode: ThreatAnalysisMode) -> Self {
+        Arc::get_mut(&mut self.config)
+            .expect("the mode is set before the detector is shared")
+            .get_mut()
+            .mode = mode;
+        self
+    }
+
+    /// Refuse requests whose analysis fails instead of letting them through;
+    /// can be switched later through `update_config`
+    pub fn with_failure_mode(mut self, failure_mode: SecurityFailureMode) -> Self {
+        Arc::get_mut(&mut self.config)
+            .expect("the failure mode is set before the detector is shared")
+            .get_mut()
+            .failure_mode = failure_mode;
+        self
+    }
+
+    /// Record failed analyses, and what the failure mode decided, in the audit trail
+    pub fn with_audit(mut self, audit: Arc<AuditLogger>) -> Self {
+        self.audit = Some(audit);
+        self
+    }
+
+    pub fn audit(&self) -> Option<&Arc<AuditLogger>> {
+        self.audit.as_ref()
+    }
+
+    /// Resolve each request's country before it's analyzed
+    pub fn with_geoip(mut self, geoip: Arc<GeoIpResolver>) -> Self {
+        self.geoip = Some(geoip);
+        self
+    }
+
+    pub fn geoip(&self) -> Option<&Arc<GeoIpResolver>> {
+        self.geoip.as_ref()
+    }
+
+    /// Read each request's JA3 hash from `header`, set by the TLS-terminating proxy
+    pub fn with_tls_fingerprint_header(mut self, header: String) -> Self {
+        self.tls_fingerprint_header = Some(header);
+        self
+    }
+
+    pub fn tls_fingerprint_header(&self) -> Option<&str> {
+        self.tls_fingerprint_header.as_deref()
+    }
+
+    /// Refuse banned clients before they're analyzed
+    pub fn with_ip_denylist(mut self, ip_denylist: Arc<IpDenylist>) -> Self {
+        self.ip_denylist = Some(ip_denylist);
+        self
+    }
+
+    pub fn ip_denylist(&self) -> Option<&Arc<IpDenylist>> {
+        self.ip_denylist.as_ref()
+    }
+
+    /// Redeem solved CAPTCHA challenges; the response engine issues them
+    pub fn with_challenges(mut self, challenges: Arc<ChallengeManager>) -> Self {
+        self.challenges = Some(challenges);
+        self
+    }
+
+    pub fn challenges(&self) -> Option<&Arc<ChallengeManager>> {
+        self.challenges.as_ref()
+    }
+
+    /// Serve the behavior analyzer's custom patterns through the API
+    pub fn with_behavior_patterns(mut self, patterns: Arc<BehaviorPatterns>) -> Self {
+        self.behavior_patterns = Some(patterns);
+        self
+    }
+
+    pub fn behavior_patterns(&self) -> Option<&Arc<BehaviorPatterns>> {
+        self.behavior_patterns.as_ref()
+    }
+
+    pub fn siem(&self) -> Option<&Arc<SiemIntegration>> {
+        self.siem_integration.as_ref()
+    }
+
+    /// Analyze a request for threats and optionally take defensive actions.
+    /// With `context.evaluate_only` the request is scored the same way, but
+    /// nothing is recorded, sent to SIEM or carried out.
     pub async fn analyze_request(&self, context: &RequestContext) -> Result<ThreatAnalysisResult> {
         let start_time = std::time::Instant::now();
         let config = self.config.read().await;
+        let overall_deadline =
+            tokio::time::Instant::from_std(start_time) + tokio::time::Duration::from_millis(config.max_total_analysis_time_ms);
 
         if !config.enabled {
             return Ok(ThreatAnalysisResult {
                 correlation_id: context.correlation_id,
                 overall_score: ThreatScore::new("disabled".to_string(), 0.0, 1.0),
                 individual_scores: Vec::new(),
+                threat_detected: false,
+                partial: false,
+                failed_analyzers: Vec::new(),
                 actions_taken: Vec::new(),
                 analysis_duration_ms: 0,
                 timestamp: chrono::Utc::now(),
             });
         }
 
-        // Run all analyzers concurrently with timeout
-        let analysis_timeout = tokio::time::Duration::from_millis(config.max_analysis_time_ms);
-        let mut individual_scores = Vec::new();
-
-        for analyzer in &self.analyzers {
-            if !analyzer.is_enabled() {
-                continue;
-            }
+        // Run the enabled analyzers concurrently under one deadline, so the
+        // slowest analyzer sets the latency. Any still running when it passes
+        // are dropped and the rest are scored without them. Evaluations don't
+        // count towards the analyzers' statistics.
+        let timeout_ms = config.max_analysis_time_ms.min(config.max_total_analysis_time_ms);
+        let deadline = tokio::time::Instant::now() + tokio::time::Duration::from_millis(config.max_analysis_time_ms);
+        let deadline = deadline.min(overall_deadline);
+        let runs = self
+            .analyzers
+            .iter()
+            .zip(&self.analyzer_counters)
+            .filter(|(analyzer, _)| analyzer.is_enabled())
+            .map(|(analyzer, counters)| {
+                let counters = (!context.evaluate_only).then_some(counters);
+                if let Some(counters) = counters {
+                    counters.runs.fetch_add(1, Ordering::Relaxed);
+                }
+                let analyzer_span = tracing::info_span!(
+                    "threat.analyze",
+                    analyzer_id = analyzer.analyzer_id(),
+                    correlation_id = %context.correlation_id,
+                );
+                async move {
+                    let result = tokio::time::timeout_at(deadline, analyzer.analyze(context))
+                        .instrument(analyzer_span)
+                        .await;
+                    (analyzer, counters, result)
+                }
+            });
 
-            match tokio::time::timeout(analysis_timeout, analyzer.analyze(context)).await {
+        let mut individual_scores = Vec::new();
+        let mut partial = false;
+        let mut failed_analyzers = Vec::new();
+        for (analyzer, counters, result) in futures_util::future::join_all(runs).await {
+            match result {
                 Ok(Ok(score)) => {
                     info!(
                         analyzer = analyzer.analyzer_id(),
@@ -93,19 +320,44 @@ impl ThreatDetector {
                         confidence = score.confidence,
                         "Threat analysis completed"
                     );
+                    if let Some(counters) = counters {
+                        crate::metrics::THREAT_SCORES
+                            .with_label_values(&[analyzer.analyzer_id()])
+                            .observe(score.score);
+                        crate::metrics::THREAT_ANALYZER_RUNS
+                            .with_label_values(&[analyzer.analyzer_id(), "ok"])
+                            .inc();
+                        counters.failing.store(false, Ordering::Relaxed);
+                    }
                     individual_scores.push(score);
                 }
                 Ok(Err(e)) => {
+                    if let Some(counters) = counters {
+                        counters.errors.fetch_add(1, Ordering::Relaxed);
+                        counters.record_failure(e.to_string());
+                        crate::metrics::THREAT_ANALYZER_RUNS
+                            .with_label_values(&[analyzer.analyzer_id(), "error"])
+                            .inc();
+                    }
                     error!(
                         analyzer = analyzer.analyzer_id(),
                         error = %e,
                         "Threat analyzer failed"
                     );
+                    failed_analyzers.push(analyzer.analyzer_id().to_string());
                 }
                 Err(_) => {
+                    partial = true;
+                    if let Some(counters) = counters {
+                        counters.timeouts.fetch_add(1, Ordering::Relaxed);
+                        counters.record_failure(format!("Timed out after {}ms", timeout_ms));
+                        crate::metrics::THREAT_ANALYZER_RUNS
+                            .with_label_values(&[analyzer.analyzer_id(), "timeout"])
+                            .inc();
+                    }
                     warn!(
                         analyzer = analyzer.analyzer_id(),
-                        timeout_ms = config.max_analysis_time_ms,
+                        timeout_ms,
                         "Threat analyzer timed out"
                     );
                 }
@@ -127,47 +379,118 @@ impl ThreatDetector {
         let overall_score = ThreatScore::combine_scores(individual_scores.clone(), Some(weights));
 
         // Determine if action should be taken
+        let threat_detected = overall_score.score >= config.threat_threshold
+            && overall_score.confidence >= config.confidence_threshold;
         let mut actions_taken = Vec::new();
-        if config.auto_response_enabled
-            && overall_score.score >= config.threat_threshold
-            && overall_score.confidence >= config.confidence_threshold
-        {
-            // Take defensive actions
-            actions_taken = self
-                .response_engine
-                .respond_to_threat(context, &overall_score)
-                .await?;
-
-            info!(
-                correlation_id = %context.correlation_id,
-                threat_score = overall_score.score,
-                actions_count = actions_taken.len(),
-                "Defensive actions taken"
-            );
-        }
+        if config.auto_response_enabled && threat_detected {
+            // Take defensive actions; if they can't finish within the
+            // budget the request is still rejected
+            let response = self.response_engine.respond_to_threat(context, &overall_score);
+            actions_taken = match tokio::time::timeout_at(overall_deadline, response).await {
+                Ok(actions) => actions?,
+                Err(_) => {
+                    warn!(
+                        correlation_id = %context.correlation_id,
+                        budget_ms = config.max_total_analysis_time_ms,
+                        "Defensive actions cut off by the analysis budget"
+                    );
+                    partial = true;
+                    vec![DefensiveAction::RejectRequest]
+                }
+            };
 
-        // Send to SIEM if configured
-        if let Some(siem) = &self.siem_integration {
-            if let Err(e) = siem
-                .send_security_event(context, &overall_score, &actions_taken)
-                .await
-            {
-                error!(error = %e, "Failed to send event to SIEM");
+            if !context.evaluate_only {
+                info!(
+                    correlation_id = %context.correlation_id,
+                    threat_score = overall_score.score,
+                    actions_count = actions_taken.len(),
+                    "Defensive actions taken"
+                );
             }
         }
 
-        let analysis_duration = start_time.elapsed().as_millis() as u64;
+        let analysis_duration = start_time.elapsed();
+        if !context.evaluate_only {
+            // Send to SIEM if configured
+            if let Some(siem) = &self.siem_integration {
+                let send = siem.send_security_event(context, &overall_score, &actions_taken);
+                match tokio::time::timeout_at(overall_deadline, send).await {
+                    Ok(Ok(())) => {}
+                    Ok(Err(e)) => error!(error = %e, "Failed to send event to SIEM"),
+                    Err(_) => warn!(
+                        correlation_id = %context.correlation_id,
+                        "SIEM event dropped, the analysis budget ran out"
+                    ),
+                }
+            }
+            self.record_analysis(analysis_duration, threat_detected, &actions_taken);
+        }
 
         Ok(ThreatAnalysisResult {
             correlation_id: context.correlation_id,
             overall_score,
             individual_scores,
+            threat_detected,
+            partial,
+            failed_analyzers,
             actions_taken,
-            analysis_duration_ms: analysis_duration,
+            analysis_duration_ms: analysis_duration.as_millis() as u64,
             timestamp: chrono::Utc::now(),
         })
     }
 
+    pub async fn mode(&self) -> ThreatAnalysisMode {
+        self.config.read().await.mode
+    }
+
+    pub async fn failure_mode(&self) -> SecurityFailureMode {
+        self.config.read().await.failure_mode
+    }
+
+    /// Start the background worker that `queue_analysis` hands requests to,
+    /// holding up to `queue_size` of them. It stops when the detector is
+    /// dropped. Does nothing if it's already running.
+    pub fn start_async_analysis(self: &Arc<Self>, queue_size: usize) {
+        let (sender, receiver) = mpsc::channel(queue_size);
+        if self.async_queue.set(sender).is_ok() {
+            tokio::spawn(analyze_queued(Arc::downgrade(self), receiver));
+        }
+    }
+
+    /// Analyze `context` in the background, carrying out defensive actions
+    /// there. False if the worker isn't running; if the queue is full the
+    /// request goes unanalyzed.
+    pub fn queue_analysis(&self, context: RequestContext) -> bool {
+        let Some(queue) = self.async_queue.get() else {
+            return false;
+        };
+        if let Err(mpsc::error::TrySendError::Full(context)) = queue.try_send(context) {
+            crate::metrics::THREAT_ANALYSES_DROPPED.inc();
+            warn!(correlation_id = %context.correlation_id, "Background threat analysis queue full, request not analyzed");
+        }
+        true
+    }
+
+    fn record_analysis(&self, duration: std::time::Duration, threat_detected: bool, actions: &[DefensiveAction]) {
+        self.counters.analyses.fetch_add(1, Ordering::Relaxed);
+        self.counters
+            .analysis_time_us
+            .fetch_add(duration.as_micros() as u64, Ordering::Relaxed);
+        crate::metrics::THREAT_ANALYSES.inc();
+        crate::metrics::THREAT_ANALYSIS_DURATION.observe(duration.as_secs_f64());
+
+        if threat_detected {
+            self.counters.threats_detected.fetch_add(1, Ordering::Relaxed);
+            crate::metrics::THREATS_DETECTED.inc();
+        }
+        self.counters
+            .actions_taken
+            .fetch_add(actions.len() as u64, Ordering::Relaxed);
+        for action in actions {
+            crate::metrics::THREAT_ACTIONS.with_label_values(&[action.as_str()]).inc();
+        }
+    }
+
     /// Add a new threat analyzer
     pub async fn add_analyzer(&mut self, analyzer: Box<dyn ThreatAnalyzer>) {
         info!(
@@ -176,6 +499,7 @@ impl ThreatDetector {
             "Adding threat analyzer"
         );
         self.analyzers.push(analyzer);
+        self.analyzer_counters.push(AnalyzerCounters::default());
     }
 
     /// Update detector configuration
@@ -191,10 +515,22 @@ impl ThreatDetector {
         self.config.read().await.clone()
     }
 
-    /// Get statistics about threat detection
+    /// Get statistics about threat detection since this instance started
     pub async fn get_statistics(&self) -> ThreatDetectorStatistics {
-        // This would typically be stored in a metrics system
-        // For now, return basic info
+        let total_analyses = self.counters.analyses.load(Ordering::Relaxed);
+        let analysis_time_us = self.counters.analysis_time_us.load(Ordering::Relaxed);
+        let analyzers: Vec<AnalyzerStatistics> = self
+            .analyzers
+            .iter()
+            .zip(&self.analyzer_counters)
+            .map(|(analyzer, counters)| AnalyzerStatistics {
+                analyzer_id: analyzer.analyzer_id().to_string(),
+                runs: counters.runs.load(Ordering::Relaxed),
+                errors: counters.errors.load(Ordering::Relaxed),
+                timeouts: counters.timeouts.load(Ordering::Relaxed),
+            })
+            .collect();
+
         ThreatDetectorStatistics {
             analyzers_count: self.analyzers.len(),
             enabled_analyzers: self
@@ -202,29 +538,50 @@ impl ThreatDetector {
                 .iter()
                 .filter(|a| a.is_enabled())
                 .count(),
-            total_analyses: 0, // Would be tracked in real implementation
-            threats_detected: 0,
-            actions_taken: 0,
-            average_analysis_time_ms: 0.0,
+            total_analyses,
+            threats_detected: self.counters.threats_detected.load(Ordering::Relaxed),
+            actions_taken: self.counters.actions_taken.load(Ordering::Relaxed),
+            average_analysis_time_ms: if total_analyses == 0 {
+                0.0
+            } else {
+                analysis_time_us as f64 / total_analyses as f64 / 1000.0
+            },
+            analyzer_errors: analyzers.iter().map(|a| a.errors + a.timeouts).sum(),
+            analyzers,
         }
     }
 
-    /// Perform a health check on all analyzers
+    /// Run every analyzer's health check concurrently. An analyzer whose
+    /// own check passes but whose last run failed is reported as degraded.
     pub async fn health_check(&self) -> Result<Vec<AnalyzerHealthStatus>> {
-        let mut health_statuses = Vec::new();
+        let checks = self.analyzers.iter().zip(&self.analyzer_counters).map(|(analyzer, counters)| async move {
+            let health = match tokio::time::timeout(HEALTH_CHECK_TIMEOUT, analyzer.health_check()).await {
+                Ok(Ok(health)) => health,
+                Ok(Err(e)) => AnalyzerHealth::new(analyzer.analyzer_id().to_string())
+                    .unhealthy(format!("Health check failed: {:#}", e)),
+                Err(_) => AnalyzerHealth::new(analyzer.analyzer_id().to_string())
+                    .unhealthy("Health check timed out".to_string()),
+            };
+            let last_error = counters.last_error.lock().unwrap_or_else(|e| e.into_inner()).clone();
+            let health = match (&last_error, counters.failing.load(Ordering::Relaxed)) {
+                (Some(error), true) if health.status == HealthStatus::Healthy => {
+                    health.degraded(format!("Last run failed: {}", error))
+                }
+                _ => health,
+            };
 
-        for analyzer in &self.analyzers {
-            let status = AnalyzerHealthStatus {
+            AnalyzerHealthStatus {
                 analyzer_id: analyzer.analyzer_id().to_string(),
                 name: analyzer.name().to_string(),
                 enabled: analyzer.is_enabled(),
-                healthy: true, // Would perform actual health check
-                last_error: None,
-            };
-            health_statuses.push(status);
-        }
+                healthy: health.status == HealthStatus::Healthy,
+                status: health.status,
+                message: health.message,
+                last_error,
+            }
+        });
 
-        Ok(health_statuses)
+        Ok(futures_util::future::join_all(checks).await)
     }
 
     /// Enable or disable the threat detector
@@ -257,6 +614,17 @@ pub struct ThreatDetectorStatistics {
     pub threats_detected: u64,
     pub actions_taken: u64,
     pub average_analysis_time_ms: f64,
+    /// Analyzer runs that failed or timed out
+    pub analyzer_errors: u64,
+    pub analyzers: Vec<AnalyzerStatistics>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AnalyzerStatistics {
+    pub analyzer_id: String,
+    pub runs: u64,
+    pub errors: u64,
+    pub timeouts: u64,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -265,9 +633,43 @@ pub struct AnalyzerHealthStatus {
     pub name: String,
     pub enabled: bool,
     pub healthy: bool,
+    pub status: HealthStatus,
+    /// Why the analyzer isn't healthy
+    pub message: Option<String>,
+    /// Error from the analyzer's most recent failed run, even if it has recovered
     pub last_error: Option<String>,
 }
 
+/// The worst status among enabled analyzers; disabled ones don't count
+pub fn overall_health(statuses: &[AnalyzerHealthStatus]) -> HealthStatus {
+    statuses
+        .iter()
+        .filter(|status| status.enabled)
+        .map(|status| status.status)
+        .max()
+        .unwrap_or(HealthStatus::Healthy)
+}
+
+/// Background analysis for `async` mode. The worker holds the detector
+/// weakly, so the queue closes and it exits once the detector is dropped.
+async fn analyze_queued(detector: Weak<ThreatDetector>, mut queue: mpsc::Receiver<RequestContext>) {
+    use futures_util::StreamExt;
+
+    futures_util::stream::poll_fn(|cx| queue.poll_recv(cx))
+        .for_each_concurrent(ASYNC_ANALYSIS_CONCURRENCY, |context| {
+            let detector = detector.clone();
+            async move {
+                let Some(detector) = detector.upgrade() else {
+                    return;
+                };
+                if let Err(e) = detector.analyze_request(&context).await {
+                    error!(correlation_id = %context.correlation_id, error = %e, "Background threat analysis failed");
+                }
+            }
+        })
+        .await;
+}
+
 impl Default for ThreatDetectorConfig {
     fn default() -> Self {
         Self {
@@ -277,6 +679,9 @@ impl Default for ThreatDetectorConfig {
             auto_response_enabled: true,
             analyzer_weights: std::collections::HashMap::new(),
             max_analysis_time_ms: 5000,
+            max_total_analysis_time_ms: 5000,
+            mode: ThreatAnalysisMode::Inline,
+            failure_mode: SecurityFailureMode::FailOpen,
         }
     }
 }
@@ -377,6 +782,234 @@ mod tests {
         assert!(result.analysis_duration_ms > 0);
     }
 
+    #[tokio::test]
+    async fn test_analyzer_weights() {
+        use crate::security::response_engine::ResponseEngine;
+
+        let analyzers: Vec<Box<dyn ThreatAnalyzer>> = vec![
+            Box::new(MockThreatAnalyzer::new("noisy".to_string(), 0.9, 0.8)),
+            Box::new(MockThreatAnalyzer::new("reliable".to_string(), 0.3, 0.8)),
+        ];
+        let response_engine = Arc::new(ResponseEngine::new(Default::default()));
+        let detector = ThreatDetector::new(analyzers, response_engine, None)
+            .with_analyzer_weights([("reliable".to_string(), 2.0)].into());
+        let context = RequestContext::new("192.168.1.1".to_string(), "/api/test".to_string(), "GET".to_string());
+
+        let result = detector.analyze_request(&context).await.unwrap();
+        assert!((result.overall_score.score - 0.5).abs() < 1e-9); // (0.9 + 2 * 0.3) / 3
+
+        // Weight 0: still analyzed, but no say in the verdict
+        let mut config = detector.get_config().await;
+        config.analyzer_weights.insert("noisy".to_string(), 0.0);
+        detector.update_config(config).await.unwrap();
+
+        let result = detector.analyze_request(&context).await.unwrap();
+        assert_eq!(result.individual_scores.len(), 2);
+        assert!((result.overall_score.score - 0.3).abs() < 1e-9);
+    }
+
+    struct FailingAnalyzer;
+
+    #[async_trait]
+    impl ThreatAnalyzer for FailingAnalyzer {
+        async fn analyze(&self, _context: &RequestContext) -> anyhow::Result<ThreatScore> {
+            anyhow::bail!("lookup failed")
+        }
+
+        fn analyzer_id(&self) -> &str {
+            "failing"
+        }
+
+        fn name(&self) -> &str {
+            "failing"
+        }
+
+        fn is_enabled(&self) -> bool {
+            true
+        }
+
+        async fn update_config(&mut self, _config: serde_json::Value) -> anyhow::Result<()> {
+            Ok(())
+        }
+    }
+
+    struct SlowAnalyzer {
+        id: &'static str,
+        delay: std::time::Duration,
+    }
+
+    #[async_trait]
+    impl ThreatAnalyzer for SlowAnalyzer {
+        async fn analyze(&self, _context: &RequestContext) -> anyhow::Result<ThreatScore> {
+            tokio::time::sleep(self.delay).await;
+            Ok(ThreatScore::new(self.id.to_string(), 0.2, 0.9))
+        }
+
+        fn analyzer_id(&self) -> &str {
+            self.id
+        }
+
+        fn name(&self) -> &str {
+            self.id
+        }
+
+        fn is_enabled(&self) -> bool {
+            true
+        }
+
+        async fn update_config(&mut self, _config: serde_json::Value) -> anyhow::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_analyzers_run_concurrently() {
+        use crate::security::response_engine::ResponseEngine;
+        use std::time::{Duration, Instant};
+
+        let analyzers: Vec<Box<dyn ThreatAnalyzer>> = vec![
+            Box::new(SlowAnalyzer { id: "slow", delay: Duration::from_millis(200) }),
+            Box::new(SlowAnalyzer { id: "slower", delay: Duration::from_millis(300) }),
+            Box::new(FailingAnalyzer),
+        ];
+        let response_engine = Arc::new(ResponseEngine::new(Default::default()));
+        let detector = ThreatDetector::new(analyzers, response_engine, None);
+        let context = RequestContext::new("192.168.1.1".to_string(), "/api/test".to_string(), "GET".to_string());
+
+        let started = Instant::now();
+        let result = detector.analyze_request(&context).await.unwrap();
+        let elapsed = started.elapsed();
+        assert_eq!(result.individual_scores.len(), 2);
+        // Close to the slower analyzer, well short of the 500ms sum
+        assert!(elapsed >= Duration::from_millis(300));
+        assert!(elapsed < Duration::from_millis(450), "took {:?}", elapsed);
+
+        // Whatever finished before the deadline is kept
+        let mut config = detector.get_config().await;
+        config.max_analysis_time_ms = 250;
+        detector.update_config(config).await.unwrap();
+
+        let result = detector.analyze_request(&context).await.unwrap();
+        assert_eq!(result.individual_scores.len(), 1);
+        assert_eq!(result.individual_scores[0].analyzer_id, "slow");
+        assert_eq!(detector.get_statistics().await.analyzers[1].timeouts, 1);
+    }
+
+    #[tokio::test]
+    async fn test_overall_budget_returns_partial_result() {
+        use crate::security::response_engine::ResponseEngine;
+        use std::time::{Duration, Instant};
+
+        let analyzers: Vec<Box<dyn ThreatAnalyzer>> = vec![
+            Box::new(SlowAnalyzer { id: "quick", delay: Duration::from_millis(20) }),
+            Box::new(SlowAnalyzer { id: "slow", delay: Duration::from_millis(400) }),
+            Box::new(SlowAnalyzer { id: "slower", delay: Duration::from_millis(600) }),
+        ];
+        let response_engine = Arc::new(ResponseEngine::new(Default::default()));
+        let detector = ThreatDetector::new(analyzers, response_engine, None).with_max_total_analysis_time_ms(100);
+        let context = RequestContext::new("192.168.1.1".to_string(), "/api/test".to_string(), "GET".to_string());
+
+        // The per-analyzer limit of 5s would let all three finish
+        let started = Instant::now();
+        let result = detector.analyze_request(&context).await.unwrap();
+        let elapsed = started.elapsed();
+        assert!(result.partial);
+        assert_eq!(result.individual_scores.len(), 1);
+        assert_eq!(result.individual_scores[0].analyzer_id, "quick");
+        assert!(elapsed < Duration::from_millis(200), "took {:?}", elapsed);
+
+        let mut config = detector.get_config().await;
+        config.max_total_analysis_time_ms = 5000;
+        detector.update_config(config).await.unwrap();
+        let result = detector.analyze_request(&context).await.unwrap();
+        assert!(!result.partial);
+        assert_eq!(result.individual_scores.len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_failing_analyzer_reported_degraded() {
+        use crate::security::response_engine::ResponseEngine;
+
+        let analyzers: Vec<Box<dyn ThreatAnalyzer>> = vec![
+            Box::new(MockThreatAnalyzer::new("analyzer1".to_string(), 0.1, 0.9)),
+            Box::new(FailingAnalyzer),
+        ];
+        let response_engine = Arc::new(ResponseEngine::new(Default::default()));
+        let detector = ThreatDetector::new(analyzers, response_engine, None);
+
+        let health = detector.health_check().await.unwrap();
+        assert!(health.iter().all(|status| status.status == HealthStatus::Healthy));
+
+        let context = RequestContext::new("192.168.1.1".to_string(), "/api/test".to_string(), "GET".to_string());
+        detector.analyze_request(&context).await.unwrap();
+
+        let health = detector.health_check().await.unwrap();
+        assert_eq!(health[0].status, HealthStatus::Healthy);
+        assert_eq!(health[1].status, HealthStatus::Degraded);
+        assert!(!health[1].healthy);
+        assert_eq!(health[1].last_error.as_deref(), Some("lookup failed"));
+        assert_eq!(overall_health(&health), HealthStatus::Degraded);
+    }
+
+    #[tokio::test]
+    async fn test_statistics_track_analyses() {
+        use crate::security::response_engine::ResponseEngine;
+
+        let analyzers: Vec<Box<dyn ThreatAnalyzer>> = vec![
+            Box::new(MockThreatAnalyzer::new("analyzer1".to_string(), 0.9, 0.9)),
+            Box::new(FailingAnalyzer),
+        ];
+        let response_engine = Arc::new(ResponseEngine::new(Default::default()));
+        let detector = ThreatDetector::new(analyzers, response_engine, None);
+        let context = RequestContext::new("192.168.1.1".to_string(), "/api/test".to_string(), "GET".to_string());
+
+        let statistics = detector.get_statistics().await;
+        assert_eq!(statistics.total_analyses, 0);
+        assert_eq!(statistics.average_analysis_time_ms, 0.0);
+
+        let rejections = crate::metrics::THREAT_ACTIONS.with_label_values(&["reject_request"]).get();
+        for _ in 0..2 {
+            detector.analyze_request(&context).await.unwrap();
+        }
+
+        let statistics = detector.get_statistics().await;
+        assert_eq!(statistics.total_analyses, 2);
+        assert_eq!(statistics.threats_detected, 2);
+        assert_eq!(statistics.actions_taken, 2);
+        assert!(statistics.average_analysis_time_ms > 0.0);
+        assert_eq!(statistics.analyzer_errors, 2);
+        assert_eq!(statistics.analyzers[0].analyzer_id, "analyzer1");
+        assert_eq!((statistics.analyzers[0].runs, statistics.analyzers[0].errors), (2, 0));
+        assert_eq!((statistics.analyzers[1].runs, statistics.analyzers[1].errors), (2, 2));
+        // Prometheus is updated from the same place
+        assert!(crate::metrics::THREAT_ACTIONS.with_label_values(&["reject_request"]).get() >= rejections + 2);
+    }
+
+    #[tokio::test]
+    async fn test_evaluation_is_not_counted() {
+        use crate::security::response_engine::ResponseEngine;
+
+        let analyzers: Vec<Box<dyn ThreatAnalyzer>> = vec![
+            Box::new(MockThreatAnalyzer::new("analyzer1".to_string(), 0.9, 0.9)),
+            Box::new(FailingAnalyzer),
+        ];
+        let response_engine = Arc::new(ResponseEngine::new(Default::default()));
+        let detector = ThreatDetector::new(analyzers, response_engine, None);
+        let context = RequestContext::new("192.168.1.1".to_string(), "/api/test".to_string(), "GET".to_string())
+            .evaluate_only();
+
+        let result = detector.analyze_request(&context).await.unwrap();
+        assert!(result.threat_detected);
+        assert_eq!(result.actions_taken, vec![DefensiveAction::RejectRequest]);
+        assert_eq!(result.individual_scores.len(), 1);
+
+        let statistics = detector.get_statistics().await;
+        assert_eq!(statistics.total_analyses, 0);
+        assert_eq!(statistics.actions_taken, 0);
+        assert_eq!(statistics.analyzer_errors, 0);
+        assert_eq!(statistics.analyzers[1].runs, 0);
+    }
+
     #[tokio::test]
     async fn test_threat_detector_disabled() {
         use crate::security::response_engine::ResponseEngine;
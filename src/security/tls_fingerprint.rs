@@ -0,0 +1,329 @@
+//! JA3 TLS client fingerprints.
+//!
+//! Scanners that rotate IPs and user agents usually keep the same TLS stack,
+//! so the JA3 hash of their ClientHello stays put. ratewatch sits behind the
+//! proxy that terminates TLS, which reports the hash in a header (see
+//! `TlsFingerprintConfig::header`); the proxy must overwrite any value the
+//! client sent; ratewatch doesn't terminate TLS in-process, so without that
+//! header there's nothing to score and the analyzer stays neutral.
+//! `TlsFingerprintAnalyzer` flags hashes on a known-bad list, hashes shared by
+//! more distinct IPs in a window than one client would use, and clients (by
+//! API key, else IP) that switch between more fingerprints in a window than a
+//! real browser would. Both are counted in Redis so every instance sees the
+//! same spread.
+
+use crate::config::TlsFingerprintConfig;
+use crate::redis_backend::RedisConnector;
+use crate::security::threat_analyzer::{AnalyzerHealth, RequestContext, ThreatAnalyzer, ThreatScore};
+use anyhow::Result;
+use async_trait::async_trait;
+use serde::Deserialize;
+use std::collections::HashSet;
+use tracing::info;
+
+/// A JA3 hash in canonical form (32 lowercase hex digits), or `None` if
+/// `value` isn't one
+pub fn normalize_ja3(value: &str) -> Option<String> {
+    let value = value.trim();
+    (value.len() == 32 && value.chars().all(|c| c.is_ascii_hexdigit())).then(|| value.to_ascii_lowercase())
+}
+
+fn ips_key(fingerprint: &str) -> String {
+    format!("ja3:ips:{}", fingerprint)
+}
+
+fn fingerprints_key(client: &str) -> String {
+    format!("ja3:fingerprints:{}", client)
+}
+
+pub struct TlsFingerprintAnalyzer {
+    redis_client: RedisConnector,
+    known_bad: HashSet<String>,
+    distinct_ip_threshold: u64,
+    distinct_fingerprint_threshold: u64,
+    window_seconds: u64,
+    known_bad_score: f64,
+    spread_score: f64,
+    switch_score: f64,
+    enabled: bool,
+}
+
+/// Settings accepted by `update_config`
+#[derive(Debug, Deserialize)]
+struct TlsFingerprintUpdate {
+    known_bad: Option<Vec<String>>,
+    distinct_ip_threshold: Option<u64>,
+    distinct_fingerprint_threshold: Option<u64>,
+    window_seconds: Option<u64>,
+    enabled: Option<bool>,
+}
+
+impl TlsFingerprintAnalyzer {
+    pub fn new(redis_client: RedisConnector, config: &TlsFingerprintConfig) -> Self {
+        Self {
+            redis_client,
+            known_bad: config.known_bad.iter().filter_map(|hash| normalize_ja3(hash)).collect(),
+            distinct_ip_threshold: config.distinct_ip_threshold,
+            distinct_fingerprint_threshold: config.distinct_fingerprint_threshold,
+            window_seconds: config.window_seconds,
+            known_bad_score: config.known_bad_score,
+            spread_score: config.spread_score,
+            switch_score: config.switch_score,
+            enabled: true,
+        }
+    }
+
+    /// The analyzer for `[security.threat_detection.tls_fingerprint]`, or `None` if it's disabled
+    pub fn from_config(redis_client: RedisConnector, config: &TlsFingerprintConfig) -> Option<Self> {
+        if !config.enabled {
+            return None;
+        }
+        info!(
+            header = %config.header,
+            known_bad = config.known_bad.len(),
+            "TLS fingerprint analysis enabled"
+        );
+        Some(Self::new(redis_client, config))
+    }
+
+    /// Record `member` in the set at `key` and count the distinct members
+    /// seen in the window
+    async fn record_distinct(&self, key: &str, member: &str) -> Result<u64> {
+        let mut conn = self.redis_client.get_async_connection().await?;
+        let now_ms = chrono::Utc::now().timestamp_millis();
+        let window_start = now_ms - (self.window_seconds * 1000) as i64;
+
+        let (count,): (u64,) = redis::pipe()
+            .atomic()
+            .cmd("ZADD").arg(key).arg(now_ms).arg(member).ignore()
+            .cmd("ZREMRANGEBYSCORE").arg(key).arg("-inf").arg(format!("({}", window_start)).ignore()
+            .cmd("ZCARD").arg(key)
+            .cmd("EXPIRE").arg(key).arg(self.window_seconds).ignore()
+            .query_async(&mut conn)
+            .await?;
+        Ok(count)
+    }
+
+    /// The distinct members `record_distinct` would count, without recording `member`
+    async fn peek_distinct(&self, key: &str, member: &str) -> Result<u64> {
+        let mut conn = self.redis_client.get_async_connection().await?;
+        let window_start = chrono::Utc::now().timestamp_millis() - (self.window_seconds * 1000) as i64;
+
+        let (others, seen_at): (u64, Option<f64>) = redis::pipe()
+            .cmd("ZCOUNT").arg(key).arg(window_start).arg("+inf")
+            .cmd("ZSCORE").arg(key).arg(member)
+            .query_async(&mut conn)
+            .await?;
+        // Already counted if it was seen in the window
+        Ok(match seen_at {
+            Some(seen_at) if seen_at >= window_start as f64 => others,
+            _ => others + 1,
+        })
+    }
+
+    /// Count distinct members, recording `member` unless only evaluating
+    async fn distinct(&self, context: &RequestContext, key: &str, member: &str) -> Result<u64> {
+        if context.evaluate_only {
+            self.peek_distinct(key, member).await
+        } else {
+            self.record_distinct(key, member).await
+        }
+    }
+}
+
+#[async_trait]
+impl ThreatAnalyzer for TlsFingerprintAnalyzer {
+    async fn analyze(&self, context: &RequestContext) -> Result<ThreatScore> {
+        let Some(fingerprint) = &context.tls_fingerprint else {
+            return Ok(ThreatScore::new("tls_fingerprint".to_string(), 0.0, 0.0)
+                .with_reason("TLS fingerprint: not reported".to_string()));
+        };
+
+        if self.known_bad.contains(fingerprint) {
+            return Ok(ThreatScore::new("tls_fingerprint".to_string(), self.known_bad_score, 0.9)
+                .with_reason(format!("TLS fingerprint {} is on the known-bad list", fingerprint))
+                .with_metadata("ja3".to_string(), serde_json::json!(fingerprint))
+                .with_metadata("known_bad".to_string(), serde_json::json!(true)));
+        }
+
+        let distinct_ips = self.distinct(context, &ips_key(fingerprint), &context.ip_key()).await?;
+        // An API key follows the client across IPs
+        let client = match &context.api_key_id {
+            Some(api_key_id) => format!("key:{}", api_key_id),
+            None => format!("ip:{}", context.ip_key()),
+        };
+        let distinct_fingerprints = self.distinct(context, &fingerprints_key(&client), fingerprint).await?;
+
+        let mut findings = Vec::new();
+        if distinct_ips >= self.distinct_ip_threshold {
+            findings.push((
+                self.spread_score,
+                format!("TLS fingerprint {} seen from {} IPs in {}s", fingerprint, distinct_ips, self.window_seconds),
+            ));
+        }
+        if distinct_fingerprints >= self.distinct_fingerprint_threshold {
+            findings.push((
+                self.switch_score,
+                format!("Client used {} TLS fingerprints in {}s", distinct_fingerprints, self.window_seconds),
+            ));
+        }
+
+        let score = if findings.is_empty() {
+            ThreatScore::new("tls_fingerprint".to_string(), 0.0, 0.6)
+                .with_reason("TLS fingerprint: no anomaly".to_string())
+        } else {
+            let highest = findings.iter().map(|(score, _)| *score).fold(0.0, f64::max);
+            findings
+                .into_iter()
+                .fold(ThreatScore::new("tls_fingerprint".to_string(), highest, 0.7), |score, (_, reason)| {
+                    score.with_reason(reason)
+                })
+        };
+
+        Ok(score
+            .with_metadata("ja3".to_string(), serde_json::json!(fingerprint))
+            .with_metadata("known_bad".to_string(), serde_json::json!(false))
+            .with_metadata("distinct_ips".to_string(), serde_json::json!(distinct_ips))
+            .with_metadata("distinct_fingerprints".to_string(), serde_json::json!(distinct_fingerprints)))
+    }
+
+    fn analyzer_id(&self) -> &str {
+        "tls_fingerprint"
+    }
+
+    fn name(&self) -> &str {
+        "TLS Fingerprint Analyzer"
+    }
+
+    fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    async fn update_config(&mut self, config: serde_json::Value) -> Result<()> {
+        let update: TlsFingerprintUpdate = serde_json::from_value(config)?;
+        if let Some(known_bad) = update.known_bad {
+            self.known_bad = known_bad.iter().filter_map(|hash| normalize_ja3(hash)).collect();
+        }
+        if let Some(threshold) = update.distinct_ip_threshold {
+            self.distinct_ip_threshold = threshold.max(2);
+        }
+        if let Some(threshold) = update.distinct_fingerprint_threshold {
+            self.distinct_fingerprint_threshold = threshold.max(2);
+        }
+        if let Some(window_seconds) = update.window_seconds {
+            self.window_seconds = window_seconds.max(1);
+        }
+        if let Some(enabled) = update.enabled {
+            self.enabled = enabled;
+        }
+        info!("TLS fingerprint analyzer configuration updated");
+        Ok(())
+    }
+
+    async fn health_check(&self) -> Result<AnalyzerHealth> {
+        Ok(AnalyzerHealth::of_redis(self.analyzer_id(), &self.redis_client).await)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCANNER: &str = "E7D705A3286E19EA42F587B344EE6865";
+
+    fn analyzer(config: TlsFingerprintConfig) -> TlsFingerprintAnalyzer {
+        TlsFingerprintAnalyzer::new(RedisConnector::open("redis://127.0.0.1:6379").unwrap(), &config)
+    }
+
+    fn context(ip: &str, fingerprint: &str) -> RequestContext {
+        RequestContext::new(ip.to_string(), "/v1/check".to_string(), "POST".to_string())
+            .with_tls_fingerprint(fingerprint.to_string())
+    }
+
+    #[test]
+    fn test_normalize_ja3() {
+        assert_eq!(normalize_ja3(&format!(" {} ", SCANNER)).unwrap(), SCANNER.to_lowercase());
+        assert!(normalize_ja3("not-a-hash").is_none());
+        assert!(normalize_ja3(&SCANNER[..31]).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_known_bad_fingerprint_is_flagged() {
+        let analyzer = analyzer(TlsFingerprintConfig {
+            enabled: true,
+            known_bad: vec![SCANNER.to_string()],
+            // These IPs see new fingerprints on every run
+            distinct_fingerprint_threshold: u64::MAX,
+            ..TlsFingerprintConfig::default()
+        });
+
+        let score = analyzer.analyze(&context("198.51.100.1", &SCANNER.to_lowercase())).await.unwrap();
+        assert_eq!(score.score, 0.9);
+        assert_eq!(score.metadata["ja3"], serde_json::json!(SCANNER.to_lowercase()));
+
+        let unreported = RequestContext::new("198.51.100.1".to_string(), "/v1/check".to_string(), "POST".to_string());
+        assert_eq!(analyzer.analyze(&unreported).await.unwrap().score, 0.0);
+
+        // A benign fingerprint is clean; it needs Redis to count its spread
+        let benign = uuid::Uuid::new_v4().simple().to_string();
+        if let Ok(score) = analyzer.analyze(&context("198.51.100.1", &benign)).await {
+            assert_eq!(score.score, 0.0);
+            assert_eq!(score.metadata["known_bad"], serde_json::json!(false));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_switching_fingerprints_is_flagged() {
+        let analyzer = analyzer(TlsFingerprintConfig {
+            enabled: true,
+            distinct_fingerprint_threshold: 3,
+            ..TlsFingerprintConfig::default()
+        });
+        let api_key_id = uuid::Uuid::new_v4().to_string();
+        let context = |fingerprint: &str| context("203.0.113.9", fingerprint).with_api_key(api_key_id.clone());
+        let fingerprints: Vec<String> = (0..3).map(|_| uuid::Uuid::new_v4().simple().to_string()).collect();
+
+        let Ok(score) = analyzer.analyze(&context(&fingerprints[0])).await else {
+            // Redis not available
+            return;
+        };
+        assert_eq!(score.score, 0.0);
+        assert_eq!(analyzer.analyze(&context(&fingerprints[1])).await.unwrap().score, 0.0);
+        // Back to one it already used
+        assert_eq!(analyzer.analyze(&context(&fingerprints[0])).await.unwrap().score, 0.0);
+
+        let score = analyzer.analyze(&context(&fingerprints[2])).await.unwrap();
+        assert_eq!(score.score, 0.6);
+        assert_eq!(score.metadata["distinct_fingerprints"], serde_json::json!(3));
+
+        // Another key from the same IP is counted separately
+        let other = self::context("203.0.113.9", &fingerprints[1]).with_api_key(uuid::Uuid::new_v4().to_string());
+        assert_eq!(analyzer.analyze(&other).await.unwrap().score, 0.0);
+    }
+
+    #[tokio::test]
+    async fn test_fingerprint_spread_across_many_ips_is_flagged() {
+        let analyzer = analyzer(TlsFingerprintConfig {
+            enabled: true,
+            distinct_ip_threshold: 3,
+            distinct_fingerprint_threshold: u64::MAX,
+            ..TlsFingerprintConfig::default()
+        });
+        let fingerprint = uuid::Uuid::new_v4().simple().to_string();
+
+        for ip in ["198.51.100.1", "198.51.100.2", "198.51.100.2"] {
+            let score = analyzer.analyze(&context(ip, &fingerprint)).await.unwrap();
+            assert_eq!(score.score, 0.0);
+        }
+
+        // Evaluating counts the IP without recording it
+        let score = analyzer.analyze(&context("198.51.100.3", &fingerprint).evaluate_only()).await.unwrap();
+        assert_eq!(score.score, 0.7);
+        let score = analyzer.analyze(&context("198.51.100.2", &fingerprint).evaluate_only()).await.unwrap();
+        assert_eq!(score.metadata["distinct_ips"], serde_json::json!(2));
+
+        let score = analyzer.analyze(&context("198.51.100.3", &fingerprint)).await.unwrap();
+        assert_eq!(score.score, 0.7);
+        assert_eq!(score.metadata["distinct_ips"], serde_json::json!(3));
+    }
+}
@@ -0,0 +1,355 @@
+use anyhow::Result;
+use axum::{
+    body::Body,
+    extract::Request as AxumRequest,
+    http::{HeaderMap, HeaderValue, Request},
+    middleware::Next,
+    response::Response,
+};
+use tracing::Span;
+use uuid::Uuid;
+use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt, EnvFilter, Layer};
+
+use crate::config::ObservabilityConfig;
+
+/// W3C trace context header used to join upstream traces
+pub const TRACEPARENT_HEADER: &str = "traceparent";
+
+/// Inbound/outbound header carrying the request correlation ID
+pub const CORRELATION_ID_HEADER: &str = "x-correlation-id";
+
+/// Correlation ID for the current request, stored in request extensions
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CorrelationId(pub Uuid);
+
+/// Parsed W3C `traceparent` header (`version-trace_id-parent_id-flags`)
+#[derive(Debug, Clone, PartialEq)]
+pub struct TraceParent {
+    pub trace_id: String,
+    pub parent_id: String,
+    pub sampled: bool,
+}
+
+impl TraceParent {
+    pub fn parse(value: &str) -> Option<Self> {
+        let parts: Vec<&str> = value.trim().split('-').collect();
+        if parts.len() != 4 {
+            return None;
+        }
+
+        let (version, trace_id, parent_id, flags) = (parts[0], parts[1], parts[2], parts[3]);
+        let is_hex = |s: &str, len: usize| s.len() == len && s.chars().all(|c| c.is_ascii_hexdigit());
+
+        if !is_hex(version, 2) || version == "ff" {
+            return None;
+        }
+        if !is_hex(trace_id, 32) || trace_id.chars().all(|c| c == '0') {
+            return None;
+        }
+        if !is_hex(parent_id, 16) || parent_id.chars().all(|c| c == '0') {
+            return None;
+        }
+        if !is_hex(flags, 2) {
+            return None;
+        }
+
+        let flags = u8::from_str_radix(flags, 16).ok()?;
+
+        Some(Self {
+            trace_id: trace_id.to_lowercase(),
+            parent_id: parent_id.to_lowercase(),
+            sampled: flags & 0x01 == 0x01,
+        })
+    }
+}
+
+/// Initialize logging and, when configured, OTLP span export
+pub fn init_tracing(observability: &ObservabilityConfig) -> Result<()> {
+    let logging = &observability.logging;
+    let filter = EnvFilter::new(logging.level.as_str());
+
+    let fmt_layer = if logging.format == "json" && logging.structured {
+        tracing_subscriber::fmt::layer().json().boxed()
+    } else {
+        tracing_subscriber::fmt::layer().boxed()
+    };
+
+    let registry = tracing_subscriber::registry().with(filter).with(fmt_layer);
+
+    #[cfg(feature = "otel")]
+    {
+        let otel_layer = otel::build_layer(&observability.tracing)?;
+        registry.with(otel_layer).init();
+    }
+
+    #[cfg(not(feature = "otel"))]
+    {
+        if observability.tracing.enabled && observability.tracing.otlp_endpoint.is_some() {
+            eprintln!("Tracing endpoint configured but ratewatch was built without the `otel` feature; spans will not be exported");
+        }
+        registry.init();
+    }
+
+    Ok(())
+}
+
+/// Flush pending spans before the process exits
+pub fn shutdown_tracing() {
+    #[cfg(feature = "otel")]
+    opentelemetry::global::shutdown_tracer_provider();
+}
+
+/// Build the root span for an incoming HTTP request, joining any upstream trace
+pub fn make_request_span(request: &Request<Body>) -> Span {
+    let traceparent = request
+        .headers()
+        .get(TRACEPARENT_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(TraceParent::parse);
+
+    let span = tracing::info_span!(
+        "http.request",
+        http.method = %request.method(),
+        http.route = %request.uri().path(),
+        trace_id = tracing::field::Empty,
+        correlation_id = tracing::field::Empty,
+    );
+
+    if let Some(parent) = &traceparent {
+        span.record("trace_id", parent.trace_id.as_str());
+    }
+
+    #[cfg(feature = "otel")]
+    otel::set_remote_parent(&span, request.headers());
+
+    span
+}
+
+/// Attach the request correlation ID to the current request span
+pub fn record_correlation_id(correlation_id: &Uuid) {
+    Span::current().record("correlation_id", tracing::field::display(correlation_id));
+}
+
+/// Resolve the correlation ID from `X-Correlation-ID` (UUID) or the `traceparent`
+/// trace ID, generating a fresh one when neither is present or well-formed
+pub fn resolve_correlation_id(headers: &HeaderMap) -> Uuid {
+    let from_header = headers
+        .get(CORRELATION_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| Uuid::parse_str(value.trim()).ok());
+
+    if let Some(id) = from_header {
+        return id;
+    }
+
+    headers
+        .get(TRACEPARENT_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(TraceParent::parse)
+        .and_then(|parent| Uuid::parse_str(&parent.trace_id).ok())
+        .unwrap_or_else(Uuid::new_v4)
+}
+
+/// Middleware that assigns the request correlation ID and echoes it in the
+/// response, and in the body of an `ApiError`
+pub async fn correlation_id_middleware(mut request: AxumRequest, next: Next) -> Response {
+    let correlation_id = resolve_correlation_id(request.headers());
+
+    record_correlation_id(&correlation_id);
+    request.extensions_mut().insert(CorrelationId(correlation_id));
+
+    let mut response = crate::api_error::with_correlation_id(next.run(request).await, correlation_id);
+
+    if let Ok(value) = HeaderValue::from_str(&correlation_id.to_string()) {
+        response.headers_mut().insert(CORRELATION_ID_HEADER, value);
+    }
+
+    response
+}
+
+#[cfg(feature = "otel")]
+mod otel {
+    use anyhow::Result;
+    use axum::http::HeaderMap;
+    use opentelemetry::{propagation::Extractor, KeyValue};
+    use opentelemetry_otlp::WithExportConfig;
+    use opentelemetry_sdk::{propagation::TraceContextPropagator, trace::Sampler, Resource};
+    use tracing::Span;
+    use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+    use crate::config::TracingConfig;
+
+    struct HeaderExtractor<'a>(&'a HeaderMap);
+
+    impl<'a> Extractor for HeaderExtractor<'a> {
+        fn get(&self, key: &str) -> Option<&str> {
+            self.0.get(key).and_then(|value| value.to_str().ok())
+        }
+
+        fn keys(&self) -> Vec<&str> {
+            self.0.keys().map(|key| key.as_str()).collect()
+        }
+    }
+
+    pub fn build_layer<S>(
+        config: &TracingConfig,
+    ) -> Result<Option<tracing_opentelemetry::OpenTelemetryLayer<S, opentelemetry_sdk::trace::Tracer>>>
+    where
+        S: tracing::Subscriber + for<'span> tracing_subscriber::registry::LookupSpan<'span>,
+    {
+        let endpoint = match (&config.enabled, &config.otlp_endpoint) {
+            (true, Some(endpoint)) => endpoint.clone(),
+            _ => return Ok(None),
+        };
+
+        opentelemetry::global::set_text_map_propagator(TraceContextPropagator::new());
+
+        let sampler = Sampler::ParentBased(Box::new(Sampler::TraceIdRatioBased(
+            config.sampling_rate.clamp(0.0, 1.0),
+        )));
+
+        let tracer = opentelemetry_otlp::new_pipeline()
+            .tracing()
+            .with_exporter(
+                opentelemetry_otlp::new_exporter()
+                    .tonic()
+                    .with_endpoint(endpoint),
+            )
+            .with_trace_config(
+                opentelemetry_sdk::trace::config()
+                    .with_sampler(sampler)
+                    .with_resource(Resource::new(vec![KeyValue::new(
+                        "service.name",
+                        config.service_name.clone(),
+                    )])),
+            )
+            .install_batch(opentelemetry_sdk::runtime::Tokio)?;
+
+        Ok(Some(tracing_opentelemetry::layer().with_tracer(tracer)))
+    }
+
+    pub fn set_remote_parent(span: &Span, headers: &HeaderMap) {
+        let parent_context = opentelemetry::global::get_text_map_propagator(|propagator| {
+            propagator.extract(&HeaderExtractor(headers))
+        });
+        span.set_parent(parent_context);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_traceparent_parsing() {
+        let parent =
+            TraceParent::parse("00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01").unwrap();
+
+        assert_eq!(parent.trace_id, "4bf92f3577b34da6a3ce929d0e0e4736");
+        assert_eq!(parent.parent_id, "00f067aa0ba902b7");
+        assert!(parent.sampled);
+    }
+
+    #[test]
+    fn test_invalid_traceparent_rejected() {
+        assert!(TraceParent::parse("").is_none());
+        assert!(TraceParent::parse("00-4bf92f3577b34da6-00f067aa0ba902b7-01").is_none());
+        assert!(TraceParent::parse("ff-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01").is_none());
+        assert!(TraceParent::parse("00-00000000000000000000000000000000-00f067aa0ba902b7-01").is_none());
+    }
+
+    #[test]
+    fn test_correlation_id_from_header() {
+        let id = Uuid::new_v4();
+        let mut headers = HeaderMap::new();
+        headers.insert(CORRELATION_ID_HEADER, id.to_string().parse().unwrap());
+        headers.insert(
+            TRACEPARENT_HEADER,
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01".parse().unwrap(),
+        );
+
+        assert_eq!(resolve_correlation_id(&headers), id);
+    }
+
+    #[test]
+    fn test_correlation_id_from_traceparent() {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            TRACEPARENT_HEADER,
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01".parse().unwrap(),
+        );
+
+        assert_eq!(
+            resolve_correlation_id(&headers).simple().to_string(),
+            "4bf92f3577b34da6a3ce929d0e0e4736"
+        );
+    }
+
+    #[test]
+    fn test_malformed_correlation_id_replaced() {
+        let mut headers = HeaderMap::new();
+        headers.insert(CORRELATION_ID_HEADER, "not-a-uuid".parse().unwrap());
+
+        let id = resolve_correlation_id(&headers);
+        assert_ne!(id.to_string(), "not-a-uuid");
+        assert_ne!(id, resolve_correlation_id(&headers));
+    }
+
+    #[tokio::test]
+    async fn test_correlation_id_preserved_and_echoed() {
+        use axum::{routing::get, Extension, Router};
+        use tower::ServiceExt;
+
+        let app = Router::new()
+            .route(
+                "/echo",
+                get(|Extension(id): Extension<CorrelationId>| async move { id.0.to_string() }),
+            )
+            .layer(axum::middleware::from_fn(correlation_id_middleware));
+
+        let id = Uuid::new_v4();
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .uri("/echo")
+                    .header(CORRELATION_ID_HEADER, id.to_string())
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(
+            response.headers().get(CORRELATION_ID_HEADER).unwrap(),
+            id.to_string().as_str()
+        );
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(body, id.to_string());
+
+        // Requests without the header still get an ID echoed back
+        let response = app
+            .oneshot(Request::builder().uri("/echo").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let echoed = response.headers().get(CORRELATION_ID_HEADER).unwrap();
+        assert!(Uuid::parse_str(echoed.to_str().unwrap()).is_ok());
+    }
+
+    #[test]
+    fn test_request_span_created() {
+        let request = Request::builder()
+            .uri("/v1/check")
+            .header(
+                TRACEPARENT_HEADER,
+                "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-00",
+            )
+            .body(Body::empty())
+            .unwrap();
+
+        // Without a subscriber installed the span is disabled, but building it must not panic
+        let _span = make_request_span(&request);
+    }
+}
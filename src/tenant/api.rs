@@ -1,7 +1,11 @@
-use super::{TenantManager, TenantOnboardingRequest, TenantConfig, TenantSettings, ResourceQuotas};
+use super::{TenantError, TenantManager, TenantOnboardingRequest, TenantConfig, TenantSettings, ResourceQuotas, TenantImportResult};
 use super::isolation::{IsolationLevel, DataClassification};
+use super::api_keys::TenantApiKey;
+use crate::api_error::ApiError;
+use crate::audit::{audit_event::{ActorInfo, AuditOutcome}, AuditLogger};
+use crate::auth::{ApiKeyIdentity, ApiKeyValidator};
 use axum::{
-    extract::{Path, Query, State},
+    extract::{Extension, Path, Query, State},
     http::StatusCode,
     response::Json,
     routing::{get, post, put, delete},
@@ -14,57 +18,162 @@ use std::sync::Arc;
 use tokio::sync::Mutex;
 
 #[derive(Debug, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 pub struct CreateTenantRequest {
     pub name: String,
     pub slug: String,
     pub admin_email: String,
     pub organization: String,
+    #[cfg_attr(feature = "openapi", schema(value_type = Option<Object>))]
     pub isolation_level: Option<IsolationLevel>,
+    #[cfg_attr(feature = "openapi", schema(value_type = Option<Object>))]
     pub data_classification: Option<DataClassification>,
+    #[cfg_attr(feature = "openapi", schema(value_type = Option<Object>))]
     pub initial_quotas: Option<ResourceQuotas>,
+    #[cfg_attr(feature = "openapi", schema(value_type = Option<Object>))]
     pub initial_settings: Option<TenantSettings>,
     pub features: Option<Vec<String>>,
     pub metadata: Option<HashMap<String, String>>,
+    /// Create as a child of this tenant, inheriting its quotas unless
+    /// `initial_quotas` narrows them
+    pub parent_id: Option<Uuid>,
+}
+
+impl From<CreateTenantRequest> for TenantOnboardingRequest {
+    fn from(request: CreateTenantRequest) -> Self {
+        Self {
+            name: request.name,
+            slug: request.slug,
+            admin_email: request.admin_email,
+            organization: request.organization,
+            isolation_level: request.isolation_level.unwrap_or(IsolationLevel::Shared),
+            data_classification: request.data_classification.unwrap_or(DataClassification::Internal),
+            initial_quotas: request.initial_quotas,
+            initial_settings: request.initial_settings,
+            features: request.features.unwrap_or_default(),
+            metadata: request.metadata.unwrap_or_default(),
+            parent_id: request.parent_id,
+        }
+    }
 }
 
 #[derive(Debug, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 pub struct UpdateTenantRequest {
     pub name: Option<String>,
+    #[cfg_attr(feature = "openapi", schema(value_type = Option<Object>))]
     pub settings: Option<TenantSettings>,
+    #[cfg_attr(feature = "openapi", schema(value_type = Option<Object>))]
     pub quotas: Option<ResourceQuotas>,
     pub features: Option<Vec<String>>,
     pub metadata: Option<HashMap<String, String>>,
 }
 
 #[derive(Debug, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::IntoParams))]
+#[cfg_attr(feature = "openapi", into_params(parameter_in = Query))]
 pub struct ListTenantsQuery {
+    /// Page size; defaults to 100, at most 1000
     pub limit: Option<u32>,
     pub offset: Option<u32>,
+    /// `next_cursor` of the previous page
+    pub cursor: Option<Uuid>,
+    /// Keep only tenants with this status, within the page
     pub status: Option<String>,
 }
 
+/// Tenants returned per page when the query gives no limit
+const DEFAULT_PAGE_SIZE: u32 = 100;
+/// Largest page a query may ask for
+const MAX_PAGE_SIZE: u32 = 1000;
+
+#[derive(Debug, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::IntoParams))]
+#[cfg_attr(feature = "openapi", into_params(parameter_in = Query))]
+pub struct DeleteTenantQuery {
+    /// Also delete the tenant's child tenants, which otherwise block deletion
+    #[serde(default)]
+    pub cascade: bool,
+}
+
 #[derive(Debug, Serialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 pub struct TenantResponse {
+    #[cfg_attr(feature = "openapi", schema(value_type = Object))]
     pub tenant: TenantConfig,
+    #[cfg_attr(feature = "openapi", schema(value_type = Vec<Object>))]
     pub quota_violations: Vec<super::resource_quota::QuotaViolation>,
     pub health_status: bool,
 }
 
 #[derive(Debug, Serialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 pub struct TenantsListResponse {
+    #[cfg_attr(feature = "openapi", schema(value_type = Vec<Object>))]
     pub tenants: Vec<TenantConfig>,
+    /// Tenants in the deployment, across all pages
     pub total: usize,
     pub limit: Option<u32>,
     pub offset: Option<u32>,
+    /// Pass as `cursor` to fetch the next page; absent on the last one
+    pub next_cursor: Option<Uuid>,
 }
 
 #[derive(Debug, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 pub struct SuspendTenantRequest {
     pub reason: String,
 }
 
+#[derive(Debug, Serialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct TenantImportResponse {
+    /// One per requested tenant, in request order
+    #[cfg_attr(feature = "openapi", schema(value_type = Vec<Object>))]
+    pub results: Vec<TenantImportResult>,
+    pub created: usize,
+    pub failed: usize,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct IssueTenantKeyRequest {
+    /// Label to tell the tenant's keys apart
+    pub name: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct IssuedTenantKeyResponse {
+    /// Shown only in this response
+    pub api_key: String,
+    #[serde(flatten)]
+    pub key: TenantApiKey,
+}
+
+#[derive(Debug, Serialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct TenantKeysResponse {
+    pub keys: Vec<TenantApiKey>,
+    pub max_api_keys: u32,
+}
+
+/// Most tenants `POST /v1/tenants/import` or `POST /tenants/batch` takes at once
+pub const MAX_TENANT_IMPORT: usize = 1000;
+
 pub type TenantManagerState = Arc<Mutex<TenantManager>>;
 
+pub struct TenantImportState {
+    pub tenant_manager: TenantManagerState,
+    pub audit: Arc<AuditLogger>,
+}
+
+pub struct TenantKeysState {
+    pub tenant_manager: TenantManagerState,
+    pub validator: Arc<ApiKeyValidator>,
+    pub audit: Arc<AuditLogger>,
+}
+
 pub fn create_tenant_routes() -> Router<TenantManagerState> {
     Router::new()
         .route("/tenants", post(create_tenant))
@@ -79,22 +188,62 @@ pub fn create_tenant_routes() -> Router<TenantManagerState> {
         .route("/tenants/slug/:slug", get(get_tenant_by_slug))
 }
 
+/// Bulk import and batch creation, kept apart from `create_tenant_routes`
+/// because they're audited
+pub fn create_tenant_import_router(tenant_manager: TenantManagerState, audit: Arc<AuditLogger>) -> Router {
+    Router::new()
+        .route("/v1/tenants/import", post(import_tenants))
+        .route("/tenants/batch", post(batch_create_tenants))
+        .with_state(Arc::new(TenantImportState { tenant_manager, audit }))
+}
+
+/// Issuing, listing and revoking a tenant's API keys, audited like the import
+pub fn create_tenant_keys_router(
+    tenant_manager: TenantManagerState,
+    validator: Arc<ApiKeyValidator>,
+    audit: Arc<AuditLogger>,
+) -> Router {
+    Router::new()
+        .route("/v1/tenants/:tenant_id/keys", post(issue_tenant_key).get(list_tenant_keys))
+        .route("/v1/tenants/:tenant_id/keys/:key_id", delete(revoke_tenant_key))
+        .with_state(Arc::new(TenantKeysState { tenant_manager, validator, audit }))
+}
+
+/// 4xx for a `TenantError`, otherwise log `e` and report `action` as failed
+fn tenant_error(e: anyhow::Error, action: &str) -> ApiError {
+    match e.downcast_ref::<TenantError>() {
+        Some(error @ TenantError::NotFound(_)) => ApiError::not_found(error.to_string()),
+        Some(error @ TenantError::SlugTaken(_)) => ApiError::conflict("SLUG_TAKEN", error.to_string()),
+        Some(error @ TenantError::Invalid(_)) => ApiError::bad_request("INVALID_TENANT", error.to_string()),
+        None => {
+            tracing::error!("{}: {}", action, e);
+            ApiError::internal(action)
+        }
+    }
+}
+
+#[cfg_attr(
+    feature = "openapi",
+    utoipa::path(
+        post,
+        path = "/tenants",
+        tag = "tenants",
+        request_body = CreateTenantRequest,
+        responses(
+            (status = 200, description = "Tenant created", body = Object),
+            (status = 400, description = "Invalid tenant", body = crate::api_error::ProblemDetails),
+            (status = 409, description = "Slug already taken", body = crate::api_error::ProblemDetails),
+            (status = 500, description = "Internal error", body = crate::api_error::ProblemDetails),
+            (status = 401, description = "Missing or invalid API key"),
+        ),
+        security(("api_key" = [])),
+    )
+)]
 async fn create_tenant(
     State(tenant_manager): State<TenantManagerState>,
     Json(request): Json<CreateTenantRequest>,
-) -> Result<Json<serde_json::Value>, StatusCode> {
-    let onboarding_request = TenantOnboardingRequest {
-        name: request.name,
-        slug: request.slug,
-        admin_email: request.admin_email,
-        organization: request.organization,
-        isolation_level: request.isolation_level.unwrap_or(IsolationLevel::Shared),
-        data_classification: request.data_classification.unwrap_or(DataClassification::Internal),
-        initial_quotas: request.initial_quotas,
-        initial_settings: request.initial_settings,
-        features: request.features.unwrap_or_default(),
-        metadata: request.metadata.unwrap_or_default(),
-    };
+) -> Result<Json<serde_json::Value>, ApiError> {
+    let onboarding_request = TenantOnboardingRequest::from(request);
 
     let mut manager = tenant_manager.lock().await;
     match manager.create_tenant(onboarding_request).await {
@@ -106,48 +255,178 @@ async fn create_tenant(
             });
             Ok(Json(response))
         }
-        Err(e) => {
-            tracing::error!("Failed to create tenant: {}", e);
-            Err(StatusCode::BAD_REQUEST)
-        }
+        Err(e) => Err(tenant_error(e, "Failed to create tenant")),
     }
 }
 
+#[cfg_attr(
+    feature = "openapi",
+    utoipa::path(
+        post,
+        path = "/v1/tenants/import",
+        tag = "tenants",
+        request_body = Vec<CreateTenantRequest>,
+        responses(
+            (status = 200, description = "Per-tenant results; some may have failed", body = TenantImportResponse),
+            (status = 400, description = "More than 1000 tenants", body = crate::api_error::ProblemDetails),
+            (status = 401, description = "Missing or invalid API key"),
+        ),
+        security(("api_key" = [])),
+    )
+)]
+async fn import_tenants(
+    State(state): State<Arc<TenantImportState>>,
+    Extension(identity): Extension<ApiKeyIdentity>,
+    Json(requests): Json<Vec<CreateTenantRequest>>,
+) -> Result<Json<TenantImportResponse>, ApiError> {
+    create_tenants(&state, identity, requests, "import_tenants").await
+}
+
+#[cfg_attr(
+    feature = "openapi",
+    utoipa::path(
+        post,
+        path = "/tenants/batch",
+        tag = "tenants",
+        request_body = Vec<CreateTenantRequest>,
+        responses(
+            (status = 200, description = "Per-tenant results in request order; some may have failed", body = TenantImportResponse),
+            (status = 400, description = "More than 1000 tenants", body = crate::api_error::ProblemDetails),
+            (status = 401, description = "Missing or invalid API key"),
+        ),
+        security(("api_key" = [])),
+    )
+)]
+async fn batch_create_tenants(
+    State(state): State<Arc<TenantImportState>>,
+    Extension(identity): Extension<ApiKeyIdentity>,
+    Json(requests): Json<Vec<CreateTenantRequest>>,
+) -> Result<Json<TenantImportResponse>, ApiError> {
+    create_tenants(&state, identity, requests, "batch_create_tenants").await
+}
+
+/// Create the tenants, each succeeding or failing on its own, and audit the
+/// batch as `action`
+async fn create_tenants(
+    state: &TenantImportState,
+    identity: ApiKeyIdentity,
+    requests: Vec<CreateTenantRequest>,
+    action: &str,
+) -> Result<Json<TenantImportResponse>, ApiError> {
+    if requests.len() > MAX_TENANT_IMPORT {
+        return Err(ApiError::bad_request(
+            "TOO_MANY_TENANTS",
+            format!("At most {} tenants can be created at once", MAX_TENANT_IMPORT),
+        ));
+    }
+
+    let requests = requests.into_iter().map(TenantOnboardingRequest::from).collect();
+    let results = state.tenant_manager.lock().await.import_tenants(requests).await;
+
+    let created: Vec<_> = results.iter().filter_map(|result| result.tenant_id).collect();
+    let failed: Vec<_> = results
+        .iter()
+        .filter(|result| result.error.is_some())
+        .map(|result| result.slug.as_str())
+        .collect();
+    let outcome = match (created.is_empty(), failed.is_empty()) {
+        (_, true) => AuditOutcome::Success,
+        (true, false) => AuditOutcome::Failure,
+        (false, false) => AuditOutcome::Partial,
+    };
+    let details = serde_json::json!({
+        "requested": results.len(),
+        "created_tenant_ids": created,
+        "failed_slugs": failed,
+    });
+    if let Err(e) = state
+        .audit
+        .log_admin_action(
+            ActorInfo::new().with_api_key(identity.key_id),
+            action,
+            "tenant",
+            None,
+            outcome,
+            None,
+            Some(details),
+        )
+        .await
+    {
+        tracing::error!("Failed to audit {}: {}", action, e);
+    }
+
+    Ok(Json(TenantImportResponse {
+        created: created.len(),
+        failed: failed.len(),
+        results,
+    }))
+}
+
+#[cfg_attr(
+    feature = "openapi",
+    utoipa::path(
+        get,
+        path = "/tenants",
+        tag = "tenants",
+        params(ListTenantsQuery),
+        responses(
+            (status = 200, description = "Tenants", body = TenantsListResponse),
+            (status = 400, description = "Invalid cursor", body = crate::api_error::ProblemDetails),
+            (status = 500, description = "Internal error", body = crate::api_error::ProblemDetails),
+            (status = 401, description = "Missing or invalid API key"),
+        ),
+        security(("api_key" = [])),
+    )
+)]
 async fn list_tenants(
     State(tenant_manager): State<TenantManagerState>,
     Query(query): Query<ListTenantsQuery>,
-) -> Result<Json<TenantsListResponse>, StatusCode> {
+) -> Result<Json<TenantsListResponse>, ApiError> {
     let mut manager = tenant_manager.lock().await;
-    
-    match manager.list_tenants(query.limit, query.offset).await {
-        Ok(tenants) => {
+    let limit = query.limit.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE);
+
+    match manager.list_tenants(Some(limit), query.offset, query.cursor).await {
+        Ok(page) => {
             let filtered_tenants = if let Some(status_filter) = &query.status {
-                tenants.into_iter()
+                page.tenants.into_iter()
                     .filter(|t| format!("{:?}", t.status).to_lowercase() == status_filter.to_lowercase())
                     .collect()
             } else {
-                tenants
+                page.tenants
             };
 
             let response = TenantsListResponse {
-                total: filtered_tenants.len(),
+                total: page.total,
                 tenants: filtered_tenants,
-                limit: query.limit,
+                limit: Some(limit),
                 offset: query.offset,
+                next_cursor: page.next_cursor,
             };
             Ok(Json(response))
         }
-        Err(e) => {
-            tracing::error!("Failed to list tenants: {}", e);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
-        }
+        Err(e) => Err(tenant_error(e, "Failed to list tenants")),
     }
 }
 
+#[cfg_attr(
+    feature = "openapi",
+    utoipa::path(
+        get,
+        path = "/tenants/{tenant_id}",
+        tag = "tenants",
+        params(("tenant_id" = Uuid, Path, description = "Tenant ID")),
+        responses(
+            (status = 200, description = "Tenant", body = TenantResponse),
+            (status = 404, description = "Tenant not found", body = crate::api_error::ProblemDetails),
+            (status = 401, description = "Missing or invalid API key"),
+        ),
+        security(("api_key" = [])),
+    )
+)]
 async fn get_tenant(
     State(tenant_manager): State<TenantManagerState>,
     Path(tenant_id): Path<Uuid>,
-) -> Result<Json<TenantResponse>, StatusCode> {
+) -> Result<Json<TenantResponse>, ApiError> {
     let mut manager = tenant_manager.lock().await;
     
     match manager.get_tenant_config(tenant_id).await {
@@ -165,14 +444,29 @@ async fn get_tenant(
             };
             Ok(Json(response))
         }
-        Err(_) => Err(StatusCode::NOT_FOUND),
+        Err(e) => Err(tenant_error(e, "Failed to get tenant")),
     }
 }
 
+#[cfg_attr(
+    feature = "openapi",
+    utoipa::path(
+        get,
+        path = "/tenants/slug/{slug}",
+        tag = "tenants",
+        params(("slug" = String, Path, description = "Tenant slug")),
+        responses(
+            (status = 200, description = "Tenant", body = TenantResponse),
+            (status = 404, description = "Tenant not found", body = crate::api_error::ProblemDetails),
+            (status = 401, description = "Missing or invalid API key"),
+        ),
+        security(("api_key" = [])),
+    )
+)]
 async fn get_tenant_by_slug(
     State(tenant_manager): State<TenantManagerState>,
     Path(slug): Path<String>,
-) -> Result<Json<TenantResponse>, StatusCode> {
+) -> Result<Json<TenantResponse>, ApiError> {
     let mut manager = tenant_manager.lock().await;
     
     match manager.get_tenant_by_slug(&slug).await {
@@ -190,19 +484,33 @@ async fn get_tenant_by_slug(
             };
             Ok(Json(response))
         }
-        Ok(None) => Err(StatusCode::NOT_FOUND),
-        Err(e) => {
-            tracing::error!("Failed to get tenant by slug: {}", e);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
-        }
+        Ok(None) => Err(ApiError::not_found(format!("No tenant with slug '{}'", slug))),
+        Err(e) => Err(tenant_error(e, "Failed to get tenant by slug")),
     }
 }
 
+#[cfg_attr(
+    feature = "openapi",
+    utoipa::path(
+        put,
+        path = "/tenants/{tenant_id}",
+        tag = "tenants",
+        params(("tenant_id" = Uuid, Path, description = "Tenant ID")),
+        request_body = UpdateTenantRequest,
+        responses(
+            (status = 200, description = "Updated tenant configuration", body = Object),
+            (status = 400, description = "Quotas exceed the parent tenant's", body = crate::api_error::ProblemDetails),
+            (status = 404, description = "Tenant not found", body = crate::api_error::ProblemDetails),
+            (status = 401, description = "Missing or invalid API key"),
+        ),
+        security(("api_key" = [])),
+    )
+)]
 async fn update_tenant(
     State(tenant_manager): State<TenantManagerState>,
     Path(tenant_id): Path<Uuid>,
     Json(request): Json<UpdateTenantRequest>,
-) -> Result<Json<TenantConfig>, StatusCode> {
+) -> Result<Json<TenantConfig>, ApiError> {
     let mut manager = tenant_manager.lock().await;
     
     match manager.get_tenant_config(tenant_id).await {
@@ -229,66 +537,130 @@ async fn update_tenant(
 
             match manager.update_tenant_config(tenant_id, tenant.clone()).await {
                 Ok(_) => Ok(Json(tenant)),
-                Err(e) => {
-                    tracing::error!("Failed to update tenant: {}", e);
-                    Err(StatusCode::INTERNAL_SERVER_ERROR)
-                }
+                Err(e) => Err(tenant_error(e, "Failed to update tenant")),
             }
         }
-        Err(_) => Err(StatusCode::NOT_FOUND),
+        Err(e) => Err(tenant_error(e, "Failed to get tenant")),
     }
 }
 
+#[cfg_attr(
+    feature = "openapi",
+    utoipa::path(
+        delete,
+        path = "/tenants/{tenant_id}",
+        tag = "tenants",
+        params(("tenant_id" = Uuid, Path, description = "Tenant ID"), DeleteTenantQuery),
+        responses(
+            (status = 204, description = "Tenant deleted"),
+            (status = 404, description = "Tenant not found", body = crate::api_error::ProblemDetails),
+            (status = 409, description = "Tenant has child tenants and cascade wasn't set", body = crate::api_error::ProblemDetails),
+            (status = 401, description = "Missing or invalid API key"),
+        ),
+        security(("api_key" = [])),
+    )
+)]
 async fn delete_tenant(
     State(tenant_manager): State<TenantManagerState>,
     Path(tenant_id): Path<Uuid>,
-) -> Result<StatusCode, StatusCode> {
+    Query(query): Query<DeleteTenantQuery>,
+) -> Result<StatusCode, ApiError> {
     let mut manager = tenant_manager.lock().await;
-    
-    match manager.delete_tenant(tenant_id).await {
-        Ok(_) => Ok(StatusCode::NO_CONTENT),
-        Err(e) => {
-            tracing::error!("Failed to delete tenant: {}", e);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
+
+    let result = if query.cascade {
+        manager.delete_tenant_cascade(tenant_id).await
+    } else {
+        match manager.child_tenant_ids(tenant_id).await {
+            Ok(children) if !children.is_empty() => {
+                return Err(ApiError::conflict(
+                    "HAS_CHILD_TENANTS",
+                    format!("Tenant has {} child tenants; delete with cascade=true to remove them too", children.len()),
+                ))
+            }
+            _ => manager.delete_tenant(tenant_id).await,
         }
+    };
+    match result {
+        Ok(_) => Ok(StatusCode::NO_CONTENT),
+        Err(e) => Err(tenant_error(e, "Failed to delete tenant")),
     }
 }
 
+#[cfg_attr(
+    feature = "openapi",
+    utoipa::path(
+        post,
+        path = "/tenants/{tenant_id}/suspend",
+        tag = "tenants",
+        params(("tenant_id" = Uuid, Path, description = "Tenant ID")),
+        request_body = SuspendTenantRequest,
+        responses(
+            (status = 200, description = "Tenant suspended"),
+            (status = 404, description = "Tenant not found", body = crate::api_error::ProblemDetails),
+            (status = 401, description = "Missing or invalid API key"),
+        ),
+        security(("api_key" = [])),
+    )
+)]
 async fn suspend_tenant(
     State(tenant_manager): State<TenantManagerState>,
     Path(tenant_id): Path<Uuid>,
     Json(request): Json<SuspendTenantRequest>,
-) -> Result<StatusCode, StatusCode> {
+) -> Result<StatusCode, ApiError> {
     let mut manager = tenant_manager.lock().await;
     
     match manager.suspend_tenant(tenant_id, request.reason).await {
         Ok(_) => Ok(StatusCode::OK),
-        Err(e) => {
-            tracing::error!("Failed to suspend tenant: {}", e);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
-        }
+        Err(e) => Err(tenant_error(e, "Failed to suspend tenant")),
     }
 }
 
+#[cfg_attr(
+    feature = "openapi",
+    utoipa::path(
+        post,
+        path = "/tenants/{tenant_id}/reactivate",
+        tag = "tenants",
+        params(("tenant_id" = Uuid, Path, description = "Tenant ID")),
+        responses(
+            (status = 200, description = "Tenant reactivated"),
+            (status = 404, description = "Tenant not found", body = crate::api_error::ProblemDetails),
+            (status = 401, description = "Missing or invalid API key"),
+        ),
+        security(("api_key" = [])),
+    )
+)]
 async fn reactivate_tenant(
     State(tenant_manager): State<TenantManagerState>,
     Path(tenant_id): Path<Uuid>,
-) -> Result<StatusCode, StatusCode> {
+) -> Result<StatusCode, ApiError> {
     let mut manager = tenant_manager.lock().await;
     
     match manager.reactivate_tenant(tenant_id).await {
         Ok(_) => Ok(StatusCode::OK),
-        Err(e) => {
-            tracing::error!("Failed to reactivate tenant: {}", e);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
-        }
+        Err(e) => Err(tenant_error(e, "Failed to reactivate tenant")),
     }
 }
 
+#[cfg_attr(
+    feature = "openapi",
+    utoipa::path(
+        get,
+        path = "/tenants/{tenant_id}/health",
+        tag = "tenants",
+        params(("tenant_id" = Uuid, Path, description = "Tenant ID")),
+        responses(
+            (status = 200, description = "Tenant health", body = Object),
+            (status = 404, description = "Tenant not found", body = crate::api_error::ProblemDetails),
+            (status = 401, description = "Missing or invalid API key"),
+        ),
+        security(("api_key" = [])),
+    )
+)]
 async fn health_check_tenant(
     State(tenant_manager): State<TenantManagerState>,
     Path(tenant_id): Path<Uuid>,
-) -> Result<Json<serde_json::Value>, StatusCode> {
+) -> Result<Json<serde_json::Value>, ApiError> {
     let mut manager = tenant_manager.lock().await;
     
     match manager.health_check_tenant(tenant_id).await {
@@ -300,17 +672,29 @@ async fn health_check_tenant(
             });
             Ok(Json(response))
         }
-        Err(e) => {
-            tracing::error!("Failed to check tenant health: {}", e);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
-        }
+        Err(e) => Err(tenant_error(e, "Failed to check tenant health")),
     }
 }
 
+#[cfg_attr(
+    feature = "openapi",
+    utoipa::path(
+        get,
+        path = "/tenants/{tenant_id}/quotas",
+        tag = "tenants",
+        params(("tenant_id" = Uuid, Path, description = "Tenant ID")),
+        responses(
+            (status = 200, description = "Tenant quotas and usage", body = Object),
+            (status = 404, description = "Tenant not found", body = crate::api_error::ProblemDetails),
+            (status = 401, description = "Missing or invalid API key"),
+        ),
+        security(("api_key" = [])),
+    )
+)]
 async fn get_tenant_quotas(
     State(tenant_manager): State<TenantManagerState>,
     Path(tenant_id): Path<Uuid>,
-) -> Result<Json<serde_json::Value>, StatusCode> {
+) -> Result<Json<serde_json::Value>, ApiError> {
     let mut manager = tenant_manager.lock().await;
     
     match manager.get_tenant_config(tenant_id).await {
@@ -325,12 +709,181 @@ async fn get_tenant_quotas(
                     });
                     Ok(Json(response))
                 }
-                Err(e) => {
-                    tracing::error!("Failed to check quota violations: {}", e);
-                    Err(StatusCode::INTERNAL_SERVER_ERROR)
-                }
+                Err(e) => Err(tenant_error(e, "Failed to check quota violations")),
             }
         }
-        Err(_) => Err(StatusCode::NOT_FOUND),
+        Err(e) => Err(tenant_error(e, "Failed to get tenant")),
+    }
+}
+
+/// Keys issued to one tenant can't be used to manage another's
+fn authorize_tenant_caller(identity: &ApiKeyIdentity, tenant_id: Uuid) -> Result<(), ApiError> {
+    match identity.tenant_id {
+        Some(own) if own != tenant_id => Err(ApiError::forbidden("API key belongs to another tenant")),
+        _ => Ok(()),
+    }
+}
+
+#[cfg_attr(
+    feature = "openapi",
+    utoipa::path(
+        post,
+        path = "/v1/tenants/{tenant_id}/keys",
+        tag = "tenants",
+        params(("tenant_id" = Uuid, Path, description = "Tenant ID")),
+        request_body = IssueTenantKeyRequest,
+        responses(
+            (status = 201, description = "Key issued; the key itself is not shown again", body = IssuedTenantKeyResponse),
+            (status = 401, description = "Missing or invalid API key"),
+            (status = 403, description = "Caller's key belongs to another tenant", body = crate::api_error::ProblemDetails),
+            (status = 404, description = "Tenant not found", body = crate::api_error::ProblemDetails),
+            (status = 409, description = "Tenant already has max_api_keys keys", body = crate::api_error::ProblemDetails),
+        ),
+        security(("api_key" = [])),
+    )
+)]
+async fn issue_tenant_key(
+    State(state): State<Arc<TenantKeysState>>,
+    Extension(identity): Extension<ApiKeyIdentity>,
+    Path(tenant_id): Path<Uuid>,
+    request: Option<Json<IssueTenantKeyRequest>>,
+) -> Result<(StatusCode, Json<IssuedTenantKeyResponse>), ApiError> {
+    authorize_tenant_caller(&identity, tenant_id)?;
+    let Json(request) = request.unwrap_or_default();
+
+    let api_key = ApiKeyValidator::generate_api_key();
+    let key_id = state.validator.identity(&api_key).key_id;
+    let issued = {
+        let mut manager = state.tenant_manager.lock().await;
+        manager
+            .get_tenant_config(tenant_id)
+            .await
+            .map_err(|e| tenant_error(e, "Failed to get tenant"))?;
+        manager.issue_api_key(tenant_id, key_id.clone(), request.name).await
+    };
+
+    let (outcome, details, result) = match issued {
+        Ok(Some(key)) => (AuditOutcome::Success, None, Ok(key)),
+        Ok(None) => (
+            AuditOutcome::Failure,
+            Some(serde_json::json!({ "reason": "max_api_keys reached" })),
+            Err(ApiError::conflict("MAX_API_KEYS", "Tenant already has max_api_keys keys")),
+        ),
+        Err(e) => {
+            tracing::error!("Failed to issue key for tenant {}: {}", tenant_id, e);
+            (AuditOutcome::Failure, None, Err(ApiError::internal("Failed to issue key")))
+        }
+    };
+    if let Err(e) = state
+        .audit
+        .log_admin_action(
+            ActorInfo::new().with_api_key(identity.key_id),
+            "issue_api_key",
+            "api_key",
+            Some(&key_id),
+            outcome,
+            Some(tenant_id.to_string()),
+            details,
+        )
+        .await
+    {
+        tracing::error!("Failed to audit tenant key issue: {}", e);
     }
-}
\ No newline at end of file
+
+    let key = result?;
+    Ok((StatusCode::CREATED, Json(IssuedTenantKeyResponse { api_key, key })))
+}
+
+#[cfg_attr(
+    feature = "openapi",
+    utoipa::path(
+        get,
+        path = "/v1/tenants/{tenant_id}/keys",
+        tag = "tenants",
+        params(("tenant_id" = Uuid, Path, description = "Tenant ID")),
+        responses(
+            (status = 200, description = "The tenant's keys, oldest first", body = TenantKeysResponse),
+            (status = 401, description = "Missing or invalid API key"),
+            (status = 403, description = "Caller's key belongs to another tenant", body = crate::api_error::ProblemDetails),
+            (status = 404, description = "Tenant not found", body = crate::api_error::ProblemDetails),
+        ),
+        security(("api_key" = [])),
+    )
+)]
+async fn list_tenant_keys(
+    State(state): State<Arc<TenantKeysState>>,
+    Extension(identity): Extension<ApiKeyIdentity>,
+    Path(tenant_id): Path<Uuid>,
+) -> Result<Json<TenantKeysResponse>, ApiError> {
+    authorize_tenant_caller(&identity, tenant_id)?;
+
+    let mut manager = state.tenant_manager.lock().await;
+    let tenant = manager
+        .get_tenant_config(tenant_id)
+        .await
+        .map_err(|e| tenant_error(e, "Failed to get tenant"))?;
+    match manager.list_api_keys(tenant_id).await {
+        Ok(keys) => Ok(Json(TenantKeysResponse {
+            keys,
+            max_api_keys: tenant.quotas.max_api_keys,
+        })),
+        Err(e) => {
+            tracing::error!("Failed to list keys for tenant {}: {}", tenant_id, e);
+            Err(ApiError::internal("Failed to list keys"))
+        }
+    }
+}
+
+#[cfg_attr(
+    feature = "openapi",
+    utoipa::path(
+        delete,
+        path = "/v1/tenants/{tenant_id}/keys/{key_id}",
+        tag = "tenants",
+        params(
+            ("tenant_id" = Uuid, Path, description = "Tenant ID"),
+            ("key_id" = String, Path, description = "Key ID, as listed"),
+        ),
+        responses(
+            (status = 204, description = "Key revoked"),
+            (status = 401, description = "Missing or invalid API key"),
+            (status = 403, description = "Caller's key belongs to another tenant", body = crate::api_error::ProblemDetails),
+            (status = 404, description = "Tenant has no such key", body = crate::api_error::ProblemDetails),
+        ),
+        security(("api_key" = [])),
+    )
+)]
+async fn revoke_tenant_key(
+    State(state): State<Arc<TenantKeysState>>,
+    Extension(identity): Extension<ApiKeyIdentity>,
+    Path((tenant_id, key_id)): Path<(Uuid, String)>,
+) -> Result<StatusCode, ApiError> {
+    authorize_tenant_caller(&identity, tenant_id)?;
+
+    let revoked = state.tenant_manager.lock().await.revoke_api_key(tenant_id, &key_id).await;
+    let (outcome, result) = match revoked {
+        Ok(true) => (AuditOutcome::Success, Ok(StatusCode::NO_CONTENT)),
+        Ok(false) => return Err(ApiError::not_found(format!("Tenant has no key '{}'", key_id))),
+        Err(e) => {
+            tracing::error!("Failed to revoke key {} for tenant {}: {}", key_id, tenant_id, e);
+            (AuditOutcome::Failure, Err(ApiError::internal("Failed to revoke key")))
+        }
+    };
+    if let Err(e) = state
+        .audit
+        .log_admin_action(
+            ActorInfo::new().with_api_key(identity.key_id),
+            "revoke_api_key",
+            "api_key",
+            Some(&key_id),
+            outcome,
+            Some(tenant_id.to_string()),
+            None,
+        )
+        .await
+    {
+        tracing::error!("Failed to audit tenant key revocation: {}", e);
+    }
+
+    result
+}
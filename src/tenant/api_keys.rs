@@ -0,0 +1,63 @@
+//! API keys issued to a tenant.
+//!
+//! A tenant's keys are the hash `tenant:{tenant_id}:api_keys`, key ID to
+//! `TenantApiKey`, whose size is what `ResourceQuotas::max_api_keys` limits.
+//! Each key ID is also bound to its tenant in `api_key_tenant:{key_id}`,
+//! which is what `ApiKeyValidator` reads to tag requests with the tenant.
+//! Revoking a key removes it from the tenant's hash but keeps the binding,
+//! marked revoked, so the key is refused rather than treated as untenanted.
+//!
+//! Only key IDs are stored; the key itself is returned once, when issued.
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::redis_backend::RedisConnector;
+
+/// What's kept about an issued key
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct TenantApiKey {
+    /// As recorded in audit events
+    pub key_id: String,
+    pub tenant_id: Uuid,
+    pub name: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// A key ID's tenant, as `ApiKeyValidator` sees it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TenantKeyBinding {
+    pub tenant_id: Uuid,
+    pub revoked_at: Option<DateTime<Utc>>,
+}
+
+pub(crate) fn tenant_keys_key(tenant_id: Uuid) -> String {
+    format!("tenant:{}:api_keys", tenant_id)
+}
+
+pub(crate) fn binding_key(key_id: &str) -> String {
+    format!("api_key_tenant:{}", key_id)
+}
+
+/// Looks up which tenant, if any, a key was issued to
+pub struct TenantKeyResolver {
+    redis: RedisConnector,
+}
+
+impl TenantKeyResolver {
+    pub fn new(redis: RedisConnector) -> Self {
+        Self { redis }
+    }
+
+    pub async fn binding(&self, key_id: &str) -> Result<Option<TenantKeyBinding>> {
+        let mut conn = self.redis.get_async_connection().await?;
+        let binding: Option<String> = redis::cmd("GET")
+            .arg(binding_key(key_id))
+            .query_async(&mut conn)
+            .await?;
+        binding.map(|binding| Ok(serde_json::from_str(&binding)?)).transpose()
+    }
+}
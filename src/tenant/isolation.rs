@@ -1,8 +1,13 @@
 use uuid::Uuid;
 use std::collections::HashMap;
+use std::sync::Arc;
 use anyhow::{Result, anyhow};
 use serde::{Deserialize, Serialize};
 
+use crate::audit::{AuditEvent, AuditEventType, AuditLogger, AuditOutcome, ActorInfo, ResourceInfo};
+use crate::redis_backend::{scan_keys, RedisConnector};
+use crate::security::siem_integration::{self, SecurityEvent, SecurityEventSeverity, SecurityEventType, SiemIntegration};
+
 #[derive(Debug, Clone)]
 pub struct TenantContext {
     pub tenant_id: Uuid,
@@ -27,17 +32,36 @@ pub enum DataClassification {
 }
 
 pub struct TenantIsolationManager {
-    redis_client: redis::Client,
+    redis_client: RedisConnector,
     namespace_prefix: String,
+    audit: Option<Arc<AuditLogger>>,
+    siem: Option<Arc<SiemIntegration>>,
 }
 
 impl TenantIsolationManager {
     pub fn new(redis_url: &str, namespace_prefix: String) -> Result<Self> {
-        let redis_client = redis::Client::open(redis_url)?;
-        Ok(Self {
+        Ok(Self::from_connector(RedisConnector::open(redis_url)?, namespace_prefix))
+    }
+
+    pub fn from_connector(redis_client: RedisConnector, namespace_prefix: String) -> Self {
+        Self {
             redis_client,
             namespace_prefix,
-        })
+            audit: None,
+            siem: None,
+        }
+    }
+
+    /// Record every denied cross-tenant access as a security audit event
+    pub fn with_audit(mut self, audit: Arc<AuditLogger>) -> Self {
+        self.audit = Some(audit);
+        self
+    }
+
+    /// Raise a SIEM event when a `Private` tenant reaches for another tenant's data
+    pub fn with_siem(mut self, siem: Arc<SiemIntegration>) -> Self {
+        self.siem = Some(siem);
+        self
     }
 
     pub fn create_tenant_context(
@@ -123,10 +147,7 @@ impl TenantIsolationManager {
         let mut conn = self.redis_client.get_async_connection().await?;
         let search_pattern = format!("{}:{}", context.namespace, pattern);
 
-        let keys: Vec<String> = redis::cmd("KEYS")
-            .arg(&search_pattern)
-            .query_async(&mut conn)
-            .await?;
+        let keys = scan_keys(&mut conn, &search_pattern).await?;
 
         // Remove namespace prefix from keys
         let clean_keys: Vec<String> = keys
@@ -147,10 +168,7 @@ impl TenantIsolationManager {
         let mut conn = self.redis_client.get_async_connection().await?;
         let pattern = format!("{}:*", context.namespace);
 
-        let keys: Vec<String> = redis::cmd("KEYS")
-            .arg(&pattern)
-            .query_async(&mut conn)
-            .await?;
+        let keys = scan_keys(&mut conn, &pattern).await?;
 
         if keys.is_empty() {
             return Ok(0);
@@ -172,22 +190,68 @@ impl TenantIsolationManager {
         target_tenant_id: Uuid,
     ) -> Result<bool> {
         // Only allow cross-tenant access for specific isolation levels and data classifications
-        match requesting_context.isolation_level {
+        let allowed = match requesting_context.isolation_level {
             IsolationLevel::Private => {
                 // Private tenants cannot access other tenant data
-                Ok(requesting_context.tenant_id == target_tenant_id)
+                requesting_context.tenant_id == target_tenant_id
             }
             IsolationLevel::Dedicated => {
                 // Dedicated tenants can only access their own data unless explicitly allowed
                 if requesting_context.tenant_id == target_tenant_id {
-                    Ok(true)
+                    true
                 } else {
-                    self.check_cross_tenant_permission(requesting_context, target_tenant_id).await
+                    self.check_cross_tenant_permission(requesting_context, target_tenant_id).await?
                 }
             }
             IsolationLevel::Shared => {
                 // Shared tenants can access other shared tenant data with proper permissions
-                self.check_cross_tenant_permission(requesting_context, target_tenant_id).await
+                self.check_cross_tenant_permission(requesting_context, target_tenant_id).await?
+            }
+        };
+
+        if !allowed {
+            self.record_denied_access(requesting_context, target_tenant_id).await;
+        }
+        Ok(allowed)
+    }
+
+    /// Audit a refused cross-tenant access, and for `Private` tenants alert
+    /// the SIEM too. Failures are logged rather than returned so the denial
+    /// itself still stands.
+    async fn record_denied_access(&self, requesting_context: &TenantContext, target_tenant_id: Uuid) {
+        let requesting_tenant_id = requesting_context.tenant_id.to_string();
+        let target_tenant_id = target_tenant_id.to_string();
+        tracing::warn!(
+            requesting_tenant_id = %requesting_tenant_id,
+            target_tenant_id = %target_tenant_id,
+            isolation_level = ?requesting_context.isolation_level,
+            "Cross-tenant access denied"
+        );
+
+        if let Some(audit) = &self.audit {
+            let event = AuditEvent::new(
+                AuditEventType::SecurityEvent,
+                ActorInfo::new().with_tenant_id(requesting_tenant_id.clone()),
+                ResourceInfo::new("tenant_data".to_string())
+                    .with_id(target_tenant_id.clone())
+                    .with_tenant_id(target_tenant_id.clone()),
+                "cross_tenant_access".to_string(),
+                AuditOutcome::Failure,
+            )
+            .with_tenant_id(requesting_tenant_id.clone())
+            .with_metadata("requesting_tenant_id".to_string(), serde_json::json!(requesting_tenant_id))
+            .with_metadata("target_tenant_id".to_string(), serde_json::json!(target_tenant_id))
+            .with_metadata("isolation_level".to_string(), serde_json::json!(requesting_context.isolation_level))
+            .with_metadata("data_classification".to_string(), serde_json::json!(requesting_context.data_classification));
+
+            if let Err(e) = audit.log_event(event).await {
+                tracing::error!("Failed to audit denied cross-tenant access: {}", e);
+            }
+        }
+
+        if requesting_context.isolation_level == IsolationLevel::Private {
+            if let Some(siem) = &self.siem {
+                siem.queue_event(isolation_violation_event(requesting_context, &target_tenant_id)).await;
             }
         }
     }
@@ -305,10 +369,7 @@ impl TenantIsolationManager {
         
         // Count keys in tenant namespace
         let pattern = format!("{}:*", context.namespace);
-        let keys: Vec<String> = redis::cmd("KEYS")
-            .arg(&pattern)
-            .query_async(&mut conn)
-            .await?;
+        let keys = scan_keys(&mut conn, &pattern).await?;
 
         let key_count = keys.len() as u64;
         
@@ -343,6 +404,52 @@ impl TenantIsolationManager {
     }
 }
 
+/// The SIEM alert for a `Private` tenant reaching for another tenant's data
+fn isolation_violation_event(requesting_context: &TenantContext, target_tenant_id: &str) -> SecurityEvent {
+    let requesting_tenant_id = requesting_context.tenant_id.to_string();
+    let mut raw_data = HashMap::new();
+    raw_data.insert("requesting_tenant_id".to_string(), serde_json::json!(requesting_tenant_id));
+    raw_data.insert("target_tenant_id".to_string(), serde_json::json!(target_tenant_id));
+    raw_data.insert("isolation_level".to_string(), serde_json::json!(requesting_context.isolation_level));
+    raw_data.insert("data_classification".to_string(), serde_json::json!(requesting_context.data_classification));
+
+    SecurityEvent {
+        event_id: Uuid::new_v4().to_string(),
+        timestamp: chrono::Utc::now(),
+        event_type: SecurityEventType::PolicyViolation,
+        severity: SecurityEventSeverity::High,
+        source: "ratewatch".to_string(),
+        title: "Tenant isolation violation attempt".to_string(),
+        description: format!(
+            "Private tenant {} attempted to access data of tenant {}",
+            requesting_tenant_id, target_tenant_id
+        ),
+        threat_score: 1.0,
+        confidence: 1.0,
+        actor: siem_integration::ActorInfo {
+            ip_address: "unknown".to_string(),
+            user_agent: None,
+            api_key_id: None,
+            tenant_id: Some(requesting_tenant_id.clone()),
+            geolocation: None,
+        },
+        target: siem_integration::TargetInfo {
+            resource_type: "tenant_data".to_string(),
+            resource_id: Some(target_tenant_id.to_string()),
+            endpoint: String::new(),
+            method: String::new(),
+        },
+        actions_taken: vec!["access_denied".to_string()],
+        raw_data,
+        tags: vec![
+            "ratewatch".to_string(),
+            "tenant_isolation".to_string(),
+            format!("tenant:{}", requesting_tenant_id),
+        ],
+        correlation_id: Uuid::new_v4().to_string(),
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct TenantMetrics {
     pub tenant_id: Uuid,
@@ -2,10 +2,13 @@ use super::{TenantManager, TenantConfig};
 use super::resource_quota::{ResourceType, QuotaManager};
 use axum::{
     extract::{Request, State},
-    http::{HeaderMap, StatusCode},
-    middleware::Next,
+    http::{request::Parts, HeaderMap, StatusCode},
+    middleware::{self, Next},
     response::Response,
+    Router,
 };
+use crate::api_error::ApiError;
+use crate::rate_limiter::DenyReason;
 use std::sync::Arc;
 use tokio::sync::Mutex;
 use uuid::Uuid;
@@ -20,19 +23,30 @@ pub struct TenantContext {
     pub is_authenticated: bool,
 }
 
+/// Resolve the tenant, then charge the call to its quota, ahead of `router`'s handlers
+pub fn with_tenant_middleware(router: Router, tenant_manager: TenantManagerState) -> Router {
+    // The last layer added runs first
+    router
+        .layer(middleware::from_fn_with_state(tenant_manager.clone(), tenant_quota_middleware))
+        .layer(middleware::from_fn_with_state(tenant_manager, tenant_resolution_middleware))
+}
+
 pub async fn tenant_resolution_middleware(
     State(tenant_manager): State<TenantManagerState>,
-    mut request: Request,
+    request: Request,
     next: Next,
-) -> Result<Response, StatusCode> {
-    let tenant_context = match resolve_tenant_from_request(&request, tenant_manager.clone()).await {
+) -> Result<Response, ApiError> {
+    // Only the head is looked at, and unlike the body it can be held across awaits
+    let (parts, body) = request.into_parts();
+    let tenant_context = match resolve_tenant_from_request(&parts, tenant_manager.clone()).await {
         Ok(context) => context,
-        Err(_) => return Err(StatusCode::BAD_REQUEST),
+        Err(e) => return Err(ApiError::bad_request("TENANT_NOT_RESOLVED", e.to_string())),
     };
+    let mut request = Request::from_parts(parts, body);
 
     // Check if tenant is active
     if !tenant_context.tenant_config.is_active() {
-        return Err(StatusCode::SERVICE_UNAVAILABLE);
+        return Err(ApiError::new(StatusCode::SERVICE_UNAVAILABLE, "TENANT_INACTIVE", "Tenant is not active"));
     }
 
     // Add tenant context to request extensions
@@ -45,37 +59,30 @@ pub async fn tenant_quota_middleware(
     State(tenant_manager): State<TenantManagerState>,
     request: Request,
     next: Next,
-) -> Result<Response, StatusCode> {
-    let tenant_context = request.extensions().get::<TenantContext>()
-        .ok_or(StatusCode::INTERNAL_SERVER_ERROR)?;
-
-    let mut manager = tenant_manager.lock().await;
-    
-    // Check if tenant can make API calls
-    let can_consume = manager.quota_manager.can_consume_resource(
-        tenant_context.tenant_id,
+) -> Result<Response, ApiError> {
+    let tenant_context = request.extensions().get::<TenantContext>().cloned()
+        .ok_or_else(|| ApiError::internal("Tenant was not resolved"))?;
+
+    // Check the tenant, and its parent for a child tenant, can make API calls.
+    // The lock is let go before the handler runs, since tenant handlers take it too.
+    let consumed = tenant_manager.lock().await.try_consume_resource(
+        &tenant_context.tenant_config,
         ResourceType::ApiCalls,
         1,
-        &tenant_context.tenant_config.quotas,
-    ).await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    ).await.map_err(|e| {
+        tracing::error!("Failed to check tenant quota: {:#}", e);
+        ApiError::internal("Failed to check tenant quota")
+    })?;
 
-    if !can_consume {
-        return Err(StatusCode::TOO_MANY_REQUESTS);
-    }
-
-    // Increment API call counter
-    if let Err(_) = manager.quota_manager.update_usage(
-        tenant_context.tenant_id,
-        ResourceType::ApiCalls,
-        1,
-    ).await {
-        tracing::warn!("Failed to update API call usage for tenant {}", tenant_context.tenant_id);
+    if !consumed {
+        return Ok(DenyReason::TenantLimit.response(StatusCode::TOO_MANY_REQUESTS));
     }
 
     let response = next.run(request).await;
 
     // Check for quota violations after request
-    if let Ok(violations) = manager.check_quota_violations(tenant_context.tenant_id).await {
+    let violations = tenant_manager.lock().await.check_quota_violations(tenant_context.tenant_id).await;
+    if let Ok(violations) = violations {
         if !violations.is_empty() {
             tracing::warn!(
                 "Quota violations detected for tenant {}: {:?}",
@@ -91,9 +98,9 @@ pub async fn tenant_quota_middleware(
 pub async fn tenant_rate_limit_middleware(
     request: Request,
     next: Next,
-) -> Result<Response, StatusCode> {
+) -> Result<Response, ApiError> {
     let tenant_context = request.extensions().get::<TenantContext>()
-        .ok_or(StatusCode::INTERNAL_SERVER_ERROR)?;
+        .ok_or_else(|| ApiError::internal("Tenant was not resolved"))?;
 
     // Check rate limits based on tenant configuration
     let rate_limits = &tenant_context.tenant_config.settings.rate_limits;
@@ -111,22 +118,22 @@ pub async fn tenant_rate_limit_middleware(
 pub async fn tenant_security_middleware(
     request: Request,
     next: Next,
-) -> Result<Response, StatusCode> {
+) -> Result<Response, ApiError> {
     let tenant_context = request.extensions().get::<TenantContext>()
-        .ok_or(StatusCode::INTERNAL_SERVER_ERROR)?;
+        .ok_or_else(|| ApiError::internal("Tenant was not resolved"))?;
 
     let security_settings = &tenant_context.tenant_config.settings.security_settings;
 
     // Check IP whitelist if configured
     if !security_settings.ip_whitelist.is_empty() {
-        if let Some(client_ip) = get_client_ip(&request) {
+        if let Some(client_ip) = crate::request_context::client_ip(&request).map(|ip| ip.to_string()) {
             if !security_settings.ip_whitelist.contains(&client_ip) {
                 tracing::warn!(
                     "IP {} not in whitelist for tenant {}",
                     client_ip,
                     tenant_context.tenant_id
                 );
-                return Err(StatusCode::FORBIDDEN);
+                return Err(ApiError::forbidden("Client IP is not allowed for this tenant"));
             }
         }
     }
@@ -141,7 +148,7 @@ pub async fn tenant_security_middleware(
                     origin_str,
                     tenant_context.tenant_id
                 );
-                return Err(StatusCode::FORBIDDEN);
+                return Err(ApiError::forbidden("Origin is not allowed for this tenant"));
             }
         }
     }
@@ -152,9 +159,9 @@ pub async fn tenant_security_middleware(
 pub async fn tenant_feature_gate_middleware(
     request: Request,
     next: Next,
-) -> Result<Response, StatusCode> {
+) -> Result<Response, ApiError> {
     let tenant_context = request.extensions().get::<TenantContext>()
-        .ok_or(StatusCode::INTERNAL_SERVER_ERROR)?;
+        .ok_or_else(|| ApiError::internal("Tenant was not resolved"))?;
 
     // Extract feature requirement from request path or headers
     let required_feature = extract_required_feature(&request);
@@ -166,7 +173,7 @@ pub async fn tenant_feature_gate_middleware(
                 tenant_context.tenant_id,
                 feature
             );
-            return Err(StatusCode::FORBIDDEN);
+            return Err(ApiError::forbidden(format!("Tenant plan does not include {}", feature)));
         }
     }
 
@@ -174,7 +181,7 @@ pub async fn tenant_feature_gate_middleware(
 }
 
 async fn resolve_tenant_from_request(
-    request: &Request,
+    request: &Parts,
     tenant_manager: TenantManagerState,
 ) -> Result<TenantContext> {
     // Try to resolve tenant from different sources
@@ -211,8 +218,8 @@ async fn resolve_tenant_from_request(
     Err(anyhow!("Could not resolve tenant from request"))
 }
 
-fn extract_tenant_from_subdomain(request: &Request) -> Option<Uuid> {
-    request.headers()
+fn extract_tenant_from_subdomain(request: &Parts) -> Option<Uuid> {
+    request.headers
         .get("host")
         .and_then(|host| host.to_str().ok())
         .and_then(|host_str| {
@@ -228,10 +235,10 @@ fn extract_tenant_from_subdomain(request: &Request) -> Option<Uuid> {
 }
 
 async fn extract_tenant_from_headers(
-    request: &Request,
+    request: &Parts,
     tenant_manager: TenantManagerState,
 ) -> Result<Option<TenantContext>> {
-    let headers = request.headers();
+    let headers = &request.headers;
 
     // Try X-Tenant-ID header
     if let Some(tenant_id_header) = headers.get("x-tenant-id") {
@@ -266,8 +273,8 @@ async fn extract_tenant_from_headers(
     Ok(None)
 }
 
-fn extract_tenant_from_path(request: &Request) -> Option<Uuid> {
-    let path = request.uri().path();
+fn extract_tenant_from_path(request: &Parts) -> Option<Uuid> {
+    let path = request.uri.path();
     
     // Look for patterns like /api/tenants/{tenant_id}/...
     let path_segments: Vec<&str> = path.split('/').collect();
@@ -283,35 +290,6 @@ fn extract_tenant_from_path(request: &Request) -> Option<Uuid> {
     None
 }
 
-fn get_client_ip(request: &Request) -> Option<String> {
-    // Check various headers for client IP
-    let headers = request.headers();
-    
-    // X-Forwarded-For (most common)
-    if let Some(xff) = headers.get("x-forwarded-for") {
-        if let Ok(xff_str) = xff.to_str() {
-            // Take the first IP in the chain
-            return xff_str.split(',').next().map(|ip| ip.trim().to_string());
-        }
-    }
-    
-    // X-Real-IP
-    if let Some(real_ip) = headers.get("x-real-ip") {
-        if let Ok(ip_str) = real_ip.to_str() {
-            return Some(ip_str.to_string());
-        }
-    }
-    
-    // CF-Connecting-IP (Cloudflare)
-    if let Some(cf_ip) = headers.get("cf-connecting-ip") {
-        if let Ok(ip_str) = cf_ip.to_str() {
-            return Some(ip_str.to_string());
-        }
-    }
-    
-    None
-}
-
 fn extract_required_feature(request: &Request) -> Option<String> {
     // Check for feature requirement in headers
     if let Some(feature_header) = request.headers().get("x-required-feature") {
@@ -2,6 +2,7 @@ pub mod tenant_manager;
 pub mod tenant_config;
 pub mod resource_quota;
 pub mod isolation;
+pub mod api_keys;
 pub mod api;
 pub mod middleware;
 
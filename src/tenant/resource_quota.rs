@@ -4,6 +4,19 @@ use uuid::Uuid;
 use chrono::{DateTime, Utc};
 use anyhow::{Result, anyhow};
 
+use crate::redis_backend::{scan_keys, LuaScript, RedisConnector};
+use crate::tenant::api_keys::{tenant_keys_key, TenantApiKey};
+
+/// Adds a key to a tenant's set unless that would take it past the limit.
+/// KEYS[1] = tenant's key hash, ARGV = key ID, key JSON, max keys
+static RESERVE_API_KEY_SCRIPT: LuaScript = LuaScript::new(r#"
+if redis.call('HLEN', KEYS[1]) >= tonumber(ARGV[3]) then
+    return 0
+end
+redis.call('HSET', KEYS[1], ARGV[1], ARGV[2])
+return 1
+"#);
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ResourceUsage {
     pub tenant_id: Uuid,
@@ -42,17 +55,16 @@ pub enum ViolationSeverity {
 }
 
 pub struct QuotaManager {
-    redis_client: redis::Client,
+    redis_client: RedisConnector,
     usage_cache: HashMap<Uuid, ResourceUsage>,
 }
 
 impl QuotaManager {
-    pub fn new(redis_url: &str) -> Result<Self> {
-        let redis_client = redis::Client::open(redis_url)?;
-        Ok(Self {
+    pub fn new(redis_client: RedisConnector) -> Self {
+        Self {
             redis_client,
             usage_cache: HashMap::new(),
-        })
+        }
     }
 
     pub async fn get_usage(&mut self, tenant_id: Uuid) -> Result<ResourceUsage> {
@@ -250,14 +262,52 @@ impl QuotaManager {
         Ok(!would_exceed)
     }
 
+    /// Record `key` against its tenant, atomically refusing (false) if the
+    /// tenant already has `max_api_keys`
+    pub async fn reserve_api_key(&self, key: &TenantApiKey, max_api_keys: u32) -> Result<bool> {
+        let mut conn = self.redis_client.get_async_connection().await?;
+        let reserved: i32 = RESERVE_API_KEY_SCRIPT
+            .prepare_invoke()
+            .key(tenant_keys_key(key.tenant_id))
+            .arg(&key.key_id)
+            .arg(serde_json::to_string(key)?)
+            .arg(max_api_keys)
+            .invoke_async(&mut conn)
+            .await?;
+        Ok(reserved == 1)
+    }
+
+    /// Give back a key's place in the quota; false if it wasn't the tenant's
+    pub async fn release_api_key(&self, tenant_id: Uuid, key_id: &str) -> Result<bool> {
+        let mut conn = self.redis_client.get_async_connection().await?;
+        let removed: i32 = redis::cmd("HDEL")
+            .arg(tenant_keys_key(tenant_id))
+            .arg(key_id)
+            .query_async(&mut conn)
+            .await?;
+        Ok(removed == 1)
+    }
+
+    pub async fn list_api_keys(&self, tenant_id: Uuid) -> Result<Vec<TenantApiKey>> {
+        let mut conn = self.redis_client.get_async_connection().await?;
+        let keys: Vec<String> = redis::cmd("HVALS")
+            .arg(tenant_keys_key(tenant_id))
+            .query_async(&mut conn)
+            .await?;
+
+        let mut keys = keys
+            .iter()
+            .map(|key| Ok(serde_json::from_str(key)?))
+            .collect::<Result<Vec<TenantApiKey>>>()?;
+        keys.sort_by_key(|key| key.created_at);
+        Ok(keys)
+    }
+
     pub async fn reset_hourly_counters(&mut self) -> Result<()> {
         let mut conn = self.redis_client.get_async_connection().await?;
         
         // Get all tenant usage keys
-        let keys: Vec<String> = redis::cmd("KEYS")
-            .arg("tenant:*:usage")
-            .query_async(&mut conn)
-            .await?;
+        let keys = scan_keys(&mut conn, "tenant:*:usage").await?;
 
         for key in keys {
             let usage_data: Option<String> = redis::cmd("GET")
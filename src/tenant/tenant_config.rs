@@ -15,6 +15,10 @@ pub struct TenantConfig {
     pub quotas: ResourceQuotas,
     pub features: Vec<String>,
     pub metadata: HashMap<String, String>,
+    /// Organization this tenant is a project of. Its usage counts toward the
+    /// parent's quotas as well as its own.
+    #[serde(default)]
+    pub parent_id: Option<Uuid>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -78,6 +82,13 @@ pub struct ResourceQuotas {
     pub max_concurrent_requests: u32,
     pub max_users: u32,
     pub max_data_export_mb: u64,
+    /// Keys issued with `POST /v1/tenants/:tenant_id/keys` and not revoked
+    #[serde(default = "default_max_api_keys")]
+    pub max_api_keys: u32,
+}
+
+fn default_max_api_keys() -> u32 {
+    10
 }
 
 impl Default for TenantSettings {
@@ -119,7 +130,34 @@ impl Default for ResourceQuotas {
             max_concurrent_requests: 100,
             max_users: 50,
             max_data_export_mb: 100,
+            max_api_keys: default_max_api_keys(),
+        }
+    }
+}
+
+impl ResourceQuotas {
+    /// Names of the quotas larger here than in `parent`'s
+    pub fn exceeding(&self, parent: &ResourceQuotas) -> Vec<&'static str> {
+        let mut exceeding = Vec::new();
+        if self.max_api_calls_per_hour > parent.max_api_calls_per_hour {
+            exceeding.push("max_api_calls_per_hour");
+        }
+        if self.max_storage_mb > parent.max_storage_mb {
+            exceeding.push("max_storage_mb");
+        }
+        if self.max_concurrent_requests > parent.max_concurrent_requests {
+            exceeding.push("max_concurrent_requests");
+        }
+        if self.max_users > parent.max_users {
+            exceeding.push("max_users");
+        }
+        if self.max_data_export_mb > parent.max_data_export_mb {
+            exceeding.push("max_data_export_mb");
+        }
+        if self.max_api_keys > parent.max_api_keys {
+            exceeding.push("max_api_keys");
         }
+        exceeding
     }
 }
 
@@ -137,6 +175,7 @@ impl TenantConfig {
             quotas: ResourceQuotas::default(),
             features: vec![],
             metadata: HashMap::new(),
+            parent_id: None,
         }
     }
 
@@ -1,12 +1,61 @@
 use super::{TenantConfig, TenantStatus, ResourceQuotas, TenantSettings};
 use super::resource_quota::{QuotaManager, ResourceType, QuotaViolation};
 use super::isolation::{TenantIsolationManager, TenantContext, IsolationLevel, DataClassification};
+use super::api_keys::{binding_key, TenantApiKey, TenantKeyBinding};
 use uuid::Uuid;
 use std::collections::HashMap;
+use std::sync::Arc;
 use anyhow::{Result, anyhow};
 use serde::{Deserialize, Serialize};
 use chrono::{DateTime, Utc};
 
+use crate::audit::AuditLogger;
+use crate::redis_backend::{scan_keys, RedisConnection, RedisConnector};
+use crate::security::SiemIntegration;
+
+fn children_key(tenant_id: Uuid) -> String {
+    format!("tenant:{}:children", tenant_id)
+}
+
+/// Every tenant ID, scored alike so the set is ordered by ID and can be paged
+/// with ZRANGEBYLEX
+const TENANT_INDEX_KEY: &str = "tenants:index";
+/// Set once tenants created before the index existed have been added to it
+const TENANT_INDEX_BUILT_KEY: &str = "tenants:index:built";
+
+/// A tenant operation refused because of what was asked rather than a
+/// backend failure. Returned inside the `anyhow::Error`, so callers that
+/// care find it with `downcast_ref`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TenantError {
+    NotFound(Uuid),
+    SlugTaken(String),
+    /// The request can't be applied as given, e.g. quotas above the parent's
+    Invalid(String),
+}
+
+impl std::fmt::Display for TenantError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TenantError::NotFound(tenant_id) => write!(f, "Tenant {} not found", tenant_id),
+            TenantError::SlugTaken(slug) => write!(f, "Tenant with slug '{}' already exists", slug),
+            TenantError::Invalid(message) => f.write_str(message),
+        }
+    }
+}
+
+impl std::error::Error for TenantError {}
+
+/// One page of `TenantManager::list_tenants`, ordered by tenant ID
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TenantPage {
+    pub tenants: Vec<TenantConfig>,
+    /// Pass as `cursor` to fetch the next page; `None` on the last one
+    pub next_cursor: Option<Uuid>,
+    /// Tenants in the deployment, across all pages
+    pub total: usize,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TenantOnboardingRequest {
     pub name: String,
@@ -19,6 +68,17 @@ pub struct TenantOnboardingRequest {
     pub initial_settings: Option<TenantSettings>,
     pub features: Vec<String>,
     pub metadata: HashMap<String, String>,
+    /// Organization tenant to create this one under
+    #[serde(default)]
+    pub parent_id: Option<Uuid>,
+}
+
+/// How one tenant in a bulk import fared
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TenantImportResult {
+    pub slug: String,
+    pub tenant_id: Option<Uuid>,
+    pub error: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -45,7 +105,7 @@ pub enum ProvisioningStep {
 }
 
 pub struct TenantManager {
-    redis_client: redis::Client,
+    redis_client: RedisConnector,
     pub quota_manager: QuotaManager,
     isolation_manager: TenantIsolationManager,
     tenant_cache: HashMap<Uuid, TenantConfig>,
@@ -53,28 +113,63 @@ pub struct TenantManager {
 
 impl TenantManager {
     pub fn new(redis_url: &str, namespace_prefix: String) -> Result<Self> {
-        let redis_client = redis::Client::open(redis_url)?;
-        let quota_manager = QuotaManager::new(redis_url)?;
-        let isolation_manager = TenantIsolationManager::new(redis_url, namespace_prefix)?;
+        Ok(Self::from_connector(RedisConnector::open(redis_url)?, namespace_prefix))
+    }
 
-        Ok(Self {
+    pub fn from_connector(redis_client: RedisConnector, namespace_prefix: String) -> Self {
+        Self {
+            quota_manager: QuotaManager::new(redis_client.clone()),
+            isolation_manager: TenantIsolationManager::from_connector(redis_client.clone(), namespace_prefix),
             redis_client,
-            quota_manager,
-            isolation_manager,
             tenant_cache: HashMap::new(),
-        })
+        }
+    }
+
+    /// Audit denied cross-tenant access
+    pub fn with_audit(mut self, audit: Arc<AuditLogger>) -> Self {
+        self.isolation_manager = self.isolation_manager.with_audit(audit);
+        self
+    }
+
+    /// Alert the SIEM when a `Private` tenant's cross-tenant access is denied
+    pub fn with_siem(mut self, siem: Arc<SiemIntegration>) -> Self {
+        self.isolation_manager = self.isolation_manager.with_siem(siem);
+        self
     }
 
     pub async fn create_tenant(&mut self, request: TenantOnboardingRequest) -> Result<Uuid> {
         // Validate slug uniqueness
         if self.tenant_exists_by_slug(&request.slug).await? {
-            return Err(anyhow!("Tenant with slug '{}' already exists", request.slug));
+            return Err(TenantError::SlugTaken(request.slug).into());
         }
 
+        // A child inherits its parent's quotas, and may only narrow them
+        let quotas = match request.parent_id {
+            Some(parent_id) => {
+                let parent = match self.get_tenant_config(parent_id).await {
+                    Err(e) if matches!(e.downcast_ref(), Some(TenantError::NotFound(_))) => {
+                        return Err(TenantError::Invalid(format!("Parent tenant {} not found", parent_id)).into());
+                    }
+                    parent => parent?,
+                };
+                if parent.parent_id.is_some() {
+                    return Err(TenantError::Invalid(format!("Parent tenant {} is itself a child tenant", parent_id)).into());
+                }
+                let quotas = request.initial_quotas.unwrap_or(parent.quotas.clone());
+                let exceeding = quotas.exceeding(&parent.quotas);
+                if !exceeding.is_empty() {
+                    return Err(TenantError::Invalid(format!("Quotas exceed the parent tenant's: {}", exceeding.join(", "))).into());
+                }
+                Some(quotas)
+            }
+            None => request.initial_quotas,
+        };
+
         let mut tenant_config = TenantConfig::new(request.name, request.slug);
+        tenant_config.parent_id = request.parent_id;
         
         // Apply custom settings if provided
-        if let Some(quotas) = request.initial_quotas {
+        if let Some(quotas) = quotas {
             tenant_config.quotas = quotas;
         }
         
@@ -102,6 +197,14 @@ impl TenantManager {
         
         // Save initial tenant config
         self.save_tenant_config(&tenant_config).await?;
+        if let Some(parent_id) = tenant_config.parent_id {
+            let mut conn = self.redis_client.get_async_connection().await?;
+            redis::cmd("SADD")
+                .arg(children_key(parent_id))
+                .arg(tenant_config.id.to_string())
+                .query_async::<_, ()>(&mut conn)
+                .await?;
+        }
 
         // Start async provisioning
         let tenant_id = tenant_config.id;
@@ -113,6 +216,55 @@ impl TenantManager {
         Ok(tenant_id)
     }
 
+    /// Create each tenant in `requests`, carrying on past failures. Every slug
+    /// is checked before any tenant is created: one that's taken already, or
+    /// used more than once in the batch, fails its item. A duplicate fails
+    /// every occurrence, since there's no telling which one was meant.
+    /// Results are in request order.
+    pub async fn import_tenants(&mut self, requests: Vec<TenantOnboardingRequest>) -> Vec<TenantImportResult> {
+        let mut slug_counts: HashMap<String, usize> = HashMap::new();
+        for request in &requests {
+            *slug_counts.entry(request.slug.clone()).or_default() += 1;
+        }
+
+        let mut slug_errors = Vec::with_capacity(requests.len());
+        for request in &requests {
+            let error = if slug_counts[&request.slug] > 1 {
+                Some(anyhow!("Slug '{}' appears more than once in the import", request.slug))
+            } else {
+                match self.tenant_exists_by_slug(&request.slug).await {
+                    Ok(true) => Some(TenantError::SlugTaken(request.slug.clone()).into()),
+                    Ok(false) => None,
+                    Err(e) => Some(e),
+                }
+            };
+            slug_errors.push(error);
+        }
+
+        let mut results = Vec::with_capacity(requests.len());
+        for (request, slug_error) in requests.into_iter().zip(slug_errors) {
+            let slug = request.slug.clone();
+            let created = match slug_error {
+                Some(e) => Err(e),
+                None => self.create_tenant(request).await,
+            };
+
+            results.push(match created {
+                Ok(tenant_id) => TenantImportResult {
+                    slug,
+                    tenant_id: Some(tenant_id),
+                    error: None,
+                },
+                Err(e) => TenantImportResult {
+                    slug,
+                    tenant_id: None,
+                    error: Some(e.to_string()),
+                },
+            });
+        }
+        results
+    }
+
     pub async fn provision_tenant(&mut self, tenant_id: Uuid, request: TenantOnboardingRequest) -> Result<()> {
         let mut status = self.get_provisioning_status(tenant_id).await?
             .ok_or_else(|| anyhow!("Provisioning status not found"))?;
@@ -214,7 +366,7 @@ impl TenantManager {
             .await?;
 
         let config = config_data
-            .ok_or_else(|| anyhow!("Tenant not found"))?;
+            .ok_or(TenantError::NotFound(tenant_id))?;
         
         let tenant_config: TenantConfig = serde_json::from_str(&config)?;
         self.tenant_cache.insert(tenant_id, tenant_config.clone());
@@ -223,6 +375,13 @@ impl TenantManager {
     }
 
     pub async fn update_tenant_config(&mut self, tenant_id: Uuid, config: TenantConfig) -> Result<()> {
+        if let Some(parent_id) = config.parent_id {
+            let parent = self.get_tenant_config(parent_id).await?;
+            let exceeding = config.quotas.exceeding(&parent.quotas);
+            if !exceeding.is_empty() {
+                return Err(TenantError::Invalid(format!("Quotas exceed the parent tenant's: {}", exceeding.join(", "))).into());
+            }
+        }
         self.save_tenant_config(&config).await?;
         self.tenant_cache.insert(tenant_id, config);
         Ok(())
@@ -245,36 +404,85 @@ impl TenantManager {
         }
     }
 
-    pub async fn list_tenants(&mut self, limit: Option<u32>, offset: Option<u32>) -> Result<Vec<TenantConfig>> {
+    /// A page of at most `limit` tenants after `cursor` (a previous page's
+    /// `next_cursor`), skipping `offset` more. Only the page's configs are read.
+    pub async fn list_tenants(&mut self, limit: Option<u32>, offset: Option<u32>, cursor: Option<Uuid>) -> Result<TenantPage> {
         let mut conn = self.redis_client.get_async_connection().await?;
-        let pattern = "tenant:*:config";
-        
-        let keys: Vec<String> = redis::cmd("KEYS")
-            .arg(pattern)
+        self.ensure_tenant_index(&mut conn).await?;
+
+        let min = match cursor {
+            Some(cursor) => format!("({}", cursor),
+            None => "-".to_string(),
+        };
+        // One past the page tells whether there's another
+        let count = limit.map_or(-1, |limit| limit as i64 + 1);
+        let mut ids: Vec<String> = redis::cmd("ZRANGEBYLEX")
+            .arg(TENANT_INDEX_KEY)
+            .arg(min)
+            .arg("+")
+            .arg("LIMIT")
+            .arg(offset.unwrap_or(0))
+            .arg(count)
+            .query_async(&mut conn)
+            .await?;
+        let total: usize = redis::cmd("ZCARD")
+            .arg(TENANT_INDEX_KEY)
             .query_async(&mut conn)
             .await?;
 
-        let mut tenants = Vec::new();
-        let start = offset.unwrap_or(0) as usize;
-        let end = if let Some(limit) = limit {
-            std::cmp::min(start + limit as usize, keys.len())
-        } else {
-            keys.len()
+        let next_cursor = match limit {
+            Some(limit) if ids.len() > limit as usize => {
+                ids.truncate(limit as usize);
+                ids.last().map(|id| Uuid::parse_str(id)).transpose()?
+            }
+            _ => None,
         };
 
-        for key in &keys[start..end] {
-            let config_data: Option<String> = redis::cmd("GET")
-                .arg(key)
+        let mut tenants = Vec::with_capacity(ids.len());
+        if !ids.is_empty() {
+            let keys: Vec<String> = ids.iter().map(|id| format!("tenant:{}:config", id)).collect();
+            let configs: Vec<Option<String>> = redis::cmd("MGET")
+                .arg(&keys)
                 .query_async(&mut conn)
                 .await?;
-
-            if let Some(data) = config_data {
-                let tenant_config: TenantConfig = serde_json::from_str(&data)?;
-                tenants.push(tenant_config);
+            for data in configs.into_iter().flatten() {
+                tenants.push(serde_json::from_str::<TenantConfig>(&data)?);
             }
         }
 
-        Ok(tenants)
+        Ok(TenantPage { tenants, next_cursor, total })
+    }
+
+    /// Add tenants saved before the index existed to it, once
+    async fn ensure_tenant_index(&self, conn: &mut RedisConnection) -> Result<()> {
+        let built: bool = redis::cmd("EXISTS")
+            .arg(TENANT_INDEX_BUILT_KEY)
+            .query_async(conn)
+            .await?;
+        if built {
+            return Ok(());
+        }
+
+        let ids: Vec<Uuid> = scan_keys(conn, "tenant:*:config")
+            .await?
+            .iter()
+            .filter_map(|key| key.strip_prefix("tenant:")?.strip_suffix(":config"))
+            .filter_map(|id| Uuid::parse_str(id).ok())
+            .collect();
+        if !ids.is_empty() {
+            let mut cmd = redis::cmd("ZADD");
+            cmd.arg(TENANT_INDEX_KEY);
+            for id in &ids {
+                cmd.arg(0).arg(id.to_string());
+            }
+            cmd.query_async::<_, ()>(conn).await?;
+        }
+        redis::cmd("SET")
+            .arg(TENANT_INDEX_BUILT_KEY)
+            .arg(1)
+            .query_async::<_, ()>(conn)
+            .await?;
+        Ok(())
     }
 
     pub async fn suspend_tenant(&mut self, tenant_id: Uuid, reason: String) -> Result<()> {
@@ -297,7 +505,23 @@ impl TenantManager {
         Ok(())
     }
 
+    /// Child tenants created under `tenant_id`
+    pub async fn child_tenant_ids(&self, tenant_id: Uuid) -> Result<Vec<Uuid>> {
+        let mut conn = self.redis_client.get_async_connection().await?;
+        let children: Vec<String> = redis::cmd("SMEMBERS")
+            .arg(children_key(tenant_id))
+            .query_async(&mut conn)
+            .await?;
+        children.iter().map(|id| Ok(Uuid::parse_str(id)?)).collect()
+    }
+
+    /// Delete a tenant, refusing while it has child tenants
     pub async fn delete_tenant(&mut self, tenant_id: Uuid) -> Result<()> {
+        let children = self.child_tenant_ids(tenant_id).await?;
+        if !children.is_empty() {
+            return Err(anyhow!("Tenant {} still has {} child tenants", tenant_id, children.len()));
+        }
+
         let config = self.get_tenant_config(tenant_id).await?;
         
         // Create isolation context for data purge
@@ -310,6 +534,11 @@ impl TenantManager {
         // Purge all tenant data
         self.isolation_manager.purge_tenant_data(&context).await?;
 
+        // Its keys would otherwise go on authenticating, untenanted
+        for key in self.quota_manager.list_api_keys(tenant_id).await? {
+            self.revoke_api_key(tenant_id, &key.key_id).await?;
+        }
+
         // Remove tenant config
         let mut conn = self.redis_client.get_async_connection().await?;
         let config_key = format!("tenant:{}:config", tenant_id);
@@ -318,8 +547,21 @@ impl TenantManager {
         redis::cmd("DEL")
             .arg(&config_key)
             .arg(&slug_key)
+            .arg(children_key(tenant_id))
             .query_async(&mut conn)
             .await?;
+        redis::cmd("ZREM")
+            .arg(TENANT_INDEX_KEY)
+            .arg(tenant_id.to_string())
+            .query_async::<_, ()>(&mut conn)
+            .await?;
+        if let Some(parent_id) = config.parent_id {
+            redis::cmd("SREM")
+                .arg(children_key(parent_id))
+                .arg(tenant_id.to_string())
+                .query_async::<_, ()>(&mut conn)
+                .await?;
+        }
 
         // Remove from cache
         self.tenant_cache.remove(&tenant_id);
@@ -327,11 +569,93 @@ impl TenantManager {
         Ok(())
     }
 
+    /// Delete a tenant and its child tenants
+    pub async fn delete_tenant_cascade(&mut self, tenant_id: Uuid) -> Result<()> {
+        for child_id in self.child_tenant_ids(tenant_id).await? {
+            self.delete_tenant(child_id).await?;
+        }
+        self.delete_tenant(tenant_id).await
+    }
+
+    /// Count `amount` of `resource_type` against the tenant and, for a child,
+    /// its parent, whose usage is the sum of its own and its children's.
+    /// False, with nothing counted, if that would put either over quota.
+    pub async fn try_consume_resource(&mut self, tenant: &TenantConfig, resource_type: ResourceType, amount: u64) -> Result<bool> {
+        let mut tenants = vec![tenant.clone()];
+        if let Some(parent_id) = tenant.parent_id {
+            tenants.push(self.get_tenant_config(parent_id).await?);
+        }
+
+        for tenant in &tenants {
+            if !self.quota_manager.can_consume_resource(tenant.id, resource_type.clone(), amount, &tenant.quotas).await? {
+                return Ok(false);
+            }
+        }
+        for tenant in &tenants {
+            self.quota_manager.update_usage(tenant.id, resource_type.clone(), amount as i64).await?;
+        }
+        Ok(true)
+    }
+
     pub async fn check_quota_violations(&mut self, tenant_id: Uuid) -> Result<Vec<QuotaViolation>> {
         let config = self.get_tenant_config(tenant_id).await?;
         self.quota_manager.check_quota_violation(tenant_id, &config.quotas).await
     }
 
+    /// Bind `key_id` to the tenant, or None if that would exceed
+    /// `ResourceQuotas::max_api_keys`
+    pub async fn issue_api_key(&mut self, tenant_id: Uuid, key_id: String, name: Option<String>) -> Result<Option<TenantApiKey>> {
+        let config = self.get_tenant_config(tenant_id).await?;
+        let key = TenantApiKey {
+            key_id,
+            tenant_id,
+            name,
+            created_at: Utc::now(),
+        };
+        if !self.quota_manager.reserve_api_key(&key, config.quotas.max_api_keys).await? {
+            return Ok(None);
+        }
+
+        let binding = TenantKeyBinding {
+            tenant_id,
+            revoked_at: None,
+        };
+        if let Err(e) = self.save_key_binding(&key.key_id, &binding).await {
+            // Without a binding the key would authenticate untenanted
+            self.quota_manager.release_api_key(tenant_id, &key.key_id).await?;
+            return Err(e);
+        }
+        Ok(Some(key))
+    }
+
+    pub async fn list_api_keys(&mut self, tenant_id: Uuid) -> Result<Vec<TenantApiKey>> {
+        self.get_tenant_config(tenant_id).await?;
+        self.quota_manager.list_api_keys(tenant_id).await
+    }
+
+    /// Revoke one of the tenant's keys; false if it has no such key
+    pub async fn revoke_api_key(&mut self, tenant_id: Uuid, key_id: &str) -> Result<bool> {
+        if !self.quota_manager.release_api_key(tenant_id, key_id).await? {
+            return Ok(false);
+        }
+        let binding = TenantKeyBinding {
+            tenant_id,
+            revoked_at: Some(Utc::now()),
+        };
+        self.save_key_binding(key_id, &binding).await?;
+        Ok(true)
+    }
+
+    async fn save_key_binding(&self, key_id: &str, binding: &TenantKeyBinding) -> Result<()> {
+        let mut conn = self.redis_client.get_async_connection().await?;
+        redis::cmd("SET")
+            .arg(binding_key(key_id))
+            .arg(serde_json::to_string(binding)?)
+            .query_async::<_, ()>(&mut conn)
+            .await?;
+        Ok(())
+    }
+
     pub async fn health_check_tenant(&mut self, tenant_id: Uuid) -> Result<bool> {
         // Check if tenant config exists and is accessible
         let config = self.get_tenant_config(tenant_id).await?;
@@ -377,6 +701,13 @@ impl TenantManager {
             .query_async(&mut conn)
             .await?;
 
+        redis::cmd("ZADD")
+            .arg(TENANT_INDEX_KEY)
+            .arg(0)
+            .arg(config.id.to_string())
+            .query_async::<_, ()>(&mut conn)
+            .await?;
+
         Ok(())
     }
 
@@ -20,6 +20,7 @@ async fn test_tenant_creation_and_retrieval() {
         initial_settings: None,
         features: vec!["analytics".to_string()],
         metadata: HashMap::new(),
+        parent_id: None,
     };
 
     let tenant_id = tenant_manager.create_tenant(request).await.unwrap();
@@ -49,6 +50,7 @@ async fn test_tenant_slug_uniqueness() {
         initial_settings: None,
         features: vec![],
         metadata: HashMap::new(),
+        parent_id: None,
     };
 
     let request2 = TenantOnboardingRequest {
@@ -62,6 +64,7 @@ async fn test_tenant_slug_uniqueness() {
         initial_settings: None,
         features: vec![],
         metadata: HashMap::new(),
+        parent_id: None,
     };
 
     let tenant_id1 = tenant_manager.create_tenant(request1).await.unwrap();
@@ -84,6 +87,7 @@ async fn test_tenant_quota_management() {
         max_concurrent_requests: 10,
         max_users: 5,
         max_data_export_mb: 25,
+        max_api_keys: 3,
     };
 
     let request = TenantOnboardingRequest {
@@ -97,6 +101,7 @@ async fn test_tenant_quota_management() {
         initial_settings: None,
         features: vec![],
         metadata: HashMap::new(),
+        parent_id: None,
     };
 
     let tenant_id = tenant_manager.create_tenant(request).await.unwrap();
@@ -179,6 +184,7 @@ async fn test_tenant_suspension_and_reactivation() {
         initial_settings: None,
         features: vec![],
         metadata: HashMap::new(),
+        parent_id: None,
     };
 
     let tenant_id = tenant_manager.create_tenant(request).await.unwrap();
@@ -249,4 +255,684 @@ async fn test_cross_tenant_access_validation() {
         .unwrap();
 
     assert!(can_access_own);
-}
\ No newline at end of file
+}
+#[tokio::test]
+async fn test_tenant_import_reports_each_tenant() {
+    let redis_url = "redis://127.0.0.1:6379";
+    let mut tenant_manager = TenantManager::new(redis_url, "test".to_string()).unwrap();
+
+    let request = |slug: &str| TenantOnboardingRequest {
+        name: format!("Imported {}", slug),
+        slug: slug.to_string(),
+        admin_email: format!("admin@{}.test", slug),
+        organization: "Import Org".to_string(),
+        isolation_level: IsolationLevel::Shared,
+        data_classification: DataClassification::Internal,
+        initial_quotas: None,
+        initial_settings: None,
+        features: vec![],
+        metadata: HashMap::new(),
+        parent_id: None,
+    };
+
+    let suffix = Uuid::new_v4().simple().to_string();
+    let existing_slug = format!("import-existing-{}", suffix);
+    let existing_id = match tenant_manager.create_tenant(request(&existing_slug)).await {
+        Ok(id) => id,
+        Err(_) => {
+            println!("Skipping tenant import test - Redis not available");
+            return;
+        }
+    };
+
+    let new_slug = format!("import-new-{}", suffix);
+    let duplicate_slug = format!("import-twice-{}", suffix);
+    let results = tenant_manager
+        .import_tenants(vec![
+            request(&new_slug),
+            request(&duplicate_slug),
+            request(&existing_slug),
+            request(&duplicate_slug),
+        ])
+        .await;
+
+    assert_eq!(results.len(), 4);
+    assert_eq!(results[0].slug, new_slug);
+    let new_id = results[0].tenant_id.expect("new tenant should be created");
+    assert!(results[0].error.is_none());
+
+    // Both copies of the in-batch duplicate fail, and neither is created
+    for result in [&results[1], &results[3]] {
+        assert!(result.tenant_id.is_none());
+        assert!(result.error.as_ref().unwrap().contains("more than once"));
+    }
+    assert!(tenant_manager.get_tenant_by_slug(&duplicate_slug).await.unwrap().is_none());
+
+    assert!(results[2].tenant_id.is_none());
+    assert!(results[2].error.as_ref().unwrap().contains("already exists"));
+
+    // Cleanup
+    tenant_manager.delete_tenant(new_id).await.unwrap();
+    tenant_manager.delete_tenant(existing_id).await.unwrap();
+}
+
+#[tokio::test]
+async fn test_tenant_api_keys_are_capped_and_resolve_to_their_tenant() {
+    use crate::auth::{auth_middleware, ApiKeyIdentity, ApiKeyValidator};
+    use crate::redis_backend::RedisConnector;
+    use axum::{body::Body, http::StatusCode, routing::get, Extension, Router};
+    use std::sync::Arc;
+    use tower::ServiceExt;
+
+    let redis_url = "redis://127.0.0.1:6379";
+    let mut tenant_manager = TenantManager::new(redis_url, "test".to_string()).unwrap();
+
+    let request = TenantOnboardingRequest {
+        name: "Key Quota Tenant".to_string(),
+        slug: format!("key-quota-{}", Uuid::new_v4().simple()),
+        admin_email: "admin@test.com".to_string(),
+        organization: "Test Org".to_string(),
+        isolation_level: IsolationLevel::Shared,
+        data_classification: DataClassification::Internal,
+        initial_quotas: Some(ResourceQuotas {
+            max_api_keys: 2,
+            ..ResourceQuotas::default()
+        }),
+        initial_settings: None,
+        features: vec![],
+        metadata: HashMap::new(),
+        parent_id: None,
+    };
+    let tenant_id = match tenant_manager.create_tenant(request).await {
+        Ok(id) => id,
+        Err(_) => {
+            println!("Skipping tenant API key test - Redis not available");
+            return;
+        }
+    };
+
+    let validator = ApiKeyValidator::new("test_secret".to_string()).with_tenant_keys(Arc::new(
+        api_keys::TenantKeyResolver::new(RedisConnector::open(redis_url).unwrap()),
+    ));
+    let keys: Vec<String> = (0..3).map(|_| ApiKeyValidator::generate_api_key()).collect();
+    let key_ids: Vec<String> = keys.iter().map(|key| validator.identity(key).key_id).collect();
+
+    for key_id in &key_ids[..2] {
+        let issued = tenant_manager.issue_api_key(tenant_id, key_id.clone(), None).await.unwrap();
+        assert_eq!(issued.unwrap().tenant_id, tenant_id);
+    }
+    // Past max_api_keys
+    assert!(tenant_manager.issue_api_key(tenant_id, key_ids[2].clone(), None).await.unwrap().is_none());
+    assert_eq!(tenant_manager.list_api_keys(tenant_id).await.unwrap().len(), 2);
+
+    let app = Router::new()
+        .route(
+            "/",
+            get(|Extension(identity): Extension<ApiKeyIdentity>| async move {
+                identity.tenant_id.map(|id| id.to_string()).unwrap_or_default()
+            }),
+        )
+        .layer(axum::middleware::from_fn_with_state(Arc::new(validator), auth_middleware));
+    let call = |key: &str| {
+        app.clone().oneshot(
+            axum::http::Request::builder()
+                .uri("/")
+                .header("authorization", format!("Bearer {key}"))
+                .body(Body::empty())
+                .unwrap(),
+        )
+    };
+    let body = |response: axum::response::Response| async move {
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        String::from_utf8(bytes.to_vec()).unwrap()
+    };
+
+    let response = call(&keys[0]).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(body(response).await, tenant_id.to_string());
+    // The refused key was never bound, so it's an ordinary untenanted key
+    assert_eq!(body(call(&keys[2]).await.unwrap()).await, "");
+
+    // Revoking refuses the key and frees its place in the quota
+    assert!(tenant_manager.revoke_api_key(tenant_id, &key_ids[0]).await.unwrap());
+    assert!(!tenant_manager.revoke_api_key(Uuid::new_v4(), &key_ids[1]).await.unwrap());
+    assert_eq!(call(&keys[0]).await.unwrap().status(), StatusCode::UNAUTHORIZED);
+    assert!(tenant_manager.issue_api_key(tenant_id, key_ids[2].clone(), None).await.unwrap().is_some());
+
+    // Deleting the tenant revokes what's left
+    tenant_manager.delete_tenant(tenant_id).await.unwrap();
+    assert_eq!(call(&keys[1]).await.unwrap().status(), StatusCode::UNAUTHORIZED);
+}
+
+fn hierarchy_request(slug: &str, parent_id: Option<Uuid>, initial_quotas: Option<ResourceQuotas>) -> TenantOnboardingRequest {
+    TenantOnboardingRequest {
+        name: format!("Hierarchy {}", slug),
+        slug: format!("{}-{}", slug, Uuid::new_v4().simple()),
+        admin_email: "admin@test.com".to_string(),
+        organization: "Test Org".to_string(),
+        isolation_level: IsolationLevel::Shared,
+        data_classification: DataClassification::Internal,
+        initial_quotas,
+        initial_settings: None,
+        features: vec![],
+        metadata: HashMap::new(),
+        parent_id,
+    }
+}
+
+#[tokio::test]
+async fn test_child_tenant_inherits_and_cannot_exceed_parent_quotas() {
+    let mut tenant_manager = TenantManager::new("redis://127.0.0.1:6379", "test".to_string()).unwrap();
+
+    let parent_quotas = ResourceQuotas {
+        max_api_calls_per_hour: 500,
+        max_users: 10,
+        ..ResourceQuotas::default()
+    };
+    let parent_id = match tenant_manager.create_tenant(hierarchy_request("org", None, Some(parent_quotas.clone()))).await {
+        Ok(id) => id,
+        Err(_) => {
+            println!("Skipping tenant hierarchy test - Redis not available");
+            return;
+        }
+    };
+
+    let child_id = tenant_manager.create_tenant(hierarchy_request("project", Some(parent_id), None)).await.unwrap();
+    let child = tenant_manager.get_tenant_config(child_id).await.unwrap();
+    assert_eq!(child.parent_id, Some(parent_id));
+    assert_eq!(child.quotas.max_api_calls_per_hour, 500);
+    assert_eq!(child.quotas.max_users, 10);
+
+    let too_big = ResourceQuotas {
+        max_api_calls_per_hour: 1000,
+        ..parent_quotas.clone()
+    };
+    let err = tenant_manager
+        .create_tenant(hierarchy_request("greedy", Some(parent_id), Some(too_big)))
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("max_api_calls_per_hour"));
+
+    let err = tenant_manager
+        .create_tenant(hierarchy_request("orphan", Some(Uuid::new_v4()), None))
+        .await
+        .unwrap_err();
+    assert!(matches!(err.downcast_ref(), Some(TenantError::Invalid(_))));
+    // One level only
+    assert!(tenant_manager.create_tenant(hierarchy_request("grandchild", Some(child_id), None)).await.is_err());
+
+    tenant_manager.delete_tenant_cascade(parent_id).await.unwrap();
+}
+
+#[tokio::test]
+async fn test_child_usage_rolls_up_to_parent_quota() {
+    let mut tenant_manager = TenantManager::new("redis://127.0.0.1:6379", "test".to_string()).unwrap();
+
+    let quotas = ResourceQuotas {
+        max_api_calls_per_hour: 3,
+        ..ResourceQuotas::default()
+    };
+    let parent_id = match tenant_manager.create_tenant(hierarchy_request("org", None, Some(quotas))).await {
+        Ok(id) => id,
+        Err(_) => {
+            println!("Skipping tenant rollup test - Redis not available");
+            return;
+        }
+    };
+    let first_id = tenant_manager.create_tenant(hierarchy_request("first", Some(parent_id), None)).await.unwrap();
+    let second_id = tenant_manager.create_tenant(hierarchy_request("second", Some(parent_id), None)).await.unwrap();
+    let first = tenant_manager.get_tenant_config(first_id).await.unwrap();
+    let second = tenant_manager.get_tenant_config(second_id).await.unwrap();
+
+    // Each child is within its own quota of 3, but together they hit the parent's
+    for tenant in [&first, &first, &second] {
+        assert!(tenant_manager.try_consume_resource(tenant, ResourceType::ApiCalls, 1).await.unwrap());
+    }
+    assert!(!tenant_manager.try_consume_resource(&second, ResourceType::ApiCalls, 1).await.unwrap());
+
+    let usage = |usage: ResourceUsage| usage.api_calls_current_hour;
+    assert_eq!(usage(tenant_manager.quota_manager.get_usage(parent_id).await.unwrap()), 3);
+    assert_eq!(usage(tenant_manager.quota_manager.get_usage(first_id).await.unwrap()), 2);
+    // The refused call wasn't counted against the child either
+    assert_eq!(usage(tenant_manager.quota_manager.get_usage(second_id).await.unwrap()), 1);
+
+    tenant_manager.delete_tenant_cascade(parent_id).await.unwrap();
+}
+
+#[tokio::test]
+async fn test_parent_with_children_cannot_be_deleted_without_cascade() {
+    let mut tenant_manager = TenantManager::new("redis://127.0.0.1:6379", "test".to_string()).unwrap();
+
+    let parent_id = match tenant_manager.create_tenant(hierarchy_request("org", None, None)).await {
+        Ok(id) => id,
+        Err(_) => {
+            println!("Skipping tenant delete guard test - Redis not available");
+            return;
+        }
+    };
+    let child_id = tenant_manager.create_tenant(hierarchy_request("project", Some(parent_id), None)).await.unwrap();
+    assert_eq!(tenant_manager.child_tenant_ids(parent_id).await.unwrap(), vec![child_id]);
+
+    assert!(tenant_manager.delete_tenant(parent_id).await.is_err());
+    assert!(tenant_manager.get_tenant_config(parent_id).await.is_ok());
+
+    // Deleting the child on its own unblocks the parent
+    tenant_manager.delete_tenant(child_id).await.unwrap();
+    assert!(tenant_manager.child_tenant_ids(parent_id).await.unwrap().is_empty());
+
+    let child_id = tenant_manager.create_tenant(hierarchy_request("project", Some(parent_id), None)).await.unwrap();
+    tenant_manager.delete_tenant_cascade(parent_id).await.unwrap();
+    let err = tenant_manager.get_tenant_config(child_id).await.unwrap_err();
+    assert_eq!(err.downcast_ref(), Some(&TenantError::NotFound(child_id)));
+    assert!(tenant_manager.get_tenant_config(parent_id).await.is_err());
+}
+
+/// Keeps audit events in memory so tests can count them
+struct RecordingAuditStorage {
+    events: std::sync::Mutex<Vec<crate::audit::AuditEvent>>,
+}
+
+#[async_trait::async_trait]
+impl crate::audit::AuditStorage for std::sync::Arc<RecordingAuditStorage> {
+    async fn store_event(&self, event: &crate::audit::AuditEvent) -> anyhow::Result<()> {
+        self.events.lock().unwrap().push(event.clone());
+        Ok(())
+    }
+
+    async fn get_event(&self, event_id: &Uuid) -> anyhow::Result<Option<crate::audit::AuditEvent>> {
+        Ok(self.events.lock().unwrap().iter().find(|event| event.id == *event_id).cloned())
+    }
+
+    async fn get_events_by_timerange(
+        &self,
+        _start: chrono::DateTime<chrono::Utc>,
+        _end: chrono::DateTime<chrono::Utc>,
+        _tenant_id: Option<&str>,
+    ) -> anyhow::Result<Vec<crate::audit::AuditEvent>> {
+        Ok(self.events.lock().unwrap().clone())
+    }
+
+    async fn get_events_by_actor(
+        &self,
+        _actor_id: &str,
+        _tenant_id: Option<&str>,
+    ) -> anyhow::Result<Vec<crate::audit::AuditEvent>> {
+        Ok(Vec::new())
+    }
+
+    async fn verify_integrity(&self) -> anyhow::Result<bool> {
+        Ok(true)
+    }
+}
+
+#[tokio::test]
+async fn test_denied_cross_tenant_access_is_audited_once() {
+    use crate::audit::{AuditEventType, AuditLogger, AuditOutcome, DigitalSigner};
+
+    let storage = std::sync::Arc::new(RecordingAuditStorage {
+        events: std::sync::Mutex::new(Vec::new()),
+    });
+    let audit = AuditLogger::new(
+        Box::new(storage.clone()),
+        DigitalSigner::new("test-signing-key-that-is-at-least-32-characters").unwrap(),
+        vec![],
+    )
+    .await
+    .unwrap();
+    // Private tenants are refused without consulting Redis
+    let isolation_manager = TenantIsolationManager::new("redis://127.0.0.1:1", "test".to_string())
+        .unwrap()
+        .with_audit(std::sync::Arc::new(audit));
+
+    let tenant1_id = Uuid::new_v4();
+    let tenant2_id = Uuid::new_v4();
+    let private_context = isolation_manager.create_tenant_context(
+        tenant1_id,
+        IsolationLevel::Private,
+        DataClassification::Restricted,
+    );
+
+    // Reading its own data is not a violation
+    assert!(isolation_manager.validate_cross_tenant_access(&private_context, tenant1_id).await.unwrap());
+    assert!(storage.events.lock().unwrap().is_empty());
+
+    assert!(!isolation_manager.validate_cross_tenant_access(&private_context, tenant2_id).await.unwrap());
+
+    let events = storage.events.lock().unwrap();
+    assert_eq!(events.len(), 1);
+    let event = &events[0];
+    assert_eq!(event.event_type, AuditEventType::SecurityEvent);
+    assert_eq!(event.outcome, AuditOutcome::Failure);
+    assert_eq!(event.action, "cross_tenant_access");
+    assert_eq!(event.actor.tenant_id.as_deref(), Some(tenant1_id.to_string().as_str()));
+    assert_eq!(event.resource.tenant_id.as_deref(), Some(tenant2_id.to_string().as_str()));
+    assert_eq!(event.resource.resource_id.as_deref(), Some(tenant2_id.to_string().as_str()));
+    assert_eq!(event.metadata["isolation_level"], serde_json::json!("Private"));
+    assert!(event.signature.is_some());
+}
+
+#[tokio::test]
+async fn test_tenant_key_listing_and_purge_cover_large_namespaces() {
+    let redis_url = "redis://127.0.0.1:6379";
+    let isolation_manager = TenantIsolationManager::new(redis_url, "test".to_string()).unwrap();
+    let context = isolation_manager.create_tenant_context(
+        Uuid::new_v4(),
+        IsolationLevel::Shared,
+        DataClassification::Internal,
+    );
+
+    // More keys than one SCAN page returns
+    let count = crate::redis_backend::SCAN_COUNT * 2 + 3;
+    for i in 0..count {
+        isolation_manager
+            .set_tenant_data(&context, &format!("item:{:04}", i), "value", Some(60))
+            .await
+            .unwrap();
+    }
+
+    let keys = isolation_manager.list_tenant_keys(&context, "item:*").await.unwrap();
+    let expected: Vec<String> = (0..count).map(|i| format!("item:{:04}", i)).collect();
+    assert_eq!(keys, expected);
+
+    // Everything in the namespace goes, including the data-access audit list
+    let deleted = isolation_manager.purge_tenant_data(&context).await.unwrap();
+    assert_eq!(deleted, count as u64 + 1);
+    assert!(isolation_manager.list_tenant_keys(&context, "item:*").await.unwrap().is_empty());
+    isolation_manager.purge_tenant_data(&context).await.unwrap();
+}
+
+#[tokio::test]
+async fn test_tenant_listing_pages_without_duplicates_or_gaps() {
+    let redis_url = "redis://127.0.0.1:6379";
+    let mut tenant_manager = TenantManager::new(redis_url, "test".to_string()).unwrap();
+
+    let suffix = Uuid::new_v4().simple().to_string();
+    let mut created = Vec::new();
+    for i in 0..25 {
+        let request = TenantOnboardingRequest {
+            name: format!("Paged {}", i),
+            slug: format!("paged-{}-{}", i, suffix),
+            admin_email: "admin@paged.test".to_string(),
+            organization: "Paging Org".to_string(),
+            isolation_level: IsolationLevel::Shared,
+            data_classification: DataClassification::Internal,
+            initial_quotas: None,
+            initial_settings: None,
+            features: vec![],
+            metadata: HashMap::new(),
+            parent_id: None,
+        };
+        created.push(tenant_manager.create_tenant(request).await.unwrap());
+    }
+
+    // Other tests share the keyspace, so only our tenants are checked exactly
+    let mut seen = Vec::new();
+    let mut cursor = None;
+    loop {
+        let page = tenant_manager.list_tenants(Some(10), None, cursor).await.unwrap();
+        assert!(page.tenants.len() <= 10);
+        assert!(page.total >= created.len());
+        seen.extend(page.tenants.iter().map(|tenant| tenant.id));
+        match page.next_cursor {
+            Some(next) => cursor = Some(next),
+            None => break,
+        }
+    }
+
+    let unique: std::collections::HashSet<_> = seen.iter().collect();
+    assert_eq!(unique.len(), seen.len(), "no tenant on two pages");
+    for id in &created {
+        assert!(unique.contains(id), "tenant {} missing from every page", id);
+    }
+    // Pages come back in ID order
+    assert!(seen.windows(2).all(|pair| pair[0].to_string() < pair[1].to_string()));
+
+    for id in created {
+        tenant_manager.delete_tenant(id).await.unwrap();
+    }
+}
+
+#[tokio::test]
+async fn test_unknown_tenant_is_not_found_with_an_error_body() {
+    use axum::{body::Body, http::StatusCode, middleware, Router};
+    use std::sync::Arc;
+    use tower::ServiceExt;
+
+    let redis = crate::redis_backend::RedisConnector::open("redis://127.0.0.1:6379").unwrap();
+    if redis.get_async_connection().await.is_err() {
+        println!("Skipping tenant not-found test - Redis not available");
+        return;
+    }
+    let tenant_manager = TenantManager::new("redis://127.0.0.1:6379", "test".to_string()).unwrap();
+    let app = Router::new()
+        .merge(api::create_tenant_routes())
+        .with_state(Arc::new(tokio::sync::Mutex::new(tenant_manager)))
+        .layer(middleware::from_fn(crate::telemetry::correlation_id_middleware));
+
+    let tenant_id = Uuid::new_v4();
+    for (method, uri) in [
+        ("GET", format!("/tenants/{}", tenant_id)),
+        ("POST", format!("/tenants/{}/reactivate", tenant_id)),
+        ("GET", "/tenants/slug/no-such-tenant".to_string()),
+    ] {
+        let request = axum::http::Request::builder().method(method).uri(&uri).body(Body::empty()).unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND, "{} {}", method, uri);
+
+        let correlation_id = response.headers()["x-correlation-id"].to_str().unwrap().to_string();
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(body["code"], "NOT_FOUND");
+        assert!(!body["detail"].as_str().unwrap().is_empty());
+        assert_eq!(body["correlation_id"], correlation_id);
+    }
+}
+
+#[tokio::test]
+async fn test_batch_create_reports_each_tenant_in_order() {
+    use axum::{body::Body, http::StatusCode, Extension};
+    use crate::audit::{AuditLogger, AuditOutcome, DigitalSigner};
+    use std::sync::Arc;
+    use tower::ServiceExt;
+
+    let redis = crate::redis_backend::RedisConnector::open("redis://127.0.0.1:6379").unwrap();
+    if redis.get_async_connection().await.is_err() {
+        println!("Skipping tenant batch test - Redis not available");
+        return;
+    }
+    let storage = Arc::new(RecordingAuditStorage {
+        events: std::sync::Mutex::new(Vec::new()),
+    });
+    let audit = AuditLogger::new(
+        Box::new(storage.clone()),
+        DigitalSigner::new("test-signing-key-that-is-at-least-32-characters").unwrap(),
+        vec![],
+    )
+    .await
+    .unwrap();
+    let tenant_manager = Arc::new(tokio::sync::Mutex::new(
+        TenantManager::new("redis://127.0.0.1:6379", "test".to_string()).unwrap(),
+    ));
+    let app = api::create_tenant_import_router(tenant_manager.clone(), Arc::new(audit)).layer(Extension(
+        crate::auth::ApiKeyIdentity {
+            key_id: "batch-admin".to_string(),
+            tenant_id: None,
+        },
+    ));
+
+    let suffix = Uuid::new_v4().simple().to_string();
+    let slugs = [
+        format!("batch-a-{}", suffix),
+        format!("batch-twice-{}", suffix),
+        format!("batch-b-{}", suffix),
+        format!("batch-twice-{}", suffix),
+    ];
+    let body = serde_json::Value::Array(
+        slugs
+            .iter()
+            .map(|slug| {
+                serde_json::json!({
+                    "name": slug,
+                    "slug": slug,
+                    "admin_email": format!("admin@{}.test", slug),
+                    "organization": "Batch Org",
+                })
+            })
+            .collect(),
+    );
+    let request = axum::http::Request::builder()
+        .method("POST")
+        .uri("/tenants/batch")
+        .header("content-type", "application/json")
+        .body(Body::from(body.to_string()))
+        .unwrap();
+    let response = app.oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+
+    assert_eq!((body["created"].as_u64(), body["failed"].as_u64()), (Some(2), Some(2)));
+    let results = body["results"].as_array().unwrap();
+    let returned: Vec<&str> = results.iter().map(|result| result["slug"].as_str().unwrap()).collect();
+    assert_eq!(returned, slugs.iter().map(String::as_str).collect::<Vec<_>>());
+    // The duplicate fails without stopping the tenants around it
+    for index in [1, 3] {
+        assert!(results[index]["tenant_id"].is_null());
+        assert!(results[index]["error"].as_str().unwrap().contains("more than once"));
+    }
+
+    let events = storage.events.lock().unwrap().clone();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].action, "batch_create_tenants");
+    assert_eq!(events[0].outcome, AuditOutcome::Partial);
+
+    let mut manager = tenant_manager.lock().await;
+    for index in [0, 2] {
+        let tenant_id: Uuid = serde_json::from_value(results[index]["tenant_id"].clone()).unwrap();
+        assert_eq!(manager.get_tenant_config(tenant_id).await.unwrap().slug, slugs[index]);
+        manager.delete_tenant(tenant_id).await.unwrap();
+    }
+}
+
+#[tokio::test]
+async fn test_tenant_key_routes_get_through_the_tenant_middleware() {
+    use crate::audit::{AuditLogger, DigitalSigner};
+    use crate::auth::{ApiKeyIdentity, ApiKeyValidator};
+    use axum::{body::Body, http::StatusCode, Extension};
+    use std::sync::Arc;
+    use tower::ServiceExt;
+
+    let redis_url = "redis://127.0.0.1:6379";
+    let mut tenant_manager = TenantManager::new(redis_url, "test".to_string()).unwrap();
+    let tenant_id = match tenant_manager.create_tenant(hierarchy_request("key-stack", None, None)).await {
+        Ok(id) => id,
+        Err(_) => {
+            println!("Skipping tenant middleware test - Redis not available");
+            return;
+        }
+    };
+    let tenant_manager = Arc::new(tokio::sync::Mutex::new(tenant_manager));
+
+    let audit = AuditLogger::new(
+        Box::new(Arc::new(RecordingAuditStorage {
+            events: std::sync::Mutex::new(Vec::new()),
+        })),
+        DigitalSigner::new("test-signing-key-that-is-at-least-32-characters").unwrap(),
+        vec![],
+    )
+    .await
+    .unwrap();
+    let keys_router = api::create_tenant_keys_router(
+        tenant_manager.clone(),
+        Arc::new(ApiKeyValidator::new("test_secret".to_string())),
+        Arc::new(audit),
+    );
+    // As `create_secure_router` mounts it
+    let identity = ApiKeyIdentity {
+        key_id: "stack-admin".to_string(),
+        tenant_id: None,
+    };
+    let app = middleware::with_tenant_middleware(keys_router, tenant_manager.clone()).layer(Extension(identity));
+
+    let request = axum::http::Request::builder()
+        .uri(format!("/v1/tenants/{}/keys", tenant_id))
+        .header("x-tenant-id", tenant_id.to_string())
+        .body(Body::empty())
+        .unwrap();
+    // The handler takes the tenant manager lock, so a layer holding it would hang
+    let response = tokio::time::timeout(std::time::Duration::from_secs(5), app.oneshot(request))
+        .await
+        .expect("request should not deadlock")
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+    assert_eq!(body["keys"], serde_json::json!([]));
+
+    tenant_manager.lock().await.delete_tenant(tenant_id).await.unwrap();
+}
+
+#[tokio::test]
+async fn test_tenant_import_gets_through_the_tenant_middleware() {
+    use crate::audit::{AuditLogger, DigitalSigner};
+    use crate::auth::ApiKeyIdentity;
+    use axum::{body::Body, http::StatusCode, Extension};
+    use std::sync::Arc;
+    use tower::ServiceExt;
+
+    let redis_url = "redis://127.0.0.1:6379";
+    let mut tenant_manager = TenantManager::new(redis_url, "test".to_string()).unwrap();
+    let caller_id = match tenant_manager.create_tenant(hierarchy_request("import-caller", None, None)).await {
+        Ok(id) => id,
+        Err(_) => {
+            println!("Skipping tenant import middleware test - Redis not available");
+            return;
+        }
+    };
+    let tenant_manager = Arc::new(tokio::sync::Mutex::new(tenant_manager));
+
+    let audit = AuditLogger::new(
+        Box::new(Arc::new(RecordingAuditStorage {
+            events: std::sync::Mutex::new(Vec::new()),
+        })),
+        DigitalSigner::new("test-signing-key-that-is-at-least-32-characters").unwrap(),
+        vec![],
+    )
+    .await
+    .unwrap();
+    let import_router = api::create_tenant_import_router(tenant_manager.clone(), Arc::new(audit));
+    let identity = ApiKeyIdentity {
+        key_id: "import-admin".to_string(),
+        tenant_id: None,
+    };
+    let app = middleware::with_tenant_middleware(import_router, tenant_manager.clone()).layer(Extension(identity));
+
+    let slug = format!("import-stack-{}", Uuid::new_v4().simple());
+    let body = serde_json::json!([{
+        "name": slug,
+        "slug": slug,
+        "admin_email": format!("admin@{}.test", slug),
+        "organization": "Import Org",
+    }]);
+    let request = axum::http::Request::builder()
+        .method("POST")
+        .uri("/v1/tenants/import")
+        .header("content-type", "application/json")
+        .header("x-tenant-id", caller_id.to_string())
+        .body(Body::from(body.to_string()))
+        .unwrap();
+    // The import takes the tenant manager lock, so a layer holding it would hang
+    let response = tokio::time::timeout(std::time::Duration::from_secs(5), app.oneshot(request))
+        .await
+        .expect("import should not deadlock")
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+    assert_eq!(body["created"].as_u64(), Some(1));
+
+    let imported: Uuid = serde_json::from_value(body["results"][0]["tenant_id"].clone()).unwrap();
+    let mut manager = tenant_manager.lock().await;
+    manager.delete_tenant(imported).await.unwrap();
+    manager.delete_tenant(caller_id).await.unwrap();
+}
@@ -0,0 +1,50 @@
+use axum::{
+    body::Body,
+    http::{Request, StatusCode},
+    routing::get,
+    Router,
+};
+use std::sync::Arc;
+use tower::ServiceExt;
+
+use ratewatch::key_extractor::{ByApiKey, ByHeader, Composite, KeyExtractor, KeyExtractorConfig};
+use ratewatch::layer::{RateLimitLayer, RATE_LIMIT_REMAINING_HEADER};
+use ratewatch::rate_limiter::RateLimiter;
+
+// RateWatch used as a library by another axum app
+
+#[tokio::test]
+async fn test_layer_can_wrap_another_router() {
+    // Nothing listens here; no key means Redis is never contacted
+    let limiter = Arc::new(RateLimiter::new("redis://127.0.0.1:1").unwrap());
+    let app = Router::new().route("/", get(|| async { "ok" })).layer(RateLimitLayer::new(
+        limiter,
+        10,
+        60,
+        |request: &Request<Body>| request.headers().get("x-client-id")?.to_str().ok().map(str::to_string),
+    ));
+
+    let response = app.oneshot(Request::builder().uri("/").body(Body::empty()).unwrap()).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(response.headers().get(RATE_LIMIT_REMAINING_HEADER).is_none());
+}
+
+#[test]
+fn test_key_extractors_are_usable_outside_the_crate() {
+    let request = Request::builder()
+        .header("x-tenant-id", "acme")
+        .header("authorization", "Bearer secret-key")
+        .body(Body::empty())
+        .unwrap();
+
+    let extractor = Composite(vec![Box::new(ByHeader("x-tenant-id".to_string())), Box::new(ByApiKey)]);
+    let key = extractor.extract(&request).unwrap();
+    assert!(key.starts_with("header:x-tenant-id:acme|api_key:"));
+    assert!(!key.contains("secret-key"));
+
+    let configured = KeyExtractorConfig::Header {
+        name: "x-tenant-id".to_string(),
+    }
+    .build();
+    assert_eq!(configured.extract(&request).as_deref(), Some("header:x-tenant-id:acme"));
+}